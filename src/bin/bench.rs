@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use cargo::core::Workspace;
 use cargo::ops;
 use cargo::util::{CliResult, CliError, Human, Config, human};
@@ -7,14 +9,18 @@ use cargo::util::important_paths::{find_root_manifest_for_wd};
 pub struct Options {
     flag_no_run: bool,
     flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
     flag_jobs: Option<u32>,
     flag_features: Vec<String>,
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
+    flag_message_format: ops::MessageFormat,
     flag_lib: bool,
     flag_bin: Vec<String>,
     flag_example: Vec<String>,
@@ -22,6 +28,9 @@ pub struct Options {
     flag_bench: Vec<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_save_baseline: Option<String>,
+    flag_baseline: Option<String>,
+    flag_threshold: f64,
     arg_args: Vec<String>,
 }
 
@@ -39,15 +48,22 @@ Options:
     --test NAME                  Benchmark only the specified test target
     --bench NAME                 Benchmark only the specified bench target
     --no-run                     Compile, but don't run benchmarks
+    --save-baseline NAME         Save the results as the baseline NAME
+    --baseline NAME              Compare the results against the baseline NAME
+    --threshold PERCENT          Slowdown counted as a regression [default: 5]
     -p SPEC, --package SPEC ...  Package to run benchmarks for
+    --all                        Benchmark all packages in the workspace
+    --exclude SPEC ...           Exclude packages from the benchmark when using --all
     -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
     --features FEATURES          Space-separated list of features to also build
     --no-default-features        Do not build the `default` feature
     --target TRIPLE              Build for the target triple
     --manifest-path PATH         Path to the manifest to build benchmarks for
+    --lockfile PATH              Path to the lock file to use instead of Cargo.lock
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
+    --message-format FMT         Error format: human, json [default: human]
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
 
@@ -57,8 +73,16 @@ run.
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be benchmarked. If it is not given, then
-the current package is benchmarked. For more information on SPEC and its format,
-see the `cargo help pkgid` command.
+the current package is benchmarked, or the `workspace.default-members` of the
+workspace when run from its root. With --all every member of the workspace not
+matching an --exclude specification is benchmarked. For more information on
+SPEC and its format, see the `cargo help pkgid` command.
+
+With --save-baseline the time each benchmark took is saved under
+`target/benchmarks`, and --baseline compares the results against a baseline
+saved earlier. A benchmark more than --threshold percent slower than in the
+baseline is a regression, and regressions make the command fail. Both flags
+may be given at once to compare against a baseline before replacing it.
 
 The --jobs argument affects the building of the benchmark executable but does
 not affect how many jobs are used when running the benchmarks.
@@ -74,19 +98,27 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_frozen,
                           options.flag_locked));
 
+    let spec = try!(ops::Packages::from_flags(options.flag_all,
+                                              &options.flag_exclude,
+                                              &options.flag_package));
+
     let ops = ops::TestOptions {
         no_run: options.flag_no_run,
         no_fail_fast: false,
         only_doc: false,
+        retries: None,
+        timeout: None,
+        lcov: false,
         compile_opts: ops::CompileOptions {
             config: config,
             jobs: options.flag_jobs,
-            target: options.flag_target.as_ref().map(|s| &s[..]),
+            targets: options.flag_target.iter().map(|s| &s[..]).collect(),
             features: &options.flag_features,
             no_default_features: options.flag_no_default_features,
-            spec: &options.flag_package,
+            spec: spec,
             exec_engine: None,
             release: true,
+            profile: None,
             mode: ops::CompileMode::Bench,
             filter: ops::CompileFilter::new(options.flag_lib,
                                             &options.flag_bin,
@@ -95,11 +127,24 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                                             &options.flag_bench),
             target_rustdoc_args: None,
             target_rustc_args: None,
+            target_crate_types: None,
+            exclude: ops::ExcludeTargets::default(),
+            build_plan: false,
+            coverage: false,
+            message_format: options.flag_message_format,
         },
     };
 
-    let ws = try!(Workspace::new(&root, config));
-    let err = try!(ops::run_benches(&ws, &ops, &options.arg_args));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    let baseline = ops::BaselineOptions {
+        save: options.flag_save_baseline.as_ref().map(|s| &s[..]),
+        compare: options.flag_baseline.as_ref().map(|s| &s[..]),
+        threshold: options.flag_threshold,
+    };
+    let err = try!(ops::run_benches(&ws, &ops, &baseline, &options.arg_args));
     match err {
         None => Ok(None),
         Some(err) => {
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_package: String,
+    flag_good: Option<String>,
+    flag_bad: Option<String>,
+    flag_test: bool,
+    flag_command: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Find the first version of a dependency which breaks the build
+
+Usage:
+    cargo bisect [options] <package>
+
+Options:
+    -h, --help               Print this message
+    --good VERSION           Version known to work, defaults to the locked one
+    --bad VERSION            Version known to break, defaults to the newest one
+    --test                   Run `cargo test` rather than `cargo build`
+    --command CMD            Run CMD rather than Cargo to test each version
+    --manifest-path PATH     Path to the manifest
+    --lockfile PATH          Path to the lock file to use instead of Cargo.lock
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+The published versions of <package> between the good and the bad one which
+satisfy the requirements of the workspace are pinned in the lock file one after
+the other, bisecting them until the first one which breaks is found. A version
+breaks if `cargo build`, `cargo test` with --test, or CMD fails once it's
+pinned. The lock file is restored once done.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path,
+                                              config.cwd()));
+
+    let opts = ops::BisectOptions {
+        package: &options.arg_package,
+        good: options.flag_good.as_ref().map(|s| &s[..]),
+        bad: options.flag_bad.as_ref().map(|s| &s[..]),
+        test: options.flag_test,
+        command: options.flag_command.as_ref().map(|s| &s[..]),
+    };
+
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    let result = try!(ops::bisect(&ws, &opts));
+    println!("{} v{} is the first version which breaks, the last one which \
+              works is v{}", result.name, result.first_bad, result.last_good);
+    if let Some(url) = result.url {
+        println!("see {} for what changed", url);
+    }
+    Ok(None)
+}
@@ -1,23 +1,30 @@
 use std::env;
+use std::path::Path;
 
 use cargo::core::Workspace;
-use cargo::ops::CompileOptions;
+use cargo::ops::{CompileOptions, MessageFormat};
 use cargo::ops;
 use cargo::util::important_paths::{find_root_manifest_for_wd};
-use cargo::util::{CliResult, Config};
+use cargo::util::{CliResult, Config, human};
 
 #[derive(RustcDecodable)]
 pub struct Options {
     flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
     flag_jobs: Option<u32>,
     flag_features: Vec<String>,
     flag_no_default_features: bool,
-    flag_target: Option<String>,
+    flag_target: Vec<String>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
+    flag_message_format: MessageFormat,
+    flag_build_plan: bool,
     flag_release: bool,
+    flag_profile: Option<String>,
     flag_lib: bool,
     flag_bin: Vec<String>,
     flag_example: Vec<String>,
@@ -36,6 +43,8 @@ Usage:
 Options:
     -h, --help                   Print this message
     -p SPEC, --package SPEC ...  Package to build
+    --all                        Build all packages in the workspace
+    --exclude SPEC ...           Exclude packages from the build when using --all
     -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
     --lib                        Build only this package's library
     --bin NAME                   Build only the specified binary
@@ -43,24 +52,45 @@ Options:
     --test NAME                  Build only the specified test target
     --bench NAME                 Build only the specified benchmark target
     --release                    Build artifacts in release mode, with optimizations
+    --profile NAME               Build artifacts with the specified profile
     --features FEATURES          Space-separated list of features to also build
     --no-default-features        Do not build the `default` feature
-    --target TRIPLE              Build for the target triple
+    --target TRIPLE ...          Build for the target triple
     --manifest-path PATH         Path to the manifest to compile
+    --lockfile PATH              Path to the lock file to use instead of Cargo.lock
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
+    --message-format FMT         Error format: human, json [default: human]
+    --build-plan                 Print the commands of the build as JSON instead
+                                 of running them
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be built. If it is not given, then the
-current package is built. For more information on SPEC and its format, see the
+current package is built, or the `workspace.default-members` of the workspace
+when run from its root. For more information on SPEC and its format, see the
 `cargo help pkgid` command.
 
+All members of the workspace are built when --all is passed, except for those
+matching one of the --exclude specifications.
+
 Compilation can be configured via the use of profiles which are configured in
 the manifest. The default profile for this command is `dev`, but passing
-the --release flag will use the `release` profile instead.
+the --release flag will use the `release` profile instead. Custom profiles of
+the manifest are selected with --profile.
+
+Passing --target several times builds for all of the target triples at once,
+their artifacts being placed in separate directories while the build scripts
+and compiler plugins built for the host are shared between them:
+
+    cargo build --release --target x86_64-unknown-linux-gnu \
+                          --target aarch64-unknown-linux-gnu
+
+With --build-plan nothing is built. Instead the compiler and build script
+invocations the build consists of are printed as a JSON object on stdout, for
+other build systems to run them.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -74,16 +104,26 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
 
     let root = try!(find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()));
 
+    let spec = try!(ops::Packages::from_flags(options.flag_all,
+                                              &options.flag_exclude,
+                                              &options.flag_package));
+
+    if options.flag_build_plan && options.flag_target.len() > 1 {
+        return Err(human("--build-plan can only be used with a single \
+                          --target").into())
+    }
+
     let opts = CompileOptions {
         config: config,
         jobs: options.flag_jobs,
-        target: options.flag_target.as_ref().map(|t| &t[..]),
+        targets: options.flag_target.iter().map(|t| &t[..]).collect(),
         features: &options.flag_features,
         no_default_features: options.flag_no_default_features,
-        spec: &options.flag_package,
+        spec: spec,
         exec_engine: None,
         mode: ops::CompileMode::Build,
         release: options.flag_release,
+        profile: options.flag_profile.as_ref().map(|s| &s[..]),
         filter: ops::CompileFilter::new(options.flag_lib,
                                         &options.flag_bin,
                                         &options.flag_test,
@@ -91,9 +131,17 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                                         &options.flag_bench),
         target_rustdoc_args: None,
         target_rustc_args: None,
+        target_crate_types: None,
+        exclude: ops::ExcludeTargets::default(),
+        build_plan: options.flag_build_plan,
+        coverage: false,
+        message_format: options.flag_message_format,
     };
 
-    let ws = try!(Workspace::new(&root, config));
-    try!(ops::compile(&ws, &opts));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    try!(ops::compile_with_summary(&ws, &opts));
     Ok(None)
 }
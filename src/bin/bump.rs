@@ -0,0 +1,82 @@
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_version: String,
+    flag_commit: bool,
+    flag_tag: bool,
+    flag_publish: bool,
+    flag_host: Option<String>,
+    flag_token: Option<String>,
+    flag_no_verify: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Bump the version of a package
+
+Usage:
+    cargo bump [options] <version>
+
+Options:
+    -h, --help               Print this message
+    --commit                 Commit the updated manifests to git
+    --tag                    Commit the changes and tag the commit as v<version>
+    --publish                Publish the package after bumping its version
+    --host HOST              Host to upload the package to
+    --token TOKEN            Token to use when uploading
+    --no-verify              Don't verify package tarball before publish
+    --manifest-path PATH     Path to the manifest of the package to bump
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+The <version> argument is either one of `major`, `minor` or `patch`, which
+increments the corresponding component of the current version, or an explicit
+semver version which must be greater than the current one.
+
+Path dependencies on the package from other members of the workspace have
+their version requirements updated to match the new version.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path.clone(),
+                                              config.cwd()));
+    let ws = try!(Workspace::new(&root, config));
+
+    let publish = ops::PublishOpts {
+        config: config,
+        token: options.flag_token,
+        index: options.flag_host,
+        verify: !options.flag_no_verify,
+        allow_dirty: !(options.flag_commit || options.flag_tag),
+        jobs: None,
+        target: None,
+        check: false,
+        dry_run: false,
+        show_payload: false,
+    };
+    try!(ops::bump(&ws, &ops::BumpOptions {
+        config: config,
+        version: &options.arg_version,
+        commit: options.flag_commit,
+        tag: options.flag_tag,
+        publish: if options.flag_publish {Some(&publish)} else {None},
+    }));
+    Ok(None)
+}
@@ -51,6 +51,7 @@ Options:
 
 Some common cargo commands are (see all commands with --list):
     build       Compile the current project
+    check       Type-check the current project without generating code
     clean       Remove the target directory
     doc         Build this project's and its dependencies' documentation
     new         Create a new cargo project
@@ -84,31 +85,44 @@ macro_rules! configure_shell {
 macro_rules! each_subcommand{
     ($mac:ident) => {
         $mac!(bench);
+        $mac!(bisect);
         $mac!(build);
+        $mac!(bump);
+        $mac!(check);
         $mac!(clean);
+        $mac!(cost);
+        $mac!(daemon);
         $mac!(doc);
         $mac!(fetch);
         $mac!(generate_lockfile);
         $mac!(git_checkout);
         $mac!(help);
+        $mac!(index_snapshot);
         $mac!(init);
         $mac!(install);
+        $mac!(lint_manifest);
+        $mac!(lockfile_diff);
         $mac!(locate_project);
         $mac!(login);
         $mac!(metadata);
+        $mac!(name_check);
         $mac!(new);
         $mac!(owner);
         $mac!(package);
         $mac!(pkgid);
         $mac!(publish);
         $mac!(read_manifest);
+        $mac!(release);
         $mac!(run);
         $mac!(rustc);
         $mac!(rustdoc);
         $mac!(search);
         $mac!(test);
+        $mac!(tree);
         $mac!(uninstall);
         $mac!(update);
+        $mac!(vendor);
+        $mac!(verify_lockfile);
         $mac!(verify_project);
         $mac!(version);
         $mac!(yank);
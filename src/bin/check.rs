@@ -0,0 +1,118 @@
+use std::env;
+use std::path::Path;
+
+use cargo::core::Workspace;
+use cargo::ops::{CompileOptions, MessageFormat};
+use cargo::ops;
+use cargo::util::important_paths::{find_root_manifest_for_wd};
+use cargo::util::{CliResult, Config};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    flag_jobs: Option<u32>,
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_target: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_message_format: MessageFormat,
+    flag_lib: bool,
+    flag_bin: Vec<String>,
+    flag_locked: bool,
+    flag_frozen: bool,
+}
+
+pub const USAGE: &'static str = "
+Check a local package and all of its dependencies for errors
+
+Usage:
+    cargo check [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package to check
+    --all                        Check all packages in the workspace
+    --exclude SPEC ...           Exclude packages from the check when using --all
+    -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
+    --lib                        Check only this package's library
+    --bin NAME                   Check only the specified binary
+    --features FEATURES          Space-separated list of features to also check
+    --no-default-features        Do not check the `default` feature
+    --target TRIPLE              Check for the target triple
+    --manifest-path PATH         Path to the manifest to check
+    --lockfile PATH              Path to the lock file to use instead of Cargo.lock
+    -v, --verbose ...            Use verbose output
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --message-format FMT         Error format: human, json [default: human]
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+
+The libraries and binaries of the package are type checked without generating
+any code, along with all of their dependencies. This reports the same errors
+and warnings as `cargo build`, only faster. Build scripts and plugins are still
+compiled completely as they have to be run.
+
+The outputs of `cargo check` are kept apart from those of `cargo build`, so
+alternating between the two commands doesn't rebuild anything.
+
+If the --package argument is given, then SPEC is a package id specification
+which indicates which package should be checked. If it is not given, then the
+current package is checked. For more information on SPEC and its format, see
+the `cargo help pkgid` command.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    debug!("executing; cmd=cargo-check; args={:?}",
+           env::args().collect::<Vec<_>>());
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()));
+
+    let spec = try!(ops::Packages::from_flags(options.flag_all,
+                                              &options.flag_exclude,
+                                              &options.flag_package));
+
+    let empty = Vec::new();
+    let opts = CompileOptions {
+        config: config,
+        jobs: options.flag_jobs,
+        targets: options.flag_target.iter().map(|t| &t[..]).collect(),
+        features: &options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        spec: spec,
+        exec_engine: None,
+        mode: ops::CompileMode::Check,
+        release: false,
+        profile: None,
+        filter: ops::CompileFilter::new(options.flag_lib,
+                                        &options.flag_bin,
+                                        &empty,
+                                        &empty,
+                                        &empty),
+        target_rustdoc_args: None,
+        target_rustc_args: None,
+        target_crate_types: None,
+        exclude: ops::ExcludeTargets::default(),
+        build_plan: false,
+        coverage: false,
+        message_format: options.flag_message_format,
+    };
+
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    try!(ops::compile_with_summary(&ws, &opts));
+    Ok(None)
+}
@@ -14,6 +14,7 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_release: bool,
+    flag_out_dirs: bool,
     flag_frozen: bool,
     flag_locked: bool,
 }
@@ -30,6 +31,8 @@ Options:
     --manifest-path PATH         Path to the manifest to the package to clean
     --target TRIPLE              Target triple to clean output for (default all)
     --release                    Whether or not to clean release artifacts
+    --out-dirs                   Only remove the build script outputs of
+                                 packages no longer in the dependency graph
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
@@ -40,6 +43,11 @@ If the --package argument is given, then SPEC is a package id specification
 which indicates which package's artifacts should be cleaned out. If it is not
 given, then all packages' artifacts are removed. For more information on SPEC
 and its format, see the `cargo help pkgid` command.
+
+The directories of build scripts, which hold the `OUT_DIR` they write to, are
+named after the exact version and source of their package. Updating
+dependencies therefore leaves the directories of the previous versions behind,
+which --out-dirs removes without touching anything else.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -56,6 +64,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         spec: &options.flag_package,
         target: options.flag_target.as_ref().map(|s| &s[..]),
         release: options.flag_release,
+        out_dirs: options.flag_out_dirs,
     };
     let ws = try!(Workspace::new(&root, config));
     try!(ops::clean(&ws, &opts));
@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_release: bool,
+    flag_sort: String,
+    flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Report the size and build cost of each dependency
+
+Usage:
+    cargo cost [options]
+
+Options:
+    -h, --help               Print this message
+    --features FEATURES      Space-separated list of features to activate
+    --no-default-features    Do not activate the `default` feature
+    --release                Use the build times of release builds
+    --sort KEY               Sort by time, size, download, memory [default: time]
+    --manifest-path PATH     Path to the manifest
+    --lockfile PATH          Path to the lock file to use instead of Cargo.lock
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+Every package the workspace depends on is listed with the size of its
+downloaded `.crate` file, its size once unpacked and the time it took to
+compile the last time it was built, along with the peak memory and CPU time
+of the processes building it where the platform reports them. The next
+columns show what depending on the package costs in total: the number of
+packages which are only in the dependency graph because of it, itself
+included, and the sum of their sizes and compile times. This is what removing
+the dependency would save.
+
+The packages are sorted by this total, largest first, except for `memory`
+which sorts by the peak memory of the package alone. Compile times are only
+known for packages which have been built in the target directory before, by
+any command, so run `cargo build` first to get them.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path,
+                                              config.cwd()));
+
+    let opts = ops::CostOptions {
+        features: options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        release: options.flag_release,
+        sort: try!(ops::CostSort::parse(&options.flag_sort)),
+    };
+
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    print!("{}", try!(ops::cost_report(&ws, &opts)));
+    Ok(None)
+}
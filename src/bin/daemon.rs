@@ -0,0 +1,61 @@
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_socket: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Serve build requests from a long running process (experimental)
+
+Usage:
+    cargo daemon [options]
+
+Options:
+    -h, --help               Print this message
+    --socket PATH            Path of the socket to listen on
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+The daemon keeps Cargo's configuration loaded between requests, avoiding the
+startup cost of a new `cargo` process for each build. The workspaces requests
+are made for stay loaded as well, along with the resolve of their dependencies
+and the registry index, until their manifests or lock file are modified.
+Requests are single lines of JSON sent over a Unix domain socket, only
+accessible to the current user, located at `daemon.sock` in the Cargo home
+directory unless --socket is given:
+
+    {\"command\": \"build\", \"manifest_path\": \"/path/to/Cargo.toml\"}
+
+Supported commands are `build`, `check`, `metadata` and `shutdown`. All but
+`shutdown` also accept `features` and `no_default_features` keys, and `build`
+and `check` a `release` key. Each request is answered with a line of JSON
+containing a `success` key.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+
+    let socket = match options.flag_socket {
+        Some(ref path) => config.cwd().join(path),
+        None => config.home().join("daemon.sock").into_path_unlocked(),
+    };
+    try!(ops::daemon(&ops::DaemonOptions {
+        config: config,
+        socket: socket,
+    }));
+    Ok(None)
+}
@@ -1,6 +1,8 @@
+use std::path::Path;
+
 use cargo::core::Workspace;
 use cargo::ops;
-use cargo::util::{CliResult, Config};
+use cargo::util::{CliError, CliResult, Config, human};
 use cargo::util::important_paths::{find_root_manifest_for_wd};
 
 #[derive(RustcDecodable)]
@@ -9,14 +11,19 @@ pub struct Options {
     flag_features: Vec<String>,
     flag_jobs: Option<u32>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_no_default_features: bool,
     flag_no_deps: bool,
+    flag_deps: bool,
+    flag_document_private_items: bool,
     flag_open: bool,
     flag_release: bool,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
     flag_lib: bool,
     flag_bin: Vec<String>,
     flag_frozen: bool,
@@ -33,7 +40,11 @@ Options:
     -h, --help                   Print this message
     --open                       Opens the docs in a browser after the operation
     -p SPEC, --package SPEC ...  Package to document
+    --all                        Document all packages in the workspace
+    --exclude SPEC ...           Exclude packages from the documentation when using --all
     --no-deps                    Don't build documentation for dependencies
+    --deps                       Build documentation for dependencies with --all
+    --document-private-items     Document the private items of workspace members
     -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
     --lib                        Document only this package's library
     --bin NAME                   Document only the specified binary
@@ -42,6 +53,7 @@ Options:
     --no-default-features        Do not build the `default` feature
     --target TRIPLE              Build for the target triple
     --manifest-path PATH         Path to the manifest to document
+    --lockfile PATH              Path to the lock file to use instead of Cargo.lock
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
@@ -50,11 +62,25 @@ Options:
 
 By default the documentation for the local package and all dependencies is
 built. The output is all placed in `target/doc` in rustdoc's usual format.
+The --open flag opens it in the browser set with `doc.browser` in the
+configuration, or otherwise in the default browser of the system.
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be documented. If it is not given, then the
 current package is documented. For more information on SPEC and its format, see
 the `cargo help pkgid` command.
+
+With --all every member of the workspace not matching an --exclude
+specification is documented, and `target/doc/index.html` lists them along
+with their descriptions. Dependencies are only documented as well when --deps
+is passed. Links between members which set `html_root_url` are
+pointed at the documentation just built rather than at that URL.
+
+Which targets of a package are documented by default can be changed in the
+manifest by setting `doc = false` on those to leave out, like internal
+binaries, or `doc = true` on examples to include. With
+--document-private-items the private items of the workspace members are
+documented as well, for documentation hosted for the people working on them.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -66,16 +92,34 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
 
     let root = try!(find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()));
 
+    if options.flag_deps && options.flag_no_deps {
+        return Err(CliError::new(human("--deps can't be used with --no-deps"),
+                                 1))
+    }
+    if options.flag_deps && !options.flag_all {
+        return Err(CliError::new(human("--deps can only be used together \
+                                        with --all"), 1))
+    }
+    // The dependencies of a whole workspace are usually beside the point
+    let deps = if options.flag_all {
+        options.flag_deps
+    } else {
+        !options.flag_no_deps
+    };
+
     let empty = Vec::new();
+    let spec = try!(ops::Packages::from_flags(options.flag_all,
+                                              &options.flag_exclude,
+                                              &options.flag_package));
     let doc_opts = ops::DocOptions {
         open_result: options.flag_open,
         compile_opts: ops::CompileOptions {
             config: config,
             jobs: options.flag_jobs,
-            target: options.flag_target.as_ref().map(|t| &t[..]),
+            targets: options.flag_target.iter().map(|t| &t[..]).collect(),
             features: &options.flag_features,
             no_default_features: options.flag_no_default_features,
-            spec: &options.flag_package,
+            spec: spec,
             exec_engine: None,
             filter: ops::CompileFilter::new(options.flag_lib,
                                             &options.flag_bin,
@@ -83,15 +127,25 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                                             &empty,
                                             &empty),
             release: options.flag_release,
+            profile: None,
             mode: ops::CompileMode::Doc {
-                deps: !options.flag_no_deps,
+                deps: deps,
+                private_items: options.flag_document_private_items,
             },
             target_rustc_args: None,
+            target_crate_types: None,
+            exclude: ops::ExcludeTargets::default(),
+            build_plan: false,
+            coverage: false,
+            message_format: ops::MessageFormat::Human,
             target_rustdoc_args: None,
         },
     };
 
-    let ws = try!(Workspace::new(&root, config));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
     try!(ops::doc(&ws, &doc_opts));
     Ok(None)
 }
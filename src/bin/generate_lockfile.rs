@@ -1,4 +1,5 @@
 use std::env;
+use std::path::Path;
 
 use cargo::core::Workspace;
 use cargo::ops;
@@ -8,6 +9,7 @@ use cargo::util::important_paths::find_root_manifest_for_wd;
 #[derive(RustcDecodable)]
 pub struct Options {
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
@@ -24,6 +26,7 @@ Usage:
 Options:
     -h, --help               Print this message
     --manifest-path PATH     Path to the manifest to generate a lockfile for
+    --lockfile PATH          Path to the lock file to use instead of Cargo.lock
     -v, --verbose ...        Use verbose output
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
@@ -40,7 +43,10 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_locked));
     let root = try!(find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()));
 
-    let ws = try!(Workspace::new(&root, config));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
     try!(ops::generate_lockfile(&ws));
     Ok(None)
 }
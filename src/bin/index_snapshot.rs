@@ -0,0 +1,62 @@
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    cmd_export: bool,
+    cmd_import: bool,
+    arg_file: String,
+    flag_rev: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Export or import a snapshot of the registry index
+
+Usage:
+    cargo index-snapshot export [options] <file>
+    cargo index-snapshot import [options] <file>
+
+Options:
+    -h, --help               Print this message
+    --rev REV                Commit of the index to export (defaults to HEAD)
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+`export` updates the registry index and writes the files of the index at the
+given commit to <file>, a gzipped tarball which can be moved to other machines.
+`import` makes a snapshot written by `export` available to builds, which use it
+instead of the index once `registry.snapshot` is set to its commit in the
+configuration. The commit is then recorded in Cargo.lock, and builds whose
+configuration names another snapshot fail until Cargo.lock is regenerated.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let file = config.cwd().join(&options.arg_file);
+    if options.cmd_export {
+        let rev = options.flag_rev.as_ref().map(|s| &s[..]);
+        let commit = try!(ops::export_index_snapshot(config, rev, &file));
+        try!(config.shell().status("Exported",
+                                   format!("snapshot {} to {}", commit,
+                                           options.arg_file)));
+    } else {
+        let commit = try!(ops::import_index_snapshot(config, &file));
+        try!(config.shell().status("Imported",
+                                   format!("snapshot {}, set \
+                                            `registry.snapshot = \"{}\"` \
+                                            to use it", commit, commit)));
+    }
+    Ok(None)
+}
@@ -52,6 +52,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         bin: flag_bin,
         path: &arg_path.unwrap_or(format!(".")),
         name: flag_name.as_ref().map(|s| s.as_ref()),
+        template: None,
+        check_registry: false,
     };
 
     try!(ops::init(opts, config));
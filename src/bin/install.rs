@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use cargo::ops;
 use cargo::core::{SourceId, GitReference};
 use cargo::util::{CliResult, Config, ToUrl, human};
@@ -7,6 +9,7 @@ pub struct Options {
     flag_jobs: Option<u32>,
     flag_features: Vec<String>,
     flag_no_default_features: bool,
+    flag_reset_features: bool,
     flag_debug: bool,
     flag_bin: Vec<String>,
     flag_example: Vec<String>,
@@ -19,7 +22,7 @@ pub struct Options {
     flag_frozen: bool,
     flag_locked: bool,
 
-    arg_crate: Option<String>,
+    arg_crate: Vec<String>,
     flag_vers: Option<String>,
 
     flag_git: Option<String>,
@@ -28,29 +31,35 @@ pub struct Options {
     flag_rev: Option<String>,
 
     flag_path: Option<String>,
+    flag_lockfile: Option<String>,
 }
 
 pub const USAGE: &'static str = "
 Install a Rust binary
 
 Usage:
-    cargo install [options] [<crate>]
+    cargo install [options] [<crate>...]
     cargo install [options] --list
 
 Specifying what crate to install:
-    --vers VERS               Specify a version to install from crates.io
+    --vers VERS               Version or version requirement to install
     --git URL                 Git URL to install the specified crate from
     --branch BRANCH           Branch to use when installing from git
     --tag TAG                 Tag to use when installing from git
     --rev SHA                 Specific commit to use when installing from git
     --path PATH               Filesystem path to local crate to install
+    --lockfile PATH           Lock file to use instead of the Cargo.lock of
+                              the workspace of a crate installed from a path
 
 Build and install options:
     -h, --help                Print this message
     -j N, --jobs N            Number of parallel jobs, defaults to # of CPUs
     --features FEATURES       Space-separated list of features to activate
-    -f, --force               Force overwriting existing crates or binaries
+    -f, --force               Force overwriting existing crates or binaries,
+                              and reinstalling or downgrading crates
     --no-default-features     Do not build the `default` feature
+    --reset-features          Build with the default features rather than
+                              those the crate was installed with
     --debug                   Build in debug mode instead of release mode
     --bin NAME                Only install the binary NAME
     --example EXAMPLE         Install the example EXAMPLE instead of binaries
@@ -59,7 +68,7 @@ Build and install options:
     -q, --quiet               Less output printed to stdout
     --color WHEN              Coloring: auto, always, never
     --frozen                  Require Cargo.lock and cache are up to date
-    --locked                  Require Cargo.lock is up to date
+    --locked                  Build with the Cargo.lock shipped with the crate
 
 This command manages Cargo's local set of installed binary crates. Only packages
 which have [[bin]] targets can be installed, and all binaries are installed into
@@ -72,17 +81,39 @@ There are multiple sources from which a crate can be installed. The default
 location is crates.io but the `--git` and `--path` flags can change this source.
 If the source contains more than one package (such as crates.io or a git
 repository with multiple crates) the `<crate>` argument is required to indicate
-which crate should be installed.
+which crate should be installed. Several crates can be installed at once, in
+which case they're built together, in parallel and in the same target
+directory so the dependencies they have in common are only compiled once. A
+crate which can't be found or built doesn't stop the others from being
+installed, though a build failure does fail the crates it was built with.
 
 Crates from crates.io can optionally specify the version they wish to install
 via the `--vers` flags, and similarly packages from git repositories can
-optionally specify the branch, tag, or revision that should be installed. If a
-crate has multiple binaries, the `--bin` argument can selectively install only
-one of them, and if you'd rather install examples the `--example` argument can
-be used as well.
-
-By default cargo will refuse to overwrite existing binaries. The `--force` flag
-enables overwriting existing binaries. Thus you can reinstall a crate with
+optionally specify the branch, tag, or revision that should be installed.
+`--vers` takes either an exact version, such as `1.2.0`, or a requirement like
+`^1.2` or `>=1.2, <1.5`, in which case the newest version matching it is
+installed. If a crate has multiple binaries, the `--bin` argument can
+selectively install only one of them, and if you'd rather install examples the
+`--example` argument can be used as well.
+
+The dependencies of the crate are resolved afresh, picking their newest
+versions, unless `--locked` is passed, in which case the versions in the
+Cargo.lock the crate was published or committed with are used. Crates with
+binaries are published along with their Cargo.lock. Installing from a path
+always uses the Cargo.lock found there, and a crate which is a member of a
+workspace is built within it, using the Cargo.lock and target directory of the
+workspace.
+
+Installing a crate which is already installed does nothing, unless a newer
+version is available, in which case it's upgraded to, or a different set of
+features or profile was asked for, in which case it's rebuilt. Unless
+`--features`, `--no-default-features` or `--reset-features` is passed, a crate
+keeps the features it was installed with when it's rebuilt or upgraded.
+Crates installed from a git repository are reinstalled when the branch, tag or
+revision they were installed from points at another commit, and those
+installed from a path are always reinstalled. Cargo refuses to install an older version
+than the one installed, or to overwrite the binaries of other crates, unless
+`--force` is passed. Thus you can reinstall a crate with
 `cargo install --force <crate>`.
 
 As a special convenience, omitting the <crate> specification entirely will
@@ -102,16 +133,22 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     let compile_opts = ops::CompileOptions {
         config: config,
         jobs: options.flag_jobs,
-        target: None,
+        targets: Vec::new(),
         features: &options.flag_features,
         no_default_features: options.flag_no_default_features,
-        spec: &[],
+        spec: ops::Packages::Packages(&[]),
         exec_engine: None,
         mode: ops::CompileMode::Build,
         release: !options.flag_debug,
+        profile: None,
         filter: ops::CompileFilter::new(false, &options.flag_bin, &[],
                                         &options.flag_example, &[]),
         target_rustc_args: None,
+        target_crate_types: None,
+        exclude: ops::ExcludeTargets::default(),
+        build_plan: false,
+        coverage: false,
+        message_format: ops::MessageFormat::Human,
         target_rustdoc_args: None,
     };
 
@@ -129,20 +166,23 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         SourceId::for_git(&url, gitref)
     } else if let Some(path) = options.flag_path {
         try!(SourceId::for_path(&config.cwd().join(path)))
-    } else if options.arg_crate == None {
+    } else if options.arg_crate.is_empty() {
         try!(SourceId::for_path(&config.cwd()))
     } else {
         try!(SourceId::for_central(config))
     };
 
-    let krate = options.arg_crate.as_ref().map(|s| &s[..]);
+    let krates = options.arg_crate.iter().map(|s| &s[..]).collect();
     let vers = options.flag_vers.as_ref().map(|s| &s[..]);
     let root = options.flag_root.as_ref().map(|s| &s[..]);
 
     if options.flag_list {
         try!(ops::install_list(root, config));
     } else {
-        try!(ops::install(root, krate, &source, vers, &compile_opts, options.flag_force));
+        let lockfile = options.flag_lockfile.as_ref().map(Path::new);
+        try!(ops::install(root, krates, &source, vers, &compile_opts,
+                          options.flag_force, !options.flag_reset_features,
+                          lockfile));
     }
     Ok(None)
 }
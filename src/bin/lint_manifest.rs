@@ -0,0 +1,62 @@
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_message_format: ops::MessageFormat,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Check the manifests of the workspace for common mistakes
+
+Usage:
+    cargo lint-manifest [options]
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest of the workspace to lint
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --message-format FMT     Output format: human, json [default: human]
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+The manifest of every member of the workspace is checked for the following:
+
+    missing-metadata        a package which may be published has no
+                            description, license or repository
+    wildcard-dependencies   a dependency from a registry accepts any version
+    duplicate-dependencies  a dependency is listed in several sections for the
+                            same platforms
+    unused-features         a feature is neither enabled by another feature
+                            nor tested for with `cfg(feature = \"...\")`
+
+Each lint warns by default. Setting `manifest-lints.<lint>` to `allow`, `warn`
+or `deny` in the configuration changes that, and the command fails if any
+denied lint is found. With `--message-format json` every finding is printed
+on stdout as a line of JSON instead.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path,
+                                              config.cwd()));
+    let ws = try!(Workspace::new(&root, config));
+    try!(ops::lint_manifests(&ws, &ops::LintManifestOptions {
+        message_format: options.flag_message_format,
+    }));
+    Ok(None)
+}
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use rustc_serialize::json;
+
+use cargo::core::Workspace;
+use cargo::ops::{self, LockfileSide};
+use cargo::util::{CliResult, Config, human};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_old: String,
+    arg_new: Option<String>,
+    flag_index: bool,
+    flag_format: Format,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+#[derive(RustcDecodable)]
+enum Format {
+    Text,
+    Markdown,
+    Json,
+}
+
+pub const USAGE: &'static str = "
+Compare the packages locked by two lock files
+
+Usage:
+    cargo lockfile-diff [options] <old> [<new>]
+
+Options:
+    -h, --help               Print this message
+    --index                  Compare with resolving the workspace afresh
+    --format FMT             Output format: text, markdown, json [default: text]
+    --manifest-path PATH     Path to the manifest of the workspace
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+Each of <old> and <new> is either the path of a lock file or a revision of the
+git repository of the workspace, such as `v1.0.0` or `HEAD~3`, at which its
+lock file is read. <new> defaults to the current lock file of the workspace.
+With --index it's instead what resolving the dependencies of the workspace
+without a lock file would give, which are the newest versions available.
+
+The packages added, removed or updated are printed to stdout along with links
+to their changes when they can be found, as with crates.io packages or git
+repositories on GitHub.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path,
+                                              config.cwd()));
+    let ws = try!(Workspace::new(&root, config));
+    let lockfile = ws.lockfile();
+
+    let new = match options.arg_new {
+        Some(..) if options.flag_index => {
+            return Err(human("<new> can't be given with --index").into())
+        }
+        Some(ref new) => side(config, new),
+        None if options.flag_index => LockfileSide::Index,
+        None => LockfileSide::Path(&lockfile),
+    };
+    let diff = try!(ops::lockfile_diff(&ws, &ops::LockfileDiffOptions {
+        old: side(config, &options.arg_old),
+        new: new,
+    }));
+
+    match options.flag_format {
+        Format::Text => print!("{}", diff.to_text()),
+        Format::Markdown => print!("{}", diff.to_markdown()),
+        Format::Json => println!("{}", json::encode(&diff).unwrap()),
+    }
+    Ok(None)
+}
+
+fn side<'a>(config: &Config, arg: &'a str) -> LockfileSide<'a> {
+    if config.cwd().join(arg).is_file() {
+        LockfileSide::Path(Path::new(arg))
+    } else {
+        LockfileSide::Revision(arg)
+    }
+}
@@ -1,5 +1,10 @@
+use std::path::Path;
+
+use rustc_serialize::json;
+
 use cargo::core::Workspace;
-use cargo::ops::{output_metadata, OutputMetadataOptions, ExportInfo};
+use cargo::ops::{output_metadata, output_metadata_cached};
+use cargo::ops::{OutputMetadataOptions, ExportInfo};
 use cargo::util::important_paths::find_root_manifest_for_wd;
 use cargo::util::{CliResult, Config};
 
@@ -9,8 +14,11 @@ pub struct Options {
     flag_features: Vec<String>,
     flag_format_version: u32,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_no_default_features: bool,
     flag_no_deps: bool,
+    flag_no_cache: bool,
+    flag_build_scripts: bool,
     flag_quiet: Option<bool>,
     flag_verbose: u32,
     flag_frozen: bool,
@@ -30,7 +38,11 @@ Options:
     --no-default-features      Do not include the `default` feature
     --no-deps                  Output information only about the root package
                                and don't fetch dependencies.
+    --no-cache                 Don't reuse the result of a previous invocation
+    --build-scripts            Include the outputs of build scripts from
+                               previous builds
     --manifest-path PATH       Path to the manifest
+    --lockfile PATH            Path to the lock file to use instead of Cargo.lock
     --format-version VERSION   Format version [default: 1]
                                Valid values: 1
     -v, --verbose ...          Use verbose output
@@ -38,6 +50,11 @@ Options:
     --color WHEN               Coloring: auto, always, never
     --frozen                   Require Cargo.lock and cache are up to date
     --locked                   Require Cargo.lock is up to date
+
+With --build-scripts the output also lists, for each package with a build
+script built in the target directory, its OUT_DIR, the metadata it passes to
+its dependents as DEP_<links>_<key> variables, its cfgs and the native
+libraries it links to. Only packages which have been built before are listed.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<ExportInfo>> {
@@ -48,14 +65,25 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<ExportInfo
                           options.flag_locked));
     let manifest = try!(find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()));
 
+    let lockfile = options.flag_lockfile;
+    let no_cache = options.flag_no_cache;
     let options = OutputMetadataOptions {
         features: options.flag_features,
         no_default_features: options.flag_no_default_features,
         no_deps: options.flag_no_deps,
         version: options.flag_format_version,
+        build_scripts: options.flag_build_scripts,
     };
 
-    let ws = try!(Workspace::new(&manifest, config));
-    let result = try!(output_metadata(&ws, &options));
-    Ok(Some(result))
+    let mut ws = try!(Workspace::new(&manifest, config));
+    if let Some(ref lockfile) = lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    let output = if no_cache {
+        json::encode(&try!(output_metadata(&ws, &options))).unwrap()
+    } else {
+        try!(output_metadata_cached(&ws, &options))
+    };
+    println!("{}", output);
+    Ok(None)
 }
@@ -0,0 +1,69 @@
+use cargo::ops;
+use cargo::util::{CliResult, Config, human};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_index: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    arg_name: String,
+}
+
+pub const USAGE: &'static str = "
+Check whether a crate name can be claimed on the registry
+
+Usage:
+    cargo name-check [options] <name>
+    cargo name-check -h | --help
+
+Options:
+    -h, --help               Print this message
+    --index INDEX            Registry index to look the name up in
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+The name is checked against the rules of crates.io: its length, the
+characters it's made of, and the names reserved for keywords, the crates
+shipped with Rust and Windows. The registry considers names differing only in
+case or in `-` and `_` to be the same, so those count as taken as well. The
+names already on the registry which are easily mistaken for this one are
+listed too, and the command fails if the name can't be claimed.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let name = &options.arg_name;
+    let check = try!(ops::name_check(name, config, options.flag_index));
+
+    for problem in check.problems.iter() {
+        println!("`{}` can't be registered: {}", name, problem);
+    }
+    if let Some(ref taken_by) = check.taken_by {
+        println!("`{}` is taken by `{}`", name, taken_by);
+    }
+    if check.available() {
+        println!("`{}` is available", name);
+    }
+    if !check.similar.is_empty() {
+        let similar = check.similar.iter().map(|s| format!("`{}`", s))
+                           .collect::<Vec<_>>();
+        println!("similar names on the registry: {}", similar.join(", "));
+    }
+
+    if check.available() {
+        Ok(None)
+    } else {
+        Err(human(format!("the name `{}` can't be claimed on the registry",
+                          name)).into())
+    }
+}
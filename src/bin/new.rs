@@ -12,6 +12,8 @@ pub struct Options {
     arg_path: String,
     flag_name: Option<String>,
     flag_vcs: Option<ops::VersionControl>,
+    flag_template: Option<String>,
+    flag_check_registry: bool,
     flag_frozen: bool,
     flag_locked: bool,
 }
@@ -30,11 +32,23 @@ Options:
                         control at all (none) overriding a global configuration.
     --bin               Use a binary instead of a library template
     --name NAME         Set the resulting package name
+    --template TEMPLATE  Copy the package from a git repository or a directory
+    --check-registry    Warn if the name is already taken on crates.io
     -v, --verbose ...   Use verbose output
     -q, --quiet         No output printed to stdout
     --color WHEN        Coloring: auto, always, never
     --frozen            Require Cargo.lock and cache are up to date
     --locked            Require Cargo.lock is up to date
+
+A template is copied as it is, except for its `cargo-template.toml` and the
+files it excludes, with placeholders such as `{{name}}`, `{{authors}}` or
+`{{license}}` replaced in the paths and contents of the files. The template's
+`cargo-template.toml` can also ask for values of its own placeholders:
+
+    exclude = [\"ci/*.sh\"]
+
+    [prompts]
+    description = \"Describe the package\"
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -45,13 +59,18 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_frozen,
                           options.flag_locked));
 
-    let Options { flag_bin, arg_path, flag_name, flag_vcs, .. } = options;
+    let Options {
+        flag_bin, arg_path, flag_name, flag_vcs, flag_template,
+        flag_check_registry, ..
+    } = options;
 
     let opts = ops::NewOptions {
         version_control: flag_vcs,
         bin: flag_bin,
         path: &arg_path,
         name: flag_name.as_ref().map(|s| s.as_ref()),
+        template: flag_template.as_ref().map(|s| s.as_ref()),
+        check_registry: flag_check_registry,
     };
 
     try!(ops::new(opts, config));
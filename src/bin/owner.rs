@@ -12,6 +12,8 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_list: bool,
+    flag_sync: Option<String>,
+    flag_yes: bool,
     flag_frozen: bool,
     flag_locked: bool,
 }
@@ -27,6 +29,8 @@ Options:
     -a, --add LOGIN          Name of a user or team to add as an owner
     -r, --remove LOGIN       Name of a user or team to remove as an owner
     -l, --list               List owners of a crate
+    --sync FILE              Add and remove owners to match those in FILE
+    -y, --yes                Apply the changes of --sync without asking
     --index INDEX            Registry index to modify owners for
     --token TOKEN            API token to use when authenticating
     -v, --verbose ...        Use verbose output
@@ -40,6 +44,12 @@ default). Note that owners of a package can upload new versions, yank old
 versions. Explicitly named owners can also modify the set of owners, so take
 caution!
 
+The owners of a crate can be kept in a TOML file with --sync, which lists the
+owners to add and remove for the crate to have the ones in its `owners` array
+before asking to apply the changes:
+
+    owners = ["alice", "github:rust-lang:owners"]
+
 See http://doc.crates.io/crates-io.html#cargo-owner for detailed documentation
 and troubleshooting.
 ";
@@ -57,6 +67,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         to_add: options.flag_add,
         to_remove: options.flag_remove,
         list: options.flag_list,
+        sync: options.flag_sync.map(|s| config.cwd().join(s)),
+        yes: options.flag_yes,
     };
     try!(ops::modify_owners(config, &opts));
     Ok(None)
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use cargo::core::Workspace;
 use cargo::ops;
 use cargo::util::{CliResult, Config};
@@ -9,11 +11,14 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_no_verify: bool,
     flag_no_metadata: bool,
     flag_list: bool,
     flag_allow_dirty: bool,
     flag_jobs: Option<u32>,
+    flag_target: Option<String>,
+    flag_check: bool,
     flag_frozen: bool,
     flag_locked: bool,
 }
@@ -28,15 +33,24 @@ Options:
     -h, --help              Print this message
     -l, --list              Print files included in a package without making one
     --no-verify             Don't verify the contents by building them
+    --target TRIPLE         Build for the target triple when verifying
+    --check                 Verify by checking the contents without codegen
     --no-metadata           Ignore warnings about a lack of human-usable metadata
     --allow-dirty           Allow dirty working directories to be packaged
     --manifest-path PATH    Path to the manifest to compile
+    --lockfile PATH         Path to the lock file to ship instead of Cargo.lock
     -j N, --jobs N          Number of parallel jobs, defaults to # of CPUs
     -v, --verbose ...       Use verbose output
     -q, --quiet             No output printed to stdout
     --color WHEN            Coloring: auto, always, never
     --frozen                Require Cargo.lock and cache are up to date
     --locked                Require Cargo.lock is up to date
+
+The contents of the package are verified by building them from the tarball.
+Packages whose build scripts need a toolchain that isn't available on this
+host can be verified for another target with --target, or with --check, which
+type checks the package without generating code. Build scripts are still
+compiled and run by --check, as the package may not type check without them.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -46,7 +60,10 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_frozen,
                           options.flag_locked));
     let root = try!(find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()));
-    let ws = try!(Workspace::new(&root, config));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
     try!(ops::package(&ws, &ops::PackageOpts {
         config: config,
         verify: !options.flag_no_verify,
@@ -54,6 +71,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         check_metadata: !options.flag_no_metadata,
         allow_dirty: options.flag_allow_dirty,
         jobs: options.flag_jobs,
+        target: options.flag_target.as_ref().map(|t| &t[..]),
+        check: options.flag_check,
     }));
     Ok(None)
 }
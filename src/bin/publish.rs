@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use cargo::core::Workspace;
 use cargo::ops;
 use cargo::util::{CliResult, Config};
@@ -8,13 +10,17 @@ pub struct Options {
     flag_host: Option<String>,
     flag_token: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_no_verify: bool,
     flag_allow_dirty: bool,
     flag_jobs: Option<u32>,
+    flag_target: Option<String>,
+    flag_check: bool,
     flag_dry_run: bool,
+    flag_show_payload: bool,
     flag_frozen: bool,
     flag_locked: bool,
 }
@@ -30,16 +36,27 @@ Options:
     --host HOST              Host to upload the package to
     --token TOKEN            Token to use when uploading
     --no-verify              Don't verify package tarball before publish
+    --target TRIPLE          Build for the target triple when verifying
+    --check                  Verify by checking the tarball without codegen
     --allow-dirty            Allow publishing with a dirty source directory
     --manifest-path PATH     Path to the manifest of the package to publish
+    --lockfile PATH          Path to the lock file to ship instead of Cargo.lock
     -j N, --jobs N           Number of parallel jobs, defaults to # of CPUs
     --dry-run                Perform all checks without uploading
+    --show-payload           Print the metadata and tarball details to upload
     -v, --verbose ...        Use verbose output
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
 
+With --show-payload the JSON metadata sent to the registry along with the
+tarball is pretty-printed on stdout, and the size and SHA-256 checksum of the
+tarball are printed as well. Combine it with --dry-run to see what would be
+uploaded without uploading it.
+
+With --check the tarball is only type checked, but build scripts are still
+compiled and run as the package may not type check without them.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -52,15 +69,22 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         flag_token: token,
         flag_host: host,
         flag_manifest_path,
+        flag_lockfile,
         flag_no_verify: no_verify,
         flag_allow_dirty: allow_dirty,
         flag_jobs: jobs,
+        flag_target: target,
+        flag_check: check,
         flag_dry_run: dry_run,
+        flag_show_payload: show_payload,
         ..
     } = options;
 
     let root = try!(find_root_manifest_for_wd(flag_manifest_path.clone(), config.cwd()));
-    let ws = try!(Workspace::new(&root, config));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
     try!(ops::publish(&ws, &ops::PublishOpts {
         config: config,
         token: token,
@@ -68,7 +92,10 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         verify: !no_verify,
         allow_dirty: allow_dirty,
         jobs: jobs,
+        target: target.as_ref().map(|t| &t[..]),
+        check: check,
         dry_run: dry_run,
+        show_payload: show_payload,
     }));
     Ok(None)
 }
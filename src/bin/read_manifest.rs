@@ -32,5 +32,6 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<Package>>
     let root = try!(find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()));
 
     let pkg = try!(Package::for_path(&root, config));
+    try!(pkg.check_unicode_paths());
     Ok(Some(pkg))
 }
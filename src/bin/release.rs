@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_version: String,
+    flag_host: Option<String>,
+    flag_token: Option<String>,
+    flag_no_verify: bool,
+    flag_no_tag: bool,
+    flag_no_publish: bool,
+    flag_wait_timeout: u64,
+    flag_dry_run: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Bump, tag and publish all packages of a workspace
+
+Usage:
+    cargo release [options] <version>
+
+Options:
+    -h, --help               Print this message
+    --host HOST              Host to upload the packages to
+    --token TOKEN            Token to use when uploading
+    --no-verify              Don't verify package tarballs before publish
+    --no-tag                 Don't commit and tag the version bump
+    --no-publish             Stop after bumping and tagging
+    --wait-timeout SECS      Seconds to wait for each crate to appear in the
+                             registry index [default: 300]
+    --dry-run                Print the release plan without doing anything
+    --manifest-path PATH     Path to the manifest of the workspace to release
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+The <version> argument is either one of `major`, `minor` or `patch`, applied
+to each publishable member of the workspace, or an explicit version which all
+of them are set to.
+
+Members are published in dependency order. Before publishing a crate which
+depends on another member, cargo waits for that member's new version to be
+visible in the registry index.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path.clone(),
+                                              config.cwd()));
+    let ws = try!(Workspace::new(&root, config));
+    try!(ops::release(&ws, &ops::ReleaseOptions {
+        config: config,
+        version: &options.arg_version,
+        token: options.flag_token.clone(),
+        index: options.flag_host.clone(),
+        verify: !options.flag_no_verify,
+        tag: !options.flag_no_tag,
+        publish: !options.flag_no_publish,
+        wait_timeout: Duration::from_secs(options.flag_wait_timeout),
+        dry_run: options.flag_dry_run,
+    }));
+    Ok(None)
+}
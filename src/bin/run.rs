@@ -1,21 +1,28 @@
+use std::path::Path;
+
 use cargo::core::Workspace;
 use cargo::ops;
-use cargo::util::{CliResult, CliError, Config, Human};
+use cargo::util::{CliResult, CliError, Config, Human, human};
 use cargo::util::important_paths::{find_root_manifest_for_wd};
 
 #[derive(RustcDecodable)]
 pub struct Options {
     flag_bin: Option<String>,
+    flag_bins: bool,
     flag_example: Option<String>,
     flag_jobs: Option<u32>,
     flag_features: Vec<String>,
+    flag_package: Option<String>,
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
+    flag_message_format: ops::MessageFormat,
     flag_release: bool,
+    flag_profile: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
     arg_args: Vec<String>,
@@ -28,25 +35,36 @@ Usage:
     cargo run [options] [--] [<args>...]
 
 Options:
-    -h, --help              Print this message
-    --bin NAME              Name of the bin target to run
-    --example NAME          Name of the example target to run
-    -j N, --jobs N          Number of parallel jobs, defaults to # of CPUs
-    --release               Build artifacts in release mode, with optimizations
-    --features FEATURES     Space-separated list of features to also build
-    --no-default-features   Do not build the `default` feature
-    --target TRIPLE         Build for the target triple
-    --manifest-path PATH    Path to the manifest to execute
-    -v, --verbose ...       Use verbose output
-    -q, --quiet             No output printed to stdout
-    --color WHEN            Coloring: auto, always, never
-    --frozen                Require Cargo.lock and cache are up to date
-    --locked                Require Cargo.lock is up to date
+    -h, --help                   Print this message
+    --bin NAME                   Name of the bin target to run
+    --bins                       Run all the bin targets, one after the other
+    --example NAME               Name of the example target to run
+    -p SPEC, --package SPEC      Package whose binary to run
+    -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
+    --release                    Build artifacts in release mode, with optimizations
+    --profile NAME               Build artifacts with the specified profile
+    --features FEATURES          Space-separated list of features to also build
+    --no-default-features        Do not build the `default` feature
+    --target TRIPLE              Build for the target triple
+    --manifest-path PATH         Path to the manifest to execute
+    --lockfile PATH              Path to the lock file to use instead of Cargo.lock
+    -v, --verbose ...            Use verbose output
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --message-format FMT         Error format: human, json [default: human]
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
 
 If neither `--bin` nor `--example` are given, then if the project only has one
 bin target it will be run. Otherwise `--bin` specifies the bin target to run,
 and `--example` specifies the example target to run. At most one of `--bin` or
-`--example` can be provided.
+`--example` can be provided. The name given to `--bin` may also be a glob
+pattern, e.g. `--bin 'tool-*'`, and every bin target it matches is run, one
+after the other, stopping at the first which fails. `--bins` runs all of them.
+
+If the --package argument is given, then SPEC is a package id specification
+naming the member of the workspace whose binary is run, instead of the current
+package.
 
 All of the trailing arguments are passed to the binary to run. If you're passing
 arguments to both Cargo and the binary, the ones after `--` go to the binary,
@@ -66,6 +84,14 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     if let Some(s) = options.flag_bin {
         bins.push(s);
     }
+    if options.flag_bins {
+        if !bins.is_empty() {
+            return Err(CliError::new(human("--bin cannot be used together \
+                                            with --bins"), 101))
+        }
+        bins.push("*".to_string());
+    }
+    let package = options.flag_package.into_iter().collect::<Vec<_>>();
     if let Some(s) = options.flag_example {
         examples.push(s);
     }
@@ -73,12 +99,13 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     let compile_opts = ops::CompileOptions {
         config: config,
         jobs: options.flag_jobs,
-        target: options.flag_target.as_ref().map(|t| &t[..]),
+        targets: options.flag_target.iter().map(|t| &t[..]).collect(),
         features: &options.flag_features,
         no_default_features: options.flag_no_default_features,
-        spec: &[],
+        spec: ops::Packages::Packages(&package),
         exec_engine: None,
         release: options.flag_release,
+        profile: options.flag_profile.as_ref().map(|s| &s[..]),
         mode: ops::CompileMode::Build,
         filter: if examples.is_empty() && bins.is_empty() {
             ops::CompileFilter::Everything
@@ -90,9 +117,17 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         },
         target_rustdoc_args: None,
         target_rustc_args: None,
+        target_crate_types: None,
+        exclude: ops::ExcludeTargets::default(),
+        build_plan: false,
+        coverage: false,
+        message_format: options.flag_message_format,
     };
 
-    let ws = try!(Workspace::new(&root, config));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
     match try!(ops::run(&ws, &compile_opts, &options.arg_args)) {
         None => Ok(None),
         Some(err) => {
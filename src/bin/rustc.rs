@@ -1,7 +1,8 @@
 use std::env;
+use std::path::Path;
 
 use cargo::core::Workspace;
-use cargo::ops::{CompileOptions, CompileMode};
+use cargo::ops::{CompileOptions, CompileMode, MessageFormat};
 use cargo::ops;
 use cargo::util::important_paths::{find_root_manifest_for_wd};
 use cargo::util::{CliResult, CliError, Config, human};
@@ -15,9 +16,11 @@ pub struct Options {
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
+    flag_message_format: MessageFormat,
     flag_release: bool,
     flag_lib: bool,
     flag_bin: Vec<String>,
@@ -25,6 +28,7 @@ pub struct Options {
     flag_test: Vec<String>,
     flag_bench: Vec<String>,
     flag_profile: Option<String>,
+    flag_crate_type: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
 }
@@ -46,13 +50,17 @@ Options:
     --bench NAME             Build only the specified benchmark target
     --release                Build artifacts in release mode, with optimizations
     --profile PROFILE        Profile to build the selected target for
+    --crate-type TYPES       Comma-separated crate types to build the library
+                             as, e.g. `cdylib,staticlib`
     --features FEATURES      Features to compile for the package
     --no-default-features    Do not compile default features for the package
     --target TRIPLE          Target triple which compiles will be for
     --manifest-path PATH     Path to the manifest to fetch dependencies for
+    --lockfile PATH          Path to the lock file to use instead of Cargo.lock
     -v, --verbose ...        Use verbose output
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
+    --message-format FMT     Error format: human, json [default: human]
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
 
@@ -63,11 +71,15 @@ dependencies. Note that the compiler will still unconditionally receive
 arguments such as -L, --extern, and --crate-type, and the specified <opts>...
 will simply be added to the compiler invocation.
 
-This command requires that only one target is being compiled. If more than one
-target is available for the current package the filters of --lib, --bin, etc,
-must be used to select which target is compiled. To pass flags to all compiler
-processes spawned by Cargo, use the $RUSTFLAGS environment variable or the
-`build.rustflags` configuration option.
+The <opts>... are passed to every target selected with the filters of --lib,
+--bin, etc. Without any filter the package must have exactly one target. The
+library, or binaries along with tests or benchmarks, can't be selected with
+other targets as those depend on them. To pass flags to all compiler processes spawned by Cargo, use the $RUSTFLAGS
+environment variable or the `build.rustflags` configuration option.
+
+With --crate-type the library is built as the given crate types, such as
+`staticlib` or `cdylib`, instead of those listed in the manifest. This only
+applies to this invocation.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -92,16 +104,22 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         }
     };
 
+    let spec = options.flag_package.map_or(Vec::new(), |s| vec![s]);
+    let crate_types = options.flag_crate_type.as_ref().map(|s| {
+        s.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+         .collect::<Vec<_>>()
+    });
     let opts = CompileOptions {
         config: config,
         jobs: options.flag_jobs,
-        target: options.flag_target.as_ref().map(|t| &t[..]),
+        targets: options.flag_target.iter().map(|t| &t[..]).collect(),
         features: &options.flag_features,
         no_default_features: options.flag_no_default_features,
-        spec: &options.flag_package.map_or(Vec::new(), |s| vec![s]),
+        spec: ops::Packages::Packages(&spec),
         exec_engine: None,
         mode: mode,
         release: options.flag_release,
+        profile: None,
         filter: ops::CompileFilter::new(options.flag_lib,
                                         &options.flag_bin,
                                         &options.flag_test,
@@ -109,10 +127,18 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                                         &options.flag_bench),
         target_rustdoc_args: None,
         target_rustc_args: options.arg_opts.as_ref().map(|a| &a[..]),
+        target_crate_types: crate_types.as_ref().map(|t| &t[..]),
+        exclude: ops::ExcludeTargets::default(),
+        build_plan: false,
+        coverage: false,
+        message_format: options.flag_message_format,
     };
 
-    let ws = try!(Workspace::new(&root, config));
-    try!(ops::compile(&ws, &opts));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    try!(ops::compile_with_summary(&ws, &opts));
     Ok(None)
 }
 
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use cargo::core::Workspace;
 use cargo::ops;
 use cargo::util::{CliResult, Config};
@@ -10,6 +12,7 @@ pub struct Options {
     flag_features: Vec<String>,
     flag_jobs: Option<u32>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_no_default_features: bool,
     flag_open: bool,
     flag_verbose: u32,
@@ -47,6 +50,7 @@ Options:
     --no-default-features    Do not build the `default` feature
     --target TRIPLE          Build for the target triple
     --manifest-path PATH     Path to the manifest to document
+    --lockfile PATH          Path to the lock file to use instead of Cargo.lock
     -v, --verbose ...        Use verbose output
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
@@ -76,29 +80,42 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     let root = try!(find_root_manifest_for_wd(options.flag_manifest_path,
                                               config.cwd()));
 
+    let spec = options.flag_package.map_or(Vec::new(), |s| vec![s]);
     let doc_opts = ops::DocOptions {
         open_result: options.flag_open,
         compile_opts: ops::CompileOptions {
             config: config,
             jobs: options.flag_jobs,
-            target: options.flag_target.as_ref().map(|t| &t[..]),
+            targets: options.flag_target.iter().map(|t| &t[..]).collect(),
             features: &options.flag_features,
             no_default_features: options.flag_no_default_features,
-            spec: &options.flag_package.map_or(Vec::new(), |s| vec![s]),
+            spec: ops::Packages::Packages(&spec),
             exec_engine: None,
             release: options.flag_release,
+            profile: None,
             filter: ops::CompileFilter::new(options.flag_lib,
                                             &options.flag_bin,
                                             &options.flag_test,
                                             &options.flag_example,
                                             &options.flag_bench),
-            mode: ops::CompileMode::Doc { deps: false },
+            mode: ops::CompileMode::Doc {
+                deps: false,
+                private_items: false,
+            },
             target_rustdoc_args: Some(&options.arg_opts),
             target_rustc_args: None,
+            target_crate_types: None,
+            exclude: ops::ExcludeTargets::default(),
+            build_plan: false,
+            coverage: false,
+            message_format: ops::MessageFormat::Human,
         },
     };
 
-    let ws = try!(Workspace::new(&root, config));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
     try!(ops::doc(&ws, &doc_opts));
 
     Ok(None)
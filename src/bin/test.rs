@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use cargo::core::Workspace;
 use cargo::ops;
 use cargo::util::{CliResult, CliError, Human, human, Config};
@@ -9,9 +11,12 @@ pub struct Options {
     flag_features: Vec<String>,
     flag_jobs: Option<u32>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_no_default_features: bool,
     flag_no_run: bool,
     flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
     flag_target: Option<String>,
     flag_lib: bool,
     flag_doc: bool,
@@ -19,11 +24,21 @@ pub struct Options {
     flag_example: Vec<String>,
     flag_test: Vec<String>,
     flag_bench: Vec<String>,
+    flag_exclude_bin: Vec<String>,
+    flag_exclude_example: Vec<String>,
+    flag_exclude_test: Vec<String>,
+    flag_exclude_bench: Vec<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
+    flag_message_format: ops::MessageFormat,
     flag_release: bool,
+    flag_profile: Option<String>,
     flag_no_fail_fast: bool,
+    flag_retries: Option<u32>,
+    flag_timeout: Option<u64>,
+    flag_coverage: bool,
+    flag_lcov: bool,
     flag_frozen: bool,
     flag_locked: bool,
 }
@@ -42,18 +57,31 @@ Options:
     --example NAME               Test only the specified example
     --test NAME                  Test only the specified integration test target
     --bench NAME                 Test only the specified benchmark target
+    --exclude-bin NAME ...       Leave out the specified binaries
+    --exclude-example NAME ...   Leave out the specified examples
+    --exclude-test NAME ...      Leave out the specified integration test targets
+    --exclude-bench NAME ...     Leave out the specified benchmark targets
     --no-run                     Compile, but don't run tests
     -p SPEC, --package SPEC ...  Package to run tests for
+    --all                        Test all packages in the workspace
+    --exclude SPEC ...           Exclude packages from the test when using --all
     -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
     --release                    Build artifacts in release mode, with optimizations
+    --profile NAME               Build artifacts with the specified profile
     --features FEATURES          Space-separated list of features to also build
     --no-default-features        Do not build the `default` feature
     --target TRIPLE              Build for the target triple
     --manifest-path PATH         Path to the manifest to build tests for
+    --lockfile PATH              Path to the lock file to use instead of Cargo.lock
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
+    --message-format FMT         Error format: human, json [default: human]
     --no-fail-fast               Run all tests regardless of failure
+    --retries N                  Run failing test executables up to N more times
+    --timeout SECS               Kill test executables running longer than SECS
+    --coverage                   Measure the code coverage of the tests
+    --lcov                       Write an lcov report of the coverage (implies --coverage)
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
 
@@ -65,8 +93,14 @@ example, this will run all tests with the name `foo` in their name:
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be tested. If it is not given, then the
-current package is tested. For more information on SPEC and its format, see the
-`cargo help pkgid` command.
+current package is tested, or the `workspace.default-members` of the workspace
+when run from its root. With --all every member of the workspace not matching
+an --exclude specification is tested. For more information on SPEC and its
+format, see the `cargo help pkgid` command.
+
+The names given to --bin, --example, --test, --bench and their --exclude-*
+counterparts may be glob patterns, e.g. `--test 'net_*'` to test all the
+integration tests whose names start with `net_`.
 
 The --jobs argument affects the building of the test executable but does
 not affect how many jobs are used when running the tests.
@@ -79,6 +113,27 @@ by passing `--nocapture` to the test binaries:
 
   cargo test -- --nocapture
 
+Test executables which fail are run again as often as --retries, or the
+`retries` key of their target in the manifest, allows. Those passing on a later
+attempt don't fail the command but are reported as flaky.
+
+Test executables still running after --timeout, or the `timeout` key of their
+target, are killed and reported as timed out. The limit is also passed to them
+in the CARGO_TEST_TIMEOUT environment variable.
+
+With --no-run the full paths of the test executables are printed once they're
+built, so they can be run by hand, e.g. under a debugger.
+
+By default the first test executable which fails stops the run. With
+--no-fail-fast every remaining one, including the doc tests, is run as well,
+and the targets which failed are listed along with their exit codes at the end.
+
+With --coverage the workspace members are instrumented for code coverage,
+which needs a nightly compiler, and the test executables write raw LLVM
+profiles to `target/coverage`. With --lcov these are merged into
+`target/coverage/lcov.info`, which needs `llvm-profdata` and `llvm-cov` on the
+PATH. Doc tests aren't run, as they can't be instrumented.
+
 To get the list of all options available for the test binaries use this:
 
   cargo test -- --help
@@ -92,11 +147,16 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_locked));
     let root = try!(find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()));
 
-    let empty = Vec::new();
+    let coverage = options.flag_coverage || options.flag_lcov;
+    if coverage && options.flag_doc {
+        return Err(CliError::new(human("--coverage can't be used with --doc, \
+                                        doc tests aren't instrumented"), 1))
+    }
+
     let (mode, filter);
     if options.flag_doc {
-        mode = ops::CompileMode::Build;
-        filter = ops::CompileFilter::new(true, &empty, &empty, &empty, &empty);
+        mode = ops::CompileMode::Doctest;
+        filter = ops::CompileFilter::Everything;
     } else {
         mode = ops::CompileMode::Test;
         filter = ops::CompileFilter::new(options.flag_lib,
@@ -106,27 +166,48 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                                          &options.flag_bench);
     }
 
+    let spec = try!(ops::Packages::from_flags(options.flag_all,
+                                              &options.flag_exclude,
+                                              &options.flag_package));
+
     let ops = ops::TestOptions {
         no_run: options.flag_no_run,
         no_fail_fast: options.flag_no_fail_fast,
         only_doc: options.flag_doc,
+        retries: options.flag_retries,
+        timeout: options.flag_timeout,
+        lcov: options.flag_lcov,
         compile_opts: ops::CompileOptions {
             config: config,
             jobs: options.flag_jobs,
-            target: options.flag_target.as_ref().map(|s| &s[..]),
+            targets: options.flag_target.iter().map(|s| &s[..]).collect(),
             features: &options.flag_features,
             no_default_features: options.flag_no_default_features,
-            spec: &options.flag_package,
+            spec: spec,
             exec_engine: None,
             release: options.flag_release,
+            profile: options.flag_profile.as_ref().map(|s| &s[..]),
             mode: mode,
             filter: filter,
             target_rustdoc_args: None,
             target_rustc_args: None,
+            target_crate_types: None,
+            exclude: ops::ExcludeTargets {
+                bins: &options.flag_exclude_bin,
+                examples: &options.flag_exclude_example,
+                tests: &options.flag_exclude_test,
+                benches: &options.flag_exclude_bench,
+            },
+            build_plan: false,
+            coverage: coverage,
+            message_format: options.flag_message_format,
         },
     };
 
-    let ws = try!(Workspace::new(&root, config));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
     let err = try!(ops::run_tests(&ws, &ops, &options.arg_args));
     match err {
         None => Ok(None),
@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_edges: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Display the dependency graph of a project as a tree
+
+Usage:
+    cargo tree [options]
+
+Options:
+    -h, --help               Print this message
+    --features FEATURES      Space-separated list of features to activate
+    --no-default-features    Do not activate the `default` feature
+    -e KINDS, --edges KINDS  Comma-separated kinds of dependencies to show
+    --manifest-path PATH     Path to the manifest
+    --lockfile PATH          Path to the lock file to use instead of Cargo.lock
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+The kinds of dependencies given to --edges are `normal`, `build` and `dev`,
+or `all` for all of them, which is the default. Adding `features` to the list
+shows the features each dependency is built with, and for optional
+dependencies and features of dependencies, which features of the depending
+package enabled them:
+
+    cargo tree --edges normal,features
+
+Packages which already appeared in the tree aren't expanded again and are
+marked with (*).
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path,
+                                              config.cwd()));
+
+    let edges = options.flag_edges.as_ref().map(|s| &s[..]).unwrap_or("all");
+    let opts = ops::TreeOptions {
+        features: options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        edges: try!(ops::Edges::parse(edges)),
+    };
+
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    print!("{}", try!(ops::tree(&ws, &opts)));
+    Ok(None)
+}
@@ -1,9 +1,10 @@
 use cargo::ops;
-use cargo::util::{CliResult, Config};
+use cargo::util::{CliResult, Config, human};
 
 #[derive(RustcDecodable)]
 pub struct Options {
     flag_bin: Vec<String>,
+    flag_package: Option<String>,
     flag_root: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
@@ -11,7 +12,7 @@ pub struct Options {
     flag_frozen: bool,
     flag_locked: bool,
 
-    arg_spec: String,
+    arg_spec: Option<String>,
 }
 
 pub const USAGE: &'static str = "
@@ -19,10 +20,12 @@ Remove a Rust binary
 
 Usage:
     cargo uninstall [options] <spec>
+    cargo uninstall [options] -p SPEC
     cargo uninstall (-h | --help)
 
 Options:
     -h, --help                Print this message
+    -p SPEC, --package SPEC   Package to uninstall
     --root DIR                Directory to uninstall packages from
     --bin NAME                Only uninstall the binary NAME
     -v, --verbose ...         Use verbose output
@@ -34,7 +37,8 @@ Options:
 The argument SPEC is a package id specification (see `cargo help pkgid`) to
 specify which crate should be uninstalled. By default all binaries are
 uninstalled for a crate but the `--bin` and `--example` flags can be used to
-only uninstall particular binaries.
+only uninstall particular binaries. Only the binaries recorded as installed by
+that package are removed, those of other packages are left alone.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -45,7 +49,12 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_locked));
 
     let root = options.flag_root.as_ref().map(|s| &s[..]);
-    try!(ops::uninstall(root, &options.arg_spec, &options.flag_bin, config));
+    let spec = match (options.arg_spec, options.flag_package) {
+        (Some(spec), None) | (None, Some(spec)) => spec,
+        _ => return Err(human("either a package id specification or \
+                               `-p SPEC` must be given, but not both").into()),
+    };
+    try!(ops::uninstall(root, &spec, &options.flag_bin, config));
     Ok(None)
 }
 
@@ -1,4 +1,5 @@
 use std::env;
+use std::path::Path;
 
 use cargo::core::Workspace;
 use cargo::ops;
@@ -10,7 +11,9 @@ pub struct Options {
     flag_package: Vec<String>,
     flag_aggressive: bool,
     flag_precise: Option<String>,
+    flag_as_of: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
@@ -29,7 +32,9 @@ Options:
     -p SPEC, --package SPEC ...  Package to update
     --aggressive                 Force updating all dependencies of <name> as well
     --precise PRECISE            Update a single dependency to exactly PRECISE
+    --as-of DATE                 Only use versions published before DATE
     --manifest-path PATH         Path to the crate's manifest
+    --lockfile PATH              Path to the lock file to use instead of Cargo.lock
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
@@ -54,6 +59,12 @@ be updated to.
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
 
+With --as-of, which updates every dependency, versions published to the
+registry on or after DATE, given as YYYY-MM-DD in UTC, are ignored as if the
+registry was still as it was then. The history of the registry index tells when
+each version was published, which makes it possible to bisect when an upstream
+release broke a build.
+
 For more information about package id specifications, see `cargo help pkgid`.
 ";
 
@@ -69,11 +80,15 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     let update_opts = ops::UpdateOptions {
         aggressive: options.flag_aggressive,
         precise: options.flag_precise.as_ref().map(|s| &s[..]),
+        as_of: options.flag_as_of.as_ref().map(|s| &s[..]),
         to_update: &options.flag_package,
         config: config,
     };
 
-    let ws = try!(Workspace::new(&root, config));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
     try!(ops::update_lockfile(&ws, &update_opts));
     Ok(None)
 }
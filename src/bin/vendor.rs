@@ -0,0 +1,61 @@
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_manifest_path: Option<String>,
+    flag_no_delete: bool,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    arg_path: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Vendor the dependencies of a package into a directory
+
+Usage:
+    cargo vendor [options] [<path>]
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest to vendor dependencies for
+    --no-delete              Keep packages the workspace doesn't depend on
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+Every dependency from a registry is unpacked into <path>, which defaults to
+`vendor` at the root of the workspace, with the checksums of its files. The
+configuration to add to `.cargo/config` to build with the vendored sources
+instead of the registries is printed once done, and the network isn't needed
+for the build anymore. Dependencies from git repositories can't be replaced
+with vendored sources, so vendoring a package depending on any is refused.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path,
+                                              config.cwd()));
+    let ws = try!(Workspace::new(&root, config));
+    let dir = match options.arg_path {
+        Some(ref path) => config.cwd().join(path),
+        None => ws.root().join("vendor"),
+    };
+    let source_config = try!(ops::vendor(&ws, &ops::VendorOptions {
+        dir: &dir,
+        no_delete: options.flag_no_delete,
+    }));
+    print!("{}", source_config);
+    Ok(None)
+}
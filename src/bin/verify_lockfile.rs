@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use rustc_serialize::json;
+
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config, human};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_manifest_path: Option<String>,
+    flag_lockfile: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Check whether Cargo.lock is up to date without modifying it
+
+Usage:
+    cargo verify-lockfile [options]
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest of the workspace to verify
+    --lockfile PATH          Path to the lock file to use instead of Cargo.lock
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+
+The dependency graph is resolved against the existing lock file exactly as a
+build would, but the lock file is never written. A JSON description of the
+packages which would be added, removed or changed is printed to stdout, and
+the command exits with status 1 if Cargo.lock is out of date.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked));
+    let root = try!(find_root_manifest_for_wd(options.flag_manifest_path,
+                                              config.cwd()));
+    let mut ws = try!(Workspace::new(&root, config));
+    if let Some(ref lockfile) = options.flag_lockfile {
+        ws.set_lockfile(Path::new(lockfile));
+    }
+    let drift = try!(ops::verify_lockfile(&ws));
+    println!("{}", json::encode(&drift).unwrap());
+    if drift.up_to_date {
+        Ok(None)
+    } else {
+        let msg = if drift.missing {
+            "Cargo.lock does not exist"
+        } else {
+            "Cargo.lock is out of date"
+        };
+        Err(CliError::new(human(msg), 1))
+    }
+}
@@ -263,6 +263,17 @@ impl Dependency {
     pub fn matches_id(&self, id: &PackageId) -> bool {
         self.inner.matches_id(id)
     }
+
+    /// Points this dependency at `replace_with` if it's on `to_replace`.
+    pub fn map_source(self, to_replace: &SourceId, replace_with: &SourceId)
+                      -> Dependency {
+        if self.source_id() != to_replace {
+            self
+        } else {
+            self.clone_inner().set_source_id(replace_with.clone())
+                .into_dependency()
+        }
+    }
 }
 
 impl Platform {
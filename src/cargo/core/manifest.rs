@@ -1,10 +1,13 @@
+use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::path::{PathBuf, Path};
 
 use semver::Version;
 use rustc_serialize::{Encoder, Encodable};
 
-use core::{Dependency, PackageId, PackageIdSpec, Summary, WorkspaceConfig};
+use core::{Dependency, PackageId, PackageIdSpec, SourceId, Summary};
+use core::WorkspaceConfig;
 use core::package_id::Metadata;
 
 pub enum EitherManifest {
@@ -26,12 +29,14 @@ pub struct Manifest {
     publish: bool,
     replace: Vec<(PackageIdSpec, Dependency)>,
     workspace: WorkspaceConfig,
+    inherits_workspace: bool,
 }
 
 #[derive(Clone, Debug)]
 pub struct VirtualManifest {
     replace: Vec<(PackageIdSpec, Dependency)>,
     workspace: WorkspaceConfig,
+    profiles: Profiles,
 }
 
 /// General metadata about a package which is just blindly uploaded to the
@@ -121,19 +126,33 @@ impl Encodable for TargetKind {
 #[derive(RustcEncodable, RustcDecodable, Clone, PartialEq, Eq, Debug, Hash)]
 pub struct Profile {
     pub opt_level: u32,
-    pub lto: bool,
+    pub lto: Lto,
     pub codegen_units: Option<u32>,    // None = use rustc default
     pub rustc_args: Option<Vec<String>>,
     pub rustdoc_args: Option<Vec<String>>,
     pub debuginfo: bool,
     pub debug_assertions: bool,
+    pub overflow_checks: bool,
+    pub incremental: bool,
     pub rpath: bool,
     pub test: bool,
     pub doc: bool,
     pub run_custom_build: bool,
+    pub check: bool,
     pub panic: Option<String>,
 }
 
+/// The kind of link-time optimization a profile asks for.
+#[derive(RustcEncodable, RustcDecodable, Clone, Copy, PartialEq, Eq, Debug,
+         Hash)]
+pub enum Lto {
+    Off,
+    /// Optimizes the whole crate graph as a single unit, `-C lto`.
+    Fat,
+    /// Optimizes across crates in parallel, `-C lto=thin`.
+    Thin,
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct Profiles {
     pub release: Profile,
@@ -144,11 +163,42 @@ pub struct Profiles {
     pub bench_deps: Profile,
     pub doc: Profile,
     pub custom_build: Profile,
+    pub check: Profile,
+    pub dev_overrides: Vec<ProfileOverride>,
+    pub release_overrides: Vec<ProfileOverride>,
+    /// The profiles build scripts, plugins and their dependencies are built
+    /// with instead of `dev` or `release`, if `build-override` is set.
+    pub dev_build_override: Option<Profile>,
+    pub release_build_override: Option<Profile>,
+    pub custom: HashMap<String, CustomProfile>,
+}
+
+/// A profile declared in the manifest besides the built-in ones, which builds
+/// select with `--profile`.
+#[derive(Clone, Debug)]
+pub struct CustomProfile {
+    /// Whether the profile inherits from `release` rather than `dev`.
+    pub release: bool,
+    pub profile: Profile,
+    pub overrides: Vec<ProfileOverride>,
+    pub build_override: Option<Profile>,
+}
+
+/// Settings of the `dev` or `release` profile which only apply to the packages
+/// matching `spec`, as configured with `[profile.dev.package."spec"]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileOverride {
+    pub spec: PackageIdSpec,
+    pub opt_level: Option<u32>,
+    pub codegen_units: Option<u32>,
+    pub debuginfo: Option<bool>,
+    pub debug_assertions: Option<bool>,
+    pub overflow_checks: Option<bool>,
 }
 
 /// Information about a binary, a library, an example, etc. that is part of the
 /// package.
-#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Target {
     kind: TargetKind,
     name: String,
@@ -160,6 +210,21 @@ pub struct Target {
     doctest: bool,
     harness: bool, // whether to use the test harness (--test)
     for_host: bool,
+    retries: u32, // times failing tests are run again before giving up
+    timeout: Option<u64>, // seconds tests may run for before being killed
+}
+
+// How tests are run doesn't change what they're compiled to, so `retries` and
+// `timeout` are left out of the hash the fingerprint of a target is made of.
+impl Hash for Target {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        let Target {
+            ref kind, ref name, ref src_path, ref metadata, tested, benched,
+            doc, doctest, harness, for_host, retries: _, timeout: _,
+        } = *self;
+        (kind, name, src_path, metadata, tested, benched, doc, doctest,
+         harness, for_host).hash(h)
+    }
 }
 
 #[derive(RustcEncodable)]
@@ -201,6 +266,7 @@ impl Manifest {
             publish: publish,
             replace: replace,
             workspace: workspace,
+            inherits_workspace: false,
         }
     }
 
@@ -225,6 +291,14 @@ impl Manifest {
         &self.workspace
     }
 
+    /// Whether any values of this manifest are inherited from the
+    /// `[workspace]` section of the workspace root.
+    pub fn inherits_workspace(&self) -> bool { self.inherits_workspace }
+
+    pub fn set_inherits_workspace(&mut self) {
+        self.inherits_workspace = true;
+    }
+
     pub fn add_warning(&mut self, s: String) {
         self.warnings.push(s)
     }
@@ -232,14 +306,24 @@ impl Manifest {
     pub fn set_summary(&mut self, summary: Summary) {
         self.summary = summary;
     }
+
+    pub fn map_source(self, to_replace: &SourceId, replace_with: &SourceId)
+                      -> Manifest {
+        Manifest {
+            summary: self.summary.map_source(to_replace, replace_with),
+            ..self
+        }
+    }
 }
 
 impl VirtualManifest {
     pub fn new(replace: Vec<(PackageIdSpec, Dependency)>,
-               workspace: WorkspaceConfig) -> VirtualManifest {
+               workspace: WorkspaceConfig,
+               profiles: Profiles) -> VirtualManifest {
         VirtualManifest {
             replace: replace,
             workspace: workspace,
+            profiles: profiles,
         }
     }
 
@@ -250,6 +334,10 @@ impl VirtualManifest {
     pub fn workspace_config(&self) -> &WorkspaceConfig {
         &self.workspace
     }
+
+    pub fn profiles(&self) -> &Profiles {
+        &self.profiles
+    }
 }
 
 impl Target {
@@ -265,6 +353,8 @@ impl Target {
             for_host: false,
             tested: true,
             benched: true,
+            retries: 0,
+            timeout: None,
         }
     }
 
@@ -353,11 +443,27 @@ impl Target {
     pub fn documented(&self) -> bool { self.doc }
     pub fn for_host(&self) -> bool { self.for_host }
     pub fn benched(&self) -> bool { self.benched }
+    pub fn retries(&self) -> u32 { self.retries }
+    pub fn timeout(&self) -> Option<u64> { self.timeout }
 
+    /// Whether the documentation of this library is tested. Procedural macros
+    /// are left out as doctests can't link to them.
     pub fn doctested(&self) -> bool {
         self.doctest && match self.kind {
             TargetKind::Lib(ref kinds) => {
-                kinds.contains(&LibKind::Rlib) || kinds.contains(&LibKind::Lib)
+                kinds.iter().any(|k| k.crate_type() != "proc-macro")
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether this library has to be compiled as an `rlib` besides its own
+    /// crate types for its doctests to link to it.
+    pub fn doctests_need_rlib(&self) -> bool {
+        self.doctested() && match self.kind {
+            TargetKind::Lib(ref kinds) => {
+                !kinds.contains(&LibKind::Rlib) &&
+                    !kinds.contains(&LibKind::Lib)
             }
             _ => false,
         }
@@ -383,6 +489,17 @@ impl Target {
         }
     }
 
+    /// Whether this is a library of procedural macros, which the compiler
+    /// loads and runs while compiling the crates using them.
+    pub fn is_proc_macro(&self) -> bool {
+        match self.kind {
+            TargetKind::Lib(ref kinds) => {
+                kinds.iter().any(|k| k.crate_type() == "proc-macro")
+            }
+            _ => false
+        }
+    }
+
     pub fn is_bin(&self) -> bool { self.kind == TargetKind::Bin }
     pub fn is_example(&self) -> bool { self.kind == TargetKind::Example }
     pub fn is_test(&self) -> bool { self.kind == TargetKind::Test }
@@ -414,6 +531,10 @@ impl Target {
         }
     }
 
+    pub fn set_kind(&mut self, kind: TargetKind) -> &mut Target {
+        self.kind = kind;
+        self
+    }
     pub fn set_tested(&mut self, tested: bool) -> &mut Target {
         self.tested = tested;
         self
@@ -438,6 +559,14 @@ impl Target {
         self.doc = doc;
         self
     }
+    pub fn set_retries(&mut self, retries: u32) -> &mut Target {
+        self.retries = retries;
+        self
+    }
+    pub fn set_timeout(&mut self, timeout: Option<u64>) -> &mut Target {
+        self.timeout = timeout;
+        self
+    }
 }
 
 impl fmt::Display for Target {
@@ -458,6 +587,7 @@ impl Profile {
         Profile {
             debuginfo: true,
             debug_assertions: true,
+            overflow_checks: true,
             ..Profile::default()
         }
     }
@@ -497,22 +627,86 @@ impl Profile {
             ..Profile::default_dev()
         }
     }
+
+    pub fn default_check() -> Profile {
+        Profile {
+            check: true,
+            ..Profile::default_dev()
+        }
+    }
+}
+
+impl Profiles {
+    /// Returns these profiles with the custom profile `name` taking the place
+    /// of the one it inherits from, including for tests and benchmarks, or
+    /// `None` if there's no such profile.
+    pub fn with_custom(&self, name: &str) -> Option<Profiles> {
+        let custom = match self.custom.get(name) {
+            Some(custom) => custom,
+            None => return None,
+        };
+        let mut ret = self.clone();
+        let profile = custom.profile.clone();
+        let test = Profile { test: true, ..profile.clone() };
+        let deps = Profile { panic: None, ..profile.clone() };
+        if custom.release {
+            ret.release = profile;
+            ret.bench = test;
+            ret.bench_deps = deps;
+            ret.release_overrides = custom.overrides.clone();
+            ret.release_build_override = custom.build_override.clone();
+        } else {
+            ret.dev = profile;
+            ret.test = test;
+            ret.test_deps = deps;
+            ret.dev_overrides = custom.overrides.clone();
+            ret.dev_build_override = custom.build_override.clone();
+        }
+        Some(ret)
+    }
+}
+
+impl ProfileOverride {
+    /// Changes the settings of `profile` configured by this override.
+    pub fn apply(&self, profile: &mut Profile) {
+        if let Some(opt_level) = self.opt_level {
+            profile.opt_level = opt_level;
+        }
+        if let Some(codegen_units) = self.codegen_units {
+            profile.codegen_units = Some(codegen_units);
+        }
+        if let Some(debuginfo) = self.debuginfo {
+            profile.debuginfo = debuginfo;
+        }
+        // Overflow checks follow the debug assertions unless they're
+        // configured on their own
+        if let Some(debug_assertions) = self.debug_assertions {
+            profile.debug_assertions = debug_assertions;
+            profile.overflow_checks = debug_assertions;
+        }
+        if let Some(overflow_checks) = self.overflow_checks {
+            profile.overflow_checks = overflow_checks;
+        }
+    }
 }
 
 impl Default for Profile {
     fn default() -> Profile {
         Profile {
             opt_level: 0,
-            lto: false,
+            lto: Lto::Off,
             codegen_units: None,
             rustc_args: None,
             rustdoc_args: None,
             debuginfo: false,
             debug_assertions: false,
+            overflow_checks: false,
+            incremental: false,
             rpath: false,
             test: false,
             doc: false,
             run_custom_build: false,
+            check: false,
             panic: None,
         }
     }
@@ -526,6 +720,8 @@ impl fmt::Display for Profile {
             write!(f, "Profile(doc)")
         } else if self.run_custom_build {
             write!(f, "Profile(run)")
+        } else if self.check {
+            write!(f, "Profile(check)")
         } else {
             write!(f, "Profile(build)")
         }
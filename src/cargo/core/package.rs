@@ -63,6 +63,14 @@ impl Package {
         }
     }
 
+    pub fn map_source(self, to_replace: &SourceId, replace_with: &SourceId)
+                      -> Package {
+        Package {
+            manifest: self.manifest.map_source(to_replace, replace_with),
+            manifest_path: self.manifest_path,
+        }
+    }
+
     pub fn for_path(manifest_path: &Path, config: &Config) -> CargoResult<Package> {
         let path = manifest_path.parent().unwrap();
         let source_id = try!(SourceId::for_path(path));
@@ -83,6 +91,23 @@ impl Package {
     pub fn authors(&self) -> &Vec<String> { &self.manifest.metadata().authors }
     pub fn publish(&self) -> bool { self.manifest.publish() }
 
+    /// Checks that all paths of this package can be serialized as they are,
+    /// rather than being mangled into their lossy representation.
+    pub fn check_unicode_paths(&self) -> CargoResult<()> {
+        if self.manifest_path.to_str().is_none() {
+            bail!("the manifest path of `{}` is not valid UTF-8: {}",
+                  self, self.manifest_path.display())
+        }
+        for target in self.targets() {
+            if target.src_path().to_str().is_none() {
+                bail!("the source path of target `{}` in `{}` is not valid \
+                       UTF-8: {}", target.name(), self,
+                      target.src_path().display())
+            }
+        }
+        Ok(())
+    }
+
     pub fn has_custom_build(&self) -> bool {
         self.targets().iter().any(|t| t.is_custom_build())
     }
@@ -141,6 +166,11 @@ impl<'cfg> PackageSet<'cfg> {
         Box::new(self.packages.iter().map(|&(ref p, _)| p))
     }
 
+    /// Returns whether the package `id` has been downloaded already.
+    pub fn is_downloaded(&self, id: &PackageId) -> bool {
+        self.packages.iter().any(|p| p.0 == *id && p.1.borrow().is_some())
+    }
+
     pub fn get(&self, id: &PackageId) -> CargoResult<&Package> {
         let slot = try!(self.packages.iter().find(|p| p.0 == *id).chain_error(|| {
             internal(format!("couldn't find `{}` in package set", id))
@@ -2,6 +2,7 @@ use std::collections::{HashSet, HashMap};
 
 use core::{Source, SourceId, SourceMap, Summary, Dependency, PackageId, Package};
 use core::PackageSet;
+use sources::SourceConfigMap;
 use util::{CargoResult, ChainError, Config, human, profile};
 
 /// Source of information about a group of packages.
@@ -158,8 +159,10 @@ impl<'cfg> PackageRegistry<'cfg> {
 
     fn load(&mut self, source_id: &SourceId, kind: Kind) -> CargoResult<()> {
         (|| {
-            // Save off the source
-            let source = source_id.load(self.config);
+            // Save off the source, or whatever it's configured to be
+            // replaced with
+            let map = try!(SourceConfigMap::new(self.config));
+            let source = try!(map.load(source_id));
             if kind == Kind::Override {
                 self.overrides.push(source_id.clone());
             }
@@ -20,7 +20,11 @@ pub type Metadata = BTreeMap<String, String>;
 impl EncodableResolve {
     pub fn to_resolve(&self, ws: &Workspace) -> CargoResult<Resolve> {
         let path_deps = build_path_deps(ws);
-        let default = try!(ws.current()).package_id().source_id();
+        let default = match ws.current_opt().or(ws.members().next()) {
+            Some(pkg) => pkg.package_id().source_id(),
+            None => bail!("the workspace at `{}` has no members",
+                          ws.root().display()),
+        };
 
         let mut g = Graph::new();
         let mut tmp = HashMap::new();
@@ -77,6 +77,7 @@ pub struct Resolve {
     features: HashMap<PackageId, HashSet<String>>,
     root: PackageId,
     metadata: Option<Metadata>,
+    checksums: HashMap<PackageId, Option<String>>,
 }
 
 pub struct Deps<'a> {
@@ -124,6 +125,7 @@ impl Resolve {
             replacements: HashMap::new(),
             features: HashMap::new(),
             metadata: None,
+            checksums: HashMap::new(),
         }
     }
 
@@ -131,6 +133,35 @@ impl Resolve {
         self.metadata = other.metadata.clone();
     }
 
+    /// Returns an entry of the `[metadata]` table of the lock file.
+    pub fn metadata_value(&self, key: &str) -> Option<&str> {
+        self.metadata.as_ref().and_then(|m| m.get(key)).map(|s| &s[..])
+    }
+
+    /// Sets an entry of the `[metadata]` table of the lock file, or removes it
+    /// if `value` is `None`.
+    pub fn set_metadata(&mut self, key: &str, value: Option<String>) {
+        let mut metadata = self.metadata.take().unwrap_or(Metadata::new());
+        match value {
+            Some(value) => { metadata.insert(key.to_string(), value); }
+            None => { metadata.remove(key); }
+        }
+        if !metadata.is_empty() {
+            self.metadata = Some(metadata);
+        }
+    }
+
+    /// Returns the keys of the `[metadata]` table of the lock file.
+    pub fn metadata_keys(&self) -> Vec<String> {
+        self.metadata.iter().flat_map(|m| m.keys().cloned()).collect()
+    }
+
+    /// Returns the checksums of the packages activated during resolution, for
+    /// those sources which provide them.
+    pub fn checksums(&self) -> &HashMap<PackageId, Option<String>> {
+        &self.checksums
+    }
+
     pub fn iter(&self) -> Nodes<PackageId> {
         self.graph.iter()
     }
@@ -252,6 +283,9 @@ fn activate(cx: &mut Context,
         }
     };
 
+    cx.resolve.checksums.insert(candidate.package_id().clone(),
+                                candidate.checksum().map(|s| s.to_string()));
+
     let deps = try!(cx.build_deps(registry, &candidate, method));
 
     Ok(Some(DepsFrame {
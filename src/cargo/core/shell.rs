@@ -38,7 +38,10 @@ impl fmt::Display for ColorConfig {
 #[derive(Clone, Copy)]
 pub struct ShellConfig {
     pub color_config: ColorConfig,
-    pub tty: bool
+    pub tty: bool,
+    /// Number of columns of the terminal, `None` if unknown or if the output
+    /// isn't going to a terminal at all.
+    pub width: Option<usize>,
 }
 
 enum AdequateTerminal {
@@ -205,6 +208,14 @@ impl Shell {
         self.config.color_config = color_config;
     }
 
+    pub fn width(&self) -> Option<usize> {
+        self.config.width
+    }
+
+    pub fn is_tty(&self) -> bool {
+        self.config.tty
+    }
+
     pub fn say<T: ToString>(&mut self, message: T, color: Color) -> CargoResult<()> {
         try!(self.reset());
         if color != BLACK { try!(self.fg(color)); }
@@ -231,7 +242,17 @@ impl Shell {
             try!(write!(self, "{}", status));
         }
         try!(self.reset());
-        try!(write!(self, " {}\n", message));
+        let message = message.to_string();
+        match self.config.width {
+            // Wrap long messages so continuation lines stay aligned with the
+            // message instead of running underneath the status.
+            Some(width) if justified && !message.contains('\n') &&
+                           width >= 13 + MIN_WRAP_WIDTH => {
+                let lines = wrap(&message, width - 13);
+                try!(write!(self, " {}\n", lines.join("\n             ")));
+            }
+            _ => try!(write!(self, " {}\n", message)),
+        }
         try!(self.flush());
         Ok(())
     }
@@ -275,12 +296,37 @@ impl Shell {
         Ok(())
     }
 
-    fn colored(&self) -> bool {
+    /// Whether what's printed here is colored.
+    pub fn colored(&self) -> bool {
         self.config.tty && Auto == self.config.color_config
             || Always == self.config.color_config
     }
 }
 
+/// Messages aren't wrapped on terminals narrower than this, as that would
+/// only make them harder to read.
+const MIN_WRAP_WIDTH: usize = 20;
+
+/// Splits `text` into lines of at most `width` characters at whitespace.
+/// Words longer than `width` are put on a line of their own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ') {
+        let len = line.chars().count();
+        if len > 0 && len + 1 + word.chars().count() > width {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+}
+
 impl Write for Shell {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self.terminal {
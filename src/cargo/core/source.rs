@@ -9,9 +9,11 @@ use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
 use url::Url;
 
 use core::{Package, PackageId, Registry};
-use sources::{PathSource, GitSource, RegistrySource};
+use ops;
+use sources::{PathSource, GitSource, RegistrySource, DirectorySource};
 use sources::git;
 use util::{human, Config, CargoResult, ToUrl};
+use util::paths;
 
 /// A Source finds and downloads remote packages based on names and
 /// versions.
@@ -37,6 +39,20 @@ pub trait Source: Registry {
     fn fingerprint(&self, pkg: &Package) -> CargoResult<String>;
 }
 
+/// Creates the `Source`s of a kind of source Cargo doesn't know about itself,
+/// like a corporate artifact store.
+///
+/// A factory is registered under the name of its kind with
+/// `Config::register_source_kind`, after which the `SourceId`s of that kind
+/// are written as `<kind>+<url>`, for example in the `registry.index` setting
+/// of the configuration or in lock files.
+pub trait SourceFactory {
+    /// Creates the source identified by `id`, whose `url` is what followed
+    /// the name of the kind.
+    fn load<'a>(&self, id: &SourceId, config: &'a Config)
+                -> CargoResult<Box<Source + 'a>>;
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Kind {
     /// Kind::Git(<git reference>) represents a git repository
@@ -45,6 +61,12 @@ enum Kind {
     Path,
     /// represents the central registry
     Registry,
+    /// represents a registry synced to a local directory, index and crates
+    LocalRegistry,
+    /// represents a local directory of vendored packages
+    Directory,
+    /// a kind of source registered with `Config::register_source_kind`
+    Custom(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -125,7 +147,19 @@ impl SourceId {
                 let url = url.to_url().unwrap();
                 SourceId::new(Kind::Path, url)
             }
-            _ => panic!("Unsupported serialized SourceId"),
+            "directory" => {
+                let url = url.to_url().unwrap();
+                SourceId::new(Kind::Directory, url)
+            }
+            "local-registry" => {
+                let url = url.to_url().unwrap();
+                SourceId::new(Kind::LocalRegistry, url)
+                    .with_precise(Some("locked".to_string()))
+            }
+            kind => {
+                let url = url.to_url().unwrap();
+                SourceId::for_custom(kind, &url)
+            }
         }
     }
 
@@ -150,12 +184,25 @@ impl SourceId {
             SourceIdInner { kind: Kind::Registry, ref url, .. } => {
                 format!("registry+{}", url)
             }
+            SourceIdInner { kind: Kind::Directory, ref url, .. } => {
+                format!("directory+{}", url)
+            }
+            SourceIdInner { kind: Kind::LocalRegistry, ref url, .. } => {
+                format!("local-registry+{}", url)
+            }
+            SourceIdInner { kind: Kind::Custom(ref kind), ref url, .. } => {
+                format!("{}+{}", kind, url)
+            }
         }
     }
 
     // Pass absolute path
+    /// Creates a `SourceId` for the package in the directory `path`.
+    ///
+    /// Symlinks in `path` are resolved so that a package always has the same
+    /// identity, whichever route it's reached through.
     pub fn for_path(path: &Path) -> CargoResult<SourceId> {
-        let url = try!(path.to_url().map_err(human));
+        let url = try!(paths::canonicalize(path).to_url().map_err(human));
         Ok(SourceId::new(Kind::Path, url))
     }
 
@@ -167,11 +214,49 @@ impl SourceId {
         SourceId::new(Kind::Registry, url.clone())
     }
 
+    /// Creates a `SourceId` for a directory of vendored packages.
+    pub fn for_directory(path: &Path) -> CargoResult<SourceId> {
+        let url = try!(path.to_url().map_err(human));
+        Ok(SourceId::new(Kind::Directory, url))
+    }
+
+    /// Creates a `SourceId` for a registry whose index and `.crate` files are
+    /// in the directory `path`.
+    pub fn for_local_registry(path: &Path) -> CargoResult<SourceId> {
+        let url = try!(path.to_url().map_err(human));
+        Ok(SourceId::new(Kind::LocalRegistry, url))
+    }
+
+    /// Creates a `SourceId` for a source of a kind registered with
+    /// `Config::register_source_kind`.
+    pub fn for_custom(kind: &str, url: &Url) -> SourceId {
+        SourceId::new(Kind::Custom(kind.to_string()), url.clone())
+    }
+
     /// Returns the `SourceId` corresponding to the main repository.
     ///
     /// This is the main cargo registry by default, but it can be overridden in
-    /// a `.cargo/config`.
+    /// a `.cargo/config`, including with the `<kind>+<url>` of a registered
+    /// kind of source.
     pub fn for_central(config: &Config) -> CargoResult<SourceId> {
+        let index = try!(ops::registry_configuration(config)).index;
+        if let Some(index) = index {
+            let mut parts = index.splitn(2, '+');
+            let kind = parts.next().unwrap();
+            // A plain URL can contain a `+` too, but never before its scheme
+            let is_kind = kind.chars().all(|c| {
+                c.is_alphanumeric() || c == '-' || c == '_'
+            });
+            if let (Some(url), true) = (parts.next(), is_kind) {
+                if config.source_kind(kind).is_none() {
+                    bail!("no kind of source named `{}` is known to this \
+                           version of Cargo, which `registry.index` needs \
+                           for `{}`", kind, index)
+                }
+                let url = try!(url.to_url().map_err(human));
+                return Ok(SourceId::for_custom(kind, &url))
+            }
+        }
         Ok(SourceId::for_registry(&try!(RegistrySource::url(config))))
     }
 
@@ -181,8 +266,12 @@ impl SourceId {
     pub fn is_path(&self) -> bool {
         self.inner.kind == Kind::Path
     }
+    /// Whether this is a registry, remote or local.
     pub fn is_registry(&self) -> bool {
-        self.inner.kind == Kind::Registry
+        match self.inner.kind {
+            Kind::Registry | Kind::LocalRegistry => true,
+            _ => false,
+        }
     }
 
     pub fn is_git(&self) -> bool {
@@ -192,19 +281,51 @@ impl SourceId {
         }
     }
 
+    /// Returns the name of the kind of source if it's one registered with
+    /// `Config::register_source_kind`.
+    pub fn custom_kind(&self) -> Option<&str> {
+        match self.inner.kind {
+            Kind::Custom(ref kind) => Some(&kind[..]),
+            _ => None,
+        }
+    }
+
     /// Creates an implementation of `Source` corresponding to this ID.
-    pub fn load<'a>(&self, config: &'a Config) -> Box<Source + 'a> {
+    pub fn load<'a>(&self, config: &'a Config)
+                    -> CargoResult<Box<Source + 'a>> {
         trace!("loading SourceId; {}", self);
         match self.inner.kind {
-            Kind::Git(..) => Box::new(GitSource::new(self, config)),
+            Kind::Git(..) => Ok(Box::new(GitSource::new(self, config))),
             Kind::Path => {
                 let path = match self.inner.url.to_file_path() {
                     Ok(p) => p,
                     Err(()) => panic!("path sources cannot be remote"),
                 };
-                Box::new(PathSource::new(&path, self, config))
+                Ok(Box::new(PathSource::new(&path, self, config)))
+            }
+            Kind::Registry => Ok(Box::new(RegistrySource::new(self, config))),
+            Kind::LocalRegistry => {
+                let path = match self.inner.url.to_file_path() {
+                    Ok(p) => p,
+                    Err(()) => panic!("local registries cannot be remote"),
+                };
+                Ok(Box::new(RegistrySource::local(self, &path, config)))
+            }
+            Kind::Directory => {
+                let path = match self.inner.url.to_file_path() {
+                    Ok(p) => p,
+                    Err(()) => panic!("directory sources cannot be remote"),
+                };
+                Ok(Box::new(DirectorySource::new(&path, self, config)))
+            }
+            Kind::Custom(ref kind) => {
+                match config.source_kind(kind) {
+                    Some(factory) => factory.load(self, config),
+                    None => bail!("no kind of source named `{}` is known to \
+                                   this version of Cargo, which `{}` needs",
+                                  kind, self.to_url()),
+                }
             }
-            Kind::Registry => Box::new(RegistrySource::new(self, config)),
         }
     }
 
@@ -291,6 +412,15 @@ impl fmt::Display for SourceId {
             SourceIdInner { kind: Kind::Registry, ref url, .. } => {
                 write!(f, "registry {}", url)
             }
+            SourceIdInner { kind: Kind::Directory, ref url, .. } => {
+                write!(f, "dir {}", url)
+            }
+            SourceIdInner { kind: Kind::LocalRegistry, ref url, .. } => {
+                write!(f, "registry {}", url)
+            }
+            SourceIdInner { kind: Kind::Custom(ref kind), ref url, .. } => {
+                write!(f, "{} {}", kind, url)
+            }
         }
     }
 }
@@ -466,4 +596,16 @@ mod tests {
         let s3 = SourceId::new(foo, loc);
         assert!(s1 != s3);
     }
+
+    #[test]
+    fn custom_sources_round_trip() {
+        let url = "https://artifacts.example.com/crates".to_url().unwrap();
+        let s1 = SourceId::for_custom("s3", &url);
+        assert_eq!(s1.to_url(), "s3+https://artifacts.example.com/crates");
+        assert_eq!(s1.custom_kind(), Some("s3"));
+
+        let s2 = SourceId::from_url(&s1.to_url());
+        assert_eq!(s1, s2);
+        assert!(s2 != SourceId::for_registry(&url));
+    }
 }
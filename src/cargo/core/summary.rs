@@ -15,6 +15,8 @@ pub struct Summary {
     package_id: PackageId,
     dependencies: Vec<Dependency>,
     features: HashMap<String, Vec<String>>,
+    // The checksum of the package's contents, if its source vouches for one
+    checksum: Option<String>,
 }
 
 impl Summary {
@@ -60,6 +62,7 @@ impl Summary {
             package_id: pkg_id,
             dependencies: dependencies,
             features: features,
+            checksum: None,
         })
     }
 
@@ -69,6 +72,13 @@ impl Summary {
     pub fn source_id(&self) -> &SourceId { self.package_id.source_id() }
     pub fn dependencies(&self) -> &[Dependency] { &self.dependencies }
     pub fn features(&self) -> &HashMap<String, Vec<String>> { &self.features }
+    pub fn checksum(&self) -> Option<&str> {
+        self.checksum.as_ref().map(|s| &s[..])
+    }
+
+    pub fn set_checksum(&mut self, cksum: String) {
+        self.checksum = Some(cksum);
+    }
 
     pub fn override_id(mut self, id: PackageId) -> Summary {
         self.package_id = id;
@@ -81,6 +91,19 @@ impl Summary {
         self.dependencies = deps.into_iter().map(f).collect();
         self
     }
+
+    /// Moves this summary and its dependencies from `to_replace` over to
+    /// `replace_with`, leaving those on other sources as they are.
+    pub fn map_source(self, to_replace: &SourceId, replace_with: &SourceId)
+                      -> Summary {
+        let me = if self.package_id().source_id() == to_replace {
+            let id = self.package_id().with_source_id(replace_with);
+            self.override_id(id)
+        } else {
+            self
+        };
+        me.map_dependencies(|dep| dep.map_source(to_replace, replace_with))
+    }
 }
 
 impl PartialEq for Summary {
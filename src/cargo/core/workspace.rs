@@ -3,10 +3,12 @@ use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::slice;
 
-use core::{Package, VirtualManifest, EitherManifest, SourceId};
+use glob;
+
+use core::{Package, VirtualManifest, EitherManifest, SourceId, Profiles};
 use core::{PackageIdSpec, Dependency};
 use ops;
-use util::{Config, CargoResult};
+use util::{Config, CargoResult, ChainError, Filesystem, human};
 use util::paths;
 
 /// The core abstraction in Cargo for working with a workspace of crates.
@@ -36,6 +38,26 @@ pub struct Workspace<'cfg> {
     // paths. The packages themselves can be looked up through the `packages`
     // set above.
     members: Vec<PathBuf>,
+
+    // The subset of `members` which commands operate on when no packages are
+    // selected explicitly. This is the `workspace.default-members` list when
+    // invoked from the root of a workspace which has one, and otherwise just
+    // the current package.
+    default_members: Vec<PathBuf>,
+
+    // An alternate lock file to resolve against instead of the `Cargo.lock`
+    // next to the root manifest, as selected with `--lockfile`.
+    lockfile: Option<PathBuf>,
+
+    // Whether the lock file is left out of resolution, so the newest
+    // versions allowed are picked, as `cargo install` does by default.
+    ignore_lock: bool,
+
+    // The directory build artifacts are placed in if it's not the `target`
+    // directory next to the root manifest, either because it was configured
+    // through `build.target-dir` or `CARGO_TARGET_DIR` or because it was
+    // selected with `set_target_dir`.
+    target_dir: Option<Filesystem>,
 }
 
 // Separate structure for tracking loaded packages (to avoid loading anything
@@ -54,8 +76,13 @@ enum MaybePackage {
 #[derive(Debug, Clone)]
 pub enum WorkspaceConfig {
     /// Indicates that `[workspace]` was present and the members were
-    /// optionally specified as well.
-    Root { members: Option<Vec<String>> },
+    /// optionally specified as well, along with the members to operate on by
+    /// default and the directories to exclude from the workspace.
+    Root {
+        members: Option<Vec<String>>,
+        default_members: Option<Vec<String>>,
+        exclude: Vec<String>,
+    },
 
     /// Indicates that `[workspace]` was present and the `root` field is the
     /// optional value of `package.workspace`, if present.
@@ -76,19 +103,33 @@ impl<'cfg> Workspace<'cfg> {
     /// This function will construct the entire workspace by determining the
     /// root and all member packages. It will then validate the workspace
     /// before returning it, so `Ok` is only returned for valid workspaces.
+    ///
+    /// The root is searched for starting from `manifest_path` as given, so a
+    /// member symlinked into the directory of a workspace belongs to it. All
+    /// manifests are then referred to by their canonical paths, which is also
+    /// what the package ids of path dependencies are based on, so a package
+    /// reached through several symlinks is still a single package.
     pub fn new(manifest_path: &Path, config: &'cfg Config)
                -> CargoResult<Workspace<'cfg>> {
+        let current_manifest = paths::canonicalize(manifest_path);
         let mut ws = Workspace {
             config: config,
-            current_manifest: manifest_path.to_path_buf(),
+            current_manifest: current_manifest.clone(),
             packages: Packages {
                 config: config,
                 packages: HashMap::new(),
             },
             root_manifest: None,
             members: Vec::new(),
+            default_members: Vec::new(),
+            lockfile: None,
+            ignore_lock: false,
+            target_dir: config.target_dir(),
         };
-        ws.root_manifest = try!(ws.find_root(manifest_path));
+        ws.root_manifest = try!(ws.find_root(manifest_path)).map(|root| {
+            paths::canonicalize(&root)
+        });
+        try!(ws.packages.load(&current_manifest));
         try!(ws.find_members());
         try!(ws.validate());
         Ok(ws)
@@ -104,21 +145,39 @@ impl<'cfg> Workspace<'cfg> {
     /// This is currently only used in niche situations like `cargo install` or
     /// `cargo package`.
     pub fn one(package: Package, config: &'cfg Config) -> Workspace<'cfg> {
+        Workspace::many(vec![package], config)
+    }
+
+    /// Creates a "temporary workspace" like `one` does, from several packages
+    /// which are all its members. The first package is the current one.
+    ///
+    /// This is used by `cargo install` to build several crates at once. Like
+    /// in any workspace, the `[profile]` and `[replace]` sections are those of
+    /// the current package, the others' are ignored.
+    pub fn many(packages: Vec<Package>, config: &'cfg Config)
+                -> Workspace<'cfg> {
+        assert!(!packages.is_empty());
         let mut ws = Workspace {
             config: config,
-            current_manifest: package.manifest_path().to_path_buf(),
+            current_manifest: packages[0].manifest_path().to_path_buf(),
             packages: Packages {
                 config: config,
                 packages: HashMap::new(),
             },
             root_manifest: None,
             members: Vec::new(),
+            default_members: Vec::new(),
+            lockfile: None,
+            ignore_lock: false,
+            target_dir: config.target_dir(),
         };
-        {
-            let key = ws.current_manifest.parent().unwrap();
+        for package in packages {
+            let manifest = package.manifest_path().to_path_buf();
+            let key = manifest.parent().unwrap().to_path_buf();
             let package = MaybePackage::Package(package);
-            ws.packages.packages.insert(key.to_path_buf(), package);
-            ws.members.push(ws.current_manifest.clone());
+            ws.packages.packages.insert(key, package);
+            ws.members.push(manifest.clone());
+            ws.default_members.push(manifest);
         }
         return ws
     }
@@ -139,6 +198,24 @@ impl<'cfg> Workspace<'cfg> {
         }
     }
 
+    /// Returns the current package of this workspace, or `None` if the
+    /// current manifest is a virtual manifest.
+    pub fn current_opt(&self) -> Option<&Package> {
+        match *self.packages.get(&self.current_manifest) {
+            MaybePackage::Package(ref p) => Some(p),
+            MaybePackage::Virtual(..) => None,
+        }
+    }
+
+    /// Returns the profiles to compile with, defined either by the current
+    /// package or by the virtual manifest.
+    pub fn profiles(&self) -> &Profiles {
+        match *self.packages.get(&self.current_manifest) {
+            MaybePackage::Package(ref p) => p.manifest().profiles(),
+            MaybePackage::Virtual(ref v) => v.profiles(),
+        }
+    }
+
     /// Returns the `Config` this workspace is associated with.
     pub fn config(&self) -> &'cfg Config {
         self.config
@@ -155,6 +232,49 @@ impl<'cfg> Workspace<'cfg> {
         }.parent().unwrap()
     }
 
+    /// Returns the path to the lock file of this workspace.
+    ///
+    /// This is `Cargo.lock` in the workspace root unless another lock file
+    /// was selected with `set_lockfile`.
+    pub fn lockfile(&self) -> PathBuf {
+        match self.lockfile {
+            Some(ref p) => p.clone(),
+            None => self.root().join("Cargo.lock"),
+        }
+    }
+
+    /// Selects an alternate lock file for this workspace, allowing several
+    /// independently locked dependency graphs (e.g. one for host tools and
+    /// one for a cross-compiled target) to be maintained side by side.
+    ///
+    /// Relative paths are interpreted relative to the current directory.
+    pub fn set_lockfile(&mut self, path: &Path) {
+        self.lockfile = Some(self.config.cwd().join(path));
+    }
+
+    /// Returns whether the lock file is ignored when resolving dependencies.
+    pub fn ignore_lock(&self) -> bool {
+        self.ignore_lock
+    }
+
+    pub fn set_ignore_lock(&mut self, ignore: bool) {
+        self.ignore_lock = ignore;
+    }
+
+    /// Returns the directory in which all build artifacts of this workspace
+    /// are placed.
+    pub fn target_dir(&self) -> Filesystem {
+        self.target_dir.clone().unwrap_or_else(|| {
+            Filesystem::new(self.root().join("target"))
+        })
+    }
+
+    /// Places the build artifacts of this workspace in `dir`, for example to
+    /// share them with another workspace.
+    pub fn set_target_dir(&mut self, dir: Filesystem) {
+        self.target_dir = Some(dir);
+    }
+
     /// Returns the root [replace] section of this workspace.
     ///
     /// This may be from a virtual crate or an actual crate.
@@ -177,6 +297,19 @@ impl<'cfg> Workspace<'cfg> {
         }
     }
 
+    /// Returns an iterator over the packages which commands operate on when no
+    /// packages were selected on the command line.
+    ///
+    /// This is the `workspace.default-members` list if the workspace root
+    /// declares one and is the current manifest, and just the current package
+    /// otherwise.
+    pub fn default_members<'a>(&'a self) -> Members<'a, 'cfg> {
+        Members {
+            ws: self,
+            iter: self.default_members.iter(),
+        }
+    }
+
     /// Finds the root of a workspace for the crate whose manifest is located
     /// at `manifest_path`.
     ///
@@ -212,9 +345,14 @@ impl<'cfg> Workspace<'cfg> {
             debug!("find_root - trying {}", manifest.display());
             if let Ok(pkg) = self.packages.load(&manifest) {
                 match *pkg.workspace_config() {
-                    WorkspaceConfig::Root { .. } => {
-                        debug!("find_root - found");
-                        return Ok(Some(manifest))
+                    WorkspaceConfig::Root { ref exclude, .. } => {
+                        if is_excluded(&manifest, exclude, manifest_path) {
+                            debug!("find_root - excluded from {}",
+                                   manifest.display());
+                        } else {
+                            debug!("find_root - found");
+                            return Ok(Some(manifest))
+                        }
                     }
                     WorkspaceConfig::Member { .. } => {}
                 }
@@ -229,42 +367,73 @@ impl<'cfg> Workspace<'cfg> {
     /// of a workspace.
     ///
     /// If the `workspace.members` configuration is present, then this just
-    /// verifies that those are all valid packages to point to. Otherwise, this
-    /// will transitively follow all `path` dependencies looking for members of
-    /// the workspace.
+    /// verifies that those are all valid packages to point to, expanding any
+    /// glob patterns in the list. Otherwise, this will transitively follow
+    /// all `path` dependencies looking for members of the workspace. Packages
+    /// inside of a directory listed in `workspace.exclude` are never members.
     fn find_members(&mut self) -> CargoResult<()> {
         let root_manifest = match self.root_manifest {
             Some(ref path) => path.clone(),
             None => {
                 debug!("find_members - only me as a member");
                 self.members.push(self.current_manifest.clone());
+                self.default_members.push(self.current_manifest.clone());
                 return Ok(())
             }
         };
-        let members = {
+        let (members, default_members) = {
             let root = try!(self.packages.load(&root_manifest));
             match *root.workspace_config() {
-                WorkspaceConfig::Root { ref members } => members.clone(),
+                WorkspaceConfig::Root { ref members, ref default_members, .. } => {
+                    (members.clone(), default_members.clone())
+                }
                 _ => bail!("root of a workspace inferred but wasn't a root: {}",
                            root_manifest.display()),
             }
         };
 
+        let root = root_manifest.parent().unwrap();
         if let Some(list) = members {
-            let root = root_manifest.parent().unwrap();
-            for path in list {
-                let manifest_path = root.join(path).join("Cargo.toml");
+            for path in try!(expand_member_paths(root, &list)) {
+                let manifest_path = paths::canonicalize(&path)
+                                         .join("Cargo.toml");
                 try!(self.find_path_deps(&manifest_path));
             }
         }
 
-        self.find_path_deps(&root_manifest)
+        try!(self.find_path_deps(&root_manifest));
+
+        match default_members {
+            Some(ref list) if self.current_manifest == root_manifest => {
+                for path in try!(expand_member_paths(root, list)) {
+                    let manifest_path = paths::canonicalize(&path)
+                                             .join("Cargo.toml");
+                    if !self.members.contains(&manifest_path) {
+                        bail!("package `{}` is listed in `workspace.default-members` \
+                               but is not a member of the workspace",
+                              manifest_path.display())
+                    }
+                    self.default_members.push(manifest_path);
+                }
+            }
+            // Virtual manifests have nothing to build on their own, so they
+            // operate on the whole workspace unless told otherwise.
+            _ if self.current_opt().is_none() => {
+                self.default_members = self.members.clone();
+            }
+            _ => self.default_members.push(self.current_manifest.clone()),
+        }
+        Ok(())
     }
 
     fn find_path_deps(&mut self, manifest_path: &Path) -> CargoResult<()> {
         if self.members.iter().any(|p| p == manifest_path) {
             return Ok(())
         }
+        if self.is_excluded(manifest_path) {
+            debug!("find_members - excluded {}", manifest_path.display());
+            return Ok(())
+        }
 
         debug!("find_members - {}", manifest_path.display());
         self.members.push(manifest_path.to_path_buf());
@@ -288,6 +457,44 @@ impl<'cfg> Workspace<'cfg> {
         Ok(())
     }
 
+    /// Returns whether `manifest_path` is inside of a directory which the root
+    /// of this workspace excludes.
+    fn is_excluded(&self, manifest_path: &Path) -> bool {
+        let root = match self.root_manifest {
+            Some(ref root) => root,
+            None => return false,
+        };
+        match *self.packages.get(root).workspace_config() {
+            WorkspaceConfig::Root { ref exclude, .. } => {
+                is_excluded(root, exclude, manifest_path)
+            }
+            WorkspaceConfig::Member { .. } => false,
+        }
+    }
+
+    /// Returns the manifests of the members listed in `workspace.members` of
+    /// the root, keyed by their canonical paths, as they're found below the
+    /// root.
+    fn listed_members(&self) -> CargoResult<HashMap<PathBuf, PathBuf>> {
+        let mut ret = HashMap::new();
+        let root_manifest = match self.root_manifest {
+            Some(ref path) => path,
+            None => return Ok(ret),
+        };
+        let list = match *self.packages.get(root_manifest).workspace_config() {
+            WorkspaceConfig::Root { members: Some(ref list), .. } => {
+                list.clone()
+            }
+            _ => return Ok(ret),
+        };
+        let root = root_manifest.parent().unwrap();
+        for path in try!(expand_member_paths(root, &list)) {
+            let canonical = paths::canonicalize(&path).join("Cargo.toml");
+            ret.insert(canonical, path.join("Cargo.toml"));
+        }
+        Ok(ret)
+    }
+
     /// Validates a workspace, ensuring that a number of invariants are upheld:
     ///
     /// 1. A workspace only has one root.
@@ -340,8 +547,16 @@ impl<'cfg> Workspace<'cfg> {
             }
         }
 
+        // A member listed by the root may be a symlink to a directory
+        // elsewhere, so its root is searched for from where it's listed, just
+        // like the root of the current package is searched for from the path
+        // it was given as.
+        let listed = try!(self.listed_members());
         for member in self.members.clone() {
-            let root = try!(self.find_root(&member));
+            let path = listed.get(&member).unwrap_or(&member).clone();
+            let root = try!(self.find_root(&path)).map(|root| {
+                paths::canonicalize(&root)
+            });
             if root == self.root_manifest {
                 continue
             }
@@ -388,7 +603,7 @@ impl<'cfg> Workspace<'cfg> {
                 MaybePackage::Virtual(_) => members_msg,
                 MaybePackage::Package(ref p) => {
                     let members = match *p.manifest().workspace_config() {
-                        WorkspaceConfig::Root { ref members } => members,
+                        WorkspaceConfig::Root { ref members, .. } => members,
                         WorkspaceConfig::Member { .. } => unreachable!(),
                     };
                     if members.is_none() {
@@ -465,3 +680,59 @@ impl MaybePackage {
         }
     }
 }
+
+/// Expands the `workspace.members` list of the workspace rooted at `root` to
+/// the directories of the members.
+///
+/// Entries containing glob metacharacters (such as `crates/*`) are expanded
+/// to all directories matching them, while other entries are taken as is so
+/// that a missing member is still reported as an error. The directories are
+/// returned as they're found below `root`, symlinks and all, so callers
+/// resolve them to identify the members.
+fn expand_member_paths(root: &Path, members: &[String])
+                       -> CargoResult<Vec<PathBuf>> {
+    let mut ret = Vec::new();
+    for member in members {
+        if !member.contains(|c: char| c == '*' || c == '?' || c == '[') {
+            ret.push(paths::normalize_path(&root.join(member)));
+            continue
+        }
+        let pattern = root.join(member);
+        let pattern = try!(pattern.to_str().chain_error(|| {
+            human(format!("workspace member pattern `{}` is not valid \
+                           unicode", pattern.display()))
+        }));
+        let matches = try!(glob::glob(pattern).map_err(|e| {
+            human(format!("invalid workspace member pattern `{}`: {}",
+                          member, e))
+        }));
+        let mut dirs = Vec::new();
+        for path in matches {
+            let path = try!(path.map_err(|e| {
+                human(format!("failed to expand workspace member pattern \
+                               `{}`: {}", member, e))
+            }));
+            if path.is_dir() {
+                dirs.push(path);
+            }
+        }
+        dirs.sort();
+        ret.extend(dirs);
+    }
+    Ok(ret)
+}
+
+/// Returns whether `manifest_path` is inside of one of the `exclude`d
+/// directories of the workspace whose root manifest is `root_manifest`.
+///
+/// Paths are compared component by component once symlinks are resolved, so
+/// excluding `foo` doesn't exclude `foobar` and `./foo/` excludes `foo`.
+fn is_excluded(root_manifest: &Path, exclude: &[String], manifest_path: &Path)
+               -> bool {
+    let root = root_manifest.parent().unwrap();
+    let manifest_path = paths::canonicalize(manifest_path);
+    exclude.iter().any(|ex| {
+        let excluded = paths::canonicalize(&root.join(ex));
+        paths::without_prefix(&manifest_path, &excluded).is_some()
+    })
+}
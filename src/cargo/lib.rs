@@ -26,6 +26,7 @@ extern crate url;
 
 use std::env;
 use std::io;
+use std::mem;
 use rustc_serialize::{Decodable, Encodable};
 use rustc_serialize::json;
 use docopt::Docopt;
@@ -75,6 +76,7 @@ fn process<V, F>(mut callback: F)
     where F: FnMut(&[String], &Config) -> CliResult<Option<V>>,
           V: Encodable
 {
+    util::crash_report::install_panic_hook();
     let mut config = None;
     let result = (|| {
         config = Some(try!(Config::default()));
@@ -110,18 +112,32 @@ pub fn shell(verbosity: Verbosity, color_config: ColorConfig) -> MultiShell {
         Stderr,
     }
 
-    let tty = isatty(Output::Stderr);
+    // A dumb terminal is treated like a pipe, it can neither handle escape
+    // codes nor be relied upon to report its width.
+    let dumb = env::var("TERM").map(|t| t == "dumb").unwrap_or(false);
 
-    let config = ShellConfig { color_config: color_config, tty: tty };
+    let tty = !dumb && isatty(Output::Stderr);
+    let width = if tty {width(Output::Stderr)} else {None};
+
+    let config = ShellConfig { color_config: color_config, tty: tty,
+                               width: width };
     let err = Shell::create(|| Box::new(io::stderr()), config);
 
-    let tty = isatty(Output::Stdout);
+    let tty = !dumb && isatty(Output::Stdout);
+    let width = if tty {width(Output::Stdout)} else {None};
 
-    let config = ShellConfig { color_config: color_config, tty: tty };
+    let config = ShellConfig { color_config: color_config, tty: tty,
+                               width: width };
     let out = Shell::create(|| Box::new(io::stdout()), config);
 
     return MultiShell::new(out, err, verbosity);
 
+    // Terminals which don't report their size may still have `COLUMNS` set
+    fn columns() -> Option<usize> {
+        env::var("COLUMNS").ok().and_then(|s| s.parse().ok())
+                           .and_then(|n| if n > 0 {Some(n)} else {None})
+    }
+
     #[cfg(unix)]
     fn isatty(output: Output) -> bool {
         let fd = match output {
@@ -131,6 +147,23 @@ pub fn shell(verbosity: Verbosity, color_config: ColorConfig) -> MultiShell {
 
         unsafe { libc::isatty(fd) != 0 }
     }
+    #[cfg(unix)]
+    fn width(output: Output) -> Option<usize> {
+        let fd = match output {
+            Output::Stdout => libc::STDOUT_FILENO,
+            Output::Stderr => libc::STDERR_FILENO,
+        };
+
+        unsafe {
+            let mut winsize: libc::winsize = mem::zeroed();
+            if libc::ioctl(fd, libc::TIOCGWINSZ, &mut winsize) == 0 &&
+               winsize.ws_col > 0 {
+                Some(winsize.ws_col as usize)
+            } else {
+                columns()
+            }
+        }
+    }
     #[cfg(windows)]
     fn isatty(output: Output) -> bool {
         extern crate kernel32;
@@ -147,6 +180,27 @@ pub fn shell(verbosity: Verbosity, color_config: ColorConfig) -> MultiShell {
             kernel32::GetConsoleMode(handle, &mut out) != 0
         }
     }
+    #[cfg(windows)]
+    fn width(output: Output) -> Option<usize> {
+        extern crate kernel32;
+        extern crate winapi;
+
+        let handle = match output {
+            Output::Stdout => winapi::winbase::STD_OUTPUT_HANDLE,
+            Output::Stderr => winapi::winbase::STD_ERROR_HANDLE,
+        };
+
+        unsafe {
+            let handle = kernel32::GetStdHandle(handle);
+            let mut info: winapi::wincon::CONSOLE_SCREEN_BUFFER_INFO =
+                mem::zeroed();
+            if kernel32::GetConsoleScreenBufferInfo(handle, &mut info) != 0 {
+                Some((info.srWindow.Right - info.srWindow.Left + 1) as usize)
+            } else {
+                columns()
+            }
+        }
+    }
 }
 
 pub fn handle_error(err: CliError, shell: &mut MultiShell) {
@@ -0,0 +1,151 @@
+//! Baselines of benchmark results, saved by `cargo bench --save-baseline` in
+//! `target/benchmarks/<name>.json` and compared against with `--baseline`.
+//!
+//! Results are parsed out of the output of libtest, one `ns/iter` figure per
+//! benchmark, and are keyed by package, target and benchmark name.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use rustc_serialize::json;
+
+use core::Workspace;
+use util::{CargoResult, ChainError, human, paths};
+
+pub struct BaselineOptions<'a> {
+    /// Name the results are saved under
+    pub save: Option<&'a str>,
+    /// Name of the saved results to compare against
+    pub compare: Option<&'a str>,
+    /// How many percent slower than in the baseline a benchmark may get
+    /// before it counts as a regression
+    pub threshold: f64,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct Baseline {
+    /// Nanoseconds per iteration of each benchmark
+    benchmarks: BTreeMap<String, u64>,
+}
+
+/// Parses a line of the output of libtest for the result of a benchmark,
+/// `test <name> ... bench:  1,234 ns/iter (+/- 56)`.
+pub fn parse_line(line: &str) -> Option<(String, u64)> {
+    if !line.starts_with("test ") {
+        return None
+    }
+    let mut parts = line[5..].splitn(2, " ... bench:");
+    let (name, rest) = match (parts.next(), parts.next()) {
+        (Some(name), Some(rest)) => (name, rest),
+        _ => return None,
+    };
+    let mut words = rest.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some(ns), Some("ns/iter")) => {
+            ns.replace(",", "").parse().ok().map(|ns| (name.to_string(), ns))
+        }
+        _ => None,
+    }
+}
+
+/// Checks that the baseline to compare against was saved, so a run isn't
+/// wasted on benchmarks whose results can't be compared.
+pub fn check(ws: &Workspace, opts: &BaselineOptions) -> CargoResult<()> {
+    if let Some(name) = opts.compare {
+        if !baseline_path(ws, name).exists() {
+            bail!("no baseline named `{}` was saved, save one with `cargo \
+                   bench --save-baseline {}`", name, name)
+        }
+    }
+    Ok(())
+}
+
+/// Compares `results` against the baseline to compare against, if any, then
+/// saves them as a baseline if asked to. Regressions fail the run once the
+/// results are saved.
+pub fn process(ws: &Workspace,
+               opts: &BaselineOptions,
+               results: &[(String, u64)]) -> CargoResult<()> {
+    let regressions = match opts.compare {
+        Some(name) => try!(compare(ws, opts, name, results)),
+        None => 0,
+    };
+    if let Some(name) = opts.save {
+        let baseline = Baseline {
+            benchmarks: results.iter().cloned().collect(),
+        };
+        let path = baseline_path(ws, name);
+        try!(fs::create_dir_all(path.parent().unwrap()));
+        try!(paths::write(&path, json::encode(&baseline).unwrap().as_bytes()));
+        try!(ws.config().shell().status("Saved",
+                                        format!("{} benchmark(s) as baseline \
+                                                 `{}`", results.len(), name)));
+    }
+    if regressions > 0 {
+        bail!("{} benchmark(s) regressed by more than {}% compared to \
+               baseline `{}`", regressions, opts.threshold,
+              opts.compare.unwrap())
+    }
+    Ok(())
+}
+
+/// Reports how `results` changed since the baseline `name`, returning how
+/// many regressed beyond the threshold.
+fn compare(ws: &Workspace,
+           opts: &BaselineOptions,
+           name: &str,
+           results: &[(String, u64)]) -> CargoResult<usize> {
+    let path = baseline_path(ws, name);
+    let baseline = try!((|| -> CargoResult<Baseline> {
+        Ok(try!(json::decode(&try!(paths::read(&path)))))
+    })().chain_error(|| {
+        human(format!("failed to read the baseline at `{}`", path.display()))
+    }));
+
+    let mut shell = ws.config().shell();
+    let mut regressions = 0;
+    for &(ref bench, new) in results.iter() {
+        let old = match baseline.benchmarks.get(bench) {
+            Some(&old) => old,
+            None => continue,
+        };
+        let change = if old == 0 {
+            0.0
+        } else {
+            (new as f64 - old as f64) * 100.0 / old as f64
+        };
+        let msg = format!("{}: {} -> {} ns/iter ({:+.1}%)", bench, old, new,
+                          change);
+        if change > opts.threshold {
+            regressions += 1;
+            try!(shell.status("Regressed", msg));
+        } else if change < -opts.threshold {
+            try!(shell.status("Improved", msg));
+        } else {
+            try!(shell.verbose(|shell| shell.status("Unchanged", &msg)));
+        }
+    }
+    Ok(regressions)
+}
+
+fn baseline_path(ws: &Workspace, name: &str) -> PathBuf {
+    ws.target_dir().into_path_unlocked().join("benchmarks")
+      .join(format!("{}.json", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_line;
+
+    #[test]
+    fn bench_lines_parsed() {
+        assert_eq!(parse_line("test foo ... bench:       1,234 ns/iter \
+                               (+/- 56)"),
+                   Some(("foo".to_string(), 1234)));
+        assert_eq!(parse_line("test a::b ... bench: 7 ns/iter (+/- 0)"),
+                   Some(("a::b".to_string(), 7)));
+        assert_eq!(parse_line("test foo ... ok"), None);
+        assert_eq!(parse_line("running 1 test"), None);
+    }
+}
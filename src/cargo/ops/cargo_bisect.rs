@@ -0,0 +1,214 @@
+use std::env;
+
+use semver::Version;
+
+use core::{Dependency, PackageId, Workspace};
+use ops;
+use util::{CargoResult, ChainError, human, paths, process};
+
+pub struct BisectOptions<'a> {
+    /// Name of the dependency whose versions are bisected
+    pub package: &'a str,
+    /// The version known to work, defaults to the locked one
+    pub good: Option<&'a str>,
+    /// The version known to break, defaults to the newest one
+    pub bad: Option<&'a str>,
+    /// Runs `cargo test` rather than `cargo build` for each version
+    pub test: bool,
+    /// Command run instead of Cargo for each version, split on whitespace
+    pub command: Option<&'a str>,
+}
+
+/// The outcome of a bisection: the first version which breaks, the one before
+/// it, and where its changes may be found.
+pub struct BisectResult {
+    pub name: String,
+    pub last_good: Version,
+    pub first_bad: Version,
+    pub url: Option<String>,
+}
+
+/// Finds the first published version of a dependency which breaks the build,
+/// or the tests, of the workspace.
+///
+/// Each version tried is pinned in the lock file as with `cargo update -p
+/// <name> --precise <version>`, and the build is then run in a separate Cargo
+/// process. The lock file is restored once done, whatever the outcome.
+pub fn bisect(ws: &Workspace, opts: &BisectOptions)
+              -> CargoResult<BisectResult> {
+    let lockfile = ws.lockfile();
+    if !lockfile.exists() {
+        bail!("a lock file is required to bisect dependencies, run `cargo \
+               generate-lockfile` first")
+    }
+    let original = try!(paths::read(&lockfile));
+    let result = run_bisect(ws, opts);
+    try!(paths::write(&lockfile, original.as_bytes()).chain_error(|| {
+        human(format!("failed to restore the lock file at `{}`",
+                      lockfile.display()))
+    }));
+    result
+}
+
+fn run_bisect(ws: &Workspace, opts: &BisectOptions)
+              -> CargoResult<BisectResult> {
+    let config = ws.config();
+    let resolve = match try!(ops::load_pkg_lockfile(ws)) {
+        Some(resolve) => resolve,
+        None => unreachable!(),
+    };
+    let locked = try!(resolve.query(opts.package)).clone();
+    if !locked.source_id().is_registry() {
+        bail!("only dependencies from a registry can be bisected, but `{}` \
+               comes from `{}`", locked, locked.source_id())
+    }
+
+    let versions = try!(candidates(ws, &locked));
+    let good = match opts.good {
+        Some(v) => try!(parse_version(v)),
+        None => locked.version().clone(),
+    };
+    let bad = match opts.bad {
+        Some(v) => try!(parse_version(v)),
+        None => match versions.last() {
+            Some(v) => v.clone(),
+            None => bail!("no versions of `{}` can be used by the workspace",
+                          locked.name()),
+        },
+    };
+    if good >= bad {
+        bail!("the good version {} must be older than the bad version {}",
+              good, bad)
+    }
+    let mut versions = versions.into_iter().filter(|v| {
+        *v > good && *v <= bad
+    }).collect::<Vec<_>>();
+    versions.insert(0, good.clone());
+    if *versions.last().unwrap() != bad {
+        bail!("version {} of `{}` isn't published or can't be used by the \
+               workspace", bad, locked.name())
+    }
+
+    if !try!(check(ws, opts, locked.name(), &bad)) {
+        bail!("`{}` v{} doesn't break the build, so there's nothing to bisect",
+              locked.name(), bad)
+    }
+    if try!(check(ws, opts, locked.name(), &good)) {
+        bail!("`{}` v{} breaks the build as well", locked.name(), good)
+    }
+
+    // The first version is known to work and the last one to break
+    let (mut lo, mut hi) = (0, versions.len() - 1);
+    while hi - lo > 1 {
+        try!(config.shell().status("Bisecting",
+                                   format!("{} versions left to test",
+                                           hi - lo - 1)));
+        let mid = (lo + hi) / 2;
+        if try!(check(ws, opts, locked.name(), &versions[mid])) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    let url = try!(changes_url(ws, locked.name(), &versions[hi]));
+    Ok(BisectResult {
+        name: locked.name().to_string(),
+        last_good: versions[lo].clone(),
+        first_bad: versions[hi].clone(),
+        url: url,
+    })
+}
+
+/// Returns the published versions of the package `locked` is a version of
+/// which satisfy what the members of the workspace ask for, oldest first.
+fn candidates(ws: &Workspace, locked: &PackageId) -> CargoResult<Vec<Version>> {
+    let source_id = locked.source_id().clone().with_precise(None);
+    let mut source = try!(source_id.load(ws.config()));
+    try!(source.update());
+    let dep = try!(Dependency::parse(locked.name(), None, &source_id));
+    let reqs = ws.members().flat_map(|m| {
+        m.dependencies().iter().filter(|d| {
+            d.name() == locked.name() && d.source_id().is_registry()
+        }).map(|d| d.version_req().clone()).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+    let mut versions = try!(source.query(&dep)).into_iter().map(|s| {
+        s.version().clone()
+    }).filter(|v| reqs.iter().all(|r| r.matches(v))).collect::<Vec<_>>();
+    versions.sort();
+    versions.dedup();
+    Ok(versions)
+}
+
+fn parse_version(s: &str) -> CargoResult<Version> {
+    Version::parse(s).chain_error(|| {
+        human(format!("invalid version `{}`", s))
+    })
+}
+
+/// Pins `version` of `name` in the lock file and runs the build, returning
+/// whether it broke.
+fn check(ws: &Workspace, opts: &BisectOptions, name: &str, version: &Version)
+         -> CargoResult<bool> {
+    let config = ws.config();
+    let precise = version.to_string();
+    try!(ops::update_lockfile(ws, &ops::UpdateOptions {
+        config: config,
+        to_update: &[name.to_string()],
+        precise: Some(&precise),
+        aggressive: false,
+        as_of: None,
+    }));
+    try!(config.shell().status("Testing", format!("{} v{}", name, version)));
+
+    let mut cmd = match opts.command {
+        Some(command) => {
+            let mut words = command.split_whitespace();
+            let mut cmd = match words.next() {
+                Some(program) => process(program),
+                None => bail!("the command to run can't be empty"),
+            };
+            cmd.args(&words.collect::<Vec<_>>());
+            cmd
+        }
+        None => {
+            let mut cmd = process(try!(env::current_exe()));
+            cmd.arg(if opts.test {"test"} else {"build"})
+               .arg("--manifest-path").arg(ws.root().join("Cargo.toml"))
+               .arg("--lockfile").arg(ws.lockfile());
+            cmd
+        }
+    };
+    cmd.cwd(ws.root());
+    let broke = cmd.exec_with_output().is_err();
+    try!(config.shell().status(if broke {"Bad"} else {"Good"},
+                               format!("{} v{}", name, version)));
+    Ok(broke)
+}
+
+/// Returns where the changes made in `version` of `name` may be found: the
+/// repository or homepage of the package, or its page on crates.io.
+fn changes_url(ws: &Workspace, name: &str, version: &Version)
+               -> CargoResult<Option<String>> {
+    let precise = version.to_string();
+    try!(ops::update_lockfile(ws, &ops::UpdateOptions {
+        config: ws.config(),
+        to_update: &[name.to_string()],
+        precise: Some(&precise),
+        aggressive: false,
+        as_of: None,
+    }));
+    let (packages, resolve) = try!(ops::resolve_dependencies(
+        ws, None, Vec::new(), false, &[]));
+    let id = try!(resolve.query(&format!("{}:{}", name, version))).clone();
+    let pkg = try!(packages.get(&id));
+    let metadata = pkg.manifest().metadata();
+    let url = metadata.repository.clone().or(metadata.homepage.clone());
+    Ok(url.or_else(|| {
+        if id.source_id().is_default_registry() {
+            Some(format!("https://crates.io/crates/{}/{}", name, version))
+        } else {
+            None
+        }
+    }))
+}
@@ -0,0 +1,300 @@
+use std::path::{Path, PathBuf};
+
+use git2;
+use semver::Version;
+
+use core::Workspace;
+use core::registry::PackageRegistry;
+use ops;
+use util::{CargoResult, human, paths, ToSemver};
+use util::config::Config;
+
+pub struct BumpOptions<'a> {
+    pub config: &'a Config,
+    /// One of `major`, `minor`, `patch` or an explicit version number
+    pub version: &'a str,
+    pub commit: bool,
+    pub tag: bool,
+    pub publish: Option<&'a ops::PublishOpts<'a>>,
+}
+
+/// Bumps the version of the current package of `ws`.
+///
+/// The `version` key of the package's manifest is rewritten in place, and any
+/// other members of the workspace which depend on the package through a path
+/// dependency with a version requirement have that requirement updated as
+/// well. The lock file is regenerated afterwards and, if requested, the
+/// changes are committed (and tagged) before optionally publishing.
+pub fn bump(ws: &Workspace, opts: &BumpOptions) -> CargoResult<Version> {
+    let pkg = try!(ws.current());
+    let old = pkg.version().clone();
+    let new = try!(next_version(&old, opts.version));
+    if new <= old {
+        bail!("new version `{}` of `{}` must be greater than the current \
+               version `{}`", new, pkg.name(), old)
+    }
+
+    let mut changed = try!(bump_manifests(ws, &new, opts.config));
+
+    // Reload the workspace to pick up the new manifests and bring the lock
+    // file in line with them.
+    let lockfile = ws.lockfile();
+    let mut ws = try!(Workspace::new(pkg.manifest_path(), opts.config));
+    ws.set_lockfile(&lockfile);
+    let mut registry = PackageRegistry::new(opts.config);
+    try!(ops::resolve_ws(&mut registry, &ws));
+    if lockfile.exists() {
+        changed.push(lockfile);
+    }
+
+    if opts.commit || opts.tag {
+        let tags = if opts.tag {vec![format!("v{}", new)]} else {Vec::new()};
+        let msg = format!("Bump {} to {}", pkg.name(), new);
+        try!(commit(ws.root(), &changed, &msg, &tags));
+    }
+
+    if let Some(publish) = opts.publish {
+        try!(ops::publish(&ws, publish));
+    }
+
+    Ok(new)
+}
+
+/// Rewrites the manifest of the current package of `ws` to declare version
+/// `new`, along with the requirements of path dependencies on it from other
+/// workspace members. Returns the manifests which were modified.
+pub fn bump_manifests(ws: &Workspace, new: &Version, config: &Config)
+                      -> CargoResult<Vec<PathBuf>> {
+    let pkg = try!(ws.current());
+    try!(config.shell().status("Bumping",
+                               format!("{} v{} -> v{}", pkg.name(),
+                                       pkg.version(), new)));
+
+    let mut changed = Vec::new();
+    let manifest = try!(paths::read(pkg.manifest_path()));
+    let manifest = try!(rewrite_package_version(&manifest, new).ok_or_else(|| {
+        human(format!("failed to find the `version` key of the package in `{}`",
+                      pkg.manifest_path().display()))
+    }));
+    try!(paths::write(pkg.manifest_path(), manifest.as_bytes()));
+    changed.push(pkg.manifest_path().to_path_buf());
+
+    for member in ws.members() {
+        if member.package_id() == pkg.package_id() {
+            continue
+        }
+        let depends = member.dependencies().iter().any(|d| {
+            d.name() == pkg.name() && d.source_id().is_path() &&
+                d.specified_req()
+        });
+        if !depends {
+            continue
+        }
+        let contents = try!(paths::read(member.manifest_path()));
+        let contents = rewrite_dependency_version(&contents, pkg.name(), new);
+        if let Some(contents) = contents {
+            try!(config.shell().status("Updating",
+                                       format!("{} dependency on {}",
+                                               member.name(), pkg.name())));
+            try!(paths::write(member.manifest_path(), contents.as_bytes()));
+            changed.push(member.manifest_path().to_path_buf());
+        }
+    }
+    Ok(changed)
+}
+
+pub fn next_version(old: &Version, spec: &str) -> CargoResult<Version> {
+    let mut new = old.clone();
+    match spec {
+        "major" => new.increment_major(),
+        "minor" => new.increment_minor(),
+        "patch" => new.increment_patch(),
+        s => {
+            new = try!(s.to_semver().map_err(|_| {
+                human(format!("invalid version `{}`, expected `major`, \
+                               `minor`, `patch` or a semver version", s))
+            }));
+        }
+    }
+    Ok(new)
+}
+
+/// Rewrites the `version` key in the `[package]` (or `[project]`) section.
+///
+/// This works line by line instead of going through the TOML parser so the
+/// formatting and comments of the manifest are preserved. Returns `None` if
+/// no such key was found.
+fn rewrite_package_version(contents: &str, new: &Version) -> Option<String> {
+    let mut section = String::new();
+    let mut found = false;
+    let lines = contents.lines().map(|line| {
+        if let Some(name) = section_name(line) {
+            section = name;
+        } else if !found && (section == "package" || section == "project") &&
+                  key_of(line) == Some("version") {
+            if let Some(l) = replace_quoted(line, 0, new) {
+                found = true;
+                return l
+            }
+        }
+        line.to_string()
+    }).collect::<Vec<_>>();
+    if found {Some(join_lines(contents, lines))} else {None}
+}
+
+/// Rewrites the version requirement of all dependencies named `name`,
+/// returning `None` if nothing was changed.
+///
+/// Both the inline form (`name = { path = "..", version = ".." }`) and the
+/// table form (`[dependencies.name]`) are handled.
+fn rewrite_dependency_version(contents: &str, name: &str, new: &Version)
+                              -> Option<String> {
+    let mut section = String::new();
+    let mut changed = false;
+    let lines = contents.lines().map(|line| {
+        if let Some(s) = section_name(line) {
+            section = s;
+            return line.to_string()
+        }
+        let in_deps = is_dep_table(&section);
+        let in_dep = section.rsplitn(2, '.').next() == Some(name) &&
+                     section.rsplitn(2, '.').nth(1).map(is_dep_table)
+                            .unwrap_or(false);
+        let key = key_of(line);
+        let replaced = if in_deps && key == Some(name) {
+            inline_key(line, "version").and_then(|i| {
+                replace_quoted(line, i, new)
+            })
+        } else if in_dep && key == Some("version") {
+            replace_quoted(line, 0, new)
+        } else {
+            None
+        };
+        match replaced {
+            Some(l) => { changed = true; l }
+            None => line.to_string(),
+        }
+    }).collect::<Vec<_>>();
+    if changed {Some(join_lines(contents, lines))} else {None}
+}
+
+pub fn is_dep_table(section: &str) -> bool {
+    let last = section.rsplit('.').next().unwrap_or("");
+    last == "dependencies" || last == "dev-dependencies" ||
+        last == "build-dependencies"
+}
+
+pub fn section_name(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.starts_with('[') && line.ends_with(']') {
+        Some(line.trim_matches(|c| c == '[' || c == ']').trim()
+                 .split('.').map(|s| s.trim().trim_matches('"'))
+                 .collect::<Vec<_>>().join("."))
+    } else {
+        None
+    }
+}
+
+pub fn key_of(line: &str) -> Option<&str> {
+    let line = line.trim_left();
+    if line.starts_with('#') {
+        return None
+    }
+    line.find('=').map(|i| line[..i].trim().trim_matches('"'))
+}
+
+/// Finds `key = ..` in the inline table of a `name = { .. }` line, returning
+/// where its value starts. Only keys outside of strings are considered, so
+/// `path = "../version"` doesn't match `version`.
+pub fn inline_key(line: &str, key: &str) -> Option<usize> {
+    let start = match line.find('=') {
+        Some(i) => i + 1,
+        None => return None,
+    };
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut boundary = true;
+    for (i, c) in line[start..].char_indices() {
+        let i = start + i;
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue
+        }
+        if boundary && line[i..].starts_with(key) {
+            let rest = line[i + key.len()..].trim_left();
+            if rest.starts_with('=') {
+                return Some(line.len() - rest.len() + 1)
+            }
+        }
+        in_string = c == '"';
+        boundary = c == '{' || c == ',' || c.is_whitespace();
+    }
+    None
+}
+
+/// Replaces the contents of the first quoted string after `start` with `new`,
+/// keeping any leading requirement operator such as `^`, `~` or `=`.
+fn replace_quoted(line: &str, start: usize, new: &Version) -> Option<String> {
+    let open = match line[start..].find('"') {
+        Some(i) => start + i + 1,
+        None => return None,
+    };
+    let close = match line[open..].find('"') {
+        Some(i) => open + i,
+        None => return None,
+    };
+    let old = &line[open..close];
+    let op = old.find(|c: char| c.is_digit(10)).map(|i| &old[..i])
+                .unwrap_or("");
+    Some(format!("{}{}{}{}", &line[..open], op, new, &line[close..]))
+}
+
+pub fn join_lines(original: &str, lines: Vec<String>) -> String {
+    let mut ret = lines.join("\n");
+    if original.ends_with('\n') {
+        ret.push('\n');
+    }
+    ret
+}
+
+/// Commits `files` to the git repository containing `root`, creating an
+/// annotated tag for each of `tags` pointing at the new commit.
+pub fn commit(root: &Path, files: &[PathBuf], msg: &str, tags: &[String])
+              -> CargoResult<()> {
+    let repo = try!(git2::Repository::discover(root).map_err(|_| {
+        human(format!("cannot commit the version bump, `{}` is not inside \
+                       of a git repository", root.display()))
+    }));
+    let workdir = match repo.workdir() {
+        Some(dir) => dir.to_path_buf(),
+        None => bail!("cannot commit the version bump to a bare repository"),
+    };
+    let mut index = try!(repo.index());
+    for file in files {
+        let file = try!(paths::without_prefix(file, &workdir).ok_or_else(|| {
+            human(format!("`{}` is not inside of the git repository at `{}`",
+                          file.display(), workdir.display()))
+        }));
+        try!(index.add_path(file));
+    }
+    try!(index.write());
+    let tree = try!(repo.find_tree(try!(index.write_tree())));
+    let sig = try!(repo.signature());
+    let mut parents = Vec::new();
+    if let Some(head) = repo.head().ok().and_then(|h| h.target()) {
+        parents.push(try!(repo.find_commit(head)));
+    }
+    let parents = parents.iter().collect::<Vec<_>>();
+    let id = try!(repo.commit(Some("HEAD"), &sig, &sig, msg, &tree, &parents));
+    let object = try!(repo.find_object(id, None));
+    for tag in tags {
+        try!(repo.tag(tag, &object, &sig, msg, false));
+    }
+    Ok(())
+}
@@ -2,7 +2,7 @@ use std::default::Default;
 use std::fs;
 use std::path::Path;
 
-use core::{Profiles, Workspace};
+use core::{PackageSet, Profiles, Resolve, Workspace};
 use core::registry::PackageRegistry;
 use util::{CargoResult, human, ChainError, Config};
 use ops::{self, Context, BuildConfig, Kind, Unit};
@@ -12,11 +12,19 @@ pub struct CleanOptions<'a> {
     pub target: Option<&'a str>,
     pub config: &'a Config,
     pub release: bool,
+    pub out_dirs: bool,
 }
 
 /// Cleans the project from build artifacts.
 pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
-    let target_dir = opts.config.target_dir(&ws);
+    let target_dir = ws.target_dir();
+
+    if opts.out_dirs {
+        if !opts.spec.is_empty() {
+            bail!("--out-dirs cannot be used together with --package")
+        }
+        return clean_out_dirs(ws, opts);
+    }
 
     // If we have a spec, then we need to delete some packages, otherwise, just
     // remove the whole target directory and be done with it!
@@ -32,16 +40,8 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
     let resolve = try!(ops::resolve_ws(&mut registry, ws));
     let packages = ops::get_resolved_packages(&resolve, registry);
 
-    let profiles = try!(ws.current()).manifest().profiles();
-    let host_triple = try!(opts.config.rustc()).host.clone();
-    let mut cx = try!(Context::new(ws, &resolve, &packages, opts.config,
-                                   BuildConfig {
-                                       host_triple: host_triple,
-                                       requested_target: opts.target.map(|s| s.to_owned()),
-                                       release: opts.release,
-                                       ..BuildConfig::default()
-                                   },
-                                   profiles));
+    let profiles = ws.profiles();
+    let mut cx = try!(context(ws, &resolve, &packages, opts));
     let mut units = Vec::new();
 
     for spec in opts.spec {
@@ -51,13 +51,16 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
 
         // Generate all relevant `Unit` targets for this package
         for target in pkg.targets() {
-            for kind in [Kind::Host, Kind::Target].iter() {
+            for kind in [Kind::Host, Kind::Target(0)].iter() {
                 let Profiles {
                     ref release, ref dev, ref test, ref bench, ref doc,
                     ref custom_build, ref test_deps, ref bench_deps,
+                    ref check, ..
                 } = *profiles;
-                let profiles = [release, dev, test, bench, doc, custom_build,
-                                test_deps, bench_deps];
+                let mut profiles = vec![release, dev, test, bench, doc,
+                                        custom_build, test_deps, bench_deps,
+                                        check];
+                profiles.extend(cx.build_override());
                 for profile in profiles.iter() {
                     units.push(Unit {
                         pkg: &pkg,
@@ -86,6 +89,38 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
     Ok(())
 }
 
+/// Removes the build script directories, including their `OUT_DIR`, of the
+/// packages which aren't part of the dependency graph anymore.
+fn clean_out_dirs(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
+    let mut registry = PackageRegistry::new(opts.config);
+    let resolve = try!(ops::resolve_ws(&mut registry, ws));
+    let packages = ops::get_resolved_packages(&resolve, registry);
+    let cx = try!(context(ws, &resolve, &packages, opts));
+
+    for dir in try!(cx.stale_build_dirs(true)) {
+        try!(opts.config.shell().status("Removing", dir.display()));
+        try!(rm_rf(&dir));
+    }
+    Ok(())
+}
+
+fn context<'a, 'cfg>(ws: &'a Workspace<'cfg>,
+                     resolve: &'a Resolve,
+                     packages: &'a PackageSet<'cfg>,
+                     opts: &CleanOptions<'cfg>)
+                     -> CargoResult<Context<'a, 'cfg>> {
+    let host_triple = try!(opts.config.rustc()).host.clone();
+    Context::new(ws, resolve, packages, opts.config,
+                 BuildConfig {
+                     host_triple: host_triple,
+                     requested_targets: opts.target.map(|s| s.to_owned())
+                                            .into_iter().collect(),
+                     release: opts.release,
+                     ..BuildConfig::default()
+                 },
+                 ws.profiles())
+}
+
 fn rm_rf(path: &Path) -> CargoResult<()> {
     let m = fs::metadata(path);
     if m.as_ref().map(|s| s.is_dir()).unwrap_or(false) {
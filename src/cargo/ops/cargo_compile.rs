@@ -22,39 +22,54 @@
 //!       previously compiled dependency
 //!
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
+
+use glob::Pattern;
+use term::color::{GREEN, RED};
 
 use core::registry::PackageRegistry;
-use core::{Source, SourceId, PackageSet, Package, Target};
-use core::{Profile, TargetKind, Profiles, Workspace};
+use core::{Source, SourceId, PackageSet, Package, PackageIdSpec, Target};
+use core::{Profile, ProfileOverride, TargetKind, Profiles, Workspace};
+use core::LibKind;
 use core::resolver::{Method, Resolve};
+use core::shell::Verbosity;
 use ops::{self, BuildOutput, ExecEngine};
 use sources::PathSource;
-use util::config::Config;
-use util::{CargoResult, profile, human, ChainError};
+use util::config::{Config, ConfigValue};
+use util::{CargoResult, profile, human, paths, ChainError};
+use util::progress::Progress;
 
 /// Contains information about how a package should be compiled.
+#[derive(Clone)]
 pub struct CompileOptions<'a> {
     pub config: &'a Config,
     /// Number of concurrent jobs to use.
     pub jobs: Option<u32>,
-    /// The target platform to compile for (example: `i686-unknown-linux-gnu`).
-    pub target: Option<&'a str>,
+    /// The target platforms to compile for (example: `i686-unknown-linux-gnu`),
+    /// all of them in a single build. The host is compiled for if it's empty.
+    pub targets: Vec<&'a str>,
     /// Extra features to build for the root package
     pub features: &'a [String],
     /// Flag if the default feature should be built for the root package
     pub no_default_features: bool,
-    /// Root package to build (if None it's the current one)
-    pub spec: &'a [String],
+    /// Which packages to build
+    pub spec: Packages<'a>,
     /// Filter to apply to the root package to select which targets will be
     /// built.
     pub filter: CompileFilter<'a>,
+    /// Targets left out of the build, whichever way targets are selected
+    pub exclude: ExcludeTargets<'a>,
     /// Engine which drives compilation
     pub exec_engine: Option<Arc<Box<ExecEngine>>>,
     /// Whether this is a release build or not
     pub release: bool,
+    /// The profile selected with `--profile`, either a built-in or a custom
+    /// one.
+    pub profile: Option<&'a str>,
     /// Mode for this compile.
     pub mode: CompileMode,
     /// Extra arguments to be passed to rustdoc (for main crate and dependencies)
@@ -62,6 +77,91 @@ pub struct CompileOptions<'a> {
     /// The specified target will be compiled with all the available arguments,
     /// note that this only accounts for the *final* invocation of rustc
     pub target_rustc_args: Option<&'a [String]>,
+    /// Crate types to build the library selected for `target_rustc_args` as,
+    /// instead of those listed in the manifest
+    pub target_crate_types: Option<&'a [String]>,
+    /// Print the invocations of the build as JSON instead of running them
+    pub build_plan: bool,
+    /// Instrument the workspace members for code coverage
+    pub coverage: bool,
+    /// How compiler diagnostics and other messages are reported
+    pub message_format: MessageFormat,
+}
+
+/// The format of the messages printed during a build.
+#[derive(RustcDecodable, Clone, Copy, PartialEq, Debug)]
+pub enum MessageFormat {
+    /// Diagnostics rendered for humans on stderr
+    Human,
+    /// Diagnostics and notifications about the build as JSON on stdout
+    Json,
+}
+
+/// The packages selected on the command line.
+#[derive(Clone, Copy)]
+pub enum Packages<'a> {
+    /// All members of the workspace except those matching `exclude`
+    All { exclude: &'a [String] },
+    /// The packages given with `-p`, or the default members of the workspace
+    /// if the list is empty
+    Packages(&'a [String]),
+}
+
+impl<'a> Packages<'a> {
+    /// Creates a selection from the `--all`, `--exclude` and `--package`
+    /// command line flags.
+    pub fn from_flags(all: bool, exclude: &'a [String], package: &'a [String])
+                      -> CargoResult<Packages<'a>> {
+        if all {
+            if !package.is_empty() {
+                bail!("--package cannot be used together with --all")
+            }
+            Ok(Packages::All { exclude: exclude })
+        } else if !exclude.is_empty() {
+            bail!("--exclude can only be used together with --all")
+        } else {
+            Ok(Packages::Packages(package))
+        }
+    }
+
+    /// Converts this selection to a list of package id specs, expanding
+    /// `--all` to the members of `ws`.
+    pub fn into_package_id_specs(self, ws: &Workspace)
+                                 -> CargoResult<Vec<PackageIdSpec>> {
+        let specs = match self {
+            Packages::All { exclude } => {
+                let exclude = try!(parse_specs(exclude));
+                let specs = ws.members().filter(|p| {
+                    !exclude.iter().any(|s| s.matches(p.package_id()))
+                }).map(|p| {
+                    PackageIdSpec::from_package_id(p.package_id())
+                }).collect::<Vec<_>>();
+                if specs.is_empty() && !exclude.is_empty() {
+                    bail!("no packages left to compile after excluding all \
+                           members of the workspace")
+                }
+                specs
+            }
+            Packages::Packages(packages) if packages.is_empty() => {
+                ws.default_members().map(|p| {
+                    PackageIdSpec::from_package_id(p.package_id())
+                }).collect()
+            }
+            Packages::Packages(packages) => try!(parse_specs(packages)),
+        };
+        if specs.is_empty() {
+            bail!("the workspace at `{}` has no members", ws.root().display())
+        }
+        Ok(specs)
+    }
+}
+
+fn parse_specs(specs: &[String]) -> CargoResult<Vec<PackageIdSpec>> {
+    specs.iter().map(|spec| {
+        PackageIdSpec::parse(spec).chain_error(|| {
+            human(format!("invalid package id specification: `{}`", spec))
+        })
+    }).collect()
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -69,9 +169,16 @@ pub enum CompileMode {
     Test,
     Build,
     Bench,
-    Doc { deps: bool },
+    Check,
+    /// Documents the selected targets, along with the libraries they depend
+    /// on if `deps` is set, and the private items of workspace members if
+    /// `private_items` is
+    Doc { deps: bool, private_items: bool },
+    /// Only builds the libraries whose doctests are run, see `cargo test --doc`
+    Doctest,
 }
 
+#[derive(Clone, Copy)]
 pub enum CompileFilter<'a> {
     Everything,
     Only {
@@ -85,16 +192,54 @@ pub enum CompileFilter<'a> {
 
 pub fn compile<'a>(ws: &Workspace<'a>, options: &CompileOptions<'a>)
                    -> CargoResult<ops::Compilation<'a>> {
-    for key in try!(ws.current()).manifest().warnings().iter() {
-        try!(options.config.shell().warn(key))
+    if let Some(pkg) = ws.current_opt() {
+        for key in pkg.manifest().warnings().iter() {
+            try!(options.config.shell().warn(key))
+        }
     }
     compile_ws(ws, None, options)
 }
 
+/// Compiles like `compile` and, when Cargo is quiet, finishes with a line
+/// telling whether the build succeeded and how long it took. Along with the
+/// diagnostics that's all which is left of a quiet build in the logs of
+/// whatever invoked Cargo. `cargo run` doesn't use this, as the output of a
+/// quiet run is that of the program alone.
+pub fn compile_with_summary<'a>(ws: &Workspace<'a>,
+                                options: &CompileOptions<'a>)
+                                -> CargoResult<ops::Compilation<'a>> {
+    let start = Instant::now();
+    let result = compile(ws, options);
+    let config = options.config;
+    if config.shell().get_verbose() == Verbosity::Quiet &&
+       !options.build_plan {
+        let elapsed = start.elapsed();
+        let secs = elapsed.as_secs() as f64 +
+                   elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+        let kind = match options.mode {
+            CompileMode::Doc { .. } => "doc",
+            CompileMode::Check => "check",
+            _ => match options.profile {
+                Some(name) => name,
+                None if options.release => "release",
+                None => "debug",
+            },
+        };
+        let (status, color) = match result {
+            Ok(..) => ("Finished", GREEN),
+            Err(..) => ("Failed", RED),
+        };
+        let msg = format!("{} target(s) in {:.2} secs", kind, secs);
+        try!(config.shell().err().say_status(status, msg, color, true));
+    }
+    result
+}
+
 pub fn resolve_dependencies<'a>(ws: &Workspace<'a>,
                                 source: Option<Box<Source + 'a>>,
                                 features: Vec<String>,
-                                no_default_features: bool)
+                                no_default_features: bool,
+                                specs: &[PackageIdSpec])
                                 -> CargoResult<(PackageSet<'a>, Resolve)> {
 
     let mut registry = PackageRegistry::new(ws.config());
@@ -123,7 +268,8 @@ pub fn resolve_dependencies<'a>(ws: &Workspace<'a>,
 
     let resolved_with_overrides =
             try!(ops::resolve_with_previous(&mut registry, ws,
-                                            method, Some(&resolve), None));
+                                            method, Some(&resolve), None,
+                                            specs));
 
     let packages = ops::get_resolved_packages(&resolved_with_overrides,
                                               registry);
@@ -135,14 +281,38 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
                       source: Option<Box<Source + 'a>>,
                       options: &CompileOptions<'a>)
                       -> CargoResult<ops::Compilation<'a>> {
-    let root_package = try!(ws.current());
-    let CompileOptions { config, jobs, target, spec, features,
+    compile_with(ws, source, None, options)
+}
+
+/// Like `compile_ws`, for a workspace whose dependencies were already
+/// resolved by `resolve_dependencies`, for the packages selected by
+/// `options`. The features of `options` are ignored in favor of those of
+/// `resolve`.
+pub fn compile_resolved<'a>(ws: &Workspace<'a>,
+                            packages: &PackageSet<'a>,
+                            resolve: &Resolve,
+                            options: &CompileOptions<'a>)
+                            -> CargoResult<ops::Compilation<'a>> {
+    compile_with(ws, None, Some((packages, resolve)), options)
+}
+
+fn compile_with<'a>(ws: &Workspace<'a>,
+                    source: Option<Box<Source + 'a>>,
+                    resolved: Option<(&PackageSet<'a>, &Resolve)>,
+                    options: &CompileOptions<'a>)
+                    -> CargoResult<ops::Compilation<'a>> {
+    let CompileOptions { config, jobs, ref targets, spec, features,
                          no_default_features, release, mode,
-                         ref filter, ref exec_engine,
+                         profile: profile_name,
+                         ref filter, exclude, ref exec_engine,
                          ref target_rustdoc_args,
-                         ref target_rustc_args } = *options;
+                         ref target_rustc_args,
+                         target_crate_types,
+                         build_plan,
+                         coverage,
+                         message_format } = *options;
 
-    let target = target.map(|s| s.to_string());
+    let targets = targets.iter().map(|s| s.to_string()).collect::<Vec<_>>();
     let features = features.iter().flat_map(|s| {
         s.split(' ')
     }).map(|s| s.to_string()).collect::<Vec<String>>();
@@ -151,23 +321,47 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
         bail!("jobs must be at least 1")
     }
 
-    let profiles = root_package.manifest().profiles();
-    if spec.len() == 0 {
-        try!(generate_targets(root_package, profiles, mode, filter, release));
+    let custom_profiles;
+    let (profiles, release, custom) = match profile_name {
+        Some(..) if release => {
+            bail!("--release and --profile cannot be used together")
+        }
+        Some("dev") => (ws.profiles(), false, None),
+        Some("release") => (ws.profiles(), true, None),
+        Some(name) => {
+            custom_profiles = match ws.profiles().with_custom(name) {
+                Some(profiles) => profiles,
+                None => bail!("profile `{}` is not defined in the manifest",
+                              name),
+            };
+            let release = ws.profiles().custom[name].release;
+            (&custom_profiles, release, Some(name))
+        }
+        None => (ws.profiles(), release, None),
+    };
+    if let (Packages::Packages(packages), Some(root_package)) =
+           (spec, ws.current_opt()) {
+        if packages.is_empty() {
+            try!(generate_targets(root_package, profiles, mode, filter,
+                                  exclude, release));
+        }
     }
 
-    let (packages, resolve_with_overrides) = {
-        try!(resolve_dependencies(ws, source, features, no_default_features))
+    let specs = try!(spec.into_package_id_specs(ws));
+    let owned;
+    let (packages, resolve_with_overrides) = match resolved {
+        Some(resolved) => resolved,
+        None => {
+            owned = try!(resolve_dependencies(ws, source, features,
+                                              no_default_features, &specs));
+            (&owned.0, &owned.1)
+        }
     };
 
     let mut pkgids = Vec::new();
-    if spec.len() > 0 {
-        for p in spec {
-            pkgids.push(try!(resolve_with_overrides.query(&p)));
-        }
-    } else {
-        pkgids.push(root_package.package_id());
-    };
+    for p in specs.iter() {
+        pkgids.push(try!(p.query(resolve_with_overrides.iter())));
+    }
 
     let to_builds = try!(pkgids.iter().map(|id| {
         packages.get(id)
@@ -176,68 +370,120 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
     let mut general_targets = Vec::new();
     let mut package_targets = Vec::new();
 
-    match (*target_rustc_args, *target_rustdoc_args) {
+    // The crate types are overridden like the arguments are passed, so the
+    // former don't require any of the latter
+    let target_rustc_args = match (*target_rustc_args, target_crate_types) {
+        (None, Some(..)) => Some(&[][..]),
+        (args, _) => args,
+    };
+
+    match (target_rustc_args, *target_rustdoc_args) {
         (Some(..), _) |
         (_, Some(..)) if to_builds.len() != 1 => {
-            panic!("`rustc` and `rustdoc` should not accept multiple `-p` flags")
+            bail!("extra arguments can only be passed to a single package, \
+                   consider selecting one with `-p SPEC`")
         }
         (Some(args), _) => {
             let targets = try!(generate_targets(to_builds[0], profiles,
-                                                mode, filter, release));
-            if targets.len() == 1 {
-                let (target, profile) = targets[0];
-                let mut profile = profile.clone();
-                profile.rustc_args = Some(args.to_vec());
-                general_targets.push((target, profile));
-            } else {
+                                                mode, filter, exclude,
+                                                release));
+            if targets.len() != 1 && !filter.is_specific() {
                 bail!("extra arguments to `rustc` can only be passed to one \
                        target, consider filtering\nthe package by passing \
                        e.g. `--lib` or `--bin NAME` to specify a single target")
             }
+            // A target the others depend on would be built a second time
+            // without the arguments for them, both builds writing the same
+            // files
+            if targets.len() > 1 {
+                let tests = targets.iter().any(|&(t, _)| {
+                    t.is_test() || t.is_bench()
+                });
+                let depended_on = targets.iter().find(|&&(t, _)| {
+                    t.is_lib() || (t.is_bin() && tests)
+                });
+                if let Some(&(target, _)) = depended_on {
+                    bail!("extra arguments to `rustc` can't be passed to `{}` \
+                           along with other targets which depend on it, \
+                           consider passing them to it alone", target.name())
+                }
+            }
+            for &(target, profile) in targets.iter() {
+                let mut target = target.clone();
+                if let Some(crate_types) = target_crate_types {
+                    if !target.is_lib() {
+                        bail!("crate types can only be overridden for \
+                               libraries, but `{}` isn't one", target.name())
+                    }
+                    let kinds = crate_types.iter().map(|s| {
+                        LibKind::from_str(s)
+                    }).collect();
+                    target.set_kind(TargetKind::Lib(kinds));
+                }
+                let mut profile = profile.clone();
+                profile.rustc_args = Some(args.to_vec());
+                general_targets.push((target, profile));
+            }
         }
         (None, Some(args)) => {
             let targets = try!(generate_targets(to_builds[0], profiles,
-                                                mode, filter, release));
-            if targets.len() == 1 {
-                let (target, profile) = targets[0];
-                let mut profile = profile.clone();
-                profile.rustdoc_args = Some(args.to_vec());
-                general_targets.push((target, profile));
-            } else {
+                                                mode, filter, exclude,
+                                                release));
+            if targets.len() != 1 && !filter.is_specific() {
                 bail!("extra arguments to `rustdoc` can only be passed to one \
                        target, consider filtering\nthe package by passing e.g. \
                        `--lib` or `--bin NAME` to specify a single target")
             }
+            for &(target, profile) in targets.iter() {
+                let mut profile = profile.clone();
+                profile.rustdoc_args = Some(args.to_vec());
+                general_targets.push((target.clone(), profile));
+            }
         }
         (None, None) => {
             for &to_build in to_builds.iter() {
                 let targets = try!(generate_targets(to_build, profiles, mode,
-                                                    filter, release));
+                                                    filter, exclude,
+                                                    release));
                 package_targets.push((to_build, targets));
             }
         }
     };
 
-    for &(target, ref profile) in &general_targets {
-        for &to_build in to_builds.iter() {
-            package_targets.push((to_build, vec![(target, profile)]));
-        }
+    if !general_targets.is_empty() {
+        let targets = general_targets.iter().map(|&(ref target, ref profile)| {
+            (target, profile)
+        }).collect();
+        package_targets.push((to_builds[0], targets));
     }
 
     let mut ret = {
         let _p = profile::start("compiling");
-        let mut build_config = try!(scrape_build_config(config, jobs, target));
-        build_config.exec_engine = exec_engine.clone();
-        build_config.release = release;
-        build_config.test = mode == CompileMode::Test;
-        if let CompileMode::Doc { deps } = mode {
+        let mut build_config = try!(scrape_build_config(config, jobs, targets,
+                                                        release, custom));
+        if exec_engine.is_some() {
+            build_config.exec_engine = exec_engine.clone();
+        }
+        build_config.run_env = try!(scrape_run_env(config, &build_config,
+                                                   ws.root()));
+        build_config.test = mode == CompileMode::Test ||
+                            mode == CompileMode::Doctest;
+        build_config.json_messages = message_format == MessageFormat::Json;
+        build_config.build_plan = build_plan;
+        build_config.progress = !build_config.json_messages && !build_plan &&
+                                try!(Progress::wanted(config));
+        build_config.coverage = coverage;
+        // Each library is compiled by a single invocation in build plans
+        build_config.pipelining = build_config.pipelining && !build_plan;
+        if let CompileMode::Doc { deps, private_items } = mode {
             build_config.doc_all = deps;
+            build_config.doc_private_items = private_items;
         }
 
         try!(ops::compile_targets(ws,
                                   &package_targets,
-                                  &packages,
-                                  &resolve_with_overrides,
+                                  packages,
+                                  resolve_with_overrides,
                                   config,
                                   build_config,
                                   profiles))
@@ -265,6 +511,15 @@ impl<'a> CompileFilter<'a> {
         }
     }
 
+    /// Whether targets were selected on the command line, rather than
+    /// building everything.
+    pub fn is_specific(&self) -> bool {
+        match *self {
+            CompileFilter::Everything => false,
+            CompileFilter::Only { .. } => true,
+        }
+    }
+
     pub fn matches(&self, target: &Target) -> bool {
         match *self {
             CompileFilter::Everything => true,
@@ -277,26 +532,92 @@ impl<'a> CompileFilter<'a> {
                     TargetKind::Lib(..) => return lib,
                     TargetKind::CustomBuild => return false,
                 };
-                list.iter().any(|x| *x == target.name())
+                list.iter().any(|x| name_matches(x, target.name()))
             }
         }
     }
 }
 
+/// Names or glob patterns of the targets of each kind to leave out of a build.
+#[derive(Clone, Copy, Default)]
+pub struct ExcludeTargets<'a> {
+    pub bins: &'a [String],
+    pub examples: &'a [String],
+    pub tests: &'a [String],
+    pub benches: &'a [String],
+}
+
+impl<'a> ExcludeTargets<'a> {
+    pub fn matches(&self, target: &Target) -> bool {
+        let list = match *target.kind() {
+            TargetKind::Bin => self.bins,
+            TargetKind::Example => self.examples,
+            TargetKind::Test => self.tests,
+            TargetKind::Bench => self.benches,
+            TargetKind::Lib(..) | TargetKind::CustomBuild => return false,
+        };
+        list.iter().any(|x| name_matches(x, target.name()))
+    }
+}
+
+pub fn is_glob(name: &str) -> bool {
+    name.chars().any(|c| c == '*' || c == '?' || c == '[')
+}
+
+fn parse_pattern(pattern: &str) -> CargoResult<Pattern> {
+    Pattern::new(pattern).map_err(|e| {
+        human(format!("could not parse pattern `{}`: {}", pattern, e))
+    })
+}
+
+/// Whether a target named `name` is selected by `pattern`, either its exact
+/// name or a glob pattern such as `net_*`.
+fn name_matches(pattern: &str, name: &str) -> bool {
+    if !is_glob(pattern) {
+        return pattern == name
+    }
+    Pattern::new(pattern).map(|p| p.matches(name)).unwrap_or(false)
+}
+
 /// Given the configuration for a build, this function will generate all
-/// target/profile combinations needed to be built.
+/// target/profile combinations needed to be built, but for the targets
+/// matching `exclude`.
 fn generate_targets<'a>(pkg: &'a Package,
                         profiles: &'a Profiles,
                         mode: CompileMode,
                         filter: &CompileFilter,
+                        exclude: ExcludeTargets,
                         release: bool)
                         -> CargoResult<Vec<(&'a Target, &'a Profile)>> {
-    let build = if release {&profiles.release} else {&profiles.dev};
+    {
+        let all = exclude.bins.iter().chain(exclude.examples)
+                         .chain(exclude.tests).chain(exclude.benches);
+        for pattern in all.filter(|p| is_glob(p)) {
+            try!(parse_pattern(pattern));
+        }
+    }
+    let mut targets = try!(select_targets(pkg, profiles, mode, filter,
+                                          release));
+    targets.retain(|&(t, _)| !exclude.matches(t));
+    Ok(targets)
+}
+
+fn select_targets<'a>(pkg: &'a Package,
+                      profiles: &'a Profiles,
+                      mode: CompileMode,
+                      filter: &CompileFilter,
+                      release: bool)
+                      -> CargoResult<Vec<(&'a Target, &'a Profile)>> {
+    let build = match mode {
+        CompileMode::Check => &profiles.check,
+        _ if release => &profiles.release,
+        _ => &profiles.dev,
+    };
     let test = if release {&profiles.bench} else {&profiles.test};
     let profile = match mode {
         CompileMode::Test => test,
         CompileMode::Bench => &profiles.bench,
-        CompileMode::Build => build,
+        CompileMode::Build | CompileMode::Check | CompileMode::Doctest => build,
         CompileMode::Doc { .. } => &profiles.doc,
     };
     match *filter {
@@ -323,7 +644,7 @@ fn generate_targets<'a>(pkg: &'a Package,
                     }
                     Ok(base)
                 }
-                CompileMode::Build => {
+                CompileMode::Build | CompileMode::Check => {
                     Ok(pkg.targets().iter().filter(|t| {
                         t.is_bin() || t.is_lib()
                     }).map(|t| (t, profile)).collect())
@@ -332,6 +653,10 @@ fn generate_targets<'a>(pkg: &'a Package,
                     Ok(pkg.targets().iter().filter(|t| t.documented())
                           .map(|t| (t, profile)).collect())
                 }
+                CompileMode::Doctest => {
+                    Ok(pkg.targets().iter().filter(|t| t.doctested())
+                          .map(|t| (t, profile)).collect())
+                }
             }
         }
         CompileFilter::Only { lib, bins, examples, tests, benches } => {
@@ -348,6 +673,21 @@ fn generate_targets<'a>(pkg: &'a Package,
             {
                 let mut find = |names: &[String], desc, kind, profile| {
                     for name in names {
+                        if is_glob(name) {
+                            let pattern = try!(parse_pattern(name));
+                            let matched = pkg.targets().iter().filter(|t| {
+                                *t.kind() == kind && pattern.matches(t.name())
+                            }).collect::<Vec<_>>();
+                            if matched.is_empty() {
+                                bail!("no {} target matches `{}`", desc, name)
+                            }
+                            for t in matched {
+                                if !targets.iter().any(|&(u, _)| u == t) {
+                                    targets.push((t, profile));
+                                }
+                            }
+                            continue
+                        }
                         let target = pkg.targets().iter().find(|t| {
                             t.name() == *name && *t.kind() == kind
                         });
@@ -388,7 +728,7 @@ fn add_overrides<'a>(registry: &mut PackageRegistry<'a>,
         Some(list) => list,
         None => return Ok(())
     };
-    let current = try!(ws.current());
+    let current = ws.current_opt().map(|p| p.root());
     let paths = paths.val.iter().map(|&(ref s, ref p)| {
         // The path listed next to the string is the config file in which the
         // key was located, so we want to pop off the `.cargo/config` component
@@ -397,7 +737,7 @@ fn add_overrides<'a>(registry: &mut PackageRegistry<'a>,
     }).filter(|&(ref p, _)| {
         // Make sure we don't override the local package, even if it's in the
         // list of override paths.
-        current.root() != &**p
+        current != Some(&**p)
     });
 
     for (path, definition) in paths {
@@ -420,10 +760,13 @@ fn add_overrides<'a>(registry: &mut PackageRegistry<'a>,
 /// * build.target
 /// * target.$target.ar
 /// * target.$target.linker
+/// * target.$target.post-process
 /// * target.$target.libfoo.metadata
 fn scrape_build_config(config: &Config,
                        jobs: Option<u32>,
-                       target: Option<String>)
+                       targets: Vec<String>,
+                       release: bool,
+                       custom_profile: Option<&str>)
                        -> CargoResult<ops::BuildConfig> {
     let cfg_jobs = match try!(config.get_i64("build.jobs")) {
         Some(v) => {
@@ -440,22 +783,278 @@ fn scrape_build_config(config: &Config,
         None => None,
     };
     let jobs = jobs.or(cfg_jobs).unwrap_or(::num_cpus::get() as u32);
-    let cfg_target = try!(config.get_string("build.target")).map(|s| s.val);
-    let target = target.or(cfg_target);
+    let targets = if targets.is_empty() {
+        let cfg_target = try!(config.get_string("build.target"));
+        cfg_target.map(|s| s.val).into_iter().collect()
+    } else {
+        targets
+    };
     let mut base = ops::BuildConfig {
         host_triple: try!(config.rustc()).host.clone(),
-        requested_target: target.clone(),
+        requested_targets: targets.clone(),
         jobs: jobs,
+        release: release,
+        custom_profile: custom_profile.map(|s| s.to_string()),
         ..Default::default()
     };
-    base.host = try!(scrape_target_config(config, &base.host_triple));
-    base.target = match target.as_ref() {
-        Some(triple) => try!(scrape_target_config(config, &triple)),
-        None => base.host.clone(),
+    if let Some(executor) = try!(config.get_path("build.remote-executor")) {
+        let engine = ops::RemoteEngine::new(executor.val);
+        base.exec_engine = Some(Arc::new(Box::new(engine) as Box<ExecEngine>));
+    }
+    base.artifact_cache = try!(config.get_string("build.artifact-cache"))
+                              .map(|s| s.val);
+    base.cache_dir = try!(config.get_path("build.cache-dir")).map(|s| s.val);
+    base.build_script_wrapper = try!(config.get_path(
+        "build.build-script-wrapper")).map(|s| s.val);
+    base.sandbox = try!(config.get_bool("build.sandbox"))
+                       .map(|s| s.val).unwrap_or(false);
+    base.build_scripts = try!(scrape_execution_policy(config,
+                                                      "build-scripts"));
+    base.proc_macros = try!(scrape_execution_policy(config, "proc-macros"));
+    let proc_macro_cache = try!(config.get_bool("build.proc-macro-cache"));
+    base.proc_macro_cache = match proc_macro_cache {
+        Some(ref v) if v.val => {
+            Some(config.home().join("proc-macros").into_path_unlocked())
+        }
+        _ => None,
+    };
+    // An empty `RUSTC_WRAPPER` turns off a wrapper set in the configuration
+    base.rustc_wrapper = match env::var_os("RUSTC_WRAPPER") {
+        Some(ref wrapper) if wrapper.is_empty() => None,
+        Some(wrapper) => Some(PathBuf::from(wrapper)),
+        None => try!(config.get_path("build.rustc-wrapper")).map(|s| s.val),
+    };
+    base.pipelining = try!(config.get_bool("build.pipelining"))
+                          .map(|s| s.val).unwrap_or(false);
+    if let Some(v) = try!(config.get_string("build.warnings")) {
+        base.warnings = match &v.val[..] {
+            "allow" => ops::Warnings::Allow,
+            "warn" => ops::Warnings::Warn,
+            "deny" => ops::Warnings::Deny,
+            other => {
+                bail!("build.warnings must be `allow`, `warn` or `deny`, but \
+                       found `{}` in {}", other, v.definition)
+            }
+        };
+    }
+    base.out_dir_quota = match try!(config.get_i64("build.out-dir-quota")) {
+        Some(v) => {
+            if v.val <= 0 {
+                bail!("build.out-dir-quota must be positive, but found {} in \
+                       {}", v.val, v.definition)
+            }
+            Some(v.val as u64 * 1024 * 1024)
+        }
+        None => None,
     };
+    try!(scrape_profile_config(config, &mut base));
+    base.host = try!(scrape_target_config(config, &base.host_triple));
+    for triple in targets.iter() {
+        base.targets.push(try!(scrape_target_config(config, triple)));
+    }
     Ok(base)
 }
 
+/// Reads which packages outside of the workspace may run code at build time
+/// through their build scripts or procedural macros, from the `section` of
+/// the configuration:
+///
+/// ```toml
+/// [build-scripts]
+/// allow = ["openssl-sys", "libgit2-sys:0.6.0"]
+/// deny-all = false
+/// ```
+fn scrape_execution_policy(config: &Config, section: &str)
+                           -> CargoResult<ops::ExecutionPolicy> {
+    let allow = try!(config.get_list(&format!("{}.allow", section)));
+    let deny_all = try!(config.get_bool(&format!("{}.deny-all", section)));
+    match (allow, deny_all) {
+        (Some(allow), Some(ref deny_all)) if deny_all.val => {
+            bail!("`{0}.deny-all` is set in {1}, so it can't be combined \
+                   with `{0}.allow`, set in {2}", section,
+                  deny_all.definition, allow.definition)
+        }
+        (_, Some(ref deny_all)) if deny_all.val => {
+            Ok(ops::ExecutionPolicy::Allow(Vec::new()))
+        }
+        (Some(allow), _) => {
+            let specs = try!(allow.val.iter().map(|&(ref spec, ref path)| {
+                PackageIdSpec::parse(spec).chain_error(|| {
+                    human(format!("invalid package id specification `{}` in \
+                                   `{}.allow` of {}", spec, section,
+                                  path.display()))
+                })
+            }).collect::<CargoResult<Vec<_>>>());
+            Ok(ops::ExecutionPolicy::Allow(specs))
+        }
+        (None, _) => Ok(ops::ExecutionPolicy::AllowAll),
+    }
+}
+
+/// Reads the environment variables the binaries of the build are run with by
+/// `cargo run` and `cargo test`. Those of the file set with `build.env-file`,
+/// or else of the `.cargo/env` file at the root of the workspace, are
+/// overridden by the `[env]` table of the configuration, itself overridden by
+/// the `env` table of the profile being built:
+///
+/// ```toml
+/// [env]
+/// RUST_LOG = "info"
+///
+/// [profile.release.env]
+/// RUST_LOG = "warn"
+/// ```
+fn scrape_run_env(config: &Config, base: &ops::BuildConfig, root: &Path)
+                  -> CargoResult<Vec<(String, String)>> {
+    let mut vars = BTreeMap::new();
+    match try!(config.get_path("build.env-file")) {
+        Some(file) => vars.extend(try!(read_env_file(&file.val))),
+        None => {
+            // The `env` file in the Cargo home, which is `~/.cargo` of a
+            // workspace in the home directory, is a shell script of rustup.
+            let dir = root.join(".cargo");
+            let home = config.home().clone().into_path_unlocked();
+            let file = dir.join("env");
+            if dir != home && Some(root.to_path_buf()) != env::home_dir() &&
+               file.is_file() {
+                vars.extend(try!(read_env_file(&file)));
+            }
+        }
+    }
+
+    let profile = match base.custom_profile {
+        Some(ref name) => &name[..],
+        None if base.release => "release",
+        None => "dev",
+    };
+    for key in ["env".to_string(), format!("profile.{}.env", profile)].iter() {
+        let table = match try!(config.get_table(key)) {
+            Some(table) => table,
+            None => continue,
+        };
+        for (name, value) in table.val {
+            match value {
+                ConfigValue::String(value, _) => {
+                    vars.insert(name, value);
+                }
+                other => {
+                    bail!("`{}.{}` must be a string, but found a {} in {}",
+                          key, name, other.desc(), table.definition)
+                }
+            }
+        }
+    }
+    Ok(vars.into_iter().collect())
+}
+
+/// Parses a `.cargo/env` file, made of `KEY=VALUE` lines. Blank lines and
+/// those starting with `#` are skipped, and values may be quoted.
+fn read_env_file(path: &Path) -> CargoResult<Vec<(String, String)>> {
+    let contents = try!(paths::read(path));
+    let mut vars = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let mut parts = line.splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if !key.trim().is_empty() => {
+                (key.trim(), value.trim())
+            }
+            _ => bail!("expected `KEY=VALUE` on line {} of `{}`", i + 1,
+                       path.display()),
+        };
+        let value = if value.len() >= 2 &&
+                       (value.starts_with('"') && value.ends_with('"') ||
+                        value.starts_with('\'') && value.ends_with('\'')) {
+            &value[1..value.len() - 1]
+        } else {
+            value
+        };
+        vars.push((key.to_string(), value.to_string()));
+    }
+    Ok(vars)
+}
+
+/// Reads the extra compiler flags for the profile being built, which apply
+/// either to all units or only to the packages matching a spec, along with
+/// the settings of the profile overridden for those packages:
+///
+/// ```toml
+/// [profile.release]
+/// rustflags = ["-C", "target-cpu=native"]
+///
+/// [profile.dev.package."foo:0.1.0"]
+/// rustflags = ["--cfg", "trace"]
+/// opt-level = 3
+/// ```
+fn scrape_profile_config(config: &Config, base: &mut ops::BuildConfig)
+                         -> CargoResult<()> {
+    let profile = match base.custom_profile {
+        Some(ref name) => &name[..],
+        None if base.release => "release",
+        None => "dev",
+    };
+    let key = format!("profile.{}.rustflags", profile);
+    if let Some(flags) = try!(config.get_list(&key)) {
+        base.profile_rustflags = flags.val.into_iter().map(|f| f.0).collect();
+    }
+
+    let key = format!("profile.{}.package", profile);
+    let table = match try!(config.get_table(&key)) {
+        Some(table) => table.val,
+        None => return Ok(()),
+    };
+    for (spec, value) in table {
+        let id_spec = try!(PackageIdSpec::parse(&spec).chain_error(|| {
+            human(format!("invalid package id specification `{}` in `{}`",
+                          spec, key))
+        }));
+        let (flags, o) = try!(value.table().and_then(|(table, _)| {
+            let flags = match table.get("rustflags") {
+                Some(flags) => try!(flags.list()).to_vec(),
+                None => Vec::new(),
+            };
+            let get_u32 = |name: &str| -> CargoResult<Option<u32>> {
+                let (n, definition) = match table.get(name) {
+                    Some(v) => try!(v.i64()),
+                    None => return Ok(None),
+                };
+                if n < 0 || n > u32::max_value() as i64 {
+                    bail!("`{}` must be a non-negative 32-bit integer, but \
+                           is `{}` (in {})", name, n, definition.display())
+                }
+                Ok(Some(n as u32))
+            };
+            let get_bool = |name: &str| -> CargoResult<Option<bool>> {
+                match table.get(name) {
+                    Some(v) => Ok(Some(try!(v.boolean()).0)),
+                    None => Ok(None),
+                }
+            };
+            Ok((flags, ProfileOverride {
+                spec: id_spec.clone(),
+                opt_level: try!(get_u32("opt-level")),
+                codegen_units: try!(get_u32("codegen-units")),
+                debuginfo: try!(get_bool("debug")),
+                debug_assertions: try!(get_bool("debug-assertions")),
+                overflow_checks: try!(get_bool("overflow-checks")),
+            }))
+        }).chain_error(|| {
+            human(format!("invalid configuration for key `{}.{}`", key, spec))
+        }));
+        if o.opt_level.is_some() || o.codegen_units.is_some() ||
+           o.debuginfo.is_some() || o.debug_assertions.is_some() ||
+           o.overflow_checks.is_some() {
+            base.profile_overrides.push(o);
+        }
+        base.package_rustflags.push((id_spec,
+                                     flags.into_iter().map(|f| f.0).collect()));
+    }
+    Ok(())
+}
+
 fn scrape_target_config(config: &Config, triple: &str)
                         -> CargoResult<ops::TargetConfig> {
 
@@ -463,6 +1062,10 @@ fn scrape_target_config(config: &Config, triple: &str)
     let mut ret = ops::TargetConfig {
         ar: try!(config.get_path(&format!("{}.ar", key))).map(|v| v.val),
         linker: try!(config.get_path(&format!("{}.linker", key))).map(|v| v.val),
+        post_process: try!(scrape_post_process(config, &key)),
+        rustflags: try!(config.get_list(&format!("{}.rustflags", key)))
+                       .map(|v| v.val.into_iter().map(|a| a.0).collect()),
+        runner: try!(scrape_runner(config, &key)),
         overrides: HashMap::new(),
     };
     let table = match try!(config.get_table(&key)) {
@@ -470,7 +1073,9 @@ fn scrape_target_config(config: &Config, triple: &str)
         None => return Ok(ret),
     };
     for (lib_name, value) in table {
-        if lib_name == "ar" || lib_name == "linker" || lib_name == "rustflags" {
+        if lib_name == "ar" || lib_name == "linker" ||
+           lib_name == "rustflags" || lib_name == "post-process" ||
+           lib_name == "runner" {
             continue
         }
 
@@ -479,8 +1084,11 @@ fn scrape_target_config(config: &Config, triple: &str)
             library_links: Vec::new(),
             cfgs: Vec::new(),
             metadata: Vec::new(),
+            native_versions: Vec::new(),
             rerun_if_changed: Vec::new(),
+            rerun_if_env_changed: Vec::new(),
             warnings: Vec::new(),
+            errors: Vec::new(),
         };
         for (k, value) in try!(value.table()).0 {
             let key = format!("{}.{}", key, k);
@@ -521,3 +1129,40 @@ fn scrape_target_config(config: &Config, triple: &str)
 
     Ok(ret)
 }
+
+/// Reads `target.$triple.runner`, a command split on whitespace.
+fn scrape_runner(config: &Config, key: &str)
+                 -> CargoResult<Option<Vec<String>>> {
+    let key = format!("{}.runner", key);
+    let runner = match try!(config.get_string(&key)) {
+        Some(runner) => runner,
+        None => return Ok(None),
+    };
+    let command = runner.val.split_whitespace().map(|s| s.to_string())
+                            .collect::<Vec<_>>();
+    if command.is_empty() {
+        bail!("empty command in `{}` (in {})", key, runner.definition)
+    }
+    Ok(Some(command))
+}
+
+/// Reads the commands of `target.$triple.post-process`, which are split on
+/// whitespace like the aliases given as a single string.
+fn scrape_post_process(config: &Config, key: &str)
+                       -> CargoResult<Vec<Vec<String>>> {
+    let key = format!("{}.post-process", key);
+    let list = match try!(config.get_list(&key)) {
+        Some(list) => list.val,
+        None => return Ok(Vec::new()),
+    };
+    let mut ret = Vec::new();
+    for (command, definition) in list {
+        let command = command.split_whitespace().map(|s| s.to_string())
+                             .collect::<Vec<_>>();
+        if command.is_empty() {
+            bail!("empty command in `{}` (in {})", key, definition.display())
+        }
+        ret.push(command);
+    }
+    Ok(ret)
+}
@@ -0,0 +1,211 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use core::{Package, PackageId, Resolve, Workspace};
+use ops::{self, BuildTime};
+use util::{CargoResult, paths};
+
+pub struct CostOptions {
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    /// Whether to use the build times of the `release` profile
+    pub release: bool,
+    pub sort: CostSort,
+}
+
+/// The measure dependencies are sorted by, largest first.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CostSort {
+    Time,
+    Size,
+    Download,
+    Memory,
+}
+
+impl CostSort {
+    pub fn parse(s: &str) -> CargoResult<CostSort> {
+        match s {
+            "time" => Ok(CostSort::Time),
+            "size" => Ok(CostSort::Size),
+            "download" => Ok(CostSort::Download),
+            "memory" => Ok(CostSort::Memory),
+            _ => bail!("unknown sort key `{}`, expected `time`, `size`, \
+                        `download` or `memory`", s),
+        }
+    }
+}
+
+/// What a single package costs, on its own and along with the packages which
+/// are only in the dependency graph because of it.
+struct Cost {
+    id: PackageId,
+    download: Option<u64>,
+    unpacked: Option<u64>,
+    time: Option<BuildTime>,
+    exclusive: usize,
+    total_download: u64,
+    total_unpacked: u64,
+    total_time: f64,
+}
+
+/// Reports the download size, unpacked size and last compile time, peak memory
+/// and CPU time of every dependency of the workspace, sorted by their
+/// transitive contribution.
+///
+/// The transitive contribution of a dependency covers the dependency itself
+/// and every package which would leave the dependency graph if it were
+/// removed, that is the packages only reachable through it. Compile times are
+/// those recorded by the last builds in the target directory, so packages
+/// which haven't been built yet don't contribute any time. Sorting by memory
+/// uses the peak memory of each package alone, as the peaks of several
+/// packages don't add up.
+pub fn cost_report(ws: &Workspace, opts: &CostOptions) -> CargoResult<String> {
+    let features = opts.features.iter().flat_map(|s| {
+        s.split(' ')
+    }).map(|s| s.to_string()).collect::<Vec<String>>();
+    let (packages, resolve) = try!(ops::resolve_dependencies(
+        ws, None, features, opts.no_default_features, &[]));
+
+    let dest = if opts.release {"release"} else {"debug"};
+    let times = ops::read_build_times(&ws.target_dir().into_path_unlocked()
+                                         .join(dest));
+
+    let members = ws.members().map(|p| p.package_id().clone())
+                              .collect::<Vec<_>>();
+    let mut own = HashMap::new();
+    for id in resolve.iter() {
+        if members.contains(id) {
+            continue
+        }
+        let pkg = try!(packages.get(id));
+        own.insert(id.clone(), (download_size(ws, pkg),
+                                paths::dir_size(pkg.root()).ok(),
+                                times.get(&id.to_string()).cloned()));
+    }
+
+    let mut costs = own.iter().map(|(id, &(download, unpacked, time))| {
+        let reachable = reachable(&resolve, &members, id);
+        let exclusive = own.keys().filter(|other| {
+            !reachable.contains(other)
+        }).collect::<Vec<_>>();
+        let mut cost = Cost {
+            id: id.clone(),
+            download: download,
+            unpacked: unpacked,
+            time: time,
+            exclusive: exclusive.len(),
+            total_download: 0,
+            total_unpacked: 0,
+            total_time: 0.0,
+        };
+        for other in exclusive {
+            let (download, unpacked, time) = own[*other];
+            cost.total_download += download.unwrap_or(0);
+            cost.total_unpacked += unpacked.unwrap_or(0);
+            cost.total_time += time.map_or(0.0, |t| t.time);
+        }
+        cost
+    }).collect::<Vec<_>>();
+
+    costs.sort_by(|a, b| {
+        let order = match opts.sort {
+            CostSort::Time => {
+                b.total_time.partial_cmp(&a.total_time).unwrap()
+            }
+            CostSort::Size => b.total_unpacked.cmp(&a.total_unpacked),
+            CostSort::Download => b.total_download.cmp(&a.total_download),
+            CostSort::Memory => max_rss(b).cmp(&max_rss(a)),
+        };
+        match order {
+            Ordering::Equal => a.id.cmp(&b.id),
+            order => order,
+        }
+    });
+
+    let mut out = format!("{:^44} {:^35}\n", "Package alone",
+                          "With its exclusive dependencies");
+    out.push_str(&row(&["Download", "Unpacked", "Build", "Memory", "CPU",
+                        "Crates", "Download", "Unpacked", "Build"],
+                      "Package"));
+    for cost in costs {
+        let time = cost.time;
+        out.push_str(&row(&[cost.download.map_or("-".to_string(), size),
+                            cost.unpacked.map_or("-".to_string(), size),
+                            time.map_or("-".to_string(), |t| secs(t.time)),
+                            max_rss(&cost).map_or("-".to_string(), size),
+                            time.and_then(|t| t.cpu_time)
+                                .map_or("-".to_string(), secs),
+                            cost.exclusive.to_string(),
+                            size(cost.total_download),
+                            size(cost.total_unpacked),
+                            secs(cost.total_time)],
+                           &cost.id.to_string()));
+    }
+    Ok(out)
+}
+
+fn max_rss(cost: &Cost) -> Option<u64> {
+    cost.time.and_then(|t| t.max_rss)
+}
+
+/// Returns the packages reachable from `roots` without going through
+/// `removed`.
+fn reachable<'a>(resolve: &'a Resolve,
+                 roots: &'a [PackageId],
+                 removed: &PackageId) -> HashSet<&'a PackageId> {
+    let mut visited = HashSet::new();
+    let mut stack = roots.iter().collect::<Vec<_>>();
+    while let Some(id) = stack.pop() {
+        if id == removed || !visited.insert(id) {
+            continue
+        }
+        stack.extend(resolve.deps(id));
+    }
+    visited
+}
+
+/// Returns the size of the `.crate` file a package was downloaded as, which
+/// only registry packages have.
+fn download_size(ws: &Workspace, pkg: &Package) -> Option<u64> {
+    if !pkg.package_id().source_id().is_registry() {
+        return None
+    }
+    // Registry packages are unpacked into a directory named like the one
+    // their `.crate` file is cached in
+    let registry = match pkg.root().parent().and_then(|p| p.file_name()) {
+        Some(name) => name,
+        None => return None,
+    };
+    let file = ws.config().registry_cache_path().into_path_unlocked()
+                 .join(registry)
+                 .join(format!("{}-{}.crate", pkg.name(), pkg.version()));
+    fs::metadata(&file).ok().map(|m| m.len())
+}
+
+/// Formats a line of the report, the first five columns being about the
+/// package itself and the next four about its transitive contribution.
+fn row<S: AsRef<str>>(columns: &[S], package: &str) -> String {
+    let columns = columns.iter().map(|c| format!("{:>8}", c.as_ref()))
+                                .collect::<Vec<_>>();
+    format!("{}  {}\n", columns.join(" "), package)
+}
+
+fn size(bytes: u64) -> String {
+    const UNITS: [&'static str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+fn secs(secs: f64) -> String {
+    format!("{:.1}s", secs)
+}
@@ -0,0 +1,306 @@
+//! An experimental long running Cargo process.
+//!
+//! `cargo daemon` listens on a local socket and serves build and metadata
+//! requests for any workspace on this machine. Each request is a single line
+//! of JSON such as
+//!
+//! ```json
+//! {"command": "build", "manifest_path": "/path/to/Cargo.toml"}
+//! ```
+//!
+//! answered by a single line of JSON of the form `{"success": true}`, with a
+//! `metadata` key holding the output of `cargo metadata` for `metadata`
+//! requests and an `error` key when the request failed. The `check` command
+//! checks the workspace like `cargo check`, and the `shutdown` command stops
+//! the daemon.
+//!
+//! The configuration (including the probed `rustc` version) is loaded once
+//! and shared by all requests, so repeated invocations from an editor or IDE
+//! don't pay for it every time. So are the workspaces requests were made for,
+//! along with the resolve of their dependencies and the sources it was made
+//! from, which keep the registry index loaded. They're loaded again once one
+//! of their manifests, their lock file or a `.cargo/config` above them is
+//! modified. Build output is printed by the daemon itself, as are the errors
+//! of connections which failed, the daemon carrying on with the next one.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use filetime::FileTime;
+use rustc_serialize::json;
+
+use core::{PackageSet, Resolve, Workspace};
+use ops;
+use util::{CargoResult, Config, human};
+
+pub struct DaemonOptions<'a> {
+    pub config: &'a Config,
+    /// Path of the socket to listen on
+    pub socket: PathBuf,
+}
+
+#[derive(RustcDecodable)]
+struct Request {
+    command: String,
+    manifest_path: Option<String>,
+    features: Option<Vec<String>>,
+    no_default_features: Option<bool>,
+    release: Option<bool>,
+}
+
+/// A workspace loaded for an earlier request, keyed by the path of its
+/// manifest.
+struct Loaded<'cfg> {
+    ws: Workspace<'cfg>,
+    features: Vec<String>,
+    no_default_features: bool,
+    packages: PackageSet<'cfg>,
+    resolve: Resolve,
+    /// The manifests, the lock file and the configuration it was loaded from,
+    /// with their modification times at the time
+    inputs: Vec<(PathBuf, Option<FileTime>)>,
+}
+
+type Cache<'cfg> = HashMap<PathBuf, Loaded<'cfg>>;
+
+/// Serves requests on `opts.socket` until a `shutdown` request is received.
+pub fn daemon(opts: &DaemonOptions) -> CargoResult<()> {
+    imp::serve(opts)
+}
+
+/// Handles a single request, returning the response and whether the daemon
+/// should shut down afterwards.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn handle<'cfg>(config: &'cfg Config, cache: &mut Cache<'cfg>, line: &str)
+                -> (String, bool) {
+    let request: Request = match json::decode(line.trim()) {
+        Ok(request) => request,
+        Err(e) => return (error_response(&format!("invalid request: {}", e)),
+                          false),
+    };
+    let result = match &request.command[..] {
+        "build" => {
+            compile(config, cache, &request, ops::CompileMode::Build)
+                .map(|()| None)
+        }
+        "check" => {
+            compile(config, cache, &request, ops::CompileMode::Check)
+                .map(|()| None)
+        }
+        "metadata" => metadata(config, cache, &request).map(Some),
+        "shutdown" => return ("{\"success\":true}".to_string(), true),
+        other => Err(human(format!("unknown command `{}`", other))),
+    };
+    let response = match result {
+        Ok(None) => "{\"success\":true}".to_string(),
+        Ok(Some(metadata)) => {
+            format!("{{\"success\":true,\"metadata\":{}}}", metadata)
+        }
+        Err(e) => {
+            let _ = config.shell().error(&e);
+            error_response(&e.to_string())
+        }
+    };
+    (response, false)
+}
+
+fn error_response(msg: &str) -> String {
+    format!("{{\"success\":false,\"error\":{}}}", json::as_json(&msg))
+}
+
+/// Returns the workspace of `request`, loading it and resolving its
+/// dependencies unless that was already done for an earlier request with the
+/// same features and none of its inputs changed since.
+fn load<'a, 'cfg>(config: &'cfg Config,
+                  cache: &'a mut Cache<'cfg>,
+                  request: &Request) -> CargoResult<&'a Loaded<'cfg>> {
+    let manifest_path = match request.manifest_path {
+        Some(ref path) => config.cwd().join(path),
+        None => bail!("the `{}` command requires a `manifest_path`",
+                      request.command),
+    };
+    let features = request.features.clone().unwrap_or(Vec::new());
+    let no_default_features = request.no_default_features.unwrap_or(false);
+
+    let fresh = match cache.get(&manifest_path) {
+        Some(loaded) => {
+            loaded.features == features &&
+                loaded.no_default_features == no_default_features &&
+                loaded.inputs.iter().all(|&(ref path, mtime)| {
+                    modified(path) == mtime
+                })
+        }
+        None => false,
+    };
+    if !fresh {
+        cache.remove(&manifest_path);
+        let ws = try!(Workspace::new(&manifest_path, config));
+        let specs = try!(ops::Packages::Packages(&[])
+                             .into_package_id_specs(&ws));
+        let (packages, resolve) = try!(ops::resolve_dependencies(
+            &ws, None, features.clone(), no_default_features, &specs));
+
+        // Resolving may have written the lock file, so this comes last
+        let mut paths = Vec::new();
+        for id in resolve.iter().filter(|id| id.source_id().is_path()) {
+            let pkg = try!(packages.get(id));
+            paths.push(pkg.manifest_path().to_path_buf());
+        }
+        paths.push(ws.root().join("Cargo.toml"));
+        paths.push(ws.lockfile());
+        {
+            let mut dir = Some(ws.root());
+            while let Some(d) = dir {
+                paths.push(d.join(".cargo/config"));
+                dir = d.parent();
+            }
+        }
+        paths.push(config.home().join("config").into_path_unlocked());
+        let inputs = paths.into_iter().map(|path| {
+            let mtime = modified(&path);
+            (path, mtime)
+        }).collect();
+
+        cache.insert(manifest_path.clone(), Loaded {
+            ws: ws,
+            features: features,
+            no_default_features: no_default_features,
+            packages: packages,
+            resolve: resolve,
+            inputs: inputs,
+        });
+    }
+    Ok(&cache[&manifest_path])
+}
+
+fn modified(path: &Path) -> Option<FileTime> {
+    fs::metadata(path).ok().map(|meta| {
+        FileTime::from_last_modification_time(&meta)
+    })
+}
+
+fn compile<'cfg>(config: &'cfg Config,
+                 cache: &mut Cache<'cfg>,
+                 request: &Request,
+                 mode: ops::CompileMode) -> CargoResult<()> {
+    let loaded = try!(load(config, cache, request));
+    try!(ops::compile_resolved(&loaded.ws, &loaded.packages, &loaded.resolve,
+                               &ops::CompileOptions {
+        config: config,
+        jobs: None,
+        targets: Vec::new(),
+        // Already applied by the resolve
+        features: &[],
+        no_default_features: false,
+        spec: ops::Packages::Packages(&[]),
+        filter: ops::CompileFilter::Everything,
+        exec_engine: None,
+        release: request.release.unwrap_or(false),
+        profile: None,
+        mode: mode,
+        target_rustdoc_args: None,
+        target_rustc_args: None,
+        target_crate_types: None,
+        exclude: ops::ExcludeTargets::default(),
+        build_plan: false,
+        coverage: false,
+        message_format: ops::MessageFormat::Human,
+    }));
+    Ok(())
+}
+
+fn metadata<'cfg>(config: &'cfg Config,
+                  cache: &mut Cache<'cfg>,
+                  request: &Request) -> CargoResult<String> {
+    let loaded = try!(load(config, cache, request));
+    let opts = ops::OutputMetadataOptions {
+        features: loaded.features.clone(),
+        no_default_features: loaded.no_default_features,
+        no_deps: false,
+        version: 1,
+        build_scripts: false,
+    };
+    let info = try!(ops::output_resolved_metadata(&loaded.ws,
+                                                  &loaded.packages,
+                                                  &loaded.resolve,
+                                                  &opts));
+    Ok(try!(json::encode(&info)))
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    use libc;
+
+    use util::{CargoResult, ChainError, Config, human};
+    use super::{Cache, DaemonOptions};
+
+    pub fn serve(opts: &DaemonOptions) -> CargoResult<()> {
+        let socket = &opts.socket;
+        if socket.exists() {
+            if UnixStream::connect(socket).is_ok() {
+                bail!("another daemon is already listening on `{}`",
+                      socket.display())
+            }
+            // Left behind by a daemon which didn't shut down cleanly
+            try!(fs::remove_file(socket));
+        }
+        // Anyone able to connect can run builds as this user, so the socket
+        // is created accessible to its owner only. The umask is the only way
+        // to get there without a window where it's accessible to others.
+        let mask = unsafe { libc::umask(0o177) };
+        let listener = UnixListener::bind(socket);
+        unsafe { libc::umask(mask); }
+        let listener = try!(listener.chain_error(|| {
+            human(format!("failed to listen on `{}`", socket.display()))
+        }));
+        try!(opts.config.shell().status("Listening",
+                                        socket.display().to_string()));
+
+        let mut cache = HashMap::new();
+        for stream in listener.incoming() {
+            // A client going away mustn't take the daemon down with it
+            let result = stream.map_err(From::from).and_then(|stream| {
+                respond(opts.config, &mut cache, stream)
+            });
+            match result {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => {
+                    let _ = opts.config.shell().warn(format!(
+                        "failed to serve a connection: {}", e));
+                }
+            }
+        }
+
+        try!(fs::remove_file(socket));
+        Ok(())
+    }
+
+    /// Answers the request read from `stream`, returning whether the daemon
+    /// should shut down.
+    fn respond<'cfg>(config: &'cfg Config, cache: &mut Cache<'cfg>,
+                     mut stream: UnixStream) -> CargoResult<bool> {
+        let mut line = String::new();
+        try!(BufReader::new(try!(stream.try_clone())).read_line(&mut line));
+        let (response, shutdown) = super::handle(config, cache, &line);
+        try!(stream.write_all(response.as_bytes()));
+        try!(stream.write_all(b"\n"));
+        Ok(shutdown)
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use util::CargoResult;
+    use super::DaemonOptions;
+
+    pub fn serve(_opts: &DaemonOptions) -> CargoResult<()> {
+        bail!("`cargo daemon` is only supported on Unix platforms")
+    }
+}
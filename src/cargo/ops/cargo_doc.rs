@@ -3,9 +3,11 @@ use std::fs;
 use std::path::Path;
 use std::process::Command;
 
-use core::{PackageIdSpec, Workspace};
+use regex::Regex;
+
+use core::{Package, PackageIdSpec, Workspace};
 use ops;
-use util::CargoResult;
+use util::{CargoResult, paths};
 
 pub struct DocOptions<'a> {
     pub open_result: bool,
@@ -14,55 +16,104 @@ pub struct DocOptions<'a> {
 
 pub fn doc(ws: &Workspace,
            options: &DocOptions) -> CargoResult<()> {
-    let package = try!(ws.current());
+    let (packages, all) = match options.compile_opts.spec {
+        ops::Packages::Packages(spec) if spec.is_empty() => {
+            (vec![try!(ws.current())], false)
+        }
+        ops::Packages::Packages(..) => (Vec::new(), false),
+        ops::Packages::All { .. } => {
+            let specs = try!(options.compile_opts.spec
+                                    .into_package_id_specs(ws));
+            (ws.members().filter(|p| {
+                specs.iter().any(|s| s.matches(p.package_id()))
+            }).collect(), true)
+        }
+    };
 
     let mut lib_names = HashSet::new();
     let mut bin_names = HashSet::new();
-    if options.compile_opts.spec.is_empty() {
+    for package in packages.iter() {
+        let mut libs = HashSet::new();
+        let mut bins = HashSet::new();
         for target in package.targets().iter().filter(|t| t.documented()) {
             if target.is_lib() {
-                assert!(lib_names.insert(target.crate_name()));
+                assert!(libs.insert(target.crate_name()));
             } else {
-                assert!(bin_names.insert(target.crate_name()));
+                assert!(bins.insert(target.crate_name()));
             }
         }
-        for bin in bin_names.iter() {
-            if lib_names.contains(bin) {
+        for bin in bins.iter() {
+            if libs.contains(bin) {
                 bail!("cannot document a package where a library and a binary \
                        have the same name. Consider renaming one or marking \
                        the target as `doc = false`")
             }
         }
+        lib_names.extend(libs);
+        bin_names.extend(bins);
     }
 
-    try!(ops::compile(ws, &options.compile_opts));
+    try!(ops::compile_with_summary(ws, &options.compile_opts));
+
+    // Don't bother locking here as if this is getting deleted there's
+    // nothing we can do about it and otherwise if it's getting overwritten
+    // then that's also ok!
+    let doc_dir = ws.target_dir().join("doc").into_path_unlocked();
+    try!(rewrite_member_links(ws, &doc_dir));
+    if all {
+        try!(write_index(ws, &packages, &doc_dir));
+    }
 
     if options.open_result {
-        let name = if options.compile_opts.spec.len() > 1 {
-            bail!("Passing multiple packages and `open` is not supported")
-        } else if options.compile_opts.spec.len() == 1 {
-            try!(PackageIdSpec::parse(&options.compile_opts.spec[0]))
-                                             .name().replace("-", "_")
-        } else {
-            match lib_names.iter().chain(bin_names.iter()).nth(0) {
-                Some(s) => s.to_string(),
-                None => return Ok(())
+        // The documentation of a whole workspace opens at its index
+        let name = match options.compile_opts.spec {
+            ops::Packages::All { .. } => None,
+            ops::Packages::Packages(spec) if spec.len() > 1 => {
+                bail!("Passing multiple packages and `open` is not supported")
+            }
+            ops::Packages::Packages(spec) if spec.len() == 1 => {
+                Some(try!(PackageIdSpec::parse(&spec[0]))
+                                             .name().replace("-", "_"))
+            }
+            ops::Packages::Packages(..) => {
+                match lib_names.iter().chain(bin_names.iter()).nth(0) {
+                    Some(s) => Some(s.to_string()),
+                    None => return Ok(())
+                }
             }
         };
 
-        // Don't bother locking here as if this is getting deleted there's
-        // nothing we can do about it and otherwise if it's getting overwritten
-        // then that's also ok!
-        let target_dir = options.compile_opts.config.target_dir(ws);
-        let path = target_dir.join("doc").join(&name).join("index.html");
-        let path = path.into_path_unlocked();
+        let path = match name {
+            Some(name) => doc_dir.join(&name).join("index.html"),
+            None => doc_dir.join("index.html"),
+        };
         if fs::metadata(&path).is_ok() {
-            let mut shell = options.compile_opts.config.shell();
-            match open_docs(&path) {
+            let config = options.compile_opts.config;
+            let browser = try!(config.get_string("doc.browser"));
+            let opened = match browser {
+                Some(browser) => {
+                    let mut words = browser.val.split_whitespace();
+                    let program = match words.next() {
+                        Some(program) => program,
+                        None => bail!("doc.browser is empty in {}",
+                                      browser.definition),
+                    };
+                    let mut cmd = Command::new(program);
+                    cmd.args(&words.collect::<Vec<_>>()).arg(&path);
+                    match cmd.status() {
+                        Ok(_) => Ok(program.to_string()),
+                        Err(_) => Err(vec![program.to_string()]),
+                    }
+                }
+                None => open_docs(&path),
+            };
+            let mut shell = config.shell();
+            match opened {
                 Ok(m) => try!(shell.status("Launching", m)),
                 Err(e) => {
-                    try!(shell.warn(
-                            "warning: could not determine a browser to open docs with, tried:"));
+                    try!(shell.warn("could not determine a browser to open \
+                                     docs with, set `doc.browser` to choose \
+                                     one, tried:"));
                     for method in e {
                         try!(shell.warn(format!("\t{}", method)));
                     }
@@ -74,23 +125,148 @@ pub fn doc(ws: &Workspace,
     Ok(())
 }
 
+/// Returns the name of the crate the documentation of `pkg` is rooted at, its
+/// library if it has one.
+fn doc_crate_name(pkg: &Package) -> Option<String> {
+    let targets = pkg.targets().iter().filter(|t| t.documented());
+    let mut names = targets.map(|t| (!t.is_lib(), t.crate_name()))
+                           .collect::<Vec<_>>();
+    names.sort();
+    names.into_iter().next().map(|(_, name)| name)
+}
+
+/// Writes the `index.html` at the root of the documentation of a workspace,
+/// linking to that of each member along with its description.
+fn write_index(ws: &Workspace, packages: &[&Package], doc_dir: &Path)
+               -> CargoResult<()> {
+    let mut rows = String::new();
+    for pkg in packages {
+        let name = match doc_crate_name(pkg) {
+            Some(name) => name,
+            None => continue,
+        };
+        let description = pkg.manifest().metadata().description.as_ref()
+                             .map(|d| escape(d)).unwrap_or(String::new());
+        rows.push_str(&format!("<tr><td><a class=\"mod\" \
+                                href=\"{0}/index.html\">{0}</a></td>\
+                                <td class=\"docblock-short\">{1}</td></tr>\n",
+                               name, description));
+    }
+    let title = ws.root().file_name().and_then(|s| s.to_str())
+                  .map(escape).unwrap_or(String::from("Workspace"));
+    let html = format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{0} - Rust documentation</title>
+<link rel="stylesheet" type="text/css" href="rustdoc.css">
+<link rel="stylesheet" type="text/css" href="main.css">
+</head>
+<body class="rustdoc">
+<section id="main" class="content">
+<h1 class="fqn">Crates of the <span class="in-band">{0}</span> workspace</h1>
+<table>
+{1}</table>
+</section>
+</body>
+</html>
+"#, title, rows);
+    let path = doc_dir.join("index.html");
+    paths::write(&path, html.as_bytes())
+}
+
+fn escape(s: &str) -> String {
+    s.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;")
+     .replace("\"", "&quot;")
+}
+
+/// Points the links between the documentation of workspace members at the
+/// copies in `doc_dir`.
+///
+/// Crates setting `#![doc(html_root_url = "...")]` are linked to at that URL
+/// by rustdoc, usually where their released documentation is published, which
+/// for members of the workspace is out of date with what was just documented.
+fn rewrite_member_links(ws: &Workspace, doc_dir: &Path) -> CargoResult<()> {
+    let re = Regex::new(r#"html_root_url\s*=\s*"([^"]+)""#).unwrap();
+    let mut roots = Vec::new();
+    for pkg in ws.members() {
+        let lib = match pkg.targets().iter().find(|t| t.is_lib()) {
+            Some(lib) => lib,
+            None => continue,
+        };
+        if !doc_dir.join(lib.crate_name()).is_dir() {
+            continue
+        }
+        let src = match paths::read(lib.src_path()) {
+            Ok(src) => src,
+            Err(..) => continue,
+        };
+        if let Some(url) = re.captures(&src).and_then(|c| c.at(1)) {
+            let url = url.trim_right_matches('/');
+            let name = lib.crate_name();
+            roots.push((format!("href=\"{}/{}/", url, name), name));
+        }
+    }
+    if roots.is_empty() {
+        return Ok(())
+    }
+
+    for pkg in ws.members() {
+        let name = match doc_crate_name(pkg) {
+            Some(name) => name,
+            None => continue,
+        };
+        let dir = doc_dir.join(&name);
+        if dir.is_dir() {
+            try!(rewrite_links_in(&dir, &roots, "../"));
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the links starting with one of `roots` in the HTML files under
+/// `dir`, `up` leading back to the root of the documentation from `dir`.
+fn rewrite_links_in(dir: &Path, roots: &[(String, String)], up: &str)
+                    -> CargoResult<()> {
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+        if path.is_dir() {
+            try!(rewrite_links_in(&path, roots, &format!("{}../", up)));
+            continue
+        }
+        if path.extension().and_then(|e| e.to_str()) != Some("html") {
+            continue
+        }
+        let html = try!(paths::read(&path));
+        let mut rewritten = html.clone();
+        for &(ref root, ref name) in roots {
+            rewritten = rewritten.replace(&root[..],
+                                          &format!("href=\"{}{}/", up, name));
+        }
+        if rewritten != html {
+            try!(paths::write(&path, rewritten.as_bytes()));
+        }
+    }
+    Ok(())
+}
+
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-fn open_docs(path: &Path) -> Result<&'static str, Vec<&'static str>> {
+fn open_docs(path: &Path) -> Result<String, Vec<String>> {
     use std::env;
     let mut methods = Vec::new();
     // trying $BROWSER
     match env::var("BROWSER"){
         Ok(name) => match Command::new(name).arg(path).status() {
-            Ok(_) => return Ok("$BROWSER"),
-            Err(_) => methods.push("$BROWSER")
+            Ok(_) => return Ok("$BROWSER".to_string()),
+            Err(_) => methods.push("$BROWSER".to_string())
         },
         Err(_) => () // Do nothing here if $BROWSER is not found
     }
 
     for m in ["xdg-open", "gnome-open", "kde-open"].iter() {
         match Command::new(m).arg(path).status() {
-            Ok(_) => return Ok(m),
-            Err(_) => methods.push(m)
+            Ok(_) => return Ok(m.to_string()),
+            Err(_) => methods.push(m.to_string())
         }
     }
 
@@ -98,17 +274,19 @@ fn open_docs(path: &Path) -> Result<&'static str, Vec<&'static str>> {
 }
 
 #[cfg(target_os = "windows")]
-fn open_docs(path: &Path) -> Result<&'static str, Vec<&'static str>> {
-    match Command::new("cmd").arg("/C").arg(path).status() {
-        Ok(_) => return Ok("cmd /C"),
-        Err(_) => return Err(vec!["cmd /C"])
-    };
+fn open_docs(path: &Path) -> Result<String, Vec<String>> {
+    // `start` takes the first quoted argument as the title of the window
+    match Command::new("cmd").arg("/C").arg("start").arg("").arg(path)
+                             .status() {
+        Ok(_) => Ok("cmd /C start".to_string()),
+        Err(_) => Err(vec!["cmd /C start".to_string()]),
+    }
 }
 
 #[cfg(target_os = "macos")]
-fn open_docs(path: &Path) -> Result<&'static str, Vec<&'static str>> {
+fn open_docs(path: &Path) -> Result<String, Vec<String>> {
     match Command::new("open").arg(path).status() {
-        Ok(_) => return Ok("open"),
-        Err(_) => return Err(vec!["open"])
-    };
+        Ok(_) => Ok("open".to_string()),
+        Err(_) => Err(vec!["open".to_string()]),
+    }
 }
@@ -1,3 +1,6 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
 use core::registry::PackageRegistry;
 use core::{PackageId, Resolve, PackageSet, Workspace};
 use ops;
@@ -8,7 +11,7 @@ pub fn fetch<'a>(ws: &Workspace<'a>) -> CargoResult<(Resolve, PackageSet<'a>)> {
     let mut registry = PackageRegistry::new(ws.config());
     let resolve = try!(ops::resolve_ws(&mut registry, ws));
     let packages = get_resolved_packages(&resolve, registry);
-    for id in resolve.iter() {
+    for id in download_order(&resolve) {
         try!(packages.get(id));
     }
     Ok((resolve, packages))
@@ -20,3 +23,42 @@ pub fn get_resolved_packages<'a>(resolve: &Resolve,
     let ids: Vec<PackageId> = resolve.iter().cloned().collect();
     registry.get(&ids)
 }
+
+/// Returns the packages of `resolve` in the order they should be downloaded.
+///
+/// Packages are ordered by their depth in the dependency graph, deepest
+/// first. The deepest crates are the first ones which can be compiled and
+/// they start the longest chains of crates waiting on each other, so getting
+/// them first keeps the critical path of a build as short as possible. Ties
+/// are broken by package id to keep the order deterministic.
+///
+/// Builds download the packages they wait on in this order, compiling the
+/// ones downloaded already in the meantime.
+pub fn download_order(resolve: &Resolve) -> Vec<&PackageId> {
+    // Longest distance from any package which nothing depends on. Cycles can
+    // only arise through dev-dependencies, so depths are capped at the number
+    // of packages to guarantee termination.
+    let max = resolve.iter().count();
+    let mut depth = HashMap::new();
+    let mut stack = resolve.iter().map(|id| (id, 0)).collect::<Vec<_>>();
+    while let Some((id, d)) = stack.pop() {
+        if d > max {
+            continue
+        }
+        match depth.get(id) {
+            Some(&prev) if prev >= d => continue,
+            _ => {}
+        }
+        depth.insert(id, d);
+        stack.extend(resolve.deps(id).map(|dep| (dep, d + 1)));
+    }
+
+    let mut ids = resolve.iter().collect::<Vec<_>>();
+    ids.sort_by(|a, b| {
+        match depth.get(b).cmp(&depth.get(a)) {
+            Ordering::Equal => a.cmp(b),
+            other => other,
+        }
+    });
+    ids
+}
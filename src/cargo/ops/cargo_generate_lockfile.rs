@@ -1,4 +1,10 @@
+use std::cmp;
 use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str;
+
+use git2;
 
 use core::PackageId;
 use core::registry::PackageRegistry;
@@ -6,20 +12,23 @@ use core::{Resolve, SourceId, Workspace};
 use core::resolver::Method;
 use ops;
 use util::config::Config;
-use util::CargoResult;
+use util::{self, CargoResult, ChainError, human, paths};
 
 pub struct UpdateOptions<'a> {
     pub config: &'a Config,
     pub to_update: &'a [String],
     pub precise: Option<&'a str>,
     pub aggressive: bool,
+    /// Only consider the versions published to the registry before this date,
+    /// given as `YYYY-MM-DD`
+    pub as_of: Option<&'a str>,
 }
 
 pub fn generate_lockfile(ws: &Workspace) -> CargoResult<()> {
     let mut registry = PackageRegistry::new(ws.config());
     let resolve = try!(ops::resolve_with_previous(&mut registry, ws,
                                                   Method::Everything,
-                                                  None, None));
+                                                  None, None, &[]));
     try!(ops::write_pkg_lockfile(ws, &resolve));
     Ok(())
 }
@@ -30,12 +39,32 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
     if opts.aggressive && opts.precise.is_some() {
         bail!("cannot specify both aggressive and precise simultaneously")
     }
+    // Packages which stay locked may have been published after the date, so
+    // everything is resolved again
+    if opts.as_of.is_some() &&
+       (opts.precise.is_some() || !opts.to_update.is_empty()) {
+        bail!("cannot specify as-of along with packages to update or precise")
+    }
+
+    let mut registry = PackageRegistry::new(opts.config);
+    // The registry is read as of the last commit of its index made before the
+    // date, which is passed along as its precise version
+    if let Some(date) = opts.as_of {
+        let timestamp = try!(parse_date(date));
+        let id = try!(SourceId::for_central(opts.config));
+        let precise = format!("as-of:{}", timestamp);
+        try!(registry.add_sources(&[id.with_precise(Some(precise))]));
+    }
 
     let previous_resolve = match try!(ops::load_pkg_lockfile(ws)) {
         Some(resolve) => resolve,
-        None => return generate_lockfile(ws),
+        None => {
+            let resolve = try!(ops::resolve_with_previous(&mut registry, ws,
+                                                          Method::Everything,
+                                                          None, None, &[]));
+            return ops::write_pkg_lockfile(ws, &resolve)
+        }
     };
-    let mut registry = PackageRegistry::new(opts.config);
     let mut to_avoid = HashSet::new();
 
     if opts.to_update.is_empty() {
@@ -74,7 +103,8 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
                                                   ws,
                                                   Method::Everything,
                                                   Some(&previous_resolve),
-                                                  Some(&to_avoid)));
+                                                  Some(&to_avoid),
+                                                  &[]));
 
     // Summarize what is changing for the user.
     let print_change = |status: &str, msg: String| {
@@ -113,66 +143,417 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
             fill_with_deps(resolve, dep, set, visited);
         }
     }
+}
 
-    fn compare_dependency_graphs<'a>(previous_resolve: &'a Resolve,
-                                     resolve: &'a Resolve) ->
-                                     Vec<(Vec<&'a PackageId>, Vec<&'a PackageId>)> {
-        fn key(dep: &PackageId) -> (&str, &SourceId) {
-            (dep.name(), dep.source_id())
-        }
-
-        // Removes all package ids in `b` from `a`. Note that this is somewhat
-        // more complicated because the equality for source ids does not take
-        // precise versions into account (e.g. git shas), but we want to take
-        // that into account here.
-        fn vec_subtract<'a>(a: &[&'a PackageId],
-                            b: &[&'a PackageId]) -> Vec<&'a PackageId> {
-            a.iter().filter(|a| {
-                // If this package id is not found in `b`, then it's definitely
-                // in the subtracted set
-                let i = match b.binary_search(a) {
-                    Ok(i) => i,
-                    Err(..) => return true,
-                };
-
-                // If we've found `a` in `b`, then we iterate over all instances
-                // (we know `b` is sorted) and see if they all have different
-                // precise versions. If so, then `a` isn't actually in `b` so
-                // we'll let it through.
-                //
-                // Note that we only check this for non-registry sources,
-                // however, as registries countain enough version information in
-                // the package id to disambiguate
-                if a.source_id().is_registry() {
-                    return false
-                }
-                b[i..].iter().take_while(|b| a == b).all(|b| {
-                    a.source_id().precise() != b.source_id().precise()
-                })
-            }).cloned().collect()
+/// Parses a `YYYY-MM-DD` date into the number of seconds from the epoch
+/// to the start of that day, in UTC.
+fn parse_date(date: &str) -> CargoResult<i64> {
+    let parts = date.split('-').map(|s| s.parse::<i64>().ok())
+                    .collect::<Vec<_>>();
+    let (y, m, d) = match (parts.len(), parts.get(0), parts.get(1),
+                           parts.get(2)) {
+        (3, Some(&Some(y)), Some(&Some(m)), Some(&Some(d)))
+                if 1 <= m && m <= 12 && 1 <= d && d <= 31 => (y, m, d),
+        _ => bail!("invalid date `{}`, expected one such as `2016-05-01`",
+                   date),
+    };
+    // Days from the epoch to the date in the proleptic Gregorian
+    // calendar, counting years from March so leap days come last
+    let y = if m <= 2 {y - 1} else {y};
+    let era = (if y >= 0 {y} else {y - 399}) / 400;
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Ok((era * 146097 + doe - 719468) * 86400)
+}
+
+/// How the `Cargo.lock` of a workspace differs from what resolution would
+/// produce, as reported by `cargo verify-lockfile`.
+#[derive(RustcEncodable)]
+pub struct LockfileDrift {
+    pub up_to_date: bool,
+    pub missing: bool,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<LockfileChange>,
+    pub dependencies_changed: Vec<String>,
+}
+
+#[derive(RustcEncodable)]
+pub struct LockfileChange {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Resolves the workspace against its current lock file and reports any
+/// differences, never writing `Cargo.lock` itself.
+pub fn verify_lockfile(ws: &Workspace) -> CargoResult<LockfileDrift> {
+    let previous = try!(ops::load_pkg_lockfile(ws));
+    let mut registry = PackageRegistry::new(ws.config());
+    let resolve = try!(ops::resolve_with_previous(&mut registry, ws,
+                                                  Method::Everything,
+                                                  previous.as_ref(), None,
+                                                  &[]));
+    let mut drift = LockfileDrift {
+        up_to_date: true,
+        missing: false,
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+        dependencies_changed: Vec::new(),
+    };
+
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            drift.up_to_date = false;
+            drift.missing = true;
+            drift.added = resolve.iter().map(|p| p.to_string()).collect();
+            drift.added.sort();
+            return Ok(drift)
+        }
+    };
+
+    for (removed, added) in compare_dependency_graphs(&previous, &resolve) {
+        if removed.len() == 1 && added.len() == 1 {
+            drift.changed.push(LockfileChange {
+                name: removed[0].name().to_string(),
+                from: describe(removed[0]),
+                to: describe(added[0]),
+            });
+        } else {
+            drift.removed.extend(removed.iter().map(|p| p.to_string()));
+            drift.added.extend(added.iter().map(|p| p.to_string()));
         }
+    }
 
-        // Map (package name, package source) to (removed versions, added versions).
-        let mut changes = BTreeMap::new();
-        let empty = (Vec::new(), Vec::new());
-        for dep in previous_resolve.iter() {
-            changes.entry(key(dep)).or_insert(empty.clone()).0.push(dep);
+    for pkg in resolve.iter() {
+        let old = previous.deps_not_replaced(pkg).collect::<HashSet<_>>();
+        let new = resolve.deps_not_replaced(pkg).collect::<HashSet<_>>();
+        if previous.iter().any(|p| p == pkg) && old != new {
+            drift.dependencies_changed.push(pkg.to_string());
         }
-        for dep in resolve.iter() {
-            changes.entry(key(dep)).or_insert(empty.clone()).1.push(dep);
+    }
+    drift.dependencies_changed.sort();
+
+    // Anything else which would change the file on disk, like metadata or
+    // formatting, still counts as drift even if the graph is identical.
+    drift.up_to_date = try!(ops::pkg_lockfile_is_current(ws, &resolve)) &&
+                       drift.added.is_empty() &&
+                       drift.removed.is_empty() &&
+                       drift.changed.is_empty() &&
+                       drift.dependencies_changed.is_empty();
+    Ok(drift)
+}
+
+fn describe(pkg: &PackageId) -> String {
+    match pkg.source_id().precise() {
+        Some(precise) if pkg.source_id().is_git() => {
+            format!("{}#{}", pkg.version(),
+                    &precise[..cmp::min(8, precise.len())])
         }
+        _ => pkg.version().to_string(),
+    }
+}
+
+/// Where one side of a `cargo lockfile-diff` comes from.
+pub enum LockfileSide<'a> {
+    /// A lock file on disk
+    Path(&'a Path),
+    /// The lock file of the workspace at a revision of its git repository
+    Revision(&'a str),
+    /// What resolving the workspace afresh against the index gives, ignoring
+    /// its lock file
+    Index,
+}
 
-        for (_, v) in changes.iter_mut() {
-            let (ref mut old, ref mut new) = *v;
-            old.sort();
-            new.sort();
-            let removed = vec_subtract(old, new);
-            let added = vec_subtract(new, old);
-            *old = removed;
-            *new = added;
+pub struct LockfileDiffOptions<'a> {
+    pub old: LockfileSide<'a>,
+    pub new: LockfileSide<'a>,
+}
+
+/// The packages added, removed or moved to another version between two lock
+/// files, as reported by `cargo lockfile-diff`.
+#[derive(RustcEncodable)]
+pub struct LockfileDiff {
+    pub added: Vec<LockfileDiffEntry>,
+    pub removed: Vec<LockfileDiffEntry>,
+    pub changed: Vec<LockfileDiffEntry>,
+}
+
+#[derive(RustcEncodable)]
+pub struct LockfileDiffEntry {
+    pub name: String,
+    /// The URL of the source, unless it's a path
+    pub source: Option<String>,
+    /// The version in the old lock file, if it's there
+    pub from: Option<String>,
+    /// The version in the new lock file, if it's there
+    pub to: Option<String>,
+    /// Where the package, or what changed in it, can be looked at
+    pub link: Option<String>,
+}
+
+/// Compares the dependency graphs of two lock files of `ws`.
+pub fn lockfile_diff(ws: &Workspace, opts: &LockfileDiffOptions)
+                     -> CargoResult<LockfileDiff> {
+    let old = try!(load_lockfile_side(ws, &opts.old));
+    let new = try!(load_lockfile_side(ws, &opts.new));
+    let mut diff = LockfileDiff {
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+    for (removed, added) in compare_dependency_graphs(&old, &new) {
+        if removed.len() == 1 && added.len() == 1 {
+            diff.changed.push(diff_entry(Some(removed[0]), Some(added[0])));
+        } else {
+            diff.removed.extend(removed.iter().map(|p| diff_entry(Some(p),
+                                                                  None)));
+            diff.added.extend(added.iter().map(|p| diff_entry(None, Some(p))));
+        }
+    }
+    Ok(diff)
+}
+
+fn load_lockfile_side(ws: &Workspace, side: &LockfileSide)
+                      -> CargoResult<Resolve> {
+    match *side {
+        LockfileSide::Path(path) => {
+            let contents = try!(paths::read(path));
+            ops::parse_lockfile(ws, &contents, path)
+        }
+        LockfileSide::Revision(rev) => {
+            let (contents, path) = try!(lockfile_at_revision(ws, rev));
+            ops::parse_lockfile(ws, &contents, &path)
+        }
+        LockfileSide::Index => {
+            let mut registry = PackageRegistry::new(ws.config());
+            ops::resolve_with_previous(&mut registry, ws, Method::Everything,
+                                       None, None, &[])
         }
-        debug!("{:#?}", changes);
+    }
+}
+
+/// Reads the lock file of `ws` as it was at revision `rev` of the git
+/// repository the workspace is in, along with a path naming it.
+fn lockfile_at_revision(ws: &Workspace, rev: &str)
+                        -> CargoResult<(String, PathBuf)> {
+    let lockfile = ws.lockfile();
+    let repo = try!(git2::Repository::discover(ws.root()).chain_error(|| {
+        human(format!("`{}` isn't a lock file, and the workspace isn't in a \
+                       git repository to look it up as a revision of", rev))
+    }));
+    let workdir = try!(repo.workdir().chain_error(|| {
+        human(format!("the git repository at `{}` has no working directory",
+                      repo.path().display()))
+    }));
+    let workdir = try!(fs::canonicalize(workdir));
+    let dir = try!(fs::canonicalize(lockfile.parent().unwrap()));
+    let relative = try!(util::without_prefix(&dir, &workdir).chain_error(|| {
+        human(format!("`{}` isn't in the git repository at `{}`",
+                      lockfile.display(), workdir.display()))
+    })).join(lockfile.file_name().unwrap());
+
+    let commit = try!(try!(repo.revparse_single(rev).chain_error(|| {
+        human(format!("`{}` is neither a lock file nor a revision of the git \
+                       repository at `{}`", rev, workdir.display()))
+    })).peel(git2::ObjectType::Commit));
+    let tree = try!(try!(repo.find_commit(commit.id())).tree());
+    let entry = try!(tree.get_path(&relative).chain_error(|| {
+        human(format!("`{}` doesn't exist at revision `{}`",
+                      relative.display(), rev))
+    }));
+    let blob = try!(repo.find_blob(entry.id()));
+    let contents = try!(str::from_utf8(blob.content()).chain_error(|| {
+        human(format!("`{}` isn't valid UTF-8 at revision `{}`",
+                      relative.display(), rev))
+    }));
+    let path = PathBuf::from(format!("{}:{}", rev, relative.display()));
+    Ok((contents.to_string(), path))
+}
+
+fn diff_entry(from: Option<&PackageId>, to: Option<&PackageId>)
+              -> LockfileDiffEntry {
+    let id = to.or(from).unwrap();
+    let source = id.source_id();
+    let url = if source.is_path() {None} else {Some(source.url().to_string())};
+    LockfileDiffEntry {
+        name: id.name().to_string(),
+        source: url,
+        from: from.map(describe),
+        to: to.map(describe),
+        link: diff_link(from, to),
+    }
+}
+
+/// Links to the changes between two versions of a package on GitHub, or to
+/// the page of the version on crates.io.
+fn diff_link(from: Option<&PackageId>, to: Option<&PackageId>)
+             -> Option<String> {
+    let id = to.or(from).unwrap();
+    let source = id.source_id();
+    if source.is_default_registry() {
+        return Some(format!("https://crates.io/crates/{}/{}", id.name(),
+                            id.version()))
+    }
+    if !source.is_git() {
+        return None
+    }
+    let url = source.url().to_string();
+    let repo = url.trim_right_matches('/').trim_right_matches(".git");
+    if !repo.starts_with("https://github.com/") {
+        return Some(repo.to_string())
+    }
+    let rev = |id: &PackageId| id.source_id().precise().map(|s| s.to_string());
+    Some(match (from.and_then(&rev), to.and_then(&rev)) {
+        (Some(a), Some(b)) => format!("{}/compare/{}...{}", repo, a, b),
+        (_, Some(r)) | (Some(r), None) => format!("{}/tree/{}", repo, r),
+        (None, None) => repo.to_string(),
+    })
+}
+
+impl LockfileDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() &&
+            self.changed.is_empty()
+    }
+
+    /// Renders the differences as lines of plain text.
+    pub fn to_text(&self) -> String {
+        let mut ret = String::new();
+        let sections = [("Added", &self.added), ("Removed", &self.removed),
+                        ("Updated", &self.changed)];
+        for &(what, entries) in sections.iter() {
+            for e in entries.iter() {
+                ret.push_str(&format!("{} {} {}", what, e.name,
+                                      e.versions("v", " -> v")));
+                if let Some(ref link) = e.link {
+                    ret.push_str(&format!(" ({})", link));
+                }
+                ret.push('\n');
+            }
+        }
+        ret
+    }
+
+    /// Renders the differences as Markdown lists, for release notes or pull
+    /// requests.
+    pub fn to_markdown(&self) -> String {
+        let mut ret = String::new();
+        let sections = [("Added", &self.added), ("Removed", &self.removed),
+                        ("Updated", &self.changed)];
+        for &(what, entries) in sections.iter() {
+            if entries.is_empty() {
+                continue
+            }
+            if !ret.is_empty() {
+                ret.push('\n');
+            }
+            ret.push_str(&format!("### {}\n\n", what));
+            for e in entries.iter() {
+                ret.push_str(&format!("* `{}` {}", e.name,
+                                      e.versions("", " -> ")));
+                if let Some(ref link) = e.link {
+                    ret.push_str(&format!(" ([link]({}))", link));
+                }
+                ret.push('\n');
+            }
+        }
+        ret
+    }
+}
+
+impl LockfileDiffEntry {
+    fn versions(&self, prefix: &str, arrow: &str) -> String {
+        match (&self.from, &self.to) {
+            (&Some(ref a), &Some(ref b)) => {
+                format!("{}{}{}{}", prefix, a, arrow, b)
+            }
+            (&Some(ref v), &None) | (&None, &Some(ref v)) => {
+                format!("{}{}", prefix, v)
+            }
+            (&None, &None) => String::new(),
+        }
+    }
+}
+
+fn compare_dependency_graphs<'a>(previous_resolve: &'a Resolve,
+                                 resolve: &'a Resolve) ->
+                                 Vec<(Vec<&'a PackageId>, Vec<&'a PackageId>)> {
+    fn key(dep: &PackageId) -> (&str, &SourceId) {
+        (dep.name(), dep.source_id())
+    }
+
+    // Removes all package ids in `b` from `a`. Note that this is somewhat
+    // more complicated because the equality for source ids does not take
+    // precise versions into account (e.g. git shas), but we want to take
+    // that into account here.
+    fn vec_subtract<'a>(a: &[&'a PackageId],
+                        b: &[&'a PackageId]) -> Vec<&'a PackageId> {
+        a.iter().filter(|a| {
+            // If this package id is not found in `b`, then it's definitely
+            // in the subtracted set
+            let i = match b.binary_search(a) {
+                Ok(i) => i,
+                Err(..) => return true,
+            };
+
+            // If we've found `a` in `b`, then we iterate over all instances
+            // (we know `b` is sorted) and see if they all have different
+            // precise versions. If so, then `a` isn't actually in `b` so
+            // we'll let it through.
+            //
+            // Note that we only check this for non-registry sources,
+            // however, as registries countain enough version information in
+            // the package id to disambiguate
+            if a.source_id().is_registry() {
+                return false
+            }
+            b[i..].iter().take_while(|b| a == b).all(|b| {
+                a.source_id().precise() != b.source_id().precise()
+            })
+        }).cloned().collect()
+    }
+
+    // Map (package name, package source) to (removed versions, added versions).
+    let mut changes = BTreeMap::new();
+    let empty = (Vec::new(), Vec::new());
+    for dep in previous_resolve.iter() {
+        changes.entry(key(dep)).or_insert(empty.clone()).0.push(dep);
+    }
+    for dep in resolve.iter() {
+        changes.entry(key(dep)).or_insert(empty.clone()).1.push(dep);
+    }
+
+    for (_, v) in changes.iter_mut() {
+        let (ref mut old, ref mut new) = *v;
+        old.sort();
+        new.sort();
+        let removed = vec_subtract(old, new);
+        let added = vec_subtract(new, old);
+        *old = removed;
+        *new = added;
+    }
+    debug!("{:#?}", changes);
+
+    changes.into_iter().map(|(_, v)| v).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_date;
 
-        changes.into_iter().map(|(_, v)| v).collect()
+    #[test]
+    fn dates_parsed() {
+        assert_eq!(parse_date("1970-01-01").unwrap(), 0);
+        assert_eq!(parse_date("2016-05-01").unwrap(), 1462060800);
+        assert_eq!(parse_date("2000-02-29").unwrap(), 951782400);
+        assert_eq!(parse_date("1969-12-31").unwrap(), -86400);
+        assert!(parse_date("2016-13-01").is_err());
+        assert!(parse_date("2016-05").is_err());
+        assert!(parse_date("yesterday").is_err());
     }
 }
@@ -0,0 +1,183 @@
+//! Snapshots of the registry index, which pin dependency resolution to the
+//! index as of a given commit.
+//!
+//! A snapshot is a gzipped tarball holding the files of the index at that
+//! commit under `index/`, along with a `snapshot.json` naming the index and the
+//! commit. Once imported, setting `registry.snapshot` to the commit makes the
+//! registry source read the snapshot instead of fetching the index, and the
+//! commit is recorded in the `[metadata]` of `Cargo.lock`.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::{GzBuilder, Compression};
+use git2::{self, ObjectType};
+use rustc_serialize::json;
+use tar::{Archive, Builder, Header};
+
+use core::{Resolve, Source, SourceId};
+use sources::RegistrySource;
+use sources::registry::INDEX_LOCK;
+use util::{CargoResult, ChainError, Config, human, internal, paths};
+
+/// Key of the `[metadata]` of `Cargo.lock` the snapshot is recorded under.
+const METADATA_KEY: &'static str = "registry-snapshot";
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct SnapshotInfo {
+    index: String,
+    commit: String,
+}
+
+/// Writes the snapshot of the registry index at `rev` to `dst`, updating the
+/// index first. Returns the commit the snapshot was taken at.
+pub fn export_index_snapshot(config: &Config,
+                             rev: Option<&str>,
+                             dst: &Path) -> CargoResult<String> {
+    if try!(config.get_string("registry.snapshot")).is_some() {
+        bail!("the registry index can't be snapshotted while `registry.\
+               snapshot` pins it to another snapshot")
+    }
+    let source_id = try!(SourceId::for_central(config));
+    try!(RegistrySource::new(&source_id, config).update());
+
+    let path = RegistrySource::index_path(config, &source_id, None);
+    let repo = try!(git2::Repository::open(path.into_path_unlocked()));
+    let rev = rev.unwrap_or("HEAD");
+    let commit = try!((|| -> CargoResult<git2::Commit> {
+        let object = try!(repo.revparse_single(rev));
+        let object = try!(object.peel(ObjectType::Commit));
+        Ok(try!(repo.find_commit(object.id())))
+    }).chain_error(|| {
+        human(format!("failed to find revision `{}` of the registry index",
+                      rev))
+    }));
+    let info = SnapshotInfo {
+        index: source_id.url().to_string(),
+        commit: commit.id().to_string(),
+    };
+
+    try!((|| -> CargoResult<()> {
+        let file = try!(File::create(dst));
+        let encoder = GzBuilder::new().write(file, Compression::Best);
+        let mut ar = Builder::new(encoder);
+        try!(append(&mut ar, "snapshot.json",
+                    json::encode(&info).unwrap().as_bytes()));
+        try!(archive_tree(&repo, &try!(commit.tree()), "index", &mut ar));
+        let encoder = try!(ar.into_inner());
+        try!(encoder.finish());
+        Ok(())
+    })().chain_error(|| {
+        human(format!("failed to write the snapshot to `{}`", dst.display()))
+    }));
+    Ok(info.commit)
+}
+
+fn archive_tree<W: Write>(repo: &git2::Repository,
+                          tree: &git2::Tree,
+                          prefix: &str,
+                          ar: &mut Builder<W>) -> CargoResult<()> {
+    for entry in tree.iter() {
+        let name = try!(entry.name().chain_error(|| {
+            internal("non-utf8 path in the registry index")
+        }));
+        let path = format!("{}/{}", prefix, name);
+        match entry.kind() {
+            Some(ObjectType::Tree) => {
+                let tree = try!(repo.find_tree(entry.id()));
+                try!(archive_tree(repo, &tree, &path, ar));
+            }
+            Some(ObjectType::Blob) => {
+                let blob = try!(repo.find_blob(entry.id()));
+                try!(append(ar, &path, blob.content()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn append<W: Write>(ar: &mut Builder<W>, path: &str, contents: &[u8])
+                    -> CargoResult<()> {
+    let mut header = Header::new_ustar();
+    try!(header.set_path(path));
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    try!(ar.append(&header, contents));
+    Ok(())
+}
+
+/// Imports the snapshot at `src`, which must be of the registry index in use,
+/// replacing any earlier import of the same commit. Returns that commit.
+pub fn import_index_snapshot(config: &Config, src: &Path)
+                             -> CargoResult<String> {
+    let td = try!(config.temp_dir("cargo-index-snapshot"));
+    let info = try!((|| -> CargoResult<SnapshotInfo> {
+        let file = try!(File::open(src));
+        let mut ar = Archive::new(try!(GzDecoder::new(file)));
+        try!(ar.unpack(td.path()));
+        let info = try!(paths::read(&td.path().join("snapshot.json")));
+        Ok(try!(json::decode(&info)))
+    }).chain_error(|| {
+        human(format!("failed to read the snapshot at `{}`", src.display()))
+    }));
+    if info.commit.is_empty() ||
+       !info.commit.chars().all(|c| c.is_digit(16)) {
+        bail!("invalid commit `{}` in the snapshot at `{}`", info.commit,
+              src.display())
+    }
+
+    let source_id = try!(SourceId::for_central(config));
+    if info.index != source_id.url().to_string() {
+        bail!("the snapshot at `{}` is of the registry index `{}`, but the \
+               one in use is `{}`", src.display(), info.index,
+              source_id.url())
+    }
+
+    let dst = RegistrySource::index_path(config, &source_id,
+                                         Some(&info.commit));
+    let dst = dst.into_path_unlocked();
+    if dst.exists() {
+        try!(fs::remove_dir_all(&dst));
+    }
+    try!(fs::create_dir_all(dst.parent().unwrap()));
+    try!(paths::move_dir(&td.path().join("index"), &dst));
+    // The lock file is what marks the import as complete
+    try!(File::create(dst.join(INDEX_LOCK)));
+    Ok(info.commit)
+}
+
+/// Records the snapshot of the registry index configured with
+/// `registry.snapshot` in the `[metadata]` of the lock file, refusing to go on
+/// if the previous lock file was resolved against another snapshot.
+///
+/// A lock file pinned to a snapshot which isn't configured anymore loses its
+/// pin, with a warning, as the live index was used instead.
+pub fn record_index_snapshot(config: &Config,
+                             resolve: &mut Resolve,
+                             previous: Option<&Resolve>) -> CargoResult<()> {
+    let snapshot = try!(config.get_string("registry.snapshot")).map(|s| s.val);
+    let locked = previous.and_then(|p| p.metadata_value(METADATA_KEY));
+    match (snapshot.as_ref(), locked) {
+        (Some(snapshot), Some(locked)) if snapshot != locked => {
+            bail!("the lock file was resolved against snapshot `{}` of the \
+                   registry index, but `registry.snapshot` is `{}`\n\
+                   remove Cargo.lock to resolve against the new snapshot",
+                  locked, snapshot)
+        }
+        (None, Some(locked)) => {
+            try!(config.shell().warn(format!(
+                "the lock file was resolved against snapshot `{}` of the \
+                 registry index, but `registry.snapshot` isn't set\n\
+                 the live index was used and the lock file isn't pinned to \
+                 the snapshot anymore, set `registry.snapshot = \"{}\"` to \
+                 keep it", locked, locked)));
+        }
+        _ => {}
+    }
+    resolve.set_metadata(METADATA_KEY, snapshot);
+    Ok(())
+}
@@ -7,27 +7,79 @@ use std::io::prelude::*;
 use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 
+use semver::VersionReq;
 use tempdir::TempDir;
 use toml;
 
 use core::{SourceId, Source, Package, Dependency, PackageIdSpec};
-use core::{PackageId, Workspace};
+use core::{PackageId, Profile, Workspace};
 use ops::{self, CompileFilter};
 use sources::{GitSource, PathSource, RegistrySource};
-use util::{CargoResult, ChainError, Config, human, internal};
+use util::{CargoResult, ChainError, Config, ToSemver, human, internal};
 use util::{Filesystem, FileLock};
 
+/// The contents of `.crates.toml` in the installation root, as written by any
+/// version of Cargo. It's always rewritten as the first version, which all
+/// versions of Cargo can read, the rest of what's known about the installed
+/// packages going to `.crates-info.toml` instead.
 #[derive(RustcDecodable, RustcEncodable)]
 enum CrateListing {
     V1(CrateListingV1),
+    V2(CrateListingV2),
     Empty,
 }
 
+/// The binaries installed by each package.
 #[derive(RustcDecodable, RustcEncodable)]
 struct CrateListingV1 {
     v1: BTreeMap<PackageId, BTreeSet<String>>,
 }
 
+/// How each package was installed, along with the binaries it installed. This
+/// is what `.crates-info.toml` holds.
+#[derive(RustcDecodable, RustcEncodable)]
+struct CrateListingV2 {
+    v2: BTreeMap<PackageId, InstallInfo>,
+}
+
+/// What's recorded about an installed package. The package id holds its
+/// version and source. Packages recorded by an older version of Cargo only
+/// have their binaries listed, the rest is `None` for them.
+#[derive(RustcDecodable, RustcEncodable, Clone, PartialEq, Debug)]
+struct InstallInfo {
+    bins: BTreeSet<String>,
+    version_req: Option<String>,
+    features: Option<BTreeSet<String>>,
+    no_default_features: Option<bool>,
+    profile: Option<String>,
+}
+
+impl CrateListingV2 {
+    /// Returns the packages installed from the same source and with the same
+    /// name as `pkg`, which installing `pkg` would upgrade or downgrade.
+    fn installed_versions(&self, pkg: &PackageId)
+                          -> Vec<(&PackageId, &InstallInfo)> {
+        self.v2.iter().filter(|&(id, _)| {
+            id.name() == pkg.name() && id.source_id() == pkg.source_id()
+        }).collect()
+    }
+}
+
+impl InstallInfo {
+    fn new(vers: Option<&str>, opts: &ops::CompileOptions) -> InstallInfo {
+        let features = opts.features.iter().flat_map(|s| {
+            s.split_whitespace()
+        }).map(|s| s.to_string()).collect();
+        InstallInfo {
+            bins: BTreeSet::new(),
+            version_req: vers.map(|s| s.to_string()),
+            features: Some(features),
+            no_default_features: Some(opts.no_default_features),
+            profile: Some(if opts.release {"release"} else {"dev"}.to_string()),
+        }
+    }
+}
+
 struct Transaction {
     bins: Vec<PathBuf>,
 }
@@ -46,54 +98,133 @@ impl Drop for Transaction {
     }
 }
 
+/// Installs `krates`, or the package at `source_id` if none is given.
+///
+/// With `keep_features` a crate which is already installed is rebuilt with
+/// the features it was installed with, unless `opts` asks for some.
 pub fn install(root: Option<&str>,
-               krate: Option<&str>,
+               krates: Vec<&str>,
                source_id: &SourceId,
                vers: Option<&str>,
                opts: &ops::CompileOptions,
-               force: bool) -> CargoResult<()> {
+               force: bool,
+               keep_features: bool,
+               lockfile: Option<&Path>) -> CargoResult<()> {
     let config = opts.config;
     let root = try!(resolve_root(root, config));
-    let (pkg, source) = if source_id.is_git() {
-        try!(select_pkg(GitSource::new(source_id, config), source_id,
-                        krate, vers, &mut |git| git.read_packages()))
-    } else if source_id.is_path() {
-        let path = source_id.url().to_file_path().ok()
-                            .expect("path sources must have a valid path");
-        let mut src = PathSource::new(&path, source_id, config);
-        try!(src.update().chain_error(|| {
-            human(format!("`{}` is not a crate root; specify a crate to \
-                           install from crates.io, or use --path or --git to \
-                           specify an alternate source", path.display()))
-        }));
-        try!(select_pkg(PathSource::new(&path, source_id, config),
-                        source_id, krate, vers,
-                        &mut |path| path.read_packages()))
-    } else {
-        try!(select_pkg(RegistrySource::new(source_id, config),
-                        source_id, krate, vers,
-                        &mut |_| Err(human("must specify a crate to install from \
-                                            crates.io, or use --path or --git to \
-                                            specify alternate source"))))
-    };
-    let ws = Workspace::one(pkg, config);
-    let pkg = try!(ws.current());
+    if lockfile.is_some() && (!source_id.is_path() || krates.len() > 1) {
+        bail!("--lockfile can only be used when installing one crate from \
+               a path")
+    }
+    if krates.len() <= 1 {
+        let krate = krates.into_iter().next();
+        return install_one(&root, krate, source_id, vers, opts, force,
+                           keep_features, None, lockfile)
+    }
+    if vers.is_some() {
+        bail!("a version can't be given with --vers when installing more \
+               than one crate")
+    }
 
-    // Preflight checks to check up front whether we'll overwrite something.
-    // We have to check this again afterwards, but may as well avoid building
-    // anything if we're gonna throw it away anyway.
-    {
-        let metadata = try!(metadata(config, &root));
-        let list = try!(read_crate_list(metadata.file()));
-        let dst = metadata.parent().join("bin");
-        try!(check_overwrites(&dst, pkg, &opts.filter, &list, force));
+    // The crates are built in one target directory, so the dependencies they
+    // have in common are only compiled once.
+    let td = match config.target_dir() {
+        Some(..) => None,
+        None => Some(try!(config.temp_dir("cargo-install"))),
+    };
+    let target_dir = match td {
+        Some(ref td) => Filesystem::new(td.path().to_path_buf()),
+        None => config.target_dir().unwrap(),
+    };
+    let mut succeeded = Vec::new();
+    let mut failed = Vec::new();
+    // Crates from a path are built in their own workspaces, and with
+    // --locked each crate is built with the lock file it ships with, so
+    // those are built one after the other.
+    if source_id.is_path() || !config.lock_update_allowed() {
+        for krate in krates {
+            match install_one(&root, Some(krate), source_id, None, opts,
+                              force, keep_features, Some(&target_dir),
+                              None) {
+                Ok(()) => succeeded.push(krate),
+                Err(e) => {
+                    try!(config.shell().error(&e));
+                    failed.push(krate);
+                }
+            }
+        }
+    } else {
+        try!(install_together(&root, &krates, source_id, opts, force,
+                              keep_features, &target_dir, &mut succeeded,
+                              &mut failed));
     }
+    if !succeeded.is_empty() {
+        try!(config.shell().status("Summary", format!(
+            "successfully installed {}", succeeded.join(", "))));
+    }
+    if !failed.is_empty() {
+        if let Some(td) = td {
+            // preserve the temporary directory, so the user can inspect it
+            td.into_path();
+        }
+        bail!("failed to install {}, intermediate artifacts can be found at \
+               `{}`", failed.join(", "), target_dir.display())
+    }
+    Ok(())
+}
 
+/// Installs `krate`, building it in `shared_target_dir` if given, which is
+/// left in place for the crates installed along with it, and resolving it
+/// with `lockfile` rather than the lock file of its workspace if given.
+fn install_one(root: &Filesystem,
+               krate: Option<&str>,
+               source_id: &SourceId,
+               vers: Option<&str>,
+               opts: &ops::CompileOptions,
+               force: bool,
+               keep_features: bool,
+               shared_target_dir: Option<&Filesystem>,
+               lockfile: Option<&Path>) -> CargoResult<()> {
+    let config = opts.config;
+    let (pkg, source) = try!(select(krate, source_id, vers, config));
+    // A package installed from a path may be a member of a workspace, in
+    // which case it's built as such: with the lock file and target directory
+    // of the workspace, and with the other members as its path dependencies.
+    let mut ws = if source_id.is_path() {
+        let path = pkg.manifest_path().to_path_buf();
+        try!(Workspace::new(&path, config).chain_error(|| {
+            human(format!("failed to load the workspace of `{}`",
+                          path.display()))
+        }))
+    } else {
+        Workspace::one(pkg, config)
+    };
+    if let Some(lockfile) = lockfile {
+        ws.set_lockfile(lockfile);
+    }
+    // The lock file a package ships with is only used with --locked, the
+    // newest versions of its dependencies are picked otherwise.
+    if !source_id.is_path() {
+        if config.lock_update_allowed() {
+            ws.set_ignore_lock(true);
+        } else if !ws.lockfile().exists() {
+            try!(config.shell().warn(&format!(
+                "`{}` doesn't ship a Cargo.lock, so the newest versions of \
+                 its dependencies are used even though --locked was passed",
+                try!(ws.current()))));
+        }
+    }
     let mut td_opt = None;
-    let target_dir = if source_id.is_path() {
-        config.target_dir(&ws)
+    // Build in a temporary directory unless we're installing from a path or
+    // a target directory has been configured explicitly, in which case the
+    // artifacts are kept around for later builds.
+    let transient = !source_id.is_path() && config.target_dir().is_none();
+    let target_dir = if let Some(dir) = shared_target_dir {
+        dir.clone()
+    } else if !transient {
+        ws.target_dir()
     } else {
-        if let Ok(td) = TempDir::new("cargo-install") {
+        if let Ok(td) = config.temp_dir("cargo-install") {
             let p = td.path().to_owned();
             td_opt = Some(td);
             Filesystem::new(p)
@@ -101,7 +232,25 @@ pub fn install(root: Option<&str>,
             Filesystem::new(config.cwd().join("target-install"))
         }
     };
-    config.set_target_dir(target_dir.clone());
+    ws.set_target_dir(target_dir.clone());
+    let pkg = try!(ws.current());
+
+    let features;
+    let mut kept_opts;
+    let opts = match try!(kept_features(root, pkg, opts, keep_features)) {
+        Some((kept_features, no_default_features)) => {
+            features = kept_features;
+            kept_opts = opts.clone();
+            kept_opts.features = &features;
+            kept_opts.no_default_features = no_default_features;
+            &kept_opts
+        }
+        None => opts,
+    };
+    if !try!(preflight(root, pkg, vers, opts, force)) {
+        return Ok(())
+    }
+
     let compile = try!(ops::compile_ws(&ws, Some(source), opts).chain_error(|| {
         if let Some(td) = td_opt.take() {
             // preserve the temporary directory, so the user can inspect it
@@ -111,17 +260,308 @@ pub fn install(root: Option<&str>,
         human(format!("failed to compile `{}`, intermediate artifacts can be \
                        found at `{}`", pkg, target_dir.display()))
     }));
-    let binaries: Vec<(&str, &Path)> = try!(compile.binaries.iter().map(|bin| {
-        let name = bin.file_name().unwrap();
-        if let Some(s) = name.to_str() {
-            Ok((s, bin.as_ref()))
+    let binaries = try!(binaries(&compile, pkg, &opts.filter));
+    let dst = try!(finish(root, pkg, &binaries, vers, opts, force, transient));
+
+    if transient && shared_target_dir.is_none() {
+        // Don't bother grabbing a lock as we're going to blow it all away
+        // anyway.
+        let target_dir = target_dir.into_path_unlocked();
+        try!(fs::remove_dir_all(&target_dir));
+    }
+    warn_if_not_in_path(config, &dst)
+}
+
+/// Installs `krates` from the registry or git repository `source_id` by
+/// building them all at once in `target_dir`, so their builds run in
+/// parallel, recording which were installed in `succeeded` and which weren't
+/// in `failed`.
+///
+/// The features passed apply to all the crates of a build, so crates which
+/// keep different features from their installation are built separately. So
+/// are crates with `[profile]` or `[replace]` sections, which only apply when
+/// the crate is the only root of its build.
+fn install_together<'a>(root: &Filesystem,
+                        krates: &[&'a str],
+                        source_id: &SourceId,
+                        opts: &ops::CompileOptions,
+                        force: bool,
+                        keep_features: bool,
+                        target_dir: &Filesystem,
+                        succeeded: &mut Vec<&'a str>,
+                        failed: &mut Vec<&'a str>) -> CargoResult<()> {
+    let config = opts.config;
+    let mut source = None;
+    // The crates to build, grouped by the features they're built with and
+    // whether they're built on their own
+    let mut groups: Vec<((Vec<String>, bool), bool,
+                         Vec<(&'a str, Package)>)> = Vec::new();
+    for &krate in krates {
+        let selected = (|| -> CargoResult<Option<_>> {
+            let (pkg, src) = try!(select(Some(krate), source_id, None,
+                                         config));
+            if source.is_none() {
+                source = Some(src);
+            }
+            let kept = try!(kept_features(root, &pkg, opts, keep_features));
+            let features = kept.unwrap_or_else(|| {
+                (opts.features.to_vec(), opts.no_default_features)
+            });
+            let wanted = {
+                let mut opts = opts.clone();
+                opts.features = &features.0;
+                opts.no_default_features = features.1;
+                try!(preflight(root, &pkg, None, &opts, force))
+            };
+            if !wanted {
+                return Ok(None)
+            }
+            // Binaries are built to the same place whatever their package
+            let bins = bin_names(&pkg, &opts.filter);
+            for &(_, _, ref pkgs) in groups.iter() {
+                for &(other, ref other_pkg) in pkgs.iter() {
+                    let names = bin_names(other_pkg, &opts.filter);
+                    if let Some(bin) = bins.iter().find(|b| names.contains(b)) {
+                        bail!("`{}` can't be installed along with `{}` as \
+                               both have a binary named `{}`", krate, other,
+                              bin)
+                    }
+                }
+            }
+            Ok(Some((features, pkg)))
+        })();
+        match selected {
+            Ok(Some((features, pkg))) => {
+                let alone = has_own_settings(&pkg);
+                let group = groups.iter().position(|&(ref f, a, _)| {
+                    !alone && !a && *f == features
+                });
+                match group {
+                    Some(i) => groups[i].2.push((krate, pkg)),
+                    None => groups.push((features, alone, vec![(krate, pkg)])),
+                }
+            }
+            Ok(None) => succeeded.push(krate),
+            Err(e) => {
+                try!(config.shell().error(&e));
+                failed.push(krate);
+            }
+        }
+    }
+
+    let transient = config.target_dir().is_none();
+    let mut dst = None;
+    for ((features, no_default_features), _, group) in groups {
+        let (names, pkgs): (Vec<_>, Vec<_>) = group.into_iter().unzip();
+        let mut opts = opts.clone();
+        opts.features = &features;
+        opts.no_default_features = no_default_features;
+        opts.spec = ops::Packages::All { exclude: &[] };
+        let mut ws = Workspace::many(pkgs, config);
+        ws.set_ignore_lock(true);
+        ws.set_target_dir(target_dir.clone());
+
+        let compile = ops::compile_ws(&ws, source.take(), &opts);
+        let compile = match compile.chain_error(|| {
+            human(format!("failed to compile {}",
+                          names.iter().map(|n| format!("`{}`", n))
+                               .collect::<Vec<_>>().join(", ")))
+        }) {
+            Ok(compile) => compile,
+            Err(e) => {
+                try!(config.shell().error(&e));
+                failed.extend(names);
+                continue
+            }
+        };
+        for (name, pkg) in names.into_iter().zip(ws.members()) {
+            let result = binaries(&compile, pkg, &opts.filter).and_then(|b| {
+                finish(root, pkg, &b, None, &opts, force, transient)
+            });
+            match result {
+                Ok(d) => {
+                    dst = Some(d);
+                    succeeded.push(name);
+                }
+                Err(e) => {
+                    try!(config.shell().error(&e));
+                    failed.push(name);
+                }
+            }
+        }
+    }
+    match dst {
+        Some(dst) => warn_if_not_in_path(config, &dst),
+        None => Ok(()),
+    }
+}
+
+/// Whether `pkg` has `[profile]` or `[replace]` sections, which a build only
+/// takes from its first root.
+fn has_own_settings(pkg: &Package) -> bool {
+    let manifest = pkg.manifest();
+    let profiles = manifest.profiles();
+    !manifest.replace().is_empty() ||
+        profiles.dev != Profile::default_dev() ||
+        profiles.release != Profile::default_release() ||
+        profiles.test != Profile::default_test() ||
+        profiles.bench != Profile::default_bench() ||
+        profiles.doc != Profile::default_doc() ||
+        !profiles.dev_overrides.is_empty() ||
+        !profiles.release_overrides.is_empty() ||
+        profiles.dev_build_override.is_some() ||
+        profiles.release_build_override.is_some() ||
+        !profiles.custom.is_empty()
+}
+
+/// Picks the package to install from `source_id`, returning the source it
+/// comes from along with it.
+fn select<'a>(krate: Option<&str>,
+              source_id: &SourceId,
+              vers: Option<&str>,
+              config: &'a Config) -> CargoResult<(Package, Box<Source + 'a>)> {
+    if source_id.is_git() {
+        select_pkg(GitSource::new(source_id, config), source_id,
+                   krate, vers, &mut |git| git.read_packages())
+    } else if source_id.is_path() {
+        let path = source_id.url().to_file_path().ok()
+                            .expect("path sources must have a valid path");
+        let mut src = PathSource::new(&path, source_id, config);
+        try!(src.update().chain_error(|| {
+            human(format!("`{}` is not a crate root; specify a crate to \
+                           install from crates.io, or use --path or --git to \
+                           specify an alternate source", path.display()))
+        }));
+        select_pkg(PathSource::new(&path, source_id, config),
+                   source_id, krate, vers,
+                   &mut |path| path.read_packages())
+    } else {
+        select_pkg(RegistrySource::new(source_id, config),
+                   source_id, krate, vers,
+                   &mut |_| Err(human("must specify a crate to install from \
+                                       crates.io, or use --path or --git to \
+                                       specify alternate source")))
+    }
+}
+
+/// Returns the features `pkg` was installed with, and whether its default
+/// features were disabled, when they're kept for installing it again.
+///
+/// Reinstalls and upgrades keep the features the crate was installed with
+/// rather than falling back to its default features.
+fn kept_features(root: &Filesystem,
+                 pkg: &Package,
+                 opts: &ops::CompileOptions,
+                 keep_features: bool)
+                 -> CargoResult<Option<(Vec<String>, bool)>> {
+    if !keep_features || !opts.features.is_empty() ||
+       opts.no_default_features {
+        return Ok(None)
+    }
+    let config = opts.config;
+    let metadata = try!(metadata(config, root));
+    let list = try!(read_crate_list(&metadata));
+    let prev = list.installed_versions(pkg.package_id()).into_iter()
+                   .max_by_key(|&(id, _)| id);
+    let kept = prev.and_then(|(_, info)| {
+        match (info.features.clone(), info.no_default_features) {
+            (Some(features), Some(no_default_features)) => {
+                Some((features.into_iter().collect::<Vec<_>>(),
+                      no_default_features))
+            }
+            _ => None,
+        }
+    });
+    if let Some((ref features, no_default_features)) = kept {
+        if !features.is_empty() || no_default_features {
+            try!(config.shell().verbose(|shell| {
+                shell.status("Keeping", format!(
+                    "features [{}]{} of `{}` from its installation",
+                    features.join(", "),
+                    if no_default_features {" without defaults"} else {""},
+                    pkg.name()))
+            }));
+        }
+    }
+    Ok(kept)
+}
+
+/// Checks up front whether installing `pkg` would change anything and
+/// whether it would overwrite something, returning whether to install it.
+///
+/// This is checked again afterwards, but may as well avoid building anything
+/// if it's going to be thrown away anyway.
+fn preflight(root: &Filesystem,
+             pkg: &Package,
+             vers: Option<&str>,
+             opts: &ops::CompileOptions,
+             force: bool) -> CargoResult<bool> {
+    let config = opts.config;
+    let metadata = try!(metadata(config, root));
+    let list = try!(read_crate_list(&metadata));
+    let dst = metadata.parent().join("bin");
+    let info = InstallInfo::new(vers, opts);
+    if !try!(needs_install(&dst, pkg, &opts.filter, &info, &list, force)) {
+        try!(config.shell().status("Ignored", format!(
+            "package `{} v{}` is already installed, use --force to \
+             override", pkg.name(), pkg.version())));
+        return Ok(false)
+    }
+    try!(check_overwrites(&dst, pkg, &opts.filter, &list, force));
+    for (id, _) in list.installed_versions(pkg.package_id()) {
+        if id == pkg.package_id() {
+            continue
+        }
+        let status = if id.version() < pkg.version() {
+            "Upgrading"
         } else {
-            bail!("Binary `{:?}` name can't be serialized into string", name)
+            "Downgrading"
+        };
+        try!(config.shell().verbose(|shell| {
+            shell.status(status, format!("`{}` to v{}", id, pkg.version()))
+        }));
+    }
+    Ok(true)
+}
+
+/// Returns the names and paths of the binaries `compile` built for `pkg`.
+fn binaries<'a>(compile: &'a ops::Compilation,
+                pkg: &Package,
+                filter: &ops::CompileFilter)
+                -> CargoResult<Vec<(&'a str, &'a Path)>> {
+    let names = bin_names(pkg, filter);
+    let mut binaries = Vec::new();
+    for bin in compile.binaries.iter() {
+        let name = bin.file_name().unwrap();
+        match name.to_str() {
+            Some(s) if names.iter().any(|n| n == s) => {
+                binaries.push((s, bin.as_ref()))
+            }
+            Some(..) => {}
+            None => {
+                bail!("Binary `{:?}` name can't be serialized into string",
+                      name)
+            }
         }
-    }).collect::<CargoResult<_>>());
+    }
+    Ok(binaries)
+}
 
+/// Installs the `binaries` built for `pkg` into the `bin` directory of the
+/// installation root, returning that directory.
+///
+/// With `transient` the binaries are moved out of the target directory
+/// rather than copied.
+fn finish(root: &Filesystem,
+          pkg: &Package,
+          binaries: &[(&str, &Path)],
+          vers: Option<&str>,
+          opts: &ops::CompileOptions,
+          force: bool,
+          transient: bool) -> CargoResult<PathBuf> {
+    let config = opts.config;
     let metadata = try!(metadata(config, &root));
-    let mut list = try!(read_crate_list(metadata.file()));
+    let mut list = try!(read_crate_list(&metadata));
     let dst = metadata.parent().join("bin");
     let duplicates = try!(check_overwrites(&dst, pkg, &opts.filter,
                                            &list, force));
@@ -135,7 +575,7 @@ pub fn install(root: Option<&str>,
     for &(bin, src) in binaries.iter() {
         let dst = staging_dir.path().join(bin);
         // Try to move if `target_dir` is transient.
-        if !source_id.is_path() {
+        if transient {
             if fs::rename(src, &dst).is_ok() {
                 continue
             }
@@ -184,34 +624,43 @@ pub fn install(root: Option<&str>,
         try_install()
     };
 
-    // Update records of replaced binaries.
+    // Update records of replaced binaries. Reinstalling a package records how
+    // it was installed this time, keeping the binaries it installed before.
+    // The previous entry is removed rather than overwritten, as inserting
+    // would keep its key, which may be pinned to another git revision.
+    let info = InstallInfo::new(vers, opts);
+    let bins = list.v2.remove(pkg.package_id())
+                      .map(|i| i.bins).unwrap_or(BTreeSet::new());
+    list.v2.insert(pkg.package_id().clone(), InstallInfo {
+        bins: bins,
+        ..info
+    });
     for &bin in replaced_names.iter() {
         if let Some(&Some(ref p)) = duplicates.get(bin) {
-            if let Some(set) = list.v1.get_mut(p) {
-                set.remove(bin);
+            if let Some(info) = list.v2.get_mut(p) {
+                info.bins.remove(bin);
             }
         }
-        list.v1.entry(pkg.package_id().clone())
-               .or_insert_with(|| BTreeSet::new())
-               .insert(bin.to_string());
-    }
-
-    // Remove empty metadata lines.
-    let pkgs = list.v1.iter()
-                      .filter_map(|(p, set)| if set.is_empty() { Some(p.clone()) } else { None })
-                      .collect::<Vec<_>>();
-    for p in pkgs.iter() {
-        list.v1.remove(p);
+        list.v2.get_mut(pkg.package_id()).unwrap()
+               .bins.insert(bin.to_string());
     }
 
     // If installation was successful record newly installed binaries.
     if result.is_ok() {
-        list.v1.entry(pkg.package_id().clone())
-               .or_insert_with(|| BTreeSet::new())
-               .extend(to_install.iter().map(|s| s.to_string()));
+        list.v2.get_mut(pkg.package_id()).unwrap()
+               .bins.extend(to_install.iter().map(|s| s.to_string()));
     }
 
-    let write_result = write_crate_list(metadata.file(), list);
+    // Remove packages which no longer have any binaries installed.
+    let pkgs = list.v2.iter()
+                      .filter(|&(_, info)| info.bins.is_empty())
+                      .map(|(p, _)| p.clone())
+                      .collect::<Vec<_>>();
+    for p in pkgs.iter() {
+        list.v2.remove(p);
+    }
+
+    let write_result = write_crate_list(&metadata, list);
     match write_result {
         // Replacement error (if any) isn't actually caused by write error
         // but this seems to be the only way to show both.
@@ -221,15 +670,20 @@ pub fn install(root: Option<&str>,
 
     // Reaching here means all actions have succeeded. Clean up.
     installed.success();
-    if !source_id.is_path() {
-        // Don't bother grabbing a lock as we're going to blow it all away
-        // anyway.
-        let target_dir = target_dir.into_path_unlocked();
-        try!(fs::remove_dir_all(&target_dir));
-    }
+    let mut bins = to_install.iter().chain(to_replace.iter())
+                             .map(|b| format!("`{}`", b))
+                             .collect::<Vec<_>>();
+    bins.sort();
+    let kind = if bins.len() == 1 {"executable"} else {"executables"};
+    try!(config.shell().status("Installed", format!(
+        "package `{} v{}` ({} {})", pkg.name(), pkg.version(), kind,
+        bins.join(", "))));
+    Ok(dst)
+}
 
-    // Print a warning that if this directory isn't in PATH that they won't be
-    // able to run these commands.
+/// Prints a warning that if `dst` isn't in PATH the installed binaries can't
+/// be run by their name.
+fn warn_if_not_in_path(config: &Config, dst: &Path) -> CargoResult<()> {
     let path = env::var_os("PATH").unwrap_or(OsString::new());
     for path in env::split_paths(&path) {
         if path == dst {
@@ -254,7 +708,12 @@ fn select_pkg<'a, T>(mut source: T,
     try!(source.update());
     match name {
         Some(name) => {
-            let dep = try!(Dependency::parse(name, vers, source_id));
+            let req = match vers {
+                Some(v) => Some(try!(version_req(v))),
+                None => None,
+            };
+            let req = req.as_ref().map(|s| &s[..]);
+            let dep = try!(Dependency::parse(name, req, source_id));
             let deps = try!(source.query(&dep));
             match deps.iter().map(|p| p.package_id()).max() {
                 Some(pkgid) => {
@@ -299,6 +758,21 @@ fn select_pkg<'a, T>(mut source: T,
     }
 }
 
+/// Turns the `--vers` passed to `cargo install` into a version requirement.
+///
+/// Unlike in a manifest, a plain version stands for exactly that version
+/// rather than any version compatible with it.
+fn version_req(vers: &str) -> CargoResult<String> {
+    if let Ok(v) = vers.to_semver() {
+        return Ok(format!("={}", v))
+    }
+    match VersionReq::parse(vers) {
+        Ok(..) => Ok(vers.to_string()),
+        Err(..) => bail!("`{}` passed to --vers is neither a version nor a \
+                          version requirement", vers),
+    }
+}
+
 fn one<I, F>(mut i: I, f: F) -> CargoResult<Option<I::Item>>
     where I: Iterator,
           F: FnOnce(Vec<I::Item>) -> String
@@ -314,10 +788,54 @@ fn one<I, F>(mut i: I, f: F) -> CargoResult<Option<I::Item>>
     }
 }
 
+/// Checks whether installing `pkg` would change anything.
+///
+/// It wouldn't if the same version from the same source is installed with
+/// the same features and profile, unless it's from a path, whose contents may
+/// have changed since, or from another commit of a git repository. Installing
+/// a version older than the one installed requires `force`, newer versions
+/// are upgraded to.
+fn needs_install(dst: &Path,
+                 pkg: &Package,
+                 filter: &ops::CompileFilter,
+                 info: &InstallInfo,
+                 list: &CrateListingV2,
+                 force: bool) -> CargoResult<bool> {
+    if force {
+        return Ok(true)
+    }
+    let id = pkg.package_id();
+    for (prev_id, prev) in list.installed_versions(id) {
+        if prev_id.version() > id.version() {
+            bail!("`{}` is already installed, which is newer than the \
+                   requested v{}\nAdd --force to downgrade", prev_id,
+                  id.version())
+        }
+        if prev_id != id || id.source_id().is_path() {
+            continue
+        }
+        // The branch a package was installed from may have moved on since
+        let same_rev = !id.source_id().is_git() ||
+                       prev_id.source_id().precise() ==
+                           id.source_id().precise();
+        let same = same_rev &&
+                   prev.features == info.features &&
+                   prev.no_default_features == info.no_default_features &&
+                   prev.profile == info.profile;
+        let installed = bin_names(pkg, filter).iter().all(|bin| {
+            prev.bins.contains(bin) && fs::metadata(dst.join(bin)).is_ok()
+        });
+        if same && installed {
+            return Ok(false)
+        }
+    }
+    Ok(true)
+}
+
 fn check_overwrites(dst: &Path,
                     pkg: &Package,
                     filter: &ops::CompileFilter,
-                    prev: &CrateListingV1,
+                    prev: &CrateListingV2,
                     force: bool) -> CargoResult<BTreeMap<String, Option<PackageId>>> {
     if let CompileFilter::Everything = *filter {
         // If explicit --bin or --example flags were passed then those'll
@@ -328,12 +846,19 @@ fn check_overwrites(dst: &Path,
         }
     }
     let duplicates = find_duplicates(dst, pkg, filter, prev);
-    if force || duplicates.is_empty() {
+    // Binaries of another version of the same package are upgraded
+    let conflicts = duplicates.iter().filter(|&(_, p)| {
+        p.as_ref().map_or(true, |p| {
+            p.name() != pkg.name() ||
+                p.source_id() != pkg.package_id().source_id()
+        })
+    }).collect::<Vec<_>>();
+    if force || conflicts.is_empty() {
         return Ok(duplicates)
     }
     // Format the error message.
     let mut msg = String::new();
-    for (ref bin, p) in duplicates.iter() {
+    for (ref bin, p) in conflicts {
         msg.push_str(&format!("binary `{}` already exists in destination", bin));
         if let Some(p) = p.as_ref() {
             msg.push_str(&format!(" as part of `{}`\n", p));
@@ -348,71 +873,136 @@ fn check_overwrites(dst: &Path,
 fn find_duplicates(dst: &Path,
                    pkg: &Package,
                    filter: &ops::CompileFilter,
-                   prev: &CrateListingV1) -> BTreeMap<String, Option<PackageId>> {
-    let check = |name| {
-        let name = format!("{}{}", name, env::consts::EXE_SUFFIX);
-        if fs::metadata(dst.join(&name)).is_err() {
-            None
-        } else if let Some((p, _)) = prev.v1.iter().find(|&(_, v)| v.contains(&name)) {
-            Some((name, Some(p.clone())))
-        } else {
-            Some((name, None))
-        }
-    };
+                   prev: &CrateListingV2) -> BTreeMap<String, Option<PackageId>> {
+    bin_names(pkg, filter).into_iter().filter(|name| {
+        fs::metadata(dst.join(name)).is_ok()
+    }).map(|name| {
+        let owner = prev.v2.iter().find(|&(_, v)| v.bins.contains(&name));
+        (name, owner.map(|(p, _)| p.clone()))
+    }).collect()
+}
+
+/// Returns the file names of the binaries installing `pkg` would install.
+fn bin_names(pkg: &Package, filter: &ops::CompileFilter) -> Vec<String> {
+    let name = |name: &str| format!("{}{}", name, env::consts::EXE_SUFFIX);
     match *filter {
         CompileFilter::Everything => {
             pkg.targets().iter()
                          .filter(|t| t.is_bin())
-                         .filter_map(|t| check(t.name()))
+                         .map(|t| name(t.name()))
                          .collect()
         }
         CompileFilter::Only { bins, examples, .. } => {
-            bins.iter().chain(examples)
-                       .filter_map(|t| check(t))
-                       .collect()
+            bins.iter().chain(examples).map(|t| name(t)).collect()
         }
     }
 }
 
-fn read_crate_list(mut file: &File) -> CargoResult<CrateListingV1> {
+/// The locked files recording the installed packages.
+struct Metadata {
+    list: FileLock,
+    info: FileLock,
+}
+
+impl Metadata {
+    fn parent(&self) -> &Path {
+        self.list.parent()
+    }
+}
+
+/// Reads what's known about the installed packages. The binaries are those
+/// listed in `.crates.toml`, as older versions of Cargo may have installed or
+/// uninstalled some since `.crates-info.toml` was written.
+fn read_crate_list(metadata: &Metadata) -> CargoResult<CrateListingV2> {
     (|| -> CargoResult<_> {
-        let mut contents = String::new();
-        try!(file.read_to_string(&mut contents));
+        let contents = try!(read_file(metadata.list.file()));
         let listing = try!(toml::decode_str(&contents).chain_error(|| {
             internal("invalid TOML found for metadata")
         }));
-        match listing {
-            CrateListing::V1(v1) => Ok(v1),
-            CrateListing::Empty => {
-                Ok(CrateListingV1 { v1: BTreeMap::new() })
+        let mut list = match listing {
+            CrateListing::V1(v1) => {
+                let v2 = v1.v1.into_iter().map(|(id, bins)| {
+                    (id, InstallInfo {
+                        bins: bins,
+                        version_req: None,
+                        features: None,
+                        no_default_features: None,
+                        profile: None,
+                    })
+                }).collect();
+                CrateListingV2 { v2: v2 }
+            }
+            CrateListing::V2(v2) => v2,
+            CrateListing::Empty => CrateListingV2 { v2: BTreeMap::new() },
+        };
+
+        let contents = try!(read_file(metadata.info.file()));
+        if contents.trim().is_empty() {
+            return Ok(list)
+        }
+        let info: CrateListingV2 = try!(toml::decode_str(&contents)
+                                            .chain_error(|| {
+            internal("invalid TOML found for metadata")
+        }));
+        let info = info.v2.into_iter().collect::<Vec<_>>();
+        for (id, entry) in list.v2.iter_mut() {
+            // What's recorded for another revision of a git repository was
+            // left behind by an older version of Cargo reinstalling it
+            let prev = info.iter().find(|&&(ref prev, _)| {
+                prev == id &&
+                prev.source_id().precise() == id.source_id().precise()
+            });
+            if let Some(&(_, ref prev)) = prev {
+                entry.version_req = prev.version_req.clone();
+                entry.features = prev.features.clone();
+                entry.no_default_features = prev.no_default_features;
+                entry.profile = prev.profile.clone();
             }
         }
+        Ok(list)
     }).chain_error(|| {
         human("failed to parse crate metadata")
     })
 }
 
-fn write_crate_list(mut file: &File, listing: CrateListingV1) -> CargoResult<()> {
+fn read_file(mut file: &File) -> CargoResult<String> {
+    let mut contents = String::new();
+    try!(file.seek(SeekFrom::Start(0)));
+    try!(file.read_to_string(&mut contents));
+    Ok(contents)
+}
+
+fn write_crate_list(metadata: &Metadata, listing: CrateListingV2)
+                    -> CargoResult<()> {
     (|| -> CargoResult<_> {
-        try!(file.seek(SeekFrom::Start(0)));
-        try!(file.set_len(0));
-        let data = toml::encode_str::<CrateListing>(&CrateListing::V1(listing));
-        try!(file.write_all(data.as_bytes()));
+        let v1 = listing.v2.iter().map(|(id, info)| {
+            (id.clone(), info.bins.clone())
+        }).collect();
+        let v1 = CrateListing::V1(CrateListingV1 { v1: v1 });
+        try!(write_file(metadata.list.file(), &toml::encode_str(&v1)));
+        try!(write_file(metadata.info.file(), &toml::encode_str(&listing)));
         Ok(())
     }).chain_error(|| {
         human("failed to write crate metadata")
     })
 }
 
+fn write_file(mut file: &File, data: &str) -> CargoResult<()> {
+    try!(file.seek(SeekFrom::Start(0)));
+    try!(file.set_len(0));
+    try!(file.write_all(data.as_bytes()));
+    Ok(())
+}
+
 pub fn install_list(dst: Option<&str>, config: &Config) -> CargoResult<()> {
     let dst = try!(resolve_root(dst, config));
     let dst = try!(metadata(config, &dst));
-    let list = try!(read_crate_list(dst.file()));
+    let list = try!(read_crate_list(&dst));
     let mut shell = config.shell();
     let out = shell.out();
-    for (k, v) in list.v1.iter() {
+    for (k, v) in list.v2.iter() {
         try!(writeln!(out, "{}:", k));
-        for bin in v {
+        for bin in v.bins.iter() {
             try!(writeln!(out, "    {}", bin));
         }
     }
@@ -425,17 +1015,17 @@ pub fn uninstall(root: Option<&str>,
                  config: &Config) -> CargoResult<()> {
     let root = try!(resolve_root(root, config));
     let crate_metadata = try!(metadata(config, &root));
-    let mut metadata = try!(read_crate_list(crate_metadata.file()));
+    let mut metadata = try!(read_crate_list(&crate_metadata));
     let mut to_remove = Vec::new();
     {
-        let result = try!(PackageIdSpec::query_str(spec, metadata.v1.keys()))
+        let result = try!(PackageIdSpec::query_str(spec, metadata.v2.keys()))
                                         .clone();
-        let mut installed = match metadata.v1.entry(result.clone()) {
+        let mut installed = match metadata.v2.entry(result.clone()) {
             Entry::Occupied(e) => e,
             Entry::Vacant(..) => panic!("entry not found: {}", result),
         };
         let dst = crate_metadata.parent().join("bin");
-        for bin in installed.get() {
+        for bin in installed.get().bins.iter() {
             let bin = dst.join(bin);
             if fs::metadata(&bin).is_err() {
                 bail!("corrupt metadata, `{}` does not exist when it should",
@@ -452,25 +1042,25 @@ pub fn uninstall(root: Option<&str>,
         }).collect::<Vec<_>>();
 
         for bin in bins.iter() {
-            if !installed.get().contains(bin) {
+            if !installed.get().bins.contains(bin) {
                 bail!("binary `{}` not installed as part of `{}`", bin, result)
             }
         }
 
         if bins.is_empty() {
-            to_remove.extend(installed.get().iter().map(|b| dst.join(b)));
-            installed.get_mut().clear();
+            to_remove.extend(installed.get().bins.iter().map(|b| dst.join(b)));
+            installed.get_mut().bins.clear();
         } else {
             for bin in bins.iter() {
                 to_remove.push(dst.join(bin));
-                installed.get_mut().remove(bin);
+                installed.get_mut().bins.remove(bin);
             }
         }
-        if installed.get().is_empty() {
+        if installed.get().bins.is_empty() {
             installed.remove();
         }
     }
-    try!(write_crate_list(crate_metadata.file(), metadata));
+    try!(write_crate_list(&crate_metadata, metadata));
     for bin in to_remove {
         try!(config.shell().status("Removing", bin.display()));
         try!(fs::remove_file(bin));
@@ -479,8 +1069,12 @@ pub fn uninstall(root: Option<&str>,
     Ok(())
 }
 
-fn metadata(config: &Config, root: &Filesystem) -> CargoResult<FileLock> {
-    root.open_rw(Path::new(".crates.toml"), config, "crate metadata")
+fn metadata(config: &Config, root: &Filesystem) -> CargoResult<Metadata> {
+    let list = try!(root.open_rw(Path::new(".crates.toml"), config,
+                                 "crate metadata"));
+    let info = try!(root.open_rw(Path::new(".crates-info.toml"), config,
+                                 "crate metadata"));
+    Ok(Metadata { list: list, info: info })
 }
 
 fn resolve_root(flag: Option<&str>,
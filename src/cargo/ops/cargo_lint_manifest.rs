@@ -0,0 +1,225 @@
+//! Lints over the manifests of the workspace members, run by `cargo
+//! lint-manifest`.
+//!
+//! Each lint has a default severity which can be changed for every workspace
+//! with `manifest-lints.<lint>` set to `allow`, `warn` or `deny` in the
+//! configuration. Denied lints make the command fail once all are reported.
+
+use std::collections::HashSet;
+
+use core::{Package, Workspace};
+use core::dependency::Kind;
+use ops::MessageFormat;
+use sources::PathSource;
+use util::{CargoResult, Config, machine_message, paths};
+
+pub struct LintManifestOptions {
+    pub message_format: MessageFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Severity {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match *self {
+            Severity::Allow => "allow",
+            Severity::Warn => "warn",
+            Severity::Deny => "deny",
+        }
+    }
+}
+
+/// The lints known to `cargo lint-manifest`, along with their default
+/// severities.
+const LINTS: &'static [(&'static str, Severity)] = &[
+    ("missing-metadata", Severity::Warn),
+    ("wildcard-dependencies", Severity::Warn),
+    ("duplicate-dependencies", Severity::Warn),
+    ("unused-features", Severity::Warn),
+];
+
+struct Finding {
+    lint: &'static str,
+    message: String,
+}
+
+/// Lints the manifest of every workspace member, reporting what's found on
+/// stderr or, with JSON messages, stdout.
+pub fn lint_manifests(ws: &Workspace, opts: &LintManifestOptions)
+                      -> CargoResult<()> {
+    let config = ws.config();
+    let mut severities = Vec::new();
+    for &(lint, default) in LINTS.iter() {
+        severities.push((lint, try!(severity(config, lint, default))));
+    }
+
+    let mut denied = 0;
+    for pkg in ws.members() {
+        let mut findings = Vec::new();
+        missing_metadata(pkg, &mut findings);
+        wildcard_dependencies(pkg, &mut findings);
+        duplicate_dependencies(pkg, &mut findings);
+        try!(unused_features(config, pkg, &mut findings));
+
+        for finding in findings {
+            let severity = severities.iter().find(|s| s.0 == finding.lint)
+                                     .unwrap().1;
+            if severity == Severity::Allow {
+                continue
+            }
+            if severity == Severity::Deny {
+                denied += 1;
+            }
+            if opts.message_format == MessageFormat::Json {
+                machine_message::emit(machine_message::ManifestLint {
+                    package_id: pkg.package_id(),
+                    lint: finding.lint,
+                    severity: severity.name(),
+                    message: &finding.message,
+                });
+                continue
+            }
+            let msg = format!("{}: {} [{}]", pkg.package_id(), finding.message,
+                              finding.lint);
+            if severity == Severity::Deny {
+                try!(config.shell().error(msg));
+            } else {
+                try!(config.shell().warn(msg));
+            }
+        }
+    }
+    if denied > 0 {
+        bail!("{} denied manifest lint(s) found", denied)
+    }
+    Ok(())
+}
+
+fn severity(config: &Config, lint: &str, default: Severity)
+            -> CargoResult<Severity> {
+    let key = format!("manifest-lints.{}", lint);
+    match try!(config.get_string(&key)) {
+        None => Ok(default),
+        Some(v) => match &v.val[..] {
+            "allow" => Ok(Severity::Allow),
+            "warn" => Ok(Severity::Warn),
+            "deny" => Ok(Severity::Deny),
+            other => {
+                bail!("{} must be `allow`, `warn` or `deny`, but found `{}` \
+                       in {}", key, other, v.definition)
+            }
+        },
+    }
+}
+
+/// Packages which may be published should say what they are, under which
+/// license, and where to find out more.
+fn missing_metadata(pkg: &Package, findings: &mut Vec<Finding>) {
+    if !pkg.publish() {
+        return
+    }
+    let md = pkg.manifest().metadata();
+    let empty = |s: &Option<String>| s.as_ref().map_or(true, |s| s.is_empty());
+    let mut missing = Vec::new();
+    if empty(&md.description) {
+        missing.push("description");
+    }
+    if empty(&md.license) && empty(&md.license_file) {
+        missing.push("license or license-file");
+    }
+    if empty(&md.repository) {
+        missing.push("repository");
+    }
+    for field in missing {
+        findings.push(Finding {
+            lint: "missing-metadata",
+            message: format!("the manifest has no {}", field),
+        });
+    }
+}
+
+/// Any version of a registry dependency, including future breaking ones, is
+/// accepted by a `*` requirement.
+fn wildcard_dependencies(pkg: &Package, findings: &mut Vec<Finding>) {
+    for dep in pkg.dependencies() {
+        if dep.source_id().is_registry() &&
+           dep.version_req().to_string() == "*" {
+            findings.push(Finding {
+                lint: "wildcard-dependencies",
+                message: format!("`{}` accepts any version", dep.name()),
+            });
+        }
+    }
+}
+
+/// The same dependency listed in several sections for the same platforms is
+/// usually left over from moving it between them.
+fn duplicate_dependencies(pkg: &Package, findings: &mut Vec<Finding>) {
+    let deps = pkg.dependencies();
+    for (i, dep) in deps.iter().enumerate() {
+        let first = deps[..i].iter().find(|d| {
+            d.name() == dep.name() && d.kind() != dep.kind() &&
+            d.platform() == dep.platform()
+        });
+        if let Some(first) = first {
+            findings.push(Finding {
+                lint: "duplicate-dependencies",
+                message: format!("`{}` is listed in both {} and {}",
+                                 dep.name(), section(first.kind()),
+                                 section(dep.kind())),
+            });
+        }
+    }
+}
+
+fn section(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Normal => "[dependencies]",
+        Kind::Development => "[dev-dependencies]",
+        Kind::Build => "[build-dependencies]",
+    }
+}
+
+/// Features which enable nothing, which no other feature enables and which no
+/// source file of the package tests for with `feature = "..."` have no effect.
+/// Those enabling other features or those of dependencies are always useful.
+fn unused_features(config: &Config, pkg: &Package, findings: &mut Vec<Finding>)
+                   -> CargoResult<()> {
+    let features = pkg.summary().features();
+    let mut used = features.values().flat_map(|v| v.iter())
+                           .cloned().collect::<HashSet<_>>();
+    used.insert("default".to_string());
+    let mut unused = features.iter().filter(|&(f, enables)| {
+        enables.is_empty() && !used.contains(f)
+    }).map(|(f, _)| f.clone()).collect::<Vec<_>>();
+    if unused.is_empty() {
+        return Ok(())
+    }
+
+    let src = PathSource::new(pkg.root(), pkg.package_id().source_id(),
+                              config);
+    for file in try!(src.list_files(pkg)) {
+        if file.extension().and_then(|e| e.to_str()) != Some("rs") {
+            continue
+        }
+        let contents = match paths::read(&file) {
+            Ok(contents) => contents.replace(" ", ""),
+            Err(..) => continue,
+        };
+        unused.retain(|f| !contents.contains(&format!("feature=\"{}\"", f)));
+    }
+    unused.sort();
+    for feature in unused {
+        findings.push(Finding {
+            lint: "unused-features",
+            message: format!("feature `{}` is never enabled by another \
+                              feature nor tested for in the sources",
+                             feature),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Checks whether a crate name can be claimed on a registry.
+//!
+//! The name is validated against the rules crates.io applies to new crates,
+//! then looked up in the index of the registry, along with the names there
+//! which are easily mistaken for it.
+
+use std::fs;
+use std::path::Path;
+
+use core::{Source, SourceId};
+use ops::cargo_new::{KEYWORDS, STD_CRATES};
+use sources::RegistrySource;
+use util::{CargoResult, ChainError, Config, ToUrl, human, lev_distance};
+
+/// The longest name crates.io accepts.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Names which can't be files on Windows.
+const WINDOWS_RESERVED: &'static [&'static str] = &[
+    "aux", "con", "nul", "prn", "com1", "com2", "com3", "com4", "com5",
+    "com6", "com7", "com8", "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5",
+    "lpt6", "lpt7", "lpt8", "lpt9"];
+
+/// How many similar names are reported at most.
+const MAX_SIMILAR: usize = 10;
+
+pub struct NameCheck {
+    /// Why the registry would reject the name whoever claimed it first
+    pub problems: Vec<String>,
+    /// The crate already published under the name, spelled as in the index
+    pub taken_by: Option<String>,
+    /// Crates whose names are easily mistaken for the name, most similar
+    /// first
+    pub similar: Vec<String>,
+}
+
+impl NameCheck {
+    pub fn available(&self) -> bool {
+        self.problems.is_empty() && self.taken_by.is_none()
+    }
+}
+
+/// Checks `name` against the rules of crates.io and against the names in
+/// the index of the registry at `index`, or of crates.io.
+pub fn name_check(name: &str, config: &Config, index: Option<String>)
+                  -> CargoResult<NameCheck> {
+    let problems = problems(name);
+
+    let sid = match index {
+        Some(index) => {
+            SourceId::for_registry(&try!(index.to_url().map_err(human)))
+        }
+        None => try!(SourceId::for_central(config)),
+    };
+    let mut source = RegistrySource::new(&sid, config);
+    try!(source.update().chain_error(|| {
+        human(format!("failed to update registry {}", sid.url()))
+    }));
+    let index_path = RegistrySource::index_path(config, &sid, None);
+    let mut names = Vec::new();
+    try!(crate_names(&index_path.into_path_unlocked(), &mut names));
+
+    let wanted = normalize(name);
+    let wanted_skeleton = skeleton(name);
+    let taken_by = names.iter().find(|n| normalize(n) == wanted).cloned();
+    let mut similar = names.iter().filter(|n| normalize(n) != wanted)
+                           .filter_map(|n| {
+        let distance = lev_distance(&wanted_skeleton, &skeleton(n));
+        if distance <= 1 {Some((distance, n.clone()))} else {None}
+    }).collect::<Vec<_>>();
+    similar.sort();
+    similar.truncate(MAX_SIMILAR);
+
+    Ok(NameCheck {
+        problems: problems,
+        taken_by: taken_by,
+        similar: similar.into_iter().map(|(_, n)| n).collect(),
+    })
+}
+
+fn problems(name: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    if name.is_empty() {
+        problems.push("the name is empty".to_string());
+        return problems
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        problems.push(format!("the name is longer than {} characters",
+                              MAX_NAME_LENGTH));
+    }
+    if !name.chars().next().unwrap().is_alphabetic() {
+        problems.push("the name doesn't start with a letter".to_string());
+    }
+    let invalid = name.chars().filter(|&c| {
+        !(c.is_alphanumeric() || c == '-' || c == '_') || c as u32 > 0x7f
+    }).collect::<String>();
+    if !invalid.is_empty() {
+        problems.push(format!("the name contains characters other than ASCII \
+                               letters, digits, `-` and `_`: `{}`", invalid));
+    }
+    let lower = name.to_lowercase();
+    if KEYWORDS.contains(&&*lower) {
+        problems.push("the name is a Rust keyword".to_string());
+    }
+    if STD_CRATES.contains(&&*normalize(name)) {
+        problems.push("the name is reserved for a crate shipped with Rust"
+                          .to_string());
+    }
+    if WINDOWS_RESERVED.contains(&&*lower) {
+        problems.push("the name is reserved by Windows".to_string());
+    }
+    problems
+}
+
+/// The spelling of `name` the registry compares names with: case and the
+/// choice between `-` and `_` don't matter.
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace("-", "_")
+}
+
+/// `name` with the characters readers are likely to confuse made the same,
+/// and separators dropped altogether.
+fn skeleton(name: &str) -> String {
+    name.to_lowercase().chars().filter(|&c| c != '-' && c != '_').map(|c| {
+        match c {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '5' => 's',
+            c => c,
+        }
+    }).collect()
+}
+
+/// Collects the names of the crates in the index checked out at `dir`.
+fn crate_names(dir: &Path, names: &mut Vec<String>) -> CargoResult<()> {
+    for entry in try!(fs::read_dir(dir)) {
+        let entry = try!(entry);
+        let path = entry.path();
+        let file_name = entry.file_name();
+        let file_name = match file_name.to_str() {
+            Some(s) => s,
+            None => continue,
+        };
+        // Skips `.git` and `config.json`, which can't be crates
+        if file_name.contains('.') {
+            continue
+        }
+        if try!(entry.file_type()).is_dir() {
+            try!(crate_names(&path, names));
+        } else {
+            names.push(file_name.to_string());
+        }
+    }
+    Ok(())
+}
@@ -1,17 +1,22 @@
 use std::env;
 use std::fs;
+use std::io::{self, Write};
 use std::path::Path;
 use std::collections::BTreeMap;
 
+use glob::Pattern;
+
 use rustc_serialize::{Decodable, Decoder};
 
 use git2::Config as GitConfig;
 
 use term::color::BLACK;
 
-use core::Workspace;
+use core::{Dependency, Source, SourceId, Workspace};
+use sources::RegistrySource;
+use sources::git::GitRemote;
 use util::{GitRepo, HgRepo, CargoResult, human, ChainError, internal};
-use util::{Config, paths};
+use util::{Config, ToUrl, paths};
 
 use toml;
 
@@ -23,6 +28,11 @@ pub struct NewOptions<'a> {
     pub bin: bool,
     pub path: &'a str,
     pub name: Option<&'a str>,
+    /// A git repository or a local directory the package is copied from,
+    /// instead of being generated
+    pub template: Option<&'a str>,
+    /// Whether to warn if the name is already taken on crates.io
+    pub check_registry: bool,
 }
 
 struct SourceFileInformation {
@@ -33,6 +43,10 @@ struct SourceFileInformation {
 
 struct MkOptions<'a> {
     version_control: Option<VersionControl>,
+    /// The version control system of the repository the package is already
+    /// part of, whose ignore file is added to instead of a new repository
+    /// being created
+    existing_vcs: Option<VersionControl>,
     path: &'a Path,
     name: &'a str,
     source_files: Vec<SourceFileInformation>,
@@ -57,8 +71,20 @@ struct CargoNewConfig {
     name: Option<String>,
     email: Option<String>,
     version_control: Option<VersionControl>,
+    license: Option<String>,
+}
+
+/// The `cargo-template.toml` at the root of a template.
+#[derive(RustcDecodable)]
+struct TemplateManifest {
+    /// Globs of the files which aren't copied into new packages
+    exclude: Option<Vec<String>>,
+    /// Placeholders whose values are asked for, and the questions asking
+    prompts: Option<BTreeMap<String, String>>,
 }
 
+const TEMPLATE_MANIFEST: &'static str = "cargo-template.toml";
+
 fn get_name<'a>(path: &'a Path, opts: &'a NewOptions, config: &Config) -> CargoResult<&'a str> {
     if let Some(name) = opts.name {
         return Ok(name);
@@ -88,22 +114,32 @@ fn get_name<'a>(path: &'a Path, opts: &'a NewOptions, config: &Config) -> CargoR
     }
 }
 
-fn check_name(name: &str) -> CargoResult<()> {
-
-    // Ban keywords + test list found at
-    // https://doc.rust-lang.org/grammar.html#keywords
-    let blacklist = ["abstract", "alignof", "as", "become", "box",
-        "break", "const", "continue", "crate", "do",
-        "else", "enum", "extern", "false", "final",
-        "fn", "for", "if", "impl", "in",
-        "let", "loop", "macro", "match", "mod",
-        "move", "mut", "offsetof", "override", "priv",
-        "proc", "pub", "pure", "ref", "return",
-        "self", "sizeof", "static", "struct",
-        "super", "test", "trait", "true", "type", "typeof",
-        "unsafe", "unsized", "use", "virtual", "where",
-        "while", "yield"];
-    if blacklist.contains(&name) {
+// Keywords + test list found at
+// https://doc.rust-lang.org/grammar.html#keywords
+pub const KEYWORDS: &'static [&'static str] = &[
+    "abstract", "alignof", "as", "become", "box",
+    "break", "const", "continue", "crate", "do",
+    "else", "enum", "extern", "false", "final",
+    "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod",
+    "move", "mut", "offsetof", "override", "priv",
+    "proc", "pub", "pure", "ref", "return",
+    "self", "sizeof", "static", "struct",
+    "super", "test", "trait", "true", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where",
+    "while", "yield"];
+
+/// Crates shipped with Rust, whose names are valid but likely to cause
+/// trouble later on.
+pub const STD_CRATES: &'static [&'static str] = &[
+    "alloc", "collections", "core", "libc", "proc_macro", "rustc", "std",
+    "std_unicode"];
+
+fn check_name(name: &str, config: &Config, check_registry: bool)
+              -> CargoResult<()> {
+
+    // Ban keywords
+    if KEYWORDS.contains(&name) {
         bail!("The name `{}` cannot be used as a crate name\n\
                use --name to override crate name",
                name)
@@ -116,9 +152,53 @@ fn check_name(name: &str) -> CargoResult<()> {
                use --name to override crate name",
               c, name)
     }
+    if name.chars().next().map_or(false, |c| c.is_numeric()) {
+        bail!("The name `{}` cannot be used as a crate name as it starts \
+               with a digit\n\
+               use --name to override crate name",
+              name)
+    }
+
+    if STD_CRATES.contains(&&*name.replace("-", "_")) {
+        try!(config.shell().warn(format!("the name `{}` is the name of a \
+                                          crate shipped with Rust, which \
+                                          the package may be confused with",
+                                         name)));
+    }
+    if name.chars().any(|c| c as u32 > 0x7f) {
+        try!(config.shell().warn(format!("the name `{}` contains \
+                                          characters other than ASCII, \
+                                          which crates.io doesn't accept",
+                                         name)));
+    }
+    if check_registry && try!(on_registry(name, config)) {
+        try!(config.shell().warn(format!("a crate named `{}` already exists \
+                                          on crates.io, the package can't be \
+                                          published under this name", name)));
+    }
     Ok(())
 }
 
+/// Whether a crate named `name` was published to crates.io, in any of the
+/// spellings the registry considers the same.
+fn on_registry(name: &str, config: &Config) -> CargoResult<bool> {
+    let sid = try!(SourceId::for_central(config));
+    let mut source = RegistrySource::new(&sid, config);
+    try!(source.update().chain_error(|| {
+        human("failed to update the index of crates.io to look the name up")
+    }));
+    let mut spellings = vec![name.to_string(), name.replace("-", "_"),
+                             name.replace("_", "-")];
+    spellings.dedup();
+    for spelling in spellings {
+        let dep = try!(Dependency::parse(&spelling, None, &sid));
+        if !try!(source.query(&dep)).is_empty() {
+            return Ok(true)
+        }
+    }
+    Ok(false)
+}
+
 fn detect_source_paths_and_types(project_path : &Path,
                                  project_name: &str,
                                  detected_files: &mut Vec<SourceFileInformation>,
@@ -171,7 +251,7 @@ fn detect_source_paths_and_types(project_path : &Path,
             }
             H::Detect => {
                 let content = try!(paths::read(&path.join(pp.clone())));
-                let isbin = content.contains("fn main");
+                let isbin = defines_main(&content);
                 SourceFileInformation {
                     relative_path: pp,
                     target_name: project_name.to_string(),
@@ -182,6 +262,58 @@ fn detect_source_paths_and_types(project_path : &Path,
         detected_files.push(sfi);
     }
 
+    // Any other file at the top level or in `src` with a `main` function is
+    // another binary, named after the file, unless it's a module of the crate
+    // next to it
+    for dir in &["", "src"] {
+        let entries = match fs::read_dir(path.join(dir)) {
+            Ok(entries) => entries,
+            Err(..) => continue,
+        };
+        let mut modules = Vec::new();
+        for root in &["main.rs", "lib.rs"] {
+            if let Ok(content) = paths::read(&path.join(dir).join(root)) {
+                modules.extend(declared_modules(&content));
+            }
+        }
+        let mut files = entries.filter_map(|e| e.ok()).map(|e| e.path())
+                               .filter(|p| p.is_file())
+                               .filter(|p| p.extension() == Some("rs".as_ref()))
+                               .collect::<Vec<_>>();
+        files.sort();
+        for file in files {
+            let file_name = file.file_name().unwrap().to_string_lossy();
+            let relative_path = if dir.is_empty() {
+                file_name.to_string()
+            } else {
+                format!("{}/{}", dir, file_name)
+            };
+            if relative_path == "build.rs" ||
+               detected_files.iter().any(|f| f.relative_path == relative_path) {
+                continue
+            }
+            let stem = file.file_stem().unwrap().to_string_lossy();
+            if modules.iter().any(|m| *m == stem) ||
+               !defines_main(&try!(paths::read(&file))) {
+                continue
+            }
+            if let Some(other) = detected_files.iter().find(|f| {
+                f.bin && f.target_name == stem
+            }) {
+                bail!("multiple possible sources found for the binary `{}`:
+  {}
+  {}
+cannot automatically generate Cargo.toml as its path would be ambiguous",
+                      stem, other.relative_path, relative_path);
+            }
+            detected_files.push(SourceFileInformation {
+                relative_path: relative_path,
+                target_name: stem.into_owned(),
+                bin: true,
+            });
+        }
+    }
+
     // Check for duplicate lib attempt
 
     let mut previous_lib_relpath : Option<&str> = None;
@@ -212,6 +344,28 @@ cannot automatically generate Cargo.toml as the main target would be ambiguous",
     Ok(())
 }
 
+/// Whether `content` defines a `main` function as an item of the crate.
+fn defines_main(content: &str) -> bool {
+    content.lines().any(|line| {
+        let line = if line.starts_with("pub ") {&line[4..]} else {line};
+        line.starts_with("fn main(") || line.starts_with("fn main (")
+    })
+}
+
+/// Returns the names of the modules `content` declares, whose code is in
+/// files of their own.
+fn declared_modules(content: &str) -> Vec<String> {
+    content.lines().filter_map(|line| {
+        let line = line.trim();
+        let line = if line.starts_with("pub ") {&line[4..]} else {line};
+        if line.starts_with("mod ") && line.ends_with(";") {
+            Some(line[4..line.len() - 1].trim().to_string())
+        } else {
+            None
+        }
+    }).collect()
+}
+
 fn plan_new_source_file(bin: bool, project_name: String) -> SourceFileInformation {
     if bin {
         SourceFileInformation {
@@ -236,17 +390,22 @@ pub fn new(opts: NewOptions, config: &Config) -> CargoResult<()> {
     }
 
     let name = try!(get_name(&path, &opts, config));
-    try!(check_name(name));
+    try!(check_name(name, config, opts.check_registry));
 
     let mkopts = MkOptions {
         version_control: opts.version_control,
+        existing_vcs: None,
         path: &path,
         name: name,
         source_files: vec![plan_new_source_file(opts.bin, name.to_string())],
         bin: opts.bin,
     };
 
-    mk(config, &mkopts).chain_error(|| {
+    let res = match opts.template {
+        Some(template) => mk_from_template(config, &mkopts, template),
+        None => mk(config, &mkopts),
+    };
+    res.chain_error(|| {
         human(format!("Failed to create project `{}` at `{}`",
                       name, path.display()))
     })
@@ -254,6 +413,9 @@ pub fn new(opts: NewOptions, config: &Config) -> CargoResult<()> {
 
 pub fn init(opts: NewOptions, config: &Config) -> CargoResult<()> {
     let path = config.cwd().join(opts.path);
+    if opts.template.is_some() {
+        bail!("templates can only be used by `cargo new`")
+    }
 
     let cargotoml_path = path.join("Cargo.toml");
     if fs::metadata(&cargotoml_path).is_ok() {
@@ -261,7 +423,7 @@ pub fn init(opts: NewOptions, config: &Config) -> CargoResult<()> {
     }
 
     let name = try!(get_name(&path, &opts, config));
-    try!(check_name(name));
+    try!(check_name(name, config, opts.check_registry));
 
     let mut src_paths_types = vec![];
 
@@ -276,6 +438,7 @@ pub fn init(opts: NewOptions, config: &Config) -> CargoResult<()> {
     }
 
     let mut version_control = opts.version_control;
+    let mut existing_vcs = None;
 
     if version_control == None {
         let mut num_detected_vsces = 0;
@@ -298,10 +461,20 @@ pub fn init(opts: NewOptions, config: &Config) -> CargoResult<()> {
                               filled in as a result, \
                               specify --vcs to override detection");
         }
+
+        // The directory may also be part of a bigger repository
+        if num_detected_vsces == 0 {
+            if GitRepo::discover(&path, config.cwd()).is_ok() {
+                existing_vcs = Some(VersionControl::Git);
+            } else if HgRepo::discover(&path, config.cwd()).is_ok() {
+                existing_vcs = Some(VersionControl::Hg);
+            }
+        }
     }
 
     let mkopts = MkOptions {
         version_control: version_control,
+        existing_vcs: existing_vcs,
         path: &path,
         name: name,
         bin: src_paths_types.iter().any(|x|x.bin),
@@ -336,47 +509,8 @@ fn mk(config: &Config, opts: &MkOptions) -> CargoResult<()> {
     let path = opts.path;
     let name = opts.name;
     let cfg = try!(global_config(config));
-    let mut ignore = "target\n".to_string();
-    let in_existing_vcs_repo = existing_vcs_repo(path.parent().unwrap(), config.cwd());
-    if !opts.bin {
-        ignore.push_str("Cargo.lock\n");
-    }
-
-    let vcs = match (opts.version_control, cfg.version_control, in_existing_vcs_repo) {
-        (None, None, false) => VersionControl::Git,
-        (None, Some(option), false) => option,
-        (Some(option), _, _) => option,
-        (_, _, true) => VersionControl::NoVcs,
-    };
-
-    match vcs {
-        VersionControl::Git => {
-            if !fs::metadata(&path.join(".git")).is_ok() {
-                try!(GitRepo::init(path, config.cwd()));
-            }
-            try!(paths::append(&path.join(".gitignore"), ignore.as_bytes()));
-        },
-        VersionControl::Hg => {
-            if !fs::metadata(&path.join(".hg")).is_ok() {
-                try!(HgRepo::init(path, config.cwd()));
-            }
-            try!(paths::append(&path.join(".hgignore"), ignore.as_bytes()));
-        },
-        VersionControl::NoVcs => {
-            try!(fs::create_dir_all(path));
-        },
-    };
-
-    let (author_name, email) = try!(discover_author());
-    // Hoo boy, sure glad we've got exhaustivenes checking behind us.
-    let author = match (cfg.name, cfg.email, author_name, email) {
-        (Some(name), Some(email), _, _) |
-        (Some(name), None, _, Some(email)) |
-        (None, Some(email), name, _) |
-        (None, None, name, Some(email)) => format!("{} <{}>", name, email),
-        (Some(name), None, _, None) |
-        (None, None, name, None) => name,
-    };
+    try!(init_vcs(config, opts, &cfg));
+    let author = try!(configured_author(&cfg));
 
     let mut cargotoml_path_specifier = String::new();
 
@@ -456,6 +590,217 @@ mod tests {
     Ok(())
 }
 
+/// Creates the directory of the new package, in a new repository of the
+/// configured version control system unless it's already in one.
+fn init_vcs(config: &Config, opts: &MkOptions, cfg: &CargoNewConfig)
+            -> CargoResult<()> {
+    let path = opts.path;
+    let mut ignore = "target\n".to_string();
+    let in_existing_vcs_repo = existing_vcs_repo(path.parent().unwrap(), config.cwd());
+    if !opts.bin {
+        ignore.push_str("Cargo.lock\n");
+    }
+
+    match opts.existing_vcs {
+        Some(VersionControl::Git) => {
+            return append_ignore(&path.join(".gitignore"), &ignore)
+        }
+        Some(VersionControl::Hg) => {
+            return append_ignore(&path.join(".hgignore"), &ignore)
+        }
+        Some(VersionControl::NoVcs) | None => {}
+    }
+
+    let vcs = match (opts.version_control, cfg.version_control, in_existing_vcs_repo) {
+        (None, None, false) => VersionControl::Git,
+        (None, Some(option), false) => option,
+        (Some(option), _, _) => option,
+        (_, _, true) => VersionControl::NoVcs,
+    };
+
+    match vcs {
+        VersionControl::Git => {
+            if !fs::metadata(&path.join(".git")).is_ok() {
+                try!(GitRepo::init(path, config.cwd()));
+            }
+            try!(append_ignore(&path.join(".gitignore"), &ignore));
+        },
+        VersionControl::Hg => {
+            if !fs::metadata(&path.join(".hg")).is_ok() {
+                try!(HgRepo::init(path, config.cwd()));
+            }
+            try!(append_ignore(&path.join(".hgignore"), &ignore));
+        },
+        VersionControl::NoVcs => {
+            try!(fs::create_dir_all(path));
+        },
+    };
+    Ok(())
+}
+
+/// Appends the lines of `ignore` which aren't in the ignore `file` yet.
+fn append_ignore(file: &Path, ignore: &str) -> CargoResult<()> {
+    let existing = if file.exists() {
+        try!(paths::read(file))
+    } else {
+        String::new()
+    };
+    let mut missing = ignore.lines().filter(|line| {
+        !existing.lines().any(|e| e.trim().trim_matches('/') == *line)
+    }).map(|line| format!("{}\n", line)).collect::<String>();
+    if missing.is_empty() {
+        return Ok(())
+    }
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        missing.insert(0, '\n');
+    }
+    paths::append(file, missing.as_bytes())
+}
+
+fn configured_author(cfg: &CargoNewConfig) -> CargoResult<String> {
+    let (author_name, email) = try!(discover_author());
+    // Hoo boy, sure glad we've got exhaustivenes checking behind us.
+    Ok(match (cfg.name.clone(), cfg.email.clone(), author_name, email) {
+        (Some(name), Some(email), _, _) |
+        (Some(name), None, _, Some(email)) |
+        (None, Some(email), name, _) |
+        (None, None, name, Some(email)) => format!("{} <{}>", name, email),
+        (Some(name), None, _, None) |
+        (None, None, name, None) => name,
+    })
+}
+
+/// Creates the package by copying the `template` directory, or the default
+/// branch of the `template` git repository, into it.
+///
+/// Placeholders like `{{name}}` are replaced in the paths and contents of the
+/// files copied with the name of the package, its author (`{{authors}}`), its
+/// license (`{{license}}`), the name of its crate (`{{crate_name}}`) or the
+/// answers to the prompts of the template's `cargo-template.toml`.
+fn mk_from_template(config: &Config, opts: &MkOptions, template: &str)
+                    -> CargoResult<()> {
+    let cfg = try!(global_config(config));
+
+    // A checkout of a git template only lives as long as this temporary
+    // directory
+    let td = try!(config.temp_dir("cargo-template"));
+    let local = config.cwd().join(template);
+    let root = if local.is_dir() {
+        local
+    } else {
+        let url = try!(template.to_url().map_err(|e| {
+            human(format!("template `{}` is neither a directory nor a valid \
+                           git URL: {}", template, e))
+        }));
+        try!(config.shell().status("Fetching", format!("template {}", url)));
+        let db = try!(GitRemote::new(&url).checkout(&td.path().join("db"),
+                                                     config));
+        let rev = try!(db.default_rev(config));
+        try!(db.copy_to(rev, &td.path().join("checkout"), config));
+        td.path().join("checkout")
+    };
+    if !root.join("Cargo.toml").is_file() {
+        bail!("template `{}` has no `Cargo.toml`", template)
+    }
+
+    let manifest_path = root.join(TEMPLATE_MANIFEST);
+    let manifest = if manifest_path.is_file() {
+        let contents = try!(paths::read(&manifest_path));
+        try!(toml::decode_str(&contents).chain_error(|| {
+            human(format!("failed to parse `{}`", manifest_path.display()))
+        }))
+    } else {
+        TemplateManifest { exclude: None, prompts: None }
+    };
+    let mut exclude = Vec::new();
+    for glob in manifest.exclude.unwrap_or(Vec::new()) {
+        exclude.push(try!(Pattern::new(&glob).map_err(|e| {
+            human(format!("invalid glob `{}` in `{}`: {}", glob,
+                          manifest_path.display(), e))
+        })));
+    }
+
+    let mut values = BTreeMap::new();
+    for (key, question) in manifest.prompts.unwrap_or(BTreeMap::new()) {
+        let mut stderr = io::stderr();
+        try!(write!(stderr, "{}: ", question));
+        try!(stderr.flush());
+        let mut answer = String::new();
+        try!(io::stdin().read_line(&mut answer).chain_error(|| {
+            human("failed to read stdin")
+        }));
+        values.insert(key, answer.trim().to_string());
+    }
+    values.insert("name".to_string(), opts.name.to_string());
+    values.insert("crate_name".to_string(), opts.name.replace("-", "_"));
+    values.insert("authors".to_string(), try!(configured_author(&cfg)));
+    values.insert("license".to_string(),
+                  cfg.license.clone().unwrap_or(String::new()));
+
+    try!(init_vcs(config, opts, &cfg));
+    try!(copy_template(&root, &root, opts.path, &exclude, &values));
+
+    if let Err(e) = Workspace::new(&opts.path.join("Cargo.toml"), config) {
+        let msg = format!("compiling this new crate may not work due to invalid \
+                           workspace configuration\n\n{}", e);
+        try!(config.shell().warn(msg));
+    }
+    Ok(())
+}
+
+/// Copies the files of the `dir` directory of the template at `root` into
+/// `dst`, except for those which are excluded.
+fn copy_template(root: &Path, dir: &Path, dst: &Path, exclude: &[Pattern],
+                 values: &BTreeMap<String, String>) -> CargoResult<()> {
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+        let relative = path.strip_prefix(root).unwrap();
+        if relative == Path::new(".git") || relative == Path::new(".hg") ||
+           relative == Path::new(TEMPLATE_MANIFEST) ||
+           exclude.iter().any(|p| p.matches_path(relative)) {
+            continue
+        }
+        let name = path.file_name().unwrap().to_string_lossy();
+        let target = dst.join(substitute(&name, values));
+        if path.is_dir() {
+            try!(fs::create_dir_all(&target));
+            try!(copy_template(root, &path, &target, exclude, values));
+            continue
+        }
+        let contents = try!(paths::read_bytes(&path));
+        let contents = match String::from_utf8(contents) {
+            Ok(s) => substitute(&s, values).into_bytes(),
+            // Binary files are copied as they are
+            Err(e) => e.into_bytes(),
+        };
+        try!(paths::write(&target, &contents));
+    }
+    Ok(())
+}
+
+/// Replaces the `{{key}}` placeholders of `s` whose key has a value, leaving
+/// the others alone.
+fn substitute(s: &str, values: &BTreeMap<String, String>) -> String {
+    let mut ret = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        ret.push_str(&rest[..start]);
+        rest = &rest[start + 2..];
+        let value = rest.find("}}").and_then(|end| {
+            values.get(rest[..end].trim()).map(|v| (v, end))
+        });
+        match value {
+            Some((v, end)) => {
+                ret.push_str(v);
+                rest = &rest[end + 2..];
+            }
+            None => ret.push_str("{{"),
+        }
+    }
+    ret.push_str(rest);
+    ret
+}
+
 fn get_environment_variable(variables: &[&str] ) -> Option<String>{
     variables.iter()
              .filter_map(|var| env::var(var).ok())
@@ -495,6 +840,7 @@ fn global_config(config: &Config) -> CargoResult<CargoNewConfig> {
     let name = try!(config.get_string("cargo-new.name")).map(|s| s.val);
     let email = try!(config.get_string("cargo-new.email")).map(|s| s.val);
     let vcs = try!(config.get_string("cargo-new.vcs"));
+    let license = try!(config.get_string("cargo-new.license")).map(|s| s.val);
 
     let vcs = match vcs.as_ref().map(|p| (&p.val[..], &p.definition)) {
         Some(("git", _)) => Some(VersionControl::Git),
@@ -511,6 +857,7 @@ fn global_config(config: &Config) -> CargoResult<CargoNewConfig> {
         name: name,
         email: email,
         version_control: vcs,
+        license: license,
     })
 }
 
@@ -1,9 +1,16 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
 use rustc_serialize::{Encodable, Encoder};
+use rustc_serialize::json;
 
 use core::resolver::Resolve;
-use core::{Package, PackageId, Workspace};
-use ops;
-use util::CargoResult;
+use core::{Package, PackageId, PackageSet, Workspace};
+use ops::{self, BuildScriptInfo};
+use util::{self, CargoResult, paths};
+use util::config::ConfigValue;
 
 const VERSION: u32 = 1;
 
@@ -12,6 +19,9 @@ pub struct OutputMetadataOptions {
     pub no_default_features: bool,
     pub no_deps: bool,
     pub version: u32,
+    /// Whether to include what build scripts told Cargo the last time they
+    /// ran
+    pub build_scripts: bool,
 }
 
 /// Loads the manifest, resolves the dependencies of the project to the concrete
@@ -19,22 +29,61 @@ pub struct OutputMetadataOptions {
 /// format to stdout.
 pub fn output_metadata(ws: &Workspace,
                        opt: &OutputMetadataOptions) -> CargoResult<ExportInfo> {
+    try!(check_version(opt));
+    let info = if opt.no_deps {
+        try!(metadata_no_deps(ws, opt))
+    } else {
+        try!(metadata_full(ws, opt))
+    };
+    finish(ws, opt, info)
+}
+
+/// Like `output_metadata`, for a workspace whose dependencies were already
+/// resolved with the features of `opt`.
+pub fn output_resolved_metadata(ws: &Workspace,
+                                packages: &PackageSet,
+                                resolve: &Resolve,
+                                opt: &OutputMetadataOptions)
+                                -> CargoResult<ExportInfo> {
+    try!(check_version(opt));
+    let info = if opt.no_deps {
+        try!(metadata_no_deps(ws, opt))
+    } else {
+        try!(metadata_resolved(packages, resolve.clone()))
+    };
+    finish(ws, opt, info)
+}
+
+fn check_version(opt: &OutputMetadataOptions) -> CargoResult<()> {
     if opt.version != VERSION {
         bail!("metadata version {} not supported, only {} is currently supported",
               opt.version, VERSION);
     }
-    if opt.no_deps {
-        metadata_no_deps(ws, opt)
-    } else {
-        metadata_full(ws, opt)
+    Ok(())
+}
+
+fn finish(ws: &Workspace, opt: &OutputMetadataOptions, mut info: ExportInfo)
+          -> CargoResult<ExportInfo> {
+    if opt.build_scripts {
+        info.build_scripts = Some(build_script_files(ws).iter().flat_map(|f| {
+            ops::read_build_script_info(f)
+        }).collect());
+    }
+    for pkg in info.packages.iter() {
+        try!(pkg.check_unicode_paths());
     }
+    Ok(info)
 }
 
 fn metadata_no_deps(ws: &Workspace,
                     _opt: &OutputMetadataOptions) -> CargoResult<ExportInfo> {
     Ok(ExportInfo {
-        packages: vec![try!(ws.current()).clone()],
+        packages: match ws.current_opt() {
+            Some(pkg) => vec![pkg.clone()],
+            None => ws.members().cloned().collect(),
+        },
         resolve: None,
+        build_scripts: None,
         version: VERSION,
     })
 }
@@ -44,9 +93,14 @@ fn metadata_full(ws: &Workspace,
     let deps = try!(ops::resolve_dependencies(ws,
                                               None,
                                               opt.features.clone(),
-                                              opt.no_default_features));
+                                              opt.no_default_features,
+                                              &[]));
     let (packages, resolve) = deps;
+    metadata_resolved(&packages, resolve)
+}
 
+fn metadata_resolved(packages: &PackageSet, resolve: Resolve)
+                     -> CargoResult<ExportInfo> {
     let packages = try!(packages.package_ids()
                                 .map(|i| packages.get(i).map(|p| p.clone()))
                                 .collect());
@@ -54,17 +108,207 @@ fn metadata_full(ws: &Workspace,
     Ok(ExportInfo {
         packages: packages,
         resolve: Some(MetadataResolve(resolve)),
+        build_scripts: None,
         version: VERSION,
     })
 }
 
-#[derive(RustcEncodable)]
+/// Returns the `build-scripts.json` files written by builds of the workspace,
+/// one for each output directory such as `target/debug`.
+fn build_script_files(ws: &Workspace) -> Vec<PathBuf> {
+    let target_dir = ws.target_dir().into_path_unlocked();
+    let mut files = match fs::read_dir(&target_dir) {
+        Ok(entries) => {
+            entries.filter_map(|e| e.ok())
+                   .map(|e| e.path().join("build-scripts.json"))
+                   .filter(|p| p.is_file())
+                   .collect::<Vec<_>>()
+        }
+        Err(..) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+/// Like `output_metadata`, but returns the result encoded as JSON.
+///
+/// Editors tend to run `cargo metadata` over and over again, so the result is
+/// cached in the target directory along with a hash of every input it was
+/// derived from: the manifests of all path packages and which of the files
+/// their targets are inferred from exist, the lock file, the members of the
+/// workspace and the configuration. Editing sources doesn't change any of
+/// those, so as long as none of them changed, later invocations with the same
+/// options return the cached result without resolving anything.
+pub fn output_metadata_cached(ws: &Workspace, opt: &OutputMetadataOptions)
+                              -> CargoResult<String> {
+    let key = try!(cache_key(ws, opt));
+    let loc = ws.target_dir().join("metadata").into_path_unlocked()
+                .join(format!("{}.json", key));
+
+    if let Ok(contents) = paths::read(&loc) {
+        if let Ok(entry) = json::decode::<CacheEntry>(&contents) {
+            let fresh = entry.inputs.iter().all(|&(ref path, read, ref hash)| {
+                input_hash(Path::new(path), read) == *hash
+            });
+            if fresh {
+                return Ok(entry.output)
+            }
+        }
+    }
+
+    let info = try!(output_metadata(ws, opt));
+    let output = try!(json::encode(&info));
+
+    // Whether each input is read or only checked for existence
+    let mut inputs = vec![(ws.root().join("Cargo.toml"), true),
+                          (ws.lockfile(), true)];
+    for pkg in info.packages.iter() {
+        if !pkg.package_id().source_id().is_path() {
+            continue
+        }
+        // Targets are inferred from the files present in these locations
+        let root = pkg.root();
+        inputs.push((pkg.manifest_path().to_path_buf(), true));
+        for layout in ["build.rs", "src/lib.rs", "src/main.rs", "src/bin",
+                       "examples", "tests", "benches"].iter() {
+            inputs.push((root.join(layout), false));
+        }
+    }
+    if opt.build_scripts {
+        // Builds in new output directories are only picked up by the cache
+        // when the target directory itself is an input
+        inputs.push((ws.target_dir().into_path_unlocked(), false));
+        inputs.extend(build_script_files(ws).into_iter().map(|f| (f, true)));
+    }
+    let entry = CacheEntry {
+        inputs: inputs.iter().map(|&(ref path, read)| {
+            (path.display().to_string(), read, input_hash(path, read))
+        }).collect(),
+        output: output.clone(),
+    };
+
+    // The cache is only an optimization, so failing to write it is fine
+    if fs::create_dir_all(loc.parent().unwrap()).is_ok() {
+        let _ = paths::write(&loc, json::encode(&entry).unwrap().as_bytes());
+    } else {
+        debug!("failed to create the metadata cache in {}", loc.display());
+    }
+    Ok(output)
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct CacheEntry {
+    inputs: Vec<(String, bool, String)>,
+    output: String,
+}
+
+/// Returns the name of the cache entry for `opt`, which also covers the
+/// configuration as it's not tied to files in a predictable location, and the
+/// members of the workspace as globs in `workspace.members` may match new
+/// directories.
+fn cache_key(ws: &Workspace, opt: &OutputMetadataOptions)
+             -> CargoResult<String> {
+    let mut features = opt.features.clone();
+    features.sort();
+    let env = env::vars().filter(|&(ref k, _)| k.starts_with("CARGO_"))
+                         .collect::<BTreeMap<_, _>>();
+    let mut config = String::new();
+    render_config(try!(ws.config().values()), &mut config);
+    let members = ws.members().map(|p| p.manifest_path()).collect::<Vec<_>>();
+    Ok(util::short_hash(&(env!("CARGO_PKG_VERSION"),
+                          opt.version,
+                          features,
+                          opt.no_default_features,
+                          opt.no_deps,
+                          opt.build_scripts,
+                          ws.root(),
+                          ws.current_opt().map(|p| p.manifest_path()),
+                          ws.lockfile(),
+                          members,
+                          env,
+                          config)))
+}
+
+/// Renders configuration values in a stable order, to be hashed.
+fn render_config(values: &HashMap<String, ConfigValue>, out: &mut String) {
+    let mut keys = values.keys().collect::<Vec<_>>();
+    keys.sort();
+    for key in keys {
+        out.push_str(key);
+        out.push('=');
+        match values[key] {
+            ConfigValue::Table(ref table, _) => {
+                out.push('{');
+                render_config(table, out);
+                out.push('}');
+            }
+            ConfigValue::Integer(i, _) => out.push_str(&i.to_string()),
+            ConfigValue::String(ref s, _) => out.push_str(&format!("{:?}", s)),
+            ConfigValue::Boolean(b, _) => out.push_str(&b.to_string()),
+            ConfigValue::List(ref list, _) => {
+                let list = list.iter().map(|&(ref s, _)| s).collect::<Vec<_>>();
+                out.push_str(&format!("{:?}", list));
+            }
+        }
+        out.push(';');
+    }
+}
+
+/// Hashes the contents of a file if it's to be `read`, its existence
+/// otherwise, or the names of the entries of a directory, so that adding a new
+/// target is noticed as well.
+fn input_hash(path: &Path, read: bool) -> String {
+    if path.is_dir() {
+        let mut names = match fs::read_dir(path) {
+            Ok(entries) => {
+                entries.filter_map(|e| e.ok()).map(|e| e.file_name())
+                       .collect::<Vec<_>>()
+            }
+            Err(..) => return "unreadable".to_string(),
+        };
+        names.sort();
+        util::short_hash(&names)
+    } else if !read {
+        if path.exists() {"present"} else {"missing"}.to_string()
+    } else {
+        match paths::read_bytes(path) {
+            Ok(contents) => util::short_hash(&contents),
+            Err(..) => "missing".to_string(),
+        }
+    }
+}
+
 pub struct ExportInfo {
     packages: Vec<Package>,
     resolve: Option<MetadataResolve>,
+    build_scripts: Option<Vec<BuildScriptInfo>>,
     version: u32,
 }
 
+/// The `build_scripts` key is only present when it was asked for, so that the
+/// output of older invocations doesn't change.
+impl Encodable for ExportInfo {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        let len = if self.build_scripts.is_some() {4} else {3};
+        s.emit_struct("ExportInfo", len, |s| {
+            try!(s.emit_struct_field("packages", 0, |s| {
+                self.packages.encode(s)
+            }));
+            try!(s.emit_struct_field("resolve", 1, |s| {
+                self.resolve.encode(s)
+            }));
+            let mut idx = 2;
+            if let Some(ref build_scripts) = self.build_scripts {
+                try!(s.emit_struct_field("build_scripts", idx, |s| {
+                    build_scripts.encode(s)
+                }));
+                idx += 1;
+            }
+            s.emit_struct_field("version", idx, |s| self.version.encode(s))
+        })
+    }
+}
+
 /// Newtype wrapper to provide a custom `Encodable` implementation.
 /// The one from lockfile does not fit because it uses a non-standard
 /// format for `PackageId`s
@@ -1,16 +1,18 @@
 use std::fs::{self, File};
 use std::io::SeekFrom;
 use std::io::prelude::*;
-use std::path::{self, Path};
+use std::path::{self, Path, PathBuf};
 
 use flate2::read::GzDecoder;
 use flate2::{GzBuilder, Compression};
 use git2;
 use tar::{Archive, Builder, Header};
+use tempdir::TempDir;
 
 use core::{SourceId, Package, PackageId, Workspace, Source};
 use sources::PathSource;
 use util::{self, CargoResult, human, internal, ChainError, Config, FileLock};
+use util::{paths, toml};
 use ops;
 
 pub struct PackageOpts<'cfg> {
@@ -20,6 +22,10 @@ pub struct PackageOpts<'cfg> {
     pub allow_dirty: bool,
     pub verify: bool,
     pub jobs: Option<u32>,
+    /// The target to verify the package for, rather than the host
+    pub target: Option<&'cfg str>,
+    /// Verifies the package with `cargo check`, skipping code generation
+    pub check: bool,
 }
 
 pub fn package(ws: &Workspace,
@@ -40,6 +46,9 @@ pub fn package(ws: &Workspace,
         let mut list: Vec<_> = try!(src.list_files(&pkg)).iter().map(|file| {
             util::without_prefix(&file, &root).unwrap().to_path_buf()
         }).collect();
+        if shipped_lockfile(ws, pkg).is_some() {
+            list.push(PathBuf::from("Cargo.lock"));
+        }
         list.sort();
         for file in list.iter() {
             println!("{}", file.display());
@@ -52,7 +61,7 @@ pub fn package(ws: &Workspace,
     }
 
     let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
-    let dir = config.target_dir(ws).join("package");
+    let dir = ws.target_dir().join("package");
     let mut dst = {
         let tmp = format!(".{}", filename);
         try!(dir.open_rw(&tmp, config, "package scratch space"))
@@ -178,8 +187,16 @@ fn tar(ws: &Workspace,
     let pkg = try!(ws.current());
     let config = ws.config();
     let root = pkg.root();
-    for file in try!(src.list_files(pkg)).iter() {
-        let relative = util::without_prefix(&file, &root).unwrap();
+    let mut files = try!(src.list_files(pkg)).into_iter().map(|file| {
+        let relative = util::without_prefix(&file, &root).unwrap()
+                            .to_path_buf();
+        (file, relative)
+    }).collect::<Vec<_>>();
+    if let Some(lockfile) = shipped_lockfile(ws, pkg) {
+        files.push((lockfile, PathBuf::from("Cargo.lock")));
+    }
+    for &(ref file, ref relative) in files.iter() {
+        let relative = relative.as_path();
         try!(check_filename(relative));
         let relative = try!(relative.to_str().chain_error(|| {
             human(format!("non-utf8 path in source directory: {}",
@@ -218,6 +235,21 @@ fn tar(ws: &Workspace,
             human(format!("failed to add to archive: `{}`", relative))
         }));
         header.set_metadata(&metadata);
+
+        // The values inherited from the workspace are filled in, as the
+        // workspace root isn't part of the package
+        if relative == "Cargo.toml" && pkg.manifest().inherits_workspace() {
+            let contents = try!(paths::read(pkg.manifest_path()));
+            let contents = try!(toml::inline_workspace_values(
+                &contents, pkg.manifest_path(), config));
+            header.set_size(contents.len() as u64);
+            header.set_cksum();
+            try!(ar.append(&header, contents.as_bytes()).chain_error(|| {
+                internal(format!("could not archive source file `{}`",
+                                 relative))
+            }));
+            continue
+        }
         header.set_cksum();
 
         try!(ar.append(&header, &mut file).chain_error(|| {
@@ -229,6 +261,22 @@ fn tar(ws: &Workspace,
     Ok(())
 }
 
+/// Returns the lock file to ship along with `pkg`, which is only done for
+/// packages with binaries so `cargo install --locked` can build them with the
+/// same dependencies as their authors.
+///
+/// The lock file of a workspace with other members also locks their
+/// dependencies, so it's only shipped when `pkg` is the only member.
+fn shipped_lockfile(ws: &Workspace, pkg: &Package) -> Option<PathBuf> {
+    let lockfile = ws.lockfile();
+    if pkg.targets().iter().any(|t| t.is_bin()) && ws.root() == pkg.root() &&
+       ws.members().count() == 1 && lockfile.exists() {
+        Some(lockfile)
+    } else {
+        None
+    }
+}
+
 fn run_verify(ws: &Workspace, tar: &File, opts: &PackageOpts) -> CargoResult<()> {
     let config = ws.config();
     let pkg = try!(ws.current());
@@ -236,8 +284,19 @@ fn run_verify(ws: &Workspace, tar: &File, opts: &PackageOpts) -> CargoResult<()>
     try!(config.shell().status("Verifying", pkg));
 
     let f = try!(GzDecoder::new(tar));
-    let dst = pkg.root().join(&format!("target/package/{}-{}",
-                                       pkg.name(), pkg.version()));
+    // The package is built within `build.tmpdir` if it's configured, in which
+    // case it's removed afterwards whether or not the build succeeded.
+    let name = format!("{}-{}", pkg.name(), pkg.version());
+    let td = match try!(config.tmpdir()) {
+        Some(dir) => Some(try!(TempDir::new_in(&dir, "cargo-package"))),
+        None => None,
+    };
+    let dst = match td {
+        Some(ref td) => td.path().join(&name),
+        None => {
+            ws.target_dir().join("package").join(&name).into_path_unlocked()
+        }
+    };
     if fs::metadata(&dst).is_ok() {
         try!(fs::remove_dir_all(&dst));
     }
@@ -270,16 +329,28 @@ fn run_verify(ws: &Workspace, tar: &File, opts: &PackageOpts) -> CargoResult<()>
     try!(ops::compile_ws(&ws, None, &ops::CompileOptions {
         config: config,
         jobs: opts.jobs,
-        target: None,
+        targets: opts.target.into_iter().collect(),
         features: &[],
         no_default_features: false,
-        spec: &[],
+        spec: ops::Packages::Packages(&[]),
         filter: ops::CompileFilter::Everything,
         exec_engine: None,
         release: false,
-        mode: ops::CompileMode::Build,
+        profile: None,
+        // Build scripts are still compiled and run when checking, as the
+        // package may not type check without what they generate.
+        mode: if opts.check {
+            ops::CompileMode::Check
+        } else {
+            ops::CompileMode::Build
+        },
         target_rustdoc_args: None,
         target_rustc_args: None,
+        target_crate_types: None,
+        exclude: ops::ExcludeTargets::default(),
+        build_plan: false,
+        coverage: false,
+        message_format: ops::MessageFormat::Human,
     }));
 
     Ok(())
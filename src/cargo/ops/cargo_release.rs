@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use semver::Version;
+use term::color::BLACK;
+
+use core::{Dependency, Package, Registry, Source, SourceId, Workspace};
+use core::dependency::Kind;
+use core::registry::PackageRegistry;
+use ops;
+use ops::cargo_bump;
+use sources::RegistrySource;
+use util::{CargoResult, human, ToUrl};
+use util::config::Config;
+
+pub struct ReleaseOptions<'a> {
+    pub config: &'a Config,
+    /// One of `major`, `minor`, `patch` or an explicit version number
+    pub version: &'a str,
+    pub token: Option<String>,
+    pub index: Option<String>,
+    pub verify: bool,
+    pub tag: bool,
+    pub publish: bool,
+    /// How long to wait for a published crate to show up in the index
+    /// before publishing the crates depending on it.
+    pub wait_timeout: Duration,
+    pub dry_run: bool,
+}
+
+struct Step {
+    name: String,
+    manifest_path: PathBuf,
+    old: Version,
+    new: Version,
+}
+
+/// Releases all publishable members of a workspace.
+///
+/// Every member has its version bumped, the lock file is updated and the
+/// result is committed and tagged. The members are then published in
+/// dependency order, waiting for each crate to become available in the
+/// registry index before publishing the crates which depend on it.
+///
+/// With `dry_run` nothing is modified and the plan is printed instead.
+pub fn release(ws: &Workspace, opts: &ReleaseOptions) -> CargoResult<()> {
+    let mut steps = Vec::new();
+    for pkg in try!(publish_order(ws)) {
+        let new = try!(cargo_bump::next_version(pkg.version(), opts.version));
+        if new <= *pkg.version() {
+            bail!("new version `{}` of `{}` must be greater than the current \
+                   version `{}`", new, pkg.name(), pkg.version())
+        }
+        steps.push(Step {
+            name: pkg.name().to_string(),
+            manifest_path: pkg.manifest_path().to_path_buf(),
+            old: pkg.version().clone(),
+            new: new,
+        });
+    }
+    if steps.is_empty() {
+        bail!("no members of the workspace at `{}` can be published",
+              ws.root().display())
+    }
+
+    let tags = if !opts.tag {
+        Vec::new()
+    } else if steps.len() == 1 {
+        vec![format!("v{}", steps[0].new)]
+    } else {
+        steps.iter().map(|s| format!("{}-v{}", s.name, s.new)).collect()
+    };
+    let msg = format!("Release {}", steps.iter().map(|s| {
+        format!("{} {}", s.name, s.new)
+    }).collect::<Vec<_>>().join(", "));
+
+    if opts.dry_run {
+        return print_plan(opts, &steps, &tags, &msg)
+    }
+
+    let mut changed = Vec::new();
+    for step in steps.iter() {
+        let ws = try!(Workspace::new(&step.manifest_path, opts.config));
+        for path in try!(cargo_bump::bump_manifests(&ws, &step.new,
+                                                    opts.config)) {
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+    }
+
+    let lockfile = ws.lockfile();
+    {
+        let mut ws = try!(Workspace::new(&steps[0].manifest_path, opts.config));
+        ws.set_lockfile(&lockfile);
+        let mut registry = PackageRegistry::new(opts.config);
+        try!(ops::resolve_ws(&mut registry, &ws));
+    }
+    if lockfile.exists() {
+        changed.push(lockfile);
+    }
+
+    if opts.tag {
+        try!(cargo_bump::commit(ws.root(), &changed, &msg, &tags));
+    }
+
+    if !opts.publish {
+        return Ok(())
+    }
+    for (i, step) in steps.iter().enumerate() {
+        let ws = try!(Workspace::new(&step.manifest_path, opts.config));
+        try!(ops::publish(&ws, &ops::PublishOpts {
+            config: opts.config,
+            token: opts.token.clone(),
+            index: opts.index.clone(),
+            verify: opts.verify,
+            allow_dirty: !opts.tag,
+            jobs: None,
+            target: None,
+            check: false,
+            dry_run: false,
+            show_payload: false,
+        }));
+        if i + 1 < steps.len() {
+            try!(wait_for_index(opts, &step.name, &step.new));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the publishable members of `ws` ordered such that each package
+/// comes after all of the members it depends on.
+fn publish_order<'a>(ws: &'a Workspace) -> CargoResult<Vec<&'a Package>> {
+    let mut pending = ws.members().filter(|p| p.publish()).collect::<Vec<_>>();
+    let mut order = Vec::new();
+    while !pending.is_empty() {
+        let names = pending.iter().map(|p| p.name()).collect::<HashSet<_>>();
+        let (ready, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|p| {
+            !p.dependencies().iter().any(|d| {
+                d.kind() != Kind::Development && d.name() != p.name() &&
+                    names.contains(d.name())
+            })
+        });
+        if ready.is_empty() {
+            bail!("cannot determine a publish order, the packages {} have \
+                   cyclic dependencies on each other",
+                  rest.iter().map(|p| format!("`{}`", p.name()))
+                      .collect::<Vec<_>>().join(", "))
+        }
+        order.extend(ready);
+        pending = rest;
+    }
+    Ok(order)
+}
+
+fn print_plan(opts: &ReleaseOptions, steps: &[Step], tags: &[String],
+              msg: &str) -> CargoResult<()> {
+    let mut plan = Vec::new();
+    for step in steps {
+        plan.push(format!("bump {} from {} to {}", step.name, step.old,
+                          step.new));
+    }
+    plan.push("update the lock file".to_string());
+    if opts.tag {
+        plan.push(format!("commit \"{}\"", msg));
+        for tag in tags {
+            plan.push(format!("tag {}", tag));
+        }
+    }
+    if opts.publish {
+        for (i, step) in steps.iter().enumerate() {
+            plan.push(format!("publish {} {}", step.name, step.new));
+            if i + 1 < steps.len() {
+                plan.push(format!("wait for {} {} to appear in the registry \
+                                   index", step.name, step.new));
+            }
+        }
+    }
+
+    let mut shell = opts.config.shell();
+    for (i, line) in plan.iter().enumerate() {
+        try!(shell.say(format!("{:>3}. {}", i + 1, line), BLACK));
+    }
+    try!(shell.warn("aborting release due to dry run"));
+    Ok(())
+}
+
+/// Polls the registry index until version `version` of `name` is available,
+/// so that crates depending on it can be published.
+fn wait_for_index(opts: &ReleaseOptions, name: &str, version: &Version)
+                  -> CargoResult<()> {
+    let index = match opts.index {
+        Some(ref index) => index.clone(),
+        None => try!(ops::registry_configuration(opts.config)).index
+                    .unwrap_or(RegistrySource::default_url()),
+    };
+    let sid = SourceId::for_registry(&try!(index.to_url().map_err(human)));
+    let dep = try!(Dependency::parse(name, Some(&format!("={}", version)),
+                                     &sid));
+    let start = Instant::now();
+    loop {
+        let mut src = RegistrySource::new(&sid, opts.config);
+        try!(src.update());
+        if !try!(src.query(&dep)).is_empty() {
+            return Ok(())
+        }
+        if start.elapsed() >= opts.wait_timeout {
+            bail!("timed out waiting for `{} v{}` to appear in the registry \
+                   index", name, version)
+        }
+        try!(opts.config.shell().status("Waiting",
+                                        format!("for {} v{} to appear in the \
+                                                 registry index", name,
+                                                version)));
+        thread::sleep(Duration::from_secs(5));
+    }
+}
@@ -1,22 +1,37 @@
 use std::path::Path;
 
-use ops::{self, CompileFilter};
+use ops::{self, CompileFilter, Packages};
+use ops::cargo_compile::is_glob;
 use util::{self, CargoResult, process, ProcessError};
-use core::Workspace;
+use core::{Package, PackageIdSpec, TargetKind, Workspace};
 
+/// Builds and runs the binaries of a package, the one selected with `-p` or
+/// the current one. Several may be run at once when selected by glob patterns,
+/// one after the other, stopping at the first which fails.
 pub fn run(ws: &Workspace,
            options: &ops::CompileOptions,
            args: &[String]) -> CargoResult<Option<ProcessError>> {
     let config = ws.config();
-    let root = try!(ws.current());
+    let root = try!(package(ws, &options.spec));
 
-    let mut bins = root.manifest().targets().iter().filter(|a| {
+    let bins = root.manifest().targets().iter().filter(|a| {
         !a.is_lib() && !a.is_custom_build() && match options.filter {
             CompileFilter::Everything => a.is_bin(),
             CompileFilter::Only { .. } => options.filter.matches(a),
         }
-    });
-    if bins.next().is_none() {
+    }).collect::<Vec<_>>();
+    let patterns = match options.filter {
+        CompileFilter::Everything => false,
+        CompileFilter::Only { bins: names, examples, .. } => {
+            for name in names.iter().filter(|n| !is_glob(n)) {
+                if !bins.iter().any(|t| t.is_bin() && t.name() == *name) {
+                    bail!("{}", missing_bin(root, name))
+                }
+            }
+            names.iter().chain(examples).any(|n| is_glob(n))
+        }
+    };
+    if bins.is_empty() {
         match options.filter {
             CompileFilter::Everything => {
                 bail!("a bin target must be available for `cargo run`")
@@ -26,12 +41,13 @@ pub fn run(ws: &Workspace,
             }
         }
     }
-    if bins.next().is_some() {
+    if bins.len() > 1 && !patterns {
         match options.filter {
             CompileFilter::Everything => {
-                bail!("`cargo run` requires that a project only have one \
-                       executable; use the `--bin` option to specify which one \
-                       to run")
+                bail!("`cargo run` could not determine which binary to run, \
+                       use the `--bin` option to specify which one to run, \
+                       or `--bins` to run them all\n\n{}",
+                      available_bins(root))
             }
             CompileFilter::Only { .. } => {
                 bail!("`cargo run` can run at most one executable, but \
@@ -41,17 +57,66 @@ pub fn run(ws: &Workspace,
     }
 
     let compile = try!(ops::compile(ws, options));
-    let exe = &compile.binaries[0];
-    let exe = match util::without_prefix(&exe, config.cwd()) {
-        Some(path) if path.file_name() == Some(path.as_os_str())
-                   => Path::new(".").join(path).to_path_buf(),
-        Some(path) => path.to_path_buf(),
-        None => exe.to_path_buf(),
+    let mut exes = compile.binaries.clone();
+    exes.sort();
+    for exe in exes {
+        let exe = match util::without_prefix(&exe, config.cwd()) {
+            Some(path) if path.file_name() == Some(path.as_os_str())
+                       => Path::new(".").join(path).to_path_buf(),
+            Some(path) => path.to_path_buf(),
+            None => exe.to_path_buf(),
+        };
+        let mut process = try!(compile.target_process(exe, root))
+                                      .into_process_builder();
+        process.args(args).cwd(config.cwd());
+
+        try!(config.shell().status("Running", process.to_string()));
+        if let Err(e) = process.exec() {
+            return Ok(Some(e))
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the package selected with `-p`, which must be a member of the
+/// workspace, or the current package if none was.
+fn package<'a>(ws: &'a Workspace, spec: &Packages) -> CargoResult<&'a Package> {
+    let specs = match *spec {
+        Packages::Packages(specs) => specs,
+        Packages::All { .. } => {
+            bail!("`cargo run` can only run the binaries of one package")
+        }
     };
-    let mut process = try!(compile.target_process(exe, &root))
-                                  .into_process_builder();
-    process.args(args).cwd(config.cwd());
+    match specs.len() {
+        0 => ws.current(),
+        1 => {
+            let spec = try!(PackageIdSpec::parse(&specs[0]));
+            match ws.members().find(|p| spec.matches(p.package_id())) {
+                Some(pkg) => Ok(pkg),
+                None => bail!("package `{}` is not a member of the workspace",
+                              specs[0]),
+            }
+        }
+        _ => bail!("`cargo run` can only run the binaries of one package"),
+    }
+}
 
-    try!(config.shell().status("Running", process.to_string()));
-    Ok(process.exec().err())
+fn missing_bin(pkg: &Package, name: &str) -> String {
+    let mut msg = format!("no bin target named `{}`", name);
+    if let Some(t) = pkg.find_closest_target(name, TargetKind::Bin) {
+        msg.push_str(&format!("\n\nDid you mean `{}`?", t.name()));
+    }
+    msg.push_str(&format!("\n\n{}", available_bins(pkg)));
+    msg
+}
+
+fn available_bins(pkg: &Package) -> String {
+    let names = pkg.targets().iter().filter(|t| t.is_bin()).map(|t| {
+        t.name()
+    }).collect::<Vec<_>>();
+    if names.is_empty() {
+        format!("`{}` has no binaries", pkg.name())
+    } else {
+        format!("available binaries of `{}`: {}", pkg.name(), names.join(", "))
+    }
 }
@@ -0,0 +1,309 @@
+//! Shared caches of compiled artifacts.
+//!
+//! The outputs of units whose fingerprint doesn't depend on the local
+//! filesystem (that is, everything but path dependencies) can be shared
+//! between builds through two kinds of caches, which are consulted before
+//! compiling such a unit and populated afterwards.
+//!
+//! When `build.cache-dir` is set, the outputs are stored in a directory per
+//! unit in there and hard linked into the target directory of every project
+//! on the machine that needs them.
+//!
+//! Procedural macros are costly to compile, rarely change and are always
+//! compiled for the host, so with `build.proc-macro-cache` set they're cached
+//! in `$CARGO_HOME/proc-macros` the same way, even when `build.cache-dir`
+//! isn't.
+//!
+//! When `build.artifact-cache` is set to a URL, the outputs are shared
+//! through plain HTTP instead:
+//!
+//! * `GET <url>/<key>` returns a gzipped tarball of the unit's outputs, or
+//!   a 404 if the cache doesn't have them.
+//! * `GET <url>/<key>.sha256` returns the hex encoded SHA-256 of the tarball,
+//!   which is checked before anything is unpacked.
+//! * `PUT <url>/<key>` and `PUT <url>/<key>.sha256` upload both of them after
+//!   a unit has been compiled.
+//!
+//! Tarballs may only contain the outputs of the unit, and they're unpacked in
+//! a temporary directory first so a failed fetch never leaves half of them in
+//! the target directory.
+//!
+//! The key is derived from the fingerprint of the unit along with the target
+//! triple it's compiled for. Failing to use a cache is never fatal, the unit
+//! is simply compiled locally.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path};
+
+use curl::easy::Easy;
+use flate2::{Compression, GzBuilder};
+use flate2::read::GzDecoder;
+use rustc_serialize::hex::ToHex;
+use tar::{Archive, Builder};
+use tempdir::TempDir;
+
+use ops;
+use util::{self, CargoResult, Sha256};
+
+use super::context::{Context, Unit};
+use super::fingerprint;
+use super::job::Work;
+
+/// Wraps `work`, the work to compile `unit`, so the outputs are taken from
+/// a cache if possible and stored in the caches otherwise.
+pub fn prepare<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
+                         unit: &Unit<'a>,
+                         work: Work) -> CargoResult<Work> {
+    let url = match cx.build_config.artifact_cache {
+        Some(ref url) if cx.config.network_allowed() => {
+            Some(url.trim_right_matches('/').to_string())
+        }
+        _ => None,
+    };
+    let cache_dir = cx.build_config.cache_dir.clone().or_else(|| {
+        if unit.target.is_proc_macro() {
+            cx.build_config.proc_macro_cache.clone()
+        } else {
+            None
+        }
+    });
+    if url.is_none() && cache_dir.is_none() {
+        return Ok(work)
+    }
+    // Build scripts may generate code depending on the environment, and
+    // custom arguments aren't tracked by the fingerprint at all.
+    let has_build_script = unit.pkg.targets().iter().any(|t| {
+        t.is_custom_build()
+    });
+    if unit.profile.doc || unit.profile.rustc_args.is_some() || has_build_script {
+        return Ok(work)
+    }
+    let key = match try!(fingerprint::artifact_key(cx, unit)) {
+        Some(key) => util::to_hex(key),
+        None => return Ok(work),
+    };
+    let mut remote = match url {
+        Some(url) => {
+            Some((try!(ops::http_handle(cx.config)), format!("{}/{}", url, key)))
+        }
+        None => None,
+    };
+    let local = cache_dir.map(|dir| dir.join(&key));
+    let root = cx.out_dir(unit);
+    let filenames = try!(cx.target_filenames(unit)).into_iter().map(|(f, _)| {
+        f
+    }).collect::<Vec<_>>();
+
+    Ok(Work::new(move |state| {
+        if let Some(ref dir) = local {
+            match link_from(dir, &root, &filenames) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => {
+                    state.stderr(&format!("warning: failed to use `{}` from \
+                                           the cache directory: {}",
+                                          dir.display(), e));
+                }
+            }
+        }
+
+        let mut fetched = false;
+        if let Some((ref mut handle, ref url)) = remote {
+            match fetch(handle, url, &root, &filenames) {
+                Ok(found) => fetched = found,
+                Err(e) => {
+                    state.stderr(&format!("warning: failed to fetch `{}` from \
+                                           the artifact cache: {}", url, e));
+                }
+            }
+        }
+        if !fetched {
+            try!(work.call(state));
+            if let Some((ref mut handle, ref url)) = remote {
+                if let Err(e) = store(handle, url, &root, &filenames) {
+                    state.stderr(&format!("warning: failed to store `{}` in \
+                                           the artifact cache: {}", url, e));
+                }
+            }
+        }
+
+        if let Some(ref dir) = local {
+            if let Err(e) = link_into(dir, &root, &filenames) {
+                state.stderr(&format!("warning: failed to store `{}` in the \
+                                       cache directory: {}", dir.display(), e));
+            }
+        }
+        Ok(())
+    }))
+}
+
+/// Links the outputs cached in `dir` into `root`, returning whether the cache
+/// had them.
+fn link_from(dir: &Path, root: &Path, filenames: &[String])
+             -> CargoResult<bool> {
+    if !filenames.iter().all(|f| dir.join(f).is_file()) {
+        return Ok(false)
+    }
+    for filename in filenames {
+        let dst = root.join(filename);
+        if fs::metadata(&dst).is_ok() {
+            try!(fs::remove_file(&dst));
+        }
+        try!(link_or_copy(&dir.join(filename), &dst));
+    }
+    Ok(true)
+}
+
+/// Adds the freshly compiled outputs in `root` to the cache as `dir`.
+///
+/// The outputs are first linked into a scratch directory which is then renamed
+/// into place, so concurrent builds never see a partially populated entry.
+fn link_into(dir: &Path, root: &Path, filenames: &[String]) -> CargoResult<()> {
+    if dir.exists() {
+        return Ok(())
+    }
+    let parent = dir.parent().unwrap();
+    try!(fs::create_dir_all(parent));
+    // Builds of the same project are serialized by the lock on its target
+    // directory, so the scratch directory only needs to be unique per project.
+    let tmp = parent.join(format!(".{}-{}", dir.file_name().unwrap()
+                                              .to_string_lossy(),
+                                  util::short_hash(root)));
+    let result = fs::create_dir_all(&tmp).map_err(From::from).and_then(|()| {
+        for filename in filenames {
+            try!(link_or_copy(&root.join(filename), &tmp.join(filename)));
+        }
+        Ok(())
+    }).and_then(|()| {
+        match fs::rename(&tmp, dir) {
+            Ok(()) => Ok(()),
+            // Another build got there first
+            Err(..) if dir.exists() => Ok(()),
+            Err(e) => Err(From::from(e)),
+        }
+    });
+    if tmp.exists() {
+        let _ = fs::remove_dir_all(&tmp);
+    }
+    result
+}
+
+/// Hard links `src` to `dst`, falling back to copying it if they're on
+/// different filesystems.
+fn link_or_copy(src: &Path, dst: &Path) -> CargoResult<()> {
+    if fs::hard_link(src, dst).is_err() {
+        try!(fs::copy(src, dst));
+    }
+    Ok(())
+}
+
+/// Downloads and unpacks the outputs stored at `url` into `root`, returning
+/// whether the cache had them.
+fn fetch(handle: &mut Easy, url: &str, root: &Path, filenames: &[String])
+         -> CargoResult<bool> {
+    let body = match try!(get(handle, url)) {
+        Some(body) => body,
+        None => return Ok(false),
+    };
+    let checksum = match try!(get(handle, &format!("{}.sha256", url))) {
+        Some(checksum) => checksum,
+        None => bail!("the cached artifact has no checksum"),
+    };
+    if String::from_utf8_lossy(&checksum).trim() != sha256(&body) {
+        bail!("the checksum of the cached artifact doesn't match")
+    }
+
+    let tmp = try!(TempDir::new_in(root, ".artifact-cache"));
+    let mut archive = Archive::new(try!(GzDecoder::new(&body[..])));
+    for entry in try!(archive.entries()) {
+        let mut entry = try!(entry);
+        let path = try!(entry.path()).into_owned();
+        if !path.components().all(|c| match c {
+            Component::Normal(..) => true,
+            _ => false,
+        }) {
+            bail!("cached artifact contains invalid path `{}`",
+                  path.display())
+        }
+        if !filenames.iter().any(|f| Path::new(f) == path.as_path()) {
+            bail!("cached artifact contains unexpected file `{}`",
+                  path.display())
+        }
+        try!(entry.unpack(&tmp.path().join(&path)));
+    }
+    for filename in filenames {
+        if !tmp.path().join(filename).exists() {
+            bail!("cached artifact is missing `{}`", filename)
+        }
+    }
+    for filename in filenames {
+        let dst = root.join(filename);
+        if fs::metadata(&dst).is_ok() {
+            try!(fs::remove_file(&dst));
+        }
+        try!(fs::rename(tmp.path().join(filename), &dst));
+    }
+    Ok(true)
+}
+
+/// Uploads the freshly compiled outputs in `root` to `url`.
+fn store(handle: &mut Easy, url: &str, root: &Path, filenames: &[String])
+         -> CargoResult<()> {
+    let mut body = Vec::new();
+    {
+        let encoder = GzBuilder::new().write(&mut body, Compression::Default);
+        let mut ar = Builder::new(encoder);
+        for filename in filenames {
+            let mut file = try!(File::open(root.join(filename)));
+            try!(ar.append_file(filename, &mut file));
+        }
+        try!(try!(ar.into_inner()).finish());
+    }
+    try!(put(handle, url, &body));
+    put(handle, &format!("{}.sha256", url), sha256(&body).as_bytes())
+}
+
+/// Returns the body served at `url`, or `None` for a 404.
+fn get(handle: &mut Easy, url: &str) -> CargoResult<Option<Vec<u8>>> {
+    try!(handle.get(true));
+    try!(handle.url(url));
+    try!(handle.follow_location(true));
+    let mut body = Vec::new();
+    {
+        let mut handle = handle.transfer();
+        try!(handle.write_function(|buf| {
+            body.extend_from_slice(buf);
+            Ok(buf.len())
+        }));
+        try!(handle.perform());
+    }
+    match try!(handle.response_code()) {
+        200 => Ok(Some(body)),
+        404 => Ok(None),
+        code => bail!("failed to get 200 response, got {}", code),
+    }
+}
+
+fn put(handle: &mut Easy, url: &str, body: &[u8]) -> CargoResult<()> {
+    try!(handle.put(true));
+    try!(handle.url(url));
+    try!(handle.upload(true));
+    try!(handle.in_filesize(body.len() as u64));
+    let mut body = body;
+    {
+        let mut handle = handle.transfer();
+        try!(handle.read_function(|buf| Ok(body.read(buf).unwrap_or(0))));
+        try!(handle.perform());
+    }
+    match try!(handle.response_code()) {
+        200 | 201 | 204 => Ok(()),
+        code => bail!("failed to get 200 response, got {}", code),
+    }
+}
+
+fn sha256(data: &[u8]) -> String {
+    let mut state = Sha256::new();
+    state.update(data);
+    state.finish().to_hex()
+}
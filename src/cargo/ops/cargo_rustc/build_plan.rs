@@ -0,0 +1,133 @@
+//! The `--build-plan` mode of builds, which prints the processes a build
+//! consists of instead of running them.
+//!
+//! Every unit of the build is planned as usual, including the evaluation of
+//! its fingerprint, but rather than being enqueued it's turned into an
+//! invocation of the compiler or of a build script. The outputs of build
+//! scripts are only known once they've run, so the `-L`, `-l` and `--cfg`
+//! flags they add to the invocations depending on them, as well as the
+//! `DEP_*` variables they pass to other build scripts, are left to whatever
+//! runs the plan.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rustc_serialize::json;
+
+use core::Target;
+use util::{CargoResult, Fresh};
+
+use super::{custom_build, fingerprint, Context, Kind, Unit};
+use super::engine::CommandPrototype;
+
+pub struct BuildPlan<'a> {
+    invocations: Vec<(Unit<'a>, Vec<Unit<'a>>, Invocation)>,
+}
+
+#[derive(RustcEncodable)]
+struct Invocation {
+    package_id: String,
+    target: Target,
+    /// Either `build`, compiling the target, or `run-build-script`
+    mode: &'static str,
+    kind: &'static str,
+    /// Whether the outputs are up to date already
+    fresh: bool,
+    /// Indices of the invocations which have to run before this one
+    deps: Vec<usize>,
+    program: String,
+    args: Vec<String>,
+    /// The variables set in addition to the environment of Cargo
+    env: BTreeMap<String, String>,
+    cwd: Option<String>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+#[derive(RustcEncodable)]
+struct SerializedBuildPlan<'a> {
+    invocations: &'a [Invocation],
+}
+
+impl<'a> BuildPlan<'a> {
+    pub fn new() -> BuildPlan<'a> {
+        BuildPlan { invocations: Vec::new() }
+    }
+
+    /// Encodes the plan as JSON, with the dependencies of each invocation
+    /// given as indices into the list of invocations.
+    pub fn to_json(&mut self) -> String {
+        let index = self.invocations.iter().enumerate().map(|(i, entry)| {
+            (entry.0, i)
+        }).collect::<HashMap<_, _>>();
+        for entry in self.invocations.iter_mut() {
+            let (_, ref deps, ref mut invocation) = *entry;
+            invocation.deps = deps.iter().filter_map(|dep| {
+                index.get(dep).cloned()
+            }).collect();
+            invocation.deps.sort();
+            invocation.deps.dedup();
+        }
+        let invocations = self.invocations.drain(..).map(|(_, _, inv)| {
+            inv
+        }).collect::<Vec<_>>();
+        json::encode(&SerializedBuildPlan {
+            invocations: &invocations,
+        }).unwrap()
+    }
+}
+
+/// Adds the invocation building `unit` to the plan of the build.
+pub fn add<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
+                     -> CargoResult<()> {
+    let run_build_script = unit.profile.run_custom_build;
+    let (mode, cmd, freshness, inputs, outputs) = if run_build_script {
+        if cx.build_state.has_override(unit) {
+            return Ok(())
+        }
+        let (_, _, freshness) = try!(custom_build::prepare(cx, unit));
+        let cmd = try!(custom_build::build_script_command(cx, unit));
+        let script = cx.layout(unit.pkg, Kind::Host).build(unit.pkg)
+                       .join(unit.target.name());
+        let out_dir = cx.layout(unit.pkg, unit.kind).build_out(unit.pkg);
+        ("run-build-script", cmd, freshness, vec![script], vec![out_dir])
+    } else {
+        let (freshness, _, _) = try!(fingerprint::prepare_target(cx, unit));
+        let (cmd, _) = try!(super::rustc_commands(cx, unit));
+        let root = cx.out_dir(unit);
+        let outputs = try!(cx.target_filenames(unit)).into_iter()
+                             .map(|(filename, _)| root.join(filename))
+                             .collect();
+        ("build", cmd, freshness, vec![super::root_path(cx, unit)], outputs)
+    };
+
+    let deps = try!(cx.dep_targets(unit));
+    let invocation = Invocation {
+        package_id: unit.pkg.package_id().to_string(),
+        target: unit.target.clone(),
+        mode: mode,
+        kind: match unit.kind {
+            Kind::Host => "host",
+            Kind::Target(_) => "target",
+        },
+        fresh: freshness == Fresh,
+        deps: Vec::new(),
+        program: cmd.get_program().to_string_lossy().into_owned(),
+        args: cmd.get_args().iter().map(|arg| {
+            arg.to_string_lossy().into_owned()
+        }).collect(),
+        env: env(&cmd),
+        cwd: cmd.get_cwd().map(|p| p.display().to_string()),
+        inputs: inputs.iter().map(|p| p.display().to_string()).collect(),
+        outputs: outputs.iter().map(|p| p.display().to_string()).collect(),
+    };
+    cx.build_plan.invocations.push((*unit, deps, invocation));
+    Ok(())
+}
+
+fn env(cmd: &CommandPrototype) -> BTreeMap<String, String> {
+    cmd.get_envs().iter().filter_map(|(key, value)| {
+        value.as_ref().map(|value| {
+            (key.clone(), value.to_string_lossy().into_owned())
+        })
+    }).collect()
+}
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
-use std::ffi::OsStr;
+use std::env;
+use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
 use semver::Version;
 
@@ -17,7 +18,7 @@ pub struct Compilation<'cfg> {
     pub libraries: HashMap<PackageId, Vec<(Target, PathBuf)>>,
 
     /// An array of all tests created during this compilation.
-    pub tests: Vec<(Package, String, PathBuf)>,
+    pub tests: Vec<(Package, Target, PathBuf)>,
 
     /// An array of all binaries created.
     pub binaries: Vec<PathBuf>,
@@ -35,6 +36,10 @@ pub struct Compilation<'cfg> {
     /// Output directory for rust dependencies
     pub deps_output: PathBuf,
 
+    /// Output directory for the dependencies compiled for the host, which
+    /// differs from `deps_output` when cross compiling
+    pub host_deps_output: PathBuf,
+
     /// Extra environment variables that were passed to compilations and should
     /// be passed to future invocations of programs.
     pub extra_env: HashMap<PackageId, Vec<(String, String)>>,
@@ -44,6 +49,13 @@ pub struct Compilation<'cfg> {
     /// Features enabled during this compilation.
     pub cfgs: HashSet<String>,
 
+    /// Program the binaries built for the target are run through, if any.
+    pub target_runner: Option<Vec<String>>,
+
+    /// Environment variables configured for the binaries built for the
+    /// target, which don't override those Cargo itself was run with.
+    pub run_env: Vec<(String, String)>,
+
     config: &'cfg Config,
 }
 
@@ -54,11 +66,14 @@ impl<'cfg> Compilation<'cfg> {
             native_dirs: HashSet::new(),  // TODO: deprecated, remove
             root_output: PathBuf::from("/"),
             deps_output: PathBuf::from("/"),
+            host_deps_output: PathBuf::from("/"),
             tests: Vec::new(),
             binaries: Vec::new(),
             extra_env: HashMap::new(),
             to_doc_test: Vec::new(),
             cfgs: HashSet::new(),
+            target_runner: None,
+            run_env: Vec::new(),
             config: config,
         }
     }
@@ -74,10 +89,28 @@ impl<'cfg> Compilation<'cfg> {
         self.process(CommandType::Rustdoc, pkg)
     }
 
-    /// See `process`.
+    /// See `process`, the command being run through `target_runner` if it's
+    /// configured, and with the variables of `run_env`.
     pub fn target_process<T: AsRef<OsStr>>(&self, cmd: T, pkg: &Package)
                                                -> CargoResult<CommandPrototype> {
-        self.process(CommandType::Target(cmd.as_ref().to_os_string()), pkg)
+        let mut process = match self.target_runner {
+            Some(ref runner) => {
+                let program = CommandType::Target(OsString::from(&runner[0]));
+                let mut process = try!(self.process(program, pkg));
+                process.args(&runner[1..]).arg(cmd);
+                process
+            }
+            None => {
+                let cmd = cmd.as_ref().to_os_string();
+                try!(self.process(CommandType::Target(cmd), pkg))
+            }
+        };
+        for &(ref k, ref v) in self.run_env.iter() {
+            if env::var_os(k).is_none() {
+                process.env(k, v);
+            }
+        }
+        Ok(process)
     }
 
     /// See `process`.
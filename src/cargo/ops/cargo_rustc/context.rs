@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::collections::{HashSet, HashMap, BTreeSet};
 use std::env;
 use std::path::{Path, PathBuf};
@@ -6,16 +7,20 @@ use std::sync::Arc;
 
 
 use core::{Package, PackageId, PackageSet, Resolve, Target, Profile};
-use core::{TargetKind, Profiles, Metadata, Dependency, Workspace};
+use core::{TargetKind, Profiles, Metadata, Dependency, Workspace, LibKind};
 use core::dependency::Kind as DepKind;
+use ops::test_coverage;
 use util::{CargoResult, ChainError, internal, Config, profile, Cfg, human};
+use util::jobserver::Client;
 
 use super::TargetConfig;
-use super::custom_build::{BuildState, BuildScripts};
+use super::build_plan::BuildPlan;
+use super::custom_build::{BuildState, BuildScripts, BuildDeps};
 use super::fingerprint::Fingerprint;
 use super::layout::{Layout, LayoutProxy};
 use super::links::Links;
-use super::{Kind, Compilation, BuildConfig};
+use super::timings::UnitTime;
+use super::{Kind, Compilation, BuildConfig, Warnings};
 use super::{ProcessEngine, ExecEngine};
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash)]
@@ -32,17 +37,32 @@ pub struct Context<'a, 'cfg: 'a> {
     pub compilation: Compilation<'cfg>,
     pub packages: &'a PackageSet<'cfg>,
     pub build_state: Arc<BuildState>,
-    pub build_explicit_deps: HashMap<Unit<'a>, (PathBuf, Vec<String>)>,
+    pub build_explicit_deps: HashMap<Unit<'a>, BuildDeps>,
     pub exec_engine: Arc<Box<ExecEngine>>,
     pub fingerprints: HashMap<Unit<'a>, Arc<Fingerprint>>,
     pub compiled: HashSet<Unit<'a>>,
     pub build_config: BuildConfig,
     pub build_scripts: HashMap<Unit<'a>, Arc<BuildScripts>>,
     pub links: Links<'a>,
+    pub jobserver: Client,
+    /// How long the jobs of each unit which was rebuilt took and what they
+    /// used, keyed by the package and a description of the unit
+    pub build_times: HashMap<(PackageId, String), UnitTime>,
+    pub build_plan: BuildPlan<'a>,
+    /// The features of the packages compiled for the host, which are resolved
+    /// separately from those of the target when cross compiling
+    pub host_features: HashMap<PackageId, HashSet<String>>,
+    /// Whether `get_package` fails for the packages which haven't been
+    /// downloaded yet rather than downloading them, adding them to `missing`
+    pub defer_downloads: bool,
+    pub missing: RefCell<Vec<PackageId>>,
 
     host: Layout,
-    target: Option<Layout>,
-    target_info: TargetInfo,
+    primary_packages: HashSet<PackageId>,
+    /// The layouts and information of each of the requested targets, in the
+    /// order of `BuildConfig::requested_targets`
+    targets: Vec<Layout>,
+    target_info: Vec<TargetInfo>,
     host_info: TargetInfo,
     profiles: &'a Profiles,
 }
@@ -61,25 +81,53 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                build_config: BuildConfig,
                profiles: &'a Profiles) -> CargoResult<Context<'a, 'cfg>> {
 
-        let dest = if build_config.release { "release" } else { "debug" };
-        let host_layout = try!(Layout::new(ws, None, &dest));
-        let target_layout = match build_config.requested_target.as_ref() {
-            Some(target) => {
-                Some(try!(Layout::new(ws, Some(&target), &dest)))
-            }
-            None => None,
+        let dest = match build_config.custom_profile {
+            Some(ref name) => name.clone(),
+            None if build_config.release => "release".to_string(),
+            None => "debug".to_string(),
         };
+        let host_layout = try!(Layout::new(ws, None, &dest));
+        let mut target_layouts = Vec::new();
+        for target in build_config.requested_targets.iter() {
+            target_layouts.push(try!(Layout::new(ws, Some(target), &dest)));
+        }
 
         let engine = build_config.exec_engine.as_ref().cloned().unwrap_or({
             Arc::new(Box::new(ProcessEngine))
         });
+
+        // Share the jobserver of a parent `make` or Cargo if there's one, and
+        // otherwise create our own to hand down to rustc and build scripts.
+        let inherited = match Client::from_env() {
+            Ok(client) => client,
+            Err(var) => {
+                try!(config.shell().warn(format!(
+                    "the jobserver advertised in `{}` isn't available, so \
+                     `-j` limits this build on its own\n\
+                     `make` only passes its jobserver to recipes which run \
+                     `$(MAKE)` or are marked recursive with a `+` prefix",
+                    var)));
+                None
+            }
+        };
+        let jobserver = match inherited {
+            Some(client) => client,
+            None => {
+                let limit = (build_config.jobs as usize).saturating_sub(1);
+                try!(Client::new(limit).chain_error(|| {
+                    internal("failed to create jobserver")
+                }))
+            }
+        };
         Ok(Context {
             host: host_layout,
-            target: target_layout,
+            primary_packages: ws.members().map(|p| p.package_id().clone())
+                                .collect(),
+            targets: target_layouts,
             resolve: resolve,
             packages: packages,
             config: config,
-            target_info: TargetInfo::default(),
+            target_info: Vec::new(),
             host_info: TargetInfo::default(),
             compilation: Compilation::new(config),
             build_state: Arc::new(BuildState::new(&build_config)),
@@ -91,6 +139,12 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             build_scripts: HashMap::new(),
             build_explicit_deps: HashMap::new(),
             links: Links::new(),
+            jobserver: jobserver,
+            build_times: HashMap::new(),
+            build_plan: BuildPlan::new(),
+            host_features: HashMap::new(),
+            defer_downloads: false,
+            missing: RefCell::new(Vec::new()),
         })
     }
 
@@ -102,19 +156,20 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         try!(self.host.prepare().chain_error(|| {
             internal(format!("couldn't prepare build directories"))
         }));
-        match self.target {
-            Some(ref mut target) => {
-                try!(target.prepare().chain_error(|| {
-                    internal(format!("couldn't prepare build directories"))
-                }));
-            }
-            None => {}
+        for target in self.targets.iter_mut() {
+            try!(target.prepare().chain_error(|| {
+                internal(format!("couldn't prepare build directories"))
+            }));
         }
 
+        // The outputs of the first target are the ones run or tested
+        let first = Kind::Target(0);
         self.compilation.root_output =
-                self.layout(root, Kind::Target).proxy().dest().to_path_buf();
+                self.layout(root, first).proxy().dest().to_path_buf();
         self.compilation.deps_output =
-                self.layout(root, Kind::Target).proxy().deps().to_path_buf();
+                self.layout(root, first).proxy().deps().to_path_buf();
+        self.compilation.host_deps_output =
+                self.layout(root, Kind::Host).proxy().deps().to_path_buf();
         Ok(())
     }
 
@@ -130,11 +185,9 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         for unit in units {
             try!(self.visit_crate_type(unit, &mut crate_types));
         }
-        try!(self.probe_target_info_kind(&crate_types, Kind::Target));
-        if self.requested_target().is_none() {
-            self.host_info = self.target_info.clone();
-        } else {
-            try!(self.probe_target_info_kind(&crate_types, Kind::Host));
+        try!(self.probe_target_info_kind(&crate_types, Kind::Host));
+        for i in 0..self.build_config.requested_targets.len() {
+            try!(self.probe_target_info_kind(&crate_types, Kind::Target(i)));
         }
         Ok(())
     }
@@ -176,8 +229,8 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         for crate_type in crate_types {
             process.arg("--crate-type").arg(crate_type);
         }
-        if kind == Kind::Target {
-            process.arg("--target").arg(&self.target_triple());
+        if let Some(target) = self.requested_target(kind) {
+            process.arg("--target").arg(target);
         }
 
         let mut with_cfg = process.clone();
@@ -227,23 +280,22 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             None
         };
 
-        let info = match kind {
-            Kind::Target => &mut self.target_info,
-            Kind::Host => &mut self.host_info,
-        };
-        info.crate_types = map;
-        info.cfg = cfg;
+        let info = TargetInfo { crate_types: map, cfg: cfg };
+        match kind {
+            Kind::Target(_) => self.target_info.push(info),
+            Kind::Host => self.host_info = info,
+        }
         Ok(())
     }
 
     /// Returns the appropriate directory layout for either a plugin or not.
     pub fn layout(&self, pkg: &Package, kind: Kind) -> LayoutProxy {
-        let primary = pkg.package_id() == self.resolve.root();
+        let primary = self.is_primary_package(pkg.package_id());
         match kind {
             Kind::Host => LayoutProxy::new(&self.host, primary),
-            Kind::Target => LayoutProxy::new(self.target.as_ref()
-                                                 .unwrap_or(&self.host),
-                                             primary),
+            Kind::Target(i) => LayoutProxy::new(self.targets.get(i)
+                                                    .unwrap_or(&self.host),
+                                                primary),
         }
     }
 
@@ -257,31 +309,84 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         }
     }
 
+    /// Returns the build script directories of all of the layouts which don't
+    /// belong to any package of the dependency graph anymore, and which no
+    /// other workspace sharing the target directory has used. With `release`
+    /// this workspace gives up the shared ones instead.
+    pub fn stale_build_dirs(&self, release: bool)
+                            -> CargoResult<Vec<PathBuf>> {
+        let live = self.resolve.iter()
+                       .chain(self.resolve.replacements().values())
+                       .collect::<Vec<_>>();
+        let mut ret = Vec::new();
+        for layout in Some(&self.host).into_iter().chain(self.targets.iter()) {
+            let dirs = layout.stale_build_dirs(&live, release);
+            ret.extend(try!(dirs.chain_error(|| {
+                internal("failed to list the build script directories")
+            })));
+        }
+        Ok(ret)
+    }
+
     /// Return the host triple for this context
     pub fn host_triple(&self) -> &str {
         &self.build_config.host_triple
     }
 
-    /// Return the target triple which this context is targeting.
-    pub fn target_triple(&self) -> &str {
-        self.requested_target().unwrap_or(self.host_triple())
+    /// Return the triple the units of `kind` are compiled for.
+    pub fn triple(&self, kind: Kind) -> &str {
+        self.requested_target(kind).unwrap_or(self.host_triple())
     }
 
-    /// Requested (not actual) target for the build
-    pub fn requested_target(&self) -> Option<&str> {
-        self.build_config.requested_target.as_ref().map(|s| &s[..])
+    /// Requested (not actual) target of the units of `kind`, which is `None`
+    /// for the host
+    pub fn requested_target(&self, kind: Kind) -> Option<&str> {
+        match kind {
+            Kind::Host => None,
+            Kind::Target(i) => {
+                self.build_config.requested_targets.get(i).map(|s| &s[..])
+            }
+        }
+    }
+
+    fn target_info(&self, kind: Kind) -> &TargetInfo {
+        match kind {
+            Kind::Host => &self.host_info,
+            Kind::Target(i) => {
+                self.target_info.get(i).unwrap_or(&self.host_info)
+            }
+        }
     }
 
     /// Get the metadata for a target in a specific profile
     pub fn target_metadata(&self, unit: &Unit) -> Option<Metadata> {
         let metadata = unit.target.metadata();
-        if unit.target.is_lib() && unit.profile.test {
+        if unit.profile.check {
+            // Checking only produces an `.rmeta` file, which must not be
+            // mistaken for the one emitted while pipelining a build, so
+            // `cargo check` and `cargo build` don't invalidate each other.
+            let mut m = metadata.cloned().unwrap_or_else(|| {
+                unit.pkg.generate_metadata()
+            });
+            m.mix(&"check");
+            Some(m)
+        } else if unit.target.is_lib() && unit.profile.test {
             // Libs and their tests are built in parallel, so we need to make
             // sure that their metadata is different.
             metadata.cloned().map(|mut m| {
                 m.mix(&"test");
                 m
             })
+        } else if unit.target.is_lib() &&
+                  self.build_override() == Some(unit.profile) {
+            // A library may be needed both by build scripts or plugins and by
+            // the code they're built for, so the two builds must not
+            // overwrite each other.
+            let mut m = metadata.cloned().unwrap_or_else(|| {
+                unit.pkg.generate_metadata()
+            });
+            m.mix(&"build-override");
+            Some(m)
         } else if unit.target.is_bin() && unit.profile.test {
             // Make sure that the name of this test executable doesn't
             // conflict with a library that has the same name and is
@@ -289,10 +394,10 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             let mut metadata = unit.pkg.generate_metadata();
             metadata.mix(&format!("bin-{}", unit.target.name()));
             Some(metadata)
-        } else if unit.pkg.package_id() == self.resolve.root() &&
+        } else if self.is_primary_package(unit.pkg.package_id()) &&
                   !unit.profile.test {
-            // If we're not building a unit test then the root package never
-            // needs any metadata as it's guaranteed to not conflict with any
+            // If we're not building a unit test then workspace members never
+            // need any metadata as they're guaranteed to not conflict with any
             // other output filenames. This means that we'll have predictable
             // file names like `target/debug/libfoo.{a,so,rlib}` and such.
             None
@@ -301,6 +406,25 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         }
     }
 
+    /// Returns whether the metadata of `unit` is emitted ahead of the rest of
+    /// its compilation when pipelining is enabled.
+    ///
+    /// This is only the case for plain Rust libraries, as only other libraries
+    /// can be compiled against nothing but the metadata of their dependencies.
+    pub fn emits_metadata(&self, unit: &Unit) -> bool {
+        if !self.build_config.pipelining || unit.profile.doc ||
+           unit.profile.check || unit.profile.test || unit.profile.run_custom_build ||
+           unit.profile.rustc_args.is_some() || unit.target.for_host() {
+            return false
+        }
+        match *unit.target.kind() {
+            TargetKind::Lib(ref kinds) => {
+                kinds.iter().all(|k| *k == LibKind::Lib || *k == LibKind::Rlib)
+            }
+            _ => false,
+        }
+    }
+
     /// Returns the file stem for a given target/profile combo
     pub fn file_stem(&self, unit: &Unit) -> String {
         match self.target_metadata(unit) {
@@ -319,10 +443,13 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
     pub fn target_filenames(&self, unit: &Unit)
                             -> CargoResult<Vec<(String, bool)>> {
         let stem = self.file_stem(unit);
+        if unit.profile.check {
+            return Ok(vec![(format!("lib{}.rmeta", stem), false)])
+        }
         let info = if unit.target.for_host() {
             &self.host_info
         } else {
-            &self.target_info
+            self.target_info(unit.kind)
         };
 
         let mut ret = Vec::new();
@@ -362,18 +489,24 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                     for lib in libs {
                         try!(add(lib.crate_type(), lib.linkable()));
                     }
+                    if self.builds_doctest_rlib(unit) {
+                        try!(add("rlib", true));
+                    }
                 }
             }
         }
+        if self.emits_metadata(unit) {
+            ret.push((format!("lib{}.rmeta", stem), false));
+        }
         if ret.is_empty() {
             if unsupported.len() > 0 {
                 bail!("cannot produce {} for `{}` as the target `{}` \
                        does not support these crate types",
-                      unsupported.join(", "), unit.pkg, self.target_triple())
+                      unsupported.join(", "), unit.pkg, self.triple(unit.kind))
             }
             bail!("cannot compile `{}` as the target `{}` does not \
                    support any of the output crate types",
-                  unit.pkg, self.target_triple());
+                  unit.pkg, self.triple(unit.kind));
         }
         Ok(ret)
     }
@@ -416,7 +549,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 // If the dependency is optional, then we're only activating it
                 // if the corresponding feature was activated
                 if d.is_optional() {
-                    match self.resolve.features(id) {
+                    match self.unit_features(unit) {
                         Some(f) if f.contains(d.name()) => {}
                         _ => return false,
                     }
@@ -433,7 +566,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                         Ok(Unit {
                             pkg: pkg,
                             target: t,
-                            profile: self.lib_profile(id),
+                            profile: self.dep_lib_profile(unit, t),
                             kind: unit.kind.for_target(t),
                         })
                     })
@@ -520,9 +653,14 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             unit.pkg.dependencies().iter().filter(|d| {
                 d.name() == dep.name()
             }).any(|dep| {
+                // Examples documented with `doc = true` may use the
+                // development dependencies, like when they're built
                 match dep.kind() {
                     DepKind::Normal => self.dep_platform_activated(dep,
                                                                    unit.kind),
+                    DepKind::Development if unit.target.is_example() => {
+                        self.dep_platform_activated(dep, unit.kind)
+                    }
                     _ => false,
                 }
             })
@@ -559,8 +697,8 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         // Be sure to build/run the build script for documented libraries as
         ret.extend(self.dep_build_script(unit));
 
-        // If we document a binary, we need the library available
-        if unit.target.is_bin() {
+        // If we document a binary or an example, we need the library available
+        if unit.target.is_bin() || unit.target.is_example() {
             ret.extend(self.maybe_lib(unit));
         }
         Ok(ret)
@@ -589,28 +727,30 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             Unit {
                 pkg: unit.pkg,
                 target: t,
-                profile: self.lib_profile(unit.pkg.package_id()),
+                profile: self.dep_lib_profile(unit, t),
                 kind: unit.kind.for_target(t),
             }
         })
     }
 
-    fn dep_platform_activated(&self, dep: &Dependency, kind: Kind) -> bool {
+    pub fn dep_platform_activated(&self, dep: &Dependency, kind: Kind) -> bool {
         // If this dependency is only available for certain platforms,
         // make sure we're only enabling it for that platform.
         let platform = match dep.platform() {
             Some(p) => p,
             None => return true,
         };
-        let (name, info) = match kind {
-            Kind::Host => (self.host_triple(), &self.host_info),
-            Kind::Target => (self.target_triple(), &self.target_info),
-        };
-        platform.matches(name, info.cfg.as_ref().map(|cfg| &cfg[..]))
+        let info = self.target_info(kind);
+        platform.matches(self.triple(kind),
+                         info.cfg.as_ref().map(|cfg| &cfg[..]))
     }
 
     /// Gets a package for the given package id.
     pub fn get_package(&self, id: &PackageId) -> CargoResult<&'a Package> {
+        if self.defer_downloads && !self.packages.is_downloaded(id) {
+            self.missing.borrow_mut().push(id.clone());
+            bail!("`{}` hasn't been downloaded yet", id)
+        }
         self.packages.get(id)
     }
 
@@ -624,17 +764,46 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         self.target_config(kind).ar.as_ref().map(|s| s.as_ref())
     }
 
-    /// Get the target configuration for a particular host or target
-    fn target_config(&self, kind: Kind) -> &TargetConfig {
-        match kind {
-            Kind::Host => &self.build_config.host,
-            Kind::Target => &self.build_config.target,
+    /// Get the commands post-processing the linked outputs of `unit`. They
+    /// only apply to the executables and `cdylib` libraries of the workspace
+    /// members, not to tests.
+    pub fn post_process(&self, unit: &Unit) -> &[Vec<String>] {
+        let linked = match *unit.target.kind() {
+            TargetKind::Bin | TargetKind::Example => true,
+            TargetKind::Lib(ref libs) => {
+                libs.iter().any(|lib| lib.crate_type() == "cdylib")
+            }
+            _ => false,
+        };
+        if !linked || unit.profile.test || unit.profile.check ||
+           unit.profile.doc ||
+           !self.is_primary_package(unit.pkg.package_id()) {
+            return &[]
         }
+        &self.target_config(unit.kind).post_process
+    }
+
+    /// Returns the directory the post-processing commands of `unit` write
+    /// their outputs to, which is emptied before they run.
+    pub fn post_process_dir(&self, unit: &Unit) -> PathBuf {
+        self.out_dir(unit).join("post-process").join(self.file_stem(unit))
+    }
+
+    /// Get the target configuration for a particular host or target
+    pub fn target_config(&self, kind: Kind) -> &TargetConfig {
+        self.build_config.target_config(kind)
     }
 
     /// Number of jobs specified for this build
     pub fn jobs(&self) -> u32 { self.build_config.jobs }
 
+    /// Whether `unit` is a library compiled as an `rlib` in addition to its
+    /// own crate types so its doctests can be run, see `doctests_need_rlib`.
+    pub fn builds_doctest_rlib(&self, unit: &Unit) -> bool {
+        self.build_config.test && unit.target.doctests_need_rlib() &&
+            !unit.profile.test && !unit.profile.doc && !unit.profile.check
+    }
+
     pub fn lib_profile(&self, _pkg: &PackageId) -> &'a Profile {
         let (normal, test) = if self.build_config.release {
             (&self.profiles.release, &self.profiles.bench_deps)
@@ -648,18 +817,137 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         }
     }
 
+    /// Returns the profile of the library `target` which `parent` depends on.
+    ///
+    /// Libraries only need to be checked for `parent` to be checked, except
+    /// for plugins which have to be built to be loaded by the compiler.
+    /// Plugins, the dependencies of build scripts and everything they depend
+    /// on in turn are built with `build-override`, if it's set.
+    fn dep_lib_profile(&self, parent: &Unit, target: &Target) -> &'a Profile {
+        if let Some(profile) = self.build_override() {
+            if target.for_host() || parent.target.is_custom_build() ||
+               parent.profile == profile {
+                return profile
+            }
+        }
+        if parent.profile.check && !target.for_host() {
+            &self.profiles.check
+        } else {
+            self.lib_profile(parent.pkg.package_id())
+        }
+    }
+
+    /// Returns the profile of the units which only run on the host during the
+    /// build if `[profile.*.build-override]` makes it differ from the one of
+    /// the other units.
+    pub fn build_override(&self) -> Option<&'a Profile> {
+        if self.build_config.release {
+            self.profiles.release_build_override.as_ref()
+        } else {
+            self.profiles.dev_build_override.as_ref()
+        }
+    }
+
+    /// Returns `profile` with the settings which `[profile.*.package]`
+    /// overrides for the package `id`, first in the manifest and then in the
+    /// configuration.
+    pub fn effective_profile(&self, id: &PackageId, profile: &Profile)
+                             -> Profile {
+        let mut profile = profile.clone();
+        let overrides = if self.build_config.release {
+            &self.profiles.release_overrides
+        } else {
+            &self.profiles.dev_overrides
+        };
+        let config = &self.build_config.profile_overrides;
+        for o in overrides.iter().chain(config.iter()) {
+            if o.spec.matches(id) {
+                o.apply(&mut profile);
+            }
+        }
+        profile
+    }
+
     pub fn build_script_profile(&self, pkg: &PackageId) -> &'a Profile {
-        // TODO: should build scripts always be built with the same library
-        //       profile? How is this controlled at the CLI layer?
-        self.lib_profile(pkg)
+        match self.build_override() {
+            Some(profile) => profile,
+            None => self.lib_profile(pkg),
+        }
     }
 
+    /// Returns the extra flags to compile `unit` with: the ones implementing
+    /// `build.warnings`, then those from `RUSTFLAGS` and finally those
+    /// configured for the profile and for the package specifically.
+    ///
+    /// These are part of the fingerprint of each unit, so unlike `RUSTFLAGS`
+    /// the flags configured for a single package only cause that package to
+    /// be rebuilt when they change.
     pub fn rustflags_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
-        env_args(self.config, &self.build_config, unit.kind, "RUSTFLAGS")
+        let id = unit.pkg.package_id();
+        let mut args = Vec::new();
+        if self.is_primary_package(id) {
+            match self.build_config.warnings {
+                Warnings::Allow => args.push("-Awarnings".to_string()),
+                Warnings::Deny => args.push("-Dwarnings".to_string()),
+                Warnings::Warn => {}
+            }
+        }
+        args.extend(try!(env_args(self.config, &self.build_config, unit.kind,
+                                  "RUSTFLAGS")));
+        // Like `RUSTFLAGS`, these are meant for the target so they're kept
+        // away from build scripts and plugins when cross compiling.
+        let for_target = self.build_config.requested_targets.is_empty() ||
+                         unit.kind != Kind::Host;
+        if for_target {
+            let config = &self.build_config;
+            args.extend(config.profile_rustflags.iter().cloned());
+            for &(ref spec, ref flags) in config.package_rustflags.iter() {
+                if spec.matches(id) {
+                    args.extend(flags.iter().cloned());
+                }
+            }
+        }
+        // Only the code of the workspace members is measured, and build
+        // scripts aren't run under the tests
+        if self.build_config.coverage && self.is_primary_package(id) &&
+           !unit.target.is_custom_build() && for_target {
+            args.extend(test_coverage::RUSTFLAGS.iter().map(|s| s.to_string()));
+        }
+        Ok(args)
     }
 
     pub fn rustdocflags_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
-        env_args(self.config, &self.build_config, unit.kind, "RUSTDOCFLAGS")
+        let mut args = try!(env_args(self.config, &self.build_config,
+                                     unit.kind, "RUSTDOCFLAGS"));
+        // Part of the flags so that toggling it documents the members again
+        if self.build_config.doc_private_items &&
+           self.is_primary_package(unit.pkg.package_id()) {
+            args.push("--document-private-items".to_string());
+        }
+        Ok(args)
+    }
+
+    /// Returns the features `unit` is compiled with.
+    ///
+    /// When cross compiling, packages compiled for the host have their own
+    /// features, except for build scripts themselves: they're compiled once
+    /// per package, which may also be compiled for the target.
+    pub fn unit_features(&self, unit: &Unit) -> Option<&HashSet<String>> {
+        let id = unit.pkg.package_id();
+        let compiles_build_script = unit.target.is_custom_build() &&
+                                    !unit.profile.run_custom_build;
+        if !self.build_config.requested_targets.is_empty() &&
+           unit.kind == Kind::Host && !compiles_build_script {
+            return self.host_features.get(id)
+        }
+        self.resolve.features(id)
+    }
+
+    /// Returns whether `id` is a member of the workspace being built, whose
+    /// artifacts are placed directly in the output directory rather than in
+    /// `deps`.
+    pub fn is_primary_package(&self, id: &PackageId) -> bool {
+        self.primary_packages.contains(id)
     }
 
     pub fn show_warnings(&self, pkg: &PackageId) -> bool {
@@ -693,8 +981,8 @@ fn env_args(config: &Config,
     // This means that, e.g. even if the specified --target is the
     // same as the host, build scripts in plugins won't get
     // RUSTFLAGS.
-    let compiling_with_target = build_config.requested_target.is_some();
-    let is_target_kind = kind == Kind::Target;
+    let compiling_with_target = !build_config.requested_targets.is_empty();
+    let is_target_kind = kind != Kind::Host;
 
     if compiling_with_target && !is_target_kind {
         // This is probably a build script or plugin and we're
@@ -712,6 +1000,14 @@ fn env_args(config: &Config,
         return Ok(args.collect());
     }
 
+    // Then the flags configured for the target being compiled for, which is
+    // also the host unless cross compiling
+    if name == "RUSTFLAGS" {
+        if let Some(ref args) = build_config.target_config(kind).rustflags {
+            return Ok(args.clone())
+        }
+    }
+
     // Then the build.rustflags value
     let name = name.chars().flat_map(|c| c.to_lowercase()).collect::<String>();
     let key = format!("build.{}", name);
@@ -1,20 +1,24 @@
-use std::collections::{HashMap, BTreeSet, HashSet};
+use std::collections::{HashMap, BTreeMap, BTreeSet, HashSet};
+use std::env;
+use std::ffi::OsString;
 use std::fs;
 use std::path::{PathBuf, Path};
 use std::str;
 use std::sync::{Mutex, Arc};
 use std::process::{Stdio, Output};
 
-use core::PackageId;
-use util::{CargoResult, Human};
+use rustc_serialize::json;
+
+use core::{Package, PackageId};
+use util::{CargoResult, Human, human};
 use util::{internal, ChainError, profile, paths};
-use util::{Freshness, ProcessBuilder, read2};
+use util::{Freshness, ProcessBuilder, read2, machine_message, rusage};
 use util::errors::{process_error, ProcessError};
 
 use super::job::Work;
 use super::job_queue::JobState;
 use super::{fingerprint, Kind, Context, Unit};
-use super::CommandType;
+use super::{CommandType, CommandPrototype};
 
 /// Contains the parsed output of a custom build script.
 #[derive(Clone, Debug, Hash)]
@@ -27,16 +31,71 @@ pub struct BuildOutput {
     pub cfgs: Vec<String>,
     /// Metadata to pass to the immediate dependencies
     pub metadata: Vec<(String, String)>,
+    /// Names and versions of the native libraries linked to
+    pub native_versions: Vec<(String, String)>,
     /// Glob paths to trigger a rerun of this build script.
     pub rerun_if_changed: Vec<String>,
+    /// Environment variables whose changes trigger a rerun of this build
+    /// script.
+    pub rerun_if_env_changed: Vec<String>,
     /// Warnings generated by this build,
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Diagnostic>,
+    /// Errors generated by this build, which fail it
+    pub errors: Vec<Diagnostic>,
+}
+
+/// A warning or an error a build script reported with `cargo:warning=` or
+/// `cargo:error=`.
+///
+/// The value is either just the message, or a list of `key=value` fields
+/// separated by `;` where `file` and `line` locate the problem and the last
+/// field, `message`, takes the rest of the line. For example:
+///
+/// ```notrust
+/// cargo:error=file=src/foo.c;line=12;message=`foo.h` not found
+/// ```
+#[derive(Clone, Debug, Hash)]
+pub struct Diagnostic {
+    pub message: String,
+    /// The file the diagnostic is about, relative to the package root
+    pub file: Option<String>,
+    pub line: Option<u32>,
 }
 
 pub type BuildMap = HashMap<(PackageId, Kind), BuildOutput>;
 
+/// What the build script of a package told Cargo, as recorded in the
+/// `build-scripts.json` file of the output directory for other tools.
+#[derive(RustcEncodable, RustcDecodable, Clone)]
+pub struct BuildScriptInfo {
+    pub package_id: String,
+    /// The target triple the package was built for
+    pub target: String,
+    /// The name of the output directory, like `debug` or `release`
+    pub profile: String,
+    pub out_dir: String,
+    /// The `links` key of the manifest
+    pub links: Option<String>,
+    /// The metadata passed to dependents as `DEP_<links>_<key>`
+    pub metadata: BTreeMap<String, String>,
+    pub cfgs: Vec<String>,
+    pub linked_libs: Vec<String>,
+    pub linked_paths: Vec<String>,
+}
+
+/// What a build script said it depends on the last time it ran.
+pub struct BuildDeps {
+    /// The file the output of the build script was saved to
+    pub build_script_output: PathBuf,
+    pub rerun_if_changed: Vec<String>,
+    pub rerun_if_env_changed: Vec<String>,
+}
+
 pub struct BuildState {
     pub outputs: Mutex<BuildMap>,
+    /// Sizes in bytes of the `OUT_DIR` of the build scripts which have been
+    /// run, once they're done.
+    pub out_dir_sizes: Mutex<HashMap<(PackageId, Kind), u64>>,
     overrides: HashMap<(String, Kind), BuildOutput>,
 }
 
@@ -85,8 +144,27 @@ pub fn prepare<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     Ok((work_dirty.then(dirty), work_fresh.then(fresh), freshness))
 }
 
-fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
-                        -> CargoResult<(Work, Work)> {
+/// Returns the path of bubblewrap, which Cargo sandboxes build scripts with
+/// when no wrapper is configured to.
+fn find_bwrap() -> CargoResult<PathBuf> {
+    if cfg!(target_os = "linux") {
+        let path = env::var_os("PATH").unwrap_or(OsString::new());
+        for dir in env::split_paths(&path) {
+            let bwrap = dir.join("bwrap");
+            if bwrap.is_file() {
+                return Ok(bwrap)
+            }
+        }
+    }
+    bail!("`build.sandbox` is set but `bwrap` wasn't found on the PATH, set \
+           `build.build-script-wrapper` to a program restricting build \
+           scripts on this platform")
+}
+
+/// Returns the command running the build script of `unit`, with everything
+/// known before its dependencies' build scripts have run.
+pub fn build_script_command(cx: &Context, unit: &Unit)
+                            -> CargoResult<CommandPrototype> {
     let (script_output, build_output) = {
         (cx.layout(unit.pkg, Kind::Host).build(unit.pkg),
          cx.layout(unit.pkg, unit.kind).build_out(unit.pkg))
@@ -99,16 +177,42 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     // environment variables. Note that the profile-related environment
     // variables are not set with this the build script's profile but rather the
     // package's library profile.
-    let profile = cx.lib_profile(unit.pkg.package_id());
+    let profile = cx.effective_profile(unit.pkg.package_id(),
+                                       cx.lib_profile(unit.pkg.package_id()));
     let to_exec = to_exec.into_os_string();
-    let mut p = try!(super::process(CommandType::Host(to_exec), unit.pkg, cx));
+    let wrapper = cx.build_config.build_script_wrapper.clone();
+    let mut p = match wrapper {
+        // The wrapper is told where the script may write to, and runs it in
+        // turn with whatever restrictions it applies
+        Some(wrapper) => {
+            let program = CommandType::Host(wrapper.into_os_string());
+            let mut p = try!(super::process(program, unit.pkg, cx));
+            p.arg(&build_output).arg("--").arg(&to_exec);
+            p
+        }
+        None if cx.build_config.sandbox => {
+            let bwrap = try!(find_bwrap().chain_error(|| {
+                human(format!("the build script of `{}` can't be sandboxed",
+                              unit.pkg))
+            }));
+            let program = CommandType::Host(bwrap.into_os_string());
+            let mut p = try!(super::process(program, unit.pkg, cx));
+            p.arg("--ro-bind").arg("/").arg("/")
+             .arg("--dev").arg("/dev")
+             .arg("--proc").arg("/proc")
+             .arg("--tmpfs").arg("/tmp")
+             .arg("--bind").arg(&build_output).arg(&build_output)
+             .arg("--unshare-net")
+             .arg("--die-with-parent")
+             .arg("--").arg(&to_exec);
+            p
+        }
+        None => try!(super::process(CommandType::Host(to_exec), unit.pkg, cx)),
+    };
     p.env("OUT_DIR", &build_output)
      .env("CARGO_MANIFEST_DIR", unit.pkg.root())
      .env("NUM_JOBS", &cx.jobs().to_string())
-     .env("TARGET", &match unit.kind {
-         Kind::Host => cx.host_triple(),
-         Kind::Target => cx.target_triple(),
-     })
+     .env("TARGET", cx.triple(unit.kind))
      .env("DEBUG", &profile.debuginfo.to_string())
      .env("OPT_LEVEL", &profile.opt_level.to_string())
      .env("PROFILE", if cx.build_config.release {"release"} else {"debug"})
@@ -120,13 +224,27 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
         p.env("CARGO_MANIFEST_LINKS", links);
      }
 
+    // Let a `make` invoked by the build script join our jobserver, unless
+    // it's the one of a parent `make` which set these up itself.
+    if !cx.jobserver.is_inherited() {
+        let makeflags = cx.jobserver.makeflags();
+        p.env("MAKEFLAGS", &makeflags).env("MFLAGS", &makeflags);
+    }
+
     // Be sure to pass along all enabled features for this package, this is the
     // last piece of statically known information that we have.
-    if let Some(features) = cx.resolve.features(unit.pkg.package_id()) {
+    if let Some(features) = cx.unit_features(unit) {
         for feat in features.iter() {
             p.env(&format!("CARGO_FEATURE_{}", super::envify(feat)), "1");
         }
     }
+    Ok(p)
+}
+
+fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
+                        -> CargoResult<(Work, Work)> {
+    let mut p = try!(build_script_command(cx, unit));
+    let build_output = cx.layout(unit.pkg, unit.kind).build_out(unit.pkg);
 
     // Gather the set of native dependencies that this package has along with
     // some other variables to close over.
@@ -151,18 +269,28 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
                output_file.clone());
     let build_scripts = super::load_build_deps(cx, unit);
     let kind = unit.kind;
+    let json_messages = cx.build_config.json_messages;
+    let track_size = cx.build_config.out_dir_quota.is_some();
 
     // Check to see if the build script as already run, and if it has keep
     // track of whether it has told us about some explicit dependencies
     let prev_output = BuildOutput::parse_file(&output_file, &pkg_name).ok();
-    let rerun_if_changed = match prev_output {
-        Some(ref prev) => prev.rerun_if_changed.clone(),
-        None => Vec::new(),
+    let deps = BuildDeps {
+        build_script_output: output_file.clone(),
+        rerun_if_changed: prev_output.as_ref().map(|prev| {
+            prev.rerun_if_changed.clone()
+        }).unwrap_or(Vec::new()),
+        rerun_if_env_changed: prev_output.as_ref().map(|prev| {
+            prev.rerun_if_env_changed.clone()
+        }).unwrap_or(Vec::new()),
     };
-    cx.build_explicit_deps.insert(*unit, (output_file.clone(), rerun_if_changed));
+    cx.build_explicit_deps.insert(*unit, deps);
 
-    try!(fs::create_dir_all(&cx.layout(unit.pkg, Kind::Host).build(unit.pkg)));
-    try!(fs::create_dir_all(&cx.layout(unit.pkg, unit.kind).build(unit.pkg)));
+    for &kind in [Kind::Host, unit.kind].iter() {
+        let layout = cx.layout(unit.pkg, kind).proxy();
+        try!(fs::create_dir_all(&layout.build(unit.pkg)));
+        try!(layout.claim_build(unit.pkg));
+    }
 
     // Prepare the unit of "dirty work" which will actually run the custom build
     // command.
@@ -209,11 +337,29 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
         state.running(&p);
         let cmd = p.into_process_builder();
         let output = try!(stream_output(state, &cmd).map_err(|mut e| {
+            // The errors the build script reported tell what went wrong much
+            // better than everything it printed
+            let errors = e.output.as_ref().and_then(|output| {
+                BuildOutput::parse(&output.stdout, &pkg_name).ok()
+            }).map(|output| output.errors).unwrap_or(Vec::new());
+            if json_messages {
+                emit_diagnostics(&id, "error", &errors);
+            }
+            let details = if errors.is_empty() {
+                e.desc.clone()
+            } else {
+                render_errors(&errors)
+            };
             e.desc = format!("failed to run custom build command for `{}`\n{}",
-                             pkg_name, e.desc);
+                             pkg_name, details);
             Human(e)
         }));
         try!(paths::write(&output_file, &output.stdout));
+        if track_size {
+            let size = try!(paths::dir_size(&build_output));
+            build_state.out_dir_sizes.lock().unwrap()
+                       .insert((id.clone(), kind), size);
+        }
 
         // After the build command has finished running, we need to be sure to
         // remember all of its output so we can later discover precisely what it
@@ -223,6 +369,17 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
         // state informing what variables were discovered via our script as
         // well.
         let parsed_output = try!(BuildOutput::parse(&output.stdout, &pkg_name));
+        if json_messages {
+            emit_build_output(&id, &parsed_output);
+        }
+        if !parsed_output.errors.is_empty() {
+            if json_messages {
+                emit_diagnostics(&id, "error", &parsed_output.errors);
+            }
+            return Err(human(format!("build script of `{}` reported errors\n{}",
+                                     pkg_name,
+                                     render_errors(&parsed_output.errors))))
+        }
         build_state.insert(id, kind, parsed_output);
         Ok(())
     });
@@ -236,6 +393,9 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
             Some(output) => output,
             None => try!(BuildOutput::parse_file(&output_file, &pkg_name)),
         };
+        if json_messages {
+            emit_build_output(&id, &output);
+        }
         build_state.insert(id, kind, output);
         Ok(())
     });
@@ -243,16 +403,112 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     Ok((dirty, fresh))
 }
 
+fn emit_build_output(id: &PackageId, output: &BuildOutput) {
+    let library_paths = output.library_paths.iter().map(|p| {
+        p.display().to_string()
+    }).collect::<Vec<_>>();
+    machine_message::emit(machine_message::BuildScript {
+        package_id: id,
+        linked_libs: &output.library_links,
+        linked_paths: &library_paths,
+        cfgs: &output.cfgs,
+    });
+}
+
+/// Emits a JSON message for each of the `diagnostics` of the build script
+/// of `id`.
+pub fn emit_diagnostics(id: &PackageId, level: &str,
+                        diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics.iter() {
+        machine_message::emit(machine_message::BuildScriptMessage {
+            package_id: id,
+            level: level,
+            message: &diagnostic.message,
+            file: diagnostic.file.as_ref().map(|s| &s[..]),
+            line: diagnostic.line,
+        });
+    }
+}
+
+fn render_errors(errors: &[Diagnostic]) -> String {
+    errors.iter().map(|e| format!("error: {}", e.render()))
+          .collect::<Vec<_>>().join("\n")
+}
+
+/// Records what the build scripts of this build told Cargo in the
+/// `build-scripts.json` file of the output directory, keeping the entries of
+/// packages which weren't part of the build.
+pub fn save_build_script_info(cx: &Context, root: &Package)
+                              -> CargoResult<()> {
+    let dest = cx.layout(root, Kind::Host).proxy().dest().to_path_buf();
+    let profile = dest.file_name().unwrap().to_string_lossy().into_owned();
+    let path = dest.join("build-scripts.json");
+    let mut infos = read_build_script_info(&path).into_iter().map(|info| {
+        ((info.package_id.clone(), info.target.clone()), info)
+    }).collect::<BTreeMap<_, _>>();
+
+    let outputs = cx.build_state.outputs.lock().unwrap();
+    for (&(ref id, kind), output) in outputs.iter() {
+        let pkg = try!(cx.packages.get(id));
+        let target = cx.triple(kind);
+        let info = BuildScriptInfo {
+            package_id: id.to_string(),
+            target: target.to_string(),
+            profile: profile.clone(),
+            out_dir: cx.layout(pkg, kind).build_out(pkg).display().to_string(),
+            links: pkg.manifest().links().map(|s| s.to_string()),
+            metadata: output.metadata.iter().cloned().collect(),
+            cfgs: output.cfgs.clone(),
+            linked_libs: output.library_links.clone(),
+            linked_paths: output.library_paths.iter().map(|p| {
+                p.display().to_string()
+            }).collect(),
+        };
+        infos.insert((info.package_id.clone(), info.target.clone()), info);
+    }
+
+    let infos = infos.into_iter().map(|(_, info)| info).collect::<Vec<_>>();
+    paths::write(&path, json::encode(&infos).unwrap().as_bytes())
+}
+
+/// Reads a `build-scripts.json` file, which is missing until something has
+/// been built.
+pub fn read_build_script_info(path: &Path) -> Vec<BuildScriptInfo> {
+    paths::read(path).ok().and_then(|contents| {
+        json::decode(&contents).ok()
+    }).unwrap_or(Vec::new())
+}
+
+/// Warns when the build script directories of the packages which aren't part
+/// of the dependency graph anymore take up more than `quota` bytes.
+pub fn warn_stale_build_dirs(cx: &Context, quota: u64) -> CargoResult<()> {
+    let dirs = try!(cx.stale_build_dirs(false));
+    let mut size = 0;
+    for dir in dirs.iter() {
+        size += try!(paths::dir_size(dir));
+    }
+    if size > quota {
+        try!(cx.config.shell().warn(format!(
+            "{} build script directories of packages which aren't used \
+             anymore take up {} MB, run `cargo clean --out-dirs` to remove \
+             them", dirs.len(), size / (1024 * 1024))));
+    }
+    Ok(())
+}
+
 impl BuildState {
     pub fn new(config: &super::BuildConfig) -> BuildState {
         let mut overrides = HashMap::new();
         let i1 = config.host.overrides.iter().map(|p| (p, Kind::Host));
-        let i2 = config.target.overrides.iter().map(|p| (p, Kind::Target));
+        let i2 = config.targets.iter().enumerate().flat_map(|(i, t)| {
+            t.overrides.iter().map(move |p| (p, Kind::Target(i)))
+        });
         for ((name, output), kind) in i1.chain(i2) {
             overrides.insert((name.clone(), kind), output.clone());
         }
         BuildState {
             outputs: Mutex::new(HashMap::new()),
+            out_dir_sizes: Mutex::new(HashMap::new()),
             overrides: overrides,
         }
     }
@@ -261,7 +517,15 @@ impl BuildState {
         self.outputs.lock().unwrap().insert((id, kind), output);
     }
 
-    fn has_override(&self, unit: &Unit) -> bool {
+    /// Whether the build script of `unit` is overridden, and so never run.
+    pub fn is_overridden(&self, unit: &Unit) -> bool {
+        let key = unit.pkg.manifest().links().map(|l| {
+            (l.to_string(), unit.kind)
+        });
+        key.map_or(false, |k| self.overrides.contains_key(&k))
+    }
+
+    pub fn has_override(&self, unit: &Unit) -> bool {
         let key = unit.pkg.manifest().links().map(|l| (l.to_string(), unit.kind));
         match key.and_then(|k| self.overrides.get(&k)) {
             Some(output) => {
@@ -287,8 +551,11 @@ impl BuildOutput {
         let mut library_links = Vec::new();
         let mut cfgs = Vec::new();
         let mut metadata = Vec::new();
+        let mut native_versions = Vec::new();
         let mut rerun_if_changed = Vec::new();
+        let mut rerun_if_env_changed = Vec::new();
         let mut warnings = Vec::new();
+        let mut errors = Vec::new();
         let whence = format!("build script of `{}`", pkg_name);
 
         for line in input.split(|b| *b == b'\n') {
@@ -327,8 +594,28 @@ impl BuildOutput {
                 "rustc-link-lib" => library_links.push(value.to_string()),
                 "rustc-link-search" => library_paths.push(PathBuf::from(value)),
                 "rustc-cfg" => cfgs.push(value.to_string()),
-                "warning" => warnings.push(value.to_string()),
+                "native-version" => {
+                    let mut parts = value.splitn(2, '=');
+                    match (parts.next(), parts.next()) {
+                        (Some(lib), Some(version)) => {
+                            native_versions.push((lib.to_string(),
+                                                  version.to_string()))
+                        }
+                        _ => bail!("native-version must be of the form \
+                                    `NAME=VERSION` in {}: `{}`", whence, line),
+                    }
+                }
+                "warning" => warnings.push(Diagnostic::parse(value)),
+                "error" => errors.push(Diagnostic::parse(value)),
+                "sandbox-violation" => errors.push(Diagnostic {
+                    message: format!("sandbox violation: {}", value),
+                    file: None,
+                    line: None,
+                }),
                 "rerun-if-changed" => rerun_if_changed.push(value.to_string()),
+                "rerun-if-env-changed" => {
+                    rerun_if_env_changed.push(value.to_string())
+                }
                 _ => metadata.push((key.to_string(), value.to_string())),
             }
         }
@@ -338,8 +625,11 @@ impl BuildOutput {
             library_links: library_links,
             cfgs: cfgs,
             metadata: metadata,
+            native_versions: native_versions,
             rerun_if_changed: rerun_if_changed,
+            rerun_if_env_changed: rerun_if_env_changed,
             warnings: warnings,
+            errors: errors,
         })
     }
 
@@ -375,6 +665,68 @@ impl BuildOutput {
     }
 }
 
+impl Diagnostic {
+    /// Parses a `warning` or `error` value, either a plain message or of the
+    /// form `file=..;line=..;message=..`. Values which don't follow the latter
+    /// form, such as `line=too long`, are taken as a plain message.
+    fn parse(value: &str) -> Diagnostic {
+        Diagnostic::parse_structured(value).unwrap_or_else(|| {
+            Diagnostic {
+                message: value.to_string(),
+                file: None,
+                line: None,
+            }
+        })
+    }
+
+    fn parse_structured(value: &str) -> Option<Diagnostic> {
+        let mut diagnostic = Diagnostic {
+            message: String::new(),
+            file: None,
+            line: None,
+        };
+        let mut rest = value;
+        loop {
+            if rest.starts_with("message=") {
+                diagnostic.message = rest["message=".len()..].to_string();
+                return Some(diagnostic)
+            }
+            let (field, remaining) = match rest.find(';') {
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+                None => return None,
+            };
+            let mut parts = field.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("file"), Some(file)) if diagnostic.file.is_none() => {
+                    diagnostic.file = Some(file.to_string());
+                }
+                (Some("line"), Some(line)) if diagnostic.line.is_none() => {
+                    match line.parse::<u32>() {
+                        Ok(line) => diagnostic.line = Some(line),
+                        Err(..) => return None,
+                    }
+                }
+                _ => return None,
+            }
+            rest = remaining;
+        }
+    }
+
+    /// Renders the diagnostic like those of the compiler, with its location
+    /// on a line of its own.
+    pub fn render(&self) -> String {
+        match (&self.file, self.line) {
+            (&Some(ref file), Some(line)) => {
+                format!("{}\n  --> {}:{}", self.message, file, line)
+            }
+            (&Some(ref file), None) => {
+                format!("{}\n  --> {}", self.message, file)
+            }
+            (&None, _) => self.message.clone(),
+        }
+    }
+}
+
 /// Compute the `build_scripts` map in the `Context` which tracks what build
 /// scripts each package depends on.
 ///
@@ -477,7 +829,7 @@ fn stream_output(state: &JobState, cmd: &ProcessBuilder)
                 state.stderr(&s);
             }
         }));
-        child.wait()
+        rusage::wait(&mut child)
     })().map_err(|e| {
         let msg = format!("could not exeute process {}", cmd);
         process_error(&msg, Some(e), None, None)
@@ -76,6 +76,13 @@ impl CommandPrototype {
         self
     }
 
+    pub fn wrapped<T: AsRef<OsStr>>(&mut self, wrapper: T)
+                                    -> &mut CommandPrototype {
+        self.builder.wrapped(wrapper);
+        self
+    }
+
+    pub fn get_program(&self) -> &OsString { self.builder.get_program() }
     pub fn get_args(&self) -> &[OsString] { self.builder.get_args() }
     pub fn get_cwd(&self) -> Option<&Path> { self.builder.get_cwd() }
 
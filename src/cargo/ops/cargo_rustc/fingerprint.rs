@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::env;
 use std::fs::{self, File, OpenOptions};
 use std::hash::{self, Hasher};
 use std::io::prelude::*;
@@ -90,12 +92,12 @@ pub fn prepare_target<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
 /// compiler flags, compiler version, etc. This structure is not simply a
 /// `String` due to the fact that some fingerprints cannot be calculated lazily.
 ///
-/// Path sources, for example, use the mtime of the corresponding dep-info file
-/// as a fingerprint (all source files must be modified *before* this mtime).
-/// This dep-info file is not generated, however, until after the crate is
-/// compiled. As a result, this structure can be thought of as a fingerprint
-/// to-be. The actual value can be calculated via `hash()`, but the operation
-/// may fail as some files may not have been generated.
+/// Path sources, for example, use a hash of the contents of all files listed in
+/// the corresponding dep-info file as a fingerprint. This dep-info file is not
+/// generated, however, until after the crate is compiled. As a result, this
+/// structure can be thought of as a fingerprint to-be. The actual value can be
+/// calculated via `hash()`, but the operation may fail as some files may not
+/// have been generated.
 ///
 /// Note that dependencies are taken into account for fingerprints because rustc
 /// requires that whenever an upstream crate is recompiled that all downstream
@@ -112,16 +114,23 @@ pub struct Fingerprint {
     local: LocalFingerprint,
     memoized_hash: Mutex<Option<u64>>,
     rustflags: Vec<String>,
+    /// Values of the environment variables a build script depends on
+    env: Vec<(String, Option<String>)>,
 }
 
 #[derive(RustcEncodable, RustcDecodable, Hash)]
 enum LocalFingerprint {
     Precalculated(String),
     MtimeBased(MtimeSlot, PathBuf),
+    ContentBased(ContentSlot, PathBuf),
 }
 
 struct MtimeSlot(Mutex<Option<FileTime>>);
 
+/// The combined hash of all source files listed in a dep-info file, `None` if
+/// the dep-info file or any of those files are missing.
+struct ContentSlot(Mutex<Option<String>>);
+
 impl Fingerprint {
     fn update_local(&self) -> CargoResult<()> {
         match self.local {
@@ -132,6 +141,13 @@ impl Fingerprint {
                 let mtime = FileTime::from_last_modification_time(&meta);
                 *slot.0.lock().unwrap() = Some(mtime);
             }
+            LocalFingerprint::ContentBased(ref slot, ref dep_info) => {
+                let hash = try!(try!(dep_info_hash(dep_info)).chain_error(|| {
+                    internal(format!("failed to hash the sources listed in \
+                                      `{}`", dep_info.display()))
+                }));
+                *slot.0.lock().unwrap() = Some(hash);
+            }
             LocalFingerprint::Precalculated(..) => return Ok(())
         }
 
@@ -139,6 +155,18 @@ impl Fingerprint {
         Ok(())
     }
 
+    /// Whether this fingerprint is independent of the local filesystem, and
+    /// so describes the same outputs on every machine.
+    fn is_portable(&self) -> bool {
+        match self.local {
+            LocalFingerprint::Precalculated(..) => {
+                self.deps.iter().all(|&(_, ref dep)| dep.is_portable())
+            }
+            LocalFingerprint::MtimeBased(..) |
+            LocalFingerprint::ContentBased(..) => false,
+        }
+    }
+
     fn hash(&self) -> u64 {
         if let Some(s) = *self.memoized_hash.lock().unwrap() {
             return s
@@ -164,6 +192,10 @@ impl Fingerprint {
         if self.rustflags != old.rustflags {
             return Err(internal("RUSTFLAGS has changed"))
         }
+        if self.env != old.env {
+            bail!("environment variables have changed: {:?} != {:?}",
+                  self.env, old.env)
+        }
         match (&self.local, &old.local) {
             (&LocalFingerprint::Precalculated(ref a),
              &LocalFingerprint::Precalculated(ref b)) => {
@@ -181,6 +213,16 @@ impl Fingerprint {
                            paths are {:?} and {:?}", *a, *b, ap, bp)
                 }
             }
+            (&LocalFingerprint::ContentBased(ref a, ref ap),
+             &LocalFingerprint::ContentBased(ref b, ref bp)) => {
+                let a = a.0.lock().unwrap();
+                let b = b.0.lock().unwrap();
+                if *a != *b {
+                    bail!("contents of source files have changed: {:?} != \
+                           {:?}, dep-info files are {:?} and {:?}",
+                          *a, *b, ap, bp)
+                }
+            }
             _ => bail!("local fingerprint type has changed"),
         }
 
@@ -207,14 +249,15 @@ impl hash::Hash for Fingerprint {
             ref local,
             memoized_hash: _,
             ref rustflags,
+            ref env,
         } = *self;
-        (rustc, features, target, profile, deps, local, rustflags).hash(h)
+        (rustc, features, target, profile, deps, local, rustflags, env).hash(h)
     }
 }
 
 impl Encodable for Fingerprint {
     fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
-        e.emit_struct("Fingerprint", 6, |e| {
+        e.emit_struct("Fingerprint", 8, |e| {
             try!(e.emit_struct_field("rustc", 0, |e| self.rustc.encode(e)));
             try!(e.emit_struct_field("target", 1, |e| self.target.encode(e)));
             try!(e.emit_struct_field("profile", 2, |e| self.profile.encode(e)));
@@ -228,6 +271,7 @@ impl Encodable for Fingerprint {
                 }).collect::<Vec<_>>().encode(e)
             }));
             try!(e.emit_struct_field("rustflags", 6, |e| self.rustflags.encode(e)));
+            try!(e.emit_struct_field("env", 7, |e| self.env.encode(e)));
             Ok(())
         })
     }
@@ -238,7 +282,7 @@ impl Decodable for Fingerprint {
         fn decode<T: Decodable, D: Decoder>(d: &mut D) -> Result<T, D::Error> {
             Decodable::decode(d)
         }
-        d.read_struct("Fingerprint", 6, |d| {
+        d.read_struct("Fingerprint", 8, |d| {
             Ok(Fingerprint {
                 rustc: try!(d.read_struct_field("rustc", 0, decode)),
                 target: try!(d.read_struct_field("target", 1, decode)),
@@ -259,10 +303,12 @@ impl Decodable for Fingerprint {
                             deps: Vec::new(),
                             memoized_hash: Mutex::new(Some(hash)),
                             rustflags: Vec::new(),
+                            env: Vec::new(),
                         }))
                     }).collect()
                 },
                 rustflags: try!(d.read_struct_field("rustflags", 6, decode)),
+                env: try!(d.read_struct_field("env", 7, decode)),
             })
         })
     }
@@ -291,6 +337,24 @@ impl Decodable for MtimeSlot {
     }
 }
 
+impl hash::Hash for ContentSlot {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        self.0.lock().unwrap().hash(h)
+    }
+}
+
+impl Encodable for ContentSlot {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        self.0.lock().unwrap().encode(e)
+    }
+}
+
+impl Decodable for ContentSlot {
+    fn decode<D: Decoder>(d: &mut D) -> Result<ContentSlot, D::Error> {
+        Ok(ContentSlot(Mutex::new(try!(Decodable::decode(d)))))
+    }
+}
+
 /// Calculates the fingerprint for a package/target pair.
 ///
 /// This fingerprint is used by Cargo to learn about when information such as:
@@ -301,8 +365,8 @@ impl Decodable for MtimeSlot {
 /// * The set of features a package is built with changes
 /// * The profile a target is compiled with changes (e.g. opt-level changes)
 ///
-/// Information like file modification time is only calculated for path
-/// dependencies and is calculated in `calculate_target_fresh`.
+/// Information like the contents of source files is only calculated for path
+/// dependencies, see `dep_info_hash`.
 fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
                        -> CargoResult<Arc<Fingerprint>> {
     if let Some(s) = cx.fingerprints.get(unit) {
@@ -312,7 +376,7 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     // First, calculate all statically known "salt data" such as the profile
     // information (compiler flags), the compiler version, activated features,
     // and target configuration.
-    let features = cx.resolve.features(unit.pkg.package_id());
+    let features = cx.unit_features(unit);
     let features = features.map(|s| {
         let mut v = s.iter().collect::<Vec<_>>();
         v.sort();
@@ -338,8 +402,8 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     // And finally, calculate what our own local fingerprint is
     let local = if use_dep_info(unit) {
         let dep_info = dep_info_loc(cx, unit);
-        let mtime = try!(dep_info_mtime_if_fresh(&dep_info));
-        LocalFingerprint::MtimeBased(MtimeSlot(Mutex::new(mtime)), dep_info)
+        let hash = try!(dep_info_hash(&dep_info));
+        LocalFingerprint::ContentBased(ContentSlot(Mutex::new(hash)), dep_info)
     } else {
         let fingerprint = try!(pkg_fingerprint(cx, unit.pkg));
         LocalFingerprint::Precalculated(fingerprint)
@@ -351,25 +415,70 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     } else {
         try!(cx.rustflags_args(unit))
     };
+    // Libraries compiled with pipelining have an extra output, so toggling it
+    // needs to rebuild them, and so does the rlib compiled for doctests. Only
+    // the units of a package whose settings are overridden are affected by
+    // changing the override.
+    let profile = cx.effective_profile(unit.pkg.package_id(), unit.profile);
+    let profile = if cx.emits_metadata(unit) {
+        util::hash_u64(&(&profile, "pipelined"))
+    } else {
+        util::hash_u64(&profile)
+    };
+    let profile = if cx.builds_doctest_rlib(unit) {
+        util::hash_u64(&(profile, "doctest-rlib"))
+    } else {
+        profile
+    };
+    // Changing the post-processing commands runs them again
+    let target = {
+        let commands = cx.post_process(unit);
+        if commands.is_empty() {
+            util::hash_u64(&unit.target)
+        } else {
+            util::hash_u64(&(&unit.target, commands))
+        }
+    };
+    // Starting or stopping to go through a wrapper rebuilds everything
+    let rustc = {
+        let version = &try!(cx.config.rustc()).verbose_version;
+        match cx.build_config.rustc_wrapper {
+            Some(ref wrapper) => util::hash_u64(&(version, wrapper)),
+            None => util::hash_u64(version),
+        }
+    };
     let fingerprint = Arc::new(Fingerprint {
-        rustc: util::hash_u64(&try!(cx.config.rustc()).verbose_version),
-        target: util::hash_u64(&unit.target),
-        profile: util::hash_u64(&unit.profile),
+        rustc: rustc,
+        target: target,
+        profile: profile,
         features: format!("{:?}", features),
         deps: deps,
         local: local,
         memoized_hash: Mutex::new(None),
         rustflags: extra_flags,
+        env: Vec::new(),
     });
     cx.fingerprints.insert(*unit, fingerprint.clone());
     Ok(fingerprint)
 }
 
+/// Returns the key under which the outputs of `unit` are stored in a shared
+/// artifact cache, or `None` if they depend on the state of the local
+/// filesystem and can't be shared.
+pub fn artifact_key<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
+                              -> CargoResult<Option<u64>> {
+    let fingerprint = try!(calculate(cx, unit));
+    if !fingerprint.is_portable() {
+        return Ok(None)
+    }
+    let triple = cx.triple(unit.kind);
+    Ok(Some(util::hash_u64(&(fingerprint.hash(), triple))))
+}
 
-// We want to use the mtime for files if we're a path source, but if we're a
-// git/registry source, then the mtime of files may fluctuate, but they won't
-// change so long as the source itself remains constant (which is the
-// responsibility of the source)
+// We want to look at the files listed in the dep-info if we're a path source,
+// but if we're a git/registry source, then the files won't change so long as
+// the source itself remains constant (which is the responsibility of the
+// source)
 fn use_dep_info(unit: &Unit) -> bool {
     let path = unit.pkg.summary().source_id().is_path();
     !unit.profile.doc && path
@@ -405,28 +514,33 @@ pub fn prepare_build_cmd<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     // is just a hash of what it was overridden with. Otherwise the fingerprint
     // is that of the entire package itself as we just consider everything as
     // input to the build script.
-    let (local, output_path) = {
+    let (local, env, output_path) = {
         let state = cx.build_state.outputs.lock().unwrap();
         match state.get(&(unit.pkg.package_id().clone(), unit.kind)) {
             Some(output) => {
                 let s = format!("overridden build state with hash: {}",
                                 util::hash_u64(output));
-                (LocalFingerprint::Precalculated(s), None)
+                (LocalFingerprint::Precalculated(s), Vec::new(), None)
             }
             None => {
-                let &(ref output, ref deps) = &cx.build_explicit_deps[unit];
+                let deps = &cx.build_explicit_deps[unit];
+                let output = &deps.build_script_output;
 
-                let local = if deps.is_empty() {
+                let local = if deps.rerun_if_changed.is_empty() &&
+                               deps.rerun_if_env_changed.is_empty() {
                     let s = try!(pkg_fingerprint(cx, unit.pkg));
                     LocalFingerprint::Precalculated(s)
                 } else {
-                    let deps = deps.iter().map(|p| unit.pkg.root().join(p));
-                    let mtime = mtime_if_fresh(output, deps);
+                    let paths = deps.rerun_if_changed.iter().map(|p| {
+                        unit.pkg.root().join(p)
+                    });
+                    let mtime = mtime_if_fresh(output, paths);
                     let mtime = MtimeSlot(Mutex::new(mtime));
                     LocalFingerprint::MtimeBased(mtime, output.clone())
                 };
 
-                (local, Some(output.clone()))
+                (local, env_values(&deps.rerun_if_env_changed),
+                 Some(output.clone()))
             }
         }
     };
@@ -440,6 +554,7 @@ pub fn prepare_build_cmd<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
         local: local,
         memoized_hash: Mutex::new(None),
         rustflags: Vec::new(),
+        env: env,
     };
     let compare = compare_old_fingerprint(&loc, &fingerprint);
     log_compare(unit, &compare);
@@ -448,21 +563,25 @@ pub fn prepare_build_cmd<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     // kind of fingerprint being recorded. If we started out, then the previous
     // run of the build script (or if it had never run before) may indicate to
     // use the `Precalculated` variant with the `pkg_fingerprint`. If the build
-    // script then prints `rerun-if-changed`, however, we need to record what's
-    // necessary for that fingerprint.
+    // script then prints `rerun-if-changed` or `rerun-if-env-changed`,
+    // however, we need to record what's necessary for that fingerprint.
     //
-    // Hence, if there were some `rerun-if-changed` directives forcibly change
-    // the kind of fingerprint over to the `MtimeBased` variant where the
-    // relevant mtime is the output path of the build script.
+    // Hence, if there were some of these directives forcibly change the kind
+    // of fingerprint over to the `MtimeBased` variant where the relevant mtime
+    // is the output path of the build script, along with the current values
+    // of the environment variables.
     let state = cx.build_state.clone();
     let key = (unit.pkg.package_id().clone(), unit.kind);
     let write_fingerprint = Work::new(move |_| {
         if let Some(output_path) = output_path {
             let outputs = state.outputs.lock().unwrap();
-            if !outputs[&key].rerun_if_changed.is_empty() {
+            let output = &outputs[&key];
+            if !output.rerun_if_changed.is_empty() ||
+               !output.rerun_if_env_changed.is_empty() {
                 let slot = MtimeSlot(Mutex::new(None));
                 fingerprint.local = LocalFingerprint::MtimeBased(slot,
                                                                  output_path);
+                fingerprint.env = env_values(&output.rerun_if_env_changed);
                 try!(fingerprint.update_local());
             }
         }
@@ -472,6 +591,11 @@ pub fn prepare_build_cmd<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     Ok((if compare.is_ok() {Fresh} else {Dirty}, write_fingerprint, Work::noop()))
 }
 
+/// Returns the current values of the environment variables `vars`.
+fn env_values(vars: &[String]) -> Vec<(String, Option<String>)> {
+    vars.iter().map(|var| (var.clone(), env::var(var).ok())).collect()
+}
+
 fn write_fingerprint(loc: &Path, fingerprint: &Fingerprint) -> CargoResult<()> {
     let hash = fingerprint.hash();
     debug!("write fingerprint: {}", loc.display());
@@ -538,7 +662,9 @@ fn log_compare(unit: &Unit, compare: &CargoResult<()>) {
     }
 }
 
-fn dep_info_mtime_if_fresh(dep_info: &Path) -> CargoResult<Option<FileTime>> {
+/// Returns the paths of all files listed in the dep-info file `dep_info`, or
+/// `None` if it doesn't exist.
+fn parse_dep_info(dep_info: &Path) -> CargoResult<Option<Vec<PathBuf>>> {
     macro_rules! fs_try {
         ($e:expr) => (match $e { Ok(e) => e, Err(..) => return Ok(None) })
     }
@@ -575,8 +701,100 @@ fn dep_info_mtime_if_fresh(dep_info: &Path) -> CargoResult<Option<FileTime>> {
         }
         paths.push(cwd.join(&file));
     }
+    Ok(Some(paths))
+}
 
-    Ok(mtime_if_fresh(&dep_info, paths.iter()))
+/// Hashes the contents of all files listed in the dep-info file `dep_info`,
+/// returning `None` if any of them (or the dep-info file itself) is missing.
+///
+/// Unlike modification times, contents survive git checkouts, copies between
+/// machines and clock skew, and touching a file doesn't change them. Reading
+/// every source file on each build would be slow though, so the hash of each
+/// file is recorded next to the dep-info file along with its size and mtime,
+/// and only files whose size or mtime differ from the recorded ones are hashed
+/// again.
+fn dep_info_hash(dep_info: &Path) -> CargoResult<Option<String>> {
+    let files = match try!(parse_dep_info(dep_info)) {
+        Some(files) => files,
+        None => return Ok(None),
+    };
+    let hashes_loc = hashes_loc(dep_info);
+    let old = read_file_hashes(&hashes_loc);
+
+    let mut new = Vec::new();
+    for path in files {
+        let meta = match fs::metadata(&path) {
+            Ok(meta) => meta,
+            Err(..) => {
+                info!("stale: {} -- missing", path.display());
+                return Ok(None)
+            }
+        };
+        let mtime = FileTime::from_last_modification_time(&meta);
+        let stamp = (mtime.seconds_relative_to_1970(), mtime.nanoseconds(),
+                     meta.len());
+        let hash = match old.get(&path) {
+            Some(&(old_stamp, ref hash)) if old_stamp == stamp => hash.clone(),
+            _ => {
+                let contents = try!(paths::read_bytes(&path));
+                util::to_hex(util::hash_u64(&contents))
+            }
+        };
+        new.push((path, (stamp, hash)));
+    }
+
+    let hashes = new.iter().map(|&(ref path, (_, ref hash))| (path, hash))
+                    .collect::<Vec<_>>();
+    let hash = util::to_hex(util::hash_u64(&hashes));
+    let changed = new.len() != old.len() || new.iter().any(|&(ref p, ref v)| {
+        old.get(p) != Some(v)
+    });
+    if changed {
+        try!(write_file_hashes(&hashes_loc, &new));
+    }
+    Ok(Some(hash))
+}
+
+type FileStamp = (u64, u32, u64);
+
+fn hashes_loc(dep_info: &Path) -> PathBuf {
+    let mut name = dep_info.file_name().unwrap().to_os_string();
+    name.push(".hashes");
+    dep_info.with_file_name(name)
+}
+
+/// Reads the file hashes recorded by `write_file_hashes`, ignoring anything
+/// which can't be understood as it'll just be recalculated.
+fn read_file_hashes(loc: &Path) -> HashMap<PathBuf, (FileStamp, String)> {
+    let contents = match paths::read(loc) {
+        Ok(contents) => contents,
+        Err(..) => return HashMap::new(),
+    };
+    contents.lines().filter_map(|line| {
+        let mut parts = line.splitn(5, ' ');
+        let secs = parts.next().and_then(|s| s.parse().ok());
+        let nanos = parts.next().and_then(|s| s.parse().ok());
+        let len = parts.next().and_then(|s| s.parse().ok());
+        let hash = parts.next();
+        let path = parts.next();
+        match (secs, nanos, len, hash, path) {
+            (Some(secs), Some(nanos), Some(len), Some(hash), Some(path)) => {
+                Some((PathBuf::from(path), ((secs, nanos, len),
+                                            hash.to_string())))
+            }
+            _ => None,
+        }
+    }).collect()
+}
+
+fn write_file_hashes(loc: &Path, hashes: &[(PathBuf, (FileStamp, String))])
+                     -> CargoResult<()> {
+    let mut contents = String::new();
+    for &(ref path, ((secs, nanos, len), ref hash)) in hashes {
+        contents.push_str(&format!("{} {} {} {} {}\n", secs, nanos, len, hash,
+                                   path.display()));
+    }
+    paths::write(loc, contents.as_bytes())
 }
 
 fn pkg_fingerprint(cx: &Context, pkg: &Package) -> CargoResult<String> {
@@ -636,6 +854,8 @@ fn filename(unit: &Unit) -> String {
         "test-"
     } else if unit.profile.doc {
         "doc-"
+    } else if unit.profile.check {
+        "check-"
     } else {
         ""
     };
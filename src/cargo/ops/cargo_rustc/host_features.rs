@@ -0,0 +1,176 @@
+//! The features of the packages compiled for the host when cross compiling.
+//!
+//! The resolver activates a single set of features for each package, the
+//! union of what all of its dependents ask for. When cross compiling, build
+//! dependencies and compiler plugins get their own copy of the packages they
+//! depend on, compiled for the host, so those copies are instead compiled with
+//! the features requested along the edges leading to them from the host side
+//! of the graph. This keeps features only the target needs, which may not
+//! even build for the host, away from build scripts and plugins.
+
+use std::collections::{HashMap, HashSet};
+
+use core::{Dependency, Package, PackageId};
+use core::dependency::Kind as DepKind;
+use util::CargoResult;
+
+use super::{Context, Kind, Unit};
+
+/// The features a dependency is asked to activate by one of its dependents.
+#[derive(Default)]
+struct Request {
+    features: HashSet<String>,
+    default: bool,
+}
+
+type Edge<'a> = (&'a Dependency, &'a PackageId, Request);
+
+/// Computes the features of every package reachable from the host units of
+/// the build: the compiler plugins among `units`, and the build dependencies
+/// and compiler plugins of the packages compiled for the target.
+pub fn resolve<'a, 'cfg>(cx: &Context<'a, 'cfg>, units: &[Unit<'a>])
+                         -> CargoResult<HashMap<PackageId, HashSet<String>>> {
+    let mut requests = HashMap::new();
+    let mut pending = Vec::new();
+
+    for unit in units.iter().filter(|u| u.kind == Kind::Host) {
+        let id = unit.pkg.package_id();
+        let request = Request {
+            features: resolved(cx, id),
+            default: false,
+        };
+        add(&mut requests, &mut pending, id, request);
+    }
+
+    // The packages on the target side of the graph are compiled with the
+    // features of the resolve, and the host side of the graph starts at their
+    // build dependencies and plugins, for each of the targets.
+    let mut visited = HashSet::new();
+    let mut stack = units.iter().filter(|u| u.kind != Kind::Host)
+                         .map(|u| (u.pkg.package_id(), u.kind))
+                         .collect::<Vec<_>>();
+    while let Some((id, kind)) = stack.pop() {
+        if !visited.insert((id, kind)) {
+            continue
+        }
+        let pkg = try!(cx.get_package(id));
+        let activated = resolved(cx, id);
+        for (dep, dep_id, request) in try!(edges(cx, pkg, &activated, kind)) {
+            let dep_pkg = try!(cx.get_package(dep_id));
+            let is_plugin = dep_pkg.targets().iter().any(|t| {
+                t.is_lib() && t.for_host()
+            });
+            if dep.kind() == DepKind::Build || is_plugin {
+                add(&mut requests, &mut pending, dep_id, request);
+            } else {
+                stack.push((dep_id, kind));
+            }
+        }
+    }
+
+    let mut features = HashMap::new();
+    while let Some(id) = pending.pop() {
+        let pkg = try!(cx.get_package(&id));
+        let activated = activate(pkg, &requests[&id]);
+        for (_, dep_id, request) in try!(edges(cx, pkg, &activated,
+                                               Kind::Host)) {
+            add(&mut requests, &mut pending, dep_id, request);
+        }
+        features.insert(id, activated);
+    }
+    Ok(features)
+}
+
+fn resolved(cx: &Context, id: &PackageId) -> HashSet<String> {
+    cx.resolve.features(id).cloned().unwrap_or(HashSet::new())
+}
+
+/// Merges `request` into the features requested of `id`, queueing it to be
+/// visited again if that activates anything new.
+fn add(requests: &mut HashMap<PackageId, Request>,
+       pending: &mut Vec<PackageId>,
+       id: &PackageId,
+       request: Request) {
+    let mut changed = !requests.contains_key(id);
+    let existing = requests.entry(id.clone()).or_insert(Request::default());
+    for feature in request.features {
+        changed = existing.features.insert(feature) || changed;
+    }
+    if request.default && !existing.default {
+        existing.default = true;
+        changed = true;
+    }
+    if changed && !pending.contains(id) {
+        pending.push(id.clone());
+    }
+}
+
+/// Expands the requested features of `pkg` into everything they activate,
+/// including the names of optional dependencies, like the resolver does.
+fn activate(pkg: &Package, request: &Request) -> HashSet<String> {
+    let features = pkg.summary().features();
+    let mut stack = request.features.iter().cloned().collect::<Vec<_>>();
+    if request.default && features.contains_key("default") {
+        stack.push("default".to_string());
+    }
+    let mut activated = HashSet::new();
+    while let Some(feature) = stack.pop() {
+        // `dep/feature` activates the dependency, the feature itself being
+        // requested of it in `edges`
+        let name = feature.splitn(2, '/').next().unwrap().to_string();
+        if !activated.insert(name.clone()) || name != feature {
+            continue
+        }
+        if let Some(values) = features.get(&name) {
+            stack.extend(values.iter().cloned());
+        }
+    }
+    activated
+}
+
+/// Returns the dependencies of `pkg` which are built when it's compiled for
+/// `kind` with the `activated` features, along with the features requested of
+/// each. Build dependencies are always compiled for the host.
+fn edges<'a, 'cfg>(cx: &Context<'a, 'cfg>,
+                   pkg: &'a Package,
+                   activated: &HashSet<String>,
+                   kind: Kind)
+                   -> CargoResult<Vec<Edge<'a>>> {
+    let id = pkg.package_id();
+    let mut ret = Vec::new();
+    for dep in pkg.dependencies() {
+        let dep_kind = if dep.kind() == DepKind::Build {Kind::Host} else {kind};
+        // Only workspace members, on the target side, have dev-dependencies
+        // in the resolve
+        if dep.kind() == DepKind::Development && kind == Kind::Host ||
+           !cx.dep_platform_activated(dep, dep_kind) ||
+           dep.is_optional() && !activated.contains(dep.name()) {
+            continue
+        }
+        let dep_id = cx.resolve.deps(id).find(|d| d.name() == dep.name());
+        let dep_id = match dep_id {
+            Some(dep_id) => dep_id,
+            None => continue,
+        };
+        let mut request = Request {
+            features: dep.features().iter().cloned().collect(),
+            default: dep.uses_default_features(),
+        };
+        for feature in activated.iter() {
+            let values = match pkg.summary().features().get(feature) {
+                Some(values) => values,
+                None => continue,
+            };
+            for value in values {
+                let mut parts = value.splitn(2, '/');
+                if parts.next() == Some(dep.name()) {
+                    request.features.extend(parts.next().map(|s| {
+                        s.to_string()
+                    }));
+                }
+            }
+        }
+        ret.push((dep, dep_id, request));
+    }
+    Ok(ret)
+}
@@ -2,7 +2,8 @@ use std::collections::HashSet;
 use std::collections::hash_map::HashMap;
 use std::fmt;
 use std::io::Write;
-use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
 
 use crossbeam::{self, Scope};
 use term::color::YELLOW;
@@ -10,9 +11,14 @@ use term::color::YELLOW;
 use core::{PackageId, Target, Profile};
 use util::{Config, DependencyQueue, Fresh, Dirty, Freshness};
 use util::{CargoResult, profile, internal};
+use util::rusage::{self, ResourceUsage};
+use util::jobserver::Client;
+use util::progress::Progress;
 
 use super::{Context, Kind, Unit};
+use super::custom_build::emit_diagnostics;
 use super::job::Job;
+use super::timings::UnitTime;
 use super::engine::CommandPrototype;
 
 /// A management structure of the entire dependency graph to compile.
@@ -30,8 +36,25 @@ pub struct JobQueue<'a> {
     compiled: HashSet<&'a PackageId>,
     documented: HashSet<&'a PackageId>,
     counts: HashMap<&'a PackageId, usize>,
+    jobserver: Client,
+    /// The job holding the jobserver token implicitly owned by Cargo
+    implicit_token: Option<Key<'a>>,
+    /// The line showing how far along the build is, which replaces the
+    /// line printed for each package compiled when it's shown
+    progress: Option<Progress>,
+    /// Jobs enqueued and finished, for the progress line
+    total: usize,
+    finished: usize,
+    /// Dirty jobs running, for the progress line
+    building: Vec<Key<'a>>,
 }
 
+/// Enqueues more of the graph to build into a `JobQueue` while it executes,
+/// returning whether there's anything left to enqueue.
+pub type MoreJobs<'a, 'cfg> = FnMut(&mut JobQueue<'a>,
+                                    &mut Context<'a, 'cfg>)
+                                    -> CargoResult<bool>;
+
 /// A helper structure for metadata about the state of a building package.
 struct PendingBuild {
     /// Number of jobs currently active
@@ -58,7 +81,12 @@ enum Message {
     Run(String),
     Stdout(String),
     Stderr(String),
-    Finish(CargoResult<()>),
+    /// Output of the compiler captured while the progress line is shown
+    Output(String),
+    Metadata,
+    /// The result of a job, and how long it took and what the processes it
+    /// ran used if it wasn't fresh
+    Finish(CargoResult<()>, Option<(Duration, Option<ResourceUsage>)>),
 }
 
 impl<'a> JobState<'a> {
@@ -73,6 +101,20 @@ impl<'a> JobState<'a> {
     pub fn stderr(&self, err: &str) {
         let _ = self.tx.send((self.key, Message::Stderr(err.to_string())));
     }
+
+    /// Prints what a process printed, whatever the verbosity, without getting
+    /// in the way of the progress line.
+    pub fn output(&self, out: &str) {
+        if !out.is_empty() {
+            let _ = self.tx.send((self.key, Message::Output(out.to_string())));
+        }
+    }
+
+    /// Signals that the metadata of the unit being compiled has been emitted,
+    /// so libraries depending on it can start compiling.
+    pub fn metadata_ready(&self) {
+        let _ = self.tx.send((self.key, Message::Metadata));
+    }
 }
 
 impl<'a> JobQueue<'a> {
@@ -88,6 +130,16 @@ impl<'a> JobQueue<'a> {
             compiled: HashSet::new(),
             documented: HashSet::new(),
             counts: HashMap::new(),
+            jobserver: cx.jobserver.clone(),
+            implicit_token: None,
+            progress: if cx.build_config.progress {
+                Some(Progress::new("Building", cx.config))
+            } else {
+                None
+            },
+            total: 0,
+            finished: 0,
+            building: Vec::new(),
         }
     }
 
@@ -98,8 +150,15 @@ impl<'a> JobQueue<'a> {
                          fresh: Freshness) -> CargoResult<()> {
         let key = Key::new(unit);
         let deps = try!(key.dependencies(cx));
-        self.queue.queue(Fresh, key, Vec::new(), &deps).push((job, fresh));
+        let keys = deps.iter().map(|&(dep, _)| dep).collect::<Vec<_>>();
+        self.queue.queue(Fresh, key, Vec::new(), &keys).push((job, fresh));
+        for &(dep, metadata_only) in deps.iter() {
+            if metadata_only {
+                self.queue.needs_only_metadata(&key, &dep);
+            }
+        }
         *self.counts.entry(key.pkg).or_insert(0) += 1;
+        self.total += 1;
         Ok(())
     }
 
@@ -108,16 +167,24 @@ impl<'a> JobQueue<'a> {
     /// This function will spawn off `config.jobs()` workers to build all of the
     /// necessary dependencies, in order. Freshness is propagated as far as
     /// possible along each dependency chain.
-    pub fn execute(&mut self, cx: &mut Context) -> CargoResult<()> {
+    ///
+    /// Whenever the jobs running leave time for it, `more` is called to
+    /// enqueue more of the graph, until it returns `false`.
+    pub fn execute<'cfg>(&mut self,
+                         cx: &mut Context<'a, 'cfg>,
+                         more: &mut MoreJobs<'a, 'cfg>) -> CargoResult<()> {
         let _p = profile::start("executing the job graph");
 
         crossbeam::scope(|scope| {
-            self.drain_the_queue(cx, scope)
+            self.drain_the_queue(cx, scope, more)
         })
     }
 
-    fn drain_the_queue(&mut self, cx: &mut Context, scope: &Scope<'a>)
-                       -> CargoResult<()> {
+    fn drain_the_queue<'cfg>(&mut self,
+                             cx: &mut Context<'a, 'cfg>,
+                             scope: &Scope<'a>,
+                             more: &mut MoreJobs<'a, 'cfg>)
+                             -> CargoResult<()> {
         let mut queue = Vec::new();
         trace!("queue: {:#?}", self.queue);
 
@@ -131,6 +198,7 @@ impl<'a> JobQueue<'a> {
         // successful and otherwise wait for pending work to finish if it failed
         // and then immediately return.
         let mut error = None;
+        let mut enqueuing = true;
         loop {
             while error.is_none() && self.active < self.jobs {
                 if !queue.is_empty() {
@@ -151,18 +219,58 @@ impl<'a> JobQueue<'a> {
                     break
                 }
             }
+
+            // Rather than waiting for a job to need attention, the rest of the
+            // graph is enqueued in the meantime.
+            let mut msg = None;
+            if error.is_none() && enqueuing {
+                match self.rx.try_recv() {
+                    Ok(m) => msg = Some(m),
+                    Err(_) => {
+                        if let Some(ref mut progress) = self.progress {
+                            progress.clear();
+                        }
+                        enqueuing = try!(more(self, cx));
+                        continue
+                    }
+                }
+            }
             if self.active == 0 {
                 break
             }
 
-            let (key, msg) = self.rx.recv().unwrap();
+            // The progress line is redrawn every so often to keep the time
+            // shown on it current, even if no job finishes in the meantime.
+            let (key, msg) = match msg {
+                Some(msg) => msg,
+                None if self.progress.is_some() => {
+                    self.tick_progress();
+                    let timeout = Duration::from_millis(250);
+                    match self.rx.recv_timeout(timeout) {
+                        Ok(msg) => msg,
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => {
+                            return Err(internal("job queue channel \
+                                                 disconnected"))
+                        }
+                    }
+                }
+                None => self.rx.recv().unwrap(),
+            };
+            if let Some(ref mut progress) = self.progress {
+                progress.clear();
+            }
 
             match msg {
                 Message::Run(cmd) => {
                     try!(cx.config.shell().verbose(|c| c.status("Running", &cmd)));
                 }
                 Message::Stdout(out) => {
-                    if cx.config.extra_verbose() {
+                    // Stdout is reserved for JSON messages when they're on
+                    if cx.config.extra_verbose() &&
+                       cx.build_config.json_messages {
+                        try!(write!(cx.config.shell().err(), "{}", out));
+                    } else if cx.config.extra_verbose() {
                         try!(write!(cx.config.shell().out(), "{}", out));
                     }
                 }
@@ -171,9 +279,28 @@ impl<'a> JobQueue<'a> {
                         try!(write!(cx.config.shell().err(), "{}", err));
                     }
                 }
-                Message::Finish(result) => {
+                Message::Output(out) => {
+                    try!(write!(cx.config.shell().err(), "{}", out));
+                }
+                Message::Metadata => {
+                    info!("metadata: {:?}", key);
+                    self.queue.metadata_ready(&key);
+                }
+                Message::Finish(result, cost) => {
                     info!("end: {:?}", key);
+                    if let Some((elapsed, usage)) = cost {
+                        let unit = format!("{}/{}/{:?}", key.target,
+                                           key.profile, key.kind);
+                        cx.build_times.entry((key.pkg.clone(), unit))
+                          .or_insert(UnitTime::default())
+                          .add(elapsed, usage);
+                    }
                     self.active -= 1;
+                    self.finished += 1;
+                    self.building.retain(|k| *k != key);
+                    if self.implicit_token == Some(key) {
+                        self.implicit_token = None;
+                    }
                     match result {
                         Ok(()) => try!(self.finish(key, cx)),
                         Err(e) => {
@@ -191,6 +318,9 @@ impl<'a> JobQueue<'a> {
             }
         }
 
+        if let Some(ref mut progress) = self.progress {
+            progress.clear();
+        }
         if self.queue.is_empty() {
             Ok(())
         } else if let Some(e) = error {
@@ -213,14 +343,42 @@ impl<'a> JobQueue<'a> {
 
         self.active += 1;
         *self.counts.get_mut(key.pkg).unwrap() -= 1;
+        if fresh == Dirty {
+            self.building.push(key);
+        }
+
+        // Fresh jobs don't run anything, and the first dirty job running uses
+        // the token implicitly owned by Cargo. Any other job waits for a token
+        // from the jobserver, shared with the processes spawned by jobs, before
+        // running.
+        let jobserver = if fresh == Fresh {
+            None
+        } else if self.implicit_token.is_none() {
+            self.implicit_token = Some(key);
+            None
+        } else {
+            Some(self.jobserver.clone())
+        };
 
         let my_tx = self.tx.clone();
         scope.spawn(move || {
-            let res = job.run(fresh, &JobState {
-                tx: my_tx.clone(),
-                key: key,
-            });
-            my_tx.send((key, Message::Finish(res))).unwrap();
+            let token = jobserver.map(|client| client.acquire());
+            let start = Instant::now();
+            let res = match token {
+                Some(Err(e)) => Err(From::from(e)),
+                _ => {
+                    job.run(fresh, &JobState {
+                        tx: my_tx.clone(),
+                        key: key,
+                    })
+                }
+            };
+            drop(token);
+            let cost = match fresh {
+                Dirty => Some((start.elapsed(), rusage::take())),
+                Fresh => None,
+            };
+            my_tx.send((key, Message::Finish(res, cost))).unwrap();
         });
 
         // Print out some nice progress information
@@ -229,15 +387,51 @@ impl<'a> JobQueue<'a> {
         Ok(())
     }
 
+    /// Redraws the progress line with the packages being built.
+    fn tick_progress(&mut self) {
+        let mut names = Vec::new();
+        for key in self.building.iter() {
+            let name = if key.profile.run_custom_build {
+                format!("{}(build)", key.pkg.name())
+            } else {
+                key.pkg.name().to_string()
+            };
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if let Some(ref mut progress) = self.progress {
+            progress.tick(self.finished, self.total, &names.join(", "));
+        }
+    }
+
     fn finish(&mut self, key: Key<'a>, cx: &mut Context) -> CargoResult<()> {
         if key.profile.run_custom_build && cx.show_warnings(key.pkg) {
             let output = cx.build_state.outputs.lock().unwrap();
             if let Some(output) = output.get(&(key.pkg.clone(), key.kind)) {
-                for warning in output.warnings.iter() {
-                    try!(cx.config.shell().warn(warning));
+                // These are diagnostics about the code being built just like
+                // the compiler's own, so they're printed even when quiet.
+                if cx.build_config.json_messages {
+                    emit_diagnostics(key.pkg, "warning", &output.warnings);
+                } else {
+                    for warning in output.warnings.iter() {
+                        try!(cx.config.shell().err()
+                               .say_status("warning:", warning.render(),
+                                           YELLOW, false));
+                    }
                 }
             }
         }
+        if key.profile.run_custom_build {
+            try!(self.check_out_dir_size(key, cx));
+
+            let state = cx.build_state.clone();
+            let output = state.outputs.lock().unwrap();
+            if let Some(output) = output.get(&(key.pkg.clone(), key.kind)) {
+                try!(cx.links.validate_native_versions(cx.resolve, key.pkg,
+                                                       key.kind, output));
+            }
+        }
         let state = self.pending.get_mut(&key).unwrap();
         state.amt -= 1;
         if state.amt == 0 {
@@ -246,6 +440,25 @@ impl<'a> JobQueue<'a> {
         Ok(())
     }
 
+    fn check_out_dir_size(&self, key: Key<'a>, cx: &Context)
+                          -> CargoResult<()> {
+        let quota = match cx.build_config.out_dir_quota {
+            Some(quota) => quota,
+            None => return Ok(()),
+        };
+        let sizes = cx.build_state.out_dir_sizes.lock().unwrap();
+        match sizes.get(&(key.pkg.clone(), key.kind)) {
+            Some(&size) if size > quota => {
+                const MB: u64 = 1024 * 1024;
+                cx.config.shell().warn(format!(
+                    "the build script of `{}` left {} MB in its OUT_DIR, more \
+                     than the {} MB allowed by `build.out-dir-quota`",
+                    key.pkg, size / MB, quota / MB))
+            }
+            _ => Ok(()),
+        }
+    }
+
     // This isn't super trivial because we don't want to print loads and
     // loads of information to the console, but we also want to produce a
     // faithful representation of what's happening. This is somewhat nuanced
@@ -265,12 +478,17 @@ impl<'a> JobQueue<'a> {
         }
 
         match fresh {
+            // The progress line shows the packages being compiled instead
+            Dirty if self.progress.is_some() => {}
             // Any dirty stage which runs at least one command gets printed as
             // being a compiled package
             Dirty => {
                 if key.profile.doc {
                     self.documented.insert(key.pkg);
                     try!(config.shell().status("Documenting", key.pkg));
+                } else if key.profile.check {
+                    self.compiled.insert(key.pkg);
+                    try!(config.shell().status("Checking", key.pkg));
                 } else {
                     self.compiled.insert(key.pkg);
                     try!(config.shell().status("Compiling", key.pkg));
@@ -296,8 +514,10 @@ impl<'a> Key<'a> {
         }
     }
 
+    /// Returns the keys this key depends on, along with whether only the
+    /// metadata of that dependency is needed to start compiling this key.
     fn dependencies<'cfg>(&self, cx: &Context<'a, 'cfg>)
-                          -> CargoResult<Vec<Key<'a>>> {
+                          -> CargoResult<Vec<(Key<'a>, bool)>> {
         let unit = Unit {
             pkg: try!(cx.get_package(self.pkg)),
             target: self.target,
@@ -305,15 +525,44 @@ impl<'a> Key<'a> {
             kind: self.kind,
         };
         let targets = try!(cx.dep_targets(&unit));
-        Ok(targets.iter().filter_map(|unit| {
+        let pipelined = cx.emits_metadata(&unit);
+        let mut keys = HashSet::new();
+        let mut deps = Vec::new();
+        for dep in targets.iter() {
             // Binaries aren't actually needed to *compile* tests, just to run
             // them, so we don't include this dependency edge in the job graph.
-            if self.target.is_test() && unit.target.is_bin() {
-                None
-            } else {
-                Some(Key::new(unit))
+            if self.target.is_test() && dep.target.is_bin() {
+                continue
+            }
+            keys.insert(Key::new(dep));
+            deps.push((Key::new(dep), pipelined && cx.emits_metadata(dep)));
+        }
+        if pipelined {
+            return Ok(deps)
+        }
+
+        // Anything linking needs the rlibs of all the libraries it links, but
+        // a library only waits for the metadata of the libraries it depends
+        // on, so it can be done before them. Wait for all of them to finish.
+        let mut visited = HashSet::new();
+        let mut stack = targets.into_iter().filter(|dep| {
+            cx.emits_metadata(dep)
+        }).collect::<Vec<_>>();
+        while let Some(dep) = stack.pop() {
+            if !visited.insert(Key::new(&dep)) {
+                continue
             }
-        }).collect())
+            for dep in try!(cx.dep_targets(&dep)) {
+                if !cx.emits_metadata(&dep) {
+                    continue
+                }
+                if keys.insert(Key::new(&dep)) {
+                    deps.push((Key::new(&dep), false));
+                }
+                stack.push(dep);
+            }
+        }
+        Ok(deps)
     }
 }
 
@@ -28,6 +28,10 @@
 //!             # is placed.
 //!             out/
 //!
+//!             # Empty marker of each workspace the package was built for,
+//!             # named after a hash of the path of its root.
+//!             .workspace-$hash
+//!
 //!     # This is the location at which the output of all old custom build
 //!     # commands are rooted
 //!     native/
@@ -43,13 +47,18 @@
 //!     # Hidden directory that holds all of the fingerprint files for all
 //!     # packages
 //!     .fingerprint/
+//!
+//!     # Intermediate results rustc keeps for the workspace members of
+//!     # profiles with `incremental = true`
+//!     incremental/
 //! ```
 
+use std::collections::HashSet;
 use std::fs;
 use std::io;
 use std::path::{PathBuf, Path};
 
-use core::{Package, Target, Workspace};
+use core::{Package, PackageId, Target, Workspace};
 use util::{Config, FileLock, CargoResult, Filesystem};
 use util::hex::short_hash;
 
@@ -60,6 +69,8 @@ pub struct Layout {
     build: PathBuf,
     fingerprint: PathBuf,
     examples: PathBuf,
+    incremental: PathBuf,
+    owner: String,
     _lock: FileLock,
 }
 
@@ -72,7 +83,7 @@ impl Layout {
     pub fn new(ws: &Workspace,
                triple: Option<&str>,
                dest: &str) -> CargoResult<Layout> {
-        let mut path = ws.config().target_dir(ws);
+        let mut path = ws.target_dir();
         // Flexible target specifications often point at filenames, so interpret
         // the target triple as a Path and then just use the file stem as the
         // component for the directory name.
@@ -80,10 +91,12 @@ impl Layout {
             path.push(Path::new(triple).file_stem().unwrap());
         }
         path.push(dest);
-        Layout::at(ws.config(), path)
+        Layout::at(ws.config(), path, ws.root())
     }
 
-    pub fn at(config: &Config, root: Filesystem) -> CargoResult<Layout> {
+    pub fn at(config: &Config,
+              root: Filesystem,
+              ws_root: &Path) -> CargoResult<Layout> {
         // For now we don't do any more finer-grained locking on the artifact
         // directory, so just lock the entire thing for the duration of this
         // compile.
@@ -96,6 +109,8 @@ impl Layout {
             build: root.join("build"),
             fingerprint: root.join(".fingerprint"),
             examples: root.join("examples"),
+            incremental: root.join("incremental"),
+            owner: format!(".workspace-{}", short_hash(&ws_root)),
             root: root,
             _lock: lock,
         })
@@ -111,6 +126,7 @@ impl Layout {
         try!(mkdir(&self.fingerprint));
         try!(mkdir(&self.examples));
         try!(mkdir(&self.build));
+        try!(mkdir(&self.incremental));
 
         return Ok(());
 
@@ -126,21 +142,82 @@ impl Layout {
     pub fn deps(&self) -> &Path { &self.deps }
     pub fn examples(&self) -> &Path { &self.examples }
     pub fn root(&self) -> &Path { &self.root }
+    pub fn incremental(&self) -> &Path { &self.incremental }
 
     pub fn fingerprint(&self, package: &Package) -> PathBuf {
-        self.fingerprint.join(&self.pkg_dir(package))
+        self.fingerprint.join(&self.pkg_dir(package.package_id()))
     }
 
     pub fn build(&self, package: &Package) -> PathBuf {
-        self.build.join(&self.pkg_dir(package))
+        self.build.join(&self.pkg_dir(package.package_id()))
     }
 
     pub fn build_out(&self, package: &Package) -> PathBuf {
         self.build(package).join("out")
     }
 
-    fn pkg_dir(&self, pkg: &Package) -> String {
-        format!("{}-{}", pkg.name(), short_hash(pkg))
+    /// Marks the build directory of `package` as used by the workspace of
+    /// this layout, which may share its target directory with others.
+    pub fn claim_build(&self, package: &Package) -> io::Result<()> {
+        let marker = self.build(package).join(&self.owner);
+        if fs::metadata(&marker).is_err() {
+            try!(fs::File::create(&marker));
+        }
+        Ok(())
+    }
+
+    /// Returns the directories under `build/` which were used by this
+    /// workspace but don't belong to any of the packages `live`, such as
+    /// those of previous versions of them.
+    ///
+    /// Directories which other workspaces sharing the target directory have
+    /// used as well are left out, unless `release` is set in which case this
+    /// workspace stops claiming them, so that the last one using them reports
+    /// them.
+    pub fn stale_build_dirs(&self, live: &[&PackageId], release: bool)
+                            -> io::Result<Vec<PathBuf>> {
+        if fs::metadata(&self.build).is_err() {
+            return Ok(Vec::new())
+        }
+        let live = live.iter().map(|id| self.pkg_dir(id))
+                       .collect::<HashSet<_>>();
+        let mut ret = Vec::new();
+        for entry in try!(fs::read_dir(&self.build)) {
+            let entry = try!(entry);
+            let is_live = entry.file_name().to_str().map(|name| {
+                live.contains(name)
+            }).unwrap_or(false);
+            if is_live || !try!(entry.file_type()).is_dir() {
+                continue
+            }
+            let dir = entry.path();
+            let mut claimed = false;
+            let mut shared = false;
+            for file in try!(fs::read_dir(&dir)) {
+                let name = try!(file).file_name();
+                let name = name.to_string_lossy();
+                if name == self.owner {
+                    claimed = true;
+                } else if name.starts_with(".workspace-") {
+                    shared = true;
+                }
+            }
+            if !claimed {
+                continue
+            }
+            if !shared {
+                ret.push(dir);
+            } else if release {
+                try!(fs::remove_file(dir.join(&self.owner)));
+            }
+        }
+        Ok(ret)
+    }
+
+    // The hash of a `Package` is the one of its id, so directories can be
+    // found from the id alone.
+    fn pkg_dir(&self, id: &PackageId) -> String {
+        format!("{}-{}", id.name(), short_hash(id))
     }
 }
 
@@ -1,12 +1,15 @@
 use std::collections::{HashMap, HashSet};
 
-use core::PackageId;
+use core::{PackageId, Resolve};
 use util::CargoResult;
-use super::Unit;
+use super::{BuildOutput, Kind, Unit};
 
 pub struct Links<'a> {
     validated: HashSet<&'a PackageId>,
     links: HashMap<String, &'a PackageId>,
+    /// The version of each native library reported by a build script, along
+    /// with the package of that build script.
+    native_versions: HashMap<(String, Kind), (String, PackageId)>,
 }
 
 impl<'a> Links<'a> {
@@ -14,6 +17,7 @@ impl<'a> Links<'a> {
         Links {
             validated: HashSet::new(),
             links: HashMap::new(),
+            native_versions: HashMap::new(),
         }
     }
 
@@ -46,4 +50,54 @@ impl<'a> Links<'a> {
         self.links.insert(lib.to_string(), unit.pkg.package_id());
         Ok(())
     }
+    /// Checks that the versions of native libraries the build script of `pkg`
+    /// reported with `cargo:native-version` agree with those reported by the
+    /// other build scripts, as only one version of a library can be linked
+    /// in.
+    pub fn validate_native_versions(&mut self,
+                                    resolve: &Resolve,
+                                    pkg: &PackageId,
+                                    kind: Kind,
+                                    output: &BuildOutput)
+                                    -> CargoResult<()> {
+        for &(ref lib, ref version) in output.native_versions.iter() {
+            let key = (lib.clone(), kind);
+            if let Some(&(ref prev_version, ref prev)) =
+                   self.native_versions.get(&key) {
+                if prev_version != version {
+                    bail!("native library `{}` is linked to with incompatible \
+                           versions, but only one of them can be linked \
+                           in\n\n  {} required by {}\n  {} required by {}",
+                          lib,
+                          prev_version, dependency_chain(resolve, prev),
+                          version, dependency_chain(resolve, pkg))
+                }
+                continue
+            }
+            self.native_versions.insert(key, (version.clone(), pkg.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Describes how `pkg` ends up in the dependency graph, as a chain of
+/// dependencies starting at a package nothing else depends on.
+fn dependency_chain(resolve: &Resolve, pkg: &PackageId) -> String {
+    let mut parents = HashMap::new();
+    for id in resolve.iter() {
+        for dep in resolve.deps(id) {
+            parents.entry(dep).or_insert(id);
+        }
+    }
+
+    let mut chain = vec![pkg];
+    while let Some(&parent) = parents.get(chain[chain.len() - 1]) {
+        // Dev-dependencies may form cycles
+        if chain.contains(&parent) {
+            break
+        }
+        chain.push(parent);
+    }
+    chain.iter().rev().map(|id| id.to_string())
+         .collect::<Vec<_>>().join(" -> ")
 }
@@ -1,58 +1,168 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::ffi::{OsStr, OsString};
 use std::fs;
+use std::io::{self, Write};
 use std::path::{self, PathBuf};
 use std::sync::Arc;
 
+use rustc_serialize::json::Json;
+
 use core::{Package, PackageId, PackageSet, Target, Resolve};
-use core::{Profile, Profiles, Workspace};
+use core::{PackageIdSpec, Profile, ProfileOverride, Profiles, Workspace};
+use core::Lto;
 use core::shell::ColorConfig;
+use ops;
 use util::{self, CargoResult, human};
 use util::{Config, internal, ChainError, profile, join_paths};
+use util::{ProcessError, process_error, machine_message};
+use util::crash_report::CrashReport;
 
 use self::job::{Job, Work};
-use self::job_queue::JobQueue;
+use self::job_queue::{JobQueue, JobState};
 
 pub use self::compilation::Compilation;
 pub use self::context::{Context, Unit};
 pub use self::engine::{CommandPrototype, CommandType, ExecEngine, ProcessEngine};
 pub use self::layout::{Layout, LayoutProxy};
+pub use self::remote::{RemoteEngine, EXECUTOR_UNAVAILABLE};
 pub use self::custom_build::{BuildOutput, BuildMap, BuildScripts};
+pub use self::custom_build::{BuildScriptInfo, read_build_script_info};
+pub use self::timings::{BuildTime, read_build_times};
 
+mod artifact_cache;
+mod build_plan;
 mod context;
 mod compilation;
 mod custom_build;
 mod engine;
 mod fingerprint;
+mod host_features;
 mod job;
 mod job_queue;
 mod layout;
 mod links;
+mod remote;
+mod timings;
 
+/// What a unit is compiled for, either the host or one of the targets, which
+/// are numbered in the order of `BuildConfig::requested_targets`. Everything
+/// is compiled for the host when no target is requested.
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord)]
-pub enum Kind { Host, Target }
+pub enum Kind { Host, Target(usize) }
 
 #[derive(Default, Clone)]
 pub struct BuildConfig {
     pub host_triple: String,
     pub host: TargetConfig,
-    pub requested_target: Option<String>,
-    pub target: TargetConfig,
+    /// The target platforms being compiled for, all of them in one build
+    pub requested_targets: Vec<String>,
+    /// The configuration of each of `requested_targets`
+    pub targets: Vec<TargetConfig>,
     pub jobs: u32,
     pub exec_engine: Option<Arc<Box<ExecEngine>>>,
+    pub artifact_cache: Option<String>,
+    pub cache_dir: Option<PathBuf>,
+    /// Directory caching compiled procedural macros, whether or not a
+    /// `cache_dir` is configured.
+    pub proc_macro_cache: Option<PathBuf>,
+    pub pipelining: bool,
+    pub json_messages: bool,
+    /// Whether a progress line is shown instead of a line for each package
+    /// compiled, the output of the compiler being captured so it doesn't
+    /// garble the progress line.
+    pub progress: bool,
+    pub warnings: Warnings,
+    pub profile_rustflags: Vec<String>,
+    pub package_rustflags: Vec<(PackageIdSpec, Vec<String>)>,
+    pub profile_overrides: Vec<ProfileOverride>,
+    pub out_dir_quota: Option<u64>,
+    /// Program build scripts are run through, such as a sandbox. Cargo only
+    /// delegates to it, what build scripts are allowed to do is up to it.
+    pub build_script_wrapper: Option<PathBuf>,
+    /// Whether build scripts must be denied network access and writes outside
+    /// of their `OUT_DIR`, by the wrapper if there's one or by Cargo.
+    pub sandbox: bool,
+    pub build_scripts: ExecutionPolicy,
+    pub proc_macros: ExecutionPolicy,
+    /// Program every invocation of the compiler is prefixed with, such as a
+    /// compilation cache.
+    pub rustc_wrapper: Option<PathBuf>,
     pub release: bool,
+    /// The custom profile being built, which has its own output directory.
+    pub custom_profile: Option<String>,
     pub test: bool,
     pub doc_all: bool,
+    /// Whether the private items of workspace members are documented
+    pub doc_private_items: bool,
+    /// Whether to print the invocations making up the build instead of
+    /// running them
+    pub build_plan: bool,
+    /// Whether the workspace members are instrumented for code coverage
+    pub coverage: bool,
+    /// Environment variables the binaries built for the target are run with
+    pub run_env: Vec<(String, String)>,
+}
+
+/// How the warnings of the workspace members are treated, as configured with
+/// `build.warnings`. Dependencies are always compiled with their warnings
+/// left as they are.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Warnings {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Default for Warnings {
+    fn default() -> Warnings {
+        Warnings::Warn
+    }
+}
+
+/// Which packages outside of the workspace may run code at build time, with
+/// their build script or their procedural macros, as configured in
+/// `[build-scripts]` and `[proc-macros]`. Workspace members always may.
+#[derive(Clone, Debug)]
+pub enum ExecutionPolicy {
+    AllowAll,
+    /// Only the packages matching one of the specifications, none with
+    /// `deny-all`
+    Allow(Vec<PackageIdSpec>),
+}
+
+impl Default for ExecutionPolicy {
+    fn default() -> ExecutionPolicy {
+        ExecutionPolicy::AllowAll
+    }
 }
 
 #[derive(Clone, Default)]
 pub struct TargetConfig {
     pub ar: Option<PathBuf>,
     pub linker: Option<PathBuf>,
+    /// Commands run on the executables and `cdylib` libraries once they're
+    /// linked, such as `wasm-bindgen` for WebAssembly targets.
+    pub post_process: Vec<Vec<String>>,
+    /// Flags passed to the compiler for this target, unless `RUSTFLAGS` is
+    /// set.
+    pub rustflags: Option<Vec<String>>,
+    /// Program and arguments the binaries built for this target are run with
+    /// by `cargo run` and `cargo test`, such as an emulator.
+    pub runner: Option<Vec<String>>,
     pub overrides: HashMap<String, BuildOutput>,
 }
 
+impl BuildConfig {
+    /// Get the target configuration for a particular host or target
+    pub fn target_config(&self, kind: Kind) -> &TargetConfig {
+        match kind {
+            Kind::Host => &self.host,
+            Kind::Target(i) => self.targets.get(i).unwrap_or(&self.host),
+        }
+    }
+}
+
 pub type PackagesToBuild<'a> = [(&'a Package, Vec<(&'a Target,&'a Profile)>)];
 
 // Returns a mapping of the root package plus its immediate dependencies to
@@ -65,21 +175,28 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
                                      build_config: BuildConfig,
                                      profiles: &'a Profiles)
                                      -> CargoResult<Compilation<'cfg>> {
-    let units = pkg_targets.iter().flat_map(|&(pkg, ref targets)| {
-        let default_kind = if build_config.requested_target.is_some() {
-            Kind::Target
-        } else {
-            Kind::Host
-        };
-        targets.iter().map(move |&(target, profile)| {
-            Unit {
-                pkg: pkg,
-                target: target,
-                profile: profile,
-                kind: if target.for_host() {Kind::Host} else {default_kind},
+    let kinds = if build_config.requested_targets.is_empty() {
+        vec![Kind::Host]
+    } else {
+        (0..build_config.requested_targets.len()).map(Kind::Target).collect()
+    };
+    let mut units = Vec::new();
+    for &kind in kinds.iter() {
+        for &(pkg, ref targets) in pkg_targets.iter() {
+            for &(target, profile) in targets.iter() {
+                let unit = Unit {
+                    pkg: pkg,
+                    target: target,
+                    profile: profile,
+                    kind: if target.for_host() {Kind::Host} else {kind},
+                };
+                // Units for the host are shared by all of the targets
+                if !units.contains(&unit) {
+                    units.push(unit);
+                }
             }
-        })
-    }).collect::<Vec<_>>();
+        }
+    }
 
     let root = try!(packages.get(resolve.root()));
     let mut cx = try!(Context::new(ws, resolve, packages, config,
@@ -89,19 +206,66 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
 
     try!(cx.prepare(root));
     try!(cx.probe_target_info(&units));
-    try!(custom_build::build_map(&mut cx, &units));
+    if !cx.build_config.requested_targets.is_empty() {
+        cx.host_features = try!(host_features::resolve(&cx, &units));
+    }
 
-    for unit in units.iter() {
-        // Build up a list of pending jobs, each of which represent
-        // compiling a particular package. No actual work is executed as
-        // part of this, that's all done next as part of the `execute`
-        // function which will run everything in order with proper
-        // parallelism.
-        try!(compile(&mut cx, &mut queue, unit));
+    if cx.build_config.build_plan {
+        try!(custom_build::build_map(&mut cx, &units));
+        for unit in units.iter() {
+            try!(compile(&mut cx, &mut queue, unit));
+        }
+        println!("{}", cx.build_plan.to_json());
+        return Ok(cx.compilation)
     }
 
-    // Now that we've figured out everything that we're going to do, do it!
-    try!(queue.execute(&mut cx));
+    // The code run at build time must all be allowed before any of it runs,
+    // so when it's restricted everything is downloaded and checked first.
+    let restricted = match (&cx.build_config.build_scripts,
+                            &cx.build_config.proc_macros) {
+        (&ExecutionPolicy::AllowAll, &ExecutionPolicy::AllowAll) => false,
+        _ => true,
+    };
+    if restricted {
+        try!(custom_build::build_map(&mut cx, &units));
+        for unit in units.iter() {
+            try!(compile(&mut cx, &mut queue, unit));
+        }
+        try!(check_policies(&cx));
+        try!(queue.execute(&mut cx, &mut |_, _| Ok(false)));
+    } else {
+        // Otherwise build up a list of pending jobs, each of which represent
+        // compiling a particular package, as the packages they need get
+        // downloaded. The jobs whose packages are all there already run in
+        // the meantime, with proper parallelism. Of the packages the build
+        // waits on, the ones starting the longest chains of crates are
+        // downloaded first.
+        let order = ops::download_order(resolve);
+        let mut complete = HashSet::new();
+        try!(queue.execute(&mut cx, &mut |queue, cx| {
+            let enqueued = complete.len();
+            let missing = try!(compile_downloaded(cx, queue, &units,
+                                                  &mut complete));
+            // The jobs enqueued get to start before the next download
+            if complete.len() > enqueued {
+                return Ok(true)
+            }
+            let next = order.iter().find(|id| missing.contains(**id));
+            match next.map(|id| *id).or(missing.iter().next()) {
+                Some(id) => {
+                    try!(packages.get(id));
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }));
+    }
+
+    if let Some(quota) = cx.build_config.out_dir_quota {
+        try!(custom_build::warn_stale_build_dirs(&cx, quota));
+    }
+    try!(custom_build::save_build_script_info(&cx, root));
+    try!(timings::save_build_times(&cx, root));
 
     for unit in units.iter() {
         let out_dir = cx.layout(unit.pkg, unit.kind).build_out(unit.pkg)
@@ -114,8 +278,10 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
             let dst = cx.out_dir(unit).join(filename);
             if unit.profile.test {
                 cx.compilation.tests.push((unit.pkg.clone(),
-                                           unit.target.name().to_string(),
+                                           unit.target.clone(),
                                            dst));
+            } else if unit.profile.check {
+                continue
             } else if unit.target.is_bin() || unit.target.is_example() {
                 cx.compilation.binaries.push(dst);
             } else if unit.target.is_lib() {
@@ -143,6 +309,9 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
         }
     }
 
+    cx.compilation.target_runner = cx.target_config(kinds[0]).runner.clone();
+    cx.compilation.run_env = cx.build_config.run_env.clone();
+
     let root_pkg = root.package_id();
     if let Some(feats) = cx.resolve.features(root_pkg) {
         cx.compilation.cfgs.extend(feats.iter().map(|feat| {
@@ -161,6 +330,100 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
     Ok(cx.compilation)
 }
 
+/// Enqueues the units of the graph below `units` whose packages have all
+/// been downloaded, returning the packages the rest of the graph waits on.
+fn compile_downloaded<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>,
+                                    jobs: &mut JobQueue<'a>,
+                                    units: &[Unit<'a>],
+                                    complete: &mut HashSet<Unit<'a>>)
+                                    -> CargoResult<HashSet<PackageId>> {
+    let mut ready = Vec::new();
+    let mut visited = HashSet::new();
+    cx.defer_downloads = true;
+    for unit in units.iter() {
+        try!(downloaded(cx, unit, complete, &mut visited, &mut ready));
+    }
+    cx.defer_downloads = false;
+    let missing: HashSet<_> = cx.missing.borrow_mut().drain(..).collect();
+
+    try!(custom_build::build_map(cx, &ready));
+    for unit in ready.iter() {
+        try!(compile(cx, jobs, unit));
+    }
+    Ok(missing)
+}
+
+/// Returns whether all of the packages needed to build `unit` have been
+/// downloaded, adding the units for which that's newly the case to `ready`.
+fn downloaded<'a, 'cfg>(cx: &Context<'a, 'cfg>,
+                        unit: &Unit<'a>,
+                        complete: &mut HashSet<Unit<'a>>,
+                        visited: &mut HashSet<Unit<'a>>,
+                        ready: &mut Vec<Unit<'a>>) -> CargoResult<bool> {
+    if complete.contains(unit) {
+        return Ok(true)
+    }
+    if !visited.insert(*unit) {
+        return Ok(false)
+    }
+    let missing = cx.missing.borrow().len();
+    let deps = match cx.dep_targets(unit) {
+        Ok(deps) => deps,
+        Err(_) if cx.missing.borrow().len() > missing => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let mut all = true;
+    for dep in deps.iter() {
+        all = try!(downloaded(cx, dep, complete, visited, ready)) && all;
+    }
+    if all {
+        complete.insert(*unit);
+        ready.push(*unit);
+    }
+    Ok(all)
+}
+
+/// Checks that the build scripts about to be run and the procedural macros
+/// about to be compiled are allowed by `[build-scripts]` and `[proc-macros]`.
+fn check_policies(cx: &Context) -> CargoResult<()> {
+    let scripts = cx.compiled.iter().filter(|unit| {
+        unit.profile.run_custom_build && !cx.build_state.is_overridden(unit)
+    });
+    try!(check_policy(cx, scripts, &cx.build_config.build_scripts,
+                      "build scripts", "build-scripts"));
+    let macros = cx.compiled.iter().filter(|unit| unit.target.is_proc_macro());
+    check_policy(cx, macros, &cx.build_config.proc_macros,
+                 "procedural macros", "proc-macros")
+}
+
+/// Fails naming all of the packages of `units` not allowed by `policy`.
+fn check_policy<'a, 'b, I>(cx: &Context,
+                           units: I,
+                           policy: &ExecutionPolicy,
+                           what: &str,
+                           key: &str) -> CargoResult<()>
+    where I: Iterator<Item=&'b Unit<'a>>, 'a: 'b
+{
+    let specs = match *policy {
+        ExecutionPolicy::AllowAll => return Ok(()),
+        ExecutionPolicy::Allow(ref specs) => specs,
+    };
+    let mut denied = units.map(|unit| unit.pkg.package_id()).filter(|id| {
+        !cx.is_primary_package(id) && !specs.iter().any(|s| s.matches(id))
+    }).collect::<Vec<_>>();
+    if denied.is_empty() {
+        return Ok(())
+    }
+    denied.sort();
+    denied.dedup();
+    let list = denied.iter().map(|id| format!("  {}\n", id))
+                     .collect::<String>();
+    bail!("the {} of these packages aren't allowed to run:\n{}\n\
+           {} run arbitrary code at build time, packages trusted to do so \
+           can be listed in `{}.allow` of .cargo/config", what, list, what,
+          key)
+}
+
 fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>,
                          jobs: &mut JobQueue<'a>,
                          unit: &Unit<'a>) -> CargoResult<()> {
@@ -175,20 +438,31 @@ fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>,
     try!(fingerprint::prepare_init(cx, unit));
     try!(cx.links.validate(unit));
 
-    let (dirty, fresh, freshness) = if unit.profile.run_custom_build {
-        try!(custom_build::prepare(cx, unit))
+    if cx.build_config.build_plan {
+        try!(build_plan::add(cx, unit));
     } else {
-        let (freshness, dirty, fresh) = try!(fingerprint::prepare_target(cx,
-                                                                         unit));
-        let work = if unit.profile.doc {
-            try!(rustdoc(cx, unit))
+        let (dirty, fresh, freshness) = if unit.profile.run_custom_build {
+            try!(custom_build::prepare(cx, unit))
         } else {
-            try!(rustc(cx, unit))
+            let (freshness, dirty, fresh) =
+                    try!(fingerprint::prepare_target(cx, unit));
+            let work = if unit.profile.doc {
+                try!(rustdoc(cx, unit))
+            } else {
+                let work = try!(rustc(cx, unit));
+                let work = try!(artifact_cache::prepare(cx, unit, work));
+                work.then(try!(post_process(cx, unit)))
+            };
+            let mut dirty = work.then(dirty);
+            let mut fresh = fresh;
+            if cx.build_config.json_messages {
+                dirty = dirty.then(try!(emit_artifact(cx, unit, false)));
+                fresh = fresh.then(try!(emit_artifact(cx, unit, true)));
+            }
+            (dirty, fresh, freshness)
         };
-        let dirty = work.then(dirty);
-        (dirty, fresh, freshness)
-    };
-    try!(jobs.enqueue(cx, unit, Job::new(dirty, fresh), freshness));
+        try!(jobs.enqueue(cx, unit, Job::new(dirty, fresh), freshness));
+    }
     drop(p);
 
     // Be sure to compile all dependencies of this target as well.
@@ -198,18 +472,165 @@ fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>,
     Ok(())
 }
 
-fn rustc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
-    let crate_types = unit.target.rustc_crate_types();
-    let mut rustc = try!(prepare_rustc(cx, crate_types, unit));
+fn emit_artifact(cx: &Context, unit: &Unit, fresh: bool) -> CargoResult<Work> {
+    let package_id = unit.pkg.package_id().clone();
+    let target = unit.target.clone();
+    let profile = unit.profile.clone();
+
+    // Documentation isn't tracked file by file
+    let mut filenames = Vec::new();
+    let mut debuginfo = Vec::new();
+    if !unit.profile.doc {
+        let out_dir = cx.out_dir(unit);
+        let triple = cx.triple(unit.kind);
+        for (filename, _linkable) in try!(cx.target_filenames(unit)) {
+            let path = out_dir.join(filename);
+            // The MSVC linker puts the debug info of executables and dynamic
+            // libraries in a `.pdb` file next to them.
+            let linked = path.extension().map_or(false, |ext| {
+                ext == "exe" || ext == "dll"
+            });
+            if unit.profile.debuginfo && triple.contains("-msvc") && linked {
+                debuginfo.push(path.with_extension("pdb"));
+            }
+            filenames.push(path);
+        }
+    }
 
-    let name = unit.pkg.name().to_string();
-    if !cx.show_warnings(unit.pkg.package_id()) {
-        if try!(cx.config.rustc()).cap_lints {
-            rustc.arg("--cap-lints").arg("allow");
-        } else {
-            rustc.arg("-Awarnings");
+    let post_process_dir = if cx.post_process(unit).is_empty() {
+        None
+    } else {
+        Some(cx.post_process_dir(unit))
+    };
+
+    Ok(Work::new(move |_| {
+        filenames.extend(debuginfo.into_iter().filter(|p| p.exists()));
+        if let Some(dir) = post_process_dir {
+            let entries = try!(fs::read_dir(&dir).chain_error(|| {
+                internal(format!("failed to read `{}`", dir.display()))
+            }));
+            for entry in entries {
+                filenames.push(try!(entry).path());
+            }
+        }
+        let filenames = filenames.iter().map(|p| {
+            p.display().to_string()
+        }).collect::<Vec<_>>();
+        machine_message::emit(machine_message::Artifact {
+            package_id: &package_id,
+            target: &target,
+            profile: &profile,
+            filenames: &filenames,
+            fresh: fresh,
+        });
+        Ok(())
+    }))
+}
+
+/// Runs the `target.$triple.post-process` commands on the files linked for
+/// `unit`, replacing `{artifact}` in their arguments with the path of each
+/// file and `{out-dir}` with a directory for their own outputs.
+fn post_process(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
+    let commands = cx.post_process(unit).to_vec();
+    if commands.is_empty() {
+        return Ok(Work::noop())
+    }
+    let out_dir = cx.post_process_dir(unit);
+    let mut processes = Vec::new();
+    for (filename, linkable) in try!(cx.target_filenames(unit)) {
+        if linkable || filename.ends_with(".rmeta") {
+            continue
+        }
+        let artifact = cx.out_dir(unit).join(&filename).display().to_string();
+        let dir = out_dir.display().to_string();
+        for command in commands.iter() {
+            let program = CommandType::Host(OsString::from(&command[0]));
+            let mut p = try!(process(program, unit.pkg, cx));
+            for arg in command[1..].iter() {
+                p.arg(arg.replace("{artifact}", &artifact)
+                         .replace("{out-dir}", &dir));
+            }
+            processes.push(p);
         }
     }
+
+    Ok(Work::new(move |state| {
+        if fs::metadata(&out_dir).is_ok() {
+            try!(fs::remove_dir_all(&out_dir));
+        }
+        try!(fs::create_dir_all(&out_dir));
+        for p in processes {
+            state.running(&p);
+            let cmd = p.to_string();
+            let output = try!(p.into_process_builder().exec_with_output()
+                               .chain_error(|| {
+                human(format!("failed to post-process with `{}`", cmd))
+            }));
+            state.stdout(&String::from_utf8_lossy(&output.stdout));
+            state.stderr(&String::from_utf8_lossy(&output.stderr));
+        }
+        Ok(())
+    }))
+}
+
+/// Returns the invocation of the compiler for `unit`, along with the one only
+/// emitting its metadata which precedes it when pipelining.
+fn rustc_commands(cx: &Context, unit: &Unit)
+                  -> CargoResult<(CommandPrototype, Option<CommandPrototype>)> {
+    let mut crate_types = unit.target.rustc_crate_types();
+    if cx.builds_doctest_rlib(unit) {
+        crate_types.push("rlib");
+    }
+    let emit = if unit.profile.check {
+        "dep-info,metadata"
+    } else {
+        "dep-info,link"
+    };
+    let mut rustc = try!(prepare_rustc(cx, &crate_types, unit, emit));
+
+    // With pipelining the metadata of a library is emitted by a separate,
+    // much quicker, invocation of the compiler first so the libraries
+    // depending on it can be compiled alongside its code generation.
+    let mut metadata = if cx.emits_metadata(unit) {
+        Some(try!(prepare_rustc(cx, &crate_types, unit, "metadata")))
+    } else {
+        None
+    };
+
+    let cap_lints = try!(cx.config.rustc()).cap_lints;
+    let rustflags = try!(cx.rustflags_args(unit));
+    let json_messages = cx.build_config.json_messages;
+    // The compiler only colors its output when printing to a terminal itself
+    let color = cx.build_config.progress &&
+                cx.config.shell().err().colored();
+    for cmd in Some(&mut rustc).into_iter().chain(metadata.as_mut()) {
+        if json_messages {
+            cmd.arg("--error-format").arg("json");
+        } else if color {
+            cmd.arg("--color").arg("always");
+        }
+        if !cx.show_warnings(unit.pkg.package_id()) {
+            if cap_lints {
+                cmd.arg("--cap-lints").arg("allow");
+            } else {
+                cmd.arg("-Awarnings");
+            }
+        }
+        cmd.args(&rustflags);
+    }
+    // The metadata invocation has already reported the warnings, so don't
+    // print them all a second time.
+    if metadata.is_some() {
+        rustc.arg("-A").arg("warnings");
+    }
+    Ok((rustc, metadata))
+}
+
+fn rustc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
+    let (mut rustc, mut metadata) = try!(rustc_commands(cx, unit));
+    let name = unit.pkg.name().to_string();
+    let json_messages = cx.build_config.json_messages;
+    let capture = cx.build_config.progress;
     let has_custom_args = unit.profile.rustc_args.is_some();
     let exec_engine = cx.exec_engine.clone();
 
@@ -225,7 +646,8 @@ fn rustc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
     // don't pass the `-l` flags.
     let pass_l_flag = unit.target.is_lib() ||
                       !unit.pkg.targets().iter().any(|t| t.is_lib());
-    let do_rename = unit.target.allows_underscores() && !unit.profile.test;
+    let do_rename = unit.target.allows_underscores() && !unit.profile.test &&
+                    !unit.profile.check;
     let real_name = unit.target.name().to_string();
     let crate_name = unit.target.crate_name();
 
@@ -236,8 +658,8 @@ fn rustc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
     }.with_extension("d");
     let dep_info_loc = fingerprint::dep_info_loc(cx, unit);
     let cwd = cx.config.cwd().to_path_buf();
-
-    rustc.args(&try!(cx.rustflags_args(unit)));
+    let target = unit.target.clone();
+    let crash = try!(CrashDetails::new(cx, unit));
 
     return Ok(Work::new(move |state| {
         // Only at runtime have we discovered what the extra -L and -l
@@ -247,11 +669,21 @@ fn rustc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
         // located somewhere in there.
         if let Some(build_deps) = build_deps {
             let build_state = build_state.outputs.lock().unwrap();
-            try!(add_native_deps(&mut rustc, &build_state, &build_deps,
-                                 pass_l_flag, &current_id));
-            try!(add_plugin_deps(&mut rustc, &build_state, &build_deps));
+            for cmd in Some(&mut rustc).into_iter().chain(metadata.as_mut()) {
+                try!(add_native_deps(cmd, &build_state, &build_deps,
+                                     pass_l_flag, &current_id));
+                try!(add_plugin_deps(cmd, &build_state, &build_deps));
+            }
         }
 
+        let output = if json_messages {
+            Output::Json(&current_id, &target)
+        } else if capture {
+            Output::Forward(state)
+        } else {
+            Output::Inherit
+        };
+
         // FIXME(rust-lang/rust#18913): we probably shouldn't have to do
         //                              this manually
         for &(ref filename, _linkable) in filenames.iter() {
@@ -261,8 +693,24 @@ fn rustc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
             }
         }
 
+        if let Some(metadata) = metadata {
+            state.running(&metadata);
+            let result = exec_rustc(&**exec_engine, metadata.clone(), &output);
+            if let Err(ref e) = result {
+                crash.check(&**exec_engine, metadata, e, &output);
+            }
+            try!(result.chain_error(|| {
+                human(format!("Could not compile `{}`.", name))
+            }));
+            state.metadata_ready();
+        }
+
         state.running(&rustc);
-        try!(exec_engine.exec(rustc).chain_error(|| {
+        let result = exec_rustc(&**exec_engine, rustc.clone(), &output);
+        if let Err(ref e) = result {
+            crash.check(&**exec_engine, rustc, e, &output);
+        }
+        try!(result.chain_error(|| {
             human(format!("Could not compile `{}`.", name))
         }));
 
@@ -319,6 +767,167 @@ fn rustc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
     }
 }
 
+/// Where the output of the compiler goes.
+enum Output<'a, 'b: 'a> {
+    /// Straight to the terminal
+    Inherit,
+    /// Through the job queue, which prints it along with its progress line
+    Forward(&'a JobState<'b>),
+    /// The diagnostics as JSON messages on stdout, naming the package and
+    /// target being compiled
+    Json(&'a PackageId, &'a Target),
+}
+
+// Runs the compiler or rustdoc, sending what it prints to `output`. Anything
+// the compiler prints besides JSON diagnostics goes to stderr to keep stdout
+// machine readable.
+fn exec_rustc(exec_engine: &ExecEngine,
+              rustc: CommandPrototype,
+              output: &Output)
+              -> Result<(), ProcessError> {
+    if let Output::Inherit = *output {
+        return exec_engine.exec(rustc)
+    }
+    let desc = rustc.to_string();
+    let (result, captured) = match exec_engine.exec_with_output(rustc) {
+        Ok(captured) => (Ok(()), Some(captured)),
+        // The captured output is forwarded below, so it's left out of the
+        // description of the error, though it's kept along with it for
+        // crashes to be recognized.
+        Err(e) => {
+            let msg = format!("Process didn't exit successfully: `{}`", desc);
+            let mut err = process_error(&msg, e.cause, e.exit.as_ref(), None);
+            err.output = e.output.clone();
+            (Err(err), e.output)
+        }
+    };
+    let captured = match captured {
+        Some(captured) => captured,
+        None => return result,
+    };
+    match *output {
+        Output::Inherit => {}
+        Output::Forward(state) => {
+            state.output(&String::from_utf8_lossy(&captured.stdout));
+            state.output(&String::from_utf8_lossy(&captured.stderr));
+        }
+        Output::Json(package_id, target) => {
+            let mut stderr = io::stderr();
+            let _ = stderr.write_all(&captured.stdout);
+            for line in String::from_utf8_lossy(&captured.stderr).lines() {
+                match Json::from_str(line) {
+                    Ok(message) => {
+                        machine_message::emit(machine_message::FromCompiler {
+                            package_id: package_id,
+                            target: target,
+                            message: message,
+                        });
+                    }
+                    Err(..) => { let _ = writeln!(stderr, "{}", line); }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// What goes into the report written when the compiler crashes while
+/// compiling a unit, gathered up front as the context isn't around by then.
+struct CrashDetails {
+    dir: PathBuf,
+    rustc_version: String,
+    packages: String,
+    config: String,
+}
+
+impl CrashDetails {
+    fn new(cx: &Context, unit: &Unit) -> CargoResult<CrashDetails> {
+        let id = unit.pkg.package_id();
+        let mut packages = format!("{}\n", id);
+        for dep in cx.resolve.deps(id) {
+            packages.push_str(&format!("    {}\n", dep));
+        }
+        let mut config = String::new();
+        for key in ["build", "target"].iter() {
+            if let Some(table) = try!(cx.config.get_table(key)) {
+                let mut entries = table.val.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                for (k, v) in entries {
+                    config.push_str(&format!("{}.{} = {:?}\n", key, k, v));
+                }
+            }
+        }
+        Ok(CrashDetails {
+            dir: cx.layout(unit.pkg, Kind::Host).proxy().dest()
+                   .join("crash-reports"),
+            rustc_version: try!(cx.config.rustc()).verbose_version.clone(),
+            packages: packages,
+            config: config,
+        })
+    }
+
+    /// Writes a crash report if `error` is the compiler having crashed while
+    /// running `rustc`.
+    ///
+    /// A crash is recognized by what the compiler printed when its output
+    /// was captured. Otherwise the exit status of a panic, which errors in
+    /// the code don't exit with, has to do. The compiler is then run again
+    /// with a backtrace for the report, which also confirms the crash when
+    /// the output wasn't captured the first time.
+    fn check(&self,
+             exec_engine: &ExecEngine,
+             mut rustc: CommandPrototype,
+             error: &ProcessError,
+             output: &Output) {
+        let crashed = match error.output {
+            Some(ref out) => {
+                String::from_utf8_lossy(&out.stderr)
+                       .contains("internal compiler error")
+            }
+            None => error.exit.as_ref().and_then(|s| s.code()) == Some(101),
+        };
+        if !crashed {
+            return
+        }
+        let desc = rustc.to_string();
+        rustc.env("RUST_BACKTRACE", "1");
+        let stderr = match exec_engine.exec_with_output(rustc) {
+            Ok(..) => return,
+            Err(e) => match e.output {
+                Some(out) => String::from_utf8_lossy(&out.stderr).into_owned(),
+                None => return,
+            },
+        };
+        if !stderr.contains("internal compiler error") {
+            return
+        }
+
+        let mut report = CrashReport::new("The compiler crashed");
+        report.add("Compiler invocation", &desc);
+        report.add("Compiler version", &self.rustc_version);
+        report.add("Package and its dependencies", &self.packages);
+        report.add("Configuration", &self.config);
+        report.add("Compiler output with backtrace", &stderr);
+        let msg = match report.write(&self.dir, "rustc-crash") {
+            Ok(path) => {
+                format!("note: the compiler crashed, a crash report was \
+                         written to `{}`, please attach it to a bug report\n",
+                        path.display())
+            }
+            Err(e) => {
+                format!("note: the compiler crashed, but writing a crash \
+                         report to `{}` failed: {}\n", self.dir.display(), e)
+            }
+        };
+        match *output {
+            Output::Forward(state) => state.output(&msg),
+            Output::Inherit | Output::Json(..) => {
+                let _ = io::stderr().write_all(msg.as_bytes());
+            }
+        }
+    }
+}
+
 fn load_build_deps(cx: &Context, unit: &Unit) -> Option<Arc<BuildScripts>> {
     cx.build_scripts.get(unit).cloned()
 }
@@ -348,11 +957,15 @@ fn add_plugin_deps(rustc: &mut CommandPrototype,
 }
 
 fn prepare_rustc(cx: &Context,
-                 crate_types: Vec<&str>,
-                 unit: &Unit) -> CargoResult<CommandPrototype> {
+                 crate_types: &[&str],
+                 unit: &Unit,
+                 emit: &str) -> CargoResult<CommandPrototype> {
     let mut base = try!(process(CommandType::Rustc, unit.pkg, cx));
-    build_base_args(cx, &mut base, unit, &crate_types);
-    build_plugin_args(&mut base, cx, unit);
+    if let Some(ref wrapper) = cx.build_config.rustc_wrapper {
+        base.wrapped(wrapper);
+    }
+    build_base_args(cx, &mut base, unit, crate_types);
+    build_plugin_args(&mut base, cx, unit, emit);
     try!(build_deps_args(&mut base, cx, unit));
     Ok(base)
 }
@@ -364,7 +977,7 @@ fn rustdoc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
            .cwd(cx.config.cwd())
            .arg("--crate-name").arg(&unit.target.crate_name());
 
-    if let Some(target) = cx.requested_target() {
+    if let Some(target) = cx.requested_target(unit.kind) {
         rustdoc.arg("--target").arg(target);
     }
 
@@ -377,7 +990,7 @@ fn rustdoc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
 
     rustdoc.arg("-o").arg(doc_dir);
 
-    if let Some(features) = cx.resolve.features(unit.pkg.package_id()) {
+    if let Some(features) = cx.unit_features(unit) {
         for feat in features {
             rustdoc.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
         }
@@ -400,6 +1013,7 @@ fn rustdoc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
     let build_state = cx.build_state.clone();
     let key = (unit.pkg.package_id().clone(), unit.kind);
     let exec_engine = cx.exec_engine.clone();
+    let capture = cx.build_config.progress;
 
     Ok(Work::new(move |state| {
         if let Some(output) = build_state.outputs.lock().unwrap().get(&key) {
@@ -408,7 +1022,8 @@ fn rustdoc(cx: &mut Context, unit: &Unit) -> CargoResult<Work> {
             }
         }
         state.running(&rustdoc);
-        exec_engine.exec(rustdoc).chain_error(|| {
+        let output = if capture {Output::Forward(state)} else {Output::Inherit};
+        exec_rustc(&**exec_engine, rustdoc, &output).chain_error(|| {
             human(format!("Could not document `{}`.", name))
         })
     }))
@@ -441,9 +1056,9 @@ fn build_base_args(cx: &Context,
                    crate_types: &[&str]) {
     let Profile {
         opt_level, lto, codegen_units, ref rustc_args, debuginfo,
-        debug_assertions, rpath, test, doc: _doc, run_custom_build,
-        ref panic, rustdoc_args: _,
-    } = *unit.profile;
+        debug_assertions, overflow_checks, incremental, rpath, test,
+        doc: _doc, run_custom_build, ref panic, rustdoc_args: _, check: _,
+    } = cx.effective_profile(unit.pkg.package_id(), unit.profile);
     assert!(!run_custom_build);
 
     // Move to cwd so the root_path() passed below is actually correct
@@ -467,7 +1082,7 @@ fn build_base_args(cx: &Context,
     let prefer_dynamic = (unit.target.for_host() &&
                           !unit.target.is_custom_build()) ||
                          (crate_types.contains(&"dylib") &&
-                          unit.pkg.package_id() != cx.resolve.root());
+                          !cx.is_primary_package(unit.pkg.package_id()));
     if prefer_dynamic {
         cmd.arg("-C").arg("prefer-dynamic");
     }
@@ -476,17 +1091,28 @@ fn build_base_args(cx: &Context,
         cmd.arg("-C").arg(&format!("opt-level={}", opt_level));
     }
 
-    if let Some(panic) = panic.as_ref() {
-        cmd.arg("-C").arg(format!("panic={}", panic));
+    // The test harness relies on unwinding to report failing tests
+    if !test {
+        if let Some(panic) = panic.as_ref() {
+            cmd.arg("-C").arg(format!("panic={}", panic));
+        }
     }
 
     // Disable LTO for host builds as prefer_dynamic and it are mutually
     // exclusive.
-    if unit.target.can_lto() && lto && !unit.target.for_host() {
+    let lto = if unit.target.can_lto() && !unit.target.for_host() {
+        lto
+    } else {
+        Lto::Off
+    };
+    if lto == Lto::Fat {
         cmd.args(&["-C", "lto"]);
     } else {
-        // There are some restrictions with LTO and codegen-units, so we
-        // only add codegen units when LTO is not used.
+        if lto == Lto::Thin {
+            cmd.args(&["-C", "lto=thin"]);
+        }
+        // There are some restrictions with fat LTO and codegen-units, so we
+        // only add codegen units when it's not used.
         if let Some(n) = codegen_units {
             cmd.arg("-C").arg(&format!("codegen-units={}", n));
         }
@@ -506,13 +1132,26 @@ fn build_base_args(cx: &Context,
         cmd.args(&["-C", "debug-assertions=off"]);
     }
 
+    // rustc enables overflow checks along with the debug assertions
+    if overflow_checks != debug_assertions {
+        let value = if overflow_checks {"on"} else {"off"};
+        cmd.arg("-C").arg(format!("overflow-checks={}", value));
+    }
+
+    // Dependencies rarely change, so caching their intermediate results
+    // would only take up space.
+    if incremental && cx.is_primary_package(unit.pkg.package_id()) {
+        let dir = cx.layout(unit.pkg, unit.kind).proxy().incremental();
+        cmd.arg("-C").arg(format!("incremental={}", dir.display()));
+    }
+
     if test && unit.target.harness() {
         cmd.arg("--test");
     } else if test {
         cmd.arg("--cfg").arg("test");
     }
 
-    if let Some(features) = cx.resolve.features(unit.pkg.package_id()) {
+    if let Some(features) = cx.unit_features(unit) {
         for feat in features.iter() {
             cmd.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
         }
@@ -529,7 +1168,8 @@ fn build_base_args(cx: &Context,
 }
 
 
-fn build_plugin_args(cmd: &mut CommandPrototype, cx: &Context, unit: &Unit) {
+fn build_plugin_args(cmd: &mut CommandPrototype, cx: &Context, unit: &Unit,
+                     emit: &str) {
     fn opt(cmd: &mut CommandPrototype, key: &str, prefix: &str,
            val: Option<&OsStr>)  {
         if let Some(val) = val {
@@ -540,11 +1180,10 @@ fn build_plugin_args(cmd: &mut CommandPrototype, cx: &Context, unit: &Unit) {
     }
 
     cmd.arg("--out-dir").arg(&cx.out_dir(unit));
-    cmd.arg("--emit=dep-info,link");
+    cmd.arg(&format!("--emit={}", emit));
 
-    if unit.kind == Kind::Target {
-        opt(cmd, "--target", "", cx.requested_target().map(|s| s.as_ref()));
-    }
+    opt(cmd, "--target", "",
+        cx.requested_target(unit.kind).map(|s| s.as_ref()));
 
     opt(cmd, "-C", "ar=", cx.ar(unit.kind).map(|s| s.as_ref()));
     opt(cmd, "-C", "linker=", cx.linker(unit.kind).map(|s| s.as_ref()));
@@ -568,20 +1207,27 @@ fn build_deps_args(cmd: &mut CommandPrototype, cx: &Context, unit: &Unit)
         cmd.env("OUT_DIR", &layout.build_out(unit.pkg));
     }
 
-    for unit in try!(cx.dep_targets(unit)).iter() {
-        if unit.target.linkable() {
-            try!(link_to(cmd, cx, unit));
+    // Libraries compiled with pipelining only need the metadata of the
+    // libraries they depend on, which may still be generating code, and
+    // checked dependencies don't have anything but their metadata.
+    let pipelined = cx.emits_metadata(unit);
+    for dep in try!(cx.dep_targets(unit)).iter() {
+        if dep.profile.check || (pipelined && cx.emits_metadata(dep)) {
+            try!(link_to(cmd, cx, dep, true));
+        } else if dep.target.linkable() {
+            try!(link_to(cmd, cx, dep, false));
         }
     }
 
     return Ok(());
 
-    fn link_to(cmd: &mut CommandPrototype, cx: &Context, unit: &Unit)
-               -> CargoResult<()> {
+    fn link_to(cmd: &mut CommandPrototype, cx: &Context, unit: &Unit,
+               metadata: bool) -> CargoResult<()> {
         let layout = cx.layout(unit.pkg, unit.kind);
 
         for (filename, linkable) in try!(cx.target_filenames(unit)) {
-            if !linkable {
+            if metadata != filename.ends_with(".rmeta") ||
+               (!metadata && !linkable) {
                 continue
             }
             let mut v = OsString::new();
@@ -609,6 +1255,7 @@ pub fn process(cmd: CommandType, pkg: &Package,
     let search_path = try!(join_paths(&search_path, util::dylib_path_envvar()));
     let mut cmd = try!(cx.compilation.process(cmd, pkg));
     cmd.env(util::dylib_path_envvar(), &search_path);
+    cmd.env("CARGO_MAKEFLAGS", &cx.jobserver.makeflags());
     Ok(cmd)
 }
 
@@ -626,8 +1273,8 @@ impl Kind {
         // that needs to be on the host we lift ourselves up to `Host`
         match *self {
             Kind::Host => Kind::Host,
-            Kind::Target if target.for_host() => Kind::Host,
-            Kind::Target => Kind::Target,
+            Kind::Target(_) if target.for_host() => Kind::Host,
+            Kind::Target(i) => Kind::Target(i),
         }
     }
 }
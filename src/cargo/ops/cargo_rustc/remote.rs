@@ -0,0 +1,135 @@
+//! Shipping compiler invocations to a remote execution service.
+//!
+//! When `build.remote-executor` is configured every `rustc` invocation is
+//! handed to that program instead of being run directly. The executor receives
+//! a description of the job as a single JSON object on stdin:
+//!
+//! ```json
+//! {
+//!     "program": "rustc",
+//!     "args": ["--crate-name", "foo", "src/lib.rs", ...],
+//!     "env": {"CARGO_PKG_NAME": "foo", ...},
+//!     "cwd": "/path/to/foo"
+//! }
+//! ```
+//!
+//! It is then responsible for uploading the inputs, running the job remotely
+//! and writing the outputs back into place. Whatever the executor prints is
+//! treated as the output of the compiler and its exit status as the exit
+//! status of the compiler, except for `EXECUTOR_UNAVAILABLE` which tells Cargo
+//! that the job couldn't be scheduled and should be run locally instead.
+//!
+//! Build scripts and `rustdoc` are always run locally.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+
+use rustc_serialize::json;
+
+use util::{ProcessError, process_error};
+use super::engine::{CommandPrototype, CommandType, ExecEngine};
+
+/// Exit status (`EX_TEMPFAIL`) an executor uses to request a local fallback.
+pub const EXECUTOR_UNAVAILABLE: i32 = 75;
+
+/// An `ExecEngine` which hands compilations off to an external executor.
+pub struct RemoteEngine {
+    executor: PathBuf,
+}
+
+#[derive(RustcEncodable)]
+struct RemoteJob {
+    program: String,
+    args: Vec<String>,
+    env: BTreeMap<String, Option<String>>,
+    cwd: Option<String>,
+}
+
+impl RemoteEngine {
+    pub fn new(executor: PathBuf) -> RemoteEngine {
+        RemoteEngine { executor: executor }
+    }
+
+    /// Runs `command` through the executor, returning `None` if the executor
+    /// asked for the job to be run locally.
+    fn run(&self, command: &CommandPrototype, capture: bool)
+           -> Result<Option<Output>, ProcessError> {
+        let job = RemoteJob {
+            program: command.get_program().to_string_lossy().into_owned(),
+            args: command.get_args().iter().map(|a| {
+                a.to_string_lossy().into_owned()
+            }).collect(),
+            env: command.get_envs().iter().map(|(k, v)| {
+                (k.clone(), v.as_ref().map(|v| v.to_string_lossy().into_owned()))
+            }).collect(),
+            cwd: command.get_cwd().map(|p| p.display().to_string()),
+        };
+        let job = json::encode(&job).unwrap();
+
+        let mut cmd = Command::new(&self.executor);
+        if let Some(cwd) = command.get_cwd() {
+            cmd.current_dir(cwd);
+        }
+        cmd.stdin(Stdio::piped());
+        if capture {
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let desc = format!("remote executor `{}` for {}",
+                           self.executor.display(), command);
+        let mut child = try!(cmd.spawn().map_err(|e| {
+            process_error(&format!("Could not execute {}", desc),
+                          Some(e), None, None)
+        }));
+        // The executor may well start printing before it's read the whole
+        // job, so the job is written while its output is read
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = thread::spawn(move || stdin.write_all(job.as_bytes()));
+        let output = try!(child.wait_with_output().map_err(|e| {
+            process_error(&format!("Could not execute {}", desc),
+                          Some(e), None, None)
+        }));
+        // An executor may decide on the job without reading all of it
+        let written = writer.join().unwrap().or_else(|e| {
+            if e.kind() == io::ErrorKind::BrokenPipe {Ok(())} else {Err(e)}
+        });
+        if let Err(e) = written {
+            return Err(process_error(&format!("Could not send job to {}", desc),
+                                     Some(e), Some(&output.status), None))
+        }
+
+        if output.status.success() {
+            Ok(Some(output))
+        } else if output.status.code() == Some(EXECUTOR_UNAVAILABLE) {
+            Ok(None)
+        } else {
+            Err(process_error(&format!("Process didn't exit successfully: {}",
+                                       desc),
+                              None, Some(&output.status),
+                              if capture {Some(&output)} else {None}))
+        }
+    }
+}
+
+impl ExecEngine for RemoteEngine {
+    fn exec(&self, command: CommandPrototype) -> Result<(), ProcessError> {
+        if let CommandType::Rustc = *command.get_type() {
+            if try!(self.run(&command, false)).is_some() {
+                return Ok(())
+            }
+        }
+        command.into_process_builder().exec()
+    }
+
+    fn exec_with_output(&self, command: CommandPrototype)
+                        -> Result<Output, ProcessError> {
+        if let CommandType::Rustc = *command.get_type() {
+            if let Some(output) = try!(self.run(&command, true)) {
+                return Ok(output)
+            }
+        }
+        command.into_process_builder().exec_with_output()
+    }
+}
@@ -0,0 +1,118 @@
+//! Records how long compiling each package took, so that reports about the
+//! cost of dependencies don't need to build anything themselves.
+//!
+//! The times are kept in the `build-times.json` file of each output directory,
+//! mapping package ids to what each of their units took the last time it was
+//! rebuilt: the time in seconds and, where the platform tells, the CPU time
+//! and the peak memory of the processes it ran. Units which were fresh keep
+//! their previous record.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::Duration;
+
+use rustc_serialize::json;
+
+use core::Package;
+use util::{CargoResult, paths};
+use util::rusage::ResourceUsage;
+
+use super::{Context, Kind};
+
+/// What the jobs of a unit took during a build.
+#[derive(Default)]
+pub struct UnitTime {
+    elapsed: Duration,
+    usage: Option<ResourceUsage>,
+}
+
+impl UnitTime {
+    pub fn add(&mut self, elapsed: Duration, usage: Option<ResourceUsage>) {
+        self.elapsed = self.elapsed + elapsed;
+        if let Some(usage) = usage {
+            let mut total = self.usage.unwrap_or(ResourceUsage::default());
+            total.cpu_time = total.cpu_time + usage.cpu_time;
+            if usage.max_rss > total.max_rss {
+                total.max_rss = usage.max_rss;
+            }
+            self.usage = Some(total);
+        }
+    }
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct Record {
+    time: f64,
+    cpu_time: Option<f64>,
+    max_rss: Option<u64>,
+}
+
+type BuildTimes = BTreeMap<String, BTreeMap<String, Record>>;
+
+/// What the last builds of a package took, over all of its units.
+#[derive(Clone, Copy)]
+pub struct BuildTime {
+    /// Seconds the units took to build
+    pub time: f64,
+    /// Seconds of CPU time used by the processes the units ran, if known
+    pub cpu_time: Option<f64>,
+    /// Peak resident set size of the largest process the units ran, in
+    /// bytes, if known
+    pub max_rss: Option<u64>,
+}
+
+pub fn save_build_times(cx: &Context, root: &Package) -> CargoResult<()> {
+    if cx.build_times.is_empty() {
+        return Ok(())
+    }
+    let path = cx.layout(root, Kind::Host).proxy().dest()
+                 .join("build-times.json");
+    let mut times = read(&path);
+    for (&(ref id, ref unit), time) in cx.build_times.iter() {
+        let record = Record {
+            time: secs(time.elapsed),
+            cpu_time: time.usage.map(|u| secs(u.cpu_time)),
+            max_rss: time.usage.map(|u| u.max_rss),
+        };
+        times.entry(id.to_string()).or_insert(BTreeMap::new())
+             .insert(unit.clone(), record);
+    }
+    paths::write(&path, json::encode(&times).unwrap().as_bytes())
+}
+
+fn secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 / 1_000_000_000.0
+}
+
+/// Returns what the last builds of each package in the output directory `dir`
+/// took, keyed by package id. The CPU time and memory are only known if every
+/// unit of the package recorded them.
+pub fn read_build_times(dir: &Path) -> HashMap<String, BuildTime> {
+    read(&dir.join("build-times.json")).into_iter().map(|(id, units)| {
+        let mut total = BuildTime {
+            time: 0.0,
+            cpu_time: Some(0.0),
+            max_rss: Some(0),
+        };
+        for record in units.values() {
+            total.time += record.time;
+            total.cpu_time = match (total.cpu_time, record.cpu_time) {
+                (Some(a), Some(b)) => Some(a + b),
+                _ => None,
+            };
+            total.max_rss = match (total.max_rss, record.max_rss) {
+                (Some(a), Some(b)) => Some(if a > b {a} else {b}),
+                _ => None,
+            };
+        }
+        (id, total)
+    }).collect()
+}
+
+/// Reads the records of `path`, those written by older versions of Cargo,
+/// which only had the time of each unit, being dropped.
+fn read(path: &Path) -> BuildTimes {
+    paths::read(path).ok().and_then(|contents| {
+        json::decode(&contents).ok()
+    }).unwrap_or(BTreeMap::new())
+}
@@ -1,14 +1,41 @@
+use std::env;
 use std::ffi::{OsString, OsStr};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use ops::{self, ExecEngine, ProcessEngine, Compilation};
-use util::{self, CargoResult, CargoTestError, ProcessError};
-use core::Workspace;
+use rustc_serialize::json::Json;
+use term::color::BLACK;
+
+use ops::{self, ExecEngine, ProcessEngine, Compilation, MessageFormat};
+use ops::CommandPrototype;
+use ops::bench_baseline::{self, BaselineOptions};
+use ops::test_coverage;
+use util::{self, CargoResult, CargoTestError, ProcessError, machine_message};
+use core::{PackageId, Target, Workspace};
 
 pub struct TestOptions<'a> {
     pub compile_opts: ops::CompileOptions<'a>,
     pub no_run: bool,
     pub no_fail_fast: bool,
     pub only_doc: bool,
+    /// How many times a failing test executable is run again before the
+    /// failure counts, instead of the `retries` of its target.
+    pub retries: Option<u32>,
+    /// How many seconds a test executable may run for before it's killed,
+    /// instead of the `timeout` of its target.
+    pub timeout: Option<u64>,
+    /// Merges the coverage profiles written by the tests into an lcov report
+    /// when compiling for coverage.
+    pub lcov: bool,
+}
+
+/// A test executable built by `cargo test` or `cargo bench`, for tools which
+/// run it on their own, e.g. under a debugger or a profiler.
+pub struct TestArtifact {
+    pub package_id: PackageId,
+    pub target: Target,
+    pub path: PathBuf,
 }
 
 pub fn run_tests(ws: &Workspace,
@@ -17,74 +44,201 @@ pub fn run_tests(ws: &Workspace,
     let compilation = try!(compile_tests(ws, options));
 
     if options.no_run {
+        try!(report_executables(options, &compilation));
         return Ok(None)
     }
-    let mut errors = if options.only_doc {
-        try!(run_doc_tests(options, test_args, &compilation))
+    if options.only_doc {
+        let errors = try!(run_doc_tests(options, test_args, &compilation));
+        return failed(options, errors)
+    }
+    let coverage = if options.compile_opts.coverage {
+        Some(try!(test_coverage::prepare(ws)))
     } else {
-        try!(run_unit_tests(options, test_args, &compilation))
+        None
     };
+    let mut errors = try!(run_unit_tests(options, test_args, &compilation,
+                                         coverage.as_ref().map(|p| &**p),
+                                         None));
+    if let Some(ref dir) = coverage {
+        try!(report_coverage(options, dir, &compilation));
+    }
 
     // If we have an error and want to fail fast, return
     if !errors.is_empty() && !options.no_fail_fast {
-        return Ok(Some(CargoTestError::new(errors)))
+        return failed(options, errors)
     }
 
     // If a specific test was requested or we're not running any tests at all,
     // don't run any doc tests.
     if let ops::CompileFilter::Only { .. } = options.compile_opts.filter {
-        match errors.len() {
-            0 => return Ok(None),
-            _ => return Ok(Some(CargoTestError::new(errors)))
-        }
+        return failed(options, errors)
     }
 
-    errors.extend(try!(run_doc_tests(options, test_args, &compilation)));
-    if errors.is_empty() {
-        Ok(None)
-    } else {
-        Ok(Some(CargoTestError::new(errors)))
+    // The doctests would link the instrumented libraries without the
+    // profiler runtime, which rustdoc knows nothing about
+    if coverage.is_some() {
+        try!(options.compile_opts.config.shell().warn("skipping doctests as \
+                                                       they aren't \
+                                                       instrumented for \
+                                                       coverage"));
+        return failed(options, errors)
     }
+
+    errors.extend(try!(run_doc_tests(options, test_args, &compilation)));
+    failed(options, errors)
 }
 
 pub fn run_benches(ws: &Workspace,
                    options: &TestOptions,
+                   baseline: &BaselineOptions,
                    args: &[String]) -> CargoResult<Option<CargoTestError>> {
+    try!(bench_baseline::check(ws, baseline));
     let mut args = args.to_vec();
     args.push("--bench".to_string());
     let compilation = try!(compile_tests(ws, options));
 
     if options.no_run {
+        try!(report_executables(options, &compilation));
         return Ok(None)
     }
-    let errors = try!(run_unit_tests(options, &args, &compilation));
-    match errors.len() {
-        0 => Ok(None),
-        _ => Ok(Some(CargoTestError::new(errors))),
+    let mut results = Vec::new();
+    let collect = baseline.save.is_some() || baseline.compare.is_some();
+    let errors = {
+        let benches = if collect {Some(&mut results)} else {None};
+        try!(run_unit_tests(options, &args, &compilation, None, benches))
+    };
+    if collect && errors.is_empty() {
+        try!(bench_baseline::process(ws, baseline, &results));
     }
+    failed(options, errors)
+}
+
+/// Tells where the coverage profiles of the tests went, merging them into an
+/// lcov report first if asked to.
+fn report_coverage(options: &TestOptions, dir: &Path,
+                   compilation: &Compilation) -> CargoResult<()> {
+    let config = options.compile_opts.config;
+    if options.lcov {
+        let lcov = try!(test_coverage::report(config, dir, compilation));
+        try!(config.shell().status("Coverage",
+                                   format!("report written to `{}`",
+                                           lcov.display())));
+    } else {
+        try!(config.shell().status("Coverage",
+                                   format!("profiles written to `{}`",
+                                           dir.display())));
+    }
+    Ok(())
+}
+
+/// Turns the failures of the test executables, each along with what it
+/// tested, into the error of the run. When the run went on past the first
+/// failure, they're listed with their exit codes first since their output may
+/// be far apart.
+fn failed(options: &TestOptions, failures: Vec<(String, ProcessError)>)
+          -> CargoResult<Option<CargoTestError>> {
+    if failures.is_empty() {
+        return Ok(None)
+    }
+    if options.no_fail_fast {
+        let mut shell = options.compile_opts.config.shell();
+        try!(shell.error(format!("{} test target(s) failed:",
+                                 failures.len())));
+        for &(ref tested, ref e) in failures.iter() {
+            let exit = match e.exit.as_ref().and_then(|s| s.code()) {
+                Some(code) => format!("exit code {}", code),
+                None if e.timed_out => "timed out".to_string(),
+                None => "killed by a signal".to_string(),
+            };
+            try!(shell.err().say(format!("    {}: {}", tested, exit), BLACK));
+        }
+    }
+    let errors = failures.into_iter().map(|(_, e)| e).collect();
+    Ok(Some(CargoTestError::new(errors)))
+}
+
+/// Describes a test target for the summary of failures, e.g.
+/// "foo v0.1.0 (test `bar`)".
+fn describe(id: &PackageId, target: &Target, doc: bool) -> String {
+    let kind = if doc {
+        "doctests".to_string()
+    } else if target.is_lib() {
+        "lib".to_string()
+    } else {
+        let kind = if target.is_bin() {
+            "bin"
+        } else if target.is_example() {
+            "example"
+        } else if target.is_bench() {
+            "bench"
+        } else {
+            "test"
+        };
+        format!("{} `{}`", kind, target.name())
+    };
+    format!("{} ({})", id, kind)
+}
+
+/// Builds the test executables selected by `options` without running them.
+pub fn build_tests(ws: &Workspace, options: &TestOptions)
+                   -> CargoResult<Vec<TestArtifact>> {
+    let compilation = try!(compile_tests(ws, options));
+    Ok(compilation.tests.into_iter().map(|(pkg, target, path)| {
+        TestArtifact {
+            package_id: pkg.package_id().clone(),
+            target: target,
+            path: path,
+        }
+    }).collect())
+}
+
+/// Prints the full paths of the test executables which were built but not
+/// run, so they can be run by hand.
+fn report_executables(options: &TestOptions, compilation: &Compilation)
+                      -> CargoResult<()> {
+    let config = options.compile_opts.config;
+    let json = options.compile_opts.message_format == MessageFormat::Json;
+    for &(ref pkg, ref target, ref exe) in compilation.tests.iter() {
+        if json {
+            machine_message::emit(machine_message::TestExecutable {
+                package_id: pkg.package_id(),
+                target: target,
+                executable: &exe.display().to_string(),
+            });
+        } else {
+            try!(config.shell().status("Executable", exe.display()));
+        }
+    }
+    Ok(())
 }
 
 fn compile_tests<'a>(ws: &Workspace<'a>,
                      options: &TestOptions<'a>)
                      -> CargoResult<Compilation<'a>> {
-    let mut compilation = try!(ops::compile(ws, &options.compile_opts));
+    let opts = &options.compile_opts;
+    let mut compilation = try!(ops::compile_with_summary(ws, opts));
     compilation.tests.sort_by(|a, b| {
-        (a.0.package_id(), &a.1).cmp(&(b.0.package_id(), &b.1))
+        (a.0.package_id(), a.1.name()).cmp(&(b.0.package_id(), b.1.name()))
     });
     Ok(compilation)
 }
 
-/// Run the unit and integration tests of a project.
+/// Run the unit and integration tests of a project, having them write their
+/// coverage profiles to `coverage` and adding the results of the benchmarks
+/// they run to `benches` if given.
 fn run_unit_tests(options: &TestOptions,
                   test_args: &[String],
-                  compilation: &Compilation)
-                  -> CargoResult<Vec<ProcessError>> {
+                  compilation: &Compilation,
+                  coverage: Option<&Path>,
+                  mut benches: Option<&mut Vec<(String, u64)>>)
+                  -> CargoResult<Vec<(String, ProcessError)>> {
     let config = options.compile_opts.config;
     let cwd = options.compile_opts.config.cwd();
 
     let mut errors = Vec::new();
+    let mut flaky = Vec::new();
 
-    for &(ref pkg, _, ref exe) in &compilation.tests {
+    for &(ref pkg, ref target, ref exe) in &compilation.tests {
         let to_display = match util::without_prefix(exe, &cwd) {
             Some(path) => path,
             None => &**exe,
@@ -98,43 +252,211 @@ fn run_unit_tests(options: &TestOptions,
             shell.status("Running", cmd.to_string())
         }));
 
-        if let Err(e) = ExecEngine::exec(&ProcessEngine, cmd) {
-            errors.push(e);
-            if !options.no_fail_fast {
-                break
+        // Harnesses able to time out individual tests can pick the limit up
+        // from the environment
+        let timeout = options.timeout.or(target.timeout())
+                             .map(Duration::from_secs);
+        if let Some(timeout) = timeout {
+            cmd.env("CARGO_TEST_TIMEOUT", timeout.as_secs().to_string());
+        }
+        if let Some(dir) = coverage {
+            cmd.env("LLVM_PROFILE_FILE",
+                    test_coverage::profile_file(dir, target));
+        }
+
+        let json = options.compile_opts.message_format == MessageFormat::Json;
+        let events = if json {Some((pkg.package_id(), target))} else {None};
+        let retries = options.retries.unwrap_or(target.retries());
+        let mut failures = 0;
+        let mut found = Vec::new();
+        let collect = benches.is_some();
+        let mut result = exec_test(cmd.clone(), timeout, events,
+                                   if collect {Some(&mut found)} else {None});
+        // A hung test is likely to hang again, so it's not worth the wait
+        while failures < retries &&
+              result.as_ref().err().map_or(false, |e| !e.timed_out) {
+            failures += 1;
+            try!(config.shell().warn(format!("`{}` failed, running it again \
+                                              ({} of {})",
+                                             to_display.display(), failures,
+                                             retries)));
+            found.clear();
+            result = exec_test(cmd.clone(), timeout, events,
+                               if collect {Some(&mut found)} else {None});
+        }
+        if let Some(ref mut benches) = benches {
+            benches.extend(found.into_iter().map(|(name, ns)| {
+                (format!("{}/{}/{}", pkg.name(), target.name(), name), ns)
+            }));
+        }
+        match result {
+            Ok(()) => {
+                if failures > 0 {
+                    flaky.push((to_display, failures));
+                    if json {
+                        machine_message::emit(machine_message::FlakyTest {
+                            package_id: pkg.package_id(),
+                            target: target,
+                            failures: failures,
+                        });
+                    }
+                }
+            }
+            Err(e) => {
+                if e.timed_out {
+                    let secs = timeout.map_or(0, |t| t.as_secs());
+                    try!(config.shell().warn(format!("`{}` timed out after \
+                                                      {}s and was killed",
+                                                     to_display.display(),
+                                                     secs)));
+                    if json {
+                        machine_message::emit(machine_message::TestTimeout {
+                            package_id: pkg.package_id(),
+                            target: target,
+                            timeout: secs,
+                        });
+                    }
+                }
+                errors.push((describe(pkg.package_id(), target, false), e));
+                if !options.no_fail_fast {
+                    break
+                }
             }
         }
     }
+
+    // Tests which only passed when run again don't fail the build, but they
+    // shouldn't go unnoticed either
+    for &(path, failures) in flaky.iter() {
+        try!(config.shell().warn(format!("`{}` passed after failing {} \
+                                          time(s), it may be flaky",
+                                         path.display(), failures)));
+    }
     Ok(errors)
 }
 
+/// Runs a test executable, turning what it prints on stdout into events if
+/// `events` gives the package and target they're attributed to, and adding
+/// the results of the benchmarks it runs to `benches` if given.
+fn exec_test(cmd: CommandPrototype,
+             timeout: Option<Duration>,
+             events: Option<(&PackageId, &Target)>,
+             mut benches: Option<&mut Vec<(String, u64)>>)
+             -> Result<(), ProcessError> {
+    if events.is_none() && benches.is_none() {
+        return match timeout {
+            Some(timeout) => {
+                cmd.into_process_builder().exec_with_timeout(timeout)
+            }
+            None => ExecEngine::exec(&ProcessEngine, cmd),
+        }
+    }
+    cmd.into_process_builder().exec_with_streaming(timeout, &mut |line: &str| {
+        if let Some(ref mut benches) = benches {
+            benches.extend(bench_baseline::parse_line(line));
+        }
+        match events {
+            Some((package_id, target)) => {
+                emit_test_line(package_id, target, line)
+            }
+            None => println!("{}", line),
+        }
+    })
+}
+
+/// Emits the event for a line printed by a test executable: either a line of
+/// machine readable output, or the outcome of a test in the output of libtest.
+/// Any other line is printed on stderr, leaving stdout to the events.
+fn emit_test_line(package_id: &PackageId, target: &Target, line: &str) {
+    if let Ok(message @ Json::Object(..)) = Json::from_str(line) {
+        machine_message::emit(machine_message::FromTest {
+            package_id: package_id,
+            target: target,
+            message: message,
+        });
+        return
+    }
+    // libtest reports tests as `test <name> ... <outcome>`
+    if line.starts_with("test ") {
+        let mut parts = line[5..].rsplitn(2, " ... ");
+        if let (Some(outcome), Some(name)) = (parts.next(), parts.next()) {
+            let outcome = match outcome {
+                "ok" => Some("ok"),
+                "FAILED" => Some("failed"),
+                "ignored" => Some("ignored"),
+                s if s.starts_with("bench:") => Some("bench"),
+                _ => None,
+            };
+            if let Some(outcome) = outcome {
+                machine_message::emit(machine_message::TestResult {
+                    package_id: package_id,
+                    target: target,
+                    name: name,
+                    outcome: outcome,
+                });
+                return
+            }
+        }
+    }
+    let _ = writeln!(io::stderr(), "{}", line);
+}
+
 fn run_doc_tests(options: &TestOptions,
                  test_args: &[String],
                  compilation: &Compilation)
-                 -> CargoResult<Vec<ProcessError>> {
+                 -> CargoResult<Vec<(String, ProcessError)>> {
     let mut errors = Vec::new();
     let config = options.compile_opts.config;
 
-    // We don't build/rust doctests if target != host
-    if let Some(target) = options.compile_opts.target {
-        if try!(config.rustc()).host != target {
-            return Ok(errors);
+    // Doctests built for another target can only be run through the runner
+    // configured for it, which rustdoc only supports as an unstable option
+    let cross_target = match options.compile_opts.targets.first() {
+        Some(&target) if try!(config.rustc()).host != target => Some(target),
+        _ => None,
+    };
+    if let Some(target) = cross_target {
+        if compilation.target_runner.is_none() {
+            try!(config.shell().warn(format!("skipping doctests as they \
+                                              can't be run for `{}` without \
+                                              `target.{}.runner`",
+                                             target, target)));
+            return Ok(errors)
+        }
+        if !try!(config.rustc()).is_nightly() {
+            try!(config.shell().warn(format!("skipping doctests as running \
+                                              them through \
+                                              `target.{}.runner` requires a \
+                                              nightly rustdoc", target)));
+            return Ok(errors)
         }
     }
 
+    let json = options.compile_opts.message_format == MessageFormat::Json;
     let libs = compilation.to_doc_test.iter().map(|package| {
-        (package, package.targets().iter().filter(|t| t.doctested())
-                         .map(|t| (t.src_path(), t.name(), t.crate_name())))
+        (package, package.targets().iter().filter(|t| t.doctested()))
     });
 
     for (package, tests) in libs {
-        for (lib, name, crate_name) in tests {
-            try!(config.shell().status("Doc-tests", name));
+        for target in tests {
+            try!(config.shell().status("Doc-tests", target.name()));
             let mut p = try!(compilation.rustdoc_process(package));
-            p.arg("--test").arg(lib)
-             .arg("--crate-name").arg(&crate_name);
+            p.arg("--test").arg(target.src_path())
+             .arg("--crate-name").arg(&target.crate_name());
 
-            for &rust_dep in &[&compilation.deps_output, &compilation.root_output] {
+            if let Some(triple) = cross_target {
+                p.arg("--target").arg(triple);
+                let runner = compilation.target_runner.as_ref().unwrap();
+                p.arg("-Z").arg("unstable-options")
+                 .arg("--runtool").arg(&runner[0]);
+                for arg in runner[1..].iter() {
+                    p.arg("--runtool-arg").arg(arg);
+                }
+            }
+
+            let outputs = [&compilation.deps_output,
+                           &compilation.root_output,
+                           &compilation.host_deps_output];
+            for &rust_dep in outputs.iter() {
                 let mut arg = OsString::from("dependency=");
                 arg.push(rust_dep);
                 p.arg("-L").arg(arg);
@@ -151,8 +473,15 @@ fn run_doc_tests(options: &TestOptions,
                 p.arg("--cfg").arg(cfg);
             }
 
+            // rustdoc runs the doctests with its own environment
+            for &(ref k, ref v) in compilation.run_env.iter() {
+                if env::var_os(k).is_none() {
+                    p.env(k, v);
+                }
+            }
+
             for (_, libs) in compilation.libraries.iter() {
-                for &(ref target, ref lib) in libs.iter() {
+                for &(ref dep, ref lib) in libs.iter() {
                     // Note that we can *only* doctest rlib outputs here.  A
                     // staticlib output cannot be linked by the compiler (it just
                     // doesn't do that). A dylib output, however, can be linked by
@@ -164,10 +493,10 @@ fn run_doc_tests(options: &TestOptions,
                     // pass `--extern` for rlib deps and skip out on all other
                     // artifacts.
                     if lib.extension() != Some(OsStr::new("rlib")) &&
-                       !target.for_host() {
+                       !dep.for_host() {
                         continue
                     }
-                    let mut arg = OsString::from(target.crate_name());
+                    let mut arg = OsString::from(dep.crate_name());
                     arg.push("=");
                     arg.push(lib);
                     p.arg("--extern").arg(&arg);
@@ -177,8 +506,14 @@ fn run_doc_tests(options: &TestOptions,
             try!(config.shell().verbose(|shell| {
                 shell.status("Running", p.to_string())
             }));
-            if let Err(e) = ExecEngine::exec(&ProcessEngine, p) {
-                errors.push(e);
+            let events = if json {
+                Some((package.package_id(), target))
+            } else {
+                None
+            };
+            if let Err(e) = exec_test(p, None, events, None) {
+                errors.push((describe(package.package_id(), target, true),
+                             e));
                 if !options.no_fail_fast {
                     return Ok(errors);
                 }
@@ -0,0 +1,232 @@
+use std::collections::{BTreeSet, HashSet};
+
+use core::dependency::Kind;
+use core::{Package, PackageId, PackageSet, Resolve, Workspace};
+use ops;
+use util::CargoResult;
+
+pub struct TreeOptions {
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    pub edges: Edges,
+}
+
+/// The edges of the dependency graph `cargo tree` follows, and whether it
+/// shows which features enabled them.
+pub struct Edges {
+    pub normal: bool,
+    pub build: bool,
+    pub dev: bool,
+    pub features: bool,
+}
+
+impl Edges {
+    /// Parses a comma-separated list of edge kinds, such as `normal,build`.
+    pub fn parse(spec: &str) -> CargoResult<Edges> {
+        let mut edges = Edges {
+            normal: false,
+            build: false,
+            dev: false,
+            features: false,
+        };
+        let kinds = spec.split(',').map(|s| s.trim()).filter(|s| !s.is_empty());
+        for kind in kinds {
+            match kind {
+                "normal" => edges.normal = true,
+                "build" => edges.build = true,
+                "dev" => edges.dev = true,
+                "features" => edges.features = true,
+                "all" => {
+                    edges.normal = true;
+                    edges.build = true;
+                    edges.dev = true;
+                }
+                _ => bail!("unknown edge kind `{}`, expected one of `normal`, \
+                            `build`, `dev`, `features` or `all`", kind),
+            }
+        }
+        if !edges.normal && !edges.build && !edges.dev {
+            edges.normal = true;
+            edges.build = true;
+            edges.dev = true;
+        }
+        Ok(edges)
+    }
+
+    fn includes(&self, kind: Kind) -> bool {
+        match kind {
+            Kind::Normal => self.normal,
+            Kind::Build => self.build,
+            Kind::Development => self.dev,
+        }
+    }
+}
+
+/// Renders the dependency graph of the current package, or of all members of
+/// the workspace, as a tree.
+///
+/// A package is only expanded the first time it's shown, later occurrences are
+/// marked with `(*)`.
+pub fn tree(ws: &Workspace, opts: &TreeOptions) -> CargoResult<String> {
+    let features = opts.features.iter().flat_map(|s| {
+        s.split(' ')
+    }).map(|s| s.to_string()).collect::<Vec<String>>();
+    let (packages, resolve) = try!(ops::resolve_dependencies(
+        ws, None, features, opts.no_default_features, &[]));
+
+    let roots = match ws.current_opt() {
+        Some(pkg) => vec![pkg.package_id().clone()],
+        None => ws.members().map(|p| p.package_id().clone()).collect(),
+    };
+
+    let graph = Graph {
+        packages: &packages,
+        resolve: &resolve,
+        edges: &opts.edges,
+    };
+    let mut out = String::new();
+    let mut visited = HashSet::new();
+    for (i, root) in roots.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("{}\n", root));
+        try!(graph.print_deps(root, &mut String::new(), &mut visited,
+                              &mut out));
+    }
+    Ok(out)
+}
+
+struct Graph<'a, 'cfg: 'a> {
+    packages: &'a PackageSet<'cfg>,
+    resolve: &'a Resolve,
+    edges: &'a Edges,
+}
+
+/// A dependency of a package along with the features of the package which
+/// enabled it, if it's optional, and the features it enables in turn.
+struct Edge<'a> {
+    id: &'a PackageId,
+    enabled_by: BTreeSet<String>,
+    features: BTreeSet<String>,
+}
+
+impl<'a, 'cfg> Graph<'a, 'cfg> {
+    fn print_deps(&self,
+                  id: &PackageId,
+                  prefix: &mut String,
+                  visited: &mut HashSet<PackageId>,
+                  out: &mut String) -> CargoResult<()> {
+        visited.insert(id.clone());
+        let pkg = try!(self.packages.get(id));
+
+        let sections = [(Kind::Normal, None),
+                        (Kind::Build, Some("[build-dependencies]")),
+                        (Kind::Development, Some("[dev-dependencies]"))];
+        let sections = sections.iter().filter(|&&(kind, _)| {
+            self.edges.includes(kind)
+        }).map(|&(kind, header)| {
+            (header, self.edges_of(pkg, kind))
+        }).filter(|&(_, ref edges)| !edges.is_empty()).collect::<Vec<_>>();
+
+        for (header, edges) in sections {
+            if let Some(header) = header {
+                out.push_str(&format!("{}{}\n", prefix, header));
+            }
+            for (i, edge) in edges.iter().enumerate() {
+                let last = i == edges.len() - 1;
+                out.push_str(prefix);
+                out.push_str(if last {"└── "} else {"├── "});
+                out.push_str(&edge.id.to_string());
+                if self.edges.features {
+                    if !edge.features.is_empty() {
+                        let features = edge.features.iter()
+                                           .map(|s| &s[..])
+                                           .collect::<Vec<_>>();
+                        out.push_str(&format!(" features: {}",
+                                              features.join(", ")));
+                    }
+                    if !edge.enabled_by.is_empty() {
+                        let features = edge.enabled_by.iter()
+                                           .map(|s| &s[..])
+                                           .collect::<Vec<_>>();
+                        out.push_str(&format!(" (enabled by {}: {})",
+                                              pkg.name(),
+                                              features.join(", ")));
+                    }
+                }
+                if visited.contains(edge.id) {
+                    out.push_str(" (*)\n");
+                    continue
+                }
+                out.push('\n');
+
+                let len = prefix.len();
+                prefix.push_str(if last {"    "} else {"│   "});
+                try!(self.print_deps(edge.id, prefix, visited, out));
+                prefix.truncate(len);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the dependencies of `pkg` of the given kind, in the order of
+    /// their names.
+    fn edges_of(&self, pkg: &'a Package, kind: Kind) -> Vec<Edge<'a>> {
+        let id = pkg.package_id();
+        let activated = self.resolve.features(id);
+        let mut edges = self.resolve.deps(id).filter_map(|dep_id| {
+            let decls = pkg.dependencies().iter().filter(|d| {
+                d.name() == dep_id.name() && d.kind() == kind
+            }).collect::<Vec<_>>();
+            if decls.is_empty() {
+                return None
+            }
+
+            let mut edge = Edge {
+                id: dep_id,
+                enabled_by: BTreeSet::new(),
+                features: BTreeSet::new(),
+            };
+            let has_default = self.resolve.features(dep_id).map_or(false, |f| {
+                f.contains("default")
+            });
+            for decl in decls.iter() {
+                edge.features.extend(decl.features().iter().cloned());
+                if decl.uses_default_features() && has_default {
+                    edge.features.insert("default".to_string());
+                }
+            }
+
+            // Features of `pkg` enable optional dependencies by naming them,
+            // and features of dependencies with `dep/feature`.
+            if let Some(activated) = activated {
+                let name = dep_id.name();
+                if decls.iter().any(|d| d.is_optional()) &&
+                   activated.contains(name) &&
+                   !pkg.summary().features().contains_key(name) {
+                    edge.enabled_by.insert(name.to_string());
+                }
+                for feature in activated.iter() {
+                    let values = match pkg.summary().features().get(feature) {
+                        Some(values) => values,
+                        None => continue,
+                    };
+                    for value in values {
+                        let mut parts = value.splitn(2, '/');
+                        if parts.next() != Some(name) {
+                            continue
+                        }
+                        edge.enabled_by.insert(feature.clone());
+                        if let Some(dep_feature) = parts.next() {
+                            edge.features.insert(dep_feature.to_string());
+                        }
+                    }
+                }
+            }
+            Some(edge)
+        }).collect::<Vec<_>>();
+        edges.sort_by(|a, b| a.id.cmp(b.id));
+        edges
+    }
+}
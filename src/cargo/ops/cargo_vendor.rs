@@ -0,0 +1,159 @@
+//! Copies the dependencies of a workspace into a directory source.
+//!
+//! Every package of the resolve graph which comes from a registry is unpacked
+//! into its own `<name>-<version>` subdirectory, along with the
+//! `.cargo-checksum.json` a directory source checks it against. The
+//! configuration replacing the registries with the directory is returned, to
+//! be added to `.cargo/config` for builds to use the vendored sources.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use rustc_serialize::json;
+
+use core::{Package, SourceId, Workspace};
+use ops;
+use sources::PathSource;
+use sources::directory::{CHECKSUM_FILE, Checksum, file_checksum};
+use util::{CargoResult, ChainError, human, paths};
+
+/// The name the directory source is given in the configuration.
+const VENDORED_SOURCE: &'static str = "vendored-sources";
+
+pub struct VendorOptions<'a> {
+    /// Where the packages are unpacked
+    pub dir: &'a Path,
+    /// Keeps packages vendored earlier which the workspace doesn't depend on
+    /// anymore
+    pub no_delete: bool,
+}
+
+/// Vendors the dependencies of `ws`, returning the `[source]` configuration
+/// to build with them.
+pub fn vendor(ws: &Workspace, opts: &VendorOptions) -> CargoResult<String> {
+    let config = ws.config();
+    let (resolve, packages) = try!(ops::fetch(ws));
+
+    let mut ids = resolve.iter().filter(|id| {
+        !id.source_id().is_path()
+    }).collect::<Vec<_>>();
+    ids.sort();
+    if let Some(id) = ids.iter().find(|id| !id.source_id().is_registry()) {
+        bail!("`{}` can't be vendored, only packages from registries can be \
+               replaced with vendored sources", id)
+    }
+
+    try!(fs::create_dir_all(opts.dir).chain_error(|| {
+        human(format!("failed to create `{}`", opts.dir.display()))
+    }));
+    let mut vendored = HashMap::new();
+    for &id in ids.iter() {
+        let name = format!("{}-{}", id.name(), id.version());
+        if let Some(other) = vendored.insert(name.clone(), id) {
+            bail!("`{}` and `{}` would both be vendored into `{}`, packages \
+                   with the same name and version from several registries \
+                   can't be vendored together", other, id,
+                  opts.dir.join(&name).display())
+        }
+    }
+    for id in ids.iter() {
+        let pkg = try!(packages.get(id));
+        let dst = opts.dir.join(format!("{}-{}", id.name(), id.version()));
+        try!(config.shell().status("Vendoring", id));
+        try!(vendor_package(ws, pkg, &dst).chain_error(|| {
+            human(format!("failed to vendor `{}` into `{}`", id,
+                          dst.display()))
+        }));
+    }
+
+    if !opts.no_delete {
+        for entry in try!(fs::read_dir(opts.dir)) {
+            let path = try!(entry).path();
+            let stale = path.join(CHECKSUM_FILE).is_file() &&
+                        path.file_name().and_then(|s| s.to_str())
+                            .map_or(false, |s| !vendored.contains_key(s));
+            if stale {
+                try!(fs::remove_dir_all(&path).chain_error(|| {
+                    human(format!("failed to remove `{}`", path.display()))
+                }));
+            }
+        }
+    }
+
+    let mut sources = ids.iter().map(|id| id.source_id())
+                         .collect::<Vec<_>>();
+    sources.sort();
+    sources.dedup();
+    source_config(ws, opts.dir, &sources)
+}
+
+/// Copies the files of `pkg` to `dst`, replacing whatever was vendored there
+/// before, and lists their checksums.
+fn vendor_package(ws: &Workspace, pkg: &Package, dst: &Path)
+                  -> CargoResult<()> {
+    // Once the configuration printed is in place the package is read from
+    // the vendored sources themselves, which are up to date then.
+    if dst.exists() {
+        if try!(fs::canonicalize(dst)) == try!(fs::canonicalize(pkg.root())) {
+            return Ok(())
+        }
+        try!(fs::remove_dir_all(dst));
+    }
+    let src = PathSource::new(pkg.root(), pkg.package_id().source_id(),
+                              ws.config());
+    let mut files = HashMap::new();
+    for file in try!(src.list_files(pkg)) {
+        let relative = paths::without_prefix(&file, pkg.root()).unwrap();
+        let to = dst.join(relative);
+        try!(fs::create_dir_all(to.parent().unwrap()));
+        try!(fs::copy(&file, &to).chain_error(|| {
+            human(format!("failed to copy `{}`", file.display()))
+        }));
+        // Checksums are listed with forward slashes on all platforms
+        let key = relative.iter().map(|s| s.to_string_lossy())
+                          .collect::<Vec<_>>().join("/");
+        files.insert(key, try!(file_checksum(&to)));
+    }
+
+    let cksum = Checksum {
+        package: pkg.summary().checksum().map(|s| s.to_string()),
+        files: files,
+    };
+    let json = try!(json::encode(&cksum).map_err(|e| {
+        human(format!("failed to encode the checksums: {}", e))
+    }));
+    paths::write(&dst.join(CHECKSUM_FILE), json.as_bytes())
+}
+
+/// The configuration replacing each of `sources` with the vendored sources in
+/// `dir`.
+fn source_config(ws: &Workspace, dir: &Path, sources: &[&SourceId])
+                 -> CargoResult<String> {
+    // Relative paths in `.cargo/config` of the workspace are relative to the
+    // workspace itself
+    let root = ws.root();
+    let dir = paths::without_prefix(dir, root).unwrap_or(dir);
+
+    let central = try!(SourceId::for_central(ws.config()));
+    let mut replaced = BTreeMap::new();
+    for (i, &id) in sources.iter().enumerate() {
+        if *id == central {
+            replaced.insert("crates-io".to_string(), None);
+        } else {
+            replaced.insert(format!("registry-{}", i + 1), Some(id.url()));
+        }
+    }
+    let mut out = String::new();
+    for (name, url) in replaced {
+        out.push_str(&format!("[source.{}]\n", name));
+        if let Some(url) = url {
+            out.push_str(&format!("registry = \"{}\"\n", url));
+        }
+        out.push_str(&format!("replace-with = \"{}\"\n\n", VENDORED_SOURCE));
+    }
+    out.push_str(&format!("[source.{}]\ndirectory = \"{}\"\n",
+                          VENDORED_SOURCE,
+                          dir.display().to_string().replace("\\", "/")));
+    Ok(out)
+}
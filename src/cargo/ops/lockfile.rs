@@ -1,77 +1,55 @@
 use std::io::prelude::*;
+use std::path::Path;
 
 use rustc_serialize::{Encodable, Decodable};
 use toml::{self, Encoder, Value};
 
 use core::{Resolve, resolver, Workspace};
 use core::resolver::WorkspaceResolve;
-use util::{CargoResult, ChainError, human, Filesystem};
+use util::{CargoResult, ChainError, human, Filesystem, paths};
 use util::toml as cargo_toml;
 
 pub fn load_pkg_lockfile(ws: &Workspace) -> CargoResult<Option<Resolve>> {
-    if !ws.root().join("Cargo.lock").exists() {
+    let path = ws.lockfile();
+    if ws.ignore_lock() || !path.exists() {
         return Ok(None)
     }
 
-    let root = Filesystem::new(ws.root().to_path_buf());
-    let mut f = try!(root.open_ro("Cargo.lock", ws.config(), "Cargo.lock file"));
+    let (root, name) = try!(lockfile_location(&path));
+    let mut f = try!(root.open_ro(name, ws.config(), "Cargo.lock file"));
 
     let mut s = String::new();
     try!(f.read_to_string(&mut s).chain_error(|| {
         human(format!("failed to read file: {}", f.path().display()))
     }));
 
+    parse_lockfile(ws, &s, f.path()).map(Some)
+}
+
+/// Parses the `contents` of a lock file of `ws`, which was read from `path`.
+pub fn parse_lockfile(ws: &Workspace, contents: &str, path: &Path)
+                      -> CargoResult<Resolve> {
     (|| {
-        let table = try!(cargo_toml::parse(&s, f.path(), ws.config()));
+        let table = try!(cargo_toml::parse(contents, path, ws.config()));
         let table = toml::Value::Table(table);
         let mut d = toml::Decoder::new(table);
         let v: resolver::EncodableResolve = try!(Decodable::decode(&mut d));
-        Ok(Some(try!(v.to_resolve(ws))))
+        v.to_resolve(ws)
     }).chain_error(|| {
-        human(format!("failed to parse lock file at: {}", f.path().display()))
+        human(format!("failed to parse lock file at: {}", path.display()))
     })
 }
 
 pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()> {
-    let mut e = Encoder::new();
-    WorkspaceResolve {
-        ws: ws,
-        resolve: resolve,
-    }.encode(&mut e).unwrap();
-
-    let mut out = String::new();
-
-    // Note that we do not use e.toml.to_string() as we want to control the
-    // exact format the toml is in to ensure pretty diffs between updates to the
-    // lockfile.
-    let root = e.toml.get(&"root".to_string()).unwrap();
-
-    out.push_str("[root]\n");
-    emit_package(root.as_table().unwrap(), &mut out);
-
-    let deps = e.toml.get(&"package".to_string()).unwrap().as_slice().unwrap();
-    for dep in deps.iter() {
-        let dep = dep.as_table().unwrap();
-
-        out.push_str("[[package]]\n");
-        emit_package(dep, &mut out);
-    }
-
-    match e.toml.get(&"metadata".to_string()) {
-        Some(metadata) => {
-            out.push_str("[metadata]\n");
-            out.push_str(&metadata.to_string());
-        }
-        None => {}
-    }
-
-    let root = Filesystem::new(ws.root().to_path_buf());
+    let mut out = resolve_to_string(ws, resolve);
+    let path = ws.lockfile();
+    let (root, name) = try!(lockfile_location(&path));
 
     // Load the original lockfile if it exists.
     //
     // If the lockfile contents haven't changed so don't rewrite it. This is
     // helpful on read-only filesystems.
-    let orig = root.open_ro("Cargo.lock", ws.config(), "Cargo.lock file");
+    let orig = root.open_ro(name, ws.config(), "Cargo.lock file");
     let orig = orig.and_then(|mut f| {
         let mut s = String::new();
         try!(f.read_to_string(&mut s));
@@ -93,16 +71,77 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CargoResult<()>
     }
 
     // Ok, if that didn't work just write it out
-    root.open_rw("Cargo.lock", ws.config(), "Cargo.lock file").and_then(|mut f| {
+    root.open_rw(name, ws.config(), "Cargo.lock file").and_then(|mut f| {
         try!(f.file().set_len(0));
         try!(f.write_all(out.as_bytes()));
         Ok(())
     }).chain_error(|| {
-        human(format!("failed to write {}",
-                      ws.root().join("Cargo.lock").display()))
+        human(format!("failed to write {}", path.display()))
     })
 }
 
+/// Returns whether the `Cargo.lock` of `ws` exists and is exactly what would
+/// be written for `resolve`, without modifying it.
+pub fn pkg_lockfile_is_current(ws: &Workspace, resolve: &Resolve)
+                               -> CargoResult<bool> {
+    let path = ws.lockfile();
+    if !path.exists() {
+        return Ok(false)
+    }
+    let mut out = resolve_to_string(ws, resolve);
+    let orig = try!(paths::read(&path));
+    if has_crlf_line_endings(&orig) {
+        out = out.replace("\n", "\r\n");
+    }
+    Ok(out == orig)
+}
+
+/// Splits the path of a lock file into its directory and file name, as
+/// expected by `Filesystem`.
+fn lockfile_location(path: &Path) -> CargoResult<(Filesystem, &str)> {
+    let dir = Filesystem::new(path.parent().unwrap().to_path_buf());
+    let name = try!(path.file_name().and_then(|s| s.to_str()).chain_error(|| {
+        human(format!("the name of the lock file `{}` isn't valid UTF-8",
+                      path.display()))
+    }));
+    Ok((dir, name))
+}
+
+fn resolve_to_string(ws: &Workspace, resolve: &Resolve) -> String {
+    let mut e = Encoder::new();
+    WorkspaceResolve {
+        ws: ws,
+        resolve: resolve,
+    }.encode(&mut e).unwrap();
+
+    let mut out = String::new();
+
+    // Note that we do not use e.toml.to_string() as we want to control the
+    // exact format the toml is in to ensure pretty diffs between updates to the
+    // lockfile.
+    let root = e.toml.get(&"root".to_string()).unwrap();
+
+    out.push_str("[root]\n");
+    emit_package(root.as_table().unwrap(), &mut out);
+
+    let deps = e.toml.get(&"package".to_string()).unwrap().as_slice().unwrap();
+    for dep in deps.iter() {
+        let dep = dep.as_table().unwrap();
+
+        out.push_str("[[package]]\n");
+        emit_package(dep, &mut out);
+    }
+
+    match e.toml.get(&"metadata".to_string()) {
+        Some(metadata) => {
+            out.push_str("[metadata]\n");
+            out.push_str(&metadata.to_string());
+        }
+        None => {}
+    }
+    out
+}
+
 fn has_crlf_line_endings(s: &str) -> bool {
     // Only check the first line.
     if let Some(lf) = s.find('\n') {
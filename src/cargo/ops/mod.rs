@@ -1,43 +1,84 @@
+pub use self::cargo_bisect::{bisect, BisectOptions, BisectResult};
+pub use self::cargo_bump::{bump, BumpOptions};
 pub use self::cargo_clean::{clean, CleanOptions};
 pub use self::cargo_compile::{compile, compile_ws, resolve_dependencies, CompileOptions};
-pub use self::cargo_compile::{CompileFilter, CompileMode};
+pub use self::cargo_compile::{CompileFilter, CompileMode, Packages};
+pub use self::cargo_compile::ExcludeTargets;
+pub use self::cargo_compile::{compile_with_summary, MessageFormat};
+pub use self::cargo_compile::compile_resolved;
+pub use self::cargo_daemon::{daemon, DaemonOptions};
 pub use self::cargo_read_manifest::{read_manifest,read_package,read_packages};
 pub use self::cargo_rustc::{compile_targets, Compilation, Layout, Kind, Unit};
 pub use self::cargo_rustc::{Context, LayoutProxy};
-pub use self::cargo_rustc::{BuildOutput, BuildConfig, TargetConfig};
+pub use self::cargo_rustc::{BuildOutput, BuildConfig, TargetConfig, Warnings};
+pub use self::cargo_rustc::ExecutionPolicy;
 pub use self::cargo_rustc::{CommandType, CommandPrototype, ExecEngine, ProcessEngine};
+pub use self::cargo_rustc::RemoteEngine;
+pub use self::cargo_rustc::{BuildScriptInfo, read_build_script_info};
+pub use self::cargo_rustc::{BuildTime, read_build_times};
 pub use self::cargo_run::run;
 pub use self::cargo_install::{install, install_list, uninstall};
+pub use self::cargo_index_snapshot::{export_index_snapshot};
+pub use self::cargo_index_snapshot::{import_index_snapshot};
+pub use self::cargo_index_snapshot::{record_index_snapshot};
 pub use self::cargo_new::{new, init, NewOptions, VersionControl};
+pub use self::cargo_name_check::{name_check, NameCheck};
 pub use self::cargo_doc::{doc, DocOptions};
 pub use self::cargo_generate_lockfile::{generate_lockfile};
 pub use self::cargo_generate_lockfile::{update_lockfile};
 pub use self::cargo_generate_lockfile::UpdateOptions;
-pub use self::lockfile::{load_pkg_lockfile, write_pkg_lockfile};
-pub use self::cargo_test::{run_tests, run_benches, TestOptions};
+pub use self::cargo_generate_lockfile::{verify_lockfile, LockfileDrift, LockfileChange};
+pub use self::cargo_generate_lockfile::{lockfile_diff, LockfileDiff};
+pub use self::cargo_generate_lockfile::{LockfileDiffEntry, LockfileDiffOptions};
+pub use self::cargo_generate_lockfile::LockfileSide;
+pub use self::lockfile::{load_pkg_lockfile, write_pkg_lockfile, pkg_lockfile_is_current};
+pub use self::lockfile::parse_lockfile;
+pub use self::cargo_test::{run_tests, run_benches, build_tests, TestOptions};
+pub use self::cargo_test::TestArtifact;
+pub use self::bench_baseline::BaselineOptions;
 pub use self::cargo_package::{package, PackageOpts};
 pub use self::registry::{publish, registry_configuration, RegistryConfig};
 pub use self::registry::{registry_login, search, http_proxy_exists, http_handle};
 pub use self::registry::{modify_owners, yank, OwnersOptions, PublishOpts};
-pub use self::cargo_fetch::{fetch, get_resolved_packages};
+pub use self::cargo_fetch::{fetch, get_resolved_packages, download_order};
 pub use self::cargo_pkgid::pkgid;
+pub use self::cargo_release::{release, ReleaseOptions};
 pub use self::resolve::{resolve_ws, resolve_with_previous};
-pub use self::cargo_output_metadata::{output_metadata, OutputMetadataOptions, ExportInfo};
+pub use self::cargo_output_metadata::{output_metadata, output_metadata_cached};
+pub use self::cargo_output_metadata::output_resolved_metadata;
+pub use self::cargo_output_metadata::{OutputMetadataOptions, ExportInfo};
+pub use self::cargo_tree::{tree, TreeOptions, Edges};
+pub use self::cargo_vendor::{vendor, VendorOptions};
+pub use self::cargo_cost::{cost_report, CostOptions, CostSort};
+pub use self::cargo_lint_manifest::{lint_manifests, LintManifestOptions};
 
+mod bench_baseline;
+mod cargo_bisect;
+mod cargo_bump;
 mod cargo_clean;
 mod cargo_compile;
+mod cargo_cost;
+mod cargo_daemon;
 mod cargo_doc;
 mod cargo_fetch;
 mod cargo_generate_lockfile;
+mod cargo_index_snapshot;
 mod cargo_install;
+mod cargo_lint_manifest;
+mod cargo_name_check;
 mod cargo_new;
 mod cargo_output_metadata;
 mod cargo_package;
 mod cargo_pkgid;
 mod cargo_read_manifest;
+mod cargo_release;
 mod cargo_run;
 mod cargo_rustc;
 mod cargo_test;
+mod cargo_tree;
+mod cargo_vendor;
 mod lockfile;
 mod registry;
 mod resolve;
+mod resolve_wizard;
+mod test_coverage;
@@ -1,14 +1,20 @@
+use std::cmp;
 use std::collections::HashMap;
 use std::env;
 use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::iter::repeat;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use curl::easy::Easy;
 use git2;
 use registry::{Registry, NewCrate, NewCrateDependency};
+use registry::Error as RegistryError;
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json;
 use term::color::BLACK;
+use toml;
 
 use url::percent_encoding::{percent_encode, QUERY_ENCODE_SET};
 
@@ -20,7 +26,8 @@ use ops;
 use sources::{RegistrySource};
 use util::config;
 use util::paths;
-use util::{CargoResult, human, ChainError, ToUrl};
+use util::toml as cargo_toml;
+use util::{CargoResult, human, ChainError, ToUrl, Sha256};
 use util::config::{Config, ConfigValue, Location};
 use util::important_paths::find_root_manifest_for_wd;
 
@@ -36,7 +43,12 @@ pub struct PublishOpts<'cfg> {
     pub verify: bool,
     pub allow_dirty: bool,
     pub jobs: Option<u32>,
+    pub target: Option<&'cfg str>,
+    pub check: bool,
     pub dry_run: bool,
+    /// Prints what's uploaded: the metadata of the crate and the size and
+    /// checksum of the tarball
+    pub show_payload: bool,
 }
 
 pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
@@ -47,10 +59,14 @@ pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
                `{}` is marked as unpublishable", pkg.name());
     }
 
+    try!(check_changelog(opts.config, &pkg));
+
     let (mut registry, reg_id) = try!(registry(opts.config,
                                                opts.token.clone(),
                                                opts.index.clone()));
+    try!(check_permissions(&mut registry, &pkg, opts.dry_run));
     try!(verify_dependencies(&pkg, &reg_id));
+    try!(check_requirements(ws, &pkg, &reg_id));
 
     // Prepare a tarball, with a non-surpressable warning if metadata
     // is missing since this is being put online.
@@ -61,29 +77,209 @@ pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
         check_metadata: true,
         allow_dirty: opts.allow_dirty,
         jobs: opts.jobs,
+        target: opts.target,
+        check: opts.check,
     })).unwrap();
 
     // Upload said tarball to the specified destination
     try!(opts.config.shell().status("Uploading", pkg.package_id().to_string()));
-    try!(transmit(opts.config, &pkg, tarball.file(), &mut registry, opts.dry_run));
+    try!(transmit(opts.config, &pkg, tarball.file(), &mut registry,
+                  opts.dry_run, opts.show_payload));
 
     Ok(())
 }
 
+/// Checks that the API token is accepted by the registry and that its user
+/// may publish `pkg`, as an owner of the crate or because no one claimed its
+/// name yet, so that this doesn't only come up once packaging is over.
+///
+/// Registries which can't answer these questions aren't held against the
+/// package, the upload then has the final say.
+fn check_permissions(registry: &mut Registry, pkg: &Package, dry_run: bool)
+                     -> CargoResult<()> {
+    let me = match registry.me() {
+        Ok(me) => me,
+        Err(RegistryError::Unauthorized) => {
+            bail!("the registry rejected the API token, run `cargo login` \
+                   with a valid token to publish `{}`", pkg.name())
+        }
+        Err(RegistryError::TokenMissing) if dry_run => return Ok(()),
+        Err(e @ RegistryError::TokenMissing) => bail!("{}", e),
+        Err(e) => {
+            debug!("skipping the check of the API token: {}", e);
+            return Ok(())
+        }
+    };
+    let owners = match registry.list_owners(pkg.name()) {
+        Ok(owners) => owners,
+        Err(RegistryError::NotFound) => return Ok(()),
+        Err(e) => {
+            debug!("skipping the check of the owners: {}", e);
+            return Ok(())
+        }
+    };
+    // Membership of the teams owning the crate can't be checked from here
+    let login = me.login.to_lowercase();
+    if owners.iter().any(|o| o.login.to_lowercase() == login ||
+                             o.login.contains(':')) {
+        return Ok(())
+    }
+    let owners = owners.iter().map(|o| format!("`{}`", o.login))
+                       .collect::<Vec<_>>();
+    bail!("`{}` isn't an owner of the crate `{}` on the registry, so it \
+           can't publish it\n\
+           the owners are {}, who can add `{}` with `cargo owner --add {}`",
+          me.login, pkg.name(), owners.join(", "), me.login, me.login)
+}
+
+/// Checks that all dependencies of `pkg` can be found in the registry once
+/// it's published there, listing every one which can't along with how to
+/// fix it.
 fn verify_dependencies(pkg: &Package, registry_src: &SourceId)
                        -> CargoResult<()> {
+    let mut problems = Vec::new();
     for dep in pkg.dependencies().iter() {
-        if dep.source_id().is_path() {
-            if !dep.specified_req() {
-                bail!("all path dependencies must have a version specified \
-                       when publishing.\ndependency `{}` does not specify \
-                       a version", dep.name())
+        let kind = match dep.kind() {
+            Kind::Normal => "dependency",
+            Kind::Build => "build-dependency",
+            Kind::Development => "dev-dependency",
+        };
+        let source = dep.source_id();
+        let problem = if source.is_path() {
+            if dep.specified_req() {
+                continue
             }
-        } else if dep.source_id() != registry_src {
-            bail!("all dependencies must come from the same source.\n\
-                   dependency `{}` comes from {} instead",
-                  dep.name(), dep.source_id())
+            format!("{} `{}` at {} does not specify a version\n  \
+                     add the `version` of `{}` published to the {}",
+                    kind, dep.name(), source, dep.name(), registry_src)
+        } else if source != registry_src {
+            let fix = if source.is_git() {
+                format!("replace the `git` source with the `version` of `{}` \
+                         published to the {}", dep.name(), registry_src)
+            } else {
+                format!("publish to that registry with `--index`, or depend \
+                         on `{}` from the {}", dep.name(), registry_src)
+            };
+            format!("{} `{}` comes from {} instead\n  {}", kind, dep.name(),
+                    source, fix)
+        } else {
+            continue
+        };
+        problems.push(problem);
+    }
+    if !problems.is_empty() {
+        bail!("all dependencies must come from the registry being published \
+               to, and path dependencies must specify a version:\n\n{}",
+              problems.join("\n"))
+    }
+    Ok(())
+}
+
+/// Checks that the requirements on the registry dependencies of `pkg` have an
+/// upper bound, so that they don't accept breaking releases to come like `*`
+/// or a lone `>=` requirement do. Each one found is reported along with the
+/// caret requirement on the locked version it could be replaced with.
+///
+/// This is an error unless the `publish.broad-requirements` configuration key
+/// is set to `warn` or `allow`. Dev-dependencies aren't checked as they don't
+/// affect the users of the package.
+fn check_requirements(ws: &Workspace, pkg: &Package, registry_src: &SourceId)
+                      -> CargoResult<()> {
+    let config = ws.config();
+    let deny = match try!(config.get_string("publish.broad-requirements")) {
+        None => true,
+        Some(v) => match &v.val[..] {
+            "deny" => true,
+            "warn" => false,
+            "allow" => return Ok(()),
+            other => {
+                bail!("publish.broad-requirements must be `allow`, `warn` or \
+                       `deny`, but found `{}` in {}", other, v.definition)
+            }
+        },
+    };
+
+    let resolve = try!(ops::load_pkg_lockfile(ws));
+    let mut problems = Vec::new();
+    for dep in pkg.dependencies().iter() {
+        let req = dep.version_req().to_string();
+        if dep.kind() == Kind::Development ||
+           dep.source_id() != registry_src || !is_unbounded(&req) {
+            continue
         }
+        let locked = resolve.as_ref().and_then(|r| {
+            r.deps(pkg.package_id()).find(|id| id.name() == dep.name())
+        });
+        let fix = match locked {
+            Some(id) => {
+                let v = id.version();
+                format!("use `{} = \"{}.{}.{}\"` to accept the versions \
+                         compatible with the locked {}", dep.name(), v.major,
+                        v.minor, v.patch, v)
+            }
+            None => {
+                format!("add an upper bound, or generate a lock file to \
+                         find out which version of `{}` to require",
+                        dep.name())
+            }
+        };
+        problems.push(format!("dependency `{}` accepts any future version \
+                               with `{}`\n  {}", dep.name(), req, fix));
+    }
+    if problems.is_empty() {
+        return Ok(())
+    }
+    if deny {
+        bail!("the requirements on registry dependencies must have an upper \
+               bound, set `publish.broad-requirements` to `warn` to publish \
+               anyway:\n\n{}", problems.join("\n"))
+    }
+    for problem in problems {
+        try!(config.shell().warn(problem));
+    }
+    Ok(())
+}
+
+/// Whether no predicate of a version requirement bounds it from above, like
+/// `*` or `>= 1.0`.
+fn is_unbounded(req: &str) -> bool {
+    req.split(',').map(|p| p.trim()).all(|p| p == "*" || p.starts_with('>'))
+}
+
+/// Checks that the changelog of `pkg` mentions the version being published.
+///
+/// This is opt-in through the `publish.changelog` configuration key, naming
+/// the changelog file relative to the package root. The heading searched for
+/// is configured with `publish.changelog-heading`, where `{name}` and
+/// `{version}` are substituted, and defaults to `## {version}`.
+fn check_changelog(config: &Config, pkg: &Package) -> CargoResult<()> {
+    let file = match try!(config.get_string("publish.changelog")) {
+        Some(file) => file.val,
+        None => return Ok(()),
+    };
+    let heading = try!(config.get_string("publish.changelog-heading"))
+                        .map(|h| h.val)
+                        .unwrap_or("## {version}".to_string());
+    let heading = heading.replace("{name}", pkg.name())
+                         .replace("{version}", &pkg.version().to_string());
+
+    let path = pkg.root().join(&file);
+    let contents = try!(paths::read(&path).chain_error(|| {
+        human(format!("failed to read the changelog `{}` of `{}`", file,
+                      pkg.name()))
+    }));
+    // "## 1.0.10" isn't an entry for 1.0.1, nor is "## 1.0.1-beta"
+    let has_entry = contents.lines().any(|line| {
+        let line = line.trim();
+        line.starts_with(&heading) &&
+            !line[heading.len()..].starts_with(|c: char| {
+                c.is_alphanumeric() || c == '.' || c == '-' || c == '+'
+            })
+    });
+    if !has_entry {
+        bail!("the changelog `{}` does not contain an entry for version `{}` \
+               of `{}`\nexpected a line starting with `{}`",
+              file, pkg.version(), pkg.name(), heading)
     }
     Ok(())
 }
@@ -92,7 +288,8 @@ fn transmit(config: &Config,
             pkg: &Package,
             tarball: &File,
             registry: &mut Registry,
-            dry_run: bool) -> CargoResult<()> {
+            dry_run: bool,
+            show_payload: bool) -> CargoResult<()> {
     let deps = pkg.dependencies().iter().map(|dep| {
         NewCrateDependency {
             optional: dep.is_optional(),
@@ -126,13 +323,7 @@ fn transmit(config: &Config,
         None => {}
     }
 
-    // Do not upload if performing a dry run
-    if dry_run {
-        try!(config.shell().warn("aborting upload due to dry run"));
-        return Ok(());
-    }
-
-    registry.publish(&NewCrate {
+    let krate = NewCrate {
         name: pkg.name().to_string(),
         vers: pkg.version().to_string(),
         deps: deps,
@@ -146,9 +337,130 @@ fn transmit(config: &Config,
         repository: repository.clone(),
         license: license.clone(),
         license_file: license_file.clone(),
-    }, tarball).map_err(|e| {
-        human(e.to_string())
-    })
+    };
+    if show_payload {
+        try!(show_upload(config, &krate, tarball));
+    }
+
+    // Do not upload if performing a dry run
+    if dry_run {
+        try!(config.shell().warn("aborting upload due to dry run"));
+        return Ok(());
+    }
+
+    let size = try!(tarball.metadata()).len();
+    match try!(upload_chunk_size(config)) {
+        Some(chunk_size) if size > chunk_size => {
+            upload_in_chunks(config, registry, &krate, tarball, chunk_size)
+        }
+        _ => {
+            registry.publish(&krate, tarball).map_err(|e| {
+                human(e.to_string())
+            })
+        }
+    }
+}
+
+/// The size of the chunks tarballs are uploaded in, if larger than that,
+/// `registry.upload-chunk-size`.
+fn upload_chunk_size(config: &Config) -> CargoResult<Option<u64>> {
+    match try!(config.get_i64("registry.upload-chunk-size")) {
+        Some(v) => {
+            if v.val <= 0 {
+                bail!("registry.upload-chunk-size must be positive, but found \
+                       {} in {}", v.val, v.definition)
+            }
+            Ok(Some(v.val as u64))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Uploads `tarball` a chunk at a time, sending each chunk again on network
+/// errors and server errors (5xx) as often as `net.retry` allows, while the
+/// other errors are reported right away. Should the upload fail anyway, the
+/// registry keeps what it received, and publishing again resumes from there.
+fn upload_in_chunks(config: &Config,
+                    registry: &mut Registry,
+                    krate: &NewCrate,
+                    mut tarball: &File,
+                    chunk_size: u64) -> CargoResult<()> {
+    let (size, cksum) = try!(tarball_checksum(tarball));
+    let upload = try!(registry.start_upload(krate, size, &cksum).map_err(|e| {
+        human(format!("failed to start uploading in chunks, the registry may \
+                       not support it: {}", e))
+    }));
+    if upload.received > 0 {
+        try!(config.shell().status("Resuming",
+                                   format!("upload at {} of {} bytes",
+                                           upload.received, size)));
+    }
+
+    let retries = try!(config.net_retry());
+    let mut offset = cmp::min(upload.received, size);
+    let mut chunk = vec![0; cmp::min(chunk_size, size) as usize];
+    try!(tarball.seek(SeekFrom::Start(offset)));
+    while offset < size {
+        let len = cmp::min(chunk_size, size - offset) as usize;
+        try!(tarball.read_exact(&mut chunk[..len]));
+        let mut remaining = retries;
+        loop {
+            let e = match registry.upload_chunk(&upload.id, offset,
+                                                &chunk[..len]) {
+                Ok(()) => break,
+                Err(e) => e,
+            };
+            let spurious = match e {
+                RegistryError::Curl(..) => true,
+                RegistryError::NotOkResponse(code, _, _) => code >= 500,
+                _ => false,
+            };
+            if !spurious || remaining == 0 {
+                bail!("failed to upload the tarball at byte {} of {}, publish \
+                       again to resume the upload\n{}", offset, size, e)
+            }
+            try!(config.shell().warn(format!("failed to upload a chunk ({} \
+                                              tries remaining): {}",
+                                             remaining, e)));
+            remaining -= 1;
+        }
+        offset += len as u64;
+        try!(config.shell().verbose(|shell| {
+            shell.status("Uploaded", format!("{} of {} bytes", offset, size))
+        }));
+    }
+
+    registry.finish_upload(&upload.id).map_err(|e| human(e.to_string()))
+}
+
+/// Returns the size and the sha256 checksum of `tarball`, leaving it at its
+/// start for it to be uploaded.
+fn tarball_checksum(mut tarball: &File) -> CargoResult<(u64, String)> {
+    let mut state = Sha256::new();
+    let mut size = 0;
+    let mut buf = [0; 64 * 1024];
+    try!(tarball.seek(SeekFrom::Start(0)));
+    loop {
+        let n = try!(tarball.read(&mut buf));
+        if n == 0 {
+            break
+        }
+        state.update(&buf[..n]);
+        size += n as u64;
+    }
+    try!(tarball.seek(SeekFrom::Start(0)));
+    Ok((size, state.finish().to_hex()))
+}
+
+/// Prints the metadata of `krate` as it's sent to the registry, along with
+/// the size and checksum of the tarball uploaded with it.
+fn show_upload(config: &Config, krate: &NewCrate, tarball: &File)
+               -> CargoResult<()> {
+    let (size, cksum) = try!(tarball_checksum(tarball));
+    println!("{}", json::as_pretty_json(krate));
+    try!(config.shell().status("Tarball", format!("{} bytes, sha256 {}",
+                                                  size, cksum)));
+    Ok(())
 }
 
 pub fn registry_configuration(config: &Config) -> CargoResult<RegistryConfig> {
@@ -276,6 +588,10 @@ pub struct OwnersOptions {
     pub to_add: Option<Vec<String>>,
     pub to_remove: Option<Vec<String>>,
     pub list: bool,
+    /// TOML file whose `owners` array lists the owners the crate should have
+    pub sync: Option<PathBuf>,
+    /// Whether to apply the changes made by `sync` without asking first
+    pub yes: bool,
 }
 
 pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
@@ -315,6 +631,10 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
         None => {}
     }
 
+    if let Some(ref path) = opts.sync {
+        try!(sync_owners(config, &mut registry, &name, path, opts.yes));
+    }
+
     if opts.list {
         let owners = try!(registry.list_owners(&name).map_err(|e| {
             human(format!("failed to list owners of crate {}: {}", name, e))
@@ -333,6 +653,94 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
     Ok(())
 }
 
+/// Adds and removes owners of the crate `name` so that they match the list in
+/// the `owners` array of the TOML file at `path`:
+///
+/// ```toml
+/// owners = ["alice", "github:rust-lang:owners"]
+/// ```
+///
+/// The changes are printed, and only applied once confirmed on stdin unless
+/// `yes` is set.
+fn sync_owners(config: &Config,
+               registry: &mut Registry,
+               name: &str,
+               path: &Path,
+               yes: bool) -> CargoResult<()> {
+    let contents = try!(paths::read(path));
+    let table = try!(cargo_toml::parse(&contents, path, config));
+    let desired = match table.get("owners") {
+        Some(&toml::Value::Array(ref owners)) => {
+            try!(owners.iter().map(|owner| {
+                match *owner {
+                    toml::Value::String(ref s) => Ok(s.clone()),
+                    _ => bail!("the `owners` of `{}` must be strings",
+                               path.display()),
+                }
+            }).collect::<CargoResult<Vec<String>>>())
+        }
+        _ => bail!("`{}` must contain an `owners` array", path.display()),
+    };
+    if desired.is_empty() {
+        bail!("refusing to remove every owner of crate {}", name)
+    }
+
+    let current = try!(registry.list_owners(name).map_err(|e| {
+        human(format!("failed to list owners of crate {}: {}", name, e))
+    }));
+    let current = current.into_iter().map(|u| u.login).collect::<Vec<_>>();
+    // Logins aren't case sensitive
+    fn contains(list: &[String], login: &str) -> bool {
+        list.iter().any(|s| s.to_lowercase() == login.to_lowercase())
+    }
+    let to_add = desired.iter().filter(|s| !contains(&current, s))
+                        .map(|s| &s[..]).collect::<Vec<_>>();
+    let to_remove = current.iter().filter(|s| !contains(&desired, s))
+                           .map(|s| &s[..]).collect::<Vec<_>>();
+    if to_add.is_empty() && to_remove.is_empty() {
+        try!(config.shell().status("Owner", format!("owners of crate {} are \
+                                                     up to date", name)));
+        return Ok(())
+    }
+
+    for login in to_add.iter() {
+        println!("  + {}", login);
+    }
+    for login in to_remove.iter() {
+        println!("  - {}", login);
+    }
+    if !yes {
+        print!("Apply these changes to the owners of {}? [y/N] ", name);
+        try!(io::stdout().flush());
+        let mut line = String::new();
+        try!(io::stdin().read_line(&mut line).chain_error(|| {
+            human("failed to read stdin")
+        }));
+        if line.trim() != "y" && line.trim() != "yes" {
+            bail!("owners of crate {} left unchanged", name)
+        }
+    }
+
+    // Adding first makes sure the crate never ends up without owners
+    if !to_add.is_empty() {
+        try!(config.shell().status("Owner", format!("adding {:?} to crate {}",
+                                                    to_add, name)));
+        try!(registry.add_owners(name, &to_add).map_err(|e| {
+            human(format!("failed to add owners to crate {}: {}", name, e))
+        }));
+    }
+    if !to_remove.is_empty() {
+        try!(config.shell().status("Owner",
+                                   format!("removing {:?} from crate {}",
+                                           to_remove, name)));
+        try!(registry.remove_owners(name, &to_remove).map_err(|e| {
+            human(format!("failed to remove owners from crate {}: {}",
+                          name, e))
+        }));
+    }
+    Ok(())
+}
+
 pub fn yank(config: &Config,
             krate: Option<String>,
             version: Option<String>,
@@ -374,10 +782,11 @@ pub fn search(query: &str,
               index: Option<String>,
               limit: u8) -> CargoResult<()> {
     fn truncate_with_ellipsis(s: &str, max_length: usize) -> String {
-        if s.len() < max_length {
+        if s.chars().count() <= max_length {
             s.to_string()
         } else {
-            format!("{}…", &s[..max_length - 1])
+            let s = s.chars().take(max_length - 1).collect::<String>();
+            format!("{}…", s)
         }
     }
 
@@ -389,23 +798,33 @@ pub fn search(query: &str,
     let list_items = crates.iter()
         .map(|krate| (
             format!("{} ({})", krate.name, krate.max_version),
-            krate.description.as_ref().map(|desc|
-                truncate_with_ellipsis(&desc.replace("\n", " "), 128))
+            krate.description.as_ref().map(|desc| desc.replace("\n", " "))
         ))
         .collect::<Vec<_>>();
     let description_margin = list_items.iter()
-        .map(|&(ref left, _)| left.len() + 4)
+        .map(|&(ref left, _)| left.chars().count() + 4)
         .max()
         .unwrap_or(0);
 
+    // Descriptions are only lined up in a column, and cut off at the edge of
+    // the screen, when printing to a terminal wide enough for it. Otherwise
+    // the padding would just be noise in a pipe or wrap around on a narrow
+    // terminal.
+    let width = config.shell().out().width();
+    let column_width = width.and_then(|w| w.checked_sub(description_margin))
+                            .and_then(|w| if w >= 20 {Some(w)} else {None});
+
     for (name, description) in list_items.into_iter() {
-        let line = match description {
-            Some(desc) => {
-                let space = repeat(' ').take(description_margin - name.len())
-                                       .collect::<String>();
-                name + &space + &desc
+        let line = match (description, column_width) {
+            (Some(desc), Some(column_width)) => {
+                let margin = description_margin - name.chars().count();
+                let space = repeat(' ').take(margin).collect::<String>();
+                name + &space + &truncate_with_ellipsis(&desc, column_width)
+            }
+            (Some(desc), None) => {
+                name + "    " + &truncate_with_ellipsis(&desc, 128)
             }
-            None => name
+            (None, _) => name
         };
         try!(config.shell().say(line, BLACK));
     }
@@ -1,23 +1,37 @@
 use std::collections::{HashMap, HashSet};
 
-use core::{PackageId, SourceId, Workspace};
+use core::{Dependency, PackageId, PackageIdSpec, SourceId, Workspace};
 use core::registry::PackageRegistry;
 use core::resolver::{self, Resolve, Method};
 use ops;
+use ops::resolve_wizard;
+use sources::git;
 use util::CargoResult;
 
 /// Resolve all dependencies for the specified `package` using the previous
 /// lockfile as a guide if present.
 ///
 /// This function will also write the result of resolution as a new
-/// lockfile.
+/// lockfile. If resolution fails, the changes to the manifests which would
+/// make it succeed may be offered interactively.
 pub fn resolve_ws(registry: &mut PackageRegistry, ws: &Workspace)
                    -> CargoResult<Resolve> {
     let prev = try!(ops::load_pkg_lockfile(ws));
-    let resolve = try!(resolve_with_previous(registry, ws,
-                                             Method::Everything,
-                                             prev.as_ref(), None));
-    if try!(ws.current()).package_id().source_id().is_path() {
+    let resolve = match resolve_with_previous(registry, ws,
+                                              Method::Everything,
+                                              prev.as_ref(), None, &[]) {
+        Ok(resolve) => resolve,
+        Err(e) => {
+            if try!(resolve_wizard::wanted(ws.config())) {
+                return Err(resolve_wizard::resolve_conflict(registry, ws, e))
+            }
+            return Err(e)
+        }
+    };
+    let is_path = ws.current_opt().map_or(true, |p| {
+        p.package_id().source_id().is_path()
+    });
+    if is_path {
         try!(ops::write_pkg_lockfile(ws, &resolve));
     }
     Ok(resolve)
@@ -30,13 +44,17 @@ pub fn resolve_ws(registry: &mut PackageRegistry, ws: &Workspace)
 /// ids that should be avoided when consulting the previous instance of resolve
 /// (often used in pairings with updates).
 ///
+/// When resolving with `Method::Required`, only the current package and the
+/// workspace members matching one of `specs` are resolved.
+///
 /// The previous resolve normally comes from a lockfile. This function does not
 /// read or write lockfiles from the filesystem.
 pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
                                  ws: &Workspace,
                                  method: Method,
                                  previous: Option<&'a Resolve>,
-                                 to_avoid: Option<&HashSet<&'a PackageId>>)
+                                 to_avoid: Option<&HashSet<&'a PackageId>>,
+                                 specs: &[PackageIdSpec])
                                  -> CargoResult<Resolve> {
     // Here we place an artificial limitation that all non-registry sources
     // cannot be locked at more than one revision. This means that if a git
@@ -52,6 +70,16 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
                                         .filter(|s| !s.is_registry()));
     }
 
+    try!(prefetch_git(ws, previous, &to_avoid_sources));
+
+    // When nothing is being updated and the previous resolve satisfies the
+    // manifests, there's no need to wait on updating the registry indices.
+    if to_avoid.is_none() {
+        if let Some(ids) = previous.and_then(|r| locked_registries(ws, r)) {
+            try!(registry.add_sources(&ids));
+        }
+    }
+
     let mut summaries = Vec::new();
     for member in ws.members() {
         try!(registry.add_sources(&[member.package_id().source_id()
@@ -59,14 +87,17 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
 
         // If we're resolving everything then we include all members of the
         // workspace. If we want a specific set of requirements then we only
-        // resolve the main crate as it's the only one we're compiling. This
-        // case should only happen after we have a previous resolution, however,
-        // so assert that the previous exists.
+        // resolve the crates we're compiling: the main crate and any members
+        // selected through `specs`. This case should only happen after we
+        // have a previous resolution, however, so assert that the previous
+        // exists.
         let method = match method {
             Method::Everything => Method::Everything,
             Method::Required { .. } => {
                 assert!(previous.is_some());
-                if member.package_id() == try!(ws.current()).package_id() {
+                let current = ws.current_opt().map(|p| p.package_id());
+                if specs.iter().any(|s| s.matches(member.package_id())) ||
+                   current == Some(member.package_id()) {
                     method
                 } else {
                     continue
@@ -121,9 +152,26 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
             }).collect::<HashMap<_, _>>();
 
             member.summary().clone().map_dependencies(|dep| {
-                match map.get(dep.name()) {
-                    Some(&lock) if dep.matches_id(lock) => dep.lock_to(lock),
-                    _ => dep,
+                if let Some(&lock) = map.get(dep.name()) {
+                    if dep.matches_id(lock) {
+                        return dep.lock_to(lock)
+                    }
+                }
+
+                // A dependency which wasn't previously locked for this member
+                // was most likely just added to the manifest. If a matching
+                // package is already part of the previous resolve (through
+                // another member or a transitive dependency) then lock to the
+                // newest such version, which avoids updating the registry
+                // index when the lock file can already satisfy the build.
+                let candidate = r.iter().filter(|p| {
+                    keep(p, to_avoid, &to_avoid_sources) &&
+                        p.source_id() == dep.source_id() &&
+                        dep.matches_id(p)
+                }).max_by_key(|p| p.version());
+                match candidate {
+                    Some(lock) => dep.lock_to(lock),
+                    None => dep,
                 }
             })
         };
@@ -148,13 +196,26 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
         None => root_replace.to_vec(),
     };
 
-    let mut resolved = try!(resolver::resolve(try!(ws.current()).package_id(),
+    // Virtual manifests don't have a package of their own, so one of the
+    // members stands in as the root of the graph.
+    let root = match ws.current_opt().or(ws.members().next()) {
+        Some(pkg) => pkg.package_id(),
+        None => bail!("the workspace at `{}` has no members",
+                      ws.root().display()),
+    };
+    let mut resolved = try!(resolver::resolve(root,
                                               &summaries,
                                               &replace,
                                               registry));
     if let Some(previous) = previous {
         resolved.copy_metadata(previous);
     }
+    try!(ops::record_index_snapshot(ws.config(), &mut resolved, previous));
+    let everything = match method {
+        Method::Everything => true,
+        Method::Required { .. } => false,
+    };
+    try!(record_checksums(&mut resolved, previous, everything));
     return Ok(resolved);
 
     fn keep<'a>(p: &&'a PackageId,
@@ -167,3 +228,102 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
         }
     }
 }
+
+/// Returns the registries of `previous`, locked so they aren't updated, if it
+/// has a package matching each registry dependency of the members and of the
+/// replacements of the workspace.
+fn locked_registries(ws: &Workspace, previous: &Resolve)
+                     -> Option<Vec<SourceId>> {
+    let satisfied = |dep: &Dependency| {
+        !dep.source_id().is_registry() || previous.iter().any(|p| {
+            p.source_id() == dep.source_id() && dep.matches_id(p)
+        })
+    };
+    let members = ws.members().all(|member| {
+        member.dependencies().iter().all(|dep| satisfied(dep))
+    });
+    let replace = ws.root_replace().iter().all(|&(_, ref dep)| {
+        satisfied(dep)
+    });
+    if !members || !replace {
+        return None
+    }
+    let mut ids = previous.iter().map(|p| p.source_id())
+                          .filter(|s| s.is_registry())
+                          .cloned().collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    Some(ids.into_iter().map(|id| {
+        id.with_precise(Some("locked".to_string()))
+    }).collect())
+}
+
+/// Records the checksums of the resolved packages in the `[metadata]` of the
+/// lock file, erroring if one differs from what the previous lock file lists.
+///
+/// When the whole graph was resolved the checksums of packages which are no
+/// longer part of it are dropped.
+fn record_checksums(resolve: &mut Resolve,
+                    previous: Option<&Resolve>,
+                    everything: bool) -> CargoResult<()> {
+    let key = |id: &PackageId| {
+        format!("checksum {} {} ({})", id.name(), id.version(),
+                id.source_id().to_url())
+    };
+    let checksums = resolve.checksums().iter().filter_map(|(id, cksum)| {
+        cksum.as_ref().map(|c| (id.clone(), c.clone()))
+    }).collect::<Vec<_>>();
+
+    for (id, cksum) in checksums.iter().cloned() {
+        let key = key(&id);
+        let prev = previous.and_then(|r| r.metadata_value(&key));
+        if let Some(prev) = prev {
+            if prev != cksum {
+                bail!("checksum for `{}` changed between lock files\n\n\
+                       this could be indicative of either a source mistake, \
+                       the vendored files having been replaced, or the lock \
+                       file being corrupt", id)
+            }
+        }
+        resolve.set_metadata(&key, Some(cksum));
+    }
+
+    if !everything {
+        return Ok(())
+    }
+    let current = checksums.iter().map(|&(ref id, _)| key(id))
+                           .collect::<HashSet<_>>();
+    for stale in resolve.metadata_keys() {
+        if stale.starts_with("checksum ") && !current.contains(&stale) {
+            resolve.set_metadata(&stale, None);
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the git repositories the resolution is known to need ahead of it,
+/// all at once: those locked by the previous resolve and those the members
+/// depend on directly.
+fn prefetch_git(ws: &Workspace,
+                previous: Option<&Resolve>,
+                to_avoid_sources: &HashSet<&SourceId>) -> CargoResult<()> {
+    let mut ids = Vec::new();
+    if let Some(r) = previous {
+        ids.extend(r.iter().map(|p| p.source_id()).filter(|s| {
+            s.is_git() && !to_avoid_sources.contains(s)
+        }).cloned());
+    }
+    for member in ws.members() {
+        for dep in member.dependencies() {
+            let id = dep.source_id();
+            // Locked repositories are only fetched at their locked revision
+            let locked = ids.iter().any(|s: &SourceId| {
+                s.url() == id.url() && s.git_reference() == id.git_reference()
+            });
+            if id.is_git() && !locked {
+                ids.push(id.clone());
+            }
+        }
+    }
+    git::prefetch(ws.config(), &ids)
+}
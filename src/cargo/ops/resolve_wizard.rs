@@ -0,0 +1,357 @@
+//! Guided recovery from a failed dependency resolution.
+//!
+//! When the dependencies of a workspace can't be resolved, each dependency of
+//! its members is relaxed in turn, by requiring another version of it or by no
+//! longer enabling one of its features, and the relaxations which make the
+//! resolution succeed are offered. The one picked is then written to the
+//! manifest it concerns, once the rewrite has been confirmed.
+//!
+//! This only happens when both stdin, which answers are read from, and stderr
+//! are terminals, unless `term.resolve-wizard` says otherwise, and never with
+//! `--frozen` or `--locked`. Each relaxation is tried with a resolution of
+//! its own, so only so many are tried before giving up on the others.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use semver::VersionReq;
+
+use core::{Dependency, Package, Registry, Workspace};
+use core::registry::PackageRegistry;
+use core::resolver::{self, Method};
+use ops::cargo_bump::{inline_key, is_dep_table, join_lines, key_of,
+                      section_name};
+use util::{CargoError, CargoResult, ChainError, Config, human, paths};
+
+/// A change to one dependency of a workspace member.
+struct Fix<'a> {
+    member: &'a Package,
+    // Position of the dependency in those of `member`
+    index: usize,
+    dep: &'a Dependency,
+    change: Change,
+}
+
+enum Change {
+    /// Requires the version given as is, which is the version requirement
+    /// it parses to
+    Requirement(String, VersionReq),
+    /// Stops enabling a feature of the dependency
+    RemoveFeature(String),
+}
+
+/// How many relaxations are tried at most, each costing a resolution.
+const MAX_ATTEMPTS: usize = 50;
+
+/// Whether resolution failures should be followed by the wizard.
+pub fn wanted(config: &Config) -> CargoResult<bool> {
+    // The manifests aren't meant to change any more than the lock file then
+    if !config.lock_update_allowed() {
+        return Ok(false)
+    }
+    match try!(config.get_bool("term.resolve-wizard")) {
+        Some(v) => Ok(v.val),
+        None => Ok(config.shell().err().is_tty() && stdin_isatty()),
+    }
+}
+
+#[cfg(unix)]
+fn stdin_isatty() -> bool {
+    use libc;
+
+    unsafe { libc::isatty(libc::STDIN_FILENO) != 0 }
+}
+
+#[cfg(windows)]
+fn stdin_isatty() -> bool {
+    extern crate kernel32;
+    extern crate winapi;
+
+    unsafe {
+        let handle = kernel32::GetStdHandle(winapi::winbase::STD_INPUT_HANDLE);
+        let mut out = 0;
+        kernel32::GetConsoleMode(handle, &mut out) != 0
+    }
+}
+
+/// Offers the changes to the manifests of `ws` which would resolve its
+/// dependencies after resolution failed with `err`, returning the error to
+/// report in the end.
+pub fn resolve_conflict(registry: &mut PackageRegistry,
+                        ws: &Workspace,
+                        err: Box<CargoError>) -> Box<CargoError> {
+    match run(registry, ws, err) {
+        Ok(err) | Err(err) => err,
+    }
+}
+
+fn run(registry: &mut PackageRegistry,
+       ws: &Workspace,
+       err: Box<CargoError>) -> CargoResult<Box<CargoError>> {
+    let config = ws.config();
+    let fixes = try!(find_fixes(registry, ws));
+    if fixes.is_empty() {
+        return Ok(err)
+    }
+
+    try!(config.shell().error(&err));
+    let mut stderr = io::stderr();
+    try!(writeln!(stderr, "\nThe dependencies would resolve with any one of \
+                           these changes:"));
+    for (i, fix) in fixes.iter().enumerate() {
+        try!(writeln!(stderr, "  {}: {}", i + 1, fix));
+    }
+
+    let unchanged = || {
+        human("failed to resolve dependencies, the manifests were left \
+               unchanged")
+    };
+    let answer = try!(prompt(&format!("Apply which change? [1-{}, or nothing \
+                                       to leave the manifests alone] ",
+                                      fixes.len())));
+    let fix = match answer.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= fixes.len() => &fixes[n - 1],
+        _ => return Ok(unchanged()),
+    };
+    let path = fix.member.manifest_path();
+    let answer = try!(prompt(&format!("Rewrite `{}` to {}? [y/N] ",
+                                      path.display(), fix)));
+    if answer != "y" && answer != "yes" {
+        return Ok(unchanged())
+    }
+
+    let contents = try!(paths::read(path));
+    let contents = try!(fix.apply(&contents).chain_error(|| {
+        human(format!("failed to find the dependency on `{}` in `{}`, it \
+                       has to be changed by hand", fix.dep.name(),
+                      path.display()))
+    }));
+    try!(paths::write(path, contents.as_bytes()));
+    try!(config.shell().status("Updated", path.display()));
+    Ok(human(format!("the manifest of `{}` was changed for its dependencies \
+                      to resolve, run the command again to use it",
+                     fix.member.name())))
+}
+
+fn prompt(msg: &str) -> CargoResult<String> {
+    let mut stderr = io::stderr();
+    try!(write!(stderr, "{}", msg));
+    try!(stderr.flush());
+    let mut line = String::new();
+    try!(io::stdin().read_line(&mut line).chain_error(|| {
+        human("failed to read stdin")
+    }));
+    Ok(line.trim().to_string())
+}
+
+/// Relaxes each dependency of the members of `ws` which doesn't come from a
+/// path, keeping the relaxations with which resolution succeeds.
+///
+/// Only the newest version of each semver compatible series outside of the
+/// current requirement is tried, and only the newest series which works is
+/// kept. No more than `MAX_ATTEMPTS` relaxations are tried in all.
+fn find_fixes<'a>(registry: &mut PackageRegistry, ws: &'a Workspace)
+                  -> CargoResult<Vec<Fix<'a>>> {
+    let mut fixes = Vec::new();
+    let mut attempts = 0;
+    let mut try_fix = |registry: &mut PackageRegistry, fix: &Fix| {
+        attempts += 1;
+        if attempts > MAX_ATTEMPTS {
+            return Ok(false)
+        }
+        resolves(registry, ws, fix)
+    };
+    for member in ws.members() {
+        for (i, dep) in member.dependencies().iter().enumerate() {
+            if dep.source_id().is_path() {
+                continue
+            }
+            let fix = |change| {
+                Fix { member: member, index: i, dep: dep, change: change }
+            };
+
+            if dep.specified_req() {
+                let any = VersionReq::parse("*").unwrap();
+                let all = dep.clone_inner().set_version_req(any)
+                             .into_dependency();
+                let mut versions = try!(registry.query(&all)).iter()
+                                       .map(|s| s.version().clone())
+                                       .collect::<Vec<_>>();
+                versions.sort();
+                let mut tried = Vec::<VersionReq>::new();
+                for v in versions.into_iter().rev() {
+                    if dep.version_req().matches(&v) ||
+                       tried.iter().any(|r| r.matches(&v)) {
+                        continue
+                    }
+                    let req = VersionReq::parse(&v.to_string()).unwrap();
+                    tried.push(req.clone());
+                    let candidate = fix(Change::Requirement(v.to_string(),
+                                                            req));
+                    if try!(try_fix(registry, &candidate)) {
+                        fixes.push(candidate);
+                        break
+                    }
+                }
+            }
+
+            for feature in dep.features() {
+                let candidate = fix(Change::RemoveFeature(feature.clone()));
+                if try!(try_fix(registry, &candidate)) {
+                    fixes.push(candidate);
+                }
+            }
+        }
+    }
+    Ok(fixes)
+}
+
+fn resolves(registry: &mut PackageRegistry, ws: &Workspace, fix: &Fix)
+            -> CargoResult<bool> {
+    let summaries = ws.members().map(|member| {
+        let mut summary = member.summary().clone();
+        if member.package_id() == fix.member.package_id() {
+            let mut i = 0;
+            summary = summary.map_dependencies(|dep| {
+                i += 1;
+                if i - 1 == fix.index {fix.relax(dep)} else {dep}
+            });
+        }
+        (summary, Method::Everything)
+    }).collect::<Vec<_>>();
+    let root = match ws.current_opt().or(ws.members().next()) {
+        Some(pkg) => pkg.package_id(),
+        None => return Ok(false),
+    };
+    Ok(resolver::resolve(root, &summaries, ws.root_replace(),
+                         registry).is_ok())
+}
+
+impl<'a> Fix<'a> {
+    fn relax(&self, dep: Dependency) -> Dependency {
+        let inner = dep.clone_inner();
+        let inner = match self.change {
+            Change::Requirement(_, ref req) => {
+                inner.set_version_req(req.clone())
+            }
+            Change::RemoveFeature(ref feature) => {
+                let features = dep.features().iter()
+                                  .filter(|f| *f != feature)
+                                  .cloned().collect();
+                inner.set_features(features)
+            }
+        };
+        inner.into_dependency()
+    }
+
+    /// Rewrites the manifest `contents` of the member, returning `None` if
+    /// the dependency couldn't be found in it.
+    fn apply(&self, contents: &str) -> Option<String> {
+        let name = self.dep.name();
+        match self.change {
+            Change::Requirement(ref version, _) => {
+                rewrite_dependency(contents, name, "version", |line, start| {
+                    replace_quoted(line, start, version)
+                })
+            }
+            Change::RemoveFeature(ref feature) => {
+                rewrite_dependency(contents, name, "features", |line, start| {
+                    remove_from_array(line, start, feature)
+                })
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for Fix<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.change {
+            Change::Requirement(ref version, _) => {
+                write!(f, "require `{}` version `{}` in `{}` instead of `{}`",
+                       self.dep.name(), version, self.member.name(),
+                       self.dep.version_req())
+            }
+            Change::RemoveFeature(ref feature) => {
+                write!(f, "stop enabling feature `{}` of `{}` in `{}`",
+                       feature, self.dep.name(), self.member.name())
+            }
+        }
+    }
+}
+
+/// Rewrites the `field` of all dependencies named `name` with `f`, which is
+/// given a line and where the value of the field starts in it.
+///
+/// Like the rewrites of `cargo bump` this goes line by line to preserve the
+/// formatting of the manifest, so values spanning several lines aren't found.
+fn rewrite_dependency<F>(contents: &str, name: &str, field: &str, mut f: F)
+                         -> Option<String>
+    where F: FnMut(&str, usize) -> Option<String>
+{
+    let mut section = String::new();
+    let mut changed = false;
+    let lines = contents.lines().map(|line| {
+        if let Some(s) = section_name(line) {
+            section = s;
+            return line.to_string()
+        }
+        let in_deps = is_dep_table(&section);
+        let in_dep = section.rsplitn(2, '.').next() == Some(name) &&
+                     section.rsplitn(2, '.').nth(1).map(is_dep_table)
+                            .unwrap_or(false);
+        let key = key_of(line);
+        let start = if in_deps && key == Some(name) {
+            // Either `name = "req"` or `name = { field = .. }`
+            let eq = line.find('=').unwrap() + 1;
+            if line[eq..].trim_left().starts_with('"') {
+                if field == "version" {Some(eq)} else {None}
+            } else {
+                inline_key(line, field)
+            }
+        } else if in_dep && key == Some(field) {
+            Some(0)
+        } else {
+            None
+        };
+        match start.and_then(|start| f(line, start)) {
+            Some(l) => { changed = true; l }
+            None => line.to_string(),
+        }
+    }).collect::<Vec<_>>();
+    if changed {Some(join_lines(contents, lines))} else {None}
+}
+
+/// Replaces the first quoted string after `start` with `new`.
+fn replace_quoted(line: &str, start: usize, new: &str) -> Option<String> {
+    let open = match line[start..].find('"') {
+        Some(i) => start + i + 1,
+        None => return None,
+    };
+    let close = match line[open..].find('"') {
+        Some(i) => open + i,
+        None => return None,
+    };
+    Some(format!("{}{}{}", &line[..open], new, &line[close..]))
+}
+
+/// Removes `item` from the first array after `start`.
+fn remove_from_array(line: &str, start: usize, item: &str) -> Option<String> {
+    let open = match line[start..].find('[') {
+        Some(i) => start + i + 1,
+        None => return None,
+    };
+    let close = match line[open..].find(']') {
+        Some(i) => open + i,
+        None => return None,
+    };
+    let items = line[open..close].split(',').map(|s| s.trim())
+                                 .filter(|s| !s.is_empty())
+                                 .collect::<Vec<_>>();
+    let kept = items.iter().filter(|s| {
+        s.trim_matches(|c| c == '"' || c == '\'') != item
+    }).cloned().collect::<Vec<_>>();
+    if kept.len() == items.len() {
+        return None
+    }
+    Some(format!("{}{}{}", &line[..open], kept.join(", "), &line[close..]))
+}
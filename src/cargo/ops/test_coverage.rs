@@ -0,0 +1,94 @@
+//! Code coverage of the tests run by `cargo test --coverage`.
+//!
+//! The workspace members are compiled with LLVM's source based coverage
+//! instrumentation, and every test executable writes a raw profile of what it
+//! ran to `target/coverage/`, as directed by `LLVM_PROFILE_FILE`. With
+//! `--lcov` the raw profiles are then merged by `llvm-profdata` and turned
+//! into `target/coverage/lcov.info` by `llvm-cov`, both of which are looked up
+//! on the `PATH`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use core::{Target, Workspace};
+use ops::Compilation;
+use util::{CargoResult, ChainError, Config, human, paths, process};
+
+/// Flags the code of the workspace members is compiled with for coverage.
+pub const RUSTFLAGS: &'static [&'static str] = &["-Zinstrument-coverage"];
+
+/// Returns the directory the raw profiles are written to, emptied of those of
+/// earlier runs so they don't end up in the report.
+pub fn prepare(ws: &Workspace) -> CargoResult<PathBuf> {
+    let dir = ws.target_dir().into_path_unlocked().join("coverage");
+    if dir.exists() {
+        for entry in try!(fs::read_dir(&dir)) {
+            let path = try!(entry).path();
+            if is_raw_profile(&path) {
+                try!(fs::remove_file(&path));
+            }
+        }
+    }
+    try!(fs::create_dir_all(&dir));
+    Ok(dir)
+}
+
+/// The value of `LLVM_PROFILE_FILE` for the executable of `target`. Each
+/// process gets its own file, as tests may spawn the executable again.
+pub fn profile_file(dir: &Path, target: &Target) -> PathBuf {
+    dir.join(format!("{}-%p-%m.profraw", target.name()))
+}
+
+fn is_raw_profile(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("profraw")
+}
+
+/// Merges the raw profiles in `dir` and writes the lcov report of the test
+/// executables of `compilation` next to them, returning its path.
+pub fn report(config: &Config, dir: &Path, compilation: &Compilation)
+              -> CargoResult<PathBuf> {
+    let mut profiles = Vec::new();
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+        if is_raw_profile(&path) {
+            profiles.push(path);
+        }
+    }
+    if profiles.is_empty() {
+        bail!("no coverage profiles were written to `{}`", dir.display())
+    }
+    profiles.sort();
+
+    let profdata = dir.join("coverage.profdata");
+    let mut merge = process("llvm-profdata");
+    merge.arg("merge").arg("-sparse").args(&profiles)
+         .arg("-o").arg(&profdata);
+    try!(config.shell().verbose(|shell| {
+        shell.status("Running", merge.to_string())
+    }));
+    try!(merge.exec_with_output().chain_error(|| {
+        human("failed to merge the coverage profiles, is `llvm-profdata` \
+               installed?")
+    }));
+
+    let mut export = process("llvm-cov");
+    export.arg("export").arg("-format=lcov")
+          .arg(format!("-instr-profile={}", profdata.display()));
+    for (i, &(_, _, ref exe)) in compilation.tests.iter().enumerate() {
+        if i > 0 {
+            export.arg("-object");
+        }
+        export.arg(exe);
+    }
+    try!(config.shell().verbose(|shell| {
+        shell.status("Running", export.to_string())
+    }));
+    let output = try!(export.exec_with_output().chain_error(|| {
+        human("failed to export the coverage report, is `llvm-cov` \
+               installed?")
+    }));
+
+    let lcov = dir.join("lcov.info");
+    try!(paths::write(&lcov, &output.stdout));
+    Ok(lcov)
+}
@@ -0,0 +1,151 @@
+//! Implementation of configuration for various sources
+//!
+//! This module will parse the various `source.*` TOML configuration keys into a
+//! structure usable by Cargo itself. Currently this is primarily used to map
+//! sources to one another via the `replace-with` key in `.cargo/config`.
+
+use std::collections::HashMap;
+
+use url::Url;
+
+use core::{Source, SourceId};
+use sources::ReplacedSource;
+use util::{CargoResult, Config, ToUrl, human};
+use util::config::Definition;
+
+pub struct SourceConfigMap<'cfg> {
+    cfgs: HashMap<String, SourceConfig>,
+    id2name: HashMap<SourceId, String>,
+    config: &'cfg Config,
+}
+
+/// A source defined in the configuration as `[source.<name>]`.
+struct SourceConfig {
+    // The source itself, unless it only names the source to replace it with
+    id: SourceId,
+    // The name of the source to use in its stead, and where that was set
+    replace_with: Option<(String, Definition)>,
+}
+
+impl<'cfg> SourceConfigMap<'cfg> {
+    pub fn new(config: &'cfg Config) -> CargoResult<SourceConfigMap<'cfg>> {
+        let mut base = SourceConfigMap {
+            cfgs: HashMap::new(),
+            id2name: HashMap::new(),
+            config: config,
+        };
+        // The central registry is always there to be replaced
+        base.add("crates-io", SourceConfig {
+            id: try!(SourceId::for_central(config)),
+            replace_with: None,
+        });
+        if let Some(table) = try!(config.get_table("source")) {
+            let mut names = table.val.keys().collect::<Vec<_>>();
+            names.sort();
+            for name in names {
+                try!(base.add_config(name));
+            }
+        }
+        Ok(base)
+    }
+
+    pub fn config(&self) -> &'cfg Config {
+        self.config
+    }
+
+    /// Loads the source for `id`, or the one it's replaced with in the
+    /// configuration.
+    pub fn load(&self, id: &SourceId) -> CargoResult<Box<Source + 'cfg>> {
+        debug!("loading: {}", id);
+        let mut name = match self.id2name.get(id) {
+            Some(name) => name,
+            None => return id.load(self.config),
+        };
+        let mut seen = Vec::new();
+        let new_id;
+        loop {
+            let cfg = match self.cfgs.get(name) {
+                Some(cfg) => cfg,
+                None => bail!("could not find a configured source with the \
+                               name `{}` when attempting to lookup `{}`",
+                              name, self.id2name[id]),
+            };
+            match cfg.replace_with {
+                Some((ref s, ref definition)) => {
+                    seen.push(name);
+                    if seen.contains(&s) {
+                        bail!("detected a cycle of `replace-with` sources, \
+                               the source `{}` is eventually replaced with \
+                               itself (defined in {})", s, definition)
+                    }
+                    name = s;
+                }
+                None if *id == cfg.id => return id.load(self.config),
+                None => {
+                    let precise = id.precise().map(|s| s.to_string());
+                    new_id = cfg.id.with_precise(precise);
+                    break
+                }
+            }
+        }
+        debug!("following pointer to {}", name);
+        let new_src = try!(new_id.load(self.config));
+        Ok(Box::new(ReplacedSource::new(id, &new_id, new_src)))
+    }
+
+    fn add(&mut self, name: &str, cfg: SourceConfig) {
+        self.id2name.insert(cfg.id.clone(), name.to_string());
+        self.cfgs.insert(name.to_string(), cfg);
+    }
+
+    fn add_config(&mut self, name: &str) -> CargoResult<()> {
+        let key = |field: &str| format!("source.{}.{}", name, field);
+
+        let mut srcs = Vec::new();
+        if let Some(val) = try!(self.config.get_string(&key("registry"))) {
+            let url = try!(url(&val.val, &key("registry"), &val.definition));
+            srcs.push(SourceId::for_registry(&url));
+        }
+        if let Some(val) = try!(self.config.get_string(&key("directory"))) {
+            let path = val.definition.root(self.config).join(&val.val);
+            srcs.push(try!(SourceId::for_directory(&path)));
+        }
+        let local = key("local-registry");
+        if let Some(val) = try!(self.config.get_string(&local)) {
+            let path = val.definition.root(self.config).join(&val.val);
+            srcs.push(try!(SourceId::for_local_registry(&path)));
+        }
+
+        let mut srcs = srcs.into_iter();
+        let id = match (srcs.next(), srcs.next()) {
+            (Some(id), None) => id,
+            (None, None) if name == "crates-io" => {
+                try!(SourceId::for_central(self.config))
+            }
+            (None, _) => {
+                bail!("no source URL specified for `source.{}`, need \
+                       either `registry`, `directory` or `local-registry` \
+                       defined", name)
+            }
+            (Some(..), Some(..)) => {
+                bail!("more than one source URL specified for \
+                       `source.{}`", name)
+            }
+        };
+
+        let replace_with = try!(self.config.get_string(&key("replace-with")));
+        self.add(name, SourceConfig {
+            id: id,
+            replace_with: replace_with.map(|v| (v.val, v.definition)),
+        });
+        return Ok(());
+
+        fn url(s: &str, key: &str, definition: &Definition)
+               -> CargoResult<Url> {
+            s.to_url().map_err(|s| {
+                human(format!("configuration key `{}` specified an invalid \
+                               URL (in {}): {}", key, definition, s))
+            })
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! A `Source` for a directory of unpacked packages, such as dependencies
+//! vendored into a repository so they can be built without network access.
+//!
+//! Each package is a subdirectory holding its `Cargo.toml` and a
+//! `.cargo-checksum.json` listing the sha256 checksum of each of its files:
+//!
+//! ```json
+//! {
+//!     "files": { "Cargo.toml": "<sha256>", "src/lib.rs": "<sha256>" },
+//!     "package": "<sha256 of the .crate file it was unpacked from>"
+//! }
+//! ```
+//!
+//! The files of a package are checked against the checksums before it's
+//! built, so an edited vendored file is reported rather than compiled, and so
+//! is a file which was removed or added since the package was vendored. The
+//! checksum of the package as a whole is recorded in the lock file, which
+//! catches the checksums themselves being replaced.
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json;
+
+use core::{Package, PackageId, Summary, SourceId, Source, Dependency, Registry};
+use sources::PathSource;
+use util::{CargoResult, ChainError, Config, Sha256, human, paths};
+
+pub const CHECKSUM_FILE: &'static str = ".cargo-checksum.json";
+
+const NOT_EDITABLE: &'static str = "\
+directory sources are not intended to be edited, if modifications are \
+required then it is recommended that [replace] is used with a forked copy of \
+the source";
+
+pub struct DirectorySource<'cfg> {
+    id: SourceId,
+    root: PathBuf,
+    packages: HashMap<PackageId, (Package, Checksum)>,
+    config: &'cfg Config,
+}
+
+#[derive(RustcDecodable, RustcEncodable)]
+pub struct Checksum {
+    pub package: Option<String>,
+    pub files: HashMap<String, String>,
+}
+
+impl<'cfg> DirectorySource<'cfg> {
+    pub fn new(path: &Path, id: &SourceId, config: &'cfg Config)
+               -> DirectorySource<'cfg> {
+        DirectorySource {
+            id: id.clone(),
+            root: path.to_path_buf(),
+            config: config,
+            packages: HashMap::new(),
+        }
+    }
+}
+
+impl<'cfg> Debug for DirectorySource<'cfg> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "DirectorySource {{ root: {:?} }}", self.root)
+    }
+}
+
+impl<'cfg> Registry for DirectorySource<'cfg> {
+    fn query(&mut self, dep: &Dependency) -> CargoResult<Vec<Summary>> {
+        let packages = self.packages.values().map(|p| &p.0);
+        let matches = packages.filter(|pkg| dep.matches(pkg.summary()));
+        let summaries = matches.map(|pkg| pkg.summary().clone());
+        Ok(summaries.collect())
+    }
+}
+
+impl<'cfg> Source for DirectorySource<'cfg> {
+    fn update(&mut self) -> CargoResult<()> {
+        self.packages.clear();
+        let entries = try!(fs::read_dir(&self.root).chain_error(|| {
+            human(format!("failed to read the directory of vendored \
+                           packages `{}`", self.root.display()))
+        }));
+
+        for entry in entries {
+            let path = try!(entry).path();
+            // Hidden directories and loose files, like a README explaining
+            // the directory, aren't packages
+            if path.file_name().and_then(|s| s.to_str())
+                   .map(|s| s.starts_with(".")) == Some(true) ||
+               !path.join("Cargo.toml").is_file() {
+                continue
+            }
+
+            let mut src = PathSource::new(&path, &self.id, self.config);
+            try!(src.update());
+            let mut pkg = try!(src.root_package());
+
+            let cksum_file = path.join(CHECKSUM_FILE);
+            let cksum = try!(paths::read(&cksum_file).chain_error(|| {
+                human(format!("failed to load the checksums of the vendored \
+                               package `{}`, which are expected in `{}`",
+                              pkg.package_id(), cksum_file.display()))
+            }));
+            let cksum: Checksum = try!(json::decode(&cksum).chain_error(|| {
+                human(format!("failed to decode `{}`", cksum_file.display()))
+            }));
+
+            let mut manifest = pkg.manifest().clone();
+            let mut summary = manifest.summary().clone();
+            if let Some(ref package) = cksum.package {
+                summary.set_checksum(package.clone());
+            }
+            manifest.set_summary(summary);
+            pkg = Package::new(manifest, pkg.manifest_path());
+            self.packages.insert(pkg.package_id().clone(), (pkg, cksum));
+        }
+
+        Ok(())
+    }
+
+    fn download(&mut self, id: &PackageId) -> CargoResult<Package> {
+        let entry = self.packages.get(id);
+        let &(ref pkg, ref cksum) = try!(entry.chain_error(|| {
+            human(format!("failed to find package with id: {}", id))
+        }));
+        try!(verify(pkg, cksum));
+        Ok(pkg.clone())
+    }
+
+    fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
+        Ok(pkg.package_id().version().to_string())
+    }
+}
+
+/// Checks the files of `pkg` against the checksums listed for them, and that
+/// no file was added to it.
+fn verify(pkg: &Package, cksum: &Checksum) -> CargoResult<()> {
+    // The directory is walked as it is rather than listed like a path
+    // source, as neither a git repository the vendored sources are committed
+    // to nor its ignore files have a say in what the package contains.
+    let mut files = Vec::new();
+    try!(walk(pkg.root(), &mut files));
+    let mut unlisted = Vec::new();
+    for file in files {
+        let relative = paths::without_prefix(&file, pkg.root()).unwrap();
+        let key = relative.iter().map(|s| s.to_string_lossy())
+                          .collect::<Vec<_>>().join("/");
+        if key != CHECKSUM_FILE && !cksum.files.contains_key(&key) {
+            unlisted.push(key);
+        }
+    }
+    if !unlisted.is_empty() {
+        unlisted.sort();
+        bail!("the vendored package `{}` has files which aren't listed in \
+               `{}`:\n  {}\n\n{}", pkg.package_id(),
+              pkg.root().join(CHECKSUM_FILE).display(),
+              unlisted.join("\n  "), NOT_EDITABLE)
+    }
+
+    let mut files = cksum.files.iter().collect::<Vec<_>>();
+    files.sort();
+    for (file, expected) in files {
+        let path = pkg.root().join(file);
+        if !path.is_file() {
+            bail!("the file `{}` listed in `{}` is missing\n\n{}",
+                  path.display(), pkg.root().join(CHECKSUM_FILE).display(),
+                  NOT_EDITABLE)
+        }
+        let actual = try!(file_checksum(&path).chain_error(|| {
+            human(format!("failed to calculate the checksum of `{}`",
+                          path.display()))
+        }));
+        if &actual != expected {
+            bail!("the listed checksum of `{}` has changed:\n\
+                   expected: {}\n\
+                   actual:   {}\n\n{}",
+                  path.display(), expected, actual, NOT_EDITABLE)
+        }
+    }
+    Ok(())
+}
+
+/// Collects the paths of all files below `dir`.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> CargoResult<()> {
+    for entry in try!(fs::read_dir(dir)) {
+        let entry = try!(entry);
+        if try!(entry.file_type()).is_dir() {
+            try!(walk(&entry.path(), files));
+        } else {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the hex encoded sha256 checksum of the contents of `path`.
+pub fn file_checksum(path: &Path) -> CargoResult<String> {
+    let mut h = Sha256::new();
+    let mut f = try!(File::open(path));
+    let mut buf = [0; 16 * 1024];
+    loop {
+        let n = try!(f.read(&mut buf));
+        if n == 0 {
+            break
+        }
+        h.update(&buf[..n]);
+    }
+    Ok(h.finish().to_hex())
+}
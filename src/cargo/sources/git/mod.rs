@@ -1,4 +1,6 @@
 pub use self::utils::{GitRemote, GitDatabase, GitCheckout, GitRevision, fetch};
 pub use self::source::{GitSource, canonicalize_url};
+pub use self::prefetch::prefetch;
 mod utils;
 mod source;
+mod prefetch;
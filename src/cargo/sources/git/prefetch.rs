@@ -0,0 +1,125 @@
+//! Fetching of the git repositories a resolution is going to need all at once,
+//! rather than one after the other as the resolver comes across them.
+//!
+//! The fetches run on up to `net.git-fetch-jobs` threads. Only the fetch into
+//! the git database happens there, the `GitSource` of each repository still
+//! checks out the revision it needs when it's updated, skipping the fetch as
+//! the database is recorded as fetched in the `Config`.
+
+use std::cmp;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::mpsc;
+
+use crossbeam;
+
+use core::{GitReference, SourceId};
+use util::{CargoResult, Config};
+
+use super::source::ident;
+use super::utils::GitRemote;
+
+struct Fetch {
+    remote: GitRemote,
+    db_path: PathBuf,
+}
+
+enum Message<'a> {
+    Warning(String),
+    Done(&'a Fetch, CargoResult<()>),
+}
+
+/// Fetches the git repositories of `ids` which aren't known to have what's
+/// needed of them yet, several at a time.
+///
+/// Nothing is done unless there are at least two repositories to fetch. A
+/// fetch which fails is left to the source of the repository, which tries
+/// again and reports the error in the context of the dependency.
+pub fn prefetch(config: &Config, ids: &[SourceId]) -> CargoResult<()> {
+    if !config.network_allowed() {
+        return Ok(())
+    }
+
+    let mut fetches = Vec::new();
+    let mut locks = Vec::new();
+    for id in ids.iter().filter(|id| id.is_git()) {
+        let ident = ident(id.url());
+        if fetches.iter().any(|f: &Fetch| f.db_path.ends_with(&ident)) {
+            continue
+        }
+        let db_lock = format!(".cargo-lock-{}", ident);
+        let db_lock = try!(config.git_db_path()
+                                 .open_rw(&db_lock, config,
+                                          "the git database"));
+        let db_path = db_lock.parent().join(&ident);
+        if config.git_fetched(&db_path) {
+            continue
+        }
+        let remote = GitRemote::new(id.url());
+        // Locked revisions only need fetching if they're missing
+        if let Some(rev) = id.precise() {
+            let reference = GitReference::Rev(rev.to_string());
+            if remote.rev_for(&db_path, &reference).is_ok() {
+                continue
+            }
+        }
+        fetches.push(Fetch { remote: remote, db_path: db_path });
+        locks.push(db_lock);
+    }
+    if fetches.len() < 2 {
+        return Ok(())
+    }
+
+    for fetch in fetches.iter() {
+        try!(config.shell().status("Updating",
+            format!("git repository `{}`", fetch.remote.url())));
+    }
+    let retries = try!(config.net_retry());
+    let threads = cmp::min(try!(config.git_fetch_jobs()), fetches.len());
+    let queue = Mutex::new(fetches.iter().rev().collect::<Vec<_>>());
+    let (tx, rx) = mpsc::channel();
+
+    crossbeam::scope(|scope| -> CargoResult<()> {
+        for _ in 0..threads {
+            let tx = tx.clone();
+            let queue = &queue;
+            scope.spawn(move || {
+                loop {
+                    let fetch = match queue.lock().unwrap().pop() {
+                        Some(fetch) => fetch,
+                        None => break,
+                    };
+                    let result = fetch.remote.checkout_with(
+                        &fetch.db_path, retries, &mut |msg: String| {
+                            let _ = tx.send(Message::Warning(msg));
+                            Ok(())
+                        });
+                    let _ = tx.send(Message::Done(fetch, result.map(|_| ())));
+                }
+            });
+        }
+        drop(tx);
+
+        let mut done = 0;
+        for message in rx {
+            match message {
+                Message::Warning(msg) => try!(config.shell().warn(msg)),
+                Message::Done(fetch, Ok(())) => {
+                    done += 1;
+                    config.set_git_fetched(fetch.db_path.clone());
+                    try!(config.shell().verbose(|shell| {
+                        shell.status("Fetched",
+                                     format!("git repository `{}` ({} of {})",
+                                             fetch.remote.url(), done,
+                                             fetches.len()))
+                    }));
+                }
+                Message::Done(fetch, Err(e)) => {
+                    debug!("failed to fetch `{}`, leaving it to its source: \
+                            {}", fetch.remote.url(), e);
+                }
+            }
+        }
+        Ok(())
+    })
+}
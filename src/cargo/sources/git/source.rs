@@ -58,7 +58,7 @@ impl<'cfg> GitSource<'cfg> {
     }
 }
 
-fn ident(url: &Url) -> String {
+pub fn ident(url: &Url) -> String {
     let mut hasher = SipHasher::new_with_keys(0,0);
 
     let url = canonicalize_url(url);
@@ -154,10 +154,12 @@ impl<'cfg> Source for GitSource<'cfg> {
         // Resolve our reference to an actual revision, and check if the
         // databaes already has that revision. If it does, we just load a
         // database pinned at that revision, and if we don't we issue an update
-        // to try to find the revision.
+        // to try to find the revision. A database fetched earlier on by this
+        // process, such as by `prefetch`, is as recent as it gets though.
         let actual_rev = self.remote.rev_for(&db_path, &self.reference);
         let should_update = actual_rev.is_err() ||
-                            self.source_id.precise().is_none();
+                            (self.source_id.precise().is_none() &&
+                             !self.config.git_fetched(&db_path));
 
         let (repo, actual_rev) = if should_update {
             try!(self.config.shell().status("Updating",
@@ -166,6 +168,7 @@ impl<'cfg> Source for GitSource<'cfg> {
             trace!("updating git source `{:?}`", self.remote);
 
             let repo = try!(self.remote.checkout(&db_path, &self.config));
+            self.config.set_git_fetched(db_path.clone());
             let rev = try!(repo.rev_for(&self.reference));
             (repo, rev)
         } else {
@@ -110,15 +110,29 @@ impl GitRemote {
     }
 
     pub fn checkout(&self, into: &Path, cargo_config: &Config) -> CargoResult<GitDatabase> {
+        try!(check_network(cargo_config));
+        let retries = try!(cargo_config.net_retry());
+        self.checkout_with(into, retries,
+                           &mut |msg: String| cargo_config.shell().warn(msg))
+    }
+
+    /// Like `checkout`, but without the `Config` so that it can run on other
+    /// threads: fetches are tried again up to `retries` times, `warn` being
+    /// handed the warning about each retry.
+    pub fn checkout_with(&self,
+                         into: &Path,
+                         retries: i64,
+                         warn: &mut FnMut(String) -> CargoResult<()>)
+                         -> CargoResult<GitDatabase> {
         let repo = match git2::Repository::open(into) {
             Ok(repo) => {
-                try!(self.fetch_into(&repo, &cargo_config).chain_error(|| {
+                try!(self.fetch_into(&repo, retries, warn).chain_error(|| {
                     human(format!("failed to fetch into {}", into.display()))
                 }));
                 repo
             }
             Err(..) => {
-                try!(self.clone_into(into, &cargo_config).chain_error(|| {
+                try!(self.clone_into(into, retries, warn).chain_error(|| {
                     human(format!("failed to clone into: {}", into.display()))
                 }))
             }
@@ -140,21 +154,26 @@ impl GitRemote {
         })
     }
 
-    fn fetch_into(&self, dst: &git2::Repository, cargo_config: &Config) -> CargoResult<()> {
+    fn fetch_into(&self, dst: &git2::Repository, retries: i64,
+                  warn: &mut FnMut(String) -> CargoResult<()>)
+                  -> CargoResult<()> {
         // Create a local anonymous remote in the repository to fetch the url
         let url = self.url.to_string();
         let refspec = "refs/heads/*:refs/heads/*";
-        fetch(dst, &url, refspec, &cargo_config)
+        fetch_with(dst, &url, refspec, retries, warn)
     }
 
-    fn clone_into(&self, dst: &Path, cargo_config: &Config) -> CargoResult<git2::Repository> {
+    fn clone_into(&self, dst: &Path, retries: i64,
+                  warn: &mut FnMut(String) -> CargoResult<()>)
+                  -> CargoResult<git2::Repository> {
         let url = self.url.to_string();
         if fs::metadata(&dst).is_ok() {
             try!(fs::remove_dir_all(dst));
         }
         try!(fs::create_dir_all(dst));
         let repo = try!(git2::Repository::init_bare(dst));
-        try!(fetch(&repo, &url, "refs/heads/*:refs/heads/*", &cargo_config));
+        try!(fetch_with(&repo, &url, "refs/heads/*:refs/heads/*", retries,
+                        warn));
         Ok(repo)
     }
 }
@@ -215,6 +234,17 @@ impl GitDatabase {
         Ok(GitRevision(id))
     }
 
+    /// Fetches the branch which the `HEAD` of the remote points to, that is
+    /// its default branch, and returns the revision it's at.
+    pub fn default_rev(&self, cargo_config: &Config)
+                       -> CargoResult<GitRevision> {
+        let url = self.remote.url().to_string();
+        try!(fetch(&self.repo, &url, "+HEAD:refs/remotes/origin/HEAD",
+                   cargo_config));
+        let id = try!(self.repo.refname_to_id("refs/remotes/origin/HEAD"));
+        Ok(GitRevision(id))
+    }
+
     pub fn has_ref(&self, reference: &str) -> CargoResult<()> {
         try!(self.repo.revparse_single(reference));
         Ok(())
@@ -564,11 +594,25 @@ pub fn fetch(repo: &git2::Repository,
              url: &str,
              refspec: &str,
              config: &Config) -> CargoResult<()> {
+    try!(check_network(config));
+    let retries = try!(config.net_retry());
+    fetch_with(repo, url, refspec, retries,
+               &mut |msg: String| config.shell().warn(msg))
+}
+
+fn check_network(config: &Config) -> CargoResult<()> {
     if !config.network_allowed() {
         bail!("attempting to update a git repository, but --frozen \
                was specified")
     }
+    Ok(())
+}
 
+fn fetch_with(repo: &git2::Repository,
+              url: &str,
+              refspec: &str,
+              retries: i64,
+              warn: &mut FnMut(String) -> CargoResult<()>) -> CargoResult<()> {
     with_authentication(url, &try!(repo.config()), |f| {
         let mut cb = git2::RemoteCallbacks::new();
         cb.credentials(f);
@@ -579,7 +623,7 @@ pub fn fetch(repo: &git2::Repository,
         opts.remote_callbacks(cb)
             .download_tags(git2::AutotagOption::All);
 
-        try!(network::with_retry(config, ||{
+        try!(network::retry(retries, &mut *warn, ||{
             remote.fetch(&[refspec], Some(&mut opts), None)
         }));
         Ok(())
@@ -99,11 +99,15 @@ impl<'cfg> PathSource<'cfg> {
         let include = try!(pkg.manifest().include().iter()
                               .map(|p| parse(p)).collect::<Result<Vec<_>, _>>());
 
+        // Patterns are matched against the lossy form of non-UTF-8 paths, as
+        // `Pattern::matches_path` would never match such a path at all and
+        // silently drop it from `include` and keep it despite `exclude`.
         let mut filter = |p: &Path| {
             let relative_path = util::without_prefix(p, &root).unwrap();
-            include.iter().any(|p| p.matches_path(&relative_path)) || {
+            let relative_path = relative_path.to_string_lossy();
+            include.iter().any(|p| p.matches(&relative_path)) || {
                 include.is_empty() &&
-                 !exclude.iter().any(|p| p.matches_path(&relative_path))
+                 !exclude.iter().any(|p| p.matches(&relative_path))
             }
         };
 
@@ -157,9 +157,27 @@
 //!         registry1-<hash>/<pkg>-<version>/...
 //!         ...
 //! ```
+//!
+//! # Local Registries
+//!
+//! A registry can also be synced to a directory, for machines which can't
+//! reach it. Such a local registry holds a plain copy of the index along with
+//! the tarballs of the crates, which take the place of the checkout of the
+//! index and of the cache above:
+//!
+//! ```notrust
+//! index/
+//!     3/u/url
+//!     ...
+//! url-1.2.0.crate
+//! ...
+//! ```
+//!
+//! Nothing's ever fetched for a local registry, but tarballs are still
+//! checked against the checksums listed in its index before being unpacked.
 
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::SeekFrom;
 use std::io::prelude::*;
 use std::path::{PathBuf, Path};
@@ -170,6 +188,7 @@ use git2;
 use rustc_serialize::hex::ToHex;
 use rustc_serialize::json;
 use tar::Archive;
+use tempdir::TempDir;
 use url::Url;
 
 use core::{Source, SourceId, PackageId, Package, Summary, Registry};
@@ -181,7 +200,7 @@ use util::network;
 use ops;
 
 const DEFAULT: &'static str = "https://github.com/rust-lang/crates.io-index";
-const INDEX_LOCK: &'static str = ".cargo-index-lock";
+pub const INDEX_LOCK: &'static str = ".cargo-index-lock";
 
 pub struct RegistrySource<'cfg> {
     source_id: SourceId,
@@ -193,6 +212,15 @@ pub struct RegistrySource<'cfg> {
     hashes: HashMap<(String, String), String>, // (name, vers) => cksum
     cache: HashMap<String, Vec<(Summary, bool)>>,
     updated: bool,
+    /// Commit of the imported snapshot of the index which is used instead of
+    /// the index itself, as configured with `registry.snapshot`
+    snapshot: Option<String>,
+    /// The last commit of the index made before the time `cargo update
+    /// --as-of` asked for, which the index is read at instead of its checkout
+    as_of: Option<git2::Oid>,
+    /// Whether this is a local registry, whose index and tarballs are read
+    /// from `checkout_path` and `cache_path` as they are
+    local: bool,
 }
 
 #[derive(RustcDecodable)]
@@ -231,11 +259,17 @@ struct RegistryDependency {
 impl<'cfg> RegistrySource<'cfg> {
     pub fn new(source_id: &SourceId,
                config: &'cfg Config) -> RegistrySource<'cfg> {
-        let hash = hex::short_hash(source_id);
-        let ident = source_id.url().host_str().unwrap_or("").to_string();
-        let part = format!("{}-{}", ident, hash);
+        let part = RegistrySource::dir_name(source_id);
+        // An invalid value is reported when the snapshot is recorded in the
+        // lock file
+        let snapshot = config.get_string("registry.snapshot").ok()
+                             .and_then(|s| s).map(|s| s.val);
+        let checkout_path = {
+            let snapshot = snapshot.as_ref().map(|s| &s[..]);
+            RegistrySource::index_path(config, source_id, snapshot)
+        };
         RegistrySource {
-            checkout_path: config.registry_index_path().join(&part),
+            checkout_path: checkout_path,
             cache_path: config.registry_cache_path().join(&part),
             src_path: config.registry_source_path().join(&part),
             config: config,
@@ -244,6 +278,51 @@ impl<'cfg> RegistrySource<'cfg> {
             hashes: HashMap::new(),
             cache: HashMap::new(),
             updated: false,
+            snapshot: snapshot,
+            as_of: None,
+            local: false,
+        }
+    }
+
+    /// Creates the source of the local registry in the directory `root`.
+    pub fn local(source_id: &SourceId,
+                 root: &Path,
+                 config: &'cfg Config) -> RegistrySource<'cfg> {
+        let part = RegistrySource::dir_name(source_id);
+        RegistrySource {
+            checkout_path: Filesystem::new(root.join("index")),
+            cache_path: Filesystem::new(root.to_path_buf()),
+            src_path: config.registry_source_path().join(&part),
+            config: config,
+            source_id: source_id.clone(),
+            handle: None,
+            hashes: HashMap::new(),
+            cache: HashMap::new(),
+            updated: false,
+            snapshot: None,
+            as_of: None,
+            local: true,
+        }
+    }
+
+    fn dir_name(source_id: &SourceId) -> String {
+        let hash = hex::short_hash(source_id);
+        let ident = source_id.url().host_str().unwrap_or("").to_string();
+        format!("{}-{}", ident, hash)
+    }
+
+    /// Returns where the index of a registry is checked out, or where the
+    /// snapshot of it at the given commit is imported.
+    pub fn index_path(config: &Config,
+                      source_id: &SourceId,
+                      snapshot: Option<&str>) -> Filesystem {
+        let part = RegistrySource::dir_name(source_id);
+        match snapshot {
+            Some(commit) => {
+                config.registry_index_path()
+                      .join(format!("{}-snapshot-{}", part, commit))
+            }
+            None => config.registry_index_path().join(&part),
         }
     }
 
@@ -335,6 +414,30 @@ impl<'cfg> RegistrySource<'cfg> {
         Ok(dst)
     }
 
+    /// Opens the tarball of `pkg` in a local registry, checking it against
+    /// the checksum listed in the index.
+    fn local_package(&mut self, pkg: &PackageId) -> CargoResult<FileLock> {
+        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
+        let mut krate = try!(self.cache_path.open_ro(&filename, self.config,
+                                                     &filename));
+        let expected_hash = try!(self.hash(pkg));
+        let mut state = Sha256::new();
+        let mut buf = [0; 16 * 1024];
+        loop {
+            let n = try!(krate.read(&mut buf));
+            if n == 0 {
+                break
+            }
+            state.update(&buf[..n]);
+        }
+        if state.finish().to_hex() != expected_hash {
+            bail!("failed to verify the checksum of `{}` in the local \
+                   registry at `{}`", pkg, krate.path().display())
+        }
+        try!(krate.seek(SeekFrom::Start(0)));
+        Ok(krate)
+    }
+
     /// Return the hash listed for a specified PackageId.
     fn hash(&mut self, pkg: &PackageId) -> CargoResult<String> {
         let key = (pkg.name().to_string(), pkg.version().to_string());
@@ -368,10 +471,31 @@ impl<'cfg> RegistrySource<'cfg> {
             return Ok(dst)
         }
 
-        let gz = try!(GzDecoder::new(tarball.file()));
-        let mut tar = Archive::new(gz);
-        try!(tar.unpack(dst.parent().unwrap()));
-        try!(File::create(&ok));
+        // The sources are first extracted into `build.tmpdir` if it's set,
+        // and either way a partially extracted package is removed on failure
+        // rather than being left for the next build to stumble upon.
+        let result = (|| -> CargoResult<()> {
+            let gz = try!(GzDecoder::new(tarball.file()));
+            let mut tar = Archive::new(gz);
+            match try!(self.config.tmpdir()) {
+                Some(tmpdir) => {
+                    let td = try!(TempDir::new_in(&tmpdir, "cargo-unpack"));
+                    try!(tar.unpack(td.path()));
+                    try!(fs::remove_dir_all(&dst));
+                    let name = dst.file_name().unwrap();
+                    try!(paths::move_dir(&td.path().join(name), &dst));
+                }
+                None => try!(tar.unpack(dst.parent().unwrap())),
+            }
+            try!(File::create(&ok));
+            Ok(())
+        })();
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&dst);
+            return e.chain_error(|| {
+                human(format!("failed to unpack package `{}`", pkg))
+            })
+        }
         Ok(dst)
     }
 
@@ -380,30 +504,43 @@ impl<'cfg> RegistrySource<'cfg> {
         if self.cache.contains_key(name) {
             return Ok(self.cache.get(name).unwrap());
         }
-        let lock = self.checkout_path.open_ro(Path::new(INDEX_LOCK),
-                                              self.config,
-                                              "the registry index");
-        let file = lock.and_then(|lock| {
-            let path = lock.path().parent().unwrap();
-            let fs_name = name.chars().flat_map(|c| {
-                c.to_lowercase()
-            }).collect::<String>();
-
-            // see module comment for why this is structured the way it is
-            let path = match fs_name.len() {
-                1 => path.join("1").join(&fs_name),
-                2 => path.join("2").join(&fs_name),
-                3 => path.join("3").join(&fs_name[..1]).join(&fs_name),
-                _ => path.join(&fs_name[0..2])
-                         .join(&fs_name[2..4])
-                         .join(&fs_name),
-            };
-            File::open(&path).map_err(human)
-        });
-        let summaries = match file {
-            Ok(mut f) => {
-                let mut contents = String::new();
-                try!(f.read_to_string(&mut contents));
+        try!(self.check_snapshot());
+        let fs_name = name.chars().flat_map(|c| {
+            c.to_lowercase()
+        }).collect::<String>();
+
+        // see module comment for why this is structured the way it is
+        let path = match fs_name.len() {
+            1 => Path::new("1").join(&fs_name),
+            2 => Path::new("2").join(&fs_name),
+            3 => Path::new("3").join(&fs_name[..1]).join(&fs_name),
+            _ => Path::new(&fs_name[0..2]).join(&fs_name[2..4]).join(&fs_name),
+        };
+        let contents = if self.local {
+            // A local registry isn't shared with other Cargo processes, and
+            // may well be read-only, so its index isn't locked
+            let path = self.checkout_path.clone().into_path_unlocked()
+                           .join(&path);
+            paths::read(&path).ok()
+        } else {
+            match self.as_of_timestamp() {
+                Some(timestamp) => try!(self.read_as_of(&path, timestamp)),
+                None => {
+                    let lock = self.checkout_path.open_ro(Path::new(INDEX_LOCK),
+                                                          self.config,
+                                                          "the registry index");
+                    lock.and_then(|lock| {
+                        let path = lock.path().parent().unwrap().join(&path);
+                        let mut contents = String::new();
+                        try!(try!(File::open(&path))
+                                 .read_to_string(&mut contents));
+                        Ok(contents)
+                    }).ok()
+                }
+            }
+        };
+        let summaries = match contents {
+            Some(contents) => {
                 let ret: CargoResult<Vec<(Summary, bool)>>;
                 ret = contents.lines().filter(|l| l.trim().len() > 0)
                               .map(|l| self.parse_registry_package(l))
@@ -413,7 +550,7 @@ impl<'cfg> RegistrySource<'cfg> {
                                       for: {}", name))
                 }))
             }
-            Err(..) => Vec::new(),
+            None => Vec::new(),
         };
         let summaries = summaries.into_iter().filter(|summary| {
             summary.0.package_id().name() == name
@@ -475,11 +612,97 @@ impl<'cfg> RegistrySource<'cfg> {
               .into_dependency())
     }
 
+    /// Returns the time, in seconds since the epoch, before which the versions
+    /// of `cargo update --as-of` were published.
+    fn as_of_timestamp(&self) -> Option<i64> {
+        self.source_id.precise().and_then(|p| {
+            if p.starts_with("as-of:") {p[6..].parse().ok()} else {None}
+        })
+    }
+
+    /// Reads the file at `path` in the index as of the last commit made before
+    /// `timestamp`, returning `None` if there's no such file.
+    fn read_as_of(&mut self, path: &Path, timestamp: i64)
+                  -> CargoResult<Option<String>> {
+        let lock = try!(self.checkout_path.open_ro(Path::new(INDEX_LOCK),
+                                                   self.config,
+                                                   "the registry index"));
+        let repo = try!(git2::Repository::open(lock.path().parent().unwrap()));
+        let oid = match self.as_of {
+            Some(oid) => oid,
+            // The history of the index is linear, so the first parents lead
+            // back to its initial commit
+            None => {
+                let head = try!(try!(repo.head()).target().chain_error(|| {
+                    internal("the registry index has no HEAD")
+                }));
+                let mut commit = try!(repo.find_commit(head));
+                while commit.time().seconds() >= timestamp {
+                    if commit.parent_count() == 0 {
+                        bail!("the registry index `{}` has no commits from \
+                               before the requested date",
+                              self.source_id.url())
+                    }
+                    commit = try!(commit.parent(0));
+                }
+                commit.id()
+            }
+        };
+        self.as_of = Some(oid);
+
+        let mut tree = try!(try!(repo.find_commit(oid)).tree());
+        let mut components = path.iter().peekable();
+        while let Some(component) = components.next() {
+            let component = component.to_str().unwrap();
+            let id = match tree.get_name(component) {
+                Some(entry) => entry.id(),
+                None => return Ok(None),
+            };
+            if components.peek().is_none() {
+                let blob = try!(repo.find_blob(id));
+                return Ok(Some(String::from_utf8_lossy(blob.content())
+                                   .into_owned()))
+            }
+            tree = try!(repo.find_tree(id));
+        }
+        Ok(None)
+    }
+
+    fn check_snapshot(&self) -> CargoResult<()> {
+        let commit = match self.snapshot {
+            Some(ref commit) => commit,
+            None => return Ok(()),
+        };
+        let lock = self.checkout_path.join(INDEX_LOCK).into_path_unlocked();
+        if !lock.exists() {
+            bail!("the snapshot `{}` of the registry index configured with \
+                   `registry.snapshot` hasn't been imported, see `cargo help \
+                   index-snapshot`", commit)
+        }
+        Ok(())
+    }
+
     /// Actually perform network operations to update the registry
     fn do_update(&mut self) -> CargoResult<()> {
         if self.updated {
             return Ok(())
         }
+        if self.local {
+            let path = self.checkout_path.clone().into_path_unlocked();
+            if !path.is_dir() {
+                bail!("the local registry `{}` has no index, which is \
+                       expected in `{}`", self.source_id.url(),
+                      path.display())
+            }
+            self.updated = true;
+            return Ok(())
+        }
+        // Snapshots never change once imported
+        if self.snapshot.is_some() {
+            try!(self.check_snapshot());
+            self.updated = true;
+            return Ok(())
+        }
         try!(self.checkout_path.create_dir());
         let lock = try!(self.checkout_path.open_rw(Path::new(INDEX_LOCK),
                                                    self.config,
@@ -569,6 +792,16 @@ impl<'cfg> Source for RegistrySource<'cfg> {
     }
 
     fn download(&mut self, package: &PackageId) -> CargoResult<Package> {
+        if self.local {
+            let krate = try!(self.local_package(package));
+            let path = try!(self.unpack_package(package, &krate)
+                                .chain_error(|| {
+                internal(format!("failed to unpack package `{}`", package))
+            }));
+            let mut src = PathSource::new(&path, &self.source_id, self.config);
+            try!(src.update());
+            return src.download(package)
+        }
         let config = try!(self.config());
         let url = try!(config.dl.to_url().map_err(internal));
         let mut url = url.clone();
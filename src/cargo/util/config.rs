@@ -1,6 +1,7 @@
 use std::cell::{RefCell, RefMut, Cell};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::hash_map::{HashMap};
+use std::collections::HashSet;
 use std::env;
 use std::fmt;
 use std::fs::{self, File};
@@ -8,14 +9,16 @@ use std::io::SeekFrom;
 use std::io::prelude::*;
 use std::mem;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::str::FromStr;
 
 use rustc_serialize::{Encodable,Encoder};
+use tempdir::TempDir;
 use toml;
 use core::shell::{Verbosity, ColorConfig};
-use core::{MultiShell, Workspace};
+use core::{MultiShell, SourceFactory};
 use util::{CargoResult, CargoError, ChainError, Rustc, internal, human};
-use util::{Filesystem, LazyCell};
+use util::{Filesystem, LazyCell, paths};
 
 use util::toml as cargo_toml;
 
@@ -28,10 +31,14 @@ pub struct Config {
     values: LazyCell<HashMap<String, ConfigValue>>,
     cwd: PathBuf,
     rustdoc: LazyCell<PathBuf>,
-    target_dir: RefCell<Option<Filesystem>>,
+    target_dir: Option<Filesystem>,
     extra_verbose: Cell<bool>,
     frozen: Cell<bool>,
     locked: Cell<bool>,
+    /// The git databases fetched by this process, which are up to date
+    git_fetched: RefCell<HashSet<PathBuf>>,
+    /// The kinds of sources registered on top of those built into Cargo
+    source_kinds: RefCell<HashMap<String, Rc<SourceFactory>>>,
 }
 
 impl Config {
@@ -45,10 +52,12 @@ impl Config {
             cwd: cwd,
             values: LazyCell::new(),
             rustdoc: LazyCell::new(),
-            target_dir: RefCell::new(None),
+            target_dir: None,
             extra_verbose: Cell::new(false),
             frozen: Cell::new(false),
             locked: Cell::new(false),
+            git_fetched: RefCell::new(HashSet::new()),
+            source_kinds: RefCell::new(HashMap::new()),
         };
 
         try!(cfg.scrape_target_dir_config());
@@ -70,6 +79,37 @@ impl Config {
 
     pub fn home(&self) -> &Filesystem { &self.home_path }
 
+    /// Registers `factory` as creating the sources whose URLs start with
+    /// `<kind>+`, replacing any factory already registered for `kind`.
+    pub fn register_source_kind<F>(&self, kind: &str, factory: F)
+                                   -> CargoResult<()>
+        where F: SourceFactory + 'static
+    {
+        match kind {
+            "git" | "path" | "registry" | "directory" => {
+                bail!("`{}` sources are built into Cargo and can't be \
+                       registered again", kind)
+            }
+            _ => {}
+        }
+        if kind.is_empty() || !kind.chars().all(|c| {
+            match c {
+                'a'...'z' | 'A'...'Z' | '0'...'9' | '-' => true,
+                _ => false,
+            }
+        }) {
+            bail!("invalid name for a kind of source: `{}`", kind)
+        }
+        self.source_kinds.borrow_mut().insert(kind.to_string(),
+                                              Rc::new(factory));
+        Ok(())
+    }
+
+    /// Returns the factory registered for sources of kind `kind`.
+    pub fn source_kind(&self, kind: &str) -> Option<Rc<SourceFactory>> {
+        self.source_kinds.borrow().get(kind).cloned()
+    }
+
     pub fn git_db_path(&self) -> Filesystem {
         self.home_path.join("git").join("db")
     }
@@ -108,14 +148,36 @@ impl Config {
 
     pub fn cwd(&self) -> &Path { &self.cwd }
 
-    pub fn target_dir(&self, ws: &Workspace) -> Filesystem {
-        self.target_dir.borrow().clone().unwrap_or_else(|| {
-            Filesystem::new(ws.root().join("target"))
-        })
+    /// Returns the target directory configured through `CARGO_TARGET_DIR` or
+    /// `build.target-dir`, if any.
+    pub fn target_dir(&self) -> Option<Filesystem> {
+        self.target_dir.clone()
+    }
+
+    /// Returns the directory configured through `build.tmpdir` for scratch
+    /// space, creating it if needed.
+    pub fn tmpdir(&self) -> CargoResult<Option<PathBuf>> {
+        let dir = match try!(self.get_path("build.tmpdir")) {
+            Some(dir) => self.cwd.join(dir.val),
+            None => return Ok(None),
+        };
+        try!(fs::create_dir_all(&dir).chain_error(|| {
+            human(format!("failed to create build.tmpdir `{}`", dir.display()))
+        }));
+        Ok(Some(dir))
     }
 
-    pub fn set_target_dir(&self, path: Filesystem) {
-        *self.target_dir.borrow_mut() = Some(path);
+    /// Creates a scratch directory, removed along with its contents once it's
+    /// dropped, within `build.tmpdir` or else within the temporary directory
+    /// of the system.
+    pub fn temp_dir(&self, prefix: &str) -> CargoResult<TempDir> {
+        let td = match try!(self.tmpdir()) {
+            Some(dir) => TempDir::new_in(&dir, prefix),
+            None => TempDir::new(prefix),
+        };
+        td.chain_error(|| {
+            human("failed to create a temporary directory")
+        })
     }
 
     fn get(&self, key: &str) -> CargoResult<Option<ConfigValue>> {
@@ -278,6 +340,29 @@ impl Config {
         }
     }
 
+    /// How many git repositories may be fetched at once, `net.git-fetch-jobs`
+    pub fn git_fetch_jobs(&self) -> CargoResult<usize> {
+        match try!(self.get_i64("net.git-fetch-jobs")) {
+            Some(v) => {
+                if v.val <= 0 {
+                    bail!("net.git-fetch-jobs must be at least 1, but found {} \
+                           in {}", v.val, v.definition)
+                }
+                Ok(v.val as usize)
+            }
+            None => Ok(4),
+        }
+    }
+
+    /// Whether the git database at `path` was already fetched by this process
+    pub fn git_fetched(&self, path: &Path) -> bool {
+        self.git_fetched.borrow().contains(path)
+    }
+
+    pub fn set_git_fetched(&self, path: PathBuf) {
+        self.git_fetched.borrow_mut().insert(path);
+    }
+
     pub fn expected<T>(&self, ty: &str, key: &str, val: CV) -> CargoResult<T> {
         val.expected(ty).map_err(|e| {
             human(format!("invalid configuration for key `{}`\n{}", key, e))
@@ -371,10 +456,10 @@ impl Config {
 
     fn scrape_target_dir_config(&mut self) -> CargoResult<()> {
         if let Some(dir) = env::var_os("CARGO_TARGET_DIR") {
-            *self.target_dir.borrow_mut() = Some(Filesystem::new(self.cwd.join(dir)));
+            self.target_dir = Some(Filesystem::new(self.cwd.join(dir)));
         } else if let Some(val) = try!(self.get_path("build.target-dir")) {
             let val = self.cwd.join(val.val);
-            *self.target_dir.borrow_mut() = Some(Filesystem::new(val));
+            self.target_dir = Some(Filesystem::new(val));
         }
         Ok(())
     }
@@ -620,6 +705,13 @@ impl fmt::Display for Definition {
 }
 
 fn homedir(cwd: &Path) -> Option<PathBuf> {
+    // Symlinks are resolved so everything below the home directory, like the
+    // sources of registry packages, has the same path however the home
+    // directory is reached, keeping fingerprints of those packages stable.
+    find_homedir(cwd).map(|home| paths::canonicalize(&home))
+}
+
+fn find_homedir(cwd: &Path) -> Option<PathBuf> {
     let cargo_home = env::var_os("CARGO_HOME").map(|home| {
         cwd.join(home)
     });
@@ -0,0 +1,108 @@
+//! Reports written when the compiler or Cargo itself crashes, holding what's
+//! needed to make a bug report about it actionable.
+//!
+//! A report is a plain text file made of sections, each with a heading. Those
+//! about the compiler are written to the `crash-reports` directory of the
+//! output directory, and those about Cargo to the system's temp directory as
+//! there may not be a workspace at all.
+
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct CrashReport {
+    title: String,
+    sections: Vec<(String, String)>,
+}
+
+impl CrashReport {
+    /// Starts a report with the sections every report has: the versions of
+    /// Cargo, the command line and the environment variables Cargo and the
+    /// compiler read.
+    pub fn new(title: &str) -> CrashReport {
+        let mut report = CrashReport {
+            title: title.to_string(),
+            sections: Vec::new(),
+        };
+        report.add("Cargo version", &::version());
+        let args = env::args().collect::<Vec<_>>();
+        report.add("Command line", &args.join(" "));
+        if let Ok(cwd) = env::current_dir() {
+            report.add("Working directory", &cwd.display().to_string());
+        }
+        let mut vars = env::vars().filter(|&(ref k, _)| {
+            (k.starts_with("CARGO") || k.starts_with("RUST")) &&
+            !k.contains("TOKEN")
+        }).map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>();
+        vars.sort();
+        report.add("Environment", &vars.join("\n"));
+        report
+    }
+
+    pub fn add(&mut self, heading: &str, contents: &str) {
+        self.sections.push((heading.to_string(), contents.to_string()));
+    }
+
+    /// Writes the report to a new file in `dir` named after `name`, returning
+    /// its path.
+    pub fn write(&self, dir: &Path, name: &str) -> io::Result<PathBuf> {
+        let mut contents = format!("{}\n", self.title);
+        for &(ref heading, ref text) in self.sections.iter() {
+            let _ = write!(contents, "\n## {}\n\n{}\n", heading,
+                           text.trim_right());
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)
+                                   .map(|d| (d.as_secs(), d.subsec_nanos()))
+                                   .unwrap_or((0, 0));
+        let path = dir.join(format!("{}-{}{:09}.txt", name, now.0, now.1));
+        try!(fs::create_dir_all(dir));
+        let mut file = try!(File::create(&path));
+        try!(file.write_all(contents.as_bytes()));
+        Ok(path)
+    }
+}
+
+/// Makes a panic of Cargo write a crash report, on top of printing the panic
+/// message like it otherwise would.
+pub fn install_panic_hook() {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default(info);
+
+        let mut report = CrashReport::new("Cargo panicked");
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(s) => s.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(s) => s.clone(),
+                None => "Box<Any>".to_string(),
+            },
+        };
+        let location = info.location().map(|l| {
+            format!("{}:{}", l.file(), l.line())
+        }).unwrap_or("unknown".to_string());
+        report.add("Panic", &format!("{}\nat {}", message, location));
+        report.add("Backtrace", if env::var_os("RUST_BACKTRACE").is_some() {
+            "printed on stderr along with the panic message"
+        } else {
+            "not captured, run the command again with RUST_BACKTRACE=1 and \
+             attach what it prints"
+        });
+
+        let mut stderr = io::stderr();
+        match report.write(&env::temp_dir(), "cargo-panic") {
+            Ok(path) => {
+                let _ = writeln!(stderr, "note: a crash report was written to \
+                                          `{}`, please attach it to a bug \
+                                          report", path.display());
+            }
+            Err(e) => {
+                let _ = writeln!(stderr, "note: failed to write a crash \
+                                          report: {}", e);
+            }
+        }
+    }));
+}
@@ -26,6 +26,14 @@ pub struct DependencyQueue<K: Eq + Hash, V> {
     /// lifecycle of the DependencyQueue.
     reverse_dep_map: HashMap<K, HashSet<K>>,
 
+    /// A mapping of a package to the packages depending on it which only need
+    /// its metadata, rather than the package being completely built.
+    metadata_only: HashMap<K, HashSet<K>>,
+
+    /// Packages whose metadata is available, for which `metadata_only`
+    /// dependents have been released already.
+    metadata_ready: HashSet<K>,
+
     /// A set of dirty packages.
     ///
     /// Packages may become dirty over time if their dependencies are rebuilt.
@@ -34,6 +42,10 @@ pub struct DependencyQueue<K: Eq + Hash, V> {
     /// The packages which are currently being built, waiting for a call to
     /// `finish`.
     pending: HashSet<K>,
+
+    /// The packages which have been built, and whether they were rebuilt, for
+    /// the packages added after them.
+    finished: HashMap<K, Freshness>,
 }
 
 /// Indication of the freshness of a package.
@@ -58,15 +70,18 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
         DependencyQueue {
             dep_map: HashMap::new(),
             reverse_dep_map: HashMap::new(),
+            metadata_only: HashMap::new(),
+            metadata_ready: HashSet::new(),
             dirty: HashSet::new(),
             pending: HashSet::new(),
+            finished: HashMap::new(),
         }
     }
 
     /// Adds a new package to this dependency queue.
     ///
     /// It is assumed that any dependencies of this package will eventually also
-    /// be added to the dependency queue.
+    /// be added to the dependency queue, if they haven't been built already.
     pub fn queue(&mut self,
                  fresh: Freshness,
                  key: K,
@@ -83,6 +98,12 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
 
         let mut my_dependencies = HashSet::new();
         for dep in dependencies {
+            if let Some(&dep_fresh) = self.finished.get(dep) {
+                if dep_fresh == Dirty {
+                    self.dirty.insert(key.clone());
+                }
+                continue
+            }
             assert!(my_dependencies.insert(dep.clone()));
             let rev = self.reverse_dep_map.entry(dep.clone())
                                           .or_insert(HashSet::new());
@@ -91,6 +112,22 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
         &mut slot.insert((my_dependencies, value)).1
     }
 
+    /// Records that `key` only needs the metadata of its dependency `dep`,
+    /// allowing it to be built as soon as `metadata_ready` is called for `dep`.
+    pub fn needs_only_metadata(&mut self, key: &K, dep: &K) {
+        self.metadata_only.entry(dep.clone()).or_insert(HashSet::new())
+                          .insert(key.clone());
+        // The metadata may have been emitted before `key` was added.
+        if !self.metadata_ready.contains(dep) {
+            return
+        }
+        if let Some(&mut (ref mut deps, _)) = self.dep_map.get_mut(key) {
+            if deps.remove(dep) {
+                self.dirty.insert(key.clone());
+            }
+        }
+    }
+
     /// Dequeues a package that is ready to be built.
     ///
     /// A package is ready to be built when it has 0 un-built dependencies. If
@@ -124,15 +161,46 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
     /// possibly allowing the next invocation of `dequeue` to return a package.
     pub fn finish(&mut self, key: &K, fresh: Freshness) {
         assert!(self.pending.remove(key));
+        self.finished.insert(key.clone(), fresh);
         let reverse_deps = match self.reverse_dep_map.get(key) {
             Some(deps) => deps,
             None => return,
         };
+        let released = if self.metadata_ready.remove(key) {
+            self.metadata_only.get(key)
+        } else {
+            None
+        };
         for dep in reverse_deps.iter() {
+            if released.map(|r| r.contains(dep)) == Some(true) {
+                continue
+            }
             if fresh == Dirty {
                 self.dirty.insert(dep.clone());
             }
             assert!(self.dep_map.get_mut(dep).unwrap().0.remove(key));
         }
     }
+
+    /// Indicate that the metadata of a package which is still being built is
+    /// available.
+    ///
+    /// Packages which only need the metadata of this package may be returned
+    /// by `dequeue` from now on, the others still wait for `finish`.
+    pub fn metadata_ready(&mut self, key: &K) {
+        assert!(self.pending.contains(key));
+        let dependents = match self.metadata_only.get(key) {
+            Some(deps) => deps,
+            None => return,
+        };
+        if !self.metadata_ready.insert(key.clone()) {
+            return
+        }
+        for dep in dependents.iter() {
+            // The package is being rebuilt, so everything depending on it
+            // needs to be rebuilt as well.
+            self.dirty.insert(dep.clone());
+            assert!(self.dep_map.get_mut(dep).unwrap().0.remove(key));
+        }
+    }
 }
@@ -110,6 +110,8 @@ pub struct ProcessError {
     pub desc: String,
     pub exit: Option<ExitStatus>,
     pub output: Option<Output>,
+    /// Whether the process was killed for running longer than it was allowed
+    pub timed_out: bool,
     cause: Option<io::Error>,
 }
 
@@ -405,6 +407,7 @@ pub fn process_error(msg: &str,
         desc: desc,
         exit: status.cloned(),
         output: output.cloned(),
+        timed_out: false,
         cause: cause,
     };
 
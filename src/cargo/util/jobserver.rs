@@ -0,0 +1,335 @@
+//! A GNU make compatible jobserver.
+//!
+//! A jobserver is a pool of tokens shared by a tree of processes, each token
+//! allowing its holder to run one job. Every process implicitly owns one token
+//! and has to acquire another one for each further job it wants to run in
+//! parallel, releasing it once that job is done. This keeps the total amount
+//! of work in the tree within the `-j` limit of the build at the top of it,
+//! however deeply `make`, Cargo and build scripts invoke each other.
+//!
+//! On Unix the pool is a pipe holding one byte per token, and on Windows a
+//! named semaphore. Both are advertised to child processes through the
+//! `--jobserver-fds` and `--jobserver-auth` options of `MAKEFLAGS`, which is
+//! also where a jobserver created by a parent `make` is picked up from. Newer
+//! versions of `make` advertise a named pipe as `--jobserver-auth=fifo:PATH`
+//! instead, which is joined by opening it.
+
+use std::env;
+use std::io;
+use std::sync::Arc;
+
+/// A client of a jobserver, created either by this process or inherited from
+/// the process which spawned it.
+#[derive(Clone)]
+pub struct Client {
+    inner: Arc<imp::Client>,
+    inherited: bool,
+}
+
+/// A token acquired from a jobserver, released back to it when dropped.
+pub struct Acquired {
+    client: Arc<imp::Client>,
+    data: imp::Acquired,
+}
+
+impl Client {
+    /// Creates a new jobserver allowing `limit` jobs to run in parallel on top
+    /// of the one implicitly owned by this process.
+    pub fn new(limit: usize) -> io::Result<Client> {
+        let inner = try!(unsafe { imp::Client::new(limit) });
+        Ok(Client { inner: Arc::new(inner), inherited: false })
+    }
+
+    /// Connects to the jobserver advertised in the environment, returning
+    /// `Ok(None)` if there's none.
+    ///
+    /// `make` only passes the jobserver along to commands it knows to be
+    /// recursive invocations of `make`, so the advertised descriptors are
+    /// checked to still be open before being used. If they aren't, the name of
+    /// the variable which advertised them is returned as the error.
+    pub fn from_env() -> Result<Option<Client>, &'static str> {
+        let vars = ["CARGO_MAKEFLAGS", "MAKEFLAGS", "MFLAGS"];
+        let found = vars.iter().filter_map(|&key| {
+            env::var(key).ok().map(|var| (key, var))
+        }).next();
+        let (key, var) = match found {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let arg = var.split_whitespace().filter_map(|arg| {
+            if arg.starts_with("--jobserver-auth=") {
+                Some(&arg["--jobserver-auth=".len()..])
+            } else if arg.starts_with("--jobserver-fds=") {
+                Some(&arg["--jobserver-fds=".len()..])
+            } else {
+                None
+            }
+        }).next();
+        let arg = match arg {
+            Some(arg) => arg,
+            None => return Ok(None),
+        };
+        match unsafe { imp::Client::open(arg) } {
+            Some(inner) => {
+                Ok(Some(Client { inner: Arc::new(inner), inherited: true }))
+            }
+            None => Err(key),
+        }
+    }
+
+    /// Whether this jobserver was inherited from the process which spawned
+    /// this one rather than created by it.
+    pub fn is_inherited(&self) -> bool {
+        self.inherited
+    }
+
+    /// Blocks until a token is available, returning it.
+    pub fn acquire(&self) -> io::Result<Acquired> {
+        let data = try!(self.inner.acquire());
+        Ok(Acquired { client: self.inner.clone(), data: data })
+    }
+
+    /// Returns a value for `MAKEFLAGS` pointing `make` and other tools at this
+    /// jobserver.
+    pub fn makeflags(&self) -> String {
+        let arg = self.inner.string_arg();
+        if arg.starts_with("fifo:") {
+            // Only understood by the versions of `make` which use them
+            format!("-j --jobserver-auth={}", arg)
+        } else {
+            format!("-j --jobserver-fds={0} --jobserver-auth={0}", arg)
+        }
+    }
+}
+
+impl Drop for Acquired {
+    fn drop(&mut self) {
+        drop(self.client.release(&self.data));
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::fs::OpenOptions;
+    use std::io;
+    use std::os::unix::prelude::*;
+    use libc;
+
+    pub struct Client {
+        read: libc::c_int,
+        write: libc::c_int,
+        owned: bool,
+        /// The path of the named pipe this client opened, if it isn't an
+        /// anonymous one
+        fifo: Option<String>,
+    }
+
+    pub struct Acquired {
+        byte: u8,
+    }
+
+    impl Client {
+        pub unsafe fn new(limit: usize) -> io::Result<Client> {
+            let mut fds = [0; 2];
+            if libc::pipe(fds.as_mut_ptr()) == -1 {
+                return Err(io::Error::last_os_error())
+            }
+            // The descriptors are deliberately inheritable so every process
+            // we spawn can take part.
+            let client = Client {
+                read: fds[0],
+                write: fds[1],
+                owned: true,
+                fifo: None,
+            };
+            for _ in 0..limit {
+                try!(client.release(&Acquired { byte: b'|' }));
+            }
+            Ok(client)
+        }
+
+        pub unsafe fn open(arg: &str) -> Option<Client> {
+            if arg.starts_with("fifo:") {
+                let path = &arg["fifo:".len()..];
+                let file = match OpenOptions::new().read(true).write(true)
+                                                   .open(path) {
+                    Ok(file) => file,
+                    Err(..) => return None,
+                };
+                let fd = file.into_raw_fd();
+                return Some(Client {
+                    read: fd,
+                    write: fd,
+                    owned: true,
+                    fifo: Some(path.to_string()),
+                })
+            }
+            let mut parts = arg.splitn(2, ',');
+            let read = parts.next().and_then(|s| s.parse().ok());
+            let write = parts.next().and_then(|s| s.parse().ok());
+            let (read, write) = match (read, write) {
+                (Some(read), Some(write)) => (read, write),
+                _ => return None,
+            };
+            if libc::fcntl(read, libc::F_GETFD) == -1 ||
+               libc::fcntl(write, libc::F_GETFD) == -1 {
+                return None
+            }
+            Some(Client { read: read, write: write, owned: false, fifo: None })
+        }
+
+        pub fn acquire(&self) -> io::Result<Acquired> {
+            let mut byte = 0u8;
+            loop {
+                let n = unsafe {
+                    libc::read(self.read, &mut byte as *mut u8 as *mut _, 1)
+                };
+                match n {
+                    1 => return Ok(Acquired { byte: byte }),
+                    0 => {
+                        return Err(io::Error::new(io::ErrorKind::Other,
+                                                  "early EOF on jobserver pipe"))
+                    }
+                    _ => {
+                        let err = io::Error::last_os_error();
+                        if err.kind() != io::ErrorKind::Interrupted {
+                            return Err(err)
+                        }
+                    }
+                }
+            }
+        }
+
+        pub fn release(&self, data: &Acquired) -> io::Result<()> {
+            loop {
+                let byte = &data.byte as *const u8;
+                let n = unsafe { libc::write(self.write, byte as *const _, 1) };
+                if n == 1 {
+                    return Ok(())
+                }
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err)
+                }
+            }
+        }
+
+        pub fn string_arg(&self) -> String {
+            match self.fifo {
+                Some(ref path) => format!("fifo:{}", path),
+                None => format!("{},{}", self.read, self.write),
+            }
+        }
+    }
+
+    impl Drop for Client {
+        fn drop(&mut self) {
+            // Inherited descriptors are left alone as they may be connected
+            // to again by a later build in this process.
+            if self.owned {
+                unsafe {
+                    libc::close(self.read);
+                    if self.write != self.read {
+                        libc::close(self.write);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    extern crate kernel32;
+    extern crate winapi;
+
+    use std::cmp;
+    use std::ffi::OsStr;
+    use std::io;
+    use std::os::windows::prelude::*;
+    use std::ptr;
+    use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+    const SYNCHRONIZE: winapi::DWORD = 0x00100000;
+    const SEMAPHORE_MODIFY_STATE: winapi::DWORD = 0x0002;
+
+    extern "system" {
+        fn OpenSemaphoreW(access: winapi::DWORD,
+                          inherit: winapi::BOOL,
+                          name: winapi::LPCWSTR) -> winapi::HANDLE;
+    }
+
+    pub struct Client {
+        sem: winapi::HANDLE,
+        name: String,
+    }
+
+    pub struct Acquired;
+
+    unsafe impl Send for Client {}
+    unsafe impl Sync for Client {}
+
+    fn wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(Some(0)).collect()
+    }
+
+    impl Client {
+        pub unsafe fn new(limit: usize) -> io::Result<Client> {
+            static COUNTER: AtomicUsize = ATOMIC_USIZE_INIT;
+            let name = format!("__cargo_jobserver_{}_{}",
+                               kernel32::GetCurrentProcessId(),
+                               COUNTER.fetch_add(1, Ordering::SeqCst));
+            // The maximum count of a semaphore has to be positive
+            let max = cmp::max(limit, 1) as winapi::LONG;
+            let sem = kernel32::CreateSemaphoreW(ptr::null_mut(),
+                                                 limit as winapi::LONG, max,
+                                                 wide(&name).as_ptr());
+            if sem.is_null() {
+                return Err(io::Error::last_os_error())
+            }
+            Ok(Client { sem: sem, name: name })
+        }
+
+        pub unsafe fn open(arg: &str) -> Option<Client> {
+            let sem = OpenSemaphoreW(SYNCHRONIZE | SEMAPHORE_MODIFY_STATE,
+                                     winapi::FALSE, wide(arg).as_ptr());
+            if sem.is_null() {
+                None
+            } else {
+                Some(Client { sem: sem, name: arg.to_string() })
+            }
+        }
+
+        pub fn acquire(&self) -> io::Result<Acquired> {
+            let r = unsafe {
+                kernel32::WaitForSingleObject(self.sem, winapi::INFINITE)
+            };
+            if r == winapi::WAIT_OBJECT_0 {
+                Ok(Acquired)
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        }
+
+        pub fn release(&self, _data: &Acquired) -> io::Result<()> {
+            let r = unsafe {
+                kernel32::ReleaseSemaphore(self.sem, 1, ptr::null_mut())
+            };
+            if r != 0 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        }
+
+        pub fn string_arg(&self) -> String {
+            self.name.clone()
+        }
+    }
+
+    impl Drop for Client {
+        fn drop(&mut self) {
+            unsafe { kernel32::CloseHandle(self.sem); }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Messages printed on stdout with `--message-format json`.
+//!
+//! Each message is a single line holding a JSON object whose `reason` field
+//! tells what kind of message it is, so tools can consume the output of a
+//! build as it happens.
+
+use rustc_serialize::Encodable;
+use rustc_serialize::json::{self, Json};
+
+use core::{PackageId, Target, Profile};
+
+pub trait Message: Encodable {
+    fn reason(&self) -> &str;
+}
+
+/// Prints `message` on stdout as a line of JSON.
+pub fn emit<T: Message>(message: T) {
+    let encoded = json::encode(&message).unwrap();
+    let mut object = match Json::from_str(&encoded) {
+        Ok(Json::Object(object)) => object,
+        _ => panic!("messages must be encoded as JSON objects"),
+    };
+    object.insert("reason".to_string(),
+                  Json::String(message.reason().to_string()));
+    println!("{}", Json::Object(object));
+}
+
+/// A diagnostic emitted by the compiler, in the format of `--error-format
+/// json`.
+#[derive(RustcEncodable)]
+pub struct FromCompiler<'a> {
+    pub package_id: &'a PackageId,
+    pub target: &'a Target,
+    pub message: Json,
+}
+
+impl<'a> Message for FromCompiler<'a> {
+    fn reason(&self) -> &str {
+        "compiler-message"
+    }
+}
+
+/// A target which has been compiled, or was already up to date if `fresh`,
+/// along with the paths of the files it's made of.
+#[derive(RustcEncodable)]
+pub struct Artifact<'a> {
+    pub package_id: &'a PackageId,
+    pub target: &'a Target,
+    pub profile: &'a Profile,
+    pub filenames: &'a [String],
+    pub fresh: bool,
+}
+
+impl<'a> Message for Artifact<'a> {
+    fn reason(&self) -> &str {
+        "compiler-artifact"
+    }
+}
+
+/// A test executable which failed and then passed when run again.
+#[derive(RustcEncodable)]
+pub struct FlakyTest<'a> {
+    pub package_id: &'a PackageId,
+    pub target: &'a Target,
+    pub failures: u32,
+}
+
+impl<'a> Message for FlakyTest<'a> {
+    fn reason(&self) -> &str {
+        "flaky-test"
+    }
+}
+
+/// A test executable which was killed for running longer than its timeout.
+#[derive(RustcEncodable)]
+pub struct TestTimeout<'a> {
+    pub package_id: &'a PackageId,
+    pub target: &'a Target,
+    pub timeout: u64,
+}
+
+impl<'a> Message for TestTimeout<'a> {
+    fn reason(&self) -> &str {
+        "test-timeout"
+    }
+}
+
+/// A line of the machine readable output of a test executable, such as the one
+/// of libtest with `--format json`.
+#[derive(RustcEncodable)]
+pub struct FromTest<'a> {
+    pub package_id: &'a PackageId,
+    pub target: &'a Target,
+    pub message: Json,
+}
+
+impl<'a> Message for FromTest<'a> {
+    fn reason(&self) -> &str {
+        "test-message"
+    }
+}
+
+/// The outcome of a single test, as reported by libtest: `ok`, `failed`,
+/// `ignored` or `bench`.
+#[derive(RustcEncodable)]
+pub struct TestResult<'a> {
+    pub package_id: &'a PackageId,
+    pub target: &'a Target,
+    pub name: &'a str,
+    pub outcome: &'a str,
+}
+
+impl<'a> Message for TestResult<'a> {
+    fn reason(&self) -> &str {
+        "test-result"
+    }
+}
+
+/// A test executable built by `cargo test --no-run`, which can be run as is.
+#[derive(RustcEncodable)]
+pub struct TestExecutable<'a> {
+    pub package_id: &'a PackageId,
+    pub target: &'a Target,
+    pub executable: &'a str,
+}
+
+impl<'a> Message for TestExecutable<'a> {
+    fn reason(&self) -> &str {
+        "test-executable"
+    }
+}
+
+/// A warning or an error reported by a build script, see `cargo:warning=`.
+#[derive(RustcEncodable)]
+pub struct BuildScriptMessage<'a> {
+    pub package_id: &'a PackageId,
+    pub level: &'a str,
+    pub message: &'a str,
+    pub file: Option<&'a str>,
+    pub line: Option<u32>,
+}
+
+impl<'a> Message for BuildScriptMessage<'a> {
+    fn reason(&self) -> &str {
+        "build-script-message"
+    }
+}
+
+/// The native libraries and `--cfg` flags a build script asked for.
+#[derive(RustcEncodable)]
+pub struct BuildScript<'a> {
+    pub package_id: &'a PackageId,
+    pub linked_libs: &'a [String],
+    pub linked_paths: &'a [String],
+    pub cfgs: &'a [String],
+}
+
+impl<'a> Message for BuildScript<'a> {
+    fn reason(&self) -> &str {
+        "build-script-executed"
+    }
+}
+
+/// A problem `cargo lint-manifest` found in the manifest of a package.
+#[derive(RustcEncodable)]
+pub struct ManifestLint<'a> {
+    pub package_id: &'a PackageId,
+    pub lint: &'a str,
+    pub severity: &'a str,
+    pub message: &'a str,
+}
+
+impl<'a> Message for ManifestLint<'a> {
+    fn reason(&self) -> &str {
+        "manifest-lint"
+    }
+}
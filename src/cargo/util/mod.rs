@@ -21,6 +21,7 @@ pub use self::vcs::{GitRepo, HgRepo};
 pub use self::read2::read2;
 
 pub mod config;
+pub mod crash_report;
 pub mod errors;
 pub mod graph;
 pub mod hex;
@@ -32,8 +33,12 @@ pub mod to_semver;
 pub mod to_url;
 pub mod toml;
 pub mod lev_distance;
+pub mod machine_message;
 pub mod job;
+pub mod jobserver;
 pub mod network;
+pub mod progress;
+pub mod rusage;
 mod cfg;
 mod dependency_queue;
 mod rustc;
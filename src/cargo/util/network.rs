@@ -14,14 +14,26 @@ pub fn with_retry<T, E, F>(config: &Config, mut callback: F) -> CargoResult<T>
     where F: FnMut() -> Result<T, E>,
           E: errors::NetworkError
 {
-    let mut remaining = try!(config.net_retry());
+    let retries = try!(config.net_retry());
+    retry(retries, &mut |msg: String| config.shell().warn(msg), callback)
+}
+
+/// The retry logic of `with_retry` for callers away from the `Config`, such
+/// as other threads: `warn` is handed the warning about each retry instead of
+/// it being printed.
+pub fn retry<T, E, F>(mut remaining: i64,
+                      warn: &mut FnMut(String) -> CargoResult<()>,
+                      mut callback: F) -> CargoResult<T>
+    where F: FnMut() -> Result<T, E>,
+          E: errors::NetworkError
+{
     loop {
         match callback() {
             Ok(ret) => return Ok(ret),
             Err(ref e) if e.maybe_spurious() && remaining > 0 => {
                 let msg = format!("spurious network error ({} tries \
                           remaining): {}", remaining, e);
-                try!(config.shell().warn(msg));
+                try!(warn(msg));
                 remaining -= 1;
             }
             Err(e) => return Err(Box::new(e)),
@@ -1,6 +1,6 @@
 use std::env;
 use std::ffi::{OsStr, OsString};
-use std::fs::File;
+use std::fs::{self, File};
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf, Component};
@@ -53,6 +53,38 @@ pub fn normalize_path(path: &Path) -> PathBuf {
     ret
 }
 
+/// Resolves all symbolic links in `path`.
+///
+/// This gives the one path Cargo uses to identify a file or directory no
+/// matter which route it was reached through, so a package behind a symlink
+/// isn't mistaken for a different package. Paths which don't exist (yet) are
+/// only normalized. On Windows the `\\?\` prefix `fs::canonicalize` adds is
+/// removed again for paths with a drive letter, as rustc and most other tools
+/// don't handle it.
+pub fn canonicalize(path: &Path) -> PathBuf {
+    match fs::canonicalize(path) {
+        Ok(path) => strip_verbatim_prefix(path),
+        Err(..) => normalize_path(path),
+    }
+}
+
+#[cfg(windows)]
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    let stripped = match path.to_str() {
+        Some(s) if s.starts_with(r"\\?\") &&
+                   s[4..].chars().nth(1) == Some(':') => {
+            Some(PathBuf::from(&s[4..]))
+        }
+        _ => None,
+    };
+    stripped.unwrap_or(path)
+}
+
+#[cfg(not(windows))]
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    path
+}
+
 pub fn without_prefix<'a>(a: &'a Path, b: &'a Path) -> Option<&'a Path> {
     let mut a = a.components();
     let mut b = b.components();
@@ -114,6 +146,57 @@ pub fn append(path: &Path, contents: &[u8]) -> CargoResult<()> {
     })
 }
 
+/// Moves the directory `src` to `dst`, copying it over if they're on different
+/// file systems.
+pub fn move_dir(src: &Path, dst: &Path) -> CargoResult<()> {
+    if fs::rename(src, dst).is_ok() {
+        return Ok(())
+    }
+    (|| -> CargoResult<()> {
+        try!(copy_dir(src, dst));
+        try!(fs::remove_dir_all(src));
+        Ok(())
+    })().chain_error(|| {
+        internal(format!("failed to move `{}` to `{}`", src.display(),
+                         dst.display()))
+    })
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> CargoResult<()> {
+    try!(fs::create_dir_all(dst));
+    for entry in try!(fs::read_dir(src)) {
+        let entry = try!(entry);
+        let to = dst.join(entry.file_name());
+        if try!(entry.file_type()).is_dir() {
+            try!(copy_dir(&entry.path(), &to));
+        } else {
+            try!(fs::copy(entry.path(), &to));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the total size in bytes of the files within `path`, without
+/// following symbolic links.
+pub fn dir_size(path: &Path) -> CargoResult<u64> {
+    (|| -> CargoResult<u64> {
+        let mut size = 0;
+        for entry in try!(fs::read_dir(path)) {
+            let entry = try!(entry);
+            let metadata = try!(fs::symlink_metadata(entry.path()));
+            if metadata.is_dir() {
+                size += try!(dir_size(&entry.path()));
+            } else {
+                size += metadata.len();
+            }
+        }
+        Ok(size)
+    })().chain_error(|| {
+        internal(format!("failed to compute the size of `{}`",
+                         path.display()))
+    })
+}
+
 #[cfg(unix)]
 pub fn path2bytes(path: &Path) -> CargoResult<&[u8]> {
     use std::os::unix::prelude::*;
@@ -2,10 +2,15 @@ use std::collections::HashMap;
 use std::env;
 use std::ffi::{OsString, OsStr};
 use std::fmt;
+use std::io::{self, BufRead, BufReader};
+use std::mem;
 use std::path::Path;
-use std::process::{Command, Output};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 
-use util::{ProcessError, process_error};
+use util::{ProcessError, process_error, read2, rusage};
 use util::shell_escape::escape;
 
 #[derive(Clone, PartialEq, Debug)]
@@ -57,6 +62,20 @@ impl ProcessBuilder {
         self
     }
 
+    /// Runs the program through `wrapper` instead, which receives the
+    /// original program as its first argument.
+    pub fn wrapped<T: AsRef<OsStr>>(&mut self, wrapper: T)
+                                    -> &mut ProcessBuilder {
+        let program = mem::replace(&mut self.program,
+                                   wrapper.as_ref().to_os_string());
+        self.args.insert(0, program);
+        self
+    }
+
+    pub fn get_program(&self) -> &OsString {
+        &self.program
+    }
+
     pub fn get_args(&self) -> &[OsString] {
         &self.args
     }
@@ -74,7 +93,9 @@ impl ProcessBuilder {
 
     pub fn exec(&self) -> Result<(), ProcessError> {
         let mut command = self.build_command();
-        let exit = try!(command.status().map_err(|e| {
+        let exit = try!(command.spawn().and_then(|mut child| {
+            rusage::wait(&mut child)
+        }).map_err(|e| {
             process_error(&format!("Could not execute process `{}`",
                                    self.debug_string()),
                           Some(e), None, None)
@@ -89,10 +110,134 @@ impl ProcessBuilder {
         }
     }
 
+    /// Like `exec`, but kills the process if it's still running once
+    /// `timeout` has elapsed. The error returned then has `timed_out` set.
+    pub fn exec_with_timeout(&self, timeout: Duration)
+                             -> Result<(), ProcessError> {
+        let mut command = self.build_command();
+        let mut child = try!(command.spawn().map_err(|e| {
+            process_error(&format!("Could not execute process `{}`",
+                                   self.debug_string()),
+                          Some(e), None, None)
+        }));
+
+        let pid = child.id();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            drop(tx.send(child.wait()));
+        });
+        let (status, timed_out) = match rx.recv_timeout(timeout) {
+            Ok(status) => (status, false),
+            Err(RecvTimeoutError::Timeout) => {
+                kill(pid);
+                (rx.recv().unwrap(), true)
+            }
+            Err(RecvTimeoutError::Disconnected) => unreachable!(),
+        };
+        let timeout = if timed_out {Some(timeout)} else {None};
+        self.exit_result(status, timeout)
+    }
+
+    /// Like `exec_with_timeout`, without any limit if `timeout` is `None`,
+    /// except that every line the process prints on stdout is passed to
+    /// `on_stdout_line` instead.
+    pub fn exec_with_streaming(&self,
+                               timeout: Option<Duration>,
+                               on_stdout_line: &mut FnMut(&str))
+                               -> Result<(), ProcessError> {
+        let mut command = self.build_command();
+        command.stdout(Stdio::piped());
+        let mut child = try!(command.spawn().map_err(|e| {
+            process_error(&format!("Could not execute process `{}`",
+                                   self.debug_string()),
+                          Some(e), None, None)
+        }));
+
+        // The process is killed by another thread if it's still running once
+        // the timeout has elapsed, and the thread gives up as soon as the
+        // process exits and the sender is dropped
+        let (tx, rx) = mpsc::channel::<()>();
+        let pid = child.id();
+        let killer = timeout.map(|timeout| {
+            thread::spawn(move || {
+                match rx.recv_timeout(timeout) {
+                    Err(RecvTimeoutError::Timeout) => { kill(pid); true }
+                    _ => false,
+                }
+            })
+        });
+
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = Vec::new();
+        loop {
+            match stdout.read_until(b'\n', &mut line) {
+                Ok(0) | Err(..) => break,
+                Ok(..) => {}
+            }
+            {
+                let text = String::from_utf8_lossy(&line);
+                on_stdout_line(text.trim_right_matches(&['\n', '\r'][..]));
+            }
+            line.clear();
+        }
+
+        let status = child.wait();
+        drop(tx);
+        let timed_out = killer.map_or(false, |k| k.join().unwrap_or(false));
+        self.exit_result(status, if timed_out {timeout} else {None})
+    }
+
+    /// Turns the exit status of the process into the result of running it,
+    /// `timed_out` being the timeout it was killed after, if it was.
+    fn exit_result(&self,
+                   status: io::Result<ExitStatus>,
+                   timed_out: Option<Duration>) -> Result<(), ProcessError> {
+        let exit = try!(status.map_err(|e| {
+            process_error(&format!("Could not execute process `{}`",
+                                   self.debug_string()),
+                          Some(e), None, None)
+        }));
+
+        if let Some(timeout) = timed_out {
+            let mut err = process_error(&format!("Process timed out after {}s \
+                                                  and was killed: `{}`",
+                                                 timeout.as_secs(),
+                                                 self.debug_string()),
+                                        None, Some(&exit), None);
+            err.timed_out = true;
+            Err(err)
+        } else if exit.success() {
+            Ok(())
+        } else {
+            Err(process_error(&format!("Process didn't exit successfully: `{}`",
+                                       self.debug_string()),
+                              None, Some(&exit), None))
+        }
+    }
+
     pub fn exec_with_output(&self) -> Result<Output, ProcessError> {
         let mut command = self.build_command();
+        command.stdin(Stdio::null())
+               .stdout(Stdio::piped())
+               .stderr(Stdio::piped());
 
-        let output = try!(command.output().map_err(|e| {
+        let output = try!((|| -> io::Result<Output> {
+            let mut child = try!(command.spawn());
+            let out = child.stdout.take().unwrap();
+            let err = child.stderr.take().unwrap();
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            try!(read2(out, err, &mut |is_out, data, eof| {
+                if eof {
+                    let dst = if is_out {&mut stdout} else {&mut stderr};
+                    dst.extend(data.drain(..));
+                }
+            }));
+            Ok(Output {
+                status: try!(rusage::wait(&mut child)),
+                stdout: stdout,
+                stderr: stderr,
+            })
+        })().map_err(|e| {
             process_error(&format!("Could not execute process `{}`",
                                self.debug_string()),
                           Some(e), None, None)
@@ -134,6 +279,31 @@ impl ProcessBuilder {
     }
 }
 
+#[cfg(unix)]
+fn kill(pid: u32) {
+    use libc;
+
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill(pid: u32) {
+    extern crate kernel32;
+    extern crate winapi;
+
+    unsafe {
+        let handle = kernel32::OpenProcess(winapi::PROCESS_TERMINATE,
+                                           winapi::FALSE,
+                                           pid as winapi::DWORD);
+        if !handle.is_null() {
+            kernel32::TerminateProcess(handle, 1);
+            kernel32::CloseHandle(handle);
+        }
+    }
+}
+
 pub fn process<T: AsRef<OsStr>>(cmd: T) -> ProcessBuilder {
     ProcessBuilder {
         program: cmd.as_ref().to_os_string(),
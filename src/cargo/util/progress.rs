@@ -0,0 +1,137 @@
+//! A status line redrawn in place at the bottom of the terminal, showing how
+//! far along a long running operation is.
+//!
+//! It's only drawn when stderr is a terminal and the output is neither verbose
+//! nor quiet, and can be turned off altogether by setting `term.progress` to
+//! `false` in the configuration. Anything else printed while it's shown has to
+//! `clear` it first so the two don't end up on the same line.
+
+use std::io::{self, Write};
+use std::iter;
+use std::time::Instant;
+
+use core::shell::Verbosity;
+use util::{CargoResult, Config};
+
+/// Width of the bar showing how many steps are done.
+const BAR_WIDTH: usize = 25;
+
+/// Terminals narrower than this don't get a progress line at all.
+const MIN_WIDTH: usize = 40;
+
+pub struct Progress {
+    name: String,
+    width: usize,
+    start: Instant,
+    drawn: bool,
+}
+
+impl Progress {
+    /// Whether a progress line should be shown with the current
+    /// configuration.
+    pub fn wanted(config: &Config) -> CargoResult<bool> {
+        let wanted = try!(config.get_bool("term.progress"))
+                         .map(|v| v.val).unwrap_or(true);
+        let mut shell = config.shell();
+        Ok(wanted && shell.get_verbose() == Verbosity::Normal &&
+           shell.err().width().map_or(false, |w| w >= MIN_WIDTH))
+    }
+
+    /// Returns a progress line named `name`, which should only be drawn if
+    /// `wanted` said so.
+    pub fn new(name: &str, config: &Config) -> Progress {
+        let width = config.shell().err().width().unwrap_or(MIN_WIDTH);
+        Progress::with_width(name, width)
+    }
+
+    fn with_width(name: &str, width: usize) -> Progress {
+        Progress {
+            name: name.to_string(),
+            width: width,
+            start: Instant::now(),
+            drawn: false,
+        }
+    }
+
+    /// Redraws the line with `done` out of `total` steps finished, followed by
+    /// `msg`, which is cut short if the terminal is too narrow.
+    pub fn tick(&mut self, done: usize, total: usize, msg: &str) {
+        let elapsed = self.start.elapsed();
+        let secs = elapsed.as_secs() as f64 +
+                   elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+        let line = self.line(done, total, secs, msg);
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r{}\r", line);
+        let _ = stderr.flush();
+        self.drawn = true;
+    }
+
+    /// The line drawn after `secs` seconds, padded to the width of the
+    /// terminal so it covers whatever was drawn before.
+    fn line(&self, done: usize, total: usize, secs: f64, msg: &str)
+            -> String {
+        let filled = if total == 0 {0} else {BAR_WIDTH * done / total};
+        let mut bar = repeat('=', filled);
+        if filled < BAR_WIDTH {
+            bar.push('>');
+            bar.push_str(&repeat(' ', BAR_WIDTH - filled - 1));
+        }
+        let line = format!("{:>12} [{}] {}/{} {:.1}s: {}", self.name, bar,
+                           done, total, secs, msg);
+
+        // Writing into the last column wraps the line on some terminals
+        let mut line = line.chars().take(self.width - 1).collect::<String>();
+        let len = line.chars().count();
+        line.push_str(&repeat(' ', self.width - 1 - len));
+        line
+    }
+
+    /// Erases the line, if it's shown.
+    pub fn clear(&mut self) {
+        if !self.drawn {
+            return
+        }
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r{}\r", repeat(' ', self.width - 1));
+        let _ = stderr.flush();
+        self.drawn = false;
+    }
+}
+
+fn repeat(c: char, n: usize) -> String {
+    iter::repeat(c).take(n).collect()
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Progress;
+
+    #[test]
+    fn line() {
+        let progress = Progress::with_width("Building", 80);
+        let line = progress.line(3, 12, 1.5, "foo, bar(build)");
+        assert_eq!(line.trim_right(),
+                   "    Building [======>                  ] 3/12 1.5s: \
+                    foo, bar(build)");
+        assert_eq!(line.len(), 79);
+
+        let line = progress.line(12, 12, 10.0, "");
+        assert_eq!(line.trim_right(),
+                   "    Building [=========================] 12/12 10.0s:");
+    }
+
+    #[test]
+    fn line_cut_short() {
+        let progress = Progress::with_width("Building", 60);
+        let line = progress.line(0, 2, 0.0, "a-long-package-name");
+        assert_eq!(line,
+                   "    Building [>                        ] 0/2 0.0s: \
+                    a-long-p");
+    }
+}
@@ -0,0 +1,85 @@
+//! Accounting of the resources used by the processes Cargo runs.
+//!
+//! Processes waited for with `wait` have their CPU time and peak memory added
+//! up per thread, as each job of a build runs on its own thread, until
+//! they're taken with `take`. Only Unix tells what a process used, elsewhere
+//! nothing is recorded.
+
+use std::cell::Cell;
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+/// The resources used by one or more processes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResourceUsage {
+    /// User and system CPU time
+    pub cpu_time: Duration,
+    /// Peak resident set size of the largest process, in bytes
+    pub max_rss: u64,
+}
+
+thread_local!(static USAGE: Cell<Option<ResourceUsage>> = Cell::new(None));
+
+/// Returns the resources used by the processes waited for on this thread
+/// since the last call, if any were recorded.
+pub fn take() -> Option<ResourceUsage> {
+    USAGE.with(|usage| {
+        let ret = usage.get();
+        usage.set(None);
+        ret
+    })
+}
+
+#[cfg(unix)]
+fn record(cpu_time: Duration, max_rss: u64) {
+    USAGE.with(|usage| {
+        let mut total = usage.get().unwrap_or(ResourceUsage::default());
+        total.cpu_time = total.cpu_time + cpu_time;
+        if max_rss > total.max_rss {
+            total.max_rss = max_rss;
+        }
+        usage.set(Some(total));
+    })
+}
+
+/// Waits for `child` to exit like `Child::wait`, recording what it used.
+#[cfg(unix)]
+pub fn wait(child: &mut Child) -> io::Result<ExitStatus> {
+    use std::mem;
+    use std::os::unix::process::ExitStatusExt;
+    use libc;
+
+    let mut status = 0;
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    loop {
+        let pid = unsafe {
+            libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut usage)
+        };
+        if pid != -1 {
+            break
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err)
+        }
+    }
+
+    let time = |t: libc::timeval| {
+        Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000)
+    };
+    // Linux and most other platforms count in kilobytes, macOS in bytes
+    let max_rss = if cfg!(target_os = "macos") {
+        usage.ru_maxrss as u64
+    } else {
+        usage.ru_maxrss as u64 * 1024
+    };
+    record(time(usage.ru_utime) + time(usage.ru_stime), max_rss);
+    Ok(ExitStatus::from_raw(status))
+}
+
+/// Waits for `child` to exit like `Child::wait`, recording what it used.
+#[cfg(not(unix))]
+pub fn wait(child: &mut Child) -> io::Result<ExitStatus> {
+    child.wait()
+}
@@ -53,4 +53,10 @@ impl Rustc {
     pub fn process(&self) -> ProcessBuilder {
         util::process(&self.path)
     }
+
+    /// Whether this compiler accepts `-Z` flags.
+    pub fn is_nightly(&self) -> bool {
+        self.verbose_version.contains("-nightly") ||
+            self.verbose_version.contains("-dev")
+    }
 }
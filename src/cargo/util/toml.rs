@@ -13,7 +13,8 @@ use core::{SourceId, Profiles, PackageIdSpec, GitReference, WorkspaceConfig};
 use core::{Summary, Manifest, Target, Dependency, DependencyInner, PackageId};
 use core::{EitherManifest, VirtualManifest};
 use core::dependency::{Kind, Platform};
-use core::manifest::{LibKind, Profile, ManifestMetadata};
+use core::manifest::{LibKind, Profile, ProfileOverride, CustomProfile};
+use core::manifest::{ManifestMetadata, Lto};
 use core::package_id::Metadata;
 use util::{self, CargoResult, human, ToUrl, ToSemver, ChainError, Config};
 
@@ -161,6 +162,114 @@ pub fn to_manifest(contents: &str,
     }
 }
 
+/// Returns the manifest `contents` of the package at `manifest_path` with the
+/// values it inherits from its workspace replaced by concrete ones, so that
+/// it's usable on its own once packaged.
+pub fn inline_workspace_values(contents: &str,
+                               manifest_path: &Path,
+                               config: &Config) -> CargoResult<String> {
+    let root = manifest_path.parent().unwrap();
+    let mut table = try!(parse(contents, manifest_path, config));
+    let inherit = {
+        let mut d = toml::Decoder::new(toml::Value::Table(table.clone()));
+        let manifest = Decodable::decode(&mut d);
+        let manifest: TomlManifest = try!(manifest.map_err(|e| {
+            human(e.to_string())
+        }));
+        let project = manifest.package.as_ref().or(manifest.project.as_ref());
+        let project = try!(project.chain_error(|| {
+            human("no `package` section found")
+        }));
+        let layout = Layout::from_project_path(root);
+        try!(manifest.inheritable_fields(project, &layout, config))
+    };
+    let inherit = try!(inherit.chain_error(|| {
+        human(format!("`{}` inherits values from its workspace but no \
+                       workspace root could be found", manifest_path.display()))
+    }));
+
+    for section in ["package", "project"].iter() {
+        let package = match table.get_mut(*section) {
+            Some(&mut toml::Value::Table(ref mut package)) => package,
+            _ => continue,
+        };
+        for (key, value) in package.iter_mut() {
+            if !is_inherited(value) {
+                continue
+            }
+            *value = try!(inherit.package_value(key).chain_error(|| {
+                human(format!("`package.{}` was inherited but `{}` does not \
+                               define `workspace.package.{}`", key,
+                              inherit.root.join("Cargo.toml").display(), key))
+            }));
+        }
+    }
+    try!(inline_dependencies(&mut table, &inherit, root));
+    if let Some(&mut toml::Value::Table(ref mut targets)) =
+           table.get_mut("target") {
+        for platform in targets.values_mut() {
+            if let toml::Value::Table(ref mut platform) = *platform {
+                try!(inline_dependencies(platform, &inherit, root));
+            }
+        }
+    }
+    Ok(toml::Value::Table(table).to_string())
+}
+
+fn is_inherited(value: &toml::Value) -> bool {
+    match *value {
+        toml::Value::Table(ref t) => {
+            t.get("workspace").and_then(|v| v.as_bool()) == Some(true)
+        }
+        _ => false,
+    }
+}
+
+/// Replaces the dependencies inherited from the workspace in the dependency
+/// sections of `table` with their definition in the workspace root.
+fn inline_dependencies(table: &mut toml::Table,
+                       inherit: &InheritableFields,
+                       root: &Path) -> CargoResult<()> {
+    for section in ["dependencies", "dev-dependencies",
+                    "build-dependencies"].iter() {
+        let deps = match table.get_mut(*section) {
+            Some(&mut toml::Value::Table(ref mut deps)) => deps,
+            _ => continue,
+        };
+        for (name, dep) in deps.iter_mut() {
+            if !is_inherited(dep) {
+                continue
+            }
+            let details: DetailedTomlDependency = {
+                let mut d = toml::Decoder::new(dep.clone());
+                try!(Decodable::decode(&mut d).map_err(|e| {
+                    human(format!("invalid dependency `{}`: {}", name, e))
+                }))
+            };
+            let details = try!(details.inherit_from(name, Some(inherit)));
+            *dep = details.to_toml(root);
+        }
+    }
+    Ok(())
+}
+
+/// Returns `path` relative to `base`, both of them being absolute.
+fn relative_to(base: &Path, path: &Path) -> PathBuf {
+    let base = base.components().collect::<Vec<_>>();
+    let path = path.components().collect::<Vec<_>>();
+    let common = base.iter().zip(path.iter()).take_while(|&(a, b)| {
+        a == b
+    }).count();
+    let mut ret = PathBuf::new();
+    for _ in common..base.len() {
+        ret.push("..");
+    }
+    for component in path[common..].iter() {
+        ret.push(component.as_os_str());
+    }
+    ret
+}
+
 pub fn parse(toml: &str,
              file: &Path,
              config: &Config) -> CargoResult<toml::Table> {
@@ -208,7 +317,7 @@ type TomlExampleTarget = TomlTarget;
 type TomlTestTarget = TomlTarget;
 type TomlBenchTarget = TomlTarget;
 
-#[derive(RustcDecodable)]
+#[derive(RustcDecodable, Clone)]
 pub enum TomlDependency {
     Simple(String),
     Detailed(DetailedTomlDependency)
@@ -226,6 +335,7 @@ pub struct DetailedTomlDependency {
     features: Option<Vec<String>>,
     optional: Option<bool>,
     default_features: Option<bool>,
+    workspace: Option<bool>,
 }
 
 #[derive(RustcDecodable)]
@@ -247,31 +357,53 @@ pub struct TomlManifest {
     workspace: Option<TomlWorkspace>,
 }
 
-#[derive(RustcDecodable, Clone, Default)]
-pub struct TomlProfiles {
-    test: Option<TomlProfile>,
-    doc: Option<TomlProfile>,
-    bench: Option<TomlProfile>,
-    dev: Option<TomlProfile>,
-    release: Option<TomlProfile>,
-}
+/// The `[profile.*]` sections, both the built-in and custom ones.
+pub type TomlProfiles = HashMap<String, TomlProfile>;
 
 #[derive(RustcDecodable, Clone, Default)]
 pub struct TomlProfile {
     opt_level: Option<u32>,
-    lto: Option<bool>,
+    lto: Option<TomlLto>,
     codegen_units: Option<u32>,
     debug: Option<bool>,
     debug_assertions: Option<bool>,
+    overflow_checks: Option<bool>,
     rpath: Option<bool>,
     panic: Option<String>,
+    incremental: Option<bool>,
+    package: Option<HashMap<String, TomlProfile>>,
+    build_override: Option<Box<TomlProfile>>,
+    inherits: Option<String>,
+}
+
+/// `lto = true` or `false`, or the name of the kind of LTO to use.
+#[derive(RustcDecodable, Clone)]
+pub enum TomlLto {
+    Bool(bool),
+    Mode(String),
+}
+
+impl TomlLto {
+    /// Returns `None` if the name of the kind of LTO is unknown.
+    fn to_lto(&self) -> Option<Lto> {
+        match *self {
+            TomlLto::Bool(true) => Some(Lto::Fat),
+            TomlLto::Bool(false) => Some(Lto::Off),
+            TomlLto::Mode(ref mode) => match &mode[..] {
+                "fat" => Some(Lto::Fat),
+                "thin" => Some(Lto::Thin),
+                "off" => Some(Lto::Off),
+                _ => None,
+            },
+        }
+    }
 }
 
 #[derive(RustcDecodable)]
 pub struct TomlProject {
     name: String,
-    version: TomlVersion,
-    authors: Vec<String>,
+    version: MaybeWorkspace<TomlVersion>,
+    authors: MaybeWorkspace<Vec<String>>,
     build: Option<String>,
     links: Option<String>,
     exclude: Option<Vec<String>>,
@@ -280,6 +412,31 @@ pub struct TomlProject {
     workspace: Option<String>,
 
     // package metadata
+    description: Option<MaybeWorkspace<String>>,
+    homepage: Option<MaybeWorkspace<String>>,
+    documentation: Option<MaybeWorkspace<String>>,
+    readme: Option<MaybeWorkspace<String>>,
+    keywords: Option<MaybeWorkspace<Vec<String>>>,
+    license: Option<MaybeWorkspace<String>>,
+    license_file: Option<MaybeWorkspace<String>>,
+    repository: Option<MaybeWorkspace<String>>,
+}
+
+#[derive(RustcDecodable)]
+pub struct TomlWorkspace {
+    members: Option<Vec<String>>,
+    default_members: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    package: Option<TomlWorkspacePackage>,
+    dependencies: Option<HashMap<String, TomlDependency>>,
+}
+
+/// The `[workspace.package]` table, holding values which members can inherit
+/// with `key = { workspace = true }`.
+#[derive(RustcDecodable, Clone, Default)]
+pub struct TomlWorkspacePackage {
+    version: Option<TomlVersion>,
+    authors: Option<Vec<String>>,
     description: Option<String>,
     homepage: Option<String>,
     documentation: Option<String>,
@@ -290,11 +447,60 @@ pub struct TomlProject {
     repository: Option<String>,
 }
 
+/// A manifest value which is either given directly or inherited from the
+/// workspace root.
 #[derive(RustcDecodable)]
-pub struct TomlWorkspace {
-    members: Option<Vec<String>>,
+pub enum MaybeWorkspace<T> {
+    Defined(T),
+    Workspace(TomlWorkspaceField),
+}
+
+#[derive(RustcDecodable)]
+pub struct TomlWorkspaceField {
+    workspace: bool,
+}
+
+/// The inheritable parts of the `[workspace]` table of a workspace root.
+struct InheritableFields {
+    package: TomlWorkspacePackage,
+    dependencies: HashMap<String, TomlDependency>,
+    root: PathBuf,
+}
+
+impl<T: Clone> MaybeWorkspace<T> {
+    fn resolve<F>(&self, key: &str, inherit: Option<&InheritableFields>, get: F)
+                  -> CargoResult<T>
+        where F: FnOnce(&TomlWorkspacePackage) -> Option<T>
+    {
+        match *self {
+            MaybeWorkspace::Defined(ref value) => Ok(value.clone()),
+            MaybeWorkspace::Workspace(TomlWorkspaceField { workspace: true }) => {
+                let inherit = try!(inherit.chain_error(|| {
+                    human(format!("`package.{}` was inherited but no workspace \
+                                   root could be found", key))
+                }));
+                get(&inherit.package).chain_error(|| {
+                    human(format!("`package.{}` was inherited but `{}` does \
+                                   not define `workspace.package.{}`", key,
+                                  inherit.root.join("Cargo.toml").display(),
+                                  key))
+                })
+            }
+            MaybeWorkspace::Workspace(..) => {
+                bail!("`workspace` cannot be false for `package.{}`", key)
+            }
+        }
+    }
+
+    fn is_inherited(&self) -> bool {
+        match *self {
+            MaybeWorkspace::Defined(..) => false,
+            MaybeWorkspace::Workspace(..) => true,
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct TomlVersion {
     version: semver::Version,
 }
@@ -310,9 +516,66 @@ impl Decodable for TomlVersion {
 }
 
 impl TomlProject {
-    pub fn to_package_id(&self, source_id: &SourceId) -> CargoResult<PackageId> {
-        PackageId::new(&self.name, self.version.version.clone(),
-                       source_id)
+    fn uses_workspace(&self) -> bool {
+        fn opt<T: Clone>(v: &Option<MaybeWorkspace<T>>) -> bool {
+            v.as_ref().map(|v| v.is_inherited()).unwrap_or(false)
+        }
+        self.version.is_inherited() || self.authors.is_inherited() ||
+            opt(&self.description) || opt(&self.homepage) ||
+            opt(&self.documentation) || opt(&self.readme) ||
+            opt(&self.keywords) || opt(&self.license) ||
+            opt(&self.license_file) || opt(&self.repository)
+    }
+}
+
+fn resolve_opt<T, F>(value: &Option<MaybeWorkspace<T>>,
+                     key: &str,
+                     inherit: Option<&InheritableFields>,
+                     get: F) -> CargoResult<Option<T>>
+    where T: Clone, F: FnOnce(&TomlWorkspacePackage) -> Option<T>
+{
+    match *value {
+        Some(ref v) => v.resolve(key, inherit, get).map(Some),
+        None => Ok(None),
+    }
+}
+
+impl InheritableFields {
+    fn new(ws: &TomlWorkspace, root: &Path) -> InheritableFields {
+        InheritableFields {
+            package: ws.package.clone().unwrap_or(Default::default()),
+            dependencies: ws.dependencies.clone().unwrap_or(HashMap::new()),
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// The value of `key` in `[workspace.package]`, if it's defined there.
+    fn package_value(&self, key: &str) -> Option<toml::Value> {
+        fn string(s: &Option<String>) -> Option<toml::Value> {
+            s.clone().map(toml::Value::String)
+        }
+        fn strings(v: &Option<Vec<String>>) -> Option<toml::Value> {
+            v.as_ref().map(|v| {
+                toml::Value::Array(v.iter().cloned()
+                                    .map(toml::Value::String).collect())
+            })
+        }
+        let p = &self.package;
+        match key {
+            "version" => p.version.as_ref().map(|v| {
+                toml::Value::String(v.version.to_string())
+            }),
+            "authors" => strings(&p.authors),
+            "description" => string(&p.description),
+            "homepage" => string(&p.homepage),
+            "documentation" => string(&p.documentation),
+            "readme" => string(&p.readme),
+            "keywords" => strings(&p.keywords),
+            "license" => string(&p.license),
+            "license-file" => string(&p.license_file),
+            "repository" => string(&p.repository),
+            _ => None,
+        }
     }
 }
 
@@ -324,6 +587,7 @@ struct Context<'a, 'b> {
     warnings: &'a mut Vec<String>,
     platform: Option<Platform>,
     layout: &'a Layout,
+    inherit: Option<&'a InheritableFields>,
 }
 
 // These functions produce the equivalent of specific manifest entries. One
@@ -415,7 +679,20 @@ impl TomlManifest {
             bail!("package name cannot be an empty string.")
         }
 
-        let pkgid = try!(project.to_package_id(source_id));
+        let uses_workspace = project.uses_workspace() ||
+                             self.uses_workspace_dependencies();
+        let inherit = if uses_workspace {
+            try!(self.inheritable_fields(project, layout, config))
+        } else {
+            None
+        };
+        let inherit = inherit.as_ref();
+
+        let version = try!(project.version.resolve("version", inherit, |p| {
+            p.version.clone()
+        }));
+        let pkgid = try!(PackageId::new(&project.name, version.version,
+                                        source_id));
         let metadata = pkgid.generate_metadata();
 
         // If we have no lib at all, use the inferred lib if available
@@ -548,6 +825,7 @@ impl TomlManifest {
                 warnings: &mut warnings,
                 platform: None,
                 layout: &layout,
+                inherit: inherit,
             };
 
             fn process_dependencies(
@@ -611,21 +889,37 @@ impl TomlManifest {
                                         self.features.clone()
                                             .unwrap_or(HashMap::new())));
         let metadata = ManifestMetadata {
-            description: project.description.clone(),
-            homepage: project.homepage.clone(),
-            documentation: project.documentation.clone(),
-            readme: project.readme.clone(),
-            authors: project.authors.clone(),
-            license: project.license.clone(),
-            license_file: project.license_file.clone(),
-            repository: project.repository.clone(),
-            keywords: project.keywords.clone().unwrap_or(Vec::new()),
+            description: try!(resolve_opt(&project.description, "description",
+                                          inherit, |p| p.description.clone())),
+            homepage: try!(resolve_opt(&project.homepage, "homepage",
+                                       inherit, |p| p.homepage.clone())),
+            documentation: try!(resolve_opt(&project.documentation,
+                                            "documentation", inherit,
+                                            |p| p.documentation.clone())),
+            readme: try!(resolve_opt(&project.readme, "readme", inherit,
+                                     |p| p.readme.clone())),
+            authors: try!(project.authors.resolve("authors", inherit,
+                                                  |p| p.authors.clone())),
+            license: try!(resolve_opt(&project.license, "license", inherit,
+                                      |p| p.license.clone())),
+            license_file: try!(resolve_opt(&project.license_file,
+                                           "license-file", inherit,
+                                           |p| p.license_file.clone())),
+            repository: try!(resolve_opt(&project.repository, "repository",
+                                         inherit, |p| p.repository.clone())),
+            keywords: try!(resolve_opt(&project.keywords, "keywords", inherit,
+                                       |p| p.keywords.clone()))
+                          .unwrap_or(Vec::new()),
         };
 
         let workspace_config = match (self.workspace.as_ref(),
                                       project.workspace.as_ref()) {
             (Some(config), None) => {
-                WorkspaceConfig::Root { members: config.members.clone() }
+                WorkspaceConfig::Root {
+                    members: config.members.clone(),
+                    default_members: config.default_members.clone(),
+                    exclude: config.exclude.clone().unwrap_or(Vec::new()),
+                }
             }
             (None, root) => {
                 WorkspaceConfig::Member { root: root.cloned() }
@@ -635,7 +929,7 @@ impl TomlManifest {
                        `[workspace]`, only one can be specified")
             }
         };
-        let profiles = build_profiles(&self.profile);
+        let profiles = try!(build_profiles(&self.profile));
         let publish = project.publish.unwrap_or(true);
         let mut manifest = Manifest::new(summary,
                                          targets,
@@ -651,6 +945,9 @@ impl TomlManifest {
             manifest.add_warning(format!("only one of `license` or \
                                           `license-file` is necessary"));
         }
+        if uses_workspace {
+            manifest.set_inherits_workspace();
+        }
         for warning in warnings {
             manifest.add_warning(warning.clone());
         }
@@ -696,16 +993,90 @@ impl TomlManifest {
             warnings: &mut warnings,
             platform: None,
             layout: layout,
+            inherit: None,
         }));
         let workspace_config = match self.workspace {
             Some(ref config) => {
-                WorkspaceConfig::Root { members: config.members.clone() }
+                WorkspaceConfig::Root {
+                    members: config.members.clone(),
+                    default_members: config.default_members.clone(),
+                    exclude: config.exclude.clone().unwrap_or(Vec::new()),
+                }
             }
             None => {
                 bail!("virtual manifests must be configured with [workspace]");
             }
         };
-        Ok((VirtualManifest::new(replace, workspace_config), nested_paths))
+        let profiles = try!(build_profiles(&self.profile));
+        Ok((VirtualManifest::new(replace, workspace_config, profiles),
+            nested_paths))
+    }
+
+    fn uses_workspace_dependencies(&self) -> bool {
+        fn any(deps: Option<&HashMap<String, TomlDependency>>) -> bool {
+            deps.iter().flat_map(|d| d.values()).any(|d| {
+                match *d {
+                    TomlDependency::Detailed(ref d) => d.workspace.is_some(),
+                    TomlDependency::Simple(..) => false,
+                }
+            })
+        }
+        any(self.dependencies.as_ref()) ||
+            any(self.dev_dependencies.as_ref()) ||
+            any(self.build_dependencies.as_ref()) ||
+            self.target.iter().flat_map(|t| t.values()).any(|p| {
+                any(p.dependencies.as_ref()) ||
+                    any(p.dev_dependencies.as_ref()) ||
+                    any(p.build_dependencies.as_ref())
+            })
+    }
+
+    /// Locates the root of the workspace this manifest belongs to and returns
+    /// the values its members may inherit.
+    ///
+    /// The root is found the same way `Workspace` finds it: either through
+    /// `package.workspace` or by walking up the filesystem looking for a
+    /// `Cargo.toml` with a `[workspace]` section.
+    fn inheritable_fields(&self,
+                          project: &TomlProject,
+                          layout: &Layout,
+                          config: &Config)
+                          -> CargoResult<Option<InheritableFields>> {
+        if let Some(ref ws) = self.workspace {
+            return Ok(Some(InheritableFields::new(ws, &layout.root)))
+        }
+
+        let candidates = match project.workspace {
+            Some(ref path) => {
+                vec![util::normalize_path(&layout.root.join(path))]
+            }
+            None => {
+                let mut dirs = Vec::new();
+                let mut cur = layout.root.parent();
+                while let Some(dir) = cur {
+                    dirs.push(dir.to_path_buf());
+                    cur = dir.parent();
+                }
+                dirs
+            }
+        };
+        for dir in candidates {
+            let manifest = dir.join("Cargo.toml");
+            let contents = match util::paths::read(&manifest) {
+                Ok(contents) => contents,
+                Err(..) => continue,
+            };
+            let table = try!(parse(&contents, &manifest, config));
+            let mut d = toml::Decoder::new(toml::Value::Table(table));
+            let root: TomlManifest = try!(Decodable::decode(&mut d).map_err(|e| {
+                human(format!("failed to parse workspace root `{}`: {}",
+                              manifest.display(), e))
+            }));
+            if let Some(ref ws) = root.workspace {
+                return Ok(Some(InheritableFields::new(ws, &dir)))
+            }
+        }
+        Ok(None)
     }
 
     fn replace(&self, cx: &mut Context)
@@ -758,7 +1129,7 @@ impl TomlDependency {
                      cx: &mut Context,
                      kind: Option<Kind>)
                      -> CargoResult<Dependency> {
-        let details = match *self {
+        let mut details = match *self {
             TomlDependency::Simple(ref version) => DetailedTomlDependency {
                 version: Some(version.clone()),
                 .. Default::default()
@@ -766,6 +1137,16 @@ impl TomlDependency {
             TomlDependency::Detailed(ref details) => details.clone(),
         };
 
+        match details.workspace {
+            Some(true) => {
+                details = try!(details.inherit_from(name, cx.inherit))
+            }
+            Some(false) => {
+                bail!("`workspace` cannot be false for dependency `{}`", name)
+            }
+            None => {}
+        }
+
         if details.version.is_none() && details.path.is_none() &&
            details.git.is_none() {
             let msg = format!("dependency ({}) specified without \
@@ -787,8 +1168,8 @@ impl TomlDependency {
             (None, Some(path)) => {
                 cx.nested_paths.push(PathBuf::from(path));
                 // If the source id for the package we're parsing is a path
-                // source, then we normalize the path here to get rid of
-                // components like `..`.
+                // source, then the id of the dependency is its canonical
+                // path, without components like `..` or symlinks.
                 //
                 // The purpose of this is to get a canonical id for the package
                 // that we're depending on to ensure that builds of this package
@@ -796,7 +1177,6 @@ impl TomlDependency {
                 // built from.
                 if cx.source_id.is_path() {
                     let path = cx.layout.root.join(path);
-                    let path = util::normalize_path(&path);
                     try!(SourceId::for_path(&path))
                 } else {
                     cx.source_id.clone()
@@ -818,6 +1198,92 @@ impl TomlDependency {
     }
 }
 
+impl DetailedTomlDependency {
+    /// Replaces a `{ workspace = true }` dependency with the definition from
+    /// `[workspace.dependencies]`, adding any `features` and `optional` keys
+    /// given by the member.
+    fn inherit_from(self, name: &str, inherit: Option<&InheritableFields>)
+                    -> CargoResult<DetailedTomlDependency> {
+        if self.version.is_some() || self.path.is_some() ||
+           self.git.is_some() || self.branch.is_some() ||
+           self.tag.is_some() || self.rev.is_some() {
+            bail!("dependency `{}` is inherited from the workspace and cannot \
+                   also specify a version or source", name)
+        }
+        let inherit = try!(inherit.chain_error(|| {
+            human(format!("dependency `{}` was inherited but no workspace \
+                           root could be found", name))
+        }));
+        let dep = try!(inherit.dependencies.get(name).chain_error(|| {
+            human(format!("dependency `{}` was inherited but `{}` does not \
+                           define `workspace.dependencies.{}`", name,
+                          inherit.root.join("Cargo.toml").display(), name))
+        }));
+        let mut ret = match *dep {
+            TomlDependency::Simple(ref version) => DetailedTomlDependency {
+                version: Some(version.clone()),
+                .. Default::default()
+            },
+            TomlDependency::Detailed(ref details) => details.clone(),
+        };
+        // Paths in the workspace root are relative to the root, not to the
+        // member inheriting them.
+        if let Some(p) = ret.path.take() {
+            let path = inherit.root.join(p);
+            let joined = try!(path.to_str().chain_error(|| {
+                human(format!("dependency `{}` was inherited from a workspace \
+                               at a non-UTF-8 path: {}", name, path.display()))
+            }));
+            ret.path = Some(joined.to_string());
+        }
+        if let Some(features) = self.features {
+            let mut all = ret.features.take().unwrap_or(Vec::new());
+            all.extend(features);
+            ret.features = Some(all);
+        }
+        ret.optional = self.optional.or(ret.optional);
+        ret.default_features = self.default_features.or(ret.default_features);
+        ret.workspace = None;
+        Ok(ret)
+    }
+
+    /// Writes out this dependency as it appears in a manifest at `root`,
+    /// its path being made relative to it.
+    fn to_toml(&self, root: &Path) -> toml::Value {
+        let mut table = toml::Table::new();
+        {
+            let mut string = |key: &str, value: Option<String>| {
+                if let Some(value) = value {
+                    table.insert(key.to_string(), toml::Value::String(value));
+                }
+            };
+            string("version", self.version.clone());
+            string("path", self.path.as_ref().map(|p| {
+                let path = util::normalize_path(Path::new(p));
+                relative_to(root, &path).to_string_lossy().into_owned()
+            }));
+            string("git", self.git.clone());
+            string("branch", self.branch.clone());
+            string("tag", self.tag.clone());
+            string("rev", self.rev.clone());
+        }
+        if let Some(ref features) = self.features {
+            let features = features.iter().cloned().map(toml::Value::String);
+            table.insert("features".to_string(),
+                         toml::Value::Array(features.collect()));
+        }
+        if let Some(optional) = self.optional {
+            table.insert("optional".to_string(),
+                         toml::Value::Boolean(optional));
+        }
+        if let Some(default_features) = self.default_features {
+            table.insert("default-features".to_string(),
+                         toml::Value::Boolean(default_features));
+        }
+        toml::Value::Table(table)
+    }
+}
+
 #[derive(RustcDecodable, Debug, Clone)]
 struct TomlTarget {
     name: Option<String>,
@@ -828,7 +1294,10 @@ struct TomlTarget {
     bench: Option<bool>,
     doc: Option<bool>,
     plugin: Option<bool>,
+    proc_macro: Option<bool>,
     harness: Option<bool>,
+    retries: Option<u32>,
+    timeout: Option<u64>,
 }
 
 #[derive(RustcDecodable, Clone)]
@@ -856,7 +1325,10 @@ impl TomlTarget {
             bench: None,
             doc: None,
             plugin: None,
+            proc_macro: None,
             harness: None,
+            retries: None,
+            timeout: None,
         }
     }
 
@@ -968,7 +1440,10 @@ fn normalize(lib: &Option<TomlLibTarget>,
               .set_doctest(toml.doctest.unwrap_or(t2.doctested()))
               .set_benched(toml.bench.unwrap_or(t2.benched()))
               .set_harness(toml.harness.unwrap_or(t2.harness()))
-              .set_for_host(toml.plugin.unwrap_or(t2.for_host()));
+              .set_for_host(toml.plugin.or(toml.proc_macro)
+                                .unwrap_or(t2.for_host()))
+              .set_retries(toml.retries.unwrap_or(t2.retries()))
+              .set_timeout(toml.timeout.or(t2.timeout()));
     }
 
     fn lib_target(dst: &mut Vec<Target>,
@@ -979,6 +1454,9 @@ fn normalize(lib: &Option<TomlLibTarget>,
         );
         let crate_types = match l.crate_type.clone() {
             Some(kinds) => kinds.iter().map(|s| LibKind::from_str(s)).collect(),
+            None if l.proc_macro == Some(true) => {
+                vec![LibKind::Other("proc-macro".to_string())]
+            }
             None => {
                 vec![ if l.plugin == Some(true) {LibKind::Dylib}
                       else {LibKind::Lib} ]
@@ -1110,50 +1588,201 @@ fn normalize(lib: &Option<TomlLibTarget>,
     ret
 }
 
-fn build_profiles(profiles: &Option<TomlProfiles>) -> Profiles {
-    let profiles = profiles.as_ref();
+fn build_profiles(toml: &Option<TomlProfiles>) -> CargoResult<Profiles> {
+    let get = |name: &str| toml.as_ref().and_then(|p| p.get(name));
+    for &name in ["test", "bench", "doc"].iter() {
+        if get(name).and_then(|p| p.package.as_ref()).is_some() {
+            bail!("package specific settings can only be given in the `dev` \
+                   and `release` profiles, not in `{}`", name)
+        }
+        if get(name).and_then(|p| p.build_override.as_ref()).is_some() {
+            bail!("`build-override` can only be given in the `dev` and \
+                   `release` profiles, not in `{}`", name)
+        }
+    }
+    for (name, profile) in toml.iter().flat_map(|p| p.iter()) {
+        match profile.lto.as_ref().map(|lto| (lto, lto.to_lto())) {
+            Some((&TomlLto::Mode(ref mode), None)) => {
+                bail!("`lto` must be a boolean, \"fat\", \"thin\" or \"off\", \
+                       but found \"{}\" in `[profile.{}]`", mode, name)
+            }
+            _ => {}
+        }
+    }
     let mut profiles = Profiles {
-        release: merge(Profile::default_release(),
-                       profiles.and_then(|p| p.release.as_ref())),
-        dev: merge(Profile::default_dev(),
-                   profiles.and_then(|p| p.dev.as_ref())),
-        test: merge(Profile::default_test(),
-                    profiles.and_then(|p| p.test.as_ref())),
-        test_deps: merge(Profile::default_dev(),
-                         profiles.and_then(|p| p.dev.as_ref())),
-        bench: merge(Profile::default_bench(),
-                     profiles.and_then(|p| p.bench.as_ref())),
-        bench_deps: merge(Profile::default_release(),
-                          profiles.and_then(|p| p.release.as_ref())),
-        doc: merge(Profile::default_doc(),
-                   profiles.and_then(|p| p.doc.as_ref())),
+        release: merge(Profile::default_release(), get("release")),
+        dev: merge(Profile::default_dev(), get("dev")),
+        test: merge(Profile::default_test(), get("test")),
+        test_deps: merge(Profile::default_dev(), get("dev")),
+        bench: merge(Profile::default_bench(), get("bench")),
+        bench_deps: merge(Profile::default_release(), get("release")),
+        doc: merge(Profile::default_doc(), get("doc")),
         custom_build: Profile::default_custom_build(),
+        check: merge(Profile::default_check(), get("dev")),
+        dev_overrides: try!(overrides("dev", get("dev"))),
+        release_overrides: try!(overrides("release", get("release"))),
+        dev_build_override: None,
+        release_build_override: None,
+        custom: HashMap::new(),
     };
+    profiles.dev_build_override =
+        try!(build_override(&profiles.dev, "dev", get("dev")));
+    profiles.release_build_override =
+        try!(build_override(&profiles.release, "release", get("release")));
     profiles.test_deps.panic = None;
     profiles.bench_deps.panic = None;
-    return profiles;
+
+    // Any other profile is a custom one, which is based on `dev` or `release`
+    for (name, custom) in toml.iter().flat_map(|p| p.iter()) {
+        let builtin = ["dev", "release", "test", "bench", "doc"];
+        if builtin.contains(&&name[..]) {
+            if custom.inherits.is_some() {
+                bail!("`inherits` can only be set in custom profiles, not in \
+                       `{}`", name)
+            }
+            continue
+        }
+        if name.is_empty() || !name.chars().all(|c| {
+            c.is_alphanumeric() || c == '-' || c == '_'
+        }) {
+            bail!("invalid profile name `{}`, only letters, numbers, `-` and \
+                   `_` can be used", name)
+        }
+        if ["debug", "package"].contains(&&name[..]) {
+            bail!("profile name `{}` is reserved as it would share its \
+                   output directory with something else", name)
+        }
+        let release = match custom.inherits.as_ref().map(|s| &s[..]) {
+            Some("dev") => false,
+            Some("release") => true,
+            _ => bail!("profile `{}` must inherit from `dev` or `release` \
+                        with `inherits = \"...\"`", name),
+        };
+        let custom = {
+            let (parent, parent_overrides) = if release {
+                (&profiles.release, &profiles.release_overrides)
+            } else {
+                (&profiles.dev, &profiles.dev_overrides)
+            };
+            let mut package_overrides = parent_overrides.clone();
+            package_overrides.extend(try!(overrides(name, Some(custom))));
+            let profile = merge(parent.clone(), Some(custom));
+            // The build scripts are built like those of the parent profile,
+            // unless the custom profile configures them itself
+            let build_override = if custom.build_override.is_some() {
+                try!(build_override(&profile, name, Some(custom)))
+            } else {
+                let parent = if release {get("release")} else {get("dev")};
+                try!(build_override(&profile, name, parent))
+            };
+            CustomProfile {
+                release: release,
+                profile: profile,
+                overrides: package_overrides,
+                build_override: build_override,
+            }
+        };
+        profiles.custom.insert(name.clone(), custom);
+    }
+    return Ok(profiles);
 
     fn merge(profile: Profile, toml: Option<&TomlProfile>) -> Profile {
         let &TomlProfile {
-            opt_level, lto, codegen_units, debug, debug_assertions, rpath,
-            ref panic
+            opt_level, ref lto, codegen_units, debug, debug_assertions,
+            overflow_checks, rpath, ref panic, incremental, package: _,
+            build_override: _, inherits: _,
         } = match toml {
             Some(toml) => toml,
             None => return profile,
         };
+        // Unknown kinds of LTO have been rejected already
+        let lto = lto.as_ref().and_then(|lto| lto.to_lto());
+        // Like with rustc, overflow checks follow the debug assertions unless
+        // they're set on their own
+        let overflow_checks = match overflow_checks.or(debug_assertions) {
+            Some(overflow_checks) => overflow_checks,
+            None => profile.overflow_checks,
+        };
         Profile {
             opt_level: opt_level.unwrap_or(profile.opt_level),
             lto: lto.unwrap_or(profile.lto),
-            codegen_units: codegen_units,
+            codegen_units: codegen_units.or(profile.codegen_units),
             rustc_args: None,
             rustdoc_args: None,
             debuginfo: debug.unwrap_or(profile.debuginfo),
             debug_assertions: debug_assertions.unwrap_or(profile.debug_assertions),
+            overflow_checks: overflow_checks,
+            incremental: incremental.unwrap_or(profile.incremental),
             rpath: rpath.unwrap_or(profile.rpath),
             test: profile.test,
             doc: profile.doc,
             run_custom_build: profile.run_custom_build,
+            check: profile.check,
             panic: panic.clone().or(profile.panic),
         }
     }
+
+    /// Returns `profile` changed by the `build-override` table of `toml`, for
+    /// the units run on the host while building.
+    fn build_override(profile: &Profile, name: &str,
+                      toml: Option<&TomlProfile>)
+                      -> CargoResult<Option<Profile>> {
+        let toml = match toml.and_then(|t| t.build_override.as_ref()) {
+            Some(toml) => toml,
+            None => return Ok(None),
+        };
+        if toml.lto.is_some() || toml.rpath.is_some() ||
+           toml.panic.is_some() || toml.incremental.is_some() ||
+           toml.package.is_some() || toml.build_override.is_some() ||
+           toml.inherits.is_some() {
+            bail!("only `opt-level`, `codegen-units`, `debug`, \
+                   `debug-assertions` and `overflow-checks` can be set in \
+                   `[profile.{}.build-override]`", name)
+        }
+        // Plugins are loaded into the compiler, so they can't abort on panics
+        let mut profile = merge(profile.clone(), Some(&**toml));
+        profile.panic = None;
+        Ok(Some(profile))
+    }
+
+    fn overrides(name: &str, toml: Option<&TomlProfile>)
+                 -> CargoResult<Vec<ProfileOverride>> {
+        let packages = match toml.and_then(|t| t.package.as_ref()) {
+            Some(packages) => packages,
+            None => return Ok(Vec::new()),
+        };
+        // Later overrides win when several of them match a package, so keep
+        // their order independent of the hash map's.
+        let mut packages = packages.iter().collect::<Vec<_>>();
+        packages.sort_by(|a, b| a.0.cmp(b.0));
+        let mut ret = Vec::new();
+        for (spec, toml) in packages {
+            let &TomlProfile {
+                opt_level, ref lto, codegen_units, debug, debug_assertions,
+                overflow_checks, rpath, ref panic, incremental, ref package,
+                ref build_override, ref inherits,
+            } = toml;
+            if lto.is_some() || rpath.is_some() || panic.is_some() ||
+               incremental.is_some() || package.is_some() ||
+               build_override.is_some() || inherits.is_some() {
+                bail!("`lto`, `rpath`, `panic`, `incremental`, `package`, \
+                       `build-override` and `inherits` can't be set for a \
+                       single package in `[profile.{}.package.\"{}\"]`",
+                      name, spec)
+            }
+            let spec = try!(PackageIdSpec::parse(spec).chain_error(|| {
+                human(format!("invalid package id specification `{}` in \
+                               `[profile.{}.package]`", spec, name))
+            }));
+            ret.push(ProfileOverride {
+                spec: spec,
+                opt_level: opt_level,
+                codegen_units: codegen_units,
+                debuginfo: debug,
+                debug_assertions: debug_assertions,
+                overflow_checks: overflow_checks,
+            });
+        }
+        Ok(ret)
+    }
 }
@@ -94,6 +94,15 @@ pub struct NewCrateDependency {
     pub kind: String,
 }
 
+/// An upload of a tarball in chunks, see `Registry::start_upload`.
+#[derive(RustcDecodable)]
+pub struct Upload {
+    pub id: String,
+    /// How much of the tarball the registry already has, from an earlier
+    /// attempt at uploading it
+    pub received: u64,
+}
+
 #[derive(RustcDecodable)]
 pub struct User {
     pub id: u32,
@@ -108,6 +117,13 @@ pub struct User {
 #[derive(RustcDecodable)] struct ApiError { detail: String }
 #[derive(RustcEncodable)] struct OwnersReq<'a> { users: &'a [&'a str] }
 #[derive(RustcDecodable)] struct Users { users: Vec<User> }
+#[derive(RustcDecodable)] struct Me { user: User }
+#[derive(RustcEncodable)] struct NewUpload<'a> {
+    krate: &'a NewCrate,
+    size: u64,
+    cksum: &'a str,
+}
+#[derive(RustcDecodable)] struct UploadResponse { upload: Upload }
 #[derive(RustcDecodable)] struct TotalCrates { total: u32 }
 #[derive(RustcDecodable)] struct Crates { crates: Vec<Crate>, meta: TotalCrates }
 
@@ -147,6 +163,12 @@ impl Registry {
         Ok(try!(json::decode::<Users>(&body)).users)
     }
 
+    /// The user the API token belongs to.
+    pub fn me(&mut self) -> Result<User> {
+        let body = try!(self.get("/me".to_string()));
+        Ok(try!(json::decode::<Me>(&body)).user)
+    }
+
     pub fn publish(&mut self, krate: &NewCrate, tarball: &File) -> Result<()> {
         let json = try!(json::encode(krate));
         // Prepare the body. The format of the upload request is:
@@ -196,6 +218,37 @@ impl Registry {
         Ok(())
     }
 
+    /// Starts uploading the tarball of `krate` in chunks, for registries
+    /// which support it. The tarball is identified by its `size` and sha256
+    /// `cksum`, so that starting again after a failure resumes the upload.
+    pub fn start_upload(&mut self, krate: &NewCrate, size: u64, cksum: &str)
+                        -> Result<Upload> {
+        let body = try!(json::encode(&NewUpload {
+            krate: krate,
+            size: size,
+            cksum: cksum,
+        }));
+        let body = try!(self.put("/crates/uploads/new".to_string(),
+                                 body.as_bytes()));
+        Ok(try!(json::decode::<UploadResponse>(&body)).upload)
+    }
+
+    /// Sends the bytes of the tarball starting at `offset`.
+    pub fn upload_chunk(&mut self, id: &str, offset: u64, chunk: &[u8])
+                        -> Result<()> {
+        try!(self.put(format!("/crates/uploads/{}/chunks/{}", id, offset),
+                      chunk));
+        Ok(())
+    }
+
+    /// Publishes the crate once all of its tarball has been uploaded.
+    pub fn finish_upload(&mut self, id: &str) -> Result<()> {
+        let body = try!(self.put(format!("/crates/uploads/{}/finish", id),
+                                 &[]));
+        assert!(try!(json::decode::<R>(&body)).ok);
+        Ok(())
+    }
+
     pub fn search(&mut self, query: &str, limit: u8) -> Result<(Vec<Crate>, u32)> {
         let formated_query = percent_encode(query.as_bytes(), QUERY_ENCODE_SET);
         let body = try!(self.req(
@@ -10,6 +10,7 @@ _cargo()
 
 	local vcs='git hg none'
 	local color='auto always never'
+	local msg_format='human json'
 
 	local opt_help='-h --help'
 	local opt_verbose='-v --verbose'
@@ -22,32 +23,45 @@ _cargo()
 	local opt_jobs='-j --jobs'
 
 	local opt___nocmd="$opt_common -V --version --list"
-	local opt__bench="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --no-run"
-	local opt__build="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --release"
-	local opt__clean="$opt_common $opt_pkg $opt_mani --target --release"
-	local opt__doc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --open --no-deps --release"
+	local opt__bench="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --no-run --save-baseline --baseline --threshold --all --exclude --message-format"
+	local opt__bisect="$opt_common $opt_mani --good --bad --test --command"
+	local opt__build="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --release --all --exclude --message-format --profile --build-plan"
+	local opt__bump="$opt_common $opt_mani --commit --tag --publish --host --token --no-verify"
+	local opt__check="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --all --exclude --message-format"
+	local opt__clean="$opt_common $opt_pkg $opt_mani --target --release --out-dirs"
+	local opt__cost="$opt_common $opt_feat $opt_mani --release --sort"
+	local opt__daemon="$opt_common --socket"
+	local opt__doc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --open --no-deps --deps --document-private-items --release --all --exclude"
 	local opt__fetch="$opt_common $opt_mani"
 	local opt__generate_lockfile="${opt__fetch}"
 	local opt__git_checkout="$opt_common --reference --url"
 	local opt__help="$opt_help"
+	local opt__index_snapshot="$opt_common --rev"
 	local opt__init="$opt_common --bin --name --vcs"
-	local opt__install="$opt_common $opt_feat $opt_jobs --bin --branch --debug --example --git --list --path --rev --root --tag --vers"
+	local opt__install="$opt_common $opt_feat $opt_jobs --bin --branch --debug --example --git --list --path --reset-features --rev --root --tag --vers"
+	local opt__lint_manifest="$opt_common $opt_mani --message-format"
+	local opt__lockfile_diff="$opt_common $opt_mani --index --format"
 	local opt__locate_project="$opt_mani -h --help"
 	local opt__login="$opt_common --host"
-	local opt__metadata="$opt_common $opt_feat $opt_mani --format-version"
-	local opt__new="$opt_common --vcs --bin --name"
-	local opt__owner="$opt_common -a --add -r --remove -l --list --index --token"
+	local opt__metadata="$opt_common $opt_feat $opt_mani --format-version --no-cache --build-scripts"
+	local opt__name_check="$opt_common --index"
+	local opt__new="$opt_common --vcs --bin --name --template --check-registry"
+	local opt__owner="$opt_common -a --add -r --remove -l --list --index --token --sync -y --yes"
 	local opt__package="$opt_common $opt_mani -l --list --no-verify --no-metadata"
 	local opt__pkgid="${opt__fetch}"
-	local opt__publish="$opt_common $opt_mani --host --token --no-verify"
+	local opt__publish="$opt_common $opt_mani --host --token --no-verify --show-payload"
 	local opt__read_manifest="$opt_help $opt_verbose $opt_mani $opt_color"
-	local opt__run="$opt_common $opt_feat $opt_mani $opt_jobs --target --bin --example --release"
-	local opt__rustc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --release"
+	local opt__release="$opt_common $opt_mani --host --token --no-verify --no-tag --no-publish --wait-timeout --dry-run"
+	local opt__run="$opt_common $opt_feat $opt_mani $opt_jobs --target $opt_pkg --bin --bins --example --release --message-format --profile"
+	local opt__rustc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --release --message-format --crate-type"
 	local opt__rustdoc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --release --open"
 	local opt__search="$opt_common --host"
-	local opt__test="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --no-run --release --no-fail-fast"
+	local opt__test="$opt_common $opt_pkg $opt_feat $opt_mani $opt_jobs --target --lib --bin --test --bench --example --exclude-bin --exclude-example --exclude-test --exclude-bench --no-run --release --no-fail-fast --retries --timeout --coverage --lcov --all --exclude --message-format --profile"
+	local opt__tree="$opt_common $opt_feat $opt_mani -e --edges"
 	local opt__uninstall="$opt_common --bin --root"
-	local opt__update="$opt_common $opt_pkg $opt_mani --aggressive --precise"
+	local opt__update="$opt_common $opt_pkg $opt_mani --aggressive --precise --as-of"
+	local opt__vendor="$opt_common $opt_mani --no-delete"
+	local opt__verify_lockfile="${opt__fetch}"
 	local opt__verify_project="${opt__fetch}"
 	local opt__version="$opt_help $opt_verbose $opt_color"
 	local opt__yank="$opt_common --vers --undo --index --token"
@@ -66,6 +80,9 @@ _cargo()
 			--color)
 				COMPREPLY=( $( compgen -W "$color" -- "$cur" ) )
 				;;
+			--message-format)
+				COMPREPLY=( $( compgen -W "$msg_format" -- "$cur" ) )
+				;;
 			--manifest-path)
 				_filedir toml
 				;;
@@ -0,0 +1,255 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use cargotest::is_nightly;
+use cargotest::support::paths::{self, CargoPathExt};
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::{assert_that, existing_dir, is_not};
+
+type Store = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// A minimal HTTP server keeping the uploaded artifacts in memory and logging
+/// the requests it served.
+fn cache_server() -> (SocketAddr, Arc<Mutex<Vec<String>>>, Store) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log2 = log.clone();
+    let store = Arc::new(Mutex::new(HashMap::new()));
+    let store2 = store.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            serve(stream.unwrap(), &store2, &log2);
+        }
+    });
+    (addr, log, store)
+}
+
+fn serve(mut stream: TcpStream,
+         store: &Mutex<HashMap<String, Vec<u8>>>,
+         log: &Mutex<Vec<String>>) {
+    let mut rdr = BufReader::new(stream.try_clone().unwrap());
+    let mut request = String::new();
+    rdr.read_line(&mut request).unwrap();
+    let mut parts = request.split_whitespace();
+    let method = parts.next().unwrap().to_string();
+    let path = parts.next().unwrap().to_string();
+
+    let mut len = 0;
+    loop {
+        let mut line = String::new();
+        rdr.read_line(&mut line).unwrap();
+        let line = line.trim().to_lowercase();
+        if line.is_empty() {
+            break
+        }
+        if line.starts_with("content-length:") {
+            len = line[15..].trim().parse().unwrap();
+        }
+        if line == "expect: 100-continue" {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+        }
+    }
+
+    let response = if method == "PUT" {
+        let mut body = vec![0; len];
+        rdr.read_exact(&mut body).unwrap();
+        store.lock().unwrap().insert(path.clone(), body);
+        (201, Vec::new())
+    } else {
+        match store.lock().unwrap().get(&path) {
+            Some(body) => (200, body.clone()),
+            None => (404, Vec::new()),
+        }
+    };
+    log.lock().unwrap().push(format!("{} {}", method, response.0));
+    write!(stream, "HTTP/1.1 {} Whatever\r\nContent-Length: {}\r\n\
+                    Connection: close\r\n\r\n",
+           response.0, response.1.len()).unwrap();
+    stream.write_all(&response.1).unwrap();
+}
+
+#[test]
+fn registry_deps_are_shared_through_the_cache() {
+    let (addr, log, _store) = cache_server();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file(".cargo/config", &format!(r#"
+            [build]
+            artifact-cache = "http://{}/cache/"
+        "#, addr));
+    Package::new("bar", "0.1.0").file("src/lib.rs", "pub fn bar() {}")
+                                .publish();
+
+    // Nothing is cached yet, so `bar` is compiled and uploaded. The path
+    // package `foo` never goes to the cache.
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_eq!(*log.lock().unwrap(), ["GET 404", "PUT 201", "PUT 201"]);
+
+    p.root().join("target").rm_rf();
+    log.lock().unwrap().clear();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_eq!(*log.lock().unwrap(), ["GET 200", "GET 200"]);
+    assert_that(p.process(&p.bin("foo")), execs().with_status(0));
+}
+
+#[test]
+fn corrupted_artifacts_are_not_used() {
+    let (addr, log, store) = cache_server();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file(".cargo/config", &format!(r#"
+            [build]
+            artifact-cache = "http://{}/cache/"
+        "#, addr));
+    Package::new("bar", "0.1.0").file("src/lib.rs", "pub fn bar() {}")
+                                .publish();
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    for (path, body) in store.lock().unwrap().iter_mut() {
+        if !path.ends_with(".sha256") {
+            let last = body.len() - 1;
+            body[last] ^= 1;
+        }
+    }
+
+    // The tarball doesn't match its checksum anymore, so `bar` is compiled
+    // again rather than unpacked
+    p.root().join("target").rm_rf();
+    log.lock().unwrap().clear();
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+warning: failed to fetch `[..]` from the artifact cache: the checksum of the \
+cached artifact doesn't match")
+                       .with_stderr_contains("[COMPILING] bar v0.1.0 [..]"));
+    assert_that(p.process(&p.bin("foo")), execs().with_status(0));
+}
+
+#[test]
+fn registry_deps_are_shared_through_the_cache_dir() {
+    let cache = project("cache");
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = "0.1.0"
+        "#)
+        .file("src/main.rs", "extern crate baz; fn main() { baz::baz() }");
+    let bar = project("bar")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = "0.1.0"
+        "#)
+        .file("src/main.rs", "extern crate baz; fn main() { baz::baz() }");
+    bar.build();
+    Package::new("baz", "0.1.0").file("src/lib.rs", "pub fn baz() {}")
+                                .publish();
+
+    assert_that(foo.cargo_process("build")
+                   .env("CARGO_BUILD_CACHE_DIR", cache.root()),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] baz v0.1.0"));
+    // `baz` is linked out of the cache directory without invoking rustc
+    assert_that(bar.cargo("build").arg("-v")
+                   .env("CARGO_BUILD_CACHE_DIR", cache.root()),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `[..]`
+[..] baz v0.1.0 (registry file://[..])
+[COMPILING] baz v0.1.0 (registry file://[..])
+[COMPILING] bar v0.0.1 ({dir})
+[RUNNING] `rustc [..] --crate-name bar [..]`
+", dir = bar.url())));
+    assert_that(bar.process(&bar.bin("bar")), execs().with_status(0));
+}
+
+#[test]
+fn proc_macros_are_shared_through_cargo_home() {
+    if !is_nightly() { return }
+
+    let manifest = |name: &str| format!(r#"
+        [project]
+        name = "{}"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        mac = "0.1.0"
+    "#, name);
+    let foo = project("foo")
+        .file("Cargo.toml", &manifest("foo"))
+        .file("src/lib.rs", "");
+    let bar = project("bar")
+        .file("Cargo.toml", &manifest("bar"))
+        .file("src/lib.rs", "");
+    bar.build();
+    Package::new("mac", "0.1.0")
+            .file("Cargo.toml", r#"
+                [package]
+                name = "mac"
+                version = "0.1.0"
+                authors = []
+
+                [lib]
+                proc-macro = true
+            "#)
+            .file("src/lib.rs", "")
+            .publish();
+
+    // The cache is opt-in
+    let cache = paths::home().join(".cargo/proc-macros");
+    assert_that(foo.cargo_process("build"), execs().with_status(0));
+    assert_that(&cache, is_not(existing_dir()));
+
+    foo.root().join("target").rm_rf();
+    assert_that(foo.cargo("build")
+                   .env("CARGO_BUILD_PROC_MACRO_CACHE", "true"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] mac v0.1.0 [..]"));
+    assert_that(&cache, existing_dir());
+    // `mac` is linked out of the cache without invoking rustc
+    assert_that(bar.cargo("build").arg("-v")
+                   .env("CARGO_BUILD_PROC_MACRO_CACHE", "true"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `[..]`
+[..] mac v0.1.0 (registry file://[..])
+[COMPILING] mac v0.1.0 (registry file://[..])
+[COMPILING] bar v0.0.1 ({dir})
+[RUNNING] `rustc [..] --crate-name bar [..]`
+", dir = bar.url())));
+}
@@ -940,6 +940,8 @@ fn test_bench_no_run() {
                 execs().with_status(0)
                        .with_stderr("\
 [COMPILING] foo v0.1.0 ([..])
+[EXECUTABLE] [..]target[..]release[..]bbaz-[..]
+[EXECUTABLE] [..]target[..]release[..]foo-[..]
 "));
 }
 
@@ -1028,3 +1030,67 @@ test bench_bar ... bench:           0 ns/iter (+/- 0)
 test result: ok. 0 passed; 0 failed; 0 ignored; 1 measured
 "));
 }
+
+#[test]
+fn save_and_compare_baseline() {
+    if !is_nightly() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", r#"
+            #![feature(test)]
+            extern crate test;
+
+            #[bench]
+            fn bench_hello(_b: &mut test::Bencher) {}
+        "#);
+
+    // Nothing is compiled without a baseline to compare against
+    assert_that(p.cargo_process("bench").arg("--baseline").arg("base"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] no baseline named `base` was saved, save one with `cargo bench \
+--save-baseline base`
+"));
+
+    assert_that(p.cargo("bench").arg("--save-baseline").arg("base"),
+                execs().with_status(0)
+                       .with_stdout_contains("\
+test bench_hello ... bench: [..] 0 ns/iter (+/- 0)")
+                       .with_stderr_contains("\
+[SAVED] 1 benchmark(s) as baseline `base`"));
+    assert_that(&p.root().join("target/benchmarks/base.json"), existing_file());
+
+    assert_that(p.cargo("bench").arg("--baseline").arg("base"),
+                execs().with_status(0));
+}
+
+#[test]
+fn regression_against_baseline_fails() {
+    if !is_nightly() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", r#"
+            #![feature(test)]
+            extern crate test;
+
+            #[bench]
+            fn bench_sum(b: &mut test::Bencher) {
+                b.iter(|| {
+                    (0..test::black_box(10000u64)).fold(0, |a, b| a ^ b)
+                })
+            }
+        "#)
+        .file("target/benchmarks/base.json", r#"
+            {"benchmarks": {"foo/foo/bench_sum": 1}}
+        "#);
+
+    assert_that(p.cargo_process("bench").arg("--baseline").arg("base"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[..]Regressed foo/foo/bench_sum: 1 -> [..] ns/iter (+[..]%)")
+                       .with_stderr_contains("\
+[ERROR] 1 benchmark(s) regressed by more than [..]% compared to baseline \
+`base`"));
+}
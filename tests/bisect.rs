@@ -0,0 +1,74 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn finds_first_breaking_version() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", r#"
+            extern crate bar;
+            fn main() { bar::f(); }
+        "#);
+    p.build();
+
+    Package::new("bar", "0.1.0").file("src/lib.rs", "pub fn f() {}").publish();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    Package::new("bar", "0.1.1").file("src/lib.rs", "pub fn f() {}").publish();
+    Package::new("bar", "0.1.2").file("src/lib.rs", "pub fn f() {}").publish();
+    Package::new("bar", "0.1.3").file("src/lib.rs", "pub fn g() {}").publish();
+    Package::new("bar", "0.1.4").file("src/lib.rs", "pub fn g() {}").publish();
+    Package::new("bar", "0.2.0").file("src/lib.rs", "pub fn f() {}").publish();
+
+    assert_that(p.cargo("bisect").arg("bar"),
+                execs().with_status(0)
+                       .with_stdout("\
+bar v0.1.3 is the first version which breaks, the last one which works is \
+v0.1.2
+")
+                       .with_stderr_contains("[..]Bad bar v0.1.3")
+                       .with_stderr_contains("[..]Good bar v0.1.2"));
+
+    let mut lock = String::new();
+    File::open(p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains("bar 0.1.0"), "lock file not restored:\n{}", lock);
+}
+
+#[test]
+fn bad_version_must_break() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    Package::new("bar", "0.1.0").publish();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    Package::new("bar", "0.1.1").publish();
+
+    assert_that(p.cargo("bisect").arg("bar"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] `bar` v0.1.1 doesn't break the build, so there's nothing to bisect"));
+}
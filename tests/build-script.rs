@@ -5,10 +5,10 @@ use std::fs::{self, File};
 use std::io::prelude::*;
 
 use cargotest::{rustc_host, is_nightly, sleep_ms};
-use cargotest::support::{project, execs};
+use cargotest::support::{project, execs, paths};
 use cargotest::support::paths::CargoPathExt;
 use cargotest::support::registry::Package;
-use hamcrest::{assert_that, existing_file, existing_dir};
+use hamcrest::{assert_that, existing_file, existing_dir, is_not};
 
 #[test]
 fn custom_build_script_failed() {
@@ -1842,6 +1842,61 @@ fn rebuild_only_on_explicit_paths() {
 "));
 }
 
+#[test]
+fn rebuild_only_on_explicit_env_vars() {
+    let p = project("a")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:rerun-if-env-changed=FOO");
+            }
+        "#);
+    p.build();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0));
+
+    // other files of the package don't matter anymore
+    sleep_ms(1000);
+    File::create(p.root().join("src/lib.rs")).unwrap();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] a v0.5.0 ([..])
+[RUNNING] `rustc src[..]lib.rs [..]`
+"));
+
+    // but the variable does, whether it's set, changed or removed
+    assert_that(p.cargo("build").arg("-v").env("FOO", "1"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] a v0.5.0 ([..])
+[RUNNING] `[..]build-script-build[..]`
+[RUNNING] `rustc src[..]lib.rs [..]`
+"));
+    assert_that(p.cargo("build").arg("-v").env("FOO", "1"),
+                execs().with_status(0).with_stderr("\
+[FRESH] a v0.5.0 ([..])
+"));
+    assert_that(p.cargo("build").arg("-v").env("FOO", "2"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] a v0.5.0 ([..])
+[RUNNING] `[..]build-script-build[..]`
+[RUNNING] `rustc src[..]lib.rs [..]`
+"));
+    assert_that(p.cargo("build").arg("-v").env_remove("FOO"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] a v0.5.0 ([..])
+[RUNNING] `[..]build-script-build[..]`
+[RUNNING] `rustc src[..]lib.rs [..]`
+"));
+}
+
 
 #[test]
 fn doctest_recieves_build_link_args() {
@@ -2056,6 +2111,179 @@ warning: bar
 "));
 }
 
+#[test]
+fn structured_diagnostics() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+
+            fn main() {
+                println!("cargo:rerun-if-env-changed=FAIL");
+                println!("cargo:warning=file=src/foo.c;line=3;message=old");
+                println!("cargo:warning=line=too long");
+                if env::var("FAIL").is_ok() {
+                    println!("cargo:error=file=src/foo.c;message=broken");
+                }
+            }
+        "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+warning: old
+  --> src/foo.c:3")
+                       .with_stderr_contains("warning: line=too long"));
+
+    assert_that(p.cargo("build").env("FAIL", "1"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] build script of `foo v0.5.0 ([..])` reported errors
+error: broken
+  --> src/foo.c"));
+}
+
+#[test]
+fn conflicting_native_versions() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            a = { path = "a" }
+            b = { path = "b" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("a/Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("a/src/lib.rs", "")
+        .file("a/build.rs", r#"
+            fn main() {
+                println!("cargo:native-version=z=1.2.8");
+            }
+        "#)
+        .file("b/Cargo.toml", r#"
+            [project]
+            name = "b"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("b/src/lib.rs", "")
+        .file("b/build.rs", r#"
+            fn main() {
+                println!("cargo:native-version=z=1.2.11");
+            }
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-j1"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] native library `z` is linked to with incompatible versions, but only \
+one of them can be linked in
+
+  1.2.[..] required by foo v0.5.0 ([..]) -> [..] v0.5.0 ([..])
+  1.2.[..] required by foo v0.5.0 ([..]) -> [..] v0.5.0 ([..])"));
+}
+
+#[cfg(unix)]
+#[test]
+fn build_script_wrapper_violations() {
+    use std::os::unix::prelude::*;
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:rustc-cfg=foo");
+            }
+        "#)
+        .file("sandbox.sh", r#"#!/bin/sh
+            echo "sandbox: out dir $1" >&2
+            if [ -n "$DENY" ]; then
+                echo "cargo:sandbox-violation=connect to crates.io denied"
+                exit 1
+            fi
+            shift 2
+            exec "$@"
+        "#);
+    p.build();
+    let sandbox = p.root().join("sandbox.sh");
+    let mut perms = fs::metadata(&sandbox).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&sandbox, perms).unwrap();
+
+    assert_that(p.cargo("build").arg("-v")
+                 .env("CARGO_BUILD_BUILD_SCRIPT_WRAPPER", &sandbox),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `[..]sandbox.sh [..]out -- [..]build-script-build`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc [..]--cfg foo[..]`"));
+
+    p.root().join("target").rm_rf();
+    assert_that(p.cargo("build")
+                 .env("CARGO_BUILD_BUILD_SCRIPT_WRAPPER", &sandbox)
+                 .env("DENY", "1"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] failed to run custom build command for `foo v0.5.0 ([..])`
+error: sandbox violation: connect to crates.io denied"));
+}
+
+#[test]
+fn sandboxed_build_script_cant_write_outside_out_dir() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+            use std::fs::File;
+            use std::path::Path;
+
+            fn main() {
+                let out = env::var("OUT_DIR").unwrap();
+                File::create(Path::new(&out).join("ok")).unwrap();
+                let root = env::var("CARGO_MANIFEST_DIR").unwrap();
+                File::create(Path::new(&root).join("escaped")).unwrap();
+            }
+        "#);
+
+    // Either the script is sandboxed and fails to write, or the build fails
+    // as it can't be sandboxed here, the script never runs unrestricted
+    assert_that(p.cargo_process("build").env("CARGO_BUILD_SANDBOX", "true"),
+                execs().with_status(101));
+    assert_that(&p.root().join("escaped"), is_not(existing_file()));
+}
+
 #[test]
 fn warnings_hidden_for_upstream() {
     Package::new("bar", "0.1.0")
@@ -2235,3 +2463,158 @@ fn rustc_and_rustdoc_set_correctly() {
     assert_that(build.cargo_process("bench"),
                 execs().with_status(0));
 }
+
+#[test]
+fn out_dir_over_quota_warns() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+            use std::fs::File;
+            use std::io::Write;
+            use std::path::Path;
+
+            fn main() {
+                let out = env::var("OUT_DIR").unwrap();
+                let mut f = File::create(Path::new(&out).join("big")).unwrap();
+                f.write_all(&vec![0; 2 * 1024 * 1024]).unwrap();
+            }
+        "#);
+
+    assert_that(p.cargo_process("build")
+                 .env("CARGO_BUILD_OUT_DIR_QUOTA", "1"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] foo v0.5.0 ([..])
+warning: the build script of `foo v0.5.0 ([..])` left 2 MB in its OUT_DIR, \
+more than the 1 MB allowed by `build.out-dir-quota`
+"));
+
+    // The size is only looked at when the build script runs
+    assert_that(p.cargo("build").env("CARGO_BUILD_OUT_DIR_QUOTA", "1"),
+                execs().with_status(0).with_stderr(""));
+    assert_that(p.cargo("build").env("CARGO_BUILD_OUT_DIR_QUOTA", "0"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] build.out-dir-quota must be positive, but found 0 in the environment
+"));
+}
+
+#[test]
+fn build_scripts_outside_workspace_need_allowing() {
+    Package::new("bar", "0.1.0")
+            .file("build.rs", "fn main() {}")
+            .file("Cargo.toml", r#"
+                [project]
+                name = "bar"
+                version = "0.1.0"
+                authors = []
+                build = "build.rs"
+            "#)
+            .file("src/lib.rs", "")
+            .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [build-scripts]
+            allow = []
+        "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] the build scripts of these packages aren't allowed to run:
+  bar v0.1.0 (registry [..])
+"));
+
+    File::create(p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [build-scripts]
+        allow = ["bar"]
+    "#).unwrap();
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    File::create(p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [build-scripts]
+        deny-all = true
+    "#).unwrap();
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] the build scripts of these packages aren't allowed to run:
+  bar v0.1.0 (registry [..])
+"));
+}
+
+#[test]
+fn no_build_script_runs_when_one_is_denied() {
+    // `aaa` has no dependencies, so it's downloaded and ready to build first
+    let marker = paths::root().join("aaa-ran");
+    Package::new("aaa", "0.1.0")
+            .file("build.rs", &format!(r#"
+                fn main() {{ std::fs::File::create({:?}).unwrap(); }}
+            "#, marker))
+            .file("Cargo.toml", r#"
+                [project]
+                name = "aaa"
+                version = "0.1.0"
+                authors = []
+                build = "build.rs"
+            "#)
+            .file("src/lib.rs", "")
+            .publish();
+    Package::new("zzz", "0.1.0")
+            .file("build.rs", "fn main() {}")
+            .file("Cargo.toml", r#"
+                [project]
+                name = "zzz"
+                version = "0.1.0"
+                authors = []
+                build = "build.rs"
+            "#)
+            .file("src/lib.rs", "")
+            .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            aaa = "0.1"
+            zzz = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [build-scripts]
+            allow = ["aaa"]
+        "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] the build scripts of these packages aren't allowed to run:
+  zzz v0.1.0 (registry [..])
+"));
+    assert_that(&marker, is_not(existing_file()));
+}
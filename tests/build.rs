@@ -2263,3 +2263,332 @@ fn no_warn_about_package_metadata() {
                 execs().with_status(0)
                        .with_stderr("[..] foo v0.0.1 ([..])\n"));
 }
+
+#[test]
+fn compiler_json_error_format() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("bar/build.rs", r#"
+            fn main() { println!("cargo:rustc-cfg=xyz"); }
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {} fn dead() {}");
+
+    assert_that(p.cargo_process("build").arg("--message-format").arg("json"),
+                execs().with_status(0).with_json(r#"
+    {
+        "reason":"compiler-artifact",
+        "package_id":"bar 0.5.0 ([..])",
+        "target":{"kind":["custom-build"],"name":"build-script-build",
+                  "src_path":"[..]build.rs"},
+        "profile":"{...}",
+        "filenames":"{...}",
+        "fresh":false
+    }
+
+    {
+        "reason":"build-script-executed",
+        "package_id":"bar 0.5.0 ([..])",
+        "linked_libs":[],
+        "linked_paths":[],
+        "cfgs":["xyz"]
+    }
+
+    {
+        "reason":"compiler-message",
+        "package_id":"bar 0.5.0 ([..])",
+        "target":{"kind":["lib"],"name":"bar","src_path":"[..]lib.rs"},
+        "message":"{...}"
+    }
+
+    {
+        "reason":"compiler-artifact",
+        "package_id":"bar 0.5.0 ([..])",
+        "target":{"kind":["lib"],"name":"bar","src_path":"[..]lib.rs"},
+        "profile":"{...}",
+        "filenames":"{...}",
+        "fresh":false
+    }
+
+    {
+        "reason":"compiler-artifact",
+        "package_id":"foo 0.5.0 ([..])",
+        "target":{"kind":["bin"],"name":"foo","src_path":"[..]main.rs"},
+        "profile":"{...}",
+        "filenames":"{...}",
+        "fresh":false
+    }
+"#));
+
+    // Nothing is compiled the second time around, so there are no
+    // diagnostics, but the artifacts and build script output are reported
+    assert_that(p.cargo("build").arg("--message-format").arg("json"),
+                execs().with_status(0).with_json(r#"
+    {
+        "reason":"compiler-artifact",
+        "package_id":"bar 0.5.0 ([..])",
+        "target":{"kind":["custom-build"],"name":"build-script-build",
+                  "src_path":"[..]build.rs"},
+        "profile":"{...}",
+        "filenames":"{...}",
+        "fresh":true
+    }
+
+    {
+        "reason":"build-script-executed",
+        "package_id":"bar 0.5.0 ([..])",
+        "linked_libs":[],
+        "linked_paths":[],
+        "cfgs":["xyz"]
+    }
+
+    {
+        "reason":"compiler-artifact",
+        "package_id":"bar 0.5.0 ([..])",
+        "target":{"kind":["lib"],"name":"bar","src_path":"[..]lib.rs"},
+        "profile":"{...}",
+        "filenames":"{...}",
+        "fresh":true
+    }
+
+    {
+        "reason":"compiler-artifact",
+        "package_id":"foo 0.5.0 ([..])",
+        "target":{"kind":["bin"],"name":"foo","src_path":"[..]main.rs"},
+        "profile":"{...}",
+        "filenames":"{...}",
+        "fresh":true
+    }
+"#));
+}
+
+#[test]
+fn wrong_message_format_option() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build").arg("--message-format").arg("XML"),
+                execs().with_status(1)
+                       .with_stderr_contains("\
+[ERROR] Could not match 'xml' with any of the allowed variants: [\"Human\", \"Json\"]"));
+}
+
+#[test]
+fn quiet_build_prints_diagnostics_and_summary() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("build.rs", r#"
+            fn main() { println!("cargo:warning=from the build script"); }
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build").arg("-q"),
+                execs().with_status(0).with_stdout("").with_stderr("\
+[WARNING] from the build script
+[FINISHED] debug target(s) in [..] secs
+"));
+
+    File::create(&p.root().join("src/lib.rs")).unwrap()
+        .write_all(b"invalid rust code").unwrap();
+    assert_that(p.cargo("build").arg("-q").arg("--release"),
+                execs().with_status(101)
+                       .with_stderr_contains("[..]invalid rust code[..]")
+                       .with_stderr_contains("\
+[FAILED] release target(s) in [..] secs"));
+}
+
+#[test]
+#[cfg(unix)]
+fn compiler_artifact_lists_output_files() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+
+            [lib]
+            crate-type = ["rlib", "dylib"]
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("build").arg("--message-format").arg("json"),
+                execs().with_status(0).with_json(&format!(r#"
+    {{
+        "reason":"compiler-artifact",
+        "package_id":"bar 0.5.0 ([..])",
+        "target":{{"kind":["rlib","dylib"],"name":"bar",
+                   "src_path":"[..]lib.rs"}},
+        "profile":"{{...}}",
+        "filenames":["{dir}/target/debug/deps/libbar[..].rlib",
+                     "{dir}/target/debug/deps/libbar[..]{suffix}"],
+        "fresh":false
+    }}
+
+    {{
+        "reason":"compiler-artifact",
+        "package_id":"foo 0.5.0 ([..])",
+        "target":{{"kind":["bin"],"name":"foo","src_path":"[..]main.rs"}},
+        "profile":"{{...}}",
+        "filenames":["{dir}/target/debug/foo"],
+        "fresh":false
+    }}
+"#, dir = p.root().display(), suffix = env::consts::DLL_SUFFIX)));
+}
+
+#[test]
+fn deny_warnings_only_applies_to_members() {
+    let bar = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "fn dead() {}");
+    bar.build();
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "../bar"
+        "#)
+        .file("src/lib.rs", "extern crate bar; fn dead() {}")
+        .file(".cargo/config", r#"
+            [build]
+            warnings = "deny"
+        "#);
+
+    assert_that(foo.cargo_process("build").arg("-v"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]lib.rs --crate-name foo [..]-Dwarnings[..]`")
+                       .with_stderr_contains("\
+[ERROR] Could not compile `foo`."));
+
+    // The warning in `bar` isn't an error as it's not part of the workspace
+    File::create(&foo.root().join("src/lib.rs")).unwrap()
+        .write_all(b"extern crate bar;").unwrap();
+    assert_that(foo.cargo("build"), execs().with_status(0));
+}
+
+#[test]
+fn bad_warnings_config() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build").env("CARGO_BUILD_WARNINGS", "error"),
+                execs().with_status(101).with_stderr("\
+[ERROR] build.warnings must be `allow`, `warn` or `deny`, but found `error` \
+in the environment
+"));
+}
+
+#[test]
+fn build_plan_lists_invocations() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("build.rs", "fn main() {}")
+        .file("src/main.rs", "extern crate bar; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("build").arg("--build-plan"),
+                execs().with_status(0)
+                       .with_stderr("")
+                       .with_stdout_contains("{\"invocations\":[[..]]}")
+                       .with_stdout_contains("[..]\"name\":\"bar\"[..]\
+                                              \"mode\":\"build\"[..]")
+                       .with_stdout_contains("[..]\"mode\":\
+                                              \"run-build-script\",\
+                                              \"kind\":\"host\",\
+                                              \"fresh\":false[..]")
+                       .with_stdout_contains("[..]\"args\":[\"src[..]main.rs\",\
+                                              [..]\"--crate-name\",\"foo\"[..]"));
+    assert_that(&p.bin("foo"), is_not(existing_file()));
+}
+
+#[test]
+fn progress_needs_a_terminal() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "extern crate bar;")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [term]
+            progress = true
+        "#);
+
+    // Output which isn't going to a terminal keeps a line for each package
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[COMPILING] bar v0.0.1 ({url}/bar)
+[COMPILING] foo v0.0.1 ({url})
+[FINISHED] debug target(s) in [..] secs
+", url = p.url())));
+}
@@ -0,0 +1,179 @@
+#[macro_use]
+extern crate cargotest;
+extern crate git2;
+extern crate hamcrest;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::{project, execs, git, paths};
+use hamcrest::assert_that;
+
+fn read(path: &std::path::Path) -> String {
+    let mut s = String::new();
+    t!(t!(File::open(path)).read_to_string(&mut s));
+    s
+}
+
+#[test]
+fn bump_minor() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            # keep this comment
+            version = "0.1.3"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("bump").arg("minor"),
+                execs().with_status(0).with_stderr("\
+[BUMPING] foo v0.1.3 -> v0.2.0
+"));
+
+    let manifest = read(&p.root().join("Cargo.toml"));
+    assert!(manifest.contains("version = \"0.2.0\""));
+    assert!(manifest.contains("# keep this comment"));
+}
+
+#[test]
+fn bump_explicit_version() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.3"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("bump").arg("1.0.0-beta.1"),
+                execs().with_status(0));
+    assert!(read(&p.root().join("Cargo.toml"))
+                .contains("version = \"1.0.0-beta.1\""));
+
+    assert_that(p.cargo("bump").arg("0.9.0"),
+                execs().with_status(101).with_stderr("\
+[ERROR] new version `0.9.0` of `foo` must be greater than the current \
+version `1.0.0-beta.1`
+"));
+
+    assert_that(p.cargo("bump").arg("huge"),
+                execs().with_status(101).with_stderr("\
+[ERROR] invalid version `huge`, expected `major`, `minor`, `patch` or a \
+semver version
+"));
+}
+
+#[test]
+fn bump_updates_workspace_path_deps() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [workspace]
+            members = ["bar", "baz"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+            workspace = ".."
+
+            [dependencies]
+            foo = { path = "..", version = "^0.1.0" }
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+            workspace = ".."
+
+            [dev-dependencies.foo]
+            path = ".."
+            version = "0.1"
+        "#)
+        .file("baz/src/lib.rs", "");
+
+    assert_that(p.cargo_process("bump").arg("major"),
+                execs().with_status(0).with_stderr("\
+[BUMPING] foo v0.1.0 -> v1.0.0
+[UPDATING] bar dependency on foo
+[UPDATING] baz dependency on foo
+"));
+
+    assert!(read(&p.root().join("bar/Cargo.toml"))
+                .contains("foo = { path = \"..\", version = \"^1.0.0\" }"));
+    assert!(read(&p.root().join("baz/Cargo.toml"))
+                .contains("version = \"1.0.0\""));
+    assert!(read(&p.root().join("Cargo.lock"))
+                .contains("\"foo 1.0.0\""));
+}
+
+#[test]
+fn bump_leaves_paths_mentioning_version_alone() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [workspace]
+            members = ["version-utils"]
+
+            [dependencies]
+            version-utils = { path = "version-utils", version = "0.1.0" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("version-utils/Cargo.toml", r#"
+            [package]
+            name = "version-utils"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("version-utils/src/lib.rs", "");
+
+    assert_that(p.cargo_process("bump").arg("minor")
+                 .arg("--manifest-path").arg("version-utils/Cargo.toml"),
+                execs().with_status(0).with_stderr("\
+[BUMPING] version-utils v0.1.0 -> v0.2.0
+[UPDATING] foo dependency on version-utils
+"));
+
+    assert!(read(&p.root().join("Cargo.toml"))
+                .contains("version-utils = { path = \"version-utils\", \
+                           version = \"0.2.0\" }"));
+}
+
+#[test]
+fn bump_commit_and_tag() {
+    let repo = git::repo(&paths::root().join("foo"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    repo.build();
+
+    assert_that(cargotest::cargo_process().arg("bump").arg("patch").arg("--tag")
+                                          .cwd(repo.root()),
+                execs().with_status(0));
+
+    let repo = t!(git2::Repository::open(repo.root()));
+    let head = t!(repo.find_commit(t!(repo.head()).target().unwrap()));
+    assert_eq!(head.message(), Some("Bump foo to 0.1.1"));
+    t!(repo.revparse_single("v0.1.1"));
+    let status = t!(repo.statuses(None));
+    assert!(status.iter().all(|s| s.status() == git2::STATUS_IGNORED));
+}
@@ -0,0 +1,103 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{project, execs};
+use hamcrest::{assert_that, existing_file, is_not};
+
+#[test]
+fn check_success() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("check").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]src[..]lib.rs --crate-name bar [..]\
+--emit=dep-info,metadata [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]main.rs --crate-name foo [..]\
+--emit=dep-info,metadata [..]--extern bar=[..]libbar-[..].rmeta`"));
+    assert_that(&p.bin("foo"), is_not(existing_file()));
+}
+
+#[test]
+fn check_fail() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() { let x: u32 = \"\"; }");
+
+    assert_that(p.cargo_process("check"),
+                execs().with_status(101)
+                       .with_stderr_contains("[..]mismatched types[..]"));
+}
+
+#[test]
+fn check_and_build_do_not_rebuild_each_other() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}")
+        .file("src/main.rs", "extern crate foo; fn main() { foo::foo() }");
+
+    assert_that(p.cargo_process("check"),
+                execs().with_status(0)
+                       .with_stderr("[CHECKING] foo v0.0.1 ([..])\n"));
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr("[COMPILING] foo v0.0.1 ([..])\n"));
+    assert_that(p.cargo("check"),
+                execs().with_status(0).with_stderr(""));
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(""));
+}
+
+#[test]
+fn check_builds_build_scripts() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:rustc-cfg=from_build_script");
+            }
+        "#)
+        .file("src/lib.rs", r#"
+            #[cfg(from_build_script)]
+            pub fn foo() {}
+
+            pub fn bar() { foo() }
+        "#);
+
+    assert_that(p.cargo_process("check"),
+                execs().with_status(0));
+}
@@ -233,3 +233,143 @@ fn registry() {
     assert_that(p.cargo("build"),
                 execs().with_status(0));
 }
+
+#[test]
+fn clean_out_dirs() {
+    let build = r#"
+        use std::env;
+        use std::fs::File;
+        use std::io::Write;
+        use std::path::Path;
+
+        fn main() {
+            let out = env::var("OUT_DIR").unwrap();
+            let mut f = File::create(Path::new(&out).join("big")).unwrap();
+            f.write_all(&vec![0; 2 * 1024 * 1024]).unwrap();
+        }
+    "#;
+    for version in ["0.1.0", "0.1.1"].iter() {
+        Package::new("bar", version)
+                .file("Cargo.toml", &format!(r#"
+                    [project]
+                    name = "bar"
+                    version = "{}"
+                    authors = []
+                    build = "build.rs"
+                "#, version))
+                .file("build.rs", build)
+                .file("src/lib.rs", "")
+                .publish();
+    }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("generate-lockfile"), execs().with_status(0));
+    assert_that(p.cargo("update").arg("-p").arg("bar")
+                 .arg("--precise").arg("0.1.0"),
+                execs().with_status(0));
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    // Updating `bar` leaves the build directory of 0.1.0 behind
+    assert_that(p.cargo("update"), execs().with_status(0));
+    assert_that(p.cargo("build").env("CARGO_BUILD_OUT_DIR_QUOTA", "1"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] 1 build script directories of packages which aren't used anymore \
+take up [..] MB, run `cargo clean --out-dirs` to remove them"));
+
+    assert_that(p.cargo("clean").arg("--out-dirs"),
+                execs().with_status(0)
+                       .with_stderr("\
+[REMOVING] [..]build[..]bar-[..]
+"));
+    assert_that(p.cargo("build").env("CARGO_BUILD_OUT_DIR_QUOTA", "1"),
+                execs().with_status(0).with_stderr(""));
+    assert_that(p.cargo("clean").arg("--out-dirs"),
+                execs().with_status(0).with_stderr(""));
+}
+
+#[test]
+fn clean_out_dirs_shared_target_dir() {
+    for version in ["0.1.0", "0.1.1"].iter() {
+        Package::new("bar", version)
+                .file("Cargo.toml", &format!(r#"
+                    [project]
+                    name = "bar"
+                    version = "{}"
+                    authors = []
+                    build = "build.rs"
+                "#, version))
+                .file("build.rs", "fn main() {}")
+                .file("src/lib.rs", "")
+                .publish();
+    }
+
+    let p = project("foo")
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("foo/src/main.rs", "fn main() {}")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("baz/src/main.rs", "fn main() {}");
+    p.build();
+    let target = p.root().join("target");
+
+    for dir in ["foo", "baz"].iter() {
+        assert_that(p.cargo("generate-lockfile").cwd(p.root().join(dir)),
+                    execs().with_status(0));
+        assert_that(p.cargo("update").cwd(p.root().join(dir))
+                     .arg("-p").arg("bar").arg("--precise").arg("0.1.0"),
+                    execs().with_status(0));
+        assert_that(p.cargo("build").cwd(p.root().join(dir))
+                     .env("CARGO_TARGET_DIR", &target),
+                    execs().with_status(0));
+    }
+
+    // `baz` still uses `bar` 0.1.0, so `foo` leaves its directory alone
+    assert_that(p.cargo("update").cwd(p.root().join("foo")),
+                execs().with_status(0));
+    assert_that(p.cargo("build").cwd(p.root().join("foo"))
+                 .env("CARGO_TARGET_DIR", &target),
+                execs().with_status(0));
+    assert_that(p.cargo("clean").arg("--out-dirs")
+                 .cwd(p.root().join("foo")).env("CARGO_TARGET_DIR", &target),
+                execs().with_status(0).with_stderr(""));
+
+    // Once `baz` moves on as well, it's the one removing it
+    assert_that(p.cargo("update").cwd(p.root().join("baz")),
+                execs().with_status(0));
+    assert_that(p.cargo("build").cwd(p.root().join("baz"))
+                 .env("CARGO_TARGET_DIR", &target),
+                execs().with_status(0));
+    assert_that(p.cargo("clean").arg("--out-dirs")
+                 .cwd(p.root().join("baz")).env("CARGO_TARGET_DIR", &target),
+                execs().with_status(0)
+                       .with_stderr("\
+[REMOVING] [..]build[..]bar-[..]
+"));
+}
@@ -0,0 +1,87 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn cost_includes_exclusive_dependencies() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = { path = "../baz" }
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "");
+
+    // Nothing has been built yet, so there are no compile times
+    assert_that(p.cargo_process("cost"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]-        2 [..]  \
+                                              bar v0.0.1 ([..])")
+                       .with_stdout_contains("[..]-        1 [..]  \
+                                              baz v0.0.1 ([..])"));
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("cost"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]s        2 [..]  \
+                                              bar v0.0.1 ([..])")
+                       .with_stdout_contains("[..]s        1 [..]  \
+                                              baz v0.0.1 ([..])"));
+
+    assert_that(p.cargo("cost").arg("--sort").arg("weight"),
+                execs().with_status(101).with_stderr("\
+[ERROR] unknown sort key `weight`, expected `time`, `size`, `download` or `memory`
+"));
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[test]
+fn cost_includes_peak_memory() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(p.cargo("cost").arg("--sort").arg("memory"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]s [..]MiB [..]s        1 \
+                                              [..]  bar v0.0.1 ([..])"));
+}
@@ -0,0 +1,40 @@
+extern crate cargo;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::panic;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cargo::util::crash_report::install_panic_hook;
+
+// The panic hook is global to the process, so this is the only test here.
+#[test]
+fn panic_writes_report() {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let message = format!("panic for the crash report test {}{:09}",
+                          now.as_secs(), now.subsec_nanos());
+    install_panic_hook();
+    let result = panic::catch_unwind(|| panic!("{}", message));
+    drop(panic::take_hook());
+    assert!(result.is_err());
+
+    let mut found = false;
+    for entry in fs::read_dir(env::temp_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if !name.starts_with("cargo-panic-") {
+            continue
+        }
+        let mut contents = String::new();
+        File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+        if contents.contains(&message) {
+            assert!(contents.starts_with("Cargo panicked\n"), contents);
+            assert!(contents.contains("## Cargo version"), contents);
+            assert!(contents.contains("crash-report.rs"), contents);
+            fs::remove_file(&path).unwrap();
+            found = true;
+        }
+    }
+    assert!(found);
+}
@@ -531,9 +531,50 @@ fn no_cross_doctests() {
                        .with_stderr(&format!("\
 [COMPILING] foo v0.0.0 ({foo})
 [RUNNING] target[..]{triple}[..]foo-[..]
+[WARNING] skipping doctests as they can't be run for `{triple}` without \
+`target.{triple}.runner`
 ", foo = p.url(), triple = target)));
 }
 
+#[test]
+fn cross_doctests_through_runner() {
+    if disabled() { return }
+
+    let target = alternate();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            authors = []
+            version = "0.0.0"
+        "#)
+        .file("src/lib.rs", r#"
+            //! ```
+            //! extern crate foo;
+            //! assert!(true);
+            //! ```
+        "#)
+        .file(".cargo/config", &format!(r#"
+            [target.{}]
+            runner = "env RUNNER=1"
+        "#, target));
+
+    if is_nightly() {
+        assert_that(p.cargo_process("test").arg("--target").arg(&target)
+                     .arg("-v"),
+                    execs().with_status(0)
+                           .with_stderr_contains(&format!("\
+[RUNNING] `rustdoc --test [..]--target {} -Z unstable-options \
+--runtool env --runtool-arg RUNNER=1 [..]`", target)));
+    } else {
+        assert_that(p.cargo_process("test").arg("--target").arg(&target),
+                    execs().with_status(0)
+                           .with_stderr_contains(&format!("\
+[WARNING] skipping doctests as running them through `target.{}.runner` \
+requires a nightly rustdoc", target)));
+    }
+}
+
 #[test]
 fn simple_cargo_run() {
     if disabled() { return }
@@ -965,3 +1006,108 @@ fn platform_specific_variables_reflected_in_build_scripts() {
     assert_that(p.cargo_process("build").arg("-v").arg("--target").arg(&target),
                 execs().with_status(0));
 }
+
+#[test]
+fn multiple_targets() {
+    if disabled() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("build.rs", "fn main() {}")
+        .file("src/main.rs", "fn main() {}");
+
+    let target = alternate();
+    assert_that(p.cargo_process("build")
+                 .arg("--target").arg(&target)
+                 .arg("--target").arg(host()),
+                execs().with_status(0));
+    assert_that(&p.target_bin(&target, "foo"), existing_file());
+    assert_that(&p.target_bin(&host(), "foo"), existing_file());
+
+    assert_that(p.cargo("build")
+                 .arg("--target").arg(&target)
+                 .arg("--target").arg(host()),
+                execs().with_status(0).with_stderr(""));
+}
+
+#[test]
+fn multiple_targets_built_together() {
+    if disabled() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("build.rs", "fn main() {}")
+        .file("src/main.rs", "fn main() {}");
+
+    let target = alternate();
+    assert_that(p.cargo_process("build").arg("-q")
+                 .arg("--target").arg(&target)
+                 .arg("--target").arg(host()),
+                execs().with_status(0).with_stderr("\
+[FINISHED] debug target(s) in [..]
+"));
+    assert_that(&p.target_bin(&target, "foo"), existing_file());
+    assert_that(&p.target_bin(&host(), "foo"), existing_file());
+}
+
+#[test]
+fn build_dep_features_separate_from_target() {
+    if disabled() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            build = "build.rs"
+
+            [dependencies.d1]
+            path = "d1"
+            features = ["target-only"]
+
+            [build-dependencies.d1]
+            path = "d1"
+        "#)
+        .file("build.rs", r#"
+            extern crate d1;
+            fn main() { assert!(!d1::target_only()); }
+        "#)
+        .file("src/main.rs", r#"
+            extern crate d1;
+            fn main() { assert!(d1::target_only()); }
+        "#)
+        .file("d1/Cargo.toml", r#"
+            [package]
+            name = "d1"
+            version = "0.0.0"
+            authors = []
+
+            [features]
+            target-only = []
+        "#)
+        .file("d1/src/lib.rs", r#"
+            #[cfg(feature = "target-only")]
+            pub fn target_only() -> bool { true }
+            #[cfg(not(feature = "target-only"))]
+            pub fn target_only() -> bool { false }
+        "#);
+
+    let target = alternate();
+    assert_that(p.cargo_process("build").arg("--target").arg(&target),
+                execs().with_status(0));
+    assert_that(process(&p.target_bin(&target, "foo")),
+                execs().with_status(0));
+}
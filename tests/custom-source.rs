@@ -0,0 +1,132 @@
+extern crate cargo;
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use cargo::core::{ColorConfig, Source, SourceFactory, SourceId, Verbosity};
+use cargo::core::Workspace;
+use cargo::ops::{self, CompileFilter, CompileMode, CompileOptions};
+use cargo::ops::{MessageFormat, Packages};
+use cargo::sources::PathSource;
+use cargo::util::{CargoResult, Config};
+use cargotest::support::{project, paths};
+use hamcrest::{assert_that, existing_file};
+
+/// Serves the package in the directory its URL points to, like an artifact
+/// store would serve the packages it holds.
+struct LocalDir;
+
+impl SourceFactory for LocalDir {
+    fn load<'a>(&self, id: &SourceId, config: &'a Config)
+                -> CargoResult<Box<Source + 'a>> {
+        let path = id.url().to_file_path().unwrap();
+        Ok(Box::new(PathSource::new(&path, id, config)))
+    }
+}
+
+fn config(cwd: &Path) -> Config {
+    let shell = cargo::shell(Verbosity::Quiet, ColorConfig::Never);
+    let config = Config::new(shell, cwd.to_path_buf(),
+                             paths::home().join(".cargo")).unwrap();
+    config.register_source_kind("local", LocalDir).unwrap();
+    config
+}
+
+fn build(config: &Config) -> CargoResult<()> {
+    let ws = try!(Workspace::new(&config.cwd().join("Cargo.toml"), config));
+    let opts = CompileOptions {
+        config: config,
+        jobs: None,
+        targets: Vec::new(),
+        features: &[],
+        no_default_features: false,
+        spec: Packages::Packages(&[]),
+        filter: CompileFilter::Everything,
+        exclude: ops::ExcludeTargets::default(),
+        exec_engine: None,
+        release: false,
+        profile: None,
+        mode: CompileMode::Build,
+        target_rustdoc_args: None,
+        target_rustc_args: None,
+        target_crate_types: None,
+        build_plan: false,
+        coverage: false,
+        message_format: MessageFormat::Human,
+    };
+    try!(ops::compile(&ws, &opts));
+    Ok(())
+}
+
+#[test]
+fn build_from_registered_kind() {
+    let bar = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn bar() -> i32 { 1 }");
+    bar.build();
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", r#"
+            extern crate bar;
+            fn main() { assert_eq!(bar::bar(), 1); }
+        "#)
+        .file(".cargo/config", &format!(r#"
+            [registry]
+            index = "local+{}"
+        "#, bar.url()));
+    foo.build();
+
+    build(&config(&foo.root())).unwrap();
+    assert_that(&foo.bin("foo"), existing_file());
+
+    let mut lock = String::new();
+    File::open(foo.root().join("Cargo.lock")).unwrap()
+        .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains(&format!("\"bar 0.1.0 (local+{})\"", bar.url())),
+            "{}", lock);
+
+    // The lock file is read back through the registered kind as well
+    build(&config(&foo.root())).unwrap();
+}
+
+#[test]
+fn unregistered_kind() {
+    let foo = project("foo");
+    foo.build();
+    let source = SourceId::for_custom("s3", &foo.url());
+    let err = source.load(&config(&foo.root())).err().unwrap();
+    assert_eq!(err.to_string(),
+               format!("no kind of source named `s3` is known to this \
+                        version of Cargo, which `s3+{}` needs", foo.url()));
+}
+
+#[test]
+fn unregistered_kind_in_registry_index() {
+    let foo = project("foo")
+        .file(".cargo/config", r#"
+            [registry]
+            index = "s3+https://example.com/index"
+        "#);
+    foo.build();
+    let err = SourceId::for_central(&config(&foo.root())).err().unwrap();
+    assert_eq!(err.to_string(),
+               "no kind of source named `s3` is known to this version of \
+                Cargo, which `registry.index` needs for \
+                `s3+https://example.com/index`");
+}
@@ -0,0 +1,170 @@
+#![cfg(unix)]
+
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use cargotest::sleep_ms;
+use cargotest::support::{project, execs, ProjectBuilder};
+use hamcrest::{assert_that, existing_file};
+
+fn request(socket: &Path, line: &str) -> String {
+    let mut stream = None;
+    for _ in 0..100 {
+        match UnixStream::connect(socket) {
+            Ok(s) => { stream = Some(s); break }
+            Err(_) => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+    let mut stream = stream.expect("daemon never started listening");
+    stream.write_all(line.as_bytes()).unwrap();
+    stream.write_all(b"\n").unwrap();
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response).unwrap();
+    response
+}
+
+fn start(p: &ProjectBuilder, socket: &Path) -> Child {
+    let mut daemon = p.cargo("daemon").arg("--socket").arg(socket)
+                      .build_command();
+    daemon.stdout(Stdio::piped()).stderr(Stdio::piped());
+    daemon.spawn().unwrap()
+}
+
+#[test]
+fn build_and_metadata_requests() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    let socket = p.root().join("daemon.sock");
+    let daemon = start(&p, &socket);
+
+    let manifest = p.root().join("Cargo.toml");
+    let response = request(&socket, &format!(
+        "{{\"command\": \"build\", \"manifest_path\": \"{}\"}}",
+        manifest.display()));
+    assert_eq!(response.trim(), "{\"success\":true}");
+    assert_that(&p.bin("foo"), existing_file());
+    let mode = fs::metadata(&socket).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let response = request(&socket, &format!(
+        "{{\"command\": \"check\", \"manifest_path\": \"{}\"}}",
+        manifest.display()));
+    assert_eq!(response.trim(), "{\"success\":true}");
+
+    let response = request(&socket, &format!(
+        "{{\"command\": \"metadata\", \"manifest_path\": \"{}\"}}",
+        manifest.display()));
+    assert!(response.starts_with("{\"success\":true,\"metadata\":"),
+            "bad response: {}", response);
+    assert!(response.contains("\"name\":\"foo\""), "bad response: {}", response);
+
+    let response = request(&socket, "{\"command\": \"frobnicate\"}");
+    assert!(response.contains("unknown command `frobnicate`"),
+            "bad response: {}", response);
+
+    let response = request(&socket, "{\"command\": \"shutdown\"}");
+    assert_eq!(response.trim(), "{\"success\":true}");
+
+    assert_that(daemon.wait_with_output().unwrap(), execs().with_status(0));
+    assert!(!socket.exists());
+}
+
+#[test]
+fn modified_manifest_is_reloaded() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+    p.build();
+
+    let socket = p.root().join("daemon.sock");
+    let daemon = start(&p, &socket);
+
+    let manifest = p.root().join("Cargo.toml");
+    let build = format!("{{\"command\": \"build\", \"manifest_path\": \"{}\"}}",
+                        manifest.display());
+    let response = request(&socket, &build);
+    assert_eq!(response.trim(), "{\"success\":true}");
+
+    sleep_ms(1000);
+    File::create(&manifest).unwrap().write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        bar = { path = "bar" }
+    "#).unwrap();
+    File::create(p.root().join("src/main.rs")).unwrap().write_all(br#"
+        extern crate bar;
+        fn main() {}
+    "#).unwrap();
+    let response = request(&socket, &build);
+    assert_eq!(response.trim(), "{\"success\":true}");
+
+    let response = request(&socket, "{\"command\": \"shutdown\"}");
+    assert_eq!(response.trim(), "{\"success\":true}");
+    assert_that(daemon.wait_with_output().unwrap(), execs().with_status(0));
+}
+
+#[test]
+fn dropped_connection_is_survived() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    let socket = p.root().join("daemon.sock");
+    let daemon = start(&p, &socket);
+
+    let manifest = p.root().join("Cargo.toml");
+    let build = format!("{{\"command\": \"build\", \"manifest_path\": \"{}\"}}",
+                        manifest.display());
+    request(&socket, "{\"command\": \"frobnicate\"}");
+    // Goes away before the response could be written
+    for _ in 0..10 {
+        let mut stream = UnixStream::connect(&socket).unwrap();
+        stream.write_all(build.as_bytes()).unwrap();
+        stream.write_all(b"\n").unwrap();
+    }
+    let response = request(&socket, &build);
+    assert_eq!(response.trim(), "{\"success\":true}");
+
+    let response = request(&socket, "{\"command\": \"shutdown\"}");
+    assert_eq!(response.trim(), "{\"success\":true}");
+    assert_that(daemon.wait_with_output().unwrap(), execs().with_status(0));
+}
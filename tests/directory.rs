@@ -0,0 +1,254 @@
+extern crate cargo;
+#[macro_use]
+extern crate cargotest;
+extern crate git2;
+extern crate hamcrest;
+extern crate rustc_serialize;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use cargo::util::Sha256;
+use cargotest::support::{project, execs, git, paths, ProjectBuilder};
+use hamcrest::assert_that;
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json;
+
+fn setup() {
+    let root = paths::root();
+    t!(fs::create_dir(&root.join(".cargo")));
+    t!(t!(File::create(root.join(".cargo/config"))).write_all(br#"
+        [source.crates-io]
+        replace-with = 'my-awesome-local-registry'
+
+        [source.my-awesome-local-registry]
+        directory = 'index'
+    "#));
+}
+
+struct VendorPackage {
+    p: Option<ProjectBuilder>,
+    cksum: Checksum,
+}
+
+#[derive(RustcEncodable)]
+struct Checksum {
+    package: Option<String>,
+    files: HashMap<String, String>,
+}
+
+impl VendorPackage {
+    fn new(name: &str) -> VendorPackage {
+        let path = paths::root().join("index").join(name);
+        VendorPackage {
+            p: Some(ProjectBuilder::new(name, path)),
+            cksum: Checksum {
+                package: None,
+                files: HashMap::new(),
+            },
+        }
+    }
+
+    fn file(&mut self, name: &str, contents: &str) -> &mut VendorPackage {
+        self.p = Some(self.p.take().unwrap().file(name, contents));
+        self.cksum.files.insert(name.to_string(), cksum(contents.as_bytes()));
+        self
+    }
+
+    fn build(&mut self) {
+        let p = self.p.take().unwrap();
+        let json = json::encode(&self.cksum).unwrap();
+        let p = p.file(".cargo-checksum.json", &json);
+        p.build();
+    }
+}
+
+fn cksum(s: &[u8]) -> String {
+    let mut sha = Sha256::new();
+    sha.update(s);
+    sha.finish().to_hex()
+}
+
+fn foo_project() -> ProjectBuilder {
+    project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            foo = "0.1.0"
+        "#)
+        .file("src/lib.rs", r#"
+            extern crate foo;
+
+            pub fn bar() {
+                foo::foo();
+            }
+        "#)
+}
+
+#[test]
+fn simple() {
+    setup();
+
+    VendorPackage::new("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}")
+        .build();
+
+    let p = foo_project();
+    p.build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] foo v0.1.0
+[COMPILING] bar v0.1.0 ([..]bar)
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn edited_file_is_reported() {
+    setup();
+
+    VendorPackage::new("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}")
+        .build();
+
+    let p = foo_project();
+    p.build();
+
+    t!(t!(File::create(paths::root().join("index/foo/src/lib.rs")))
+        .write_all(b"pub fn foo() { panic!() }"));
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]the listed checksum of `[..]lib.rs` has changed:
+expected: [..]
+actual:   [..]
+
+directory sources are not intended to be edited, [..]
+"));
+}
+
+#[test]
+fn added_file_is_reported() {
+    setup();
+
+    VendorPackage::new("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}")
+        .build();
+
+    let p = foo_project();
+    p.build();
+
+    t!(t!(File::create(paths::root().join("index/foo/src/extra.rs")))
+        .write_all(b"pub fn extra() {}"));
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]the vendored package `foo v0.1.0` has files which aren't listed in \
+`[..].cargo-checksum.json`:
+  src/extra.rs
+
+directory sources are not intended to be edited, [..]
+"));
+}
+
+#[test]
+fn committed_to_git() {
+    setup();
+
+    VendorPackage::new("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}")
+        .build();
+
+    let p = foo_project();
+    p.build();
+
+    // The checksum file is tracked like the rest of the vendored package,
+    // while files ignored by the repository still count as added files
+    t!(t!(File::create(paths::root().join(".gitignore")))
+        .write_all(b"/index/foo/src/extra.rs\n"));
+    let repo = t!(git2::Repository::init(paths::root()));
+    git::add(&repo);
+    git::commit(&repo);
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    t!(t!(File::create(paths::root().join("index/foo/src/extra.rs")))
+        .write_all(b"pub fn extra() {}"));
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]the vendored package `foo v0.1.0` has files which aren't listed in \
+`[..].cargo-checksum.json`:
+  src/extra.rs
+"));
+}
+
+#[test]
+fn package_checksum_checked_against_lock_file() {
+    setup();
+
+    let mut foo = VendorPackage::new("foo");
+    foo.file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+       .file("src/lib.rs", "pub fn foo() {}");
+    foo.cksum.package = Some(cksum(b"original"));
+    foo.build();
+
+    let p = foo_project();
+    p.build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    let mut lock = String::new();
+    t!(t!(File::open(p.root().join("Cargo.lock"))).read_to_string(&mut lock));
+    assert!(lock.contains(&cksum(b"original")), "{}", lock);
+
+    let mut foo = VendorPackage::new("foo");
+    foo.file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+       .file("src/lib.rs", "pub fn foo() {}");
+    foo.cksum.package = Some(cksum(b"replaced"));
+    foo.build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]checksum for `foo v0.1.0` changed between lock files
+"));
+}
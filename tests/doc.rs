@@ -2,10 +2,12 @@ extern crate cargotest;
 extern crate hamcrest;
 
 use std::str;
-use std::fs;
+use std::fs::{self, File};
+use std::io::Read;
 
 use cargotest::{is_nightly, rustc_host};
 use cargotest::support::{project, execs, path2url};
+use cargotest::support::registry::Package;
 use hamcrest::{assert_that, existing_file, existing_dir, is_not};
 
 #[test]
@@ -589,3 +591,215 @@ fn document_only_lib() {
                 execs().with_status(0));
     assert_that(&p.root().join("target/doc/foo/index.html"), existing_file());
 }
+
+#[test]
+fn doc_all_writes_index() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["a", "b"]
+        "#)
+        .file("a/Cargo.toml", r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+            authors = []
+            description = "Does <a> things"
+        "#)
+        .file("a/src/lib.rs", r#"
+            #![doc(html_root_url = "https://docs.rs/a/0.1.0")]
+            pub struct A;
+        "#)
+        .file("b/Cargo.toml", r#"
+            [package]
+            name = "b-crate"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            a = { path = "../a" }
+        "#)
+        .file("b/src/lib.rs", r#"
+            extern crate a;
+            pub fn b() -> a::A { a::A }
+        "#);
+
+    assert_that(p.cargo_process("doc").arg("--all"),
+                execs().with_status(0));
+
+    let mut index = String::new();
+    File::open(p.root().join("target/doc/index.html")).unwrap()
+         .read_to_string(&mut index).unwrap();
+    assert!(index.contains("<a class=\"mod\" href=\"a/index.html\">a</a>"));
+    assert!(index.contains("<td class=\"docblock-short\">\
+                            Does &lt;a&gt; things</td>"));
+    assert!(index.contains("href=\"b_crate/index.html\""));
+
+    // The link to `A` leads to the documentation just built
+    let mut b = String::new();
+    File::open(p.root().join("target/doc/b_crate/fn.b.html")).unwrap()
+         .read_to_string(&mut b).unwrap();
+    assert!(b.contains("href=\"../a/struct.A.html\""));
+    assert!(!b.contains("docs.rs"));
+}
+
+#[test]
+fn doc_exclude_requires_all() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("doc").arg("--exclude").arg("foo"),
+                execs().with_status(101).with_stderr("\
+[ERROR] --exclude can only be used together with --all
+"));
+}
+
+#[test]
+fn doc_all_skips_dependencies() {
+    Package::new("dep", "0.1.0").file("src/lib.rs", "pub fn dep() {}")
+                                .publish();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            dep = "0.1"
+        "#)
+        .file("src/lib.rs", "extern crate dep;");
+
+    assert_that(p.cargo_process("doc").arg("--all"),
+                execs().with_status(0));
+    assert_that(&p.root().join("target/doc/foo/index.html"), existing_file());
+    assert_that(&p.root().join("target/doc/dep/index.html"),
+                is_not(existing_file()));
+
+    assert_that(p.cargo("doc").arg("--all").arg("--deps"),
+                execs().with_status(0));
+    assert_that(&p.root().join("target/doc/dep/index.html"), existing_file());
+
+    assert_that(p.cargo("doc").arg("--deps").arg("--no-deps"),
+                execs().with_status(1).with_stderr("\
+[ERROR] --deps can't be used with --no-deps
+"));
+
+    // Dependencies are already documented without --all
+    assert_that(p.cargo("doc").arg("--deps"),
+                execs().with_status(1).with_stderr("\
+[ERROR] --deps can only be used together with --all
+"));
+}
+
+#[cfg(unix)]
+#[test]
+fn doc_open_with_configured_browser() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [doc]
+            browser = "echo opening"
+        "#);
+
+    assert_that(p.cargo_process("doc").arg("--open"),
+                execs().with_status(0)
+                       .with_stdout_contains("opening [..]/target/doc/foo/\
+                                              index.html")
+                       .with_stderr_contains("[..]Launching echo"));
+}
+
+#[test]
+fn doc_private_items_of_members() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "extern crate bar; fn private() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("doc").arg("--document-private-items")
+                 .arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustdoc src[/]lib.rs --crate-name foo [..]\
+--document-private-items[..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustdoc [..]bar[/]src[/]lib.rs --crate-name bar \
+-o [..]target[/]doc -L [..]`"));
+}
+
+#[test]
+fn doc_private_items_documents_again() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "fn private() {}");
+
+    assert_that(p.cargo_process("doc"), execs().with_status(0));
+    assert_that(&p.root().join("target/doc/foo/fn.private.html"),
+                is_not(existing_file()));
+
+    assert_that(p.cargo("doc").arg("--document-private-items"),
+                execs().with_status(0).with_stderr("\
+[DOCUMENTING] foo v0.0.1 ([..])
+"));
+    assert_that(&p.root().join("target/doc/foo/fn.private.html"),
+                existing_file());
+}
+
+#[test]
+fn doc_examples_when_enabled() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [[example]]
+            name = "ex"
+            doc = true
+
+            [[bin]]
+            name = "internal"
+            doc = false
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}")
+        .file("src/bin/internal.rs", "fn main() {}")
+        .file("examples/ex.rs", "extern crate foo; fn main() { foo::foo() }");
+
+    assert_that(p.cargo_process("doc"), execs().with_status(0));
+    assert_that(&p.root().join("target/doc/foo/index.html"), existing_file());
+    assert_that(&p.root().join("target/doc/ex/index.html"), existing_file());
+    assert_that(&p.root().join("target/doc/internal/index.html"),
+                is_not(existing_file()));
+}
@@ -2,6 +2,7 @@ extern crate cargotest;
 extern crate hamcrest;
 
 use cargotest::support::{project, execs};
+use cargotest::support::registry::Package;
 use hamcrest::assert_that;
 
 #[test]
@@ -21,3 +22,33 @@ fn no_deps() {
     assert_that(p.cargo_process("fetch"),
                 execs().with_status(0).with_stdout(""));
 }
+
+#[test]
+fn downloads_deepest_first() {
+    Package::new("d", "0.1.0").publish();
+    Package::new("c", "0.1.0").dep("d", "0.1").publish();
+    Package::new("b", "0.1.0").dep("c", "0.1").publish();
+    Package::new("a", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            authors = []
+            version = "0.0.1"
+
+            [dependencies]
+            a = "0.1"
+            b = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("fetch"),
+                execs().with_status(0).with_stderr("\
+[UPDATING] registry `[..]`
+[DOWNLOADING] d v0.1.0 (registry file://[..])
+[DOWNLOADING] c v0.1.0 (registry file://[..])
+[DOWNLOADING] a v0.1.0 (registry file://[..])
+[DOWNLOADING] b v0.1.0 (registry file://[..])
+"));
+}
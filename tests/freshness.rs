@@ -75,10 +75,12 @@ fn modify_only_some_files() {
 
     let lib = p.root().join("src/lib.rs");
     let bin = p.root().join("src/b.rs");
+    let rlib = p.root().join("target/debug/libfoo.rlib");
+    let rlib_mtime = fs::metadata(&rlib).unwrap().modified().unwrap();
 
-    File::create(&lib).unwrap().write_all(b"invalid rust code").unwrap();
+    // Touch the library without changing it
+    File::create(&lib).unwrap().write_all(b"mod a;").unwrap();
     File::create(&bin).unwrap().write_all(b"#[allow(unused)]fn foo() {}").unwrap();
-    lib.move_into_the_past();
 
     // Make sure the binary is rebuilt, not the lib
     assert_that(p.cargo("build"),
@@ -86,6 +88,7 @@ fn modify_only_some_files() {
 [COMPILING] foo v0.0.1 ({dir})
 ", dir = path2url(p.root()))));
     assert_that(&p.bin("foo"), existing_file());
+    assert_eq!(fs::metadata(&rlib).unwrap().modified().unwrap(), rlib_mtime);
 }
 
 #[test]
@@ -260,6 +263,8 @@ fn no_rebuild_transitive_target_deps() {
 [COMPILING] c v0.0.1 ([..])
 [COMPILING] b v0.0.1 ([..])
 [COMPILING] foo v0.0.1 ([..])
+[EXECUTABLE] [..]target[..]debug[..]foo-[..]
+[EXECUTABLE] [..]target[..]debug[..]foo-[..]
 "));
 }
 
@@ -360,3 +365,44 @@ fn same_build_dir_cached_packages() {
 [COMPILING] a2 v0.0.1 ({dir}/a2)
 ", dir = p.url())));
 }
+
+#[test]
+fn touching_files_without_changes_is_fresh() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            authors = []
+            version = "0.0.1"
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            authors = []
+            version = "0.0.1"
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+
+    // Rewriting a file with the same contents bumps its mtime, which used to
+    // trigger a rebuild of it and everything depending on it.
+    File::create(&p.root().join("bar/src/lib.rs")).unwrap()
+         .write_all(b"pub fn bar() {}").unwrap();
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stdout("").with_stderr(""));
+
+    File::create(&p.root().join("bar/src/lib.rs")).unwrap()
+         .write_all(b"pub fn bar() { println!(\"changed\") }").unwrap();
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[COMPILING] bar v0.0.1 ({dir}/bar)
+[COMPILING] foo v0.0.1 ({dir})
+", dir = path2url(p.root()))));
+}
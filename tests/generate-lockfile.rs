@@ -200,3 +200,46 @@ fn cargo_update_generate_lockfile() {
     assert_that(&lockfile, existing_file());
 
 }
+
+#[test]
+fn alternate_lockfile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            authors = []
+            version = "0.0.1"
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            authors = []
+            version = "0.0.1"
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("generate-lockfile")
+                 .arg("--lockfile").arg("locks/host.lock"),
+                execs().with_status(0));
+    let lockfile = p.root().join("locks/host.lock");
+    assert_that(&lockfile, existing_file());
+    assert_that(&p.root().join("Cargo.lock"), is_not(existing_file()));
+
+    let mut lock = String::new();
+    File::open(&lockfile).unwrap().read_to_string(&mut lock).unwrap();
+    assert!(lock.contains("name = \"bar\""));
+
+    assert_that(p.cargo("build").arg("--lockfile").arg("locks/host.lock"),
+                execs().with_status(0));
+    assert_that(&p.root().join("Cargo.lock"), is_not(existing_file()));
+
+    // Each lock file is maintained independently
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.root().join("Cargo.lock"), existing_file());
+    assert_that(p.cargo("verify-lockfile").arg("--lockfile").arg("locks/host.lock"),
+                execs().with_status(0));
+}
@@ -1794,3 +1794,55 @@ fn add_a_git_dep() {
 
     assert_that(p.cargo("build"), execs().with_status(0));
 }
+
+#[test]
+fn fetches_git_deps_at_once() {
+    let a = git::new("a", |p| {
+        p.file("Cargo.toml", r#"
+            [package]
+            name = "a"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+    let b = git::new("b", |p| {
+        p.file("Cargo.toml", r#"
+            [package]
+            name = "b"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = {{ git = '{}' }}
+            b = {{ git = '{}' }}
+        "#, a.url(), b.url()))
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [net]
+            git-fetch-jobs = 2
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains(&format!("\
+[UPDATING] git repository `{}`
+[UPDATING] git repository `{}`", a.url(), b.url()))
+                       .with_stderr_contains("\
+[..]Fetched git repository `[..]` (2 of 2)"));
+
+    // Once locked and fetched, nothing is fetched again
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr("[FINISHED] [..]"));
+}
@@ -239,6 +239,90 @@ fn auto_git() {
     assert_that(&foo.join(".gitignore"), existing_file());
 }
 
+#[test]
+fn extra_binaries_detected() {
+    let path = paths::root().join("foo");
+    fs::create_dir_all(path.join("src")).unwrap();
+    File::create(path.join("src/main.rs")).unwrap()
+        .write_all(b"fn main() {}").unwrap();
+    File::create(path.join("src/tool.rs")).unwrap()
+        .write_all(b"fn main() {}").unwrap();
+    File::create(path.join("src/util.rs")).unwrap()
+        .write_all(b"pub fn util() {}").unwrap();
+
+    assert_that(cargo_process("init").arg("--vcs").arg("none")
+                                    .env("USER", "foo").cwd(&path),
+                execs().with_status(0));
+
+    let mut contents = String::new();
+    File::open(path.join("Cargo.toml")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    assert!(contents.contains(r#"name = "tool""#), "{}", contents);
+    assert!(contents.contains(r#"path = "src/tool.rs""#), "{}", contents);
+    assert!(!contents.contains("util"), "{}", contents);
+}
+
+#[test]
+fn modules_and_nested_mains_not_binaries() {
+    let path = paths::root().join("foo");
+    fs::create_dir_all(path.join("src")).unwrap();
+    File::create(path.join("src/main.rs")).unwrap()
+        .write_all(b"mod cli;\nfn main() {}").unwrap();
+    File::create(path.join("src/cli.rs")).unwrap()
+        .write_all(b"fn main() {}").unwrap();
+    File::create(path.join("src/notes.rs")).unwrap()
+        .write_all(b"// not fn main\nmod t {\n    fn main() {}\n}").unwrap();
+
+    assert_that(cargo_process("init").arg("--vcs").arg("none")
+                                    .env("USER", "foo").cwd(&path),
+                execs().with_status(0));
+
+    let mut contents = String::new();
+    File::open(path.join("Cargo.toml")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    assert!(!contents.contains("cli"), "{}", contents);
+    assert!(!contents.contains("notes"), "{}", contents);
+}
+
+#[test]
+fn extra_binaries_with_the_same_name() {
+    let path = paths::root().join("foo");
+    fs::create_dir_all(path.join("src")).unwrap();
+    File::create(path.join("src/main.rs")).unwrap()
+        .write_all(b"fn main() {}").unwrap();
+    File::create(path.join("tool.rs")).unwrap()
+        .write_all(b"fn main() {}").unwrap();
+    File::create(path.join("src/tool.rs")).unwrap()
+        .write_all(b"fn main() {}").unwrap();
+
+    assert_that(cargo_process("init").arg("--vcs").arg("none")
+                                    .env("USER", "foo").cwd(&path),
+                execs().with_status(101).with_stderr("\
+[ERROR] multiple possible sources found for the binary `tool`:
+  tool.rs
+  src/tool.rs
+cannot automatically generate Cargo.toml as its path would be ambiguous
+"));
+
+    assert_that(&path.join("Cargo.toml"), is_not(existing_file()));
+}
+
+#[test]
+fn inside_existing_repository() {
+    let td = TempDir::new("cargo").unwrap();
+    assert_that(process("git").arg("init").cwd(td.path()),
+                execs().with_status(0));
+    let foo = td.path().join("foo");
+    fs::create_dir_all(&foo).unwrap();
+
+    assert_that(cargo_process("init").cwd(&foo).env("USER", "foo"),
+                execs().with_status(0));
+
+    assert_that(&foo.join("Cargo.toml"), existing_file());
+    assert_that(&foo.join(".git"), is_not(existing_dir()));
+    assert_that(&foo.join(".gitignore"), existing_file());
+}
+
 #[test]
 fn invalid_dir_name() {
     let foo = &paths::root().join("foo.bar");
@@ -319,6 +403,22 @@ fn gitignore_appended_not_replaced() {
     assert!(contents.contains(r#"qqqqqq"#));
 }
 
+#[test]
+fn gitignore_entries_not_duplicated() {
+    fs::create_dir(&paths::root().join(".git")).unwrap();
+
+    File::create(&paths::root().join(".gitignore")).unwrap()
+        .write_all(b"/target/\nqqqqqq").unwrap();
+
+    assert_that(cargo_process("init").env("USER", "foo"),
+                execs().with_status(0));
+
+    let mut contents = String::new();
+    File::open(&paths::root().join(".gitignore")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "/target/\nqqqqqq\nCargo.lock\n");
+}
+
 #[test]
 fn cargo_lock_gitignored_if_lib1() {
     fs::create_dir(&paths::root().join(".git")).unwrap();
@@ -1,7 +1,10 @@
 extern crate cargo;
+#[macro_use]
 extern crate cargotest;
+extern crate git2;
 extern crate hamcrest;
 
+use std::env;
 use std::fs::{self, File};
 use std::io::prelude::*;
 
@@ -9,7 +12,7 @@ use cargo::util::ProcessBuilder;
 use cargotest::install::{cargo_home, has_installed_exe};
 use cargotest::support::git;
 use cargotest::support::paths;
-use cargotest::support::registry::Package;
+use cargotest::support::registry::{registry, Package};
 use cargotest::support::{project, execs};
 use hamcrest::{assert_that, is_not};
 
@@ -39,6 +42,7 @@ fn simple() {
 [DOWNLOADING] foo v0.0.1 (registry file://[..])
 [COMPILING] foo v0.0.1 (registry file://[..])
 [INSTALLING] {home}[..]bin[..]foo[..]
+[INSTALLED] package `foo v0.0.1` (executable `foo[..]`)
 warning: be sure to add `[..]` to your PATH to be able to run the installed binaries
 ",
         home = cargo_home().display())));
@@ -63,12 +67,185 @@ fn pick_max_version() {
 [DOWNLOADING] foo v0.0.2 (registry file://[..])
 [COMPILING] foo v0.0.2 (registry file://[..])
 [INSTALLING] {home}[..]bin[..]foo[..]
+[INSTALLED] package `foo v0.0.2` (executable `foo[..]`)
 warning: be sure to add `[..]` to your PATH to be able to run the installed binaries
 ",
         home = cargo_home().display())));
     assert_that(cargo_home(), has_installed_exe("foo"));
 }
 
+#[test]
+fn version_requirement() {
+    pkg("foo", "0.0.1");
+    pkg("foo", "0.0.2");
+    pkg("foo", "0.1.0");
+
+    assert_that(cargo_process("install").arg("foo").arg("--vers=0.0.1"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[INSTALLED] package `foo v0.0.1` (executable `foo[..]`)"));
+    assert_that(cargo_process("install").arg("foo").arg("--force")
+                                        .arg("--vers=<0.1"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[INSTALLED] package `foo v0.0.2` (executable `foo[..]`)"));
+    assert_that(cargo_process("install").arg("foo").arg("--vers=foo"),
+                execs().with_status(101).with_stderr("\
+[UPDATING] registry [..]
+[ERROR] `foo` passed to --vers is neither a version nor a version requirement
+"));
+}
+
+#[test]
+fn locked_uses_shipped_lockfile() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1").publish();
+    Package::new("foo", "0.1.0")
+        .dep("bar", "0.1")
+        .file("src/main.rs", "fn main() {}")
+        .file("Cargo.lock", &format!(r#"
+            [root]
+            name = "foo"
+            version = "0.1.0"
+            dependencies = [
+             "bar 0.1.0 (registry+{reg})",
+            ]
+
+            [[package]]
+            name = "bar"
+            version = "0.1.0"
+            source = "registry+{reg}"
+        "#, reg = registry()))
+        .publish();
+
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] bar v0.1.1 [..]"));
+    assert_that(cargo_process("install").arg("foo").arg("--force")
+                                        .arg("--locked"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] bar v0.1.0 [..]"));
+}
+
+#[test]
+fn locked_without_shipped_lockfile() {
+    pkg("foo", "0.0.1");
+
+    assert_that(cargo_process("install").arg("foo").arg("--locked"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] `foo v0.0.1 ([..])` doesn't ship a Cargo.lock, so the newest \
+versions of its dependencies are used even though --locked was passed"));
+}
+
+#[test]
+fn multiple_crates() {
+    pkg("foo", "0.0.1");
+    pkg("bar", "0.0.2");
+
+    assert_that(cargo_process("install").arg("foo").arg("bar"),
+                execs().with_status(0).with_stderr_contains("\
+[SUMMARY] successfully installed foo, bar
+"));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert_that(cargo_home(), has_installed_exe("bar"));
+}
+
+#[test]
+fn multiple_crates_built_together() {
+    Package::new("dep", "0.1.0")
+        .file("src/lib.rs", "pub fn dep() {}")
+        .publish();
+    for name in ["foo", "bar"].iter() {
+        Package::new(name, "0.0.1")
+            .dep("dep", "0.1")
+            .file("src/main.rs", "extern crate dep; fn main() { dep::dep() }")
+            .publish();
+    }
+
+    let out = cargo_process("install").arg("foo").arg("bar")
+                                      .exec_with_output().unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    // Both crates are compiled before either is installed, and the
+    // dependency they share is compiled once
+    let compiled = stderr.rfind("Compiling").unwrap();
+    let installed = stderr.find("Installing").unwrap();
+    assert!(compiled < installed, "{}", stderr);
+    assert_eq!(stderr.matches("Compiling dep v0.1.0").count(), 1, "{}",
+               stderr);
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert_that(cargo_home(), has_installed_exe("bar"));
+}
+
+#[test]
+fn multiple_crates_with_own_profiles() {
+    let p = git::repo(&paths::root().join("foo"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("a/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [profile.release]
+            debug-assertions = true
+        "#)
+        .file("a/src/main.rs", "fn main() {}");
+    p.build();
+
+    // `bar` is built on its own so that its profile applies
+    assert_that(cargo_process("install").arg("--git").arg(p.url().to_string())
+                                        .arg("foo").arg("bar").arg("-v"),
+                execs().with_status(0).with_stderr_contains("\
+[RUNNING] `rustc [..]a[/]src[/]main.rs [..]-C debug-assertions=on[..]`"));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert_that(cargo_home(), has_installed_exe("bar"));
+}
+
+#[test]
+fn multiple_crates_with_same_binary() {
+    for name in ["foo", "bar"].iter() {
+        Package::new(name, "0.0.1")
+            .file("src/bin/tool.rs", "fn main() {}")
+            .publish();
+    }
+
+    assert_that(cargo_process("install").arg("foo").arg("bar"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] `bar` can't be installed along with `foo` as both have a binary named \
+`tool[..]`
+").with_stderr_contains("\
+[SUMMARY] successfully installed foo
+"));
+    assert_that(cargo_home(), has_installed_exe("tool"));
+}
+
+#[test]
+fn multiple_crates_some_failing() {
+    pkg("foo", "0.0.1");
+
+    assert_that(cargo_process("install").arg("baz").arg("foo"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] could not find `baz` in `registry [..]`
+").with_stderr_contains("\
+[SUMMARY] successfully installed foo
+[ERROR] failed to install baz, intermediate artifacts can be found at `[..]`
+"));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+
+    assert_that(cargo_process("install").arg("foo").arg("bar")
+                    .arg("--vers").arg("0.0.1"),
+                execs().with_status(101).with_stderr("\
+[ERROR] a version can't be given with --vers when installing more than one crate
+"));
+}
+
 #[test]
 fn missing() {
     pkg("foo", "0.0.1");
@@ -168,10 +345,96 @@ fn install_path() {
                 execs().with_status(0));
     assert_that(cargo_home(), has_installed_exe("foo"));
     assert_that(cargo_process("install").arg("--path").arg(".").cwd(p.root()),
+                execs().with_status(0).with_stderr_contains("\
+[REPLACING] [..]foo[..]
+"));
+}
+
+#[test]
+fn install_path_with_lockfile() {
+    Package::new("bar", "0.1.0").publish();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+    assert_that(p.cargo("generate-lockfile").arg("--lockfile")
+                 .arg("locks/old.lock"),
+                execs().with_status(0));
+    Package::new("bar", "0.1.1").publish();
+
+    assert_that(cargo_process("install").arg("--path").arg(".")
+                 .arg("--lockfile").arg("locks/old.lock").cwd(p.root()),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] bar v0.1.0 [..]"));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert!(!p.root().join("Cargo.lock").exists());
+
+    assert_that(cargo_process("install").arg("bar")
+                 .arg("--lockfile").arg("locks/old.lock").cwd(p.root()),
                 execs().with_status(101).with_stderr("\
-[ERROR] binary `foo[..]` already exists in destination as part of `foo v0.1.0 [..]`
-Add --force to overwrite
+[ERROR] --lockfile can only be used when installing one crate from a path
+"));
+}
+
+#[test]
+fn install_workspace_member() {
+    Package::new("dep", "0.1.0").publish();
+
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["foo", "bar", "baz"]
+        "#)
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "../bar" }
+        "#)
+        .file("foo/src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            dep = "0.1"
+        "#)
+        .file("bar/src/lib.rs", "extern crate dep; pub fn bar() {}")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("baz/src/main.rs", "fn main() {}");
+    p.build();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    // The lock file of the workspace is used rather than the newest version
+    Package::new("dep", "0.1.1").publish();
+
+    assert_that(cargo_process("install").arg("--path").arg(".")
+                 .cwd(p.root().join("foo")),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[DOWNLOADING] dep v0.1.0 (registry file://[..])
 "));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert_that(cargo_home(), is_not(has_installed_exe("baz")));
+    assert!(p.root().join("target").is_dir());
 }
 
 #[test]
@@ -372,14 +635,101 @@ fn install_twice() {
 
     assert_that(cargo_process("install").arg("--path").arg(p.root()),
                 execs().with_status(0));
+    // The contents of a path may have changed, so it's always reinstalled
     assert_that(cargo_process("install").arg("--path").arg(p.root()),
-                execs().with_status(101).with_stderr("\
-[ERROR] binary `foo-bin1[..]` already exists in destination as part of `foo v0.1.0 ([..])`
-binary `foo-bin2[..]` already exists in destination as part of `foo v0.1.0 ([..])`
-Add --force to overwrite
+                execs().with_status(0).with_stderr(&format!("\
+[COMPILING] foo v0.1.0 ([..])
+[REPLACING] {home}[..]bin[..]foo-bin1[..]
+[REPLACING] {home}[..]bin[..]foo-bin2[..]
+[INSTALLED] package `foo v0.1.0` (executables `foo-bin1[..]`, `foo-bin2[..]`)
+warning: be sure to add `[..]` to your PATH to be able to run the installed binaries
+",
+        home = cargo_home().display())));
+}
+
+#[test]
+fn reinstall_is_a_no_op() {
+    pkg("foo", "0.0.1");
+
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0));
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0).with_stderr_contains("\
+[IGNORED] package `foo v0.0.1` is already installed, use --force to override
+"));
+
+    // A different profile or set of features is rebuilt
+    assert_that(cargo_process("install").arg("foo").arg("--debug"),
+                execs().with_status(0).with_stderr_contains("\
+[REPLACING] [..]foo[..]
 "));
 }
 
+#[test]
+fn reinstall_keeps_features() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [features]
+            extra = []
+        "#)
+        .file("src/main.rs", r#"
+            #[cfg(feature = "extra")]
+            fn main() {}
+        "#);
+    p.build();
+
+    assert_that(cargo_process("install").arg("--path").arg(p.root())
+                    .arg("--features").arg("extra"),
+                execs().with_status(0));
+    assert_that(cargo_process("install").arg("--path").arg(p.root())
+                    .arg("-v"),
+                execs().with_status(0).with_stderr_contains("\
+[KEEPING] features [extra] of `foo` from its installation
+").with_stderr_contains("\
+[RUNNING] `rustc [..]--cfg feature=[..]extra[..]`
+"));
+
+    // Without the feature there's no `main`
+    assert_that(cargo_process("install").arg("--path").arg(p.root())
+                    .arg("--reset-features"),
+                execs().with_status(101));
+}
+
+#[test]
+fn upgrade_and_downgrade() {
+    pkg("foo", "0.0.1");
+
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0));
+
+    pkg("foo", "0.0.2");
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0).with_stderr_contains("\
+[REPLACING] [..]foo[..]
+[INSTALLED] package `foo v0.0.2` (executable `foo[..]`)
+"));
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.2 (registry [..]):
+    foo[..]
+"));
+
+    assert_that(cargo_process("install").arg("foo").arg("--vers").arg("0.0.1"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] `foo v0.0.2 (registry [..])` is already installed, which is newer than \
+the requested v0.0.1
+Add --force to downgrade
+"));
+    assert_that(cargo_process("install").arg("foo").arg("--vers").arg("0.0.1")
+                    .arg("--force"),
+                execs().with_status(0));
+}
+
 #[test]
 fn install_force() {
     let p = project("foo")
@@ -409,6 +759,7 @@ fn install_force() {
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] foo v0.2.0 ([..])
 [REPLACING] {home}[..]bin[..]foo[..]
+[INSTALLED] package `foo v0.2.0` (executable `foo[..]`)
 warning: be sure to add `[..]` to your PATH to be able to run the installed binaries
 ",
         home = cargo_home().display())));
@@ -452,6 +803,7 @@ fn install_force_partial_overlap() {
 [COMPILING] foo v0.2.0 ([..])
 [INSTALLING] {home}[..]bin[..]foo-bin3[..]
 [REPLACING] {home}[..]bin[..]foo-bin2[..]
+[INSTALLED] package `foo v0.2.0` (executables `foo-bin2[..]`, `foo-bin3[..]`)
 warning: be sure to add `[..]` to your PATH to be able to run the installed binaries
 ",
         home = cargo_home().display())));
@@ -501,6 +853,7 @@ fn install_force_bin() {
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] foo v0.2.0 ([..])
 [REPLACING] {home}[..]bin[..]foo-bin2[..]
+[INSTALLED] package `foo v0.2.0` (executable `foo-bin2[..]`)
 warning: be sure to add `[..]` to your PATH to be able to run the installed binaries
 ",
         home = cargo_home().display())));
@@ -555,6 +908,7 @@ fn git_repo() {
 [UPDATING] git repository `[..]`
 [COMPILING] foo v0.1.0 ([..])
 [INSTALLING] {home}[..]bin[..]foo[..]
+[INSTALLED] package `foo v0.1.0` (executable `foo[..]`)
 warning: be sure to add `[..]` to your PATH to be able to run the installed binaries
 ",
         home = cargo_home().display())));
@@ -642,6 +996,76 @@ fn uninstall_piecemeal() {
 "));
 }
 
+#[test]
+fn uninstall_with_package_flag() {
+    pkg("foo", "0.0.1");
+    pkg("bar", "0.0.1");
+
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0));
+    assert_that(cargo_process("install").arg("bar"),
+                execs().with_status(0));
+    assert_that(cargo_process("uninstall").arg("-p").arg("foo"),
+                execs().with_status(0).with_stderr("\
+[REMOVING] [..]foo[..]
+"));
+    assert_that(cargo_home(), is_not(has_installed_exe("foo")));
+    assert_that(cargo_home(), has_installed_exe("bar"));
+}
+
+#[test]
+fn metadata_records_how_packages_were_installed() {
+    pkg("foo", "0.0.1");
+
+    assert_that(cargo_process("install").arg("foo").arg("--debug")
+                    .arg("--vers").arg("0.0.1"),
+                execs().with_status(0));
+
+    // `.crates.toml` stays in the format every version of Cargo reads
+    let mut list = String::new();
+    t!(t!(File::open(cargo_home().join(".crates.toml")))
+        .read_to_string(&mut list));
+    assert!(list.starts_with("[v1]\n\"foo 0.0.1 (registry+"), "{}", list);
+    assert!(!list.contains("profile"), "{}", list);
+
+    let mut metadata = String::new();
+    t!(t!(File::open(cargo_home().join(".crates-info.toml")))
+        .read_to_string(&mut metadata));
+    assert!(metadata.contains("[v2.\"foo 0.0.1 (registry+"), "{}", metadata);
+    assert!(metadata.contains("profile = \"dev\""), "{}", metadata);
+    assert!(metadata.contains("version_req = \"0.0.1\""), "{}", metadata);
+}
+
+#[test]
+fn old_metadata_is_upgraded() {
+    pkg("foo", "0.0.1");
+    pkg("bar", "0.0.1");
+
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0));
+    let path = cargo_home().join(".crates.toml");
+    let mut metadata = String::new();
+    t!(t!(File::open(&path)).read_to_string(&mut metadata));
+    let start = metadata.find('"').unwrap();
+    let end = start + metadata[start + 1..].find('"').unwrap() + 2;
+    let v1 = format!("[v1]\n{} = [\"foo{}\"]\n", &metadata[start..end],
+                     env::consts::EXE_SUFFIX);
+    t!(t!(File::create(&path)).write_all(v1.as_bytes()));
+
+    assert_that(cargo_process("install").arg("bar"),
+                execs().with_status(0));
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout("\
+bar v0.0.1 (registry [..]):
+    bar[..]
+foo v0.0.1 (registry [..]):
+    foo[..]
+"));
+    assert_that(cargo_process("uninstall").arg("foo"),
+                execs().with_status(0));
+    assert_that(cargo_home(), is_not(has_installed_exe("foo")));
+}
+
 #[test]
 fn subcommand_works_out_of_the_box() {
     Package::new("cargo-foo", "1.0.0")
@@ -692,6 +1116,7 @@ fn do_not_rebuilds_on_local_install() {
     assert_that(cargo_process("install").arg("--path").arg(p.root()),
                 execs().with_status(0).with_stderr("\
 [INSTALLING] [..]
+[INSTALLED] package `foo v0.1.0` (executable `foo[..]`)
 warning: be sure to add `[..]` to your PATH to be able to run the installed binaries
 "));
 
@@ -755,6 +1180,52 @@ fn git_with_lockfile() {
                 execs().with_status(0));
 }
 
+#[test]
+fn git_reinstalled_when_branch_moves() {
+    let p = git::repo(&paths::root().join("foo"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+    let url = p.url().to_string();
+
+    assert_that(cargo_process("install").arg("--git").arg(&url)
+                    .arg("--branch").arg("master"),
+                execs().with_status(0));
+    assert_that(cargo_process("install").arg("--git").arg(&url)
+                    .arg("--branch").arg("master"),
+                execs().with_status(0).with_stderr_contains("\
+[IGNORED] package `foo v0.1.0` is already installed, use --force to override
+"));
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout("\
+foo v0.1.0 ([..]#[..]):
+    foo[..]
+"));
+
+    let repo = git2::Repository::open(&p.root()).unwrap();
+    t!(t!(File::create(p.root().join("src/main.rs")))
+        .write_all(b"fn main() { println!(\"new\"); }"));
+    git::add(&repo);
+    git::commit(&repo);
+
+    assert_that(cargo_process("install").arg("--git").arg(&url)
+                    .arg("--branch").arg("master"),
+                execs().with_status(0).with_stderr_contains("\
+[REPLACING] [..]foo[..]
+"));
+    // The new revision was recorded, so there's nothing left to do
+    assert_that(cargo_process("install").arg("--git").arg(&url)
+                    .arg("--branch").arg("master"),
+                execs().with_status(0).with_stderr_contains("\
+[IGNORED] package `foo v0.1.0` is already installed, use --force to override
+"));
+}
+
 #[test]
 fn q_silences_warnings() {
     let p = project("foo")
@@ -786,3 +1257,15 @@ fn readonly_dir() {
                 execs().with_status(0));
     assert_that(cargo_home(), has_installed_exe("foo"));
 }
+
+#[test]
+fn install_keeps_artifacts_in_configured_target_dir() {
+    pkg("foo", "0.0.1");
+    let target = paths::root().join("shared-target");
+
+    assert_that(cargo_process("install").arg("foo")
+                                        .env("CARGO_TARGET_DIR", &target),
+                execs().with_status(0));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert!(target.join("release").is_dir());
+}
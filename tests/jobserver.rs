@@ -0,0 +1,121 @@
+#![cfg(unix)]
+
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{cargo_dir, project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn build_scripts_get_a_jobserver() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+            use std::fs::File;
+            use std::io::prelude::*;
+            use std::os::unix::prelude::*;
+
+            fn main() {
+                let flags = env::var("CARGO_MAKEFLAGS").unwrap();
+                assert_eq!(env::var("MAKEFLAGS").unwrap(), flags);
+                let fds = flags.split_whitespace().filter_map(|arg| {
+                    if arg.starts_with("--jobserver-fds=") {
+                        Some(&arg["--jobserver-fds=".len()..])
+                    } else {
+                        None
+                    }
+                }).next().unwrap();
+                let mut fds = fds.split(',').map(|fd| fd.parse().unwrap());
+                let mut read = unsafe { File::from_raw_fd(fds.next().unwrap()) };
+                let mut write = unsafe { File::from_raw_fd(fds.next().unwrap()) };
+
+                // With `-j2` there's exactly one token in the pool, which
+                // we take and then hand back.
+                let mut token = [0];
+                read.read_exact(&mut token).unwrap();
+                write.write_all(&token).unwrap();
+                read.into_raw_fd();
+                write.into_raw_fd();
+            }
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-j2")
+                 .env_remove("CARGO_MAKEFLAGS").env_remove("MAKEFLAGS")
+                 .env_remove("MFLAGS"),
+                execs().with_status(0));
+}
+
+#[test]
+fn builds_run_by_make_share_its_jobserver() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+
+            fn jobserver(var: &str) -> String {
+                let flags = env::var(var).unwrap();
+                let arg = flags.split_whitespace().filter(|arg| {
+                    arg.starts_with("--jobserver-")
+                }).next().unwrap();
+                arg.splitn(2, '=').nth(1).unwrap().to_string()
+            }
+
+            fn main() {
+                // `make` set up `MAKEFLAGS`, and Cargo passed its jobserver
+                // along rather than creating its own
+                let ours = jobserver("CARGO_MAKEFLAGS");
+                assert_eq!(ours, jobserver("MAKEFLAGS"));
+            }
+        "#)
+        .file("Makefile", "\
+all:
+\t+$(CARGO) build
+");
+    p.build();
+
+    assert_that(p.process("make").arg("-j2")
+                 .env("CARGO", cargo_dir().join("cargo"))
+                 .env_remove("CARGO_MAKEFLAGS").env_remove("MAKEFLAGS")
+                 .env_remove("MFLAGS"),
+                execs().with_status(0));
+}
+
+#[test]
+fn unavailable_jobserver_warns() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    // What `make` advertises to a recipe it doesn't know to be recursive,
+    // having closed the descriptors
+    assert_that(p.cargo_process("build")
+                 .env_remove("CARGO_MAKEFLAGS").env_remove("MFLAGS")
+                 .env("MAKEFLAGS", "-j --jobserver-fds=900,901 \
+                                    --jobserver-auth=900,901"),
+                execs().with_status(0).with_stderr_contains("\
+[WARNING] the jobserver advertised in `MAKEFLAGS` isn't available, so `-j` \
+limits this build on its own
+`make` only passes its jobserver to recipes which run `$(MAKE)` or are marked \
+recursive with a `+` prefix
+"));
+}
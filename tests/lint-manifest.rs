@@ -0,0 +1,80 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn lints_warn_by_default() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+
+            [dev-dependencies]
+            bar = "0.1"
+
+            [features]
+            a = []
+            b = []
+            c = ["bar/std"]
+        "#)
+        .file("src/lib.rs", r#"
+            #[cfg(feature = "a")]
+            pub fn a() {}
+        "#);
+
+    assert_that(p.cargo_process("lint-manifest"),
+                execs().with_status(0)
+                       .with_stderr("\
+[WARNING] foo v0.0.1 ([..]): the manifest has no description [missing-metadata]
+[WARNING] foo v0.0.1 ([..]): the manifest has no license or license-file \
+[missing-metadata]
+[WARNING] foo v0.0.1 ([..]): the manifest has no repository [missing-metadata]
+[WARNING] foo v0.0.1 ([..]): `bar` accepts any version [wildcard-dependencies]
+[WARNING] foo v0.0.1 ([..]): `bar` is listed in both [dependencies] and \
+[dev-dependencies] [duplicate-dependencies]
+[WARNING] foo v0.0.1 ([..]): feature `b` is never enabled by another feature \
+nor tested for in the sources [unused-features]
+"));
+}
+
+#[test]
+fn severities_configurable() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            publish = false
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [manifest-lints]
+            wildcard-dependencies = "deny"
+        "#);
+
+    assert_that(p.cargo_process("lint-manifest"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] foo v0.0.1 ([..]): `bar` accepts any version [wildcard-dependencies]
+[ERROR] 1 denied manifest lint(s) found
+"));
+
+    assert_that(p.cargo("lint-manifest").arg("--message-format").arg("json"),
+                execs().with_status(101)
+                       .with_stdout("\
+{\"lint\":\"wildcard-dependencies\",\"message\":\"`bar` accepts any version\",\
+\"package_id\":\"foo 0.0.1 ([..])\",\"reason\":\"manifest-lint\",\
+\"severity\":\"deny\"}
+"));
+}
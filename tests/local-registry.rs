@@ -0,0 +1,118 @@
+#[macro_use]
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+use cargotest::support::{project, execs, paths};
+use cargotest::support::registry::{self, Package};
+use hamcrest::assert_that;
+
+fn setup() {
+    let root = paths::root();
+    t!(fs::create_dir(&root.join(".cargo")));
+    t!(t!(File::create(root.join(".cargo/config"))).write_all(br#"
+        [source.crates-io]
+        replace-with = 'my-awesome-local-registry'
+
+        [source.my-awesome-local-registry]
+        local-registry = 'local'
+    "#));
+}
+
+/// Publishes `pkg` to the test registry and copies it into the local
+/// registry in `local`.
+fn publish_local(pkg: &mut Package, name: &str, vers: &str) {
+    pkg.publish();
+    let local = paths::root().join("local");
+    copy_index(&registry::registry_path(), &local.join("index"));
+    t!(fs::copy(pkg.archive_dst(),
+                local.join(format!("{}-{}.crate", name, vers))));
+}
+
+fn copy_index(src: &Path, dst: &Path) {
+    t!(fs::create_dir_all(dst));
+    for entry in t!(fs::read_dir(src)) {
+        let path = t!(entry).path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if name.starts_with('.') || name == "config.json" {
+            continue
+        }
+        if path.is_dir() {
+            copy_index(&path, &dst.join(&name));
+        } else {
+            t!(fs::copy(&path, dst.join(&name)));
+        }
+    }
+}
+
+fn bar_project() -> cargotest::support::ProjectBuilder {
+    project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            foo = "0.1.0"
+        "#)
+        .file("src/lib.rs", r#"
+            extern crate foo;
+
+            pub fn bar() {
+                foo::foo();
+            }
+        "#)
+}
+
+#[test]
+fn simple() {
+    setup();
+    publish_local(Package::new("foo", "0.1.0")
+                      .file("src/lib.rs", "pub fn foo() {}"), "foo", "0.1.0");
+
+    let p = bar_project();
+    p.build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] foo v0.1.0[..]
+[COMPILING] bar v0.1.0 ([..]bar)
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn tampered_crate_is_rejected() {
+    setup();
+    publish_local(Package::new("foo", "0.1.0")
+                      .file("src/lib.rs", "pub fn foo() {}"), "foo", "0.1.0");
+    t!(t!(File::create(paths::root().join("local/foo-0.1.0.crate")))
+        .write_all(b"not a tarball"));
+
+    let p = bar_project();
+    p.build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]failed to verify the checksum of `foo v0.1.0[..]` in the local registry \
+at `[..]foo-0.1.0.crate`
+"));
+}
+
+#[test]
+fn missing_index() {
+    setup();
+    t!(fs::create_dir_all(paths::root().join("local")));
+
+    let p = bar_project();
+    p.build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]the local registry `[..]` has no index, which is expected in `[..]index`
+"));
+}
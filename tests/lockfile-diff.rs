@@ -0,0 +1,114 @@
+#[macro_use]
+extern crate cargotest;
+extern crate git2;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use cargotest::support::{project, execs, git, paths};
+use cargotest::support::registry::Package;
+use hamcrest::assert_that;
+
+#[test]
+fn lock_files() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("baz", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            baz = "0.1"
+        "#)
+        .file("src/lib.rs", "");
+    assert_that(p.cargo_process("generate-lockfile"), execs().with_status(0));
+    t!(fs::copy(p.root().join("Cargo.lock"), p.root().join("old.lock")));
+
+    Package::new("bar", "0.1.1").publish();
+    Package::new("qux", "0.1.0").publish();
+    t!(t!(File::create(p.root().join("Cargo.toml"))).write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "0.1"
+        qux = "0.1"
+    "#));
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    assert_that(p.cargo("lockfile-diff").arg("old.lock"),
+                execs().with_status(0).with_stdout("\
+Added qux v0.1.0
+Removed baz v0.1.0
+Updated bar v0.1.0 -> v0.1.1
+"));
+    assert_that(p.cargo("lockfile-diff").arg("Cargo.lock").arg("old.lock")
+                 .arg("--format").arg("markdown"),
+                execs().with_status(0).with_stdout("\
+### Added
+
+* `baz` 0.1.0
+
+### Removed
+
+* `qux` 0.1.0
+
+### Updated
+
+* `bar` 0.1.1 -> 0.1.0
+"));
+    assert_that(p.cargo("lockfile-diff").arg("old.lock")
+                 .arg("--format").arg("json"),
+                execs().with_status(0).with_stdout_contains("\
+[..]{\"name\":\"bar\",\"source\":\"file://[..]\",\"from\":\"0.1.0\",\
+\"to\":\"0.1.1\",\"link\":null}[..]
+"));
+}
+
+#[test]
+fn revisions_and_index() {
+    Package::new("bar", "0.1.0").publish();
+
+    let root = paths::root().join("foo");
+    git::repo(&root)
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    let p = project("foo");
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    let repo = t!(git2::Repository::open(&root));
+    git::add(&repo);
+    git::commit(&repo);
+
+    Package::new("bar", "0.1.1").publish();
+    assert_that(p.cargo("lockfile-diff").arg("Cargo.lock").arg("--index"),
+                execs().with_status(0).with_stdout("\
+Updated bar v0.1.0 -> v0.1.1
+"));
+
+    assert_that(p.cargo("update"), execs().with_status(0));
+    assert_that(p.cargo("lockfile-diff").arg("HEAD"),
+                execs().with_status(0).with_stdout("\
+Updated bar v0.1.0 -> v0.1.1
+"));
+    assert_that(p.cargo("lockfile-diff").arg("nope"),
+                execs().with_status(101).with_stderr_contains("\
+[..]`nope` is neither a lock file nor a revision of the git repository[..]
+"));
+}
@@ -1,7 +1,11 @@
 extern crate cargotest;
 extern crate hamcrest;
 
+use std::fs::{self, File};
+use std::io::prelude::*;
+
 use hamcrest::assert_that;
+use cargotest::sleep_ms;
 use cargotest::support::registry::Package;
 use cargotest::support::{project, execs, basic_bin_manifest, main_file};
 
@@ -286,3 +290,128 @@ fn carg_metadata_bad_version() {
                 execs().with_status(101)
     .with_stderr("[ERROR] metadata version 2 not supported, only 1 is currently supported"));
 }
+
+#[test]
+fn cargo_metadata_cache_is_invalidated() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", &main_file(r#""i am foo""#, &[]));
+
+    assert_that(p.cargo_process("metadata"),
+                execs().with_status(0).with_stdout_contains("[..]\"0.5.0\"[..]"));
+    let cache = p.root().join("target/metadata");
+    assert_eq!(fs::read_dir(&cache).unwrap().count(), 1);
+    assert_that(p.cargo("metadata"),
+                execs().with_status(0).with_stdout_contains("[..]\"0.5.0\"[..]"));
+
+    File::create(&p.root().join("Cargo.toml")).unwrap()
+         .write_all(basic_bin_manifest("foo").replace("0.5.0", "0.6.0")
+                                             .as_bytes()).unwrap();
+    assert_that(p.cargo("metadata"),
+                execs().with_status(0).with_stdout_contains("[..]\"0.6.0\"[..]"));
+
+    // Targets inferred from the layout of the package are picked up as well
+    File::create(&p.root().join("src/lib.rs")).unwrap();
+    assert_that(p.cargo("metadata"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]\"kind\":[\"lib\"][..]"));
+
+    assert_that(p.cargo("metadata").arg("--no-cache"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]\"kind\":[\"lib\"][..]"));
+}
+
+#[test]
+fn cargo_metadata_cache_follows_members_not_sources() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["crates/*"]
+        "#)
+        .file("crates/a/Cargo.toml", r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("crates/a/src/lib.rs", "");
+
+    assert_that(p.cargo_process("metadata"), execs().with_status(0));
+    let cache = p.root().join("target/metadata");
+    let entry = fs::read_dir(&cache).unwrap().next().unwrap().unwrap().path();
+    let written = fs::metadata(&entry).unwrap().modified().unwrap();
+
+    // Editing a source file leaves the cached result alone
+    sleep_ms(1000);
+    File::create(&p.root().join("crates/a/src/lib.rs")).unwrap()
+         .write_all(b"pub fn a() {}").unwrap();
+    assert_that(p.cargo("metadata"), execs().with_status(0));
+    assert_eq!(fs::metadata(&entry).unwrap().modified().unwrap(), written);
+
+    // A new directory matching `members` is a new member
+    fs::create_dir_all(p.root().join("crates/b/src")).unwrap();
+    File::create(&p.root().join("crates/b/Cargo.toml")).unwrap()
+         .write_all(br#"
+            [package]
+            name = "b"
+            version = "0.1.0"
+            authors = []
+         "#).unwrap();
+    File::create(&p.root().join("crates/b/src/lib.rs")).unwrap();
+    assert_that(p.cargo("metadata"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]\"name\":\"b\"[..]"));
+}
+
+#[cfg(unix)]
+#[test]
+fn cargo_metadata_non_utf8_path() {
+    use std::ffi::OsStr;
+    use std::os::unix::prelude::*;
+
+    let p = project("foo");
+    p.build();
+    let root = p.root().join(OsStr::from_bytes(b"f\xffo"));
+    fs::create_dir_all(root.join("src")).unwrap();
+    File::create(root.join("Cargo.toml")).unwrap()
+         .write_all(basic_bin_manifest("foo").as_bytes()).unwrap();
+    File::create(root.join("src/foo.rs")).unwrap()
+         .write_all(main_file(r#""i am foo""#, &[]).as_bytes()).unwrap();
+
+    assert_that(p.cargo("metadata").cwd(&root),
+                execs().with_status(101).with_stderr("\
+[ERROR] the manifest path of `foo v0.5.0 ([..])` is not valid UTF-8: \
+[..]f\u{FFFD}o[..]Cargo.toml
+"));
+}
+
+#[test]
+fn cargo_metadata_build_scripts() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            links = "foo"
+            build = "build.rs"
+        "#)
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:root=/opt/foo");
+                println!("cargo:rustc-cfg=has_foo");
+            }
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("metadata").arg("--build-scripts"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]\"build_scripts\":[][..]"));
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("metadata").arg("--build-scripts"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]\"links\":\"foo\",\
+                           \"metadata\":{\"root\":\"/opt/foo\"},\
+                           \"cfgs\":[\"has_foo\"][..]"));
+}
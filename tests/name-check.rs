@@ -0,0 +1,58 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{execs, project};
+use cargotest::support::registry::Package;
+use hamcrest::assert_that;
+
+#[test]
+fn available_and_taken() {
+    Package::new("serde", "0.8.0").publish();
+    Package::new("foo-bar", "0.1.0").publish();
+
+    let p = project("foo");
+    assert_that(p.cargo_process("name-check").arg("ferris"),
+                execs().with_status(0).with_stdout("\
+`ferris` is available
+"));
+    assert_that(p.cargo("name-check").arg("Foo_Bar"),
+                execs().with_status(101).with_stdout("\
+`Foo_Bar` is taken by `foo-bar`
+").with_stderr("\
+[UPDATING] registry `[..]`
+[ERROR] the name `Foo_Bar` can't be claimed on the registry
+"));
+}
+
+#[test]
+fn similar_names() {
+    Package::new("serde", "0.8.0").publish();
+    Package::new("5erde", "0.1.0").publish();
+    Package::new("sered", "0.1.0").publish();
+
+    let p = project("foo");
+    assert_that(p.cargo_process("name-check").arg("serde_"),
+                execs().with_status(0).with_stdout("\
+`serde_` is available
+similar names on the registry: `5erde`, `serde`
+"));
+}
+
+#[test]
+fn registry_rules() {
+    Package::new("serde", "0.8.0").publish();
+
+    let p = project("foo");
+    assert_that(p.cargo_process("name-check").arg("2fast"),
+                execs().with_status(101).with_stdout("\
+`2fast` can't be registered: the name doesn't start with a letter
+"));
+    assert_that(p.cargo("name-check").arg("Std"),
+                execs().with_status(101).with_stdout("\
+`Std` can't be registered: the name is reserved for a crate shipped with Rust
+"));
+    assert_that(p.cargo("name-check").arg("con"),
+                execs().with_status(101).with_stdout("\
+`con` can't be registered: the name is reserved by Windows
+"));
+}
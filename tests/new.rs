@@ -1,5 +1,6 @@
 extern crate cargo;
 extern crate cargotest;
+extern crate git2;
 extern crate hamcrest;
 extern crate tempdir;
 
@@ -9,7 +10,8 @@ use std::env;
 
 use cargo::util::ProcessBuilder;
 use cargotest::process;
-use cargotest::support::{execs, paths};
+use cargotest::support::{execs, git, paths};
+use cargotest::support::registry::Package;
 use hamcrest::{assert_that, existing_file, existing_dir, is_not};
 use tempdir::TempDir;
 
@@ -118,6 +120,51 @@ fn keyword_name() {
 use --name to override crate name"));
 }
 
+#[test]
+fn leading_digit_name() {
+    assert_that(cargo_process("new").arg("2048"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] The name `2048` cannot be used as a crate name as it starts with a \
+digit
+use --name to override crate name"));
+}
+
+#[test]
+fn std_crate_name() {
+    assert_that(cargo_process("new").arg("core").arg("--vcs").arg("none")
+                                    .env("USER", "foo"),
+                execs().with_status(0)
+                       .with_stderr("\
+[WARNING] the name `core` is the name of a crate shipped with Rust, which \
+the package may be confused with
+"));
+    assert_that(&paths::root().join("core/Cargo.toml"), existing_file());
+}
+
+#[test]
+fn name_taken_on_registry() {
+    Package::new("foo", "0.1.0").publish();
+
+    assert_that(cargo_process("new").arg("foo").arg("--vcs").arg("none")
+                                    .arg("--check-registry")
+                                    .env("USER", "foo"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] a crate named `foo` already exists on crates.io, the package \
+can't be published under this name
+"));
+    assert_that(&paths::root().join("foo/Cargo.toml"), existing_file());
+
+    assert_that(cargo_process("new").arg("bar").arg("--vcs").arg("none")
+                                    .arg("--check-registry")
+                                    .env("USER", "foo"),
+                execs().with_status(0)
+                       .with_stderr("\
+[UPDATING] registry `[..]`
+"));
+}
+
 #[test]
 fn rust_prefix_stripped() {
     assert_that(cargo_process("new").arg("rust-foo").env("USER", "foo"),
@@ -359,6 +406,94 @@ fn subpackage_git_with_vcs_arg() {
                  existing_file());
 }
 
+#[test]
+fn template_directory() {
+    let tpl = paths::root().join("tpl");
+    fs::create_dir_all(tpl.join("src")).unwrap();
+    fs::create_dir_all(tpl.join("ci")).unwrap();
+    File::create(tpl.join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "{{name}}"
+        version = "0.1.0"
+        authors = ["{{authors}}"]
+        license = "{{license}}"
+    "#).unwrap();
+    File::create(tpl.join("src/lib.rs")).unwrap()
+        .write_all(b"//! The {{crate_name}} crate, {{unknown}} is kept").unwrap();
+    File::create(tpl.join("ci/run.sh")).unwrap().write_all(b"").unwrap();
+    File::create(tpl.join("cargo-template.toml")).unwrap()
+        .write_all(b"exclude = [\"ci/*\"]").unwrap();
+
+    assert_that(cargo_process("new").arg("foo-bar").arg("--vcs").arg("none")
+                                    .arg("--template").arg("tpl")
+                                    .env("USER", "foo"),
+                execs().with_status(0));
+
+    let root = paths::root().join("foo-bar");
+    let mut contents = String::new();
+    File::open(root.join("Cargo.toml")).unwrap()
+         .read_to_string(&mut contents).unwrap();
+    assert!(contents.contains(r#"name = "foo-bar""#), "{}", contents);
+    assert!(contents.contains(r#"authors = ["foo"]"#), "{}", contents);
+    assert!(contents.contains(r#"license = """#), "{}", contents);
+    let mut contents = String::new();
+    File::open(root.join("src/lib.rs")).unwrap()
+         .read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "//! The foo_bar crate, {{unknown}} is kept");
+    assert_that(&root.join("ci/run.sh"), is_not(existing_file()));
+    assert_that(&root.join("cargo-template.toml"), is_not(existing_file()));
+
+    assert_that(cargo_process("build").cwd(&root),
+                execs().with_status(0));
+}
+
+#[test]
+fn template_without_manifest() {
+    fs::create_dir_all(paths::root().join("tpl/src")).unwrap();
+
+    assert_that(cargo_process("new").arg("foo").arg("--vcs").arg("none")
+                                    .arg("--template").arg("tpl")
+                                    .env("USER", "foo"),
+                execs().with_status(101).with_stderr_contains("\
+[..]template `tpl` has no `Cargo.toml`
+"));
+    assert_that(&paths::root().join("foo"), is_not(existing_dir()));
+}
+
+#[test]
+fn template_git_default_branch() {
+    let tpl = git::repo(&paths::root().join("tpl"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "{{name}}"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    tpl.build();
+
+    // Move the template to a `trunk` branch, without any `master`
+    let repo = git2::Repository::open(tpl.root()).unwrap();
+    let head = repo.head().unwrap().target().unwrap();
+    let head = repo.find_commit(head).unwrap();
+    repo.branch("trunk", &head, true).unwrap();
+    repo.set_head("refs/heads/trunk").unwrap();
+    repo.find_branch("master", git2::BranchType::Local).unwrap()
+        .delete().unwrap();
+
+    assert_that(cargo_process("new").arg("foo").arg("--vcs").arg("none")
+                                    .arg("--template")
+                                    .arg(tpl.url().to_string())
+                                    .env("USER", "foo"),
+                execs().with_status(0));
+
+    let mut contents = String::new();
+    File::open(paths::root().join("foo/Cargo.toml")).unwrap()
+         .read_to_string(&mut contents).unwrap();
+    assert!(contents.contains(r#"name = "foo""#), "{}", contents);
+    assert_that(&paths::root().join("foo/src/lib.rs"), existing_file());
+}
+
 #[test]
 fn unknown_flags() {
     assert_that(cargo_process("new").arg("foo").arg("--flag"),
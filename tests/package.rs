@@ -10,7 +10,7 @@ use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
 use cargo::util::process;
-use cargotest::cargo_process;
+use cargotest::{cargo_process, rustc_host};
 use cargotest::support::{project, execs, paths, git, path2url, cargo_dir};
 use flate2::read::GzDecoder;
 use hamcrest::{assert_that, existing_file, contains};
@@ -163,6 +163,7 @@ fn package_verbose() {
 [PACKAGING] foo v0.0.1 ([..])
 [ARCHIVING] [..]
 [ARCHIVING] [..]
+[ARCHIVING] Cargo.lock
 "));
 
     println!("package sub-repo");
@@ -200,6 +201,29 @@ fn package_verification() {
         dir = p.url())));
 }
 
+#[test]
+fn package_verification_target_and_check() {
+    let p = project("all")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            fn main() {}
+        "#);
+    assert_that(p.cargo_process("package").arg("-v")
+                 .arg("--target").arg(rustc_host()),
+                execs().with_status(0).with_stderr_contains(&format!("\
+[RUNNING] `rustc [..] --target {}[..]`
+", rustc_host())));
+    assert_that(p.cargo("package").arg("-v").arg("--check"),
+                execs().with_status(0).with_stderr_contains("\
+[RUNNING] `rustc [..]--emit=dep-info,metadata [..]`
+"));
+}
+
 #[test]
 fn exclude() {
     let p = project("foo")
@@ -325,11 +349,41 @@ fn no_duplicates_from_modified_tracked_files() {
     assert_that(cargo.clone().arg("build"), execs().with_status(0));
     assert_that(cargo.arg("package").arg("--list"),
                 execs().with_status(0).with_stdout("\
+Cargo.lock
 Cargo.toml
 src/main.rs
 "));
 }
 
+#[test]
+fn ships_alternate_lockfile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    assert_that(p.cargo_process("generate-lockfile")
+                 .arg("--lockfile").arg("locks/host.lock"),
+                execs().with_status(0));
+
+    assert_that(p.cargo("package").arg("--list")
+                 .arg("--lockfile").arg("locks/host.lock"),
+                execs().with_status(0).with_stdout("\
+Cargo.lock
+Cargo.toml
+locks[..]host.lock
+src[..]main.rs
+"));
+    assert_that(p.cargo("package").arg("-v").arg("--no-verify")
+                 .arg("--lockfile").arg("locks/host.lock"),
+                execs().with_status(0)
+                       .with_stderr_contains("[ARCHIVING] Cargo.lock"));
+    assert!(!p.root().join("Cargo.lock").exists());
+}
+
 #[test]
 fn ignore_nested() {
     let cargo_toml = r#"
@@ -459,3 +513,54 @@ fn repackage_on_source_change() {
     }).collect::<Vec<PathBuf>>();
     assert_that(&entry_paths, contains(vec![PathBuf::from("foo-0.0.1/src/foo.rs")]));
 }
+
+#[test]
+fn verify_honors_target_dir() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("package").arg("--no-metadata")
+                 .env("CARGO_TARGET_DIR", "shared"),
+                execs().with_status(0));
+    let shared = p.root().join("shared");
+    assert_that(&shared.join("package/foo-0.0.1.crate"), existing_file());
+    assert_that(&shared.join("package/foo-0.0.1/Cargo.toml"), existing_file());
+    assert!(shared.join("debug").is_dir());
+    assert!(!p.root().join("target").exists());
+}
+
+#[test]
+fn verify_in_tmpdir() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    let tmpdir = paths::root().join("scratch");
+
+    assert_that(p.cargo_process("package").arg("--no-metadata")
+                 .env("CARGO_BUILD_TMPDIR", &tmpdir),
+                execs().with_status(0).with_stderr(&format!("\
+[PACKAGING] foo v0.0.1 ({dir})
+[VERIFYING] foo v0.0.1 ({dir})
+[COMPILING] foo v0.0.1 ({tmp}[..]foo-0.0.1)
+",
+        dir = p.url(), tmp = path2url(tmpdir.clone()))));
+    assert_that(&p.root().join("target/package/foo-0.0.1.crate"),
+                existing_file());
+    assert!(!p.root().join("target/package/foo-0.0.1").exists());
+    assert_eq!(tmpdir.read_dir().unwrap().count(), 0);
+}
@@ -0,0 +1,98 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{project, execs};
+use hamcrest::{assert_that, existing_file};
+
+#[test]
+fn libraries_emit_metadata_first() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .file("src/main.rs", "extern crate foo; fn main() { foo::foo() }")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("build").arg("-v")
+                 .env("CARGO_BUILD_PIPELINING", "true"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]src[..]lib.rs --crate-name bar [..]--emit=metadata [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]src[..]lib.rs --crate-name bar [..]--emit=dep-info,link [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]lib.rs --crate-name foo [..]--emit=metadata [..]\
+--extern bar=[..]libbar[..].rmeta`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]main.rs --crate-name foo [..]--emit=dep-info,link [..]\
+--extern foo=[..]libfoo.rlib[..]`"));
+    assert_that(&p.root().join("target/debug/libfoo.rmeta"), existing_file());
+    assert_that(p.process(&p.bin("foo")), execs().with_status(0));
+
+    // Turning pipelining off rebuilds the libraries without their metadata
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] bar v0.0.1 ([..])
+[COMPILING] foo v0.0.1 ([..])
+"));
+}
+
+#[test]
+fn binaries_wait_for_every_rlib() {
+    // Enough code in `baz` for its rlib to be written well after its metadata
+    let baz = (0..2000).map(|i| {
+        format!("pub fn baz{}() -> usize {{ {} }}\n", i, i)
+    }).collect::<String>();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/main.rs", r#"
+            extern crate bar;
+            fn main() { assert_eq!(bar::bar(), 1999); }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = { path = "../baz" }
+        "#)
+        .file("bar/src/lib.rs", r#"
+            extern crate baz;
+            pub fn bar() -> usize { baz::baz1999() }
+        "#)
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", &baz);
+
+    assert_that(p.cargo_process("build")
+                 .env("CARGO_BUILD_PIPELINING", "true"),
+                execs().with_status(0));
+    assert_that(p.process(&p.bin("foo")), execs().with_status(0));
+}
@@ -6,6 +6,7 @@ use std::env;
 
 use cargotest::{is_nightly, rustc_host};
 use cargotest::support::{project, execs};
+use cargotest::support::registry::Package;
 use hamcrest::assert_that;
 
 #[test]
@@ -274,3 +275,43 @@ fn native_plugin_dependency_with_custom_ar_linker() {
 [ERROR] could not exec the linker [..]
 "));
 }
+
+#[test]
+fn proc_macros_outside_workspace_need_allowing() {
+    if !is_nightly() { return }
+
+    Package::new("bar", "0.1.0")
+            .file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                authors = []
+
+                [lib]
+                proc-macro = true
+            "#)
+            .file("src/lib.rs", "")
+            .publish();
+
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [proc-macros]
+            deny-all = true
+        "#);
+
+    assert_that(foo.cargo_process("build"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] the procedural macros of these packages aren't allowed to run:
+  bar v0.1.0 (registry [..])
+"));
+}
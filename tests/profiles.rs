@@ -2,10 +2,12 @@ extern crate cargotest;
 extern crate hamcrest;
 
 use std::env;
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::MAIN_SEPARATOR as SEP;
 
 use cargotest::support::{project, execs};
-use hamcrest::assert_that;
+use hamcrest::{assert_that, existing_file};
 
 #[test]
 fn profile_overrides() {
@@ -107,3 +109,307 @@ fn top_level_overrides_deps() {
                     prefix = env::consts::DLL_PREFIX,
                     suffix = env::consts::DLL_SUFFIX)));
 }
+
+#[test]
+fn package_overrides() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [profile.dev.package.bar]
+            opt-level = 3
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]lib.rs --crate-name bar [..]-C opt-level=3 -g \
+-C debug-assertions=on [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]main.rs --crate-name foo --crate-type bin -g [..]`"));
+
+    // The configuration takes precedence over the manifest
+    fs::create_dir(p.root().join(".cargo")).unwrap();
+    File::create(p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [profile.dev.package.bar]
+        opt-level = 2
+    "#).unwrap();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]lib.rs --crate-name bar [..]-C opt-level=2 [..]`"));
+}
+
+#[test]
+fn package_overrides_disable_checks() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [profile.dev.package.bar]
+            debug-assertions = false
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]lib.rs --crate-name bar [..]\
+-C debug-assertions=off [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]main.rs --crate-name foo --crate-type bin -g [..]`"));
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(""));
+
+    // Keeping the overflow checks is a different configuration of `bar`
+    File::create(p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = { path = "bar" }
+
+        [profile.dev.package.bar]
+        debug-assertions = false
+        overflow-checks = true
+    "#).unwrap();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]lib.rs --crate-name bar [..]\
+-C debug-assertions=off -C overflow-checks=on [..]`"));
+}
+
+#[test]
+fn package_overrides_out_of_range_in_config() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file(".cargo/config", r#"
+            [profile.dev.package.foo]
+            codegen-units = -1
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] invalid configuration for key `profile.dev.package.foo`
+
+Caused by:
+  `codegen-units` must be a non-negative 32-bit integer, but is `-1` \
+(in [..]config)
+"));
+}
+
+#[test]
+fn package_overrides_unsupported_key() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev.package.bar]
+            lto = true
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+  `lto`, `rpath`, `panic`, `incremental`, `package`, `build-override` and \
+`inherits` can't be set for a single package in \
+`[profile.dev.package.\"bar\"]`"));
+}
+
+#[test]
+fn custom_profile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.release-lto]
+            inherits = "release"
+            lto = true
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build").arg("-v")
+                 .arg("--profile").arg("release-lto"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]main.rs --crate-name foo --crate-type bin \
+-C opt-level=3 -C lto [..]`"));
+    assert_that(&p.root().join(format!("target/release-lto/foo{}",
+                                        env::consts::EXE_SUFFIX)),
+                existing_file());
+
+    // Building with another profile doesn't clobber the custom one
+    assert_that(p.cargo("build"),
+                execs().with_status(0));
+    assert_that(p.cargo("build").arg("--profile").arg("release-lto"),
+                execs().with_status(0).with_stderr(""));
+}
+
+#[test]
+fn custom_profile_errors() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.fast]
+            opt-level = 2
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+  profile `fast` must inherit from `dev` or `release` with \
+`inherits = \"...\"`"));
+
+    let p = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build").arg("--profile").arg("fast"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] profile `fast` is not defined in the manifest"));
+}
+
+#[test]
+fn thin_lto_overflow_checks_and_incremental() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.release]
+            lto = "thin"
+            codegen-units = 4
+            overflow-checks = true
+            incremental = true
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build").arg("--release").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]main.rs --crate-name foo --crate-type bin \
+-C opt-level=3 -C lto=thin -C codegen-units=4 -C overflow-checks=on \
+-C incremental=[..]target[..]release[..]incremental [..]`"));
+}
+
+#[test]
+fn unknown_lto_mode() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.release]
+            lto = "fast"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+  `lto` must be a boolean, \"fat\", \"thin\" or \"off\", but found \"fast\" \
+in `[profile.release]`"));
+}
+
+#[test]
+fn build_override() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [build-dependencies]
+            bar = { path = "bar" }
+
+            [profile.release.build-override]
+            opt-level = 0
+        "#)
+        .file("build.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("build").arg("--release").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc build.rs --crate-name build_script_build --crate-type bin \
+-C debug-assertions=off [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]lib.rs --crate-name bar --crate-type lib \
+-C opt-level=3 [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc bar[..]lib.rs --crate-name bar --crate-type lib \
+-C debug-assertions=off [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]main.rs --crate-name foo --crate-type bin \
+-C opt-level=3 [..]`"));
+}
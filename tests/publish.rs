@@ -5,10 +5,14 @@ extern crate hamcrest;
 extern crate tar;
 extern crate url;
 
+use std::collections::HashMap;
 use std::io::prelude::*;
+use std::io::{BufReader, SeekFrom};
 use std::fs::{self, File};
-use std::io::SeekFrom;
+use std::net::{SocketAddr, TcpListener};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 use cargotest::support::git::repo;
 use cargotest::support::paths;
@@ -24,6 +28,10 @@ fn upload_path() -> PathBuf { paths::root().join("upload") }
 fn upload() -> Url { Url::from_file_path(&*upload_path()).ok().unwrap() }
 
 fn setup() {
+    setup_with_api(&upload().to_string());
+}
+
+fn setup_with_api(api: &str) {
     let config = paths::root().join(".cargo/config");
     fs::create_dir_all(config.parent().unwrap()).unwrap();
     File::create(&config).unwrap().write_all(&format!(r#"
@@ -35,12 +43,101 @@ fn setup() {
 
     repo(&registry_path())
         .file("config.json", &format!(r#"{{
-            "dl": "{0}",
-            "api": "{0}"
-        }}"#, upload()))
+            "dl": "{}",
+            "api": "{}"
+        }}"#, upload(), api))
         .build();
 }
 
+/// A registry API serving uploads in chunks over HTTP, which keeps the bytes
+/// it received and logs the requests it served. The `n`th chunk sent is
+/// answered with `failures[n]` rather than stored, if there's one.
+fn chunk_server(failures: HashMap<usize, u32>)
+                -> (SocketAddr, Arc<Mutex<Vec<u8>>>, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (received2, log2) = (received.clone(), log.clone());
+    thread::spawn(move || {
+        let mut chunks = 0;
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut rdr = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            rdr.read_line(&mut request).unwrap();
+            let path = request.split_whitespace().nth(1).unwrap().to_string();
+            let mut len = 0;
+            loop {
+                let mut line = String::new();
+                rdr.read_line(&mut line).unwrap();
+                let line = line.trim().to_lowercase();
+                if line.is_empty() {
+                    break
+                }
+                if line.starts_with("content-length:") {
+                    len = line[15..].trim().parse().unwrap();
+                }
+                if line == "expect: 100-continue" {
+                    stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                          .unwrap();
+                }
+            }
+            let mut body = vec![0; len];
+            rdr.read_exact(&mut body).unwrap();
+
+            let mut received = received2.lock().unwrap();
+            let (code, response) = if path.ends_with("/uploads/new") {
+                (200, format!(r#"{{"upload":{{"id":"1","received":{}}}}}"#,
+                              received.len()))
+            } else if path.ends_with("/finish") {
+                (200, r#"{"ok":true}"#.to_string())
+            } else {
+                chunks += 1;
+                match failures.get(&chunks) {
+                    Some(&code) => (code, String::new()),
+                    None => {
+                        let offset = path.rsplit('/').next().unwrap();
+                        received.truncate(offset.parse().unwrap());
+                        received.extend_from_slice(&body);
+                        (200, "{}".to_string())
+                    }
+                }
+            };
+            log2.lock().unwrap().push(format!("{} {}", path, code));
+            write!(stream, "HTTP/1.1 {} Whatever\r\nContent-Length: {}\r\n\
+                            Connection: close\r\n\r\n",
+                   code, response.len()).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (addr, received, log)
+}
+
+fn chunked_project() -> cargotest::support::ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [registry]
+            upload-chunk-size = 64
+        "#)
+}
+
+fn packaged(p: &cargotest::support::ProjectBuilder) -> Vec<u8> {
+    let mut tarball = Vec::new();
+    File::open(p.root().join("target/package/foo-0.0.1.crate")).unwrap()
+         .read_to_end(&mut tarball).unwrap();
+    tarball
+}
+
 #[test]
 fn simple() {
     setup();
@@ -92,6 +189,49 @@ fn simple() {
     }
 }
 
+#[test]
+fn ships_alternate_lockfile() {
+    setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    assert_that(p.cargo_process("generate-lockfile")
+                 .arg("--lockfile").arg("host.lock"),
+                execs().with_status(0));
+    assert_that(p.cargo("publish").arg("--no-verify")
+                 .arg("--lockfile").arg("host.lock"),
+                execs().with_status(0));
+
+    let mut f = File::open(&upload_path().join("api/v1/crates/new")).unwrap();
+    let mut sz = [0; 4];
+    assert_eq!(f.read(&mut sz).unwrap(), 4);
+    let sz = ((sz[0] as u32) <<  0) |
+             ((sz[1] as u32) <<  8) |
+             ((sz[2] as u32) << 16) |
+             ((sz[3] as u32) << 24);
+    f.seek(SeekFrom::Current(sz as i64 + 4)).unwrap();
+
+    let mut rdr = GzDecoder::new(f).unwrap();
+    let mut contents = Vec::new();
+    rdr.read_to_end(&mut contents).unwrap();
+    let mut ar = Archive::new(&contents[..]);
+    let mut shipped = false;
+    for file in ar.entries().unwrap() {
+        let file = file.unwrap();
+        shipped |= &*file.header().path_bytes() == b"foo-0.0.1/Cargo.lock";
+    }
+    assert!(shipped);
+    assert!(!p.root().join("Cargo.lock").exists());
+}
+
 #[test]
 fn git_deps() {
     setup();
@@ -113,8 +253,12 @@ fn git_deps() {
     assert_that(p.cargo_process("publish").arg("-v").arg("--no-verify"),
                 execs().with_status(101).with_stderr("\
 [UPDATING] registry [..]
-[ERROR] all dependencies must come from the same source.
+[ERROR] all dependencies must come from the registry being published to, and \
+path dependencies must specify a version:
+
 dependency `foo` comes from git://path/to/nowhere instead
+  replace the `git` source with the `version` of `foo` published to the \
+registry [..]
 "));
 }
 
@@ -144,13 +288,134 @@ fn path_dependency_no_version() {
         .file("bar/src/lib.rs", "");
 
     assert_that(p.cargo_process("publish"),
+                execs().with_status(101).with_stderr(&format!("\
+[UPDATING] registry [..]
+[ERROR] all dependencies must come from the registry being published to, and \
+path dependencies must specify a version:
+
+dependency `bar` at {dir}/bar does not specify a version
+  add the `version` of `bar` published to the registry [..]
+", dir = p.url())));
+}
+
+#[test]
+fn lists_every_rejected_dependency() {
+    setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [dependencies.bar]
+            path = "bar"
+
+            [build-dependencies.baz]
+            git = "git://path/to/nowhere"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(101).with_stderr(&format!("\
+[UPDATING] registry [..]
+[ERROR] all dependencies must come from the registry being published to, and \
+path dependencies must specify a version:
+
+dependency `bar` at {dir}/bar does not specify a version
+  add the `version` of `bar` published to the registry [..]
+build-dependency `baz` comes from git://path/to/nowhere instead
+  replace the `git` source with the `version` of `baz` published to the \
+registry [..]
+", dir = p.url())));
+}
+
+#[test]
+fn wildcard_requirement_rejected() {
+    setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [dependencies]
+            bar = "*"
+            baz = ">= 0.2"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("Cargo.lock", &format!(r#"
+            [root]
+            name = "foo"
+            version = "0.0.1"
+            dependencies = [
+             "bar 1.2.3 (registry+{reg})",
+            ]
+
+            [[package]]
+            name = "bar"
+            version = "1.2.3"
+            source = "registry+{reg}"
+        "#, reg = registry()));
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
                 execs().with_status(101).with_stderr("\
 [UPDATING] registry [..]
-[ERROR] all path dependencies must have a version specified when publishing.
-dependency `bar` does not specify a version
+[ERROR] the requirements on registry dependencies must have an upper bound, \
+set `publish.broad-requirements` to `warn` to publish anyway:
+
+dependency `bar` accepts any future version with `*`
+  use `bar = \"1.2.3\"` to accept the versions compatible with the locked 1.2.3
+dependency `baz` accepts any future version with `>= 0.2`
+  add an upper bound, or generate a lock file to find out which version of \
+`baz` to require
 "));
 }
 
+#[test]
+fn wildcard_requirement_allowed_by_policy() {
+    setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [publish]
+            broad-requirements = "warn"
+        "#);
+
+    assert_that(p.cargo_process("publish").arg("--no-verify")
+                 .arg("--dry-run"),
+                execs().with_status(0).with_stderr_contains("\
+[WARNING] dependency `bar` accepts any future version with `*`
+  add an upper bound, or generate a lock file to find out which version of \
+`bar` to require"));
+}
+
 #[test]
 fn unpublishable_crate() {
     setup();
@@ -359,3 +624,383 @@ fn dry_run() {
     // Ensure the API request wasn't actually made
     assert!(!upload_path().join("api/v1/crates/new").exists());
 }
+
+#[test]
+fn show_payload() {
+    setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [features]
+            bar = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--dry-run")
+                 .arg("--show-payload"),
+                execs().with_status(0)
+                       .with_stdout_contains("  \"name\": \"foo\",")
+                       .with_stdout_contains("  \"vers\": \"0.0.1\",")
+                       .with_stdout_contains("    \"bar\": []")
+                       .with_stderr_contains("\
+[UPLOADING] foo v0.0.1 ([..])
+[..]Tarball [..] bytes, sha256 [..]
+[WARNING] aborting upload due to dry run"));
+
+    assert!(!upload_path().join("api/v1/crates/new").exists());
+}
+
+#[test]
+fn upload_in_chunks_needs_registry_support() {
+    setup();
+    fs::create_dir_all(&upload_path().join("api/v1/crates/uploads")).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [registry]
+            upload-chunk-size = 16
+        "#);
+
+    // The registry of the tests only stores what's sent to it, so it can't
+    // tell where the upload is at
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] failed to start uploading in chunks, the registry may not support \
+it: [..]"));
+
+    let mut request = String::new();
+    File::open(&upload_path().join("api/v1/crates/uploads/new")).unwrap()
+         .read_to_string(&mut request).unwrap();
+    assert!(request.contains("\"name\":\"foo\""), "{}", request);
+    assert!(request.contains("\"cksum\":\""), "{}", request);
+    assert!(!upload_path().join("api/v1/crates/new").exists());
+}
+
+#[test]
+fn upload_in_chunks() {
+    let (addr, received, log) = chunk_server(HashMap::new());
+    setup_with_api(&format!("http://{}", addr));
+    let p = chunked_project();
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(0));
+    let tarball = packaged(&p);
+    assert!(tarball.len() > 64);
+    assert!(*received.lock().unwrap() == tarball);
+
+    let log = log.lock().unwrap();
+    let chunks = (tarball.len() + 63) / 64;
+    assert_eq!(log.len(), chunks + 2);
+    assert_eq!(log[0], "/api/v1/crates/uploads/new 200");
+    assert_eq!(log[1], "/api/v1/crates/uploads/1/chunks/0 200");
+    assert_eq!(log[2], "/api/v1/crates/uploads/1/chunks/64 200");
+    assert_eq!(log[chunks + 1], "/api/v1/crates/uploads/1/finish 200");
+}
+
+#[test]
+fn upload_in_chunks_retries_server_errors() {
+    let mut failures = HashMap::new();
+    failures.insert(2, 500);
+    failures.insert(3, 503);
+    let (addr, received, log) = chunk_server(failures);
+    setup_with_api(&format!("http://{}", addr));
+    let p = chunked_project();
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] failed to upload a chunk (2 tries remaining): failed to get a 200 \
+OK response, got 500")
+                       .with_stderr_contains("\
+[WARNING] failed to upload a chunk (1 tries remaining): failed to get a 200 \
+OK response, got 503"));
+    assert!(*received.lock().unwrap() == packaged(&p));
+
+    let log = log.lock().unwrap();
+    assert_eq!(log[2], "/api/v1/crates/uploads/1/chunks/64 500");
+    assert_eq!(log[3], "/api/v1/crates/uploads/1/chunks/64 503");
+    assert_eq!(log[4], "/api/v1/crates/uploads/1/chunks/64 200");
+}
+
+#[test]
+fn upload_in_chunks_resumes() {
+    let mut failures = HashMap::new();
+    failures.insert(3, 400);
+    let (addr, received, log) = chunk_server(failures);
+    setup_with_api(&format!("http://{}", addr));
+    let p = chunked_project();
+
+    // Client errors aren't retried
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] failed to upload the tarball at byte 128 of [..], publish again to \
+resume the upload"));
+    assert_eq!(received.lock().unwrap().len(), 128);
+    assert_eq!(log.lock().unwrap().last().unwrap(),
+               "/api/v1/crates/uploads/1/chunks/128 400");
+
+    log.lock().unwrap().clear();
+    assert_that(p.cargo("publish").arg("--no-verify"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[..]Resuming upload at 128 of [..] bytes"));
+    assert!(*received.lock().unwrap() == packaged(&p));
+    let log = log.lock().unwrap();
+    assert_eq!(log[1], "/api/v1/crates/uploads/1/chunks/128 200");
+}
+
+#[test]
+fn changelog_missing_entry() {
+    setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.2"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file(".cargo/config", r#"
+            [publish]
+            changelog = "CHANGELOG.md"
+        "#)
+        .file("CHANGELOG.md", "\
+# Changelog
+
+## 0.0.1
+- initial release
+")
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(101).with_stderr("\
+[ERROR] the changelog `CHANGELOG.md` does not contain an entry for version \
+`0.0.2` of `foo`
+expected a line starting with `## 0.0.2`
+"));
+}
+
+#[test]
+fn changelog_entry_of_longer_version() {
+    setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file(".cargo/config", r#"
+            [publish]
+            changelog = "CHANGELOG.md"
+        "#)
+        .file("CHANGELOG.md", "\
+## 0.0.10
+- later release
+
+## 0.0.1-beta
+- prerelease
+")
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(101).with_stderr("\
+[ERROR] the changelog `CHANGELOG.md` does not contain an entry for version \
+`0.0.1` of `foo`
+expected a line starting with `## 0.0.1`
+"));
+}
+
+#[test]
+fn changelog_custom_heading() {
+    setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.2"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file(".cargo/config", r#"
+            [publish]
+            changelog = "CHANGES"
+            changelog-heading = "{name} [{version}]"
+        "#)
+        .file("CHANGES", "\
+foo [0.0.2] - 2016-08-01
+  * fixed things
+")
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(0));
+}
+
+#[test]
+fn not_an_owner() {
+    setup();
+
+    let user = |login: &str| {
+        format!(r#"{{"id":1,"login":"{}","avatar":null,"email":null,
+                    "name":null}}"#, login)
+    };
+    File::create(upload_path().join("api/v1/me")).unwrap()
+        .write_all(format!(r#"{{"user":{}}}"#, user("alice")).as_bytes())
+        .unwrap();
+    fs::create_dir_all(upload_path().join("api/v1/crates/foo")).unwrap();
+    File::create(upload_path().join("api/v1/crates/foo/owners")).unwrap()
+        .write_all(format!(r#"{{"users":[{}]}}"#, user("bob")).as_bytes())
+        .unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish"),
+                execs().with_status(101).with_stderr("\
+[UPDATING] registry `[..]`
+[ERROR] `alice` isn't an owner of the crate `foo` on the registry, so it \
+can't publish it
+the owners are `bob`, who can add `alice` with `cargo owner --add alice`
+"));
+
+    // Once the crate is owned the package is uploaded
+    File::create(upload_path().join("api/v1/crates/foo/owners")).unwrap()
+        .write_all(format!(r#"{{"users":[{}]}}"#, user("Alice")).as_bytes())
+        .unwrap();
+    assert_that(p.cargo("publish").arg("--no-verify"),
+                execs().with_status(0));
+}
+
+/// A registry API listing `owners` as the owners of every crate and taking
+/// any change to them, which logs the requests it served along with their
+/// bodies.
+fn owners_server(owners: &'static [&'static str])
+                 -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log2 = log.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut rdr = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            rdr.read_line(&mut request).unwrap();
+            let mut parts = request.split_whitespace();
+            let method = parts.next().unwrap().to_string();
+            let path = parts.next().unwrap().to_string();
+            let mut len = 0;
+            loop {
+                let mut line = String::new();
+                rdr.read_line(&mut line).unwrap();
+                let line = line.trim().to_lowercase();
+                if line.is_empty() {
+                    break
+                }
+                if line.starts_with("content-length:") {
+                    len = line[15..].trim().parse().unwrap();
+                }
+                if line == "expect: 100-continue" {
+                    stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                          .unwrap();
+                }
+            }
+            let mut body = vec![0; len];
+            rdr.read_exact(&mut body).unwrap();
+
+            let response = if method == "GET" {
+                let users = owners.iter().map(|login| {
+                    format!(r#"{{"id":1,"login":"{}","avatar":null,
+                                "email":null,"name":null}}"#, login)
+                }).collect::<Vec<_>>();
+                format!(r#"{{"users":[{}]}}"#, users.join(","))
+            } else {
+                r#"{"ok":true}"#.to_string()
+            };
+            log2.lock().unwrap().push(format!("{} {} {}", method, path,
+                                              String::from_utf8(body)
+                                                  .unwrap()));
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+                            Connection: close\r\n\r\n",
+                   response.len()).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (addr, log)
+}
+
+#[test]
+fn sync_owners_adds_and_removes() {
+    let (addr, log) = owners_server(&["alice", "bob"]);
+    setup_with_api(&format!("http://{}", addr));
+
+    let p = project("foo")
+        .file("owners.toml", r#"owners = ["Alice", "carol"]"#);
+
+    assert_that(p.cargo_process("owner").arg("foo")
+                 .arg("--sync").arg("owners.toml").arg("-y"),
+                execs().with_status(0)
+                       .with_stdout("  + carol\n  - bob\n")
+                       .with_stderr_contains("\
+[..]Owner adding [\"carol\"] to crate foo")
+                       .with_stderr_contains("\
+[..]Owner removing [\"bob\"] from crate foo"));
+
+    // Owners are added before any is removed
+    let log = log.lock().unwrap();
+    assert_eq!(*log, vec![
+        "GET /api/v1/crates/foo/owners ".to_string(),
+        r#"PUT /api/v1/crates/foo/owners {"users":["carol"]}"#.to_string(),
+        r#"DELETE /api/v1/crates/foo/owners {"users":["bob"]}"#.to_string(),
+    ]);
+}
+
+#[test]
+fn sync_owners_up_to_date() {
+    let (addr, log) = owners_server(&["alice", "bob"]);
+    setup_with_api(&format!("http://{}", addr));
+
+    let p = project("foo")
+        .file("owners.toml", r#"owners = ["bob", "Alice"]"#);
+
+    assert_that(p.cargo_process("owner").arg("foo")
+                 .arg("--sync").arg("owners.toml"),
+                execs().with_status(0).with_stdout("")
+                       .with_stderr_contains("\
+[..]Owner owners of crate foo are up to date"));
+    assert_eq!(log.lock().unwrap().len(), 1);
+}
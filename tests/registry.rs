@@ -1,5 +1,6 @@
 #[macro_use]
 extern crate cargotest;
+extern crate git2;
 extern crate hamcrest;
 
 use std::fs::{self, File};
@@ -79,6 +80,41 @@ fn deps() {
         reg = registry::registry())));
 }
 
+#[test]
+fn compiles_while_downloading() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "0.1"
+            b = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    Package::new("a", "0.1.0").publish();
+    Package::new("c", "0.1.0").publish();
+    Package::new("b", "0.1.0").dep("c", "0.1").publish();
+
+    // `a` starts compiling as soon as it's there, before `c` is downloaded
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `{reg}`
+[DOWNLOADING] [..] v0.1.0 (registry file://[..])
+[DOWNLOADING] [..] v0.1.0 (registry file://[..])
+[COMPILING] a v0.1.0 (registry file://[..])
+[DOWNLOADING] c v0.1.0 (registry file://[..])
+[COMPILING] c v0.1.0 (registry file://[..])
+[COMPILING] b v0.1.0 (registry file://[..])
+[COMPILING] foo v0.0.1 ({dir})
+",
+        dir = p.url(),
+        reg = registry::registry())));
+}
+
 #[test]
 fn nonexistent() {
     Package::new("init", "0.0.1").publish();
@@ -330,6 +366,87 @@ fn lockfile_locks_transitively() {
                 execs().with_status(0).with_stdout(""));
 }
 
+#[test]
+fn add_dep_already_in_lockfile_dont_update_registry() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    Package::new("baz", "0.0.1").publish();
+    Package::new("bar", "0.0.1").dep("baz", "*").publish();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    t!(File::create(&p.root().join("Cargo.toml"))).write_all(br#"
+        [project]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "*"
+        baz = "*"
+    "#).unwrap();
+    Package::new("baz", "0.0.2").publish();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[COMPILING] foo v0.0.1 ({dir})
+",
+   dir = p.url())));
+}
+
+#[test]
+fn lockfile_satisfied_dont_update_registry() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    Package::new("baz", "0.0.1").publish();
+    Package::new("bar", "0.0.1").dep("baz", "*").publish();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    // Updating the index would fail now
+    t!(fs::remove_dir_all(registry::registry_path()));
+    t!(File::create(&p.root().join("Cargo.toml"))).write_all(br#"
+        [project]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "0.0.1"
+
+        [dev-dependencies]
+        baz = "0.0.1"
+    "#).unwrap();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[COMPILING] foo v0.0.1 ({dir})
+",
+   dir = p.url())));
+}
+
 #[test]
 fn yanks_are_not_used() {
     let p = project("foo")
@@ -1101,3 +1218,139 @@ Caused by:
   attempting to update a git repository, but --frozen was specified
 "));
 }
+
+#[test]
+fn index_snapshot() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    Package::new("bar", "0.0.1").publish();
+    let repo = git2::Repository::open(&registry::registry_path()).unwrap();
+    let commit = repo.head().unwrap().target().unwrap().to_string();
+    assert_that(p.cargo("index-snapshot").arg("export").arg("index.tar.gz"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `{reg}`
+[EXPORTED] snapshot {commit} to index.tar.gz
+",
+        reg = registry::registry(),
+        commit = commit)));
+
+    Package::new("bar", "0.0.2").publish();
+    assert_that(p.cargo("index-snapshot").arg("import").arg("index.tar.gz"),
+                execs().with_status(0).with_stderr(&format!("\
+[IMPORTED] snapshot {commit}, set `registry.snapshot = \"{commit}\"` to use it
+",
+        commit = commit)));
+
+    fs::create_dir_all(p.root().join(".cargo")).unwrap();
+    File::create(p.root().join(".cargo/config")).unwrap()
+         .write_all(format!(r#"
+            [registry]
+            snapshot = "{}"
+         "#, commit).as_bytes()).unwrap();
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[DOWNLOADING] bar v0.0.1 (registry file://[..])
+[COMPILING] bar v0.0.1 (registry file://[..])
+[COMPILING] foo v0.0.1 ({dir})
+",
+        dir = p.url())));
+
+    let mut lock = String::new();
+    File::open(p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains("registry-snapshot") && lock.contains(&commit),
+            "snapshot not recorded in:\n{}", lock);
+
+    File::create(p.root().join(".cargo/config")).unwrap()
+         .write_all(br#"
+            [registry]
+            snapshot = "0123456789abcdef"
+         "#).unwrap();
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr(&format!("\
+[ERROR] the lock file was resolved against snapshot `{}` of the registry \
+index, but `registry.snapshot` is `0123456789abcdef`
+remove Cargo.lock to resolve against the new snapshot
+",
+        commit)));
+
+    t!(fs::remove_file(p.root().join(".cargo/config")));
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[WARNING] the lock file was resolved against snapshot `{commit}` of the \
+registry index, but `registry.snapshot` isn't set
+the live index was used and the lock file isn't pinned to the snapshot \
+anymore, set `registry.snapshot = \"{commit}\"` to keep it
+",
+        commit = commit)));
+    let mut lock = String::new();
+    t!(t!(File::open(p.root().join("Cargo.lock"))).read_to_string(&mut lock));
+    assert!(!lock.contains("registry-snapshot"), "{}", lock);
+}
+
+#[test]
+fn update_as_of() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    Package::new("bar", "0.0.1").publish();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    Package::new("bar", "0.0.2").publish();
+
+    // Pretend the next version gets published in 2990
+    Package::new("bar", "0.0.3").publish();
+    let repo = t!(git2::Repository::open(&registry::registry_path()));
+    let head = t!(repo.refname_to_id("refs/heads/master"));
+    let head = t!(repo.find_commit(head));
+    let time = git2::Time::new(32188147200, 0);
+    let sig = t!(git2::Signature::new("foo", "foo@example.com", &time));
+    let future = t!(repo.commit(None, &sig, &sig, "Another commit",
+                                &t!(head.tree()), &[&t!(head.parent(0))]));
+    t!(repo.reference("refs/heads/master", future, true, "publish later"));
+
+    assert_that(p.cargo("update").arg("--as-of").arg("1990-01-01"),
+                execs().with_status(101).with_stderr_contains("\
+[..]the registry index `[..]` has no commits from before the requested date"));
+
+    assert_that(p.cargo("update").arg("--as-of").arg("2900-01-01"),
+                execs().with_status(0).with_stderr("\
+[UPDATING] registry `[..]`
+[UPDATING] bar v0.0.1 (registry file://[..]) -> v0.0.2
+"));
+    assert_that(p.cargo("update"),
+                execs().with_status(0).with_stderr("\
+[UPDATING] registry `[..]`
+[UPDATING] bar v0.0.2 (registry file://[..]) -> v0.0.3
+"));
+
+    assert_that(p.cargo("update").arg("--as-of").arg("2016-05")
+                 .arg("-p").arg("bar"),
+                execs().with_status(101).with_stderr("\
+[ERROR] cannot specify as-of along with packages to update or precise
+"));
+    assert_that(p.cargo("update").arg("--as-of").arg("2016-05"),
+                execs().with_status(101).with_stderr("\
+[ERROR] invalid date `2016-05`, expected one such as `2016-05-01`
+"));
+}
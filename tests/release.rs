@@ -0,0 +1,99 @@
+#[macro_use]
+extern crate cargotest;
+extern crate git2;
+extern crate hamcrest;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::{project, execs, git, paths};
+use hamcrest::assert_that;
+
+fn read(path: &std::path::Path) -> String {
+    let mut s = String::new();
+    t!(t!(File::open(path)).read_to_string(&mut s));
+    s
+}
+
+const FOO: &'static str = r#"
+    [package]
+    name = "foo"
+    version = "0.1.0"
+    authors = []
+    license = "MIT"
+    description = "foo"
+
+    [dependencies]
+    bar = { path = "bar", version = "0.2.0" }
+
+    [workspace]
+    members = ["bar"]
+"#;
+
+const BAR: &'static str = r#"
+    [package]
+    name = "bar"
+    version = "0.2.0"
+    authors = []
+    license = "MIT"
+    description = "bar"
+    workspace = ".."
+"#;
+
+#[test]
+fn dry_run_prints_plan() {
+    let p = project("foo")
+        .file("Cargo.toml", FOO)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", BAR)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("release").arg("minor").arg("--dry-run"),
+                execs().with_status(0).with_stdout("\
+  1. bump bar from 0.2.0 to 0.3.0
+  2. bump foo from 0.1.0 to 0.2.0
+  3. update the lock file
+  4. commit \"Release bar 0.3.0, foo 0.2.0\"
+  5. tag bar-v0.3.0
+  6. tag foo-v0.2.0
+  7. publish bar 0.3.0
+  8. wait for bar 0.3.0 to appear in the registry index
+  9. publish foo 0.2.0
+").with_stderr("\
+[WARNING] aborting release due to dry run
+"));
+
+    assert_eq!(read(&p.root().join("Cargo.toml")), FOO);
+    assert_eq!(read(&p.root().join("bar/Cargo.toml")), BAR);
+}
+
+#[test]
+fn bump_and_tag_without_publishing() {
+    let repo = git::repo(&paths::root().join("foo"))
+        .file("Cargo.toml", FOO)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", BAR)
+        .file("bar/src/lib.rs", "");
+    repo.build();
+
+    assert_that(cargotest::cargo_process().arg("release").arg("patch")
+                                          .arg("--no-publish")
+                                          .cwd(repo.root()),
+                execs().with_status(0).with_stderr("\
+[BUMPING] bar v0.2.0 -> v0.2.1
+[UPDATING] foo dependency on bar
+[BUMPING] foo v0.1.0 -> v0.1.1
+"));
+
+    let foo = read(&repo.root().join("Cargo.toml"));
+    assert!(foo.contains("version = \"0.1.1\""));
+    assert!(foo.contains("bar = { path = \"bar\", version = \"0.2.1\" }"));
+    assert!(read(&repo.root().join("bar/Cargo.toml"))
+                .contains("version = \"0.2.1\""));
+
+    let repo = t!(git2::Repository::open(repo.root()));
+    let head = t!(repo.find_commit(t!(repo.head()).target().unwrap()));
+    assert_eq!(head.message(), Some("Release bar 0.2.1, foo 0.1.1"));
+    t!(repo.revparse_single("bar-v0.2.1"));
+    t!(repo.revparse_single("foo-v0.1.1"));
+}
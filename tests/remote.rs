@@ -0,0 +1,94 @@
+#![cfg(unix)]
+
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::os::unix::prelude::*;
+use std::path::{Path, PathBuf};
+
+use cargotest::support::{project, execs};
+use hamcrest::{assert_that, existing_file};
+
+fn executor(root: &Path, script: &str) -> PathBuf {
+    let path = root.join("executor.sh");
+    let mut f = File::create(&path).unwrap();
+    f.write_all(format!("#!/bin/sh\n{}\n", script).as_bytes()).unwrap();
+    let mut perms = fs::metadata(&path).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&path, perms).unwrap();
+    path
+}
+
+#[test]
+fn unavailable_executor_falls_back_to_local() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    p.build();
+    let job = p.root().join("job.json");
+    let exe = executor(&p.root(),
+                       &format!("cat >> {}\nexit 75", job.display()));
+
+    assert_that(p.cargo("build").env("CARGO_BUILD_REMOTE_EXECUTOR", &exe),
+                execs().with_status(0));
+    assert_that(&p.root().join("target/debug/libfoo.rlib"), existing_file());
+
+    let mut contents = String::new();
+    File::open(&job).unwrap().read_to_string(&mut contents).unwrap();
+    assert!(contents.contains(r#""program":"rustc""#), "{}", contents);
+    assert!(contents.contains(r#""--crate-name","foo""#), "{}", contents);
+    assert!(contents.contains(r#""CARGO_PKG_NAME":"foo""#), "{}", contents);
+}
+
+#[test]
+fn failing_executor_fails_the_build() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    p.build();
+    let exe = executor(&p.root(), "echo remote failure >&2\nexit 3");
+
+    assert_that(p.cargo("build").env("CARGO_BUILD_REMOTE_EXECUTOR", &exe),
+                execs().with_status(101).with_stderr_contains("\
+remote failure")
+                       .with_stderr_contains("\
+[..]Process didn't exit successfully: remote executor `[..]executor.sh` for \
+`rustc [..]` (exit code: 3)"));
+}
+
+#[test]
+fn executor_printing_before_reading_the_job() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    p.build();
+    // Both the job and the output are too big for a pipe buffer
+    let exe = executor(&p.root(), "head -c 200000 /dev/zero >&2\n\
+                                   cat > /dev/null\n\
+                                   exit 75");
+    let flags = (0..10000).map(|i| format!("--cfg c{}", i))
+                          .collect::<Vec<_>>().join(" ");
+
+    assert_that(p.cargo("build").arg("--message-format").arg("json")
+                 .env("CARGO_BUILD_REMOTE_EXECUTOR", &exe)
+                 .env("RUSTFLAGS", &flags),
+                execs().with_status(0));
+    assert_that(&p.root().join("target/debug/libfoo.rlib"), existing_file());
+}
@@ -0,0 +1,131 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::process::Stdio;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn conflicting_requirement_is_relaxed() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1").publish();
+    Package::new("baz", "0.1.0").dep("bar", "=0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "=0.1.1"
+            baz = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    let mut cargo = p.cargo("fetch").env("CARGO_TERM_RESOLVE_WIZARD", "true")
+                     .build_command();
+    cargo.stdin(Stdio::piped())
+         .stdout(Stdio::piped())
+         .stderr(Stdio::piped());
+    let mut child = cargo.spawn().unwrap();
+    child.stdin.take().unwrap().write_all(b"1\ny\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(!out.status.success());
+    assert!(stderr.contains("  1: require `bar` version `0.1.0` in `foo` \
+                             instead of `"), "{}", stderr);
+    assert!(stderr.contains("the manifest of `foo` was changed"), "{}",
+            stderr);
+
+    let mut manifest = String::new();
+    File::open(p.root().join("Cargo.toml")).unwrap()
+         .read_to_string(&mut manifest).unwrap();
+    assert!(manifest.contains("bar = \"0.1.0\""), "{}", manifest);
+
+    assert_that(p.cargo("fetch"), execs().with_status(0));
+}
+
+#[test]
+fn declining_leaves_manifest_alone() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1").publish();
+    Package::new("baz", "0.1.0").dep("bar", "=0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "=0.1.1"
+            baz = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    let mut cargo = p.cargo("fetch").env("CARGO_TERM_RESOLVE_WIZARD", "true")
+                     .build_command();
+    cargo.stdin(Stdio::piped())
+         .stdout(Stdio::piped())
+         .stderr(Stdio::piped());
+    let mut child = cargo.spawn().unwrap();
+    child.stdin.take().unwrap().write_all(b"\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(!out.status.success());
+    assert!(stderr.contains("the manifests were left unchanged"), "{}",
+            stderr);
+
+    let mut manifest = String::new();
+    File::open(p.root().join("Cargo.toml")).unwrap()
+         .read_to_string(&mut manifest).unwrap();
+    assert!(manifest.contains("bar = \"=0.1.1\""), "{}", manifest);
+
+    // Without the wizard the error is reported as it is
+    assert_that(p.cargo("fetch").env("CARGO_TERM_RESOLVE_WIZARD", "false"),
+                execs().with_status(101).with_stderr_contains("\
+[..]failed to select a version for `bar`[..]
+"));
+}
+
+#[test]
+fn never_offered_when_locked() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1").publish();
+    Package::new("baz", "0.1.0").dep("bar", "=0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "=0.1.1"
+            baz = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    let out = p.cargo("fetch").arg("--locked")
+               .env("CARGO_TERM_RESOLVE_WIZARD", "true")
+               .exec_with_output().err().unwrap().output.unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("failed to select a version for `bar`"), "{}",
+            stderr);
+    assert!(!stderr.contains("would resolve"), "{}", stderr);
+    let mut manifest = String::new();
+    File::open(p.root().join("Cargo.toml")).unwrap()
+         .read_to_string(&mut manifest).unwrap();
+    assert!(manifest.contains("bar = \"=0.1.1\""), "{}", manifest);
+}
@@ -1,11 +1,14 @@
 extern crate cargo;
+#[macro_use]
 extern crate cargotest;
 extern crate hamcrest;
 
+use std::fs::{self, File};
+use std::io::prelude::*;
 use std::path::MAIN_SEPARATOR as SEP;
 
 use cargo::util::paths::dylib_path_envvar;
-use cargotest::support::{project, execs, path2url};
+use cargotest::support::{project, execs, path2url, paths};
 use hamcrest::{assert_that, existing_file};
 
 #[test]
@@ -188,10 +191,11 @@ fn too_many_bins() {
         .file("src/bin/b.rs", "");
 
     assert_that(p.cargo_process("run"),
-                execs().with_status(101)
-                       .with_stderr("[ERROR] `cargo run` requires that a project only \
-                                     have one executable; use the `--bin` option \
-                                     to specify which one to run\n"));
+                execs().with_status(101).with_stderr("\
+[ERROR] `cargo run` could not determine which binary to run, use the `--bin` \
+option to specify which one to run, or `--bins` to run them all
+
+available binaries of `foo`: a, b"));
 }
 
 #[test]
@@ -282,13 +286,17 @@ fn run_with_filename() {
 
     assert_that(p.cargo_process("run").arg("--bin").arg("bin.rs"),
                 execs().with_status(101).with_stderr("\
-[ERROR] no bin target named `bin.rs`"));
+[ERROR] no bin target named `bin.rs`
+
+available binaries of `foo`: a"));
 
     assert_that(p.cargo_process("run").arg("--bin").arg("a.rs"),
                 execs().with_status(101).with_stderr("\
 [ERROR] no bin target named `a.rs`
 
-Did you mean `a`?"));
+Did you mean `a`?
+
+available binaries of `foo`: a"));
 
     assert_that(p.cargo_process("run").arg("--example").arg("example.rs"),
                 execs().with_status(101).with_stderr("\
@@ -631,3 +639,133 @@ fn fail_no_extra_verbose() {
                        .with_stdout("")
                        .with_stderr(""));
 }
+
+#[test]
+fn run_bins_one_after_the_other() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/bin/a.rs", r#"
+            fn main() { println!("hello a"); }
+        "#)
+        .file("src/bin/b.rs", r#"
+            fn main() { println!("hello b"); }
+        "#);
+
+    assert_that(p.cargo_process("run"),
+                execs().with_status(101).with_stderr("\
+[ERROR] `cargo run` could not determine which binary to run, use the `--bin` \
+option to specify which one to run, or `--bins` to run them all
+
+available binaries of `foo`: a, b"));
+
+    assert_that(p.cargo("run").arg("--bins"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `target[..]debug[..]a[..]`
+[RUNNING] `target[..]debug[..]b[..]`")
+                       .with_stdout("\
+hello a
+hello b
+"));
+}
+
+#[test]
+fn run_member_binary() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", r#"
+            fn main() { println!("hello bar"); }
+        "#);
+
+    assert_that(p.cargo_process("run").arg("-p").arg("bar"),
+                execs().with_status(0).with_stdout("hello bar\n"));
+
+    assert_that(p.cargo("run").arg("-p").arg("baz"),
+                execs().with_status(101).with_stderr("\
+[ERROR] package `baz` is not a member of the workspace
+"));
+}
+
+#[test]
+fn run_with_configured_env() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            use std::env;
+            fn main() {
+                println!("{} {}", env::var("CARGO_TEST_FOO").unwrap(),
+                         env::var("CARGO_TEST_BAR").unwrap());
+            }
+        "#)
+        .file(".cargo/env", r#"
+            # Read when running binaries
+            CARGO_TEST_FOO=from-env-file
+            CARGO_TEST_BAR="quoted"
+        "#)
+        .file(".cargo/config", r#"
+            [env]
+            CARGO_TEST_FOO = "from-config"
+
+            [profile.release.env]
+            CARGO_TEST_FOO = "from-release"
+        "#);
+
+    assert_that(p.cargo_process("run"),
+                execs().with_status(0).with_stdout("from-config quoted\n"));
+    assert_that(p.cargo("run").arg("--release"),
+                execs().with_status(0).with_stdout("from-release quoted\n"));
+}
+
+#[test]
+fn env_file_only_read_at_workspace_root() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", r#"
+            use std::env;
+            fn main() {
+                println!("{:?}", env::var("CARGO_TEST_FOO").ok());
+            }
+        "#)
+        .file("bar/.cargo/env", "CARGO_TEST_FOO=from-member")
+        .file("other.env", "CARGO_TEST_FOO=from-configured-file");
+
+    // Neither the one of a member nor those above the workspace are read
+    t!(fs::create_dir_all(paths::root().join(".cargo")));
+    t!(t!(File::create(paths::root().join(".cargo/env")))
+        .write_all(b"not a valid line"));
+    assert_that(p.cargo_process("run").cwd(p.root().join("bar")),
+                execs().with_status(0).with_stdout("None\n"));
+
+    assert_that(p.cargo("run").cwd(p.root().join("bar"))
+                 .env("CARGO_BUILD_ENV_FILE", p.root().join("other.env")),
+                execs().with_status(0)
+                       .with_stdout("Some(\"from-configured-file\")\n"));
+}
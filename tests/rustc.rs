@@ -4,7 +4,7 @@ extern crate hamcrest;
 use std::path::MAIN_SEPARATOR as SEP;
 
 use cargotest::support::{execs, project};
-use hamcrest::assert_that;
+use hamcrest::{assert_that, existing_file, is_not};
 
 const CARGO_RUSTC_ERROR: &'static str =
 "[ERROR] extra arguments to `rustc` can only be passed to one target, consider filtering
@@ -187,6 +187,103 @@ fn fails_with_args_to_all_binaries() {
                 .with_stderr(CARGO_RUSTC_ERROR));
 }
 
+#[test]
+fn build_with_args_to_several_binaries() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/bin/foo.rs", r#"
+            fn main() {}
+        "#)
+        .file("src/bin/bar.rs", r#"
+            fn main() {}
+        "#)
+        .file("src/bin/baz.rs", r#"
+            fn main() {}
+        "#);
+
+    assert_that(p.cargo_process("rustc").arg("-v")
+                .arg("--bin").arg("foo").arg("--bin").arg("bar")
+                .arg("--").arg("-C").arg("debug-assertions"),
+                execs()
+                .with_status(0)
+                .with_stderr_contains(format!("\
+[RUNNING] `rustc src{sep}bin{sep}foo.rs --crate-name foo --crate-type bin -g \
+        -C debug-assertions [..]`", sep = SEP))
+                .with_stderr_contains(format!("\
+[RUNNING] `rustc src{sep}bin{sep}bar.rs --crate-name bar --crate-type bin -g \
+        -C debug-assertions [..]`", sep = SEP)));
+    assert_that(&p.bin("foo"), existing_file());
+    assert_that(&p.bin("bar"), existing_file());
+    assert_that(&p.bin("baz"), is_not(existing_file()));
+}
+
+#[test]
+fn fails_with_args_to_library_and_its_dependents() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            fn main() {}
+        "#)
+        .file("src/lib.rs", r#" "#)
+        .file("tests/t.rs", r#" "#);
+
+    assert_that(p.cargo_process("rustc").arg("--lib").arg("--bin").arg("foo")
+                .arg("--").arg("-C").arg("debug-assertions"),
+                execs()
+                .with_status(101)
+                .with_stderr("\
+[ERROR] extra arguments to `rustc` can't be passed to `foo` along with other \
+targets which depend on it, consider passing them to it alone"));
+
+    assert_that(p.cargo("rustc").arg("--bin").arg("foo").arg("--test").arg("t")
+                .arg("--").arg("-C").arg("debug-assertions"),
+                execs()
+                .with_status(101)
+                .with_stderr("\
+[ERROR] extra arguments to `rustc` can't be passed to `foo` along with other \
+targets which depend on it, consider passing them to it alone"));
+}
+
+#[test]
+fn override_crate_type() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            fn main() {}
+        "#)
+        .file("src/lib.rs", r#" "#);
+
+    assert_that(p.cargo_process("rustc").arg("-v").arg("--lib")
+                .arg("--crate-type").arg("staticlib,rlib"),
+                execs()
+                .with_status(0)
+                .with_stderr_contains(format!("\
+[RUNNING] `rustc src{sep}lib.rs --crate-name foo --crate-type staticlib \
+        --crate-type rlib -g [..]`", sep = SEP)));
+
+    assert_that(p.cargo("rustc").arg("-v").arg("--bin").arg("foo")
+                .arg("--crate-type").arg("staticlib"),
+                execs()
+                .with_status(101)
+                .with_stderr("\
+[ERROR] crate types can only be overridden for libraries, but `foo` isn't one"));
+}
+
 #[test]
 fn build_with_args_to_one_of_multiple_tests() {
     let p = project("foo")
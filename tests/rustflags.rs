@@ -852,3 +852,71 @@ fn build_rustflags_no_recompile() {
     assert_that(p.cargo("build").env("RUSTFLAGS", "--cfg foo"),
                 execs().with_stdout("").with_status(0));
 }
+
+#[test]
+fn package_rustflags_only_rebuild_the_package() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/lib.rs", "extern crate bar;")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [profile.dev.package.foo]
+            rustflags = ["--cfg", "first"]
+            "#);
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[..]lib.rs --crate-name foo [..]--cfg first`"));
+
+    File::create(&p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [profile.dev.package.foo]
+        rustflags = ["--cfg", "second"]
+
+        [profile.release]
+        rustflags = ["-Z", "bogus"]
+    "#).unwrap();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr("\
+[FRESH] bar v0.0.1 ([..])
+[COMPILING] foo v0.0.1 ([..])
+[RUNNING] `rustc src[..]lib.rs --crate-name foo [..]--cfg second`
+"));
+
+    // The flags of the release profile are only used with --release
+    assert_that(p.cargo("build").arg("--release"),
+                execs().with_status(101));
+}
+
+#[test]
+fn package_rustflags_bad_spec() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [profile.dev.package."foo:bad"]
+            rustflags = ["--cfg", "foo"]
+            "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] invalid package id specification `foo:bad` in `profile.dev.package`"));
+}
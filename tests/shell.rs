@@ -25,7 +25,7 @@ impl Write for Sink {
 
 #[test]
 fn non_tty() {
-    let config = ShellConfig { color_config: Auto, tty: false };
+    let config = ShellConfig { color_config: Auto, tty: false, width: None };
     let a = Arc::new(Mutex::new(Vec::new()));
 
     Shell::create(|| Box::new(Sink(a.clone())), config).tap(|shell| {
@@ -40,7 +40,7 @@ fn color_explicitly_disabled() {
     let term = TerminfoTerminal::new(Vec::new());
     if term.is_none() { return }
 
-    let config = ShellConfig { color_config: Never, tty: true };
+    let config = ShellConfig { color_config: Never, tty: true, width: None };
     let a = Arc::new(Mutex::new(Vec::new()));
 
     Shell::create(|| Box::new(Sink(a.clone())), config).tap(|shell| {
@@ -55,7 +55,7 @@ fn colored_shell() {
     let term = TerminfoTerminal::new(Vec::new());
     if term.is_none() { return }
 
-    let config = ShellConfig { color_config: Auto, tty: true };
+    let config = ShellConfig { color_config: Auto, tty: true, width: None };
     let a = Arc::new(Mutex::new(Vec::new()));
 
     Shell::create(|| Box::new(Sink(a.clone())), config).tap(|shell| {
@@ -72,7 +72,7 @@ fn color_explicitly_enabled() {
     let term = TerminfoTerminal::new(Vec::new());
     if term.is_none() { return }
 
-    let config = ShellConfig { color_config: Always, tty: false };
+    let config = ShellConfig { color_config: Always, tty: false, width: None };
     let a = Arc::new(Mutex::new(Vec::new()));
 
     Shell::create(|| Box::new(Sink(a.clone())), config).tap(|shell| {
@@ -84,6 +84,23 @@ fn color_explicitly_enabled() {
                                             color::RED).unwrap()));
 }
 
+#[test]
+fn long_status_lines_are_wrapped() {
+    let config = ShellConfig { color_config: Auto, tty: false, width: Some(40) };
+    let a = Arc::new(Mutex::new(Vec::new()));
+
+    Shell::create(|| Box::new(Sink(a.clone())), config).tap(|shell| {
+        shell.say_status("Compiling", "a-crate-with-a-long-name v0.1.0 \
+                                       (file:///some/where/on/disk)",
+                         color::GREEN, true).unwrap();
+    });
+    let buf = a.lock().unwrap().clone();
+    assert_that(&buf[..], shell_writes("   Compiling a-crate-with-a-long-name
+             v0.1.0
+             (file:///some/where/on/disk)
+"));
+}
+
 #[test]
 fn no_term() {
     // Verify that shell creation is successful when $TERM does not exist.
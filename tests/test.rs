@@ -1105,8 +1105,11 @@ fn test_no_run() {
                 execs().with_status(0)
                        .with_stderr(&format!("\
 [COMPILING] foo v0.0.1 ({dir})
+[EXECUTABLE] {exe}
 ",
-                       dir = p.url())));
+                       dir = p.url(),
+                       exe = p.root().join("target/debug/foo-[..]")
+                              .display())));
 }
 
 #[test]
@@ -1171,6 +1174,76 @@ test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
 "));
 }
 
+#[test]
+fn test_run_test_targets_by_pattern() {
+    let prj = project("foo")
+        .file("Cargo.toml" , r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/net_tcp.rs", "#[test] fn tcp() { }")
+        .file("tests/net_udp.rs", "#[test] fn udp() { }")
+        .file("tests/disk.rs", "#[test] fn disk() { }");
+
+    assert_that(prj.cargo_process("test").arg("--test").arg("net_*")
+                   .arg("--exclude-test").arg("net_udp"),
+                execs().with_status(0)
+                       .with_stderr(format!("\
+[COMPILING] foo v0.0.1 ({dir})
+[RUNNING] target[..]net_tcp-[..]", dir = prj.url()))
+                       .with_stdout("
+running 1 test
+test tcp ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
+
+"));
+
+    assert_that(prj.cargo("test").arg("--exclude-test").arg("net_*"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] target[..]disk-[..]")
+                       .with_stdout_contains("test disk ... ok"));
+
+    assert_that(prj.cargo("test").arg("--test").arg("fs_*"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] no test target matches `fs_*`"));
+}
+
+#[test]
+fn test_exclude_other_kinds_of_targets() {
+    let prj = project("foo")
+        .file("Cargo.toml" , r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("src/bin/tool_a.rs", "fn main() {} #[test] fn tool_a() { }")
+        .file("src/bin/tool_b.rs", "fn main() {} #[test] fn tool_b() { }")
+        .file("examples/demo.rs", "fn main() {} #[test] fn demo() { }")
+        .file("benches/speed.rs", "#[test] fn speed() { }");
+
+    let out = prj.cargo_process("test").arg("-v")
+                 .arg("--exclude-bin").arg("tool_*")
+                 .arg("--exclude-example").arg("demo")
+                 .arg("--exclude-bench").arg("speed")
+                 .exec_with_output().unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(!stderr.contains("tool_"), "{}", stderr);
+    assert!(!stderr.contains("demo"), "{}", stderr);
+    assert!(!stderr.contains("speed"), "{}", stderr);
+
+    assert_that(prj.cargo("test").arg("--bin").arg("tool_*")
+                   .arg("--exclude-bin").arg("tool_b"),
+                execs().with_status(0)
+                       .with_stdout_contains("test tool_a ... ok"));
+}
+
 #[test]
 fn test_no_harness() {
     let p = project("foo")
@@ -1483,6 +1556,7 @@ fn example_bin_same_name() {
 [COMPILING] foo v0.0.1 ({dir})
 [RUNNING] `rustc [..]`
 [RUNNING] `rustc [..]`
+[EXECUTABLE] [..]target[..]debug[..]foo-[..]
 ", dir = p.url())));
 
     assert_that(&p.bin("foo"), is_not(existing_file()));
@@ -1759,7 +1833,8 @@ test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
 
 #[test]
 fn dylib_doctest2() {
-    // can't doctest dylibs as they're statically linked together
+    // dylibs can't be doctested as they're statically linked together, so an
+    // rlib is compiled for the doctests
     let p = project("foo")
         .file("Cargo.toml", r#"
             [package]
@@ -1780,7 +1855,50 @@ fn dylib_doctest2() {
         "#);
 
     assert_that(p.cargo_process("test"),
-                execs().with_stdout(""));
+                execs().with_stderr("\
+[COMPILING] foo v0.0.1 ([..])
+[DOCTEST] foo")
+                       .with_stdout("
+running 1 test
+test foo_0 ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
+
+"));
+    assert_that(&p.root().join("target/debug/libfoo.rlib"), existing_file());
+}
+
+#[test]
+fn dylib_doctest_after_build() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [lib]
+            name = "foo"
+            crate-type = ["dylib"]
+            test = false
+        "#)
+        .file("src/lib.rs", r#"
+            /// ```
+            /// foo::foo();
+            /// ```
+            pub fn foo() {}
+        "#);
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(&p.root().join("target/debug/libfoo.rlib"),
+                is_not(existing_file()));
+
+    // The library is compiled again with the rlib its doctests need
+    assert_that(p.cargo("test"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] foo v0.0.1 ([..])
+[DOCTEST] foo"));
+    assert_that(&p.root().join("target/debug/libfoo.rlib"), existing_file());
 }
 
 #[test]
@@ -1937,6 +2055,49 @@ test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
 "))
 }
 
+#[test]
+fn no_fail_fast_across_packages() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/lib.rs", r#"
+            #[test]
+            fn fails() { panic!() }
+        "#)
+        .file("tests/passes.rs", r#"
+            #[test]
+            fn passes() {}
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("bar/tests/fails.rs", r#"
+            #[test]
+            fn fails() { std::process::exit(3) }
+        "#);
+    assert_that(p.cargo_process("test").arg("--all"),
+                execs().with_status(3));
+    assert_that(p.cargo("test").arg("--all").arg("--no-fail-fast"),
+                execs().with_status(3)
+                       .with_stderr_contains("\
+[RUNNING] target[..]passes-[..]")
+                       .with_stderr_contains("\
+[ERROR] 2 test target(s) failed:
+    bar v0.0.1 ([..]) (test `fails`): exit code 3
+    foo v0.0.1 ([..]) (lib): exit code 101"));
+}
+
 #[test]
 fn test_multiple_packages() {
     let p = project("foo")
@@ -2025,6 +2186,9 @@ fn bin_does_not_rebuild_tests() {
 [COMPILING] foo v0.0.1 ([..])
 [RUNNING] `rustc src[..]main.rs [..]`
 [RUNNING] `rustc src[..]main.rs [..]`
+[EXECUTABLE] [..]
+[EXECUTABLE] [..]
+[EXECUTABLE] [..]
 "));
 }
 
@@ -2086,6 +2250,7 @@ fn selective_test_optional_dep() {
 [COMPILING] a v0.0.1 ([..])
 [RUNNING] `rustc a[..]src[..]lib.rs [..]`
 [RUNNING] `rustc a[..]src[..]lib.rs [..]`
+[EXECUTABLE] [..]target[..]debug[..]a-[..]
 "));
 }
 
@@ -2193,3 +2358,238 @@ fn cfg_test_even_with_no_harness() {
 [RUNNING] `[..]`
 "));
 }
+
+#[test]
+fn retries_flaky_tests() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [[test]]
+            name = "flaky"
+            retries = 1
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/flaky.rs", r#"
+            use std::fs::File;
+            use std::path::Path;
+
+            #[test]
+            fn flaky() {
+                let marker = Path::new(env!("CARGO_MANIFEST_DIR"))
+                                  .join("marker");
+                if !marker.exists() {
+                    File::create(&marker).unwrap();
+                    panic!("first attempt");
+                }
+            }
+        "#);
+
+    assert_that(p.cargo_process("test").arg("--test").arg("flaky"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] `target[..]debug[..]flaky-[..]` failed, running it again (1 of 1)")
+                       .with_stderr_contains("\
+[WARNING] `target[..]debug[..]flaky-[..]` passed after failing 1 time(s), it \
+may be flaky"));
+
+    // The flag takes precedence over the manifest
+    p.root().join("marker").rm_rf();
+    assert_that(p.cargo("test").arg("--test").arg("flaky")
+                 .arg("--retries").arg("0"),
+                execs().with_status(101));
+}
+
+#[test]
+fn timeout_kills_hung_tests() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [[test]]
+            name = "hang"
+            timeout = 1
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/hang.rs", r#"
+            use std::env;
+            use std::thread;
+            use std::time::Duration;
+
+            #[test]
+            fn hang() {
+                assert_eq!(env::var("CARGO_TEST_TIMEOUT").unwrap(), "1");
+                thread::sleep(Duration::from_secs(1000));
+            }
+        "#);
+
+    assert_that(p.cargo_process("test").arg("--test").arg("hang")
+                 .arg("--retries").arg("2"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[WARNING] `target[..]debug[..]hang-[..]` timed out after 1s and was killed")
+                       .with_stderr_contains("\
+[ERROR] Process timed out after 1s and was killed: `[..]hang-[..]` [..]"));
+}
+
+#[test]
+fn json_test_results() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            #[test]
+            fn pass() {}
+
+            #[test]
+            fn fail() { panic!("nope") }
+
+            #[test]
+            #[ignore]
+            fn skip() {}
+        "#);
+
+    assert_that(p.cargo_process("test").arg("--lib")
+                 .arg("--message-format").arg("json"),
+                execs().with_status(101)
+                       .with_stdout_contains("\
+{\"name\":\"pass\",\"outcome\":\"ok\",\"package_id\":\"foo 0.0.1 ([..])\",\
+\"reason\":\"test-result\",\"target\":{[..]\"name\":\"foo\"[..]}}")
+                       .with_stdout_contains("\
+{\"name\":\"fail\",\"outcome\":\"failed\",\"package_id\":\"foo 0.0.1 ([..])\",\
+\"reason\":\"test-result\",\"target\":{[..]}}")
+                       .with_stdout_contains("\
+{\"name\":\"skip\",\"outcome\":\"ignored\",[..]}")
+                       .with_stderr_contains("test result: FAILED.[..]"));
+}
+
+#[test]
+fn no_run_prints_executables() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/bar.rs", "");
+
+    assert_that(p.cargo_process("test").arg("--no-run"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[EXECUTABLE] [..]target[..]debug[..]bar-[..]
+[EXECUTABLE] [..]target[..]debug[..]foo-[..]"));
+
+    assert_that(p.cargo("test").arg("--no-run")
+                 .arg("--message-format").arg("json"),
+                execs().with_status(0)
+                       .with_stdout_contains("\
+{\"executable\":\"[..]bar-[..]\",\"package_id\":\"foo 0.0.1 ([..])\",\
+\"reason\":\"test-executable\",\"target\":{[..]\"name\":\"bar\"[..]}}"));
+}
+
+#[test]
+fn coverage_of_doc_tests_rejected() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("test").arg("--doc").arg("--lcov"),
+                execs().with_status(1)
+                       .with_stderr("\
+[ERROR] --coverage can't be used with --doc, doc tests aren't instrumented
+"));
+}
+
+#[test]
+fn coverage_instruments_members_and_skips_doc_tests() {
+    if !is_nightly() {
+        return
+    }
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            /// ```
+            /// foo::foo();
+            /// ```
+            pub fn foo() {}
+
+            #[test]
+            fn profile_file() {
+                let file = std::env::var("LLVM_PROFILE_FILE").unwrap();
+                assert!(file.ends_with("foo-%p-%m.profraw"), "{}", file);
+            }
+        "#);
+
+    assert_that(p.cargo_process("test").arg("--coverage").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc src[/]lib.rs [..]-Zinstrument-coverage[..]`")
+                       .with_stderr_contains("\
+[WARNING] skipping doctests as they aren't instrumented for coverage"));
+
+    let profiles = p.root().join("target/coverage").read_dir().unwrap()
+                    .map(|e| e.unwrap().file_name().into_string().unwrap())
+                    .filter(|name| name.ends_with(".profraw"))
+                    .count();
+    assert!(profiles > 0);
+}
+
+#[test]
+fn quiet_test_prints_summary() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "#[test] fn works() {}");
+
+    assert_that(p.cargo_process("test").arg("-q"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[FINISHED] debug target(s) in [..] secs"));
+}
+
+#[test]
+fn doctests_run_with_configured_env() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            /// ```
+            /// assert_eq!(std::env::var("CARGO_TEST_FOO").unwrap(), "bar");
+            /// ```
+            pub fn foo() {}
+        "#)
+        .file(".cargo/env", "CARGO_TEST_FOO=bar");
+
+    assert_that(p.cargo_process("test").arg("--doc"),
+                execs().with_status(0));
+}
@@ -1,9 +1,12 @@
 extern crate cargotest;
 extern crate hamcrest;
 
+use std::fs::File;
+use std::io::Read;
+
 use cargotest::rustc_host;
 use cargotest::support::{path2url, project, execs};
-use hamcrest::assert_that;
+use hamcrest::{assert_that, existing_dir, existing_file, is_not};
 
 #[test]
 fn pathless_tools() {
@@ -121,3 +124,200 @@ fn relative_tools() {
 [RUNNING] `rustc [..] -C ar={ar} -C linker={linker} [..]`
 ", url = foo_url, ar = output.0, linker = output.1)))
 }
+
+#[cfg(unix)]
+#[test]
+fn post_process() {
+    let target = rustc_host();
+
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &format!(r#"
+            [target.{}]
+            post-process = ["cp {{artifact}} {{out-dir}}/copy"]
+        "#, target));
+
+    assert_that(foo.cargo_process("build").arg("--verbose"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `cp [..]target[..]debug[..]foo \
+[..]target[..]debug[..]post-process[..]foo[..]copy`"));
+    assert_that(&foo.root().join("target/debug/post-process/foo/copy"),
+                existing_file());
+
+    assert_that(foo.cargo("build").arg("--message-format").arg("json"),
+                execs().with_status(0)
+                       .with_stdout_contains("\
+[..]\"filenames\":[\"[..]foo\",\"[..]post-process[..]copy\"][..]"));
+}
+
+#[cfg(unix)]
+#[test]
+fn rustc_wrapper() {
+    use std::fs;
+    use std::os::unix::prelude::*;
+
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("wrapper.sh", r#"#!/bin/sh
+            echo "$@" >> "$(dirname "$0")/wrapped"
+            exec "$@"
+        "#);
+    foo.build();
+    let wrapper = foo.root().join("wrapper.sh");
+    let mut perms = fs::metadata(&wrapper).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&wrapper, perms).unwrap();
+
+    assert_that(foo.cargo("build").arg("-v")
+                   .env("RUSTC_WRAPPER", &wrapper),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `[..]wrapper.sh rustc [..]`"));
+    assert_that(&foo.root().join("wrapped"), existing_file());
+
+    // Dropping the wrapper rebuilds, and it's no longer run
+    fs::remove_file(foo.root().join("wrapped")).unwrap();
+    assert_that(foo.cargo("build").arg("-v").env("RUSTC_WRAPPER", ""),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `rustc [..]`"));
+    assert_that(&foo.root().join("wrapped"), is_not(existing_file()));
+}
+
+#[cfg(unix)]
+#[test]
+fn compiler_crash_writes_report() {
+    use std::fs;
+    use std::os::unix::prelude::*;
+
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("wrapper.sh", r#"#!/bin/sh
+            case "$*" in
+                *--crate-name\ foo*)
+                    echo "error: internal compiler error: unexpected panic" >&2
+                    echo "thread 'rustc' panicked at 'boom', src/lib.rs:1" >&2
+                    exit 101;;
+            esac
+            exec "$@"
+        "#);
+    foo.build();
+    let wrapper = foo.root().join("wrapper.sh");
+    let mut perms = fs::metadata(&wrapper).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&wrapper, perms).unwrap();
+
+    // The output of the compiler isn't captured by a plain build, and it's
+    // captured for JSON messages
+    let dir = foo.root().join("target/debug/crash-reports");
+    for args in [&[][..], &["--message-format", "json"][..]].iter() {
+        assert_that(foo.cargo("build").args(args)
+                       .env("RUSTC_WRAPPER", &wrapper),
+                    execs().with_status(101)
+                           .with_stderr_contains("\
+note: the compiler crashed, a crash report was written to \
+`[..]target[/]debug[/]crash-reports[/]rustc-crash-[..].txt`, please attach \
+it to a bug report"));
+
+        let report = fs::read_dir(&dir).unwrap().next().unwrap().unwrap()
+                        .path();
+        let mut contents = String::new();
+        File::open(&report).unwrap().read_to_string(&mut contents).unwrap();
+        assert!(contents.contains("## Package and its dependencies"),
+                contents);
+        assert!(contents.contains("foo v0.0.1"), contents);
+        assert!(contents.contains("panicked at 'boom'"), contents);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn compile_error_is_not_a_crash() {
+    use std::fs;
+    use std::os::unix::prelude::*;
+
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("wrapper.sh", r#"#!/bin/sh
+            case "$*" in
+                *--crate-name\ foo*)
+                    echo run >> "$(dirname "$0")/runs"
+                    echo "error: expected item, found \`}\`" >&2
+                    exit 101;;
+            esac
+            exec "$@"
+        "#);
+    foo.build();
+    let wrapper = foo.root().join("wrapper.sh");
+    let mut perms = fs::metadata(&wrapper).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&wrapper, perms).unwrap();
+
+    assert_that(foo.cargo("build").arg("--message-format").arg("json")
+                   .env("RUSTC_WRAPPER", &wrapper),
+                execs().with_status(101));
+
+    // The compiler isn't run again to look for a crash
+    let mut runs = String::new();
+    File::open(foo.root().join("runs")).unwrap()
+        .read_to_string(&mut runs).unwrap();
+    assert_eq!(runs, "run\n");
+    assert_that(&foo.root().join("target/debug/crash-reports"),
+                is_not(existing_dir()));
+}
+
+#[cfg(unix)]
+#[test]
+fn target_runner_and_rustflags() {
+    let target = rustc_host();
+
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            #[cfg(from_target_config)]
+            fn main() {}
+        "#)
+        .file(".cargo/config", &format!(r#"
+            [target.{}]
+            rustflags = ["--cfg", "from_target_config"]
+            runner = "echo runner:"
+        "#, target));
+
+    assert_that(foo.cargo_process("run").arg("--").arg("arg"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `echo runner: target[..]debug[..]foo[..] arg`")
+                       .with_stdout("\
+runner: target[..]debug[..]foo[..] arg
+"));
+}
@@ -0,0 +1,126 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn tree_filters_edge_kinds() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [build-dependencies]
+            baz = { path = "baz" }
+
+            [dev-dependencies]
+            qux = { path = "qux" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = { path = "../baz" }
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "")
+        .file("qux/Cargo.toml", r#"
+            [package]
+            name = "qux"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("qux/src/lib.rs", "");
+
+    assert_that(p.cargo_process("tree"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+└── bar v0.0.1 ([..])
+    └── baz v0.0.1 ([..])
+[build-dependencies]
+└── baz v0.0.1 ([..]) (*)
+[dev-dependencies]
+└── qux v0.0.1 ([..])
+"));
+
+    assert_that(p.cargo("tree").arg("--edges").arg("normal"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+└── bar v0.0.1 ([..])
+    └── baz v0.0.1 ([..])
+"));
+
+    assert_that(p.cargo("tree").arg("--edges").arg("build,dev"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+[build-dependencies]
+└── baz v0.0.1 ([..])
+[dev-dependencies]
+└── qux v0.0.1 ([..])
+"));
+
+    assert_that(p.cargo("tree").arg("--edges").arg("optional"),
+                execs().with_status(101).with_stderr("\
+[ERROR] unknown edge kind `optional`, expected one of `normal`, `build`, \
+`dev`, `features` or `all`
+"));
+}
+
+#[test]
+fn tree_shows_feature_edges() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            default = ["serialize"]
+            serialize = ["bar/std"]
+
+            [dependencies]
+            bar = { path = "bar", features = ["alloc"] }
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            alloc = []
+            std = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("tree").arg("-e").arg("normal,features"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+└── bar v0.0.1 ([..]) features: alloc, std (enabled by foo: serialize)
+"));
+
+    assert_that(p.cargo("tree").arg("-e").arg("features")
+                 .arg("--no-default-features"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+└── bar v0.0.1 ([..]) features: alloc
+"));
+}
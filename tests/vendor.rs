@@ -0,0 +1,106 @@
+#[macro_use]
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use cargotest::support::{project, execs};
+use cargotest::support::registry::Package;
+use hamcrest::{assert_that, existing_dir, existing_file, is_not};
+
+#[test]
+fn vendor_and_build_offline() {
+    Package::new("baz", "0.1.0")
+        .file("src/lib.rs", "pub fn baz() {}")
+        .publish();
+    Package::new("bar", "0.1.0")
+        .dep("baz", "0.1")
+        .file("src/lib.rs", "extern crate baz; pub fn bar() { baz::baz() }")
+        .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }");
+    p.build();
+
+    assert_that(p.cargo("vendor"),
+                execs().with_status(0).with_stdout("\
+[source.crates-io]
+replace-with = \"vendored-sources\"
+
+[source.vendored-sources]
+directory = \"vendor\"
+").with_stderr_contains("\
+[VENDORING] bar v0.1.0 [..]
+[VENDORING] baz v0.1.0 [..]
+"));
+    assert_that(&p.root().join("vendor/bar-0.1.0/src/lib.rs"), existing_file());
+    assert_that(&p.root().join("vendor/baz-0.1.0/.cargo-checksum.json"),
+                existing_file());
+
+    // The printed configuration builds without the registry
+    t!(fs::create_dir(p.root().join(".cargo")));
+    t!(t!(File::create(p.root().join(".cargo/config"))).write_all(br#"
+        [source.crates-io]
+        replace-with = "vendored-sources"
+
+        [source.vendored-sources]
+        directory = "vendor"
+    "#));
+    t!(fs::remove_dir_all(cargotest::support::registry::registry_path()));
+    assert_that(p.cargo("build").arg("--frozen"),
+                execs().with_status(0).with_stderr_contains("\
+[COMPILING] baz v0.1.0
+"));
+
+    // Vendoring again reads the packages from the vendored sources
+    assert_that(p.cargo("vendor").arg("--frozen"),
+                execs().with_status(0));
+    assert_that(&p.root().join("vendor/bar-0.1.0/src/lib.rs"), existing_file());
+    assert_that(&p.root().join("vendor/baz-0.1.0/.cargo-checksum.json"),
+                existing_file());
+    assert_that(p.cargo("build").arg("--frozen"), execs().with_status(0));
+}
+
+#[test]
+fn stale_packages_removed() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "");
+    assert_that(p.cargo_process("generate-lockfile"), execs().with_status(0));
+    assert_that(p.cargo("update").arg("-p").arg("bar")
+                 .arg("--precise").arg("0.1.0"),
+                execs().with_status(0));
+    assert_that(p.cargo("vendor").arg("deps"), execs().with_status(0));
+    assert_that(&p.root().join("deps/bar-0.1.0"), existing_dir());
+
+    assert_that(p.cargo("update"), execs().with_status(0));
+    assert_that(p.cargo("vendor").arg("deps").arg("--no-delete"),
+                execs().with_status(0));
+    assert_that(&p.root().join("deps/bar-0.1.0"), existing_dir());
+    assert_that(&p.root().join("deps/bar-0.1.1"), existing_dir());
+
+    assert_that(p.cargo("vendor").arg("deps"), execs().with_status(0));
+    assert_that(&p.root().join("deps/bar-0.1.0"), is_not(existing_dir()));
+    assert_that(&p.root().join("deps/bar-0.1.1"), existing_dir());
+}
@@ -0,0 +1,101 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::{project, execs};
+use cargotest::support::registry::Package;
+use hamcrest::{assert_that, existing_file, is_not};
+
+#[test]
+fn missing_lockfile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("verify-lockfile"),
+                execs().with_status(1)
+                       .with_stdout("\
+{\"up_to_date\":false,\"missing\":true,\"added\":[\"foo 0.0.1 ([..])\"],\
+\"removed\":[],\"changed\":[],\"dependencies_changed\":[]}
+")
+                       .with_stderr("\
+[ERROR] Cargo.lock does not exist
+"));
+    assert_that(&p.root().join("Cargo.lock"), is_not(existing_file()));
+}
+
+#[test]
+fn up_to_date() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("generate-lockfile"), execs().with_status(0));
+    assert_that(p.cargo("verify-lockfile"),
+                execs().with_status(0)
+                       .with_stdout("\
+{\"up_to_date\":true,\"missing\":false,\"added\":[],\"removed\":[],\
+\"changed\":[],\"dependencies_changed\":[]}
+"));
+}
+
+#[test]
+fn detects_added_and_changed_deps() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("baz", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("generate-lockfile"), execs().with_status(0));
+    let lockfile = p.root().join("Cargo.lock");
+    let mut lock = String::new();
+    File::open(&lockfile).unwrap().read_to_string(&mut lock).unwrap();
+
+    Package::new("bar", "0.2.0").publish();
+    File::create(&p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "0.2"
+        baz = "0.1"
+    "#).unwrap();
+
+    assert_that(p.cargo("verify-lockfile"),
+                execs().with_status(1)
+                       .with_stdout("\
+{\"up_to_date\":false,\"missing\":false,\"added\":[\"baz 0.1.0 ([..])\"],\
+\"removed\":[],\"changed\":[{\"name\":\"bar\",\"from\":\"0.1.0\",\
+\"to\":\"0.2.0\"}],\"dependencies_changed\":[\"foo 0.0.1 ([..])\"]}
+")
+                       .with_stderr_contains("\
+[ERROR] Cargo.lock is out of date
+"));
+    let mut lock2 = String::new();
+    File::open(&lockfile).unwrap().read_to_string(&mut lock2).unwrap();
+    assert_eq!(lock, lock2);
+}
@@ -663,11 +663,73 @@ fn virtual_build() {
     assert_that(p.cargo("build"),
                 execs().with_status(101)
                        .with_stderr("\
+error: the workspace at `[..]` has no members
+"));
+}
+
+#[test]
+fn virtual_build_all_members() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["bar", "baz"]
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", "fn main() {}")
+        .file("baz/Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("baz/src/main.rs", "fn main() {}");
+    p.build();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.bin("bar"), existing_file());
+    assert_that(&p.bin("baz"), existing_file());
+    assert_that(&p.root().join("Cargo.lock"), existing_file());
+
+    assert_that(p.cargo("run"),
+                execs().with_status(101)
+                       .with_stderr("\
 error: manifest path `[..]` is a virtual manifest, but this command \
 requires running against an actual package in this workspace
 "));
 }
 
+#[test]
+fn virtual_default_members() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["bar", "baz"]
+            default-members = ["baz"]
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", "fn main() {}")
+        .file("baz/Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("baz/src/main.rs", "fn main() {}");
+    p.build();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.bin("bar"), is_not(existing_file()));
+    assert_that(&p.bin("baz"), existing_file());
+}
+
 #[test]
 fn include_virtual() {
     let p = project("foo")
@@ -824,3 +886,372 @@ fn lock_doesnt_change_depending_on_crate() {
 
     assert_eq!(lockfile, lockfile2);
 }
+
+#[test]
+fn inherit_package_fields_and_dependencies() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["shared", "cli"]
+
+            [workspace.package]
+            version = "1.2.0"
+            authors = ["someone"]
+            license = "MIT"
+
+            [workspace.dependencies]
+            shared = { path = "shared", version = "1.2.0" }
+            bar = "0.1"
+        "#)
+        .file("shared/Cargo.toml", r#"
+            [project]
+            name = "shared"
+            version = { workspace = true }
+            authors = { workspace = true }
+            license = { workspace = true }
+        "#)
+        .file("shared/src/lib.rs", "pub fn shared() {}")
+        .file("cli/Cargo.toml", r#"
+            [project]
+            name = "cli"
+            version = { workspace = true }
+            authors = { workspace = true }
+
+            [dependencies]
+            shared = { workspace = true }
+            bar = { workspace = true }
+        "#)
+        .file("cli/src/main.rs", r#"
+            extern crate shared;
+            extern crate bar;
+            fn main() { shared::shared(); }
+        "#);
+    p.build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("build").cwd(p.root().join("cli")),
+                execs().with_status(0));
+    assert_that(&p.bin("cli"), existing_file());
+    assert_that(p.cargo("pkgid").cwd(p.root().join("cli")),
+                execs().with_status(0).with_stdout("[..]cli#1.2.0"));
+
+    Package::new("shared", "1.2.0").file("src/lib.rs", "pub fn shared() {}")
+                                   .publish();
+    assert_that(p.cargo("package").cwd(p.root().join("cli")),
+                execs().with_status(0));
+    let mut manifest = String::new();
+    let packaged = p.root().join("target/package/cli-1.2.0/Cargo.toml");
+    t!(t!(File::open(&packaged)).read_to_string(&mut manifest));
+    assert!(!manifest.contains("workspace"), "{}", manifest);
+    assert!(manifest.contains("version = \"1.2.0\""), "{}", manifest);
+    assert!(manifest.contains("authors = [\"someone\"]"), "{}", manifest);
+    assert!(manifest.contains("path = \"../shared\""), "{}", manifest);
+    assert!(manifest.contains("version = \"0.1\""), "{}", manifest);
+}
+
+#[test]
+fn inherit_missing_workspace_dependency() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            baz = { workspace = true }
+        "#)
+        .file("bar/src/lib.rs", "");
+    p.build();
+
+    assert_that(p.cargo("build").cwd(p.root().join("bar")),
+                execs().with_status(101).with_stderr_contains("\
+[..]dependency `baz` was inherited but `[..]Cargo.toml` does not define \
+`workspace.dependencies.baz`
+"));
+}
+
+#[test]
+fn default_members() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [workspace]
+            members = ["bar", "baz"]
+            default-members = ["bar"]
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", "fn main() {}")
+        .file("baz/Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("baz/src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.bin("foo"), is_not(existing_file()));
+    assert_that(&p.bin("bar"), existing_file());
+    assert_that(&p.bin("baz"), is_not(existing_file()));
+
+    assert_that(p.cargo("build").cwd(p.root().join("baz")),
+                execs().with_status(0));
+    assert_that(&p.bin("baz"), existing_file());
+}
+
+#[test]
+fn build_all_exclude() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [workspace]
+            members = ["bar", "baz"]
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", "fn main() {}")
+        .file("baz/Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("baz/src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(p.cargo("build").arg("--all").arg("--exclude").arg("baz"),
+                execs().with_status(0));
+    assert_that(&p.bin("foo"), existing_file());
+    assert_that(&p.bin("bar"), existing_file());
+    assert_that(&p.bin("baz"), is_not(existing_file()));
+
+    assert_that(p.cargo("build").arg("--exclude").arg("baz"),
+                execs().with_status(101).with_stderr("\
+[ERROR] --exclude can only be used together with --all
+"));
+}
+
+#[test]
+fn glob_members_and_exclude() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["crates/*"]
+            exclude = ["crates/scratch"]
+        "#)
+        .file("crates/bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("crates/bar/src/main.rs", "fn main() {}")
+        .file("crates/baz/Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("crates/baz/src/main.rs", "fn main() {}")
+        .file("crates/README.md", "")
+        .file("crates/scratch/Cargo.toml", r#"
+            [project]
+            name = "scratch"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("crates/scratch/src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.bin("bar"), existing_file());
+    assert_that(&p.bin("baz"), existing_file());
+    assert_that(&p.bin("scratch"), is_not(existing_file()));
+
+    // The excluded crate is a workspace of its own
+    assert_that(p.cargo("build").cwd(p.root().join("crates/scratch")),
+                execs().with_status(0));
+    assert_that(&p.root().join("crates/scratch/Cargo.lock"), existing_file());
+}
+
+#[test]
+fn exclude_compares_path_components() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["bar", "barbaz"]
+            exclude = ["./bar/"]
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", "fn main() {}")
+        .file("barbaz/Cargo.toml", r#"
+            [project]
+            name = "barbaz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("barbaz/src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.bin("bar"), is_not(existing_file()));
+    assert_that(&p.bin("barbaz"), existing_file());
+}
+
+#[test]
+fn glob_default_members() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["crates/*", "tools/*"]
+            default-members = ["crates/*"]
+        "#)
+        .file("crates/bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("crates/bar/src/main.rs", "fn main() {}")
+        .file("tools/baz/Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("tools/baz/src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.bin("bar"), existing_file());
+    assert_that(&p.bin("baz"), is_not(existing_file()));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_paths_are_one_package() {
+    use std::os::unix::fs::symlink;
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            baz = { path = "baz" }
+
+            [workspace]
+        "#)
+        .file("src/main.rs", "extern crate bar; extern crate baz; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "../bar-link" }
+        "#)
+        .file("baz/src/lib.rs", "extern crate bar;");
+    p.build();
+    symlink(p.root().join("bar"), p.root().join("bar-link")).unwrap();
+    let link = p.root().parent().unwrap().join("foo-link");
+    symlink(p.root(), &link).unwrap();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] bar v0.1.0 ([..])
+[COMPILING] baz v0.1.0 ([..])
+[COMPILING] foo v0.1.0 ([..])
+"));
+
+    // Building through a symlink to the workspace is the same build
+    assert_that(p.cargo("build").arg("--manifest-path")
+                 .arg(link.join("Cargo.toml")),
+                execs().with_status(0).with_stderr(""));
+    assert_that(p.cargo("build").cwd(link.join("baz")),
+                execs().with_status(0).with_stderr(""));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_member_outside_of_root() {
+    use std::os::unix::fs::symlink;
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+    let bar = project("bar")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    bar.build();
+    symlink(bar.root(), p.root().join("bar")).unwrap();
+
+    assert_that(p.cargo("build").arg("--all"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] bar v0.1.0 ([..])")
+                       .with_stderr_contains("[COMPILING] foo v0.1.0 ([..])"));
+    assert_that(&p.root().join("target"), existing_dir());
+    assert_that(&bar.root().join("target"), is_not(existing_dir()));
+
+    // Built from where it's listed, the member uses the workspace as well
+    assert_that(p.cargo("build").arg("--manifest-path")
+                 .arg(p.root().join("bar/Cargo.toml")),
+                execs().with_status(0).with_stderr(""));
+    assert_that(&bar.root().join("target"), is_not(existing_dir()));
+}